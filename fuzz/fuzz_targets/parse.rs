@@ -0,0 +1,9 @@
+//! Feeds arbitrary bytes to `parse_untrusted`, which must fail with a diagnostic rather than panic or hang.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = algo_parser::parse_untrusted(data);
+});
@@ -0,0 +1,12 @@
+//! Prints the programs generated from the seeds and parses them back, which must give the same programs.
+
+#![no_main]
+
+use algo_parser::parser::testing::{check_round_trip, Generator};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|seed: u64| {
+    if let Err(e) = check_round_trip(&Generator::new(seed).program()) {
+        panic!("{}", e);
+    }
+});
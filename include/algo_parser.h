@@ -0,0 +1,23 @@
+/* C bindings of algo_parser, built with `cargo build --release --features ffi` into libalgo_parser.so. */
+#ifndef ALGO_PARSER_H
+#define ALGO_PARSER_H
+
+#include <stdbool.h>
+
+/* Result of a call, holding a JSON text, or the JSON array of the diagnostics when ok is false. */
+typedef struct AlgoResult {
+    bool ok;
+    char *json;
+} AlgoResult;
+
+/* Parses the program, the JSON being its syntax tree. */
+AlgoResult *algo_parse(const char *source);
+
+/* Compiles the program for target (nasm, gas, riscv, wasm, c, python, js or ir),
+ * the JSON being {"code": "...", "diagnostics": [...]}. */
+AlgoResult *algo_compile(const char *source, const char *target);
+
+/* Frees a result returned by the library. */
+void algo_free_result(AlgoResult *result);
+
+#endif
@@ -0,0 +1,156 @@
+//! Coverage of the statements of a program by its runs. `Coverage::new` lists the statements of the program, and
+//! the coverage, as the tracer of `interpreter::program::run_program`, counts the runs of each of them. The counts
+//! are reported per line of the source as an lcov tracefile, as json or as the source annotated with them.
+
+use std::collections::HashMap;
+
+use super::super::interpreter::program::Frame;
+use super::super::interpreter::trace::Tracer;
+use super::super::json;
+use super::super::lexer::Span;
+use super::super::parser::Ast;
+
+/// Statements of a program, along with the number of times the runs recorded so far ran each of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    /// position of each statement.
+    pub probes: Vec<Span>,
+    pub hits: Vec<u64>,
+    /// index of the statement at each position.
+    index: HashMap<Span, usize>,
+}
+
+/// Whether the statement runs code, rather than only declaring something.
+fn is_run(node: &Ast) -> bool {
+    return !matches!(
+        node,
+        Ast::FunctionDeclaration { .. } | Ast::FunctionHeader { .. } | Ast::RecordDeclaration { .. } | Ast::ConstDeclaration { .. } | Ast::Pragma { .. }
+    );
+}
+
+/// Adds the positions of the statements of the block, and of the blocks they contain.
+fn block_probes(children: &[Ast], probes: &mut Vec<Span>) {
+    for child in children {
+        if let Some(span) = child.span().filter(|_| is_run(child)) {
+            probes.push(span);
+        }
+        node_probes(child, probes);
+    }
+}
+
+/// Adds the positions of the statements of the blocks the node contains.
+fn node_probes(node: &Ast, probes: &mut Vec<Span>) {
+    match node {
+        Ast::FunctionDeclaration { children, .. } | Ast::WhileLoop { children, .. } => block_probes(children, probes),
+        Ast::Condition { valid_branch, invalid_branch, .. } => {
+            block_probes(valid_branch, probes);
+            block_probes(invalid_branch, probes);
+        },
+        Ast::Try { children, handler, .. } => {
+            block_probes(children, probes);
+            block_probes(handler, probes);
+        },
+        Ast::Match { cases, default, .. } => {
+            for (_, children) in cases {
+                block_probes(children, probes);
+            }
+            block_probes(default.as_deref().unwrap_or_default(), probes);
+        },
+        _ => (),
+    };
+}
+
+impl Coverage {
+    /// Coverage of the statements of the program, none of them run yet.
+    /// The top level of a program defining `main` only initializes its globals, and its statements are not counted.
+    pub fn new(ast: &Ast) -> Self {
+        let children = match ast {
+            Ast::Global(children) => children,
+            _ => return Coverage::default(),
+        };
+        let defines_main = children.iter().any(|child| matches!(child, Ast::FunctionDeclaration { name, .. } if name == "main"));
+        let mut probes = Vec::new();
+        match defines_main {
+            true => children.iter().for_each(|child| node_probes(child, &mut probes)),
+            false => block_probes(children, &mut probes),
+        };
+        let index = probes.iter().enumerate().map(|(i, span)| (*span, i)).collect();
+        let hits = vec![0; probes.len()];
+        return Coverage { probes, hits, index };
+    }
+
+    /// Lines of the `file`-th file holding statements, in order, with the runs of their statements, the statements
+    /// of a line running as many times as the most run of them.
+    fn lines(&self, file: usize) -> Vec<(usize, u64)> {
+        let mut res = Vec::<(usize, u64)>::new();
+        for (span, hits) in self.probes.iter().zip(&self.hits).filter(|(span, _)| span.file == file) {
+            match res.iter_mut().find(|(line, _)| *line == span.line) {
+                Some((_, line_hits)) => *line_hits = (*line_hits).max(*hits),
+                None => res.push((span.line, *hits)),
+            };
+        }
+        res.sort();
+        return res;
+    }
+}
+
+impl Tracer for Coverage {
+    fn statement(&mut self, span: Span, _frames: &[Frame]) {
+        if let Some(i) = self.index.get(&span) {
+            self.hits[*i] += 1;
+        }
+    }
+}
+
+/// lcov tracefile of the files at `paths`, one record per file listing the runs of each of its lines.
+pub fn to_lcov(coverage: &Coverage, paths: &[String]) -> String {
+    let mut res = String::new();
+    for (file, path) in paths.iter().enumerate() {
+        let lines = coverage.lines(file);
+        res.push_str(&format!("TN:\nSF:{}\n", path));
+        for (line, hits) in &lines {
+            res.push_str(&format!("DA:{},{}\n", line, hits));
+        }
+        res.push_str(&format!("LF:{}\nLH:{}\nend_of_record\n", lines.len(), lines.iter().filter(|(_, hits)| *hits > 0).count()));
+    }
+    return res;
+}
+
+/// `[{"file": "a.algo", "lines": [{"line": 2, "hits": 3}, ...], "covered": 4, "total": 5}, ...]`.
+pub fn to_json(coverage: &Coverage, paths: &[String]) -> String {
+    let files = paths
+        .iter()
+        .enumerate()
+        .map(|(file, path)| {
+            let lines = coverage.lines(file);
+            let covered = lines.iter().filter(|(_, hits)| *hits > 0).count();
+            let entries = lines.iter().map(|(line, hits)| format!("{{\"line\": {}, \"hits\": {}}}", line, hits)).collect::<Vec<String>>();
+            format!("{{\"file\": {}, \"lines\": [{}], \"covered\": {}, \"total\": {}}}", json::string(path), entries.join(", "), covered, lines.len())
+        })
+        .collect::<Vec<String>>();
+    return format!("[{}]\n", files.join(", "));
+}
+
+/// Each file of `paths`, whose text is in `sources`, with the runs of each line before it as gcov shows them: `#####` for
+/// the lines never run and `-` for those without statements, followed by the share of the lines run.
+pub fn annotate(coverage: &Coverage, paths: &[String], sources: &[String]) -> String {
+    let mut res = String::new();
+    let (mut covered, mut total) = (0, 0);
+    for (file, (path, source)) in paths.iter().zip(sources).enumerate() {
+        let lines = coverage.lines(file);
+        res.push_str(&format!("{}:\n", path));
+        for (i, text) in source.lines().enumerate() {
+            let count = match lines.iter().find(|(line, _)| *line == i + 1) {
+                None => String::from("-"),
+                Some((_, 0)) => String::from("#####"),
+                Some((_, hits)) => hits.to_string(),
+            };
+            res.push_str(&format!("{:>9}:{:>5}: {}\n", count, i + 1, text));
+        }
+        covered += lines.iter().filter(|(_, hits)| *hits > 0).count();
+        total += lines.len();
+    }
+    let percent = if total == 0 { 100.0 } else { covered as f64 * 100.0 / total as f64 };
+    res.push_str(&format!("{} of {} lines run ({:.1}%)\n", covered, total, percent));
+    return res;
+}
@@ -0,0 +1,144 @@
+//! Functions of the whole program which no run of it can reach: those neither called nor referenced from the top level
+//! statements, from `main`, from the functions given their own symbol, nor from a function reached from them.
+
+use super::super::diagnostic::{Code, Diagnostic};
+use super::super::json;
+use super::super::lexer::Span;
+use super::super::parser::{Ast, NodeRef};
+
+/// Function implemented by the program and never reached.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadFunction {
+    pub name: String,
+    pub span: Span,
+    /// dead functions calling or referencing it, in source order.
+    pub callers: Vec<String>,
+}
+
+/// Names of the functions called or referenced by `node`, outside of the functions it declares, which reference
+/// their own. Variables named as a function are counted, the parser reading references to functions as variables.
+fn used_names<'a>(node: &'a Ast, functions: &[&str], res: &mut Vec<&'a str>) {
+    let name = match node {
+        Ast::FunctionCall { name, .. } | Ast::FunctionReference(name) => Some(name),
+        Ast::Variable(var) => Some(&var.name),
+        _ => None,
+    };
+    if let Some(name) = name.filter(|name| functions.contains(&name.as_str()) && !res.contains(&name.as_str())) {
+        res.push(name);
+    }
+    for child in node.children() {
+        if !matches!(child, Ast::FunctionDeclaration { .. }) {
+            used_names(child, functions, res);
+        }
+    }
+}
+
+/// Functions of the program never reached, in source order, the files compiled together being given as one program
+/// as `compiler::merge_programs` builds it. The overloads of a function share its name, all of them being reached
+/// when one is used.
+pub fn dead_functions(ast: &Ast) -> Vec<DeadFunction> {
+    let declarations = ast.find_functions();
+    let functions = declarations
+        .iter()
+        .filter_map(|NodeRef { node, .. }| match node {
+            Ast::FunctionDeclaration { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<&str>>();
+    // `main` is called by the runtime, and the functions with a symbol, given by their header or by themselves, by
+    // other programs.
+    let exported = ast
+        .find_nodes(|n| matches!(n, Ast::FunctionDeclaration { symbol: Some(..), .. } | Ast::FunctionHeader { symbol: Some(..), .. }))
+        .into_iter()
+        .filter_map(|NodeRef { node, .. }| match node {
+            Ast::FunctionDeclaration { name, .. } | Ast::FunctionHeader { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<&str>>();
+
+    let mut reached = functions.iter().copied().filter(|name| *name == "main" || exported.contains(name)).collect::<Vec<&str>>();
+    if let Ast::Global(children) = ast {
+        for child in children.iter().filter(|child| !matches!(child, Ast::FunctionDeclaration { .. })) {
+            used_names(child, &functions, &mut reached);
+        }
+    }
+    // names used by each function, its overloads adding to one another.
+    let uses = declarations
+        .iter()
+        .filter_map(|NodeRef { node, .. }| match node {
+            Ast::FunctionDeclaration { name, children, .. } => {
+                let mut used = Vec::new();
+                for child in children.iter().filter(|child| !matches!(child, Ast::FunctionDeclaration { .. })) {
+                    used_names(child, &functions, &mut used);
+                }
+                Some((name.as_str(), used))
+            },
+            _ => None,
+        })
+        .collect::<Vec<(&str, Vec<&str>)>>();
+    let mut i = 0;
+    while i < reached.len() {
+        let function = reached[i];
+        for (_, used) in uses.iter().filter(|(name, _)| *name == function) {
+            for name in used {
+                if !reached.contains(name) {
+                    reached.push(name);
+                }
+            }
+        }
+        i += 1;
+    }
+
+    let mut res = Vec::<DeadFunction>::new();
+    for NodeRef { node, span } in declarations {
+        let name = match node {
+            Ast::FunctionDeclaration { name, .. } if !reached.contains(&name.as_str()) => name,
+            _ => continue,
+        };
+        // the overloads of a dead function are reported once.
+        if res.iter().any(|dead| &dead.name == name) {
+            continue;
+        }
+        let mut callers = Vec::<String>::new();
+        for (caller, _) in uses.iter().filter(|(caller, used)| *caller != name.as_str() && used.contains(&name.as_str())) {
+            if !callers.iter().any(|known| known == caller) {
+                callers.push(caller.to_string());
+            }
+        }
+        res.push(DeadFunction { name: name.clone(), span, callers });
+    }
+    return res;
+}
+
+/// Warnings of the dead functions, noting the dead functions using them.
+pub fn to_diagnostics(dead: &[DeadFunction]) -> Vec<Diagnostic> {
+    return dead
+        .iter()
+        .map(|f| {
+            let message = format!("function '{}' is never reached from the program", f.name);
+            let warning = Diagnostic::warning(Code::DeadFunction, message, f.span);
+            match f.callers.as_slice() {
+                [] => warning,
+                [caller] => warning.with_note(&format!("it is only used by '{}', which is never reached either", caller)),
+                callers => {
+                    let callers = callers.iter().map(|caller| format!("'{}'", caller)).collect::<Vec<String>>();
+                    warning.with_note(&format!("it is only used by {}, which are never reached either", callers.join(", ")))
+                },
+            }
+        })
+        .collect();
+}
+
+/// `[{"name": "helper", "file": "a.algo", "line": 3, "callers": ["unused"]}, ...]`, the file being the path of the
+/// `span.file`-th of `paths`.
+pub fn to_json(dead: &[DeadFunction], paths: &[String]) -> String {
+    let functions = dead
+        .iter()
+        .map(|f| {
+            let file = paths.get(f.span.file).map(|path| json::string(path)).unwrap_or(String::from("null"));
+            let callers = f.callers.iter().map(|caller| json::string(caller)).collect::<Vec<String>>();
+            format!("{{\"name\": {}, \"file\": {}, \"line\": {}, \"callers\": [{}]}}", json::string(&f.name), file, f.span.line, callers.join(", "))
+        })
+        .collect::<Vec<String>>();
+    return format!("[{}]\n", functions.join(", "));
+}
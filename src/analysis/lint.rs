@@ -0,0 +1,228 @@
+use super::super::diagnostic::{Code, Diagnostic};
+use super::super::lexer::Span;
+use super::super::parser::{Ast, NodeRef};
+
+/// Check of the style of the program, reported with its own code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// function implemented but never called nor referenced outside of itself.
+    UnusedFunction,
+    /// assignment used as the condition of an `if` or a `while`, usually meant as a comparison.
+    AssignmentInCondition,
+    /// number other than 0, 1 and 2 written outside of a constant.
+    MagicNumber,
+    /// function, parameter or variable whose name is not in snake_case.
+    SnakeCase,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 4] = [Rule::UnusedFunction, Rule::AssignmentInCondition, Rule::MagicNumber, Rule::SnakeCase];
+
+    /// Name of the rule in the options and the configuration files.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Rule::UnusedFunction => "unused-function",
+            Rule::AssignmentInCondition => "assignment-in-condition",
+            Rule::MagicNumber => "magic-number",
+            Rule::SnakeCase => "snake-case",
+        };
+    }
+
+    pub fn code(&self) -> Code {
+        return match self {
+            Rule::UnusedFunction => Code::UnusedFunction,
+            Rule::AssignmentInCondition => Code::AssignmentInCondition,
+            Rule::MagicNumber => Code::MagicNumber,
+            Rule::SnakeCase => Code::NonSnakeCase,
+        };
+    }
+
+    /// Rule named `name`, or reported with the code `name`.
+    pub fn from_name(name: &str) -> Option<Rule> {
+        return Rule::ALL.iter().find(|rule| rule.name() == name || rule.code().as_str() == name).copied();
+    }
+}
+
+/// Rules run by `lint`, all of them by default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintConfig {
+    rules: Vec<Rule>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        return LintConfig { rules: Rule::ALL.to_vec() };
+    }
+}
+
+impl LintConfig {
+    /// Enables or disables the rule `name`, a name as `magic-number`, a code as `L0003` or `all`, returning whether it exists.
+    pub fn set(&mut self, name: &str, enabled: bool) -> bool {
+        let rules = match (name, Rule::from_name(name)) {
+            ("all", _) => Rule::ALL.to_vec(),
+            (_, Some(rule)) => vec![rule],
+            (_, None) => return false,
+        };
+        self.rules.retain(|rule| !rules.contains(rule));
+        if enabled {
+            self.rules.extend(rules);
+        }
+        return true;
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        return self.rules.contains(&rule);
+    }
+
+    /// Applies a configuration file, each line setting a rule as `magic-number = off`, `on` enabling it back.
+    /// Empty lines and the text following a `#` are ignored. The error is located on the line of the file.
+    pub fn read(&mut self, text: &str) -> Result<(), Diagnostic> {
+        for (i, line) in text.lines().enumerate() {
+            let span = Span { line: i + 1, column: 1, file: 0 };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = match line.split_once('=') {
+                None => return Err(Diagnostic::error(Code::InvalidConfig, format!("expected `rule = on` or `rule = off`, got '{}'", line), span)),
+                Some((name, value)) => (name.trim(), value.trim()),
+            };
+            let enabled = match value {
+                "on" => true,
+                "off" => false,
+                value => return Err(Diagnostic::error(Code::InvalidConfig, format!("expected on or off for {}, got '{}'", name, value), span)),
+            };
+            if !self.set(name, enabled) {
+                return Err(Diagnostic::error(Code::InvalidConfig, format!("unknown rule '{}'", name), span));
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// Warnings of the enabled rules, in the order of the rules then of the source.
+pub fn lint(ast: &Ast, config: &LintConfig) -> Vec<Diagnostic> {
+    let mut res = Vec::new();
+    for rule in Rule::ALL.iter().filter(|rule| config.is_enabled(**rule)) {
+        let found = match rule {
+            Rule::UnusedFunction => unused_functions(ast),
+            Rule::AssignmentInCondition => assignments_in_conditions(ast),
+            Rule::MagicNumber => magic_numbers(ast),
+            Rule::SnakeCase => non_snake_case_names(ast),
+        };
+        res.extend(found.into_iter().map(|(message, span)| Diagnostic::warning(rule.code(), message, span)));
+    }
+    return res;
+}
+
+/// Calls and references to the function `name` in `node`.
+fn uses(node: &Ast, name: &str) -> usize {
+    return node
+        .find_nodes(|n| match n {
+            Ast::FunctionCall { name: called, .. } | Ast::FunctionReference(called) => called == name,
+            _ => false,
+        })
+        .len();
+}
+
+fn unused_functions(ast: &Ast) -> Vec<(String, Span)> {
+    let mut res = Vec::new();
+    for NodeRef { node, span } in ast.find_functions() {
+        let name = match node {
+            // `main` is called by the runtime, and functions with their own symbol by other programs.
+            Ast::FunctionDeclaration { name, symbol: None, .. } if name != "main" => name,
+            _ => continue,
+        };
+        // the recursive calls of the function do not use it.
+        if uses(ast, name) == uses(node, name) {
+            res.push((format!("function '{}' is never used", name), span));
+        }
+    }
+    return res;
+}
+
+fn contains_assignment(condition: &Ast) -> bool {
+    return !condition.find_nodes(|n| matches!(n, Ast::Assignement { .. })).is_empty();
+}
+
+fn assignments_in_conditions(ast: &Ast) -> Vec<(String, Span)> {
+    return ast
+        .find_nodes(|n| match n {
+            Ast::Condition { condition, .. } | Ast::WhileLoop { condition, .. } => contains_assignment(condition),
+            _ => false,
+        })
+        .into_iter()
+        .map(|NodeRef { span, .. }| (String::from("assignment in a condition, compare the values with `==`"), span))
+        .collect();
+}
+
+fn is_magic(node: &Ast) -> bool {
+    return match node {
+        Ast::Int(value) => !(0..=2).contains(value),
+        Ast::Float(value) => *value != 0.0 && *value != 1.0 && *value != 2.0,
+        _ => false,
+    };
+}
+
+fn magic_numbers(ast: &Ast) -> Vec<(String, Span)> {
+    let mut res = Vec::new();
+    collect_magic_numbers(ast, Span::default(), &mut res);
+    return res;
+}
+
+fn collect_magic_numbers(node: &Ast, parent_span: Span, res: &mut Vec<(String, Span)>) {
+    let span = node.span().unwrap_or(parent_span);
+    match node {
+        // the values of constants are the names given to the numbers.
+        Ast::ConstDeclaration { .. } => return,
+        node if is_magic(node) => res.push((format!("magic number {:?}, give it a name with `const`", node), span)),
+        _ => (),
+    };
+    for child in node.children() {
+        collect_magic_numbers(child, span, res);
+    }
+}
+
+fn is_snake_case(name: &str) -> bool {
+    return name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+}
+
+fn non_snake_case_names(ast: &Ast) -> Vec<(String, Span)> {
+    let mut named = Vec::<(&str, &str, Span)>::new();
+    for NodeRef { node, span } in ast.find_nodes(|_| true) {
+        match node {
+            Ast::FunctionDeclaration { name, parameters, .. } | Ast::FunctionHeader { name, parameters, .. } => {
+                named.push(("function", name, span));
+                named.extend(parameters.iter().map(|parameter| ("parameter", parameter.name.as_str(), span)));
+            },
+            Ast::Lambda { parameters, .. } => named.extend(parameters.iter().map(|parameter| ("parameter", parameter.name.as_str(), span))),
+            Ast::Assignement { variable, .. } => if let Ast::Variable(var) = variable.as_ref() {
+                named.push(("variable", var.name.as_str(), span));
+            },
+            _ => (),
+        };
+    }
+
+    let mut res = Vec::new();
+    let mut reported = Vec::<&str>::new();
+    for (kind, name, span) in named {
+        if is_snake_case(name) || reported.contains(&name) {
+            continue;
+        }
+        reported.push(name);
+        res.push((format!("{} '{}' should have a snake_case name, as '{}'", kind, name, to_snake_case(name)), span));
+    }
+    return res;
+}
+
+/// `name` in snake_case: `maxValue` and `MaxValue` become `max_value`.
+fn to_snake_case(name: &str) -> String {
+    let mut res = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() && i > 0 && !res.ends_with('_') {
+            res.push('_');
+        }
+        res.push(c.to_ascii_lowercase());
+    }
+    return res;
+}
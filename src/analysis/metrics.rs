@@ -0,0 +1,118 @@
+use super::super::json;
+use super::super::parser::Ast;
+
+/// Measures of a function of the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionMetrics {
+    pub name: String,
+    pub line: usize,
+    /// cyclomatic complexity, one more than the decisions of the function: its `if`, `while`, `and` and `or`.
+    pub complexity: usize,
+    /// deepest nesting of `if` and `while` blocks, an `else if` not nesting deeper than its `if`.
+    pub nesting: usize,
+    /// statements of the function, blocks included, nested functions being measured on their own.
+    pub statements: usize,
+}
+
+/// Measures every function of the program, nested ones following the function declaring them.
+pub fn function_metrics(ast: &Ast) -> Vec<FunctionMetrics> {
+    let mut res = Vec::new();
+    if let Ast::Global(children) = ast {
+        for child in children {
+            collect_functions(child, &mut res);
+        }
+    }
+    return res;
+}
+
+fn collect_functions(node: &Ast, res: &mut Vec<FunctionMetrics>) {
+    let (name, children, span) = match node {
+        Ast::FunctionDeclaration { name, children, span, .. } => (name, children, span),
+        _ => return,
+    };
+    let mut metrics = FunctionMetrics { name: name.clone(), line: span.line, complexity: 1, nesting: 0, statements: 0 };
+    let index = res.len();
+    res.push(metrics.clone());
+    measure_block(children, 0, &mut metrics, res);
+    res[index] = metrics;
+}
+
+fn measure_block(children: &[Ast], depth: usize, metrics: &mut FunctionMetrics, res: &mut Vec<FunctionMetrics>) {
+    for child in children {
+        measure_statement(child, depth, metrics, res);
+    }
+}
+
+fn measure_statement(statement: &Ast, depth: usize, metrics: &mut FunctionMetrics, res: &mut Vec<FunctionMetrics>) {
+    match statement {
+        Ast::FunctionDeclaration { .. } => collect_functions(statement, res),
+        Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+            metrics.statements += 1;
+            metrics.complexity += 1 + decisions(condition);
+            metrics.nesting = metrics.nesting.max(depth + 1);
+            measure_block(valid_branch, depth + 1, metrics, res);
+            match invalid_branch.as_slice() {
+                // `else if` chains stay at the depth of their first `if`.
+                [branch @ Ast::Condition { .. }] => measure_statement(branch, depth, metrics, res),
+                branch => measure_block(branch, depth + 1, metrics, res),
+            };
+        },
+        Ast::WhileLoop { condition, children, .. } => {
+            metrics.statements += 1;
+            metrics.complexity += 1 + decisions(condition);
+            metrics.nesting = metrics.nesting.max(depth + 1);
+            measure_block(children, depth + 1, metrics, res);
+        },
+        Ast::Match { value, cases, default, .. } => {
+            // each case is a decision.
+            metrics.statements += 1;
+            metrics.complexity += cases.len() + decisions(value);
+            metrics.nesting = metrics.nesting.max(depth + 1);
+            for children in cases.iter().map(|(_, children)| children).chain(default.iter()) {
+                measure_block(children, depth + 1, metrics, res);
+            }
+        },
+        Ast::Try { children, handler, .. } => {
+            metrics.statements += 1;
+            metrics.complexity += 1;
+            metrics.nesting = metrics.nesting.max(depth + 1);
+            measure_block(children, depth + 1, metrics, res);
+            measure_block(handler, depth + 1, metrics, res);
+        },
+        statement => {
+            metrics.statements += 1;
+            metrics.complexity += decisions(statement);
+        },
+    };
+}
+
+/// Short-circuit operators of an expression, each of them branching.
+fn decisions(expression: &Ast) -> usize {
+    let own = match expression {
+        Ast::And { .. } | Ast::Or { .. } => 1,
+        _ => 0,
+    };
+    return own + expression.children().into_iter().map(decisions).sum::<usize>();
+}
+
+/// One line per function, with a header, the columns aligned.
+pub fn to_table(metrics: &[FunctionMetrics]) -> String {
+    let width = metrics.iter().map(|m| m.name.len()).chain(std::iter::once("function".len())).max().unwrap_or(0);
+    let mut res = format!("{:<width$}  {:>5}  {:>10}  {:>7}  {:>10}\n", "function", "line", "complexity", "nesting", "statements", width = width);
+    for m in metrics {
+        res.push_str(format!("{:<width$}  {:>5}  {:>10}  {:>7}  {:>10}\n", m.name, m.line, m.complexity, m.nesting, m.statements, width = width).as_str());
+    }
+    return res;
+}
+
+/// JSON array of the functions, `[{"name": "f", "line": 1, ...}]`.
+pub fn to_json(metrics: &[FunctionMetrics]) -> String {
+    let functions = metrics
+        .iter()
+        .map(|m| format!(
+            "{{\"name\": {}, \"line\": {}, \"complexity\": {}, \"nesting\": {}, \"statements\": {}}}",
+            json::string(&m.name), m.line, m.complexity, m.nesting, m.statements,
+        ))
+        .collect::<Vec<String>>();
+    return format!("[{}]\n", functions.join(", "));
+}
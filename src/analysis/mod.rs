@@ -0,0 +1,9 @@
+//! Measures of the source program, computed from its tree without compiling it: its style, its metrics, the functions
+//! it never reaches and the semantic tokens editors highlight it with. Its coverage is the exception, the statements
+//! run being counted by the interpreter running it.
+
+pub mod coverage;
+pub mod dead_functions;
+pub mod lint;
+pub mod metrics;
+pub mod semantic_tokens;
@@ -0,0 +1,260 @@
+//! Semantic tokens of a program, for editors to highlight it: each keyword, name and literal gets a kind, the names
+//! being told apart with the declarations of the program. `to_json` writes them as a list and `to_lsp` as the
+//! semantic tokens of the language server protocol.
+
+use super::super::diagnostic::Diagnostic;
+use super::super::json;
+use super::super::lexer::{Span, TokenType};
+use super::super::parser::{self, Ast};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Keyword,
+    Function,
+    Parameter,
+    Variable,
+    Type,
+    /// field of a record, where it is declared and where it is read.
+    Property,
+    Number,
+    String,
+}
+
+impl Kind {
+    /// Kinds in the order of the legend of `to_lsp`, which is the order of their declaration.
+    pub const ALL: [Kind; 8] = [Kind::Keyword, Kind::Function, Kind::Parameter, Kind::Variable, Kind::Type, Kind::Property, Kind::Number, Kind::String];
+
+    /// Name of the kind among the token types of the language server protocol.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Kind::Keyword => "keyword",
+            Kind::Function => "function",
+            Kind::Parameter => "parameter",
+            Kind::Variable => "variable",
+            Kind::Type => "type",
+            Kind::Property => "property",
+            Kind::Number => "number",
+            Kind::String => "string",
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SemanticToken {
+    /// position of the first character of the token.
+    pub span: Span,
+    /// characters of the token.
+    pub length: usize,
+    pub kind: Kind,
+}
+
+/// Parameters being declared by the header of a function or of a lambda.
+struct Header<'a> {
+    lambda: bool,
+    /// whether the function has a body, which `declare function` has not.
+    body: bool,
+    parentheses: usize,
+    parameters: Vec<&'a str>,
+}
+
+/// Characters of the source making `token`, which starts at the 1-based `column` of `line`.
+fn text_length(token: &TokenType, line: Option<&Vec<char>>, column: usize) -> usize {
+    return match token {
+        TokenType::EndLine => 0,
+        TokenType::Int(..) | TokenType::Float(..) => match line {
+            Some(line) => line.iter().skip(column.saturating_sub(1)).take_while(|c| c.is_ascii_digit() || **c == '.').count(),
+            None => 0,
+        },
+        TokenType::String(value) => value.chars().count() + 2,
+        TokenType::Bool(value) => value.to_string().len(),
+        // `int[]`, `.field`.
+        TokenType::ArrayTypeDef(name) => name.chars().count() + 2,
+        TokenType::Member(name) => name.chars().count() + 1,
+        // `@pragma`, its name and its value being read as text.
+        TokenType::Pragma(..) => "@pragma".len(),
+        TokenType::Keyword(text)
+        | TokenType::Variable(text)
+        | TokenType::FunctionCall(text)
+        | TokenType::TypeDef(text)
+        | TokenType::BinaryOperator(text)
+        | TokenType::UnaryOperator(text) => text.chars().count(),
+        _ => 1,
+    };
+}
+
+/// Names of the nodes of `ast` matching `name`.
+fn declared(ast: Option<&Ast>, name: fn(&Ast) -> Option<&String>) -> Vec<&str> {
+    return match ast {
+        Some(ast) => ast.find_nodes(|n| name(n).is_some()).into_iter().filter_map(|n| name(n.node)).map(|name| name.as_str()).collect(),
+        None => Vec::new(),
+    };
+}
+
+/// Classified tokens of `source`, in the order of the source. The names are told apart with the functions and records
+/// the program declares and with the parameters of the function or lambda containing them, a program which does not
+/// parse only getting the kinds its tokens tell. Fails when the source cannot be tokenized.
+pub fn semantic_tokens(source: &str) -> Result<Vec<SemanticToken>, Diagnostic> {
+    let (tokens, spans) = match super::super::tokenize_str(source) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let ast = parser::load_ast_with_spans(&tokens, &spans).ok();
+    let functions = declared(ast.as_ref(), |n| match n {
+        Ast::FunctionDeclaration { name, .. } | Ast::FunctionHeader { name, .. } => Some(name),
+        _ => None,
+    });
+    let records = declared(ast.as_ref(), |n| match n {
+        Ast::RecordDeclaration { name, .. } => Some(name),
+        _ => None,
+    });
+    let lines = source.lines().map(|line| line.chars().collect::<Vec<char>>()).collect::<Vec<Vec<char>>>();
+
+    let mut res = Vec::new();
+    // tokens lexed from the same word, as `b.x` or `<--1`, share its position and follow one another.
+    let mut previous: Option<(Span, usize)> = None;
+    let mut previous_keyword: Option<&str> = None;
+    let mut depth = 0;
+    // parameters of the functions containing the token, with the depth of the block of each function.
+    let mut scopes = Vec::<(usize, Vec<&str>)>::new();
+    // parameters of the lambdas of the line, whose body ends with it.
+    let mut lambda_parameters = Vec::<&str>::new();
+    let mut header: Option<Header> = None;
+    let mut record_depth: Option<usize> = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let span = spans[i];
+        let column = match previous {
+            Some((previous_span, end)) if previous_span == span => end,
+            _ => span.column,
+        };
+        let length = text_length(token, lines.get(span.line.wrapping_sub(1)), column);
+        previous = Some((span, column + length));
+        let next = tokens.get(i + 1);
+
+        let kind = match token {
+            TokenType::Keyword(keyword) => {
+                // an `else if` continues the block of its `if`, and a `declare function` opens none.
+                match (previous_keyword, keyword.as_str()) {
+                    (Some("else"), "if") => (),
+                    (Some("declare"), "function") => header = Some(Header { lambda: false, body: false, parentheses: 0, parameters: Vec::new() }),
+                    (_, "function") => {
+                        depth += 1;
+                        header = Some(Header { lambda: false, body: true, parentheses: 0, parameters: Vec::new() });
+                    },
+                    (_, "fn") => header = Some(Header { lambda: true, body: false, parentheses: 0, parameters: Vec::new() }),
+                    (_, "record") => {
+                        depth += 1;
+                        record_depth = Some(depth);
+                    },
+                    (_, "if" | "while" | "match" | "try") => depth += 1,
+                    (_, "end") => {
+                        if scopes.last().is_some_and(|(scope_depth, _)| *scope_depth == depth) {
+                            scopes.pop();
+                        }
+                        if record_depth == Some(depth) {
+                            record_depth = None;
+                        }
+                        depth -= depth.min(1);
+                    },
+                    _ => (),
+                };
+                previous_keyword = Some(keyword.as_str());
+                Some(Kind::Keyword)
+            },
+            TokenType::OpeningParenthesis => {
+                if let Some(header) = header.as_mut() {
+                    header.parentheses += 1;
+                }
+                None
+            },
+            TokenType::ClosingParenthesis => {
+                match header.as_mut() {
+                    Some(Header { parentheses: 1, .. }) => match header.take() {
+                        Some(Header { lambda: true, parameters, .. }) => lambda_parameters.extend(parameters),
+                        Some(Header { body: true, parameters, .. }) => scopes.push((depth, parameters)),
+                        _ => (),
+                    },
+                    Some(header) => header.parentheses -= header.parentheses.min(1),
+                    None => (),
+                };
+                None
+            },
+            TokenType::EndLine => {
+                // a header left open by a syntax error does not run past its line.
+                header = None;
+                lambda_parameters.clear();
+                None
+            },
+            TokenType::Variable(name) => Some(match (&mut header, next) {
+                (Some(Header { parentheses: 0, lambda: false, .. }), _) => Kind::Function,
+                (Some(header), Some(TokenType::Colon | TokenType::Comma | TokenType::ClosingParenthesis)) if header.parentheses == 1 => {
+                    header.parameters.push(name);
+                    Kind::Parameter
+                },
+                _ if previous_keyword == Some("record") => Kind::Type,
+                (_, Some(TokenType::Colon)) if record_depth == Some(depth) => Kind::Property,
+                _ if lambda_parameters.contains(&name.as_str()) || scopes.iter().any(|(_, parameters)| parameters.contains(&name.as_str())) => {
+                    Kind::Parameter
+                },
+                _ if functions.contains(&name.as_str()) => Kind::Function,
+                _ if records.contains(&name.as_str()) => Kind::Type,
+                _ => Kind::Variable,
+            }),
+            TokenType::BinaryOperator(operator) if operator == "and" || operator == "or" => Some(Kind::Keyword),
+            TokenType::Bool(..) | TokenType::Pragma(..) => Some(Kind::Keyword),
+            TokenType::FunctionCall(..) => Some(Kind::Function),
+            TokenType::TypeDef(..) | TokenType::ArrayTypeDef(..) => Some(Kind::Type),
+            TokenType::Member(..) => Some(Kind::Property),
+            TokenType::Int(..) | TokenType::Float(..) => Some(Kind::Number),
+            TokenType::String(..) => Some(Kind::String),
+            _ => None,
+        };
+        if !matches!(token, TokenType::Keyword(..)) {
+            previous_keyword = None;
+        }
+
+        let token = match (kind, token) {
+            (None, _) => continue,
+            // the name of the field follows its dot, and the brackets of an array type are punctuation.
+            (Some(kind), TokenType::Member(name)) => SemanticToken { span: Span { column: column + 1, ..span }, length: name.chars().count(), kind },
+            (Some(kind), TokenType::ArrayTypeDef(name)) => SemanticToken { span: Span { column, ..span }, length: name.chars().count(), kind },
+            (Some(kind), _) => SemanticToken { span: Span { column, ..span }, length, kind },
+        };
+        res.push(token);
+    }
+    return Ok(res);
+}
+
+/// `[{"line": 1, "column": 1, "length": 8, "kind": "keyword"}, ...]`, positions counting characters from 1.
+pub fn to_json(tokens: &[SemanticToken]) -> String {
+    let tokens = tokens
+        .iter()
+        .map(|t| format!("{{\"line\": {}, \"column\": {}, \"length\": {}, \"kind\": {}}}", t.span.line, t.span.column, t.length, json::string(t.kind.name())))
+        .collect::<Vec<String>>();
+    return format!("[{}]\n", tokens.join(", "));
+}
+
+/// UTF-16 code units before the `index`-th character of `line`.
+fn utf16_offset(line: &str, index: usize) -> usize {
+    return line.chars().take(index).map(char::len_utf16).sum();
+}
+
+/// `{"legend": {"tokenTypes": [...], "tokenModifiers": []}, "data": [...]}`, the `data` being encoded as the language
+/// server protocol tells: five integers per token, its line and start relative to the previous token, its length,
+/// its index in `tokenTypes` and its modifiers, none here. Positions count UTF-16 code units from 0.
+pub fn to_lsp(tokens: &[SemanticToken], source: &str) -> String {
+    let lines = source.lines().collect::<Vec<&str>>();
+    let mut data = Vec::<String>::new();
+    let (mut line, mut start) = (0, 0);
+    for token in tokens {
+        let text = lines.get(token.span.line.wrapping_sub(1)).copied().unwrap_or_default();
+        let token_line = token.span.line.saturating_sub(1);
+        let token_start = utf16_offset(text, token.span.column.saturating_sub(1));
+        let length = utf16_offset(text, token.span.column.saturating_sub(1) + token.length) - token_start;
+        let delta_start = if token_line == line { token_start - start } else { token_start };
+        data.push(format!("{}, {}, {}, {}, 0", token_line - line, delta_start, length, token.kind as usize));
+        (line, start) = (token_line, token_start);
+    }
+    let types = Kind::ALL.iter().map(|kind| json::string(kind.name())).collect::<Vec<String>>();
+    return format!("{{\"legend\": {{\"tokenTypes\": [{}], \"tokenModifiers\": []}}, \"data\": [{}]}}\n", types.join(", "), data.join(", "));
+}
@@ -0,0 +1,232 @@
+//! `algo_parser bench`, timing the calls to functions of the programs. Each call is added to the program, run several
+//! times by the interpreter after a warm up run, or built into an executable with `--native`. The program without the
+//! call is timed as well, its time being that of running the top level statements, and of starting the executable.
+
+use std::process::{exit, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use algo_parser::diagnostic::{Code, Diagnostic};
+use algo_parser::interpreter::program::run_program;
+use algo_parser::interpreter::trace::NoTrace;
+use algo_parser::lexer::Span;
+use algo_parser::{compiler, parser};
+
+use super::trace::interpreter_options;
+use super::{input_paths, message_format, message_language, read_sources, usage_error, Reporter, EXIT_FAILURE};
+
+/// Options of `bench` followed by a value.
+const BENCH_OPTIONS: [&str; 5] = ["--call", "--runs", "--checks", "--message-format", "--lang"];
+
+const DEFAULT_RUNS: usize = 10;
+
+/// Times of the runs of an executable, in milliseconds.
+struct Stats {
+    min: f64,
+    max: f64,
+    mean: f64,
+    median: f64,
+    /// standard deviation of the times.
+    deviation: f64,
+}
+
+impl Stats {
+    fn new(times: &[Duration]) -> Stats {
+        let mut times = times.iter().map(|time| time.as_secs_f64() * 1000.0).collect::<Vec<f64>>();
+        times.sort_by(|a, b| a.total_cmp(b));
+        let count = times.len() as f64;
+        let mean = times.iter().sum::<f64>() / count;
+        let median = match times.len() % 2 {
+            0 => (times[times.len() / 2 - 1] + times[times.len() / 2]) / 2.0,
+            _ => times[times.len() / 2],
+        };
+        let deviation = (times.iter().map(|time| (time - mean) * (time - mean)).sum::<f64>() / count).sqrt();
+        return Stats { min: times[0], max: times[times.len() - 1], mean, median, deviation };
+    }
+}
+
+/// Runs the executable `runs` times after a warm up run, its output being discarded.
+fn time_runs(path: &str, runs: usize) -> Result<Vec<Duration>, Diagnostic> {
+    let mut times = Vec::new();
+    for i in 0..=runs {
+        let start = Instant::now();
+        let status = match Command::new(path).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status() {
+            Err(e) => return Err(Diagnostic::error(Code::Build, format!("could not run {}: {}", path, e), Span::default())),
+            Ok(status) => status,
+        };
+        let time = start.elapsed();
+        if !status.success() {
+            return Err(Diagnostic::error(Code::Build, format!("the program failed with {}", status), Span::default()));
+        }
+        if i > 0 {
+            times.push(time);
+        }
+    }
+    return Ok(times);
+}
+
+/// Runs the program `runs` times with the interpreter after a warm up run, its output being discarded.
+fn time_interpreted(program: &parser::Ast, options: &compiler::CompilerOptions, runs: usize) -> Result<Vec<Duration>, Diagnostic> {
+    let mut times = Vec::new();
+    for i in 0..=runs {
+        let start = Instant::now();
+        let status = match run_program(program, options, &mut std::io::empty(), &mut std::io::sink(), &mut NoTrace) {
+            Err(e) => return Err(e),
+            Ok(status) => status,
+        };
+        let time = start.elapsed();
+        if status != 0 {
+            let status = format!("exit status: {}", status);
+            return Err(Diagnostic::error(Code::Build, format!("the program failed with {}", status), Span::default()));
+        }
+        if i > 0 {
+            times.push(time);
+        }
+    }
+    return Ok(times);
+}
+
+/// Calls given with `--call`, as `fibo(25)`, or the calls to the functions without parameters of the top level
+/// named `bench_*`, with the text they are printed as.
+fn benchmarks(args: &[String], ast: &parser::Ast) -> Result<Vec<(String, parser::Ast)>, Diagnostic> {
+    let mut res = Vec::new();
+    for (i, arg) in args.iter().enumerate() {
+        if arg != "--call" {
+            continue;
+        }
+        let call = match args.get(i + 1) {
+            Some(call) => call,
+            None => usage_error("missing value for --call"),
+        };
+        let statements = match algo_parser::parse_str(call) {
+            Err(e) => return Err(Diagnostic { message: format!("in --call '{}': {}", call, e.message), ..e }),
+            Ok(parser::Ast::Global(statements)) => statements,
+            Ok(_) => Vec::new(),
+        };
+        match statements.as_slice() {
+            // the call is not part of the files, it is located nowhere.
+            [parser::Ast::FunctionCall { name, children, .. }] => {
+                res.push((call.clone(), parser::Ast::FunctionCall { name: name.clone(), children: children.clone(), span: Span::default() }));
+            },
+            _ => return Err(Diagnostic::error(Code::Syntax, format!("--call '{}' is not a function call", call), Span::default())),
+        };
+    }
+    if !res.is_empty() {
+        return Ok(res);
+    }
+
+    if let parser::Ast::Global(children) = ast {
+        for child in children {
+            if let parser::Ast::FunctionDeclaration { name, parameters, .. } = child {
+                if name.starts_with("bench_") && parameters.is_empty() {
+                    res.push((format!("{}()", name), parser::Ast::FunctionCall { name: name.clone(), children: Vec::new(), span: Span::default() }));
+                }
+            }
+        }
+    }
+    return Ok(res);
+}
+
+/// Times the benchmarks of the files given in `args`.
+pub fn run(args: &[String]) {
+    let paths = match input_paths(args, &BENCH_OPTIONS, &["--native", "-O0", "-O1", "-O2"]) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let format = message_format(args);
+    let language = message_language(args);
+    let runs = match args.iter().position(|arg| arg == "--runs") {
+        Some(i) => match args.get(i + 1).map(|runs| runs.parse::<usize>()) {
+            Some(Ok(runs)) if runs > 0 => runs,
+            Some(..) => usage_error("invalid value for --runs, expected a positive number"),
+            None => usage_error("missing value for --runs"),
+        },
+        None => DEFAULT_RUNS,
+    };
+    let native = args.iter().any(|arg| arg == "--native");
+    // native benchmarks measure the optimized code unless told otherwise.
+    let opt_level = match args.iter().rev().find(|arg| arg.starts_with("-O")).map(|arg| arg.as_str()) {
+        None | Some("-O2") => compiler::OptLevel::O2,
+        Some("-O1") => compiler::OptLevel::O1,
+        Some("-O0") => compiler::OptLevel::O0,
+        Some(level) => usage_error(&format!("unknown optimization level '{}', expected -O0, -O1 or -O2", level)),
+    };
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+
+    let ast = match algo_parser::parse_sources(&sources) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(ast) => ast,
+    };
+    let benchmarks = match benchmarks(args, &ast) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(benchmarks) => benchmarks,
+    };
+    let interpreted = interpreter_options(args);
+    if !native {
+        match compiler::analyze(&ast, &interpreted) {
+            Err(diagnostics) => {
+                reporter.report(&diagnostics);
+                exit(EXIT_FAILURE);
+            },
+            Ok(diagnostics) => reporter.report(&diagnostics),
+        };
+    }
+    let children = match ast {
+        parser::Ast::Global(children) => children,
+        _ => Vec::new(),
+    };
+
+    let options = compiler::CompilerOptions { target: compiler::Target::Gas, opt_level, ..interpreted.clone() };
+    // the program without any call comes first, the others being compared to it.
+    let mut programs = vec![(String::from("baseline"), children.clone())];
+    for (name, call) in benchmarks {
+        let mut program = children.clone();
+        program.push(call);
+        programs.push((name, program));
+    }
+    let mut baseline = 0.0;
+    for (i, (name, program)) in programs.into_iter().enumerate() {
+        let program = parser::Ast::Global(program);
+        let times = match native {
+            false => time_interpreted(&program, &interpreted, runs),
+            true => {
+                let compiled = match compiler::compile(&program, &options) {
+                    Err(diagnostics) => {
+                        reporter.report(&diagnostics);
+                        exit(EXIT_FAILURE);
+                    },
+                    Ok(compiled) => compiled,
+                };
+                if i == 0 {
+                    reporter.report(&compiled.diagnostics);
+                }
+                algo_parser::with_executable(&compiled.code, &options, |path| time_runs(path, runs))
+            },
+        };
+        let stats = match times {
+            Err(e) => {
+                println!("bench {} ... failed", name);
+                reporter.report(&vec![e]);
+                exit(EXIT_FAILURE);
+            },
+            Ok(times) => Stats::new(&times),
+        };
+        let over = match i {
+            0 => {
+                baseline = stats.median;
+                String::new()
+            },
+            _ => format!(" ({:+.3} ms over the baseline)", stats.median - baseline),
+        };
+        println!(
+            "bench {} ... median {:.3} ms{}, mean {:.3} ms ± {:.3} ms, min {:.3} ms, max {:.3} ms, {} runs",
+            name, stats.median, over, stats.mean, stats.deviation, stats.min, stats.max, runs,
+        );
+    }
+}
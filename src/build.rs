@@ -0,0 +1,84 @@
+//! Assembles and links the programs compiled to x86-64 assembly with the tools of the system.
+
+use std::process::Command;
+
+use super::compiler::{runtime_library, AsmSyntax, Platform};
+use super::diagnostic::{Code, Diagnostic};
+use super::lexer::Span;
+
+/// Runs `program`, failing with its output when it does not succeed.
+pub fn run_tool(program: &str, args: &[&str]) -> Result<(), Diagnostic> {
+    let output = match Command::new(program).args(args).output() {
+        Err(e) => return Err(Diagnostic::error(Code::Build, format!("could not run {}: {}", program, e), Span::default())),
+        Ok(output) => output,
+    };
+    if !output.status.success() {
+        let message = format!("{} failed:\n{}", program, String::from_utf8_lossy(&output.stderr));
+        return Err(Diagnostic::error(Code::Build, message, Span::default()));
+    }
+    return Ok(());
+}
+
+/// Assembly source generated for the `output` executable.
+pub fn program_path(output: &str, syntax: AsmSyntax) -> String {
+    return format!("{}.{}", output, extension(syntax));
+}
+
+/// Assembly source of the runtime library of the `output` executable.
+pub fn runtime_path(output: &str, syntax: AsmSyntax) -> String {
+    return format!("{}.runtime.{}", output, extension(syntax));
+}
+
+fn extension(syntax: AsmSyntax) -> &'static str {
+    return match syntax {
+        AsmSyntax::Intel => "asm",
+        AsmSyntax::Att => "s",
+    };
+}
+
+/// Assembles the program written by the compiler along with the runtime library, and links them into the `output` executable.
+/// A `freestanding` executable is linked statically without the C library, the runtime starting it.
+/// The objects are in the format of `platform`.
+pub fn build_executable(syntax: AsmSyntax, output: &str, freestanding: bool, platform: Platform) -> Result<(), Diagnostic> {
+    let program = program_path(output, syntax);
+    let runtime = runtime_path(output, syntax);
+    if let Err(e) = std::fs::write(&runtime, runtime_library(syntax, freestanding)) {
+        return Err(Diagnostic::error(Code::Io, format!("could not write {}: {}", runtime, e), Span::default()));
+    }
+
+    let objects = match syntax {
+        // cc only understands gas, nasm sources are assembled first.
+        AsmSyntax::Intel => {
+            let objects = vec![format!("{}.o", program), format!("{}.o", runtime)];
+            for (source, object) in [&program, &runtime].iter().zip(objects.iter()) {
+                if let Err(e) = run_tool("nasm", &["-f", platform.object_format(), "-o", object, source]) {
+                    return Err(e);
+                }
+            }
+            objects
+        },
+        AsmSyntax::Att => vec![program, runtime],
+    };
+
+    let mut args = vec!["-no-pie", "-o", output];
+    if freestanding {
+        args.extend(["-nostdlib", "-static"]);
+    }
+    args.extend(objects.iter().map(|o| o.as_str()));
+    // the float builtins are those of the C math library.
+    if !freestanding {
+        args.push("-lm");
+    }
+    return run_tool("cc", &args);
+}
+
+/// Removes the executable built at `output` along with the sources and objects it was built from.
+pub fn remove_executable(syntax: AsmSyntax, output: &str) {
+    let program = program_path(output, syntax);
+    let runtime = runtime_path(output, syntax);
+    let objects = [format!("{}.o", program), format!("{}.o", runtime)];
+    for path in [output, &program, &runtime, &objects[0], &objects[1]] {
+        // the objects only exist for nasm.
+        let _ = std::fs::remove_file(path);
+    }
+}
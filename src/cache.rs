@@ -0,0 +1,165 @@
+//! On-disk cache of the compiler, keeping the syntax trees of the files and the code generated for whole programs
+//! under the hash of what they were built from. Entries are written by this version of the compiler only, and
+//! an entry which cannot be read or written is rebuilt rather than reported. Each entry starts with the key it was
+//! built from, which is compared on read: two keys sharing a hash share a file, the entry of the other key being a miss.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use super::compiler::{self, CompilerOptions};
+use super::diagnostic::{Code, Diagnostic};
+use super::lexer::{self, Span};
+use super::parser;
+
+/// Bumped when the format of the entries changes.
+const FORMAT: &str = "2";
+
+/// Key of an entry of `kind`, its parts each followed by a byte no text holds for `("ab", "c")` and `("a", "bc")` to
+/// differ.
+fn key(kind: &str, parts: &[&str]) -> Vec<u8> {
+    let mut res = Vec::<u8>::new();
+    for part in [env!("CARGO_PKG_VERSION"), FORMAT, kind].iter().chain(parts) {
+        res.extend_from_slice(part.as_bytes());
+        res.push(0xff);
+    }
+    return res;
+}
+
+/// FNV-1a hash of `bytes`.
+fn hash(bytes: &[u8]) -> u64 {
+    let mut res: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        res ^= *byte as u64;
+        res = res.wrapping_mul(0x100000001b3);
+    }
+    return res;
+}
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// Cache stored in `dir`, created when an entry is first written.
+    pub fn new(dir: &str) -> Cache {
+        return Cache { dir: PathBuf::from(dir) };
+    }
+
+    fn path(&self, kind: &str, key: &[u8]) -> PathBuf {
+        return self.dir.join(format!("{}-{:016x}", kind, hash(key)));
+    }
+
+    /// Content of the entry at `path`, `None` when it is missing, malformed or stored for another key.
+    fn read(&self, path: &PathBuf, key: &[u8]) -> Option<Vec<u8>> {
+        let bytes = match std::fs::read(path) {
+            Err(_) => return None,
+            Ok(bytes) => bytes,
+        };
+        // the entry starts with the length of its key on a line of its own, followed by the key and the content.
+        let end = match bytes.iter().position(|byte| *byte == b'\n') {
+            None => return None,
+            Some(end) => end,
+        };
+        let length = match std::str::from_utf8(&bytes[..end]).ok().and_then(|text| text.parse::<usize>().ok()) {
+            None => return None,
+            Some(length) => length,
+        };
+        let rest = &bytes[end + 1..];
+        if rest.len() < length || &rest[..length] != key {
+            return None;
+        }
+        return Some(rest[length..].to_vec());
+    }
+
+    fn store(&self, path: &PathBuf, key: &[u8], bytes: &[u8]) {
+        let mut entry = format!("{}\n", key.len()).into_bytes();
+        entry.extend_from_slice(key);
+        entry.extend_from_slice(bytes);
+        // the entry is written aside then renamed, for concurrent builds never to read half of it.
+        let partial = path.with_extension(format!("{}.tmp", std::process::id()));
+        if std::fs::create_dir_all(&self.dir).is_ok() && std::fs::write(&partial, entry).is_ok() {
+            let _ = std::fs::rename(&partial, path);
+        }
+    }
+
+    /// Syntax tree of `source`, the `file`-th file compiled, read from the cache or parsed and stored when missing.
+    pub fn parse_file(&self, source: &str, file: usize) -> Result<parser::Ast, Diagnostic> {
+        let key = key("ast", &[&file.to_string(), source]);
+        let path = self.path("ast", &key);
+        if let Some(ast) = self.read(&path, &key).and_then(|bytes| parser::from_bytes(&bytes)) {
+            return Ok(ast);
+        }
+        let lines = source.lines().map(String::from).collect::<Vec<String>>();
+        let (tokens, spans) = match lexer::tokenize_file(&lines, file) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let ast = match parser::load_ast_with_spans(&tokens, &spans) {
+            Err(e) => return Err(e),
+            Ok(ast) => ast,
+        };
+        self.store(&path, &key, &parser::to_bytes(&ast));
+        return Ok(ast);
+    }
+
+    /// Parses the files compiled together as `parse_sources` does, the unchanged files being read from the cache.
+    pub fn parse_sources(&self, sources: &[String]) -> Result<parser::Ast, Diagnostic> {
+        let mut programs = Vec::<parser::Ast>::new();
+        for (i, source) in sources.iter().enumerate() {
+            match self.parse_file(source, i) {
+                Err(e) => return Err(e),
+                Ok(program) => programs.push(program),
+            };
+        }
+        return compiler::merge_programs(&programs);
+    }
+
+    /// Compiles `ast`, parsed from `sources`, as `compiler::compile_to` does. The code generated for the same sources
+    /// and options is written from the cache, only the code of programs without warnings being stored for the builds
+    /// reading it to report the same diagnostics.
+    pub fn compile_to(&self, ast: &parser::Ast, sources: &[String], options: &CompilerOptions, out: &mut dyn Write) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+        let options_key = format!("{:?}", options);
+        let mut parts = vec![options_key.as_str()];
+        parts.extend(sources.iter().map(|source| source.as_str()));
+        let key = key("code", &parts);
+        let path = self.path("code", &key);
+
+        let (code, diagnostics) = match self.read(&path, &key) {
+            Some(code) => (code, Vec::new()),
+            None => match compiler::compile(ast, options) {
+                Err(diagnostics) => return Err(diagnostics),
+                Ok(compiled) => {
+                    if compiled.diagnostics.is_empty() {
+                        self.store(&path, &key, compiled.code.as_bytes());
+                    }
+                    (compiled.code.into_bytes(), compiled.diagnostics)
+                },
+            },
+        };
+        return match out.write_all(&code) {
+            Err(e) => Err(vec![Diagnostic::error(Code::Io, format!("could not write the generated code: {}", e), Span::default())]),
+            Ok(()) => Ok(diagnostics),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_an_entry_stored_for_another_key_as_a_miss() {
+        let dir = std::env::temp_dir().join(format!("algo-cache-{}", std::process::id()));
+        let cache = Cache::new(&dir.to_string_lossy());
+        let stored = key("code", &["x := 1"]);
+        let path = cache.path("code", &stored);
+        cache.store(&path, &stored, b"code");
+
+        let hit = cache.read(&path, &stored);
+        // a key whose hash is the same finds the file of the other one.
+        let miss = cache.read(&path, &key("code", &["x := 2"]));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(hit, Some(b"code".to_vec()));
+        assert_eq!(miss, None);
+    }
+}
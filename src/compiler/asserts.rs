@@ -0,0 +1,43 @@
+use std::convert::Infallible;
+
+use super::super::lexer::Span;
+use super::super::parser;
+
+/// Exit status of the programs whose assertion failed.
+pub const ASSERT_STATUS: i64 = 101;
+
+fn assertion(condition: parser::Ast, span: Span) -> parser::Ast {
+    let message = format!("assertion failed at line {}", span.line);
+    let failed = vec![
+        parser::Ast::FunctionCall { name: String::from("print"), children: vec![parser::Ast::Str(message)], span },
+        parser::Ast::Exit { code: Box::new(parser::Ast::Int(ASSERT_STATUS)), span },
+    ];
+    let condition = parser::Ast::EqualTo { left: Box::new(condition), right: Box::new(parser::Ast::Bool(false)) };
+    return parser::Ast::Condition { condition: Box::new(condition), valid_branch: failed, invalid_branch: Vec::new(), span };
+}
+
+fn lower(node: parser::Ast) -> Result<parser::Ast, Infallible> {
+    let node = match node {
+        parser::Ast::FunctionCall { name, mut children, span } if name == "assert" && children.len() == 1 => assertion(children.remove(0), span),
+        node => node,
+    };
+    return node.map_children(&mut lower);
+}
+
+/// Lowers the calls to `assert(condition)` to a statement printing the line of the call and ending the program
+/// with `ASSERT_STATUS` when the condition is false. Programs declaring their own `assert` are left as is.
+pub(super) fn lower_asserts(ast: &parser::Ast) -> parser::Ast {
+    let declared = !ast
+        .find_nodes(|n| match n {
+            parser::Ast::FunctionDeclaration { name, .. } | parser::Ast::FunctionHeader { name, .. } => name == "assert",
+            _ => false,
+        })
+        .is_empty();
+    if declared || ast.find_calls("assert").is_empty() {
+        return ast.clone();
+    }
+    return match lower(ast.clone()) {
+        Err(never) => match never {},
+        Ok(lowered) => lowered,
+    };
+}
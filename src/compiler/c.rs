@@ -0,0 +1,657 @@
+use std::collections::HashMap;
+
+use super::super::parser;
+use super::codegen::{expression_type, is_float};
+use super::{runtime, Backend, CompilerContext, Function, FunctionDeclaration, Type, Variable};
+
+/// Readable C99 source, using `stdbool.h` for booleans and a (pointer, length) struct for strings.
+pub struct CBackend;
+
+impl Backend for CBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return visit_module(context);
+    }
+}
+
+/// C identifier of a function symbol, symbols given with `as` may contain other characters.
+pub(super) fn function_name(name: &str) -> String {
+    let mut res = String::new();
+    for part in name.split(['(', ')', ',']) {
+        if part.is_empty() {
+            continue;
+        }
+        if !res.is_empty() {
+            res.push('_');
+        }
+        res.push_str(part.replace("[]", "_array").replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_").as_str());
+    }
+    return res;
+}
+
+/// C keywords which are valid variable names in the pseudocode.
+const RESERVED: [&str; 33] = [
+    "auto", "break", "case", "char", "const", "continue", "default", "do",
+    "double", "else", "enum", "extern", "float", "for", "goto", "if",
+    "inline", "int", "long", "register", "restrict", "return", "short", "signed", "sizeof",
+    "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile",
+];
+
+/// C identifier of a variable, keywords get a trailing underscore.
+fn variable_name(name: &str) -> String {
+    if RESERVED.contains(&name) || name == "main" {
+        return format!("{}_", name);
+    }
+    return name.to_string();
+}
+
+fn c_type(typeval: &Type) -> String {
+    if let Some(element) = &typeval.element {
+        if typeval.is_slice() {
+            return slice_name(element);
+        }
+        return format!("{} *", c_type(element));
+    }
+    return match typeval.name.as_str() {
+        "int" => String::from("int"),
+        "bool" => String::from("bool"),
+        "float" => String::from("double"),
+        "str" => String::from("str"),
+        // function references
+        _ => String::from("void *"),
+    };
+}
+
+/// Struct holding an array of unknown size, laid out like the strings.
+fn slice_name(element: &Type) -> String {
+    return format!("{}_slice", c_type(element).replace(" *", "_ptr"));
+}
+
+/// Typedefs of the arrays of unknown size used by the module, in order of first use.
+/// Arrays created with `new` are allocated by a constructor defined along with their type.
+fn slice_typedefs(context: &CompilerContext) -> String {
+    let mut elements = Vec::<Type>::new();
+    let names = context.extern_symbols.iter().chain(context.functions.iter().map(|f| &f.name));
+    let declared = names.filter_map(|name| context.declarations.get(name)).flat_map(|dec| dec.parameters.iter().chain(dec.return_type.iter()));
+    let variables = context.functions.iter().chain(std::iter::once(&context.main_function)).flat_map(|f| f.variables.iter().map(|v| &v.typeval));
+    for typeval in declared.chain(variables).chain(context.globals.iter().map(|g| &g.variable.typeval)) {
+        if let (true, Some(element)) = (typeval.is_slice(), &typeval.element) {
+            if !elements.contains(element) {
+                elements.push(*element.clone());
+            }
+        }
+    }
+    let mut res = String::new();
+    let allocates = context.extern_symbols.iter().any(|name| name == runtime::ALLOC_ROUTINE);
+    for element in &elements {
+        let name = slice_name(element);
+        res.push_str(format!("typedef struct {{\n    {}*data;\n    int length;\n}} {};\n\n", declaration("", element), name).as_str());
+        if allocates {
+            res.push_str(format!("static {} {}_new(int length) {{\n    return ({}){{calloc(length, sizeof({})), length}};\n}}\n\n", name, name, name, c_type(element)).as_str());
+        }
+    }
+    return res;
+}
+
+/// Declaration of a variable, fixed size arrays are declared inline.
+fn declaration(name: &str, typeval: &Type) -> String {
+    let name = variable_name(name);
+    return match (&typeval.element, typeval.count) {
+        (Some(element), Some(count)) => format!("{} {}[{}]", c_type(element), name, count),
+        _ => {
+            let typename = c_type(typeval);
+            if typename.ends_with('*') {
+                format!("{}{}", typename, name)
+            } else {
+                format!("{} {}", typename, name)
+            }
+        },
+    };
+}
+
+fn prototype(name: &str, dec: &FunctionDeclaration, parameters: Option<&[Variable]>) -> String {
+    let return_type = match &dec.return_type {
+        Some(typeval) => c_type(typeval),
+        None => String::from("void"),
+    };
+    let params = match parameters {
+        Some(parameters) => parameters.iter().map(|p| declaration(p.name.as_str(), &p.typeval)).collect::<Vec<String>>(),
+        None => dec.parameters.iter().map(c_type).collect::<Vec<String>>(),
+    };
+    let params = if params.is_empty() { String::from("void") } else { params.join(", ") };
+    let separator = if return_type.ends_with('*') { "" } else { " " };
+    return format!("{}{}{}({})", return_type, separator, function_name(name), params);
+}
+
+/// C string literal, non printable characters are octal escaped.
+fn string_literal(value: &str) -> String {
+    let mut res = String::from("\"");
+    for byte in value.bytes() {
+        match byte {
+            b'"' => res.push_str("\\\""),
+            b'\\' => res.push_str("\\\\"),
+            b'\n' => res.push_str("\\n"),
+            b'\t' => res.push_str("\\t"),
+            byte if byte.is_ascii_graphic() || byte == b' ' => res.push(byte as char),
+            byte => res.push_str(format!("\\{:03o}", byte).as_str()),
+        };
+    }
+    res.push('"');
+    return res;
+}
+
+/// Precedence of the C operator of a node, higher binds tighter.
+pub(super) fn precedence(expression: &parser::Ast) -> u8 {
+    return match expression {
+        parser::Ast::Or {..} => 1,
+        parser::Ast::And {..} => 2,
+        parser::Ast::EqualTo {..} | parser::Ast::NotEqualTo {..} => 3,
+        parser::Ast::GreaterThan {..}
+        | parser::Ast::GreaterOrEqual {..}
+        | parser::Ast::LowerThan {..}
+        | parser::Ast::LowerOrEqual {..} => 4,
+        parser::Ast::Addition {..} | parser::Ast::Substraction {..} => 5,
+        parser::Ast::Multiplication {..} | parser::Ast::Division {..} | parser::Ast::Modulo {..} => 6,
+        parser::Ast::UnaryPlus {..} | parser::Ast::UnaryMinus {..} | parser::Ast::Cast {..} => 7,
+        _ => 8,
+    };
+}
+
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    variables: HashMap<String, Type>,
+    output: String,
+    depth: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    fn emit(&mut self, line: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str("    ");
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    fn visit_operand(&self, operand: &parser::Ast, parent: &parser::Ast, is_right: bool) -> Result<String, String> {
+        let value = match self.visit_expression(operand) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let (operand_precedence, parent_precedence) = (precedence(operand), precedence(parent));
+        if operand_precedence < parent_precedence || is_right && operand_precedence == parent_precedence {
+            return Ok(format!("({})", value));
+        }
+        return Ok(value);
+    }
+
+    fn visit_expression(&self, expression: &parser::Ast) -> Result<String, String> {
+        return Ok(match expression {
+            parser::Ast::Int(val) => val.to_string(),
+            parser::Ast::Bool(val) => val.to_string(),
+            parser::Ast::Float(val) => format!("{:?}", val),
+            parser::Ast::Str(val) => format!("(str){{{}, {}}}", string_literal(val), val.len()),
+            parser::Ast::Variable(var) => variable_name(&var.name),
+            parser::Ast::MemberAccess { record, field } => match expression_type(record, &self.variables, self.context) {
+                Err(e) => return Err(e),
+                Ok(typeval) if typeval.is_record() => return Err(String::from("codegen: records are not supported by the C backend")),
+                Ok(_) => match self.visit_expression(record) {
+                    Err(e) => return Err(e),
+                    Ok(val) => format!("{}.{}", val, field),
+                },
+            },
+            parser::Ast::ArrayAccess { variable, index } => match self.visit_expression(index) {
+                Err(e) => return Err(e),
+                Ok(index) if self.variables.get(variable).is_some_and(|typeval| typeval.is_slice()) => format!("{}.data[{}]", variable_name(variable), index),
+                Ok(index) => format!("{}[{}]", variable_name(variable), index),
+            },
+            parser::Ast::FunctionReference(name) => format!("(void *)&{}", function_name(name)),
+            parser::Ast::NewArray { size, .. } => match (self.visit_expression(size), expression_type(expression, &self.variables, self.context)) {
+                (Err(e), _) | (_, Err(e)) => return Err(e),
+                (Ok(size), Ok(Type { element: Some(element), .. })) => format!("{}_new({})", slice_name(&element), size),
+                (Ok(..), Ok(typeval)) => return Err(format!("codegen: cannot allocate {}", typeval)),
+            },
+            // arrays created with `new` are released by the C library.
+            parser::Ast::FunctionCall { name, children, .. } if name == runtime::RELEASE_ROUTINE => match children.first().map(|child| self.visit_expression(child)) {
+                Some(Err(e)) => return Err(e),
+                Some(Ok(val)) => format!("free({}.data)", val),
+                None => return Err(String::from("codegen: missing array to release")),
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                let mut arguments = Vec::<String>::new();
+                for child in children {
+                    match self.visit_expression(child) {
+                        Err(e) => return Err(e),
+                        Ok(val) => arguments.push(val),
+                    };
+                }
+                format!("{}({})", function_name(name), arguments.join(", "))
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_operand(child, expression, false),
+            parser::Ast::UnaryMinus { child } => match self.visit_operand(child, expression, false) {
+                Err(e) => return Err(e),
+                // avoids `--x`, which is a decrement in C.
+                Ok(val) if val.starts_with('-') => format!("-({})", val),
+                Ok(val) => format!("-{}", val),
+            },
+            parser::Ast::Cast { child, .. } if matches!(expression_type(expression, &self.variables, self.context), Ok(typeval) if typeval.is_slice()) => {
+                match (self.visit_expression(child), expression_type(child, &self.variables, self.context)) {
+                    (Err(e), _) | (_, Err(e)) => return Err(e),
+                    (Ok(val), Ok(Type { element: Some(element), count: Some(count), .. })) => format!("({}){{{}, {}}}", slice_name(&element), val, count),
+                    (Ok(val), Ok(..)) => val,
+                }
+            },
+            parser::Ast::Cast { child, typename } => match self.visit_operand(child, expression, false) {
+                Err(e) => return Err(e),
+                Ok(val) if typename == "float" => format!("(double){}", val),
+                Ok(val) => format!("(int){}", val),
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right }
+            | parser::Ast::And { left, right }
+            | parser::Ast::Or { left, right } => {
+                let left_type = match expression_type(left, &self.variables, self.context) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let operator = match expression {
+                    parser::Ast::Addition {..} => "+",
+                    parser::Ast::Substraction {..} => "-",
+                    parser::Ast::Multiplication {..} => "*",
+                    parser::Ast::Division {..} => "/",
+                    parser::Ast::Modulo {..} if is_float(&left_type) => return Err(String::from("codegen: modulo is not defined on floats")),
+                    parser::Ast::Modulo {..} => "%",
+                    parser::Ast::EqualTo {..} => "==",
+                    parser::Ast::NotEqualTo {..} => "!=",
+                    parser::Ast::GreaterThan {..} => ">",
+                    parser::Ast::GreaterOrEqual {..} => ">=",
+                    parser::Ast::LowerThan {..} => "<",
+                    parser::Ast::And {..} => "&&",
+                    parser::Ast::Or {..} => "||",
+                    _ => "<=",
+                };
+                let left = match self.visit_operand(left, expression, false) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right = match self.visit_operand(right, expression, true) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                format!("{} {} {}", left, operator, right)
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        });
+    }
+
+    fn visit_block(&mut self, statements: &[parser::Ast]) -> Result<(), String> {
+        self.depth += 1;
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        self.depth -= 1;
+        return Ok(());
+    }
+
+    /// Emits an `if`, `else if` chains are kept flat.
+    fn visit_condition(&mut self, condition: &parser::Ast, valid_branch: &[parser::Ast], invalid_branch: &[parser::Ast], keyword: &str) -> Result<(), String> {
+        let condition = match self.visit_expression(condition) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        self.emit(format!("{} ({}) {{", keyword, condition).as_str());
+        if let Err(e) = self.visit_block(valid_branch) {
+            return Err(e);
+        }
+        match invalid_branch {
+            [] => self.emit("}"),
+            [parser::Ast::Condition { condition, valid_branch, invalid_branch, .. }] => {
+                return self.visit_condition(condition, valid_branch, invalid_branch, "} else if");
+            },
+            statements => {
+                self.emit("} else {");
+                if let Err(e) = self.visit_block(statements) {
+                    return Err(e);
+                }
+                self.emit("}");
+            },
+        };
+        return Ok(());
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                let target = match self.visit_expression(variable) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if let parser::Ast::ArrayValue(children) = &**expression {
+                    for (i, child) in children.iter().enumerate() {
+                        match self.visit_expression(child) {
+                            Err(e) => return Err(e),
+                            Ok(val) => self.emit(format!("{}[{}] = {};", target, i, val).as_str()),
+                        };
+                    }
+                    return Ok(());
+                }
+                match self.visit_expression(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) => self.emit(format!("{} = {};", target, val).as_str()),
+                };
+            },
+            parser::Ast::ReturnStatement { value: None, .. } => self.emit("return;"),
+            parser::Ast::ReturnStatement { value: Some(value), .. } => match self.visit_expression(value) {
+                Err(e) => return Err(e),
+                Ok(val) => self.emit(format!("return {};", val).as_str()),
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                return self.visit_condition(condition, valid_branch, invalid_branch, "if");
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                match self.visit_expression(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => self.emit(format!("while ({}) {{", val).as_str()),
+                };
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+                self.emit("}");
+            },
+            expression => match self.visit_expression(expression) {
+                Err(e) => return Err(e),
+                Ok(val) => self.emit(format!("{};", val).as_str()),
+            },
+        };
+        return Ok(());
+    }
+}
+
+fn visit_function(func: &Function, context: &CompilerContext, is_main: bool) -> Result<String, String> {
+    let mut generator = FunctionGenerator {
+        context,
+        // locals shadow the globals.
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        output: String::new(),
+        depth: 0,
+    };
+
+    let header = match (is_main, context.declarations.get(&func.name)) {
+        (true, _) => String::from("int main(void)"),
+        (false, Some(dec)) => prototype(&func.name, dec, Some(&func.variables[..func.parameter_count])),
+        (false, None) => return Err(format!("codegen: undefined function {}", func.name)),
+    };
+    generator.emit(format!("{} {{", header).as_str());
+
+    generator.depth += 1;
+    for var in &func.variables[func.parameter_count..] {
+        generator.emit(format!("{};", declaration(var.name.as_str(), &var.typeval)).as_str());
+    }
+    if func.variables.len() > func.parameter_count {
+        generator.output.push('\n');
+    }
+    generator.depth -= 1;
+
+    if let Err(e) = generator.visit_block(&func.statements) {
+        return Err(e);
+    }
+    generator.emit("}");
+    return Ok(generator.output);
+}
+
+/// File scope variables, starting with their value or zeroed.
+fn visit_globals(context: &CompilerContext) -> Result<String, String> {
+    let generator = FunctionGenerator { context, variables: HashMap::new(), output: String::new(), depth: 0 };
+    let mut res = String::new();
+    for global in &context.globals {
+        let declaration = declaration(global.variable.name.as_str(), &global.variable.typeval);
+        match &global.value {
+            None => res.push_str(format!("static {};\n", declaration).as_str()),
+            Some(value) => match generator.visit_expression(value) {
+                Err(e) => return Err(e),
+                Ok(value) => res.push_str(format!("static {} = {};\n", declaration, value).as_str()),
+            },
+        };
+    }
+    if !res.is_empty() {
+        res.push('\n');
+    }
+    return Ok(res);
+}
+
+/// C implementation of the runtime functions, strings being written and compared by their length as they are not
+/// terminated by a null character.
+fn runtime_function(symbol: &str) -> Option<&'static str> {
+    return match symbol {
+        "print_int" => Some("static void print_int(int value) {\n    printf(\"%d\\n\", value);\n}\n"),
+        "print_str" => Some("static void print_str(str value) {\n    printf(\"%.*s\\n\", value.length, value.data);\n}\n"),
+        "write_int" => Some("static void write_int(int value) {\n    printf(\"%d\", value);\n}\n"),
+        "write_str" => Some("static void write_str(str value) {\n    printf(\"%.*s\", value.length, value.data);\n}\n"),
+        // same as the compiled programs: the other characters than digits and `-` of the line are ignored.
+        "read_int" => Some(concat!(
+            "static int read_int(void) {\n    int value = 0;\n    bool negative = false;\n    int c;\n",
+            "    while ((c = getchar()) != EOF && c != '\\n') {\n        if (c == '-') {\n            negative = true;\n",
+            "        } else if (c >= '0' && c <= '9') {\n            value = value * 10 + (c - '0');\n        }\n    }\n",
+            "    return negative ? -value : value;\n}\n",
+        )),
+        "str_length" => Some("static int str_length(str value) {\n    return value.length;\n}\n"),
+        "str_substring" => Some(concat!(
+            "static str str_substring(str value, int start, int length) {\n",
+            "    start = start < 0 ? 0 : start > value.length ? value.length : start;\n",
+            "    length = length < 0 ? 0 : length > value.length - start ? value.length - start : length;\n",
+            "    return (str){value.data + start, length};\n}\n",
+        )),
+        "str_concat" => Some(concat!(
+            "static str str_concat(str a, str b) {\n    char *data = malloc(a.length + b.length + 1);\n",
+            "    memcpy(data, a.data, a.length);\n    memcpy(data + a.length, b.data, b.length);\n",
+            "    return (str){data, a.length + b.length};\n}\n",
+        )),
+        "str_compare" => Some(concat!(
+            "static int str_compare(str a, str b) {\n    int order = memcmp(a.data, b.data, a.length < b.length ? a.length : b.length);\n",
+            "    if (order == 0) {\n        order = a.length - b.length;\n    }\n    return (order > 0) - (order < 0);\n}\n",
+        )),
+        "int_to_string" => Some(concat!(
+            "static str int_to_string(int value) {\n    char *data = malloc(12);\n",
+            "    return (str){data, sprintf(data, \"%d\", value)};\n}\n",
+        )),
+        "int_abs" => Some("static int int_abs(int value) {\n    return value < 0 ? -value : value;\n}\n"),
+        "int_min" => Some("static int int_min(int a, int b) {\n    return a < b ? a : b;\n}\n"),
+        "int_max" => Some("static int int_max(int a, int b) {\n    return a > b ? a : b;\n}\n"),
+        "exit_program" => Some("static void exit_program(int code) {\n    exit(code);\n}\n"),
+        _ => None,
+    };
+}
+
+/// C implementation of the runtime functions on arrays, which only differ by their element type.
+fn array_function(symbol: &str, context: &CompilerContext) -> Option<String> {
+    let operation = match symbol.split_once("_array_") {
+        None => return None,
+        Some((_, operation)) => operation,
+    };
+    let (slice, element) = match context.declarations.get(symbol).and_then(|dec| dec.parameters.first()) {
+        Some(typeval @ Type { element: Some(element), .. }) => (c_type(typeval), c_type(element)),
+        _ => return None,
+    };
+    return match operation {
+        "copy" => Some(format!(
+            "static {} {}({} values) {{\n    {} res = {{malloc(values.length * sizeof({})), values.length}};\n    memcpy(res.data, values.data, values.length * sizeof({}));\n    return res;\n}}\n",
+            slice, symbol, slice, slice, element, element,
+        )),
+        "append" => Some(format!(
+            "static {} {}({} values, {} value) {{\n    {} res = {{malloc((values.length + 1) * sizeof({})), values.length + 1}};\n    memcpy(res.data, values.data, values.length * sizeof({}));\n    res.data[values.length] = value;\n    return res;\n}}\n",
+            slice, symbol, slice, element, slice, element, element,
+        )),
+        "fill" => Some(format!(
+            "static void {}({} values, {} value) {{\n    for (int i = 0; i < values.length; i++) {{\n        values.data[i] = value;\n    }}\n}}\n",
+            symbol, slice, element,
+        )),
+        "sort" => Some(format!(
+            "static int {}_order(const void *a, const void *b) {{\n    {} x = *(const {} *)a, y = *(const {} *)b;\n    return (x > y) - (x < y);\n}}\n\nstatic void {}({} values) {{\n    qsort(values.data, values.length, sizeof({}), {}_order);\n}}\n",
+            symbol, element, element, element, symbol, slice, element, symbol,
+        )),
+        _ => None,
+    };
+}
+
+/// Entries of the maps, a list starting with an empty entry so that the maps can be shared, `NULL` standing for a map
+/// never assigned. Each entry holds the fields of every key and value type, only those of its map being used.
+const MAP_ENTRY: &str = "typedef struct map_entry {\n    struct map_entry *next;\n    int int_key;\n    str str_key;\n    int int_value;\n    double float_value;\n    bool bool_value;\n    str str_value;\n} map_entry;\n";
+
+/// Entry of the map holding the key of type `key`, or `NULL`, as `int_map_find`.
+fn map_find(key: &str) -> String {
+    let equal = match key {
+        "str" => "entry->str_key.length == key.length && memcmp(entry->str_key.data, key.data, key.length) == 0",
+        _ => "entry->int_key == key",
+    };
+    return format!(
+        "static map_entry *{}_map_find(void *values, {} key) {{\n    for (map_entry *entry = values == NULL ? NULL : ((map_entry *)values)->next; entry != NULL; entry = entry->next) {{\n        if ({}) {{\n            return entry;\n        }}\n    }}\n    return NULL;\n}}\n",
+        key, key, equal,
+    );
+}
+
+/// C implementation of the runtime functions on maps, through the `map_find` of their key type.
+fn map_function(symbol: &str, context: &CompilerContext) -> Option<String> {
+    if symbol == runtime::MAP_NEW_ROUTINE {
+        return Some(String::from("static void *map_new(void) {\n    return calloc(1, sizeof(map_entry));\n}\n"));
+    }
+    let (types, operation) = match symbol.split_once("_map_") {
+        None => return None,
+        Some(val) => val,
+    };
+    let key = types.split('_').next().unwrap_or(types);
+    let key_type = if key == "str" { "str" } else { "int" };
+    // the value type is the one returned by `get` and stored by `set`.
+    let (value, value_type) = match context.declarations.get(symbol).and_then(|dec| dec.parameters.get(2).or(dec.return_type.as_ref())) {
+        Some(typeval) => (typeval.name.clone(), c_type(typeval)),
+        None => (String::new(), String::new()),
+    };
+    return match operation {
+        "get" => Some(format!(
+            "static {} {}(void *values, {} key) {{\n    map_entry *entry = {}_map_find(values, key);\n    return entry == NULL ? ({}){{0}} : entry->{}_value;\n}}\n",
+            value_type, symbol, key_type, key, value_type, value,
+        )),
+        "set" => Some(format!(
+            concat!(
+                "static void *{}(void *values, {} key, {} value) {{\n    map_entry *entry = {}_map_find(values, key);\n",
+                "    if (values == NULL) {{\n        values = calloc(1, sizeof(map_entry));\n    }}\n",
+                "    if (entry == NULL) {{\n        entry = calloc(1, sizeof(map_entry));\n        entry->{}_key = key;\n",
+                "        entry->next = ((map_entry *)values)->next;\n        ((map_entry *)values)->next = entry;\n    }}\n",
+                "    entry->{}_value = value;\n    return values;\n}}\n",
+            ),
+            symbol, key_type, value_type, key, key, value,
+        )),
+        "contains" => Some(format!(
+            "static bool {}(void *values, {} key) {{\n    return {}_map_find(values, key) != NULL;\n}}\n",
+            symbol, key_type, key,
+        )),
+        "remove" => Some(format!(
+            concat!(
+                "static void {}(void *values, {} key) {{\n    map_entry *entry = {}_map_find(values, key);\n",
+                "    for (map_entry *previous = values; entry != NULL && previous != NULL; previous = previous->next) {{\n",
+                "        if (previous->next == entry) {{\n            previous->next = entry->next;\n            free(entry);\n            return;\n        }}\n    }}\n}}\n",
+            ),
+            symbol, key_type, key,
+        )),
+        _ => None,
+    };
+}
+
+/// Definitions of the runtime functions called by the module, along with the map entries and the functions finding
+/// their keys when maps are used.
+fn runtime_definitions(context: &CompilerContext) -> Vec<String> {
+    let mut res = Vec::<String>::new();
+    let maps = context.extern_symbols.iter().filter_map(|name| map_function(name, context).map(|source| (name, source))).collect::<Vec<(&String, String)>>();
+    if !maps.is_empty() {
+        res.push(String::from(MAP_ENTRY));
+        for key in ["int", "str"] {
+            if maps.iter().any(|(name, _)| name.starts_with(format!("{}_", key).as_str())) {
+                res.push(map_find(key));
+            }
+        }
+    }
+    for name in &context.extern_symbols {
+        if let Some(source) = runtime_function(name).map(String::from).or_else(|| array_function(name, context)).or_else(|| map_function(name, context)) {
+            res.push(source);
+        }
+    }
+    return res;
+}
+
+fn visit_module(context: &CompilerContext) -> Result<String, String> {
+    let mut res = String::from("#include <stdbool.h>\n");
+    let definitions = runtime_definitions(context);
+    // the runtime functions are defined on the C library.
+    if !definitions.is_empty() {
+        res.push_str("#include <stdio.h>\n");
+    }
+    // the runtime routines managing arrays are replaced by the C library.
+    let allocation = [runtime::ALLOC_ROUTINE, runtime::RELEASE_ROUTINE];
+    if !definitions.is_empty() || context.extern_symbols.iter().any(|name| allocation.contains(&name.as_str())) {
+        res.push_str("#include <stdlib.h>\n");
+    }
+    if !definitions.is_empty() {
+        res.push_str("#include <string.h>\n");
+    }
+    // the float builtins are those of the C math library, declared by its header.
+    if context.extern_symbols.iter().any(|name| runtime::is_math_function(name)) {
+        res.push_str("#include <math.h>\n");
+    }
+    res.push('\n');
+    // same layout as the compiled strings, so extern functions receive the same values.
+    res.push_str("typedef struct {\n    const char *data;\n    int length;\n} str;\n\n");
+    res.push_str(slice_typedefs(context).as_str());
+    for definition in definitions {
+        res.push_str(definition.as_str());
+        res.push('\n');
+    }
+
+    let defined = |name: &str| runtime_function(name).is_some() || array_function(name, context).is_some() || map_function(name, context).is_some();
+    let names = context.extern_symbols.iter()
+        .filter(|name| !allocation.contains(&name.as_str()) && !runtime::is_math_function(name) && !defined(name))
+        .chain(context.functions.iter().map(|f| &f.name));
+    let mut has_prototypes = false;
+    for name in names {
+        match context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => res.push_str(format!("{};\n", prototype(name, dec, None)).as_str()),
+        };
+        has_prototypes = true;
+    }
+    if has_prototypes {
+        res.push('\n');
+    }
+
+    match visit_globals(context) {
+        Err(e) => return Err(e),
+        Ok(val) => res.push_str(val.as_str()),
+    };
+
+    for f in &context.functions {
+        match visit_function(f, context, false) {
+            Err(e) => return Err(e),
+            Ok(val) => {
+                res.push_str(val.as_str());
+                res.push('\n');
+            },
+        };
+    }
+
+    return match visit_function(&context.main_function, context, true) {
+        Err(e) => Err(e),
+        Ok(val) => {
+            res.push_str(val.as_str());
+            Ok(res)
+        },
+    };
+}
@@ -0,0 +1,1077 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::super::lexer::Span;
+use super::super::parser;
+use super::ir;
+use super::source_map::{self, SourceMap};
+use super::syntax::{self, AsmSyntax};
+use super::super::error::CompileError;
+use super::{codegen_error, write_code, written_code, Backend, CompilerContext, StringTable, Type, Variable, int_type, float_type, bool_type, string_type, array_type, function_type, align_to};
+
+/// System V AMD64 integer argument registers, as (64 bits, 32 bits, 8 bits) names.
+const INTEGER_REGISTERS: [(&str, &str, &str); 6] = [
+    ("rdi", "edi", "dil"),
+    ("rsi", "esi", "sil"),
+    ("rdx", "edx", "dl"),
+    ("rcx", "ecx", "cl"),
+    ("r8", "r8d", "r8b"),
+    ("r9", "r9d", "r9b"),
+];
+
+/// Registers holding the operands of an instruction, as (64 bits, 32 bits, 8 bits) names.
+const ACCUMULATORS: [(&str, &str, &str); 2] = [
+    ("rax", "eax", "al"),
+    ("rcx", "ecx", "cl"),
+];
+
+/// Number of `xmm` registers used to pass floating point arguments.
+const FLOAT_REGISTERS: usize = 8;
+
+pub(super) fn generate_variable_addresses(variables: &Vec<Variable>, stack_size: u64) -> Result<HashMap<String, u64>, String> {
+    let mut res = HashMap::new();
+
+    let mut current_offset = 0;
+
+    for var in variables {
+        current_offset += var.typeval.size;
+        res.insert(var.name.clone(), current_offset);
+    }
+
+    if current_offset == stack_size {
+        return Ok(res);
+    } else {
+        return Err(format!("mismatched stack size, expected {}, got {}", stack_size, current_offset));
+    }
+
+}
+
+pub(super) fn is_float(typeval: &Type) -> bool {
+    return typeval == &float_type();
+}
+
+pub(super) fn is_string(typeval: &Type) -> bool {
+    return typeval == &string_type();
+}
+
+/// Whether the value is a pointer followed by a length, as strings and arrays of unknown size.
+fn is_pair(typeval: &Type) -> bool {
+    return is_string(typeval) || typeval.is_slice();
+}
+
+/// Address `offset` bytes after the memory operand `address`.
+fn shift_address(address: &str, offset: u64) -> String {
+    return format!("{} + {}]", address.trim_end_matches(']'), offset);
+}
+
+/// Number of 8 bytes registers (or stack slots) holding a value, pairs using one for the length.
+fn eightbytes(typeval: &Type) -> usize {
+    return if is_pair(typeval) { 2 } else { 1 };
+}
+
+/// Target type of a `Cast` node, casts convert between ints and floats.
+pub(super) fn cast_type(typename: &String) -> Result<Type, String> {
+    return match typename.as_str() {
+        "int" => Ok(int_type()),
+        "float" => Ok(float_type()),
+        _ => Err(format!("codegen: unsupported conversion to {}", typename)),
+    };
+}
+
+/// Type of `new typename[n]`, records are rejected before code generation.
+pub(super) fn new_array_type(typename: &String) -> Result<Type, String> {
+    let element = match typename.as_str() {
+        "int" => int_type(),
+        "float" => float_type(),
+        "bool" => bool_type(),
+        "str" => string_type(),
+        _ => return Err(format!("codegen: unsupported array of {}", typename)),
+    };
+    return Ok(array_type(element, None));
+}
+
+fn inverse_condition(code: &str) -> &'static str {
+    return match code {
+        "e" => "ne",
+        "ne" => "e",
+        "g" => "le",
+        "ge" => "l",
+        "l" => "ge",
+        "le" => "g",
+        "a" => "be",
+        "ae" => "b",
+        "b" => "ae",
+        _ => "a",
+    };
+}
+
+/// x86-64 assembly, following the System V AMD64 calling convention.
+/// Each statement is preceded by a comment quoting its line when the options give the source.
+pub struct X86Backend {
+    pub syntax: AsmSyntax,
+}
+
+impl Backend for X86Backend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return written_code(|out| self.write_module(context, out));
+    }
+
+    /// Writes the functions one at a time.
+    fn write(&self, context: &CompilerContext, out: &mut dyn Write) -> Result<(), CompileError> {
+        return match self.write_module(context, out) {
+            Err(e) => Err(codegen_error(e)),
+            Ok(()) => Ok(()),
+        };
+    }
+}
+
+impl X86Backend {
+    fn write_module(&self, context: &CompilerContext, out: &mut dyn Write) -> Result<(), String> {
+        return match visit_module(context, self.syntax, context.options.debug_info.as_ref(), out) {
+            Err(e) => Err(e),
+            Ok(_) => Ok(()),
+        };
+    }
+}
+
+/// Lines of the assembly generated in `syntax` for the statements of the program.
+pub(super) fn source_map(context: &CompilerContext, syntax: AsmSyntax) -> Result<SourceMap, String> {
+    let mut code = Vec::<u8>::new();
+    return match visit_module(context, syntax, context.options.debug_info.as_ref(), &mut code) {
+        Err(e) => Err(e),
+        Ok(offsets) => Ok(source_map::build_source_map(&String::from_utf8_lossy(&code), &offsets)),
+    };
+}
+
+/// Type of an expression of the flattened tree, given the variables of the function.
+pub(super) fn expression_type(expression: &parser::Ast, variables: &HashMap<String, Type>, context: &CompilerContext) -> Result<Type, String> {
+    return match expression {
+        parser::Ast::Int(..) => Ok(int_type()),
+        parser::Ast::Float(..) => Ok(float_type()),
+        parser::Ast::Bool(..) => Ok(bool_type()),
+        parser::Ast::Str(..) => Ok(string_type()),
+        parser::Ast::ArrayValue(children) => match children.first() {
+            None => Err(String::from("codegen: empty array value")),
+            Some(child) => match expression_type(child, variables, context) {
+                Err(e) => Err(e),
+                Ok(element) => Ok(array_type(element, Some(children.len() as u64))),
+            },
+        },
+        parser::Ast::ArrayAccess { variable, .. } => match variables.get(variable) {
+            None => Err(format!("codegen: unknown variable '{}'", variable)),
+            Some(Type { element: Some(element), .. }) => Ok(*element.clone()),
+            Some(typeval) => Err(format!("codegen: cannot index variable '{}' of type {}", variable, typeval)),
+        },
+        parser::Ast::EqualTo {..}
+        | parser::Ast::NotEqualTo {..}
+        | parser::Ast::GreaterThan {..}
+        | parser::Ast::GreaterOrEqual {..}
+        | parser::Ast::LowerThan {..}
+        | parser::Ast::LowerOrEqual {..}
+        | parser::Ast::And {..}
+        | parser::Ast::Or {..}
+            => Ok(bool_type()),
+        parser::Ast::Substraction { left, right }
+        | parser::Ast::Addition { left, right }
+        | parser::Ast::Division { left, right }
+        | parser::Ast::Multiplication { left, right }
+        | parser::Ast::Modulo { left, right } => {
+            let left = match expression_type(left, variables, context) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            let right = match expression_type(right, variables, context) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            Ok(if is_float(&right) { right } else { left })
+        },
+        parser::Ast::UnaryPlus { child } | parser::Ast::UnaryMinus { child } => expression_type(child, variables, context),
+        parser::Ast::NewArray { typename, .. } => new_array_type(typename),
+        parser::Ast::Cast { child, typename } => match expression_type(child, variables, context) {
+            // arrays passed for an array of unknown size.
+            Ok(Type { element: Some(element), .. }) => Ok(array_type(*element, None)),
+            Ok(..) => cast_type(typename),
+            Err(e) => Err(e),
+        },
+        parser::Ast::MemberAccess { record, field } => match expression_type(record, variables, context) {
+            Err(e) => Err(e),
+            Ok(val) => match val.field(field) {
+                None => Err(format!("codegen: unknown field '{}' of {}", field, val)),
+                Some(val) => Ok(val.typeval.clone()),
+            },
+        },
+        parser::Ast::Variable(var) => match variables.get(&var.name) {
+            None => Err(format!("codegen: unknown variable '{}'", var.name)),
+            Some(typeval) => Ok(typeval.clone()),
+        },
+        parser::Ast::FunctionCall { name, .. } => match context.declarations.get(name) {
+            None => Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => match &dec.return_type {
+                None => Err(format!("codegen: function {} has no return value", dec)),
+                Some(val) => Ok(val.clone()),
+            },
+        },
+        parser::Ast::FunctionReference(name) => match context.declarations.get(name) {
+            None => Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => Ok(function_type(dec)),
+        },
+        val => Err(format!("codegen: unsupported expression {:?}", val)),
+    };
+}
+
+/// Generates the code of a single function from its three-address code.
+/// Temporaries live in stack slots after the local variables, so no register is live across
+/// an instruction and caller-saved registers never need to be preserved.
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    addresses: HashMap<String, u64>,
+    variables: HashMap<String, Type>,
+    /// type and offset of each temporary.
+    temporaries: Vec<(u64, Type)>,
+    output: String,
+    syntax: AsmSyntax,
+    /// index of the function in the module, keeps AT&T local labels unique.
+    function_id: usize,
+    source: Option<&'a Vec<String>>,
+    /// statement the instructions are generated for, `None` in the prologue and the epilogue.
+    span: Option<Span>,
+    /// offset in the output of each instruction and label generated for a statement.
+    offsets: Vec<(usize, Span)>,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    /// Emits an intel syntax instruction, rewritten for the output syntax.
+    fn emit(&mut self, instruction: &str) {
+        self.map_line();
+        self.output.push('\t');
+        match self.syntax {
+            AsmSyntax::Intel => self.output.push_str(instruction),
+            AsmSyntax::Att => self.output.push_str(syntax::to_att(instruction).as_str()),
+        };
+        self.output.push('\n');
+    }
+
+    fn label(&mut self, label: &str) {
+        self.map_line();
+        match self.syntax {
+            AsmSyntax::Intel => self.output.push_str(label),
+            AsmSyntax::Att => self.output.push_str(syntax::symbol(label).as_str()),
+        };
+        self.output.push_str(":\n");
+    }
+
+    /// Maps the line about to be written to the current statement.
+    fn map_line(&mut self) {
+        if let Some(span) = self.span {
+            self.offsets.push((self.output.len(), span));
+        }
+    }
+
+    /// Name of a label local to the function, nasm scopes `.name` to the previous label while gas needs unique names.
+    fn local_label(&self, name: &str) -> String {
+        return match self.syntax {
+            AsmSyntax::Intel => format!(".{}", name),
+            AsmSyntax::Att => format!(".L{}.{}", self.function_id, name),
+        };
+    }
+
+    /// Memory operand of the variable, `offset` bytes after its start, along with the type of the whole variable.
+    /// Locals are addressed from `rbp`, globals relative to `rip`.
+    fn variable(&self, name: &String, offset: u64) -> Result<(String, Type), String> {
+        if let (Some(address), Some(typeval)) = (self.addresses.get(name), self.variables.get(name)) {
+            return Ok((format!("[rbp - {}]", address - offset), typeval.clone()));
+        }
+        return match self.context.globals.iter().find(|g| &g.variable.name == name) {
+            Some(global) if offset == 0 => Ok((format!("[rel {}]", global.label()), global.variable.typeval.clone())),
+            Some(global) => Ok((format!("[rel {} + {}]", global.label(), offset), global.variable.typeval.clone())),
+            None => Err(format!("codegen: unknown variable '{}'", name)),
+        };
+    }
+
+    fn temporary(&self, id: usize) -> Result<(u64, Type), String> {
+        return match self.temporaries.get(id) {
+            Some(val) => Ok(val.clone()),
+            None => Err(format!("codegen: unknown temporary t{}", id)),
+        };
+    }
+
+    /// Loads the value at `address` in the given accumulator (`rax` or `rcx`), or `xmm0`/`xmm1` for floats.
+    /// The length of a pair goes to `rdx` with `rax`, only its pointer is loaded in `rcx`.
+    fn load(&mut self, address: &str, typeval: &Type, accumulator: usize) {
+        if is_float(typeval) {
+            self.emit(format!("movsd xmm{}, qword {}", accumulator, address).as_str());
+            return;
+        }
+        if is_pair(typeval) {
+            self.emit(format!("mov {}, qword {}", ACCUMULATORS[accumulator].0, address).as_str());
+            if accumulator == 0 {
+                self.emit(format!("movsxd rdx, dword {}", shift_address(address, 8)).as_str());
+            }
+            return;
+        }
+        let (register64, register32, _) = ACCUMULATORS[accumulator];
+        match typeval.size {
+            1 => self.emit(format!("movzx {}, byte {}", register32, address).as_str()),
+            4 => self.emit(format!("movsxd {}, dword {}", register64, address).as_str()),
+            _ => self.emit(format!("mov {}, qword {}", register64, address).as_str()),
+        };
+    }
+
+    /// Stores `rax` (or `xmm0` for floats, `rax` and `rdx` for pairs) at `address`.
+    fn store(&mut self, address: &str, typeval: &Type) {
+        if is_float(typeval) {
+            self.emit(format!("movsd qword {}, xmm0", address).as_str());
+            return;
+        }
+        if is_pair(typeval) {
+            self.emit(format!("mov qword {}, rax", address).as_str());
+            self.emit(format!("mov dword {}, edx", shift_address(address, 8)).as_str());
+            return;
+        }
+        match typeval.size {
+            1 => self.emit(format!("mov byte {}, al", address).as_str()),
+            4 => self.emit(format!("mov dword {}, eax", address).as_str()),
+            _ => self.emit(format!("mov qword {}, rax", address).as_str()),
+        };
+    }
+
+    /// Stores `rax` (or `xmm0` for floats) in the temporary.
+    fn store_temporary(&mut self, id: usize) -> Result<(), String> {
+        let (offset, typeval) = match self.temporary(id) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        self.store(format!("[rbp - {}]", offset).as_str(), &typeval);
+        return Ok(());
+    }
+
+    /// Loads the operand in the given accumulator (`rax` or `rcx`), or `xmm0`/`xmm1` for floats.
+    fn load_operand(&mut self, operand: &ir::Operand, accumulator: usize) -> Result<Type, String> {
+        let (register64, register32, _) = ACCUMULATORS[accumulator];
+        return Ok(match operand {
+            ir::Operand::Int(val) => {
+                self.emit(format!("mov {}, {}", register64, val).as_str());
+                int_type()
+            },
+            ir::Operand::Bool(val) => {
+                self.emit(format!("mov {}, {}", register32, *val as u8).as_str());
+                bool_type()
+            },
+            ir::Operand::Float(val) => {
+                self.emit(format!("mov {}, 0x{:x}", register64, val.to_bits()).as_str());
+                self.emit(format!("movq xmm{}, {}", accumulator, register64).as_str());
+                float_type()
+            },
+            ir::Operand::Str { label, length } => {
+                self.emit(format!("lea {}, [rel {}]", register64, label).as_str());
+                if accumulator == 0 {
+                    self.emit(format!("mov edx, {}", length).as_str());
+                }
+                string_type()
+            },
+            ir::Operand::Function(name) => match self.context.declarations.get(name) {
+                None => return Err(format!("codegen: undefined function {}", name)),
+                Some(dec) => {
+                    let typeval = function_type(dec);
+                    self.emit(format!("lea {}, [rel {}]", register64, name).as_str());
+                    typeval
+                },
+            },
+            ir::Operand::Variable(name) => {
+                let (address, typeval) = match self.variable(name, 0) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                match typeval.count {
+                    // fixed size arrays are evaluated to their address.
+                    Some(..) => self.emit(format!("lea {}, {}", register64, address).as_str()),
+                    None => self.load(address.as_str(), &typeval, accumulator),
+                };
+                typeval
+            },
+            ir::Operand::Temp(id) => {
+                let (offset, typeval) = match self.temporary(*id) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.load(format!("[rbp - {}]", offset).as_str(), &typeval, accumulator);
+                typeval
+            },
+        });
+    }
+
+    /// Computes `left op right` in `rax`/`xmm0`, comparisons only set the flags.
+    /// Returns the condition code (`e`, `l`, ...) true when a comparison holds.
+    fn visit_binary(&mut self, op: ir::BinaryOp, left: &ir::Operand, right: &ir::Operand) -> Result<&'static str, String> {
+        let left_type = match self.load_operand(left, 0) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let right_type = match self.load_operand(right, 1) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+
+        if left_type != right_type {
+            return Err(format!("codegen: implicit conversion between {} and {} is not supported", left_type, right_type));
+        }
+
+        let float = is_float(&left_type);
+        if op.is_comparison() {
+            self.emit(if float { "ucomisd xmm0, xmm1" } else { "cmp rax, rcx" });
+        } else if float {
+            match op {
+                ir::BinaryOp::Add => self.emit("addsd xmm0, xmm1"),
+                ir::BinaryOp::Sub => self.emit("subsd xmm0, xmm1"),
+                ir::BinaryOp::Mul => self.emit("mulsd xmm0, xmm1"),
+                ir::BinaryOp::Div => self.emit("divsd xmm0, xmm1"),
+                ir::BinaryOp::Mod => return Err(String::from("codegen: modulo is not defined on floats")),
+                _ => return Err(String::from("codegen: shifts are not defined on floats")),
+            };
+        } else {
+            // ints are 32 bits wide, so is the arithmetic setting the overflow flag.
+            match op {
+                ir::BinaryOp::Add => self.emit("add eax, ecx"),
+                ir::BinaryOp::Sub => self.emit("sub eax, ecx"),
+                ir::BinaryOp::Mul => self.emit("imul eax, ecx"),
+                ir::BinaryOp::Shl => self.emit("shl eax, cl"),
+                ir::BinaryOp::Sar => self.emit("sar eax, cl"),
+                ir::BinaryOp::Shr => self.emit("shr eax, cl"),
+                ir::BinaryOp::Div => {
+                    self.emit("cqo");
+                    self.emit("idiv rcx");
+                },
+                _ => {
+                    self.emit("cqo");
+                    self.emit("idiv rcx");
+                    self.emit("mov rax, rdx");
+                },
+            };
+        }
+
+        // ucomisd sets the flags like an unsigned comparison.
+        return Ok(match (op, float) {
+            (ir::BinaryOp::Eq, _) => "e",
+            (ir::BinaryOp::Ne, _) => "ne",
+            (ir::BinaryOp::Gt, false) => "g",
+            (ir::BinaryOp::Ge, false) => "ge",
+            (ir::BinaryOp::Lt, false) => "l",
+            (ir::BinaryOp::Le, false) => "le",
+            (ir::BinaryOp::Gt, true) => "a",
+            (ir::BinaryOp::Ge, true) => "ae",
+            (ir::BinaryOp::Lt, true) => "b",
+            _ => "be",
+        });
+    }
+
+    /// Loads the index of `array[index]` in `rcx`, returning the address of the element and its type.
+    /// The address uses `rcx` (and `rsi` for arrays passed by address, `rdx` holding string lengths).
+    fn element_address(&mut self, array: &String, index: &ir::Operand) -> Result<(String, Type), String> {
+        let (address, typeval) = match self.variable(array, 0) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let element = match &typeval.element {
+            Some(element) => *element.clone(),
+            None => return Err(format!("codegen: cannot index variable '{}' of type {}", array, typeval)),
+        };
+
+        if let Err(e) = self.load_operand(index, 1) {
+            return Err(e);
+        }
+
+        let scale = match element.size {
+            1 | 2 | 4 | 8 => element.size,
+            size => {
+                self.emit(format!("imul rcx, rcx, {}", size).as_str());
+                1
+            },
+        };
+
+        return Ok(match typeval.count {
+            // rip relative addresses cannot be indexed.
+            Some(..) if address.starts_with("[rel ") => {
+                self.emit(format!("lea rsi, {}", address).as_str());
+                (format!("[rsi + rcx*{}]", scale), element)
+            },
+            Some(..) => (format!("{} + rcx*{}]", address.trim_end_matches(']'), scale), element),
+            None => {
+                self.emit(format!("mov rsi, qword {}", address).as_str());
+                (format!("[rsi + rcx*{}]", scale), element)
+            },
+        });
+    }
+
+    /// Calls `name` following the System V AMD64 calling convention, the result is left in `rax`/`xmm0`.
+    fn visit_call(&mut self, name: &String, arguments: &[ir::Operand]) -> Result<(), String> {
+        let dec = match self.context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec.clone(),
+        };
+
+        let mut integer_arguments = Vec::<usize>::new();
+        let mut integer_count = 0;
+        let mut float_arguments = Vec::<usize>::new();
+        let mut stack_arguments = Vec::<usize>::new();
+        let mut stack_count = 0;
+        for (i, argument) in arguments.iter().enumerate() {
+            let typeval = match argument {
+                ir::Operand::Float(..) => float_type(),
+                ir::Operand::Str {..} => string_type(),
+                ir::Operand::Variable(name) => match self.variable(name, 0) {
+                    Err(e) => return Err(e),
+                    Ok((_, typeval)) => typeval,
+                },
+                ir::Operand::Temp(id) => match self.temporary(*id) {
+                    Err(e) => return Err(e),
+                    Ok((_, typeval)) => typeval,
+                },
+                _ => int_type(),
+            };
+            let float = is_float(&typeval);
+            let size = eightbytes(&typeval);
+            if float && float_arguments.len() < FLOAT_REGISTERS {
+                float_arguments.push(i);
+            } else if !float && integer_count + size <= INTEGER_REGISTERS.len() {
+                integer_arguments.push(i);
+                integer_count += size;
+            } else {
+                stack_arguments.push(i);
+                stack_count += size;
+            }
+        }
+
+        // an odd number of stack eightbytes is padded to keep `rsp` aligned at the call.
+        let padding = stack_count % 2;
+        if padding > 0 {
+            self.emit("sub rsp, 8");
+        }
+
+        // the last stack argument is pushed first, the length of a pair goes above its pointer.
+        for i in stack_arguments.iter().rev() {
+            match self.load_operand(&arguments[*i], 0) {
+                Err(e) => return Err(e),
+                Ok(typeval) if is_float(&typeval) => self.emit("movq rax, xmm0"),
+                Ok(typeval) if is_pair(&typeval) => self.emit("push rdx"),
+                _ => (),
+            };
+            self.emit("push rax");
+        }
+
+        // loading a pair overwrites `rdx`, the registers are filled once every argument is loaded.
+        for i in &integer_arguments {
+            match self.load_operand(&arguments[*i], 0) {
+                Err(e) => return Err(e),
+                Ok(typeval) if is_pair(&typeval) => {
+                    self.emit("push rax");
+                    self.emit("push rdx");
+                },
+                Ok(_) => self.emit("push rax"),
+            };
+        }
+        for register in INTEGER_REGISTERS[..integer_count].iter().rev() {
+            self.emit(format!("pop {}", register.0).as_str());
+        }
+        // loading a float goes through xmm0, so it is filled last.
+        for (register, i) in float_arguments.iter().enumerate().rev() {
+            if let Err(e) = self.load_operand(&arguments[*i], 0) {
+                return Err(e);
+            }
+            if register > 0 {
+                self.emit(format!("movsd xmm{}, xmm0", register).as_str());
+            }
+        }
+
+        // number of vector registers used, required by variadic functions.
+        self.emit(format!("mov eax, {}", float_arguments.len()).as_str());
+        self.emit(format!("call {}", name).as_str());
+        if stack_count > 0 {
+            self.emit(format!("add rsp, {}", 8 * (stack_count + padding)).as_str());
+        }
+
+        match &dec.return_type {
+            Some(typeval) if !is_float(typeval) && typeval.size == 4 => self.emit("movsxd rax, eax"),
+            Some(typeval) if typeval.size == 1 => self.emit("movzx eax, al"),
+            _ => (),
+        };
+
+        return Ok(());
+    }
+
+    /// Copies the parameters from their registers (or the caller's frame) to their local slots.
+    fn store_parameters(&mut self, func: &ir::Function) -> Result<(), String> {
+        let mut integer_count = 0;
+        let mut float_count = 0;
+        let mut stack_count = 0;
+        for param in &func.variables[..func.parameter_count] {
+            let address = match self.variable(&param.name, 0) {
+                Err(e) => return Err(e),
+                Ok((address, _)) => address,
+            };
+            if is_float(&param.typeval) && float_count < FLOAT_REGISTERS {
+                self.emit(format!("movsd qword {}, xmm{}", address, float_count).as_str());
+                float_count += 1;
+            } else if is_pair(&param.typeval) && integer_count + 2 <= INTEGER_REGISTERS.len() {
+                self.emit(format!("mov qword {}, {}", address, INTEGER_REGISTERS[integer_count].0).as_str());
+                self.emit(format!("mov dword {}, {}", shift_address(&address, 8), INTEGER_REGISTERS[integer_count + 1].1).as_str());
+                integer_count += 2;
+            } else if !is_float(&param.typeval) && !is_pair(&param.typeval) && integer_count < INTEGER_REGISTERS.len() {
+                let (register64, register32, register8) = INTEGER_REGISTERS[integer_count];
+                match param.typeval.size {
+                    1 => self.emit(format!("mov byte {}, {}", address, register8).as_str()),
+                    4 => self.emit(format!("mov dword {}, {}", address, register32).as_str()),
+                    _ => self.emit(format!("mov qword {}, {}", address, register64).as_str()),
+                };
+                integer_count += 1;
+            } else {
+                self.load(format!("[rbp + {}]", 16 + 8 * stack_count).as_str(), &param.typeval, 0);
+                self.store(address.as_str(), &param.typeval);
+                stack_count += eightbytes(&param.typeval);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Emits the instructions, a comparison directly followed by a conditional jump on its result is fused.
+    fn visit_instructions(&mut self, instructions: &[ir::Instr]) -> Result<(), String> {
+        let mut i = 0;
+        while i < instructions.len() {
+            if let (ir::Instr::Binary { dest, op, left, right }, Some(ir::Instr::JumpIfFalse { condition: ir::Operand::Temp(condition), label }))
+                = (&instructions[i], instructions.get(i + 1)) {
+                if op.is_comparison() && dest == condition {
+                    let code = match self.visit_binary(*op, left, right) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    };
+                    let label = self.local_label(label);
+                    self.emit(format!("j{} {}", inverse_condition(code), label).as_str());
+                    i += 2;
+                    continue;
+                }
+            }
+            if let Err(e) = self.visit_instruction(&instructions[i]) {
+                return Err(e);
+            }
+            i += 1;
+        }
+        return Ok(());
+    }
+
+    fn visit_instruction(&mut self, instruction: &ir::Instr) -> Result<(), String> {
+        match instruction {
+            ir::Instr::Assign { variable, value } => {
+                let (address, typeval) = match self.variable(variable, 0) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if let Err(e) = self.load_operand(value, 0) {
+                    return Err(e);
+                }
+                self.store(address.as_str(), &typeval);
+            },
+            ir::Instr::Copy { dest, value } => {
+                if let Err(e) = self.load_operand(value, 0) {
+                    return Err(e);
+                }
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::Binary { dest, op, left, right } => {
+                let code = match self.visit_binary(*op, left, right) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if op.is_comparison() {
+                    self.emit(format!("set{} al", code).as_str());
+                    self.emit("movzx eax, al");
+                }
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::Unary { dest, op: ir::UnaryOp::Neg, value } => {
+                match self.load_operand(value, 0) {
+                    Err(e) => return Err(e),
+                    Ok(typeval) if is_float(&typeval) => {
+                        self.emit("movq rax, xmm0");
+                        self.emit("btc rax, 63");
+                        self.emit("movq xmm0, rax");
+                    },
+                    Ok(_) => self.emit("neg eax"),
+                };
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::Unary { dest, op: ir::UnaryOp::IntToFloat, value } => {
+                if let Err(e) = self.load_operand(value, 0) {
+                    return Err(e);
+                }
+                self.emit("cvtsi2sd xmm0, rax");
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::Unary { dest, op: ir::UnaryOp::FloatToInt, value } => {
+                if let Err(e) = self.load_operand(value, 0) {
+                    return Err(e);
+                }
+                self.emit("cvttsd2si rax, xmm0");
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::Unary { dest, op: ir::UnaryOp::ToSlice, value } => {
+                match self.load_operand(value, 0) {
+                    Err(e) => return Err(e),
+                    Ok(Type { count: Some(count), .. }) => self.emit(format!("mov edx, {}", count).as_str()),
+                    // already a pair.
+                    Ok(..) => (),
+                };
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::LoadElement { dest, array, index } => {
+                let (address, element) = match self.element_address(array, index) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.load(address.as_str(), &element, 0);
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::StoreElement { array, index, value } => {
+                if let Err(e) = self.load_operand(value, 0) {
+                    return Err(e);
+                }
+                let (address, element) = match self.element_address(array, index) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.store(address.as_str(), &element);
+            },
+            ir::Instr::LoadField { dest, variable, offset } => {
+                let (address, typeval) = match (self.variable(variable, *offset), self.temporary(*dest)) {
+                    (Err(e), _) | (_, Err(e)) => return Err(e),
+                    (Ok((address, _)), Ok((_, typeval))) => (address, typeval),
+                };
+                match typeval.count {
+                    // fixed size arrays are evaluated to their address.
+                    Some(..) => self.emit(format!("lea rax, {}", address).as_str()),
+                    None => self.load(address.as_str(), &typeval, 0),
+                };
+                return self.store_temporary(*dest);
+            },
+            ir::Instr::StoreField { variable, offset, value } => {
+                let typeval = match self.load_operand(value, 0) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let address = match self.variable(variable, *offset) {
+                    Err(e) => return Err(e),
+                    Ok((address, _)) => address,
+                };
+                self.store(address.as_str(), &typeval);
+            },
+            ir::Instr::Call { dest, function, arguments } => {
+                if let Err(e) = self.visit_call(function, arguments) {
+                    return Err(e);
+                }
+                if let Some(dest) = dest {
+                    return self.store_temporary(*dest);
+                }
+            },
+            ir::Instr::Line(span) => {
+                self.span = Some(*span);
+                if let Some(text) = self.source.and_then(|source| source.get(span.line - 1)) {
+                    let comment = match self.syntax {
+                        AsmSyntax::Intel => ";",
+                        AsmSyntax::Att => "#",
+                    };
+                    self.output.push_str(format!("\t{} line {}: {}\n", comment, span.line, text.trim()).as_str());
+                }
+            },
+            ir::Instr::Label(label) => {
+                let label = self.local_label(label);
+                self.label(label.as_str());
+            },
+            ir::Instr::Jump(label) => {
+                let label = self.local_label(label);
+                self.emit(format!("jmp {}", label).as_str());
+            },
+            ir::Instr::JumpIfFalse { condition, label } => {
+                if let Err(e) = self.load_operand(condition, 0) {
+                    return Err(e);
+                }
+                let label = self.local_label(label);
+                self.emit("test al, al");
+                self.emit(format!("jz {}", label).as_str());
+            },
+            // storing the result of the operation leaves the flags untouched.
+            ir::Instr::JumpIfNoOverflow(label) => {
+                let label = self.local_label(label);
+                self.emit(format!("jno {}", label).as_str());
+            },
+            ir::Instr::Return(value) => {
+                if let Some(value) = value {
+                    if let Err(e) = self.load_operand(value, 0) {
+                        return Err(e);
+                    }
+                }
+                let label = self.local_label("return");
+                self.emit(format!("jmp {}", label).as_str());
+            },
+        };
+        return Ok(());
+    }
+}
+
+/// Bytes reserved below `rbp` for the variables and the temporaries of the function.
+pub(super) fn frame_size(func: &ir::Function) -> u64 {
+    let variables = func.variables.iter().map(|v| v.typeval.size).sum::<u64>();
+    let temporaries = func.temporaries.iter().map(|t| 8 * eightbytes(t) as u64).sum::<u64>();
+    // `rsp` is 16 bytes aligned once `rbp` is pushed, and stays so at call sites.
+    return align_to(variables + temporaries, 16);
+}
+
+/// Assembly of the function, along with the offsets of the lines generated for its statements.
+fn visit_function<'a>(func: &ir::Function, function_id: usize, context: &'a CompilerContext, syntax: AsmSyntax, source: Option<&'a Vec<String>>) -> Result<(String, Vec<(usize, Span)>), String> {
+    let stack_size = func.variables.iter().map(|v| v.typeval.size).sum::<u64>();
+    let frame_size = frame_size(func);
+
+    let addresses = match generate_variable_addresses(&func.variables, stack_size) {
+        Err(e) => return Err(e),
+        Ok(v) => v,
+    };
+
+    // each temporary gets an 8 bytes slot (16 for strings) below the variables.
+    let mut offset = stack_size;
+    let mut temporaries = Vec::<(u64, Type)>::new();
+    for typeval in &func.temporaries {
+        offset += 8 * eightbytes(typeval) as u64;
+        temporaries.push((offset, typeval.clone()));
+    }
+
+    let mut generator = FunctionGenerator {
+        context,
+        addresses,
+        variables: func.variables.iter().map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        temporaries,
+        output: String::new(),
+        syntax,
+        function_id,
+        source,
+        span: None,
+        offsets: Vec::new(),
+    };
+
+    generator.label(func.name.as_str());
+    generator.emit("push rbp");
+    generator.emit("mov rbp, rsp");
+    if frame_size > 0 {
+        generator.emit(format!("sub rsp, {}", frame_size).as_str());
+    }
+
+    if let Err(e) = generator.store_parameters(func) {
+        return Err(e);
+    }
+
+    if let Err(e) = generator.visit_instructions(&func.instructions) {
+        return Err(e);
+    }
+
+    generator.span = None;
+    let return_label = generator.local_label("return");
+    generator.label(return_label.as_str());
+    generator.emit("mov rsp, rbp");
+    generator.emit("pop rbp");
+    generator.emit("ret");
+    return Ok((generator.output, generator.offsets));
+}
+
+/// Null-terminated nasm data directive, printable characters are kept in quotes.
+fn string_data(value: &str) -> String {
+    let mut parts = Vec::<String>::new();
+    let mut quoted = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_graphic() && byte != b'"' || byte == b' ' {
+            quoted.push(byte as char);
+        } else {
+            if !quoted.is_empty() {
+                parts.push(format!("\"{}\"", quoted));
+                quoted.clear();
+            }
+            parts.push(byte.to_string());
+        }
+    }
+    if !quoted.is_empty() {
+        parts.push(format!("\"{}\"", quoted));
+    }
+    parts.push(String::from("0"));
+    return format!("db {}", parts.join(", "));
+}
+
+/// Content of the `.rodata` section, one label per string literal.
+fn visit_strings(strings: &StringTable, syntax: AsmSyntax) -> String {
+    let mut res = String::new();
+    for value in &strings.values {
+        if let Some(label) = strings.label(value) {
+            let data = match syntax {
+                AsmSyntax::Intel => string_data(value),
+                AsmSyntax::Att => syntax::asciz(value),
+            };
+            res.push_str(format!("{}:\n\t{}\n", label, data).as_str());
+        }
+    }
+    return res;
+}
+
+/// Globals of the `.data` section (those with an initial value) or of the `.bss` section, one label per global.
+fn visit_globals(context: &CompilerContext, syntax: AsmSyntax, initialized: bool) -> String {
+    let mut res = String::new();
+    for global in context.globals.iter().filter(|g| g.value.is_some() == initialized) {
+        let data = match (&global.value, syntax) {
+            (Some(parser::Ast::Int(val)), AsmSyntax::Intel) => format!("dd {}", val),
+            (Some(parser::Ast::Int(val)), AsmSyntax::Att) => format!(".long {}", val),
+            (Some(parser::Ast::Float(val)), AsmSyntax::Intel) => format!("dq 0x{:x}", val.to_bits()),
+            (Some(parser::Ast::Float(val)), AsmSyntax::Att) => format!(".quad 0x{:x}", val.to_bits()),
+            (Some(parser::Ast::Bool(val)), AsmSyntax::Intel) => format!("db {}", *val as u8),
+            (Some(parser::Ast::Bool(val)), AsmSyntax::Att) => format!(".byte {}", *val as u8),
+            (_, AsmSyntax::Intel) => format!("resb {}", global.variable.typeval.size),
+            (_, AsmSyntax::Att) => format!(".zero {}", global.variable.typeval.size),
+        };
+        let align = match syntax {
+            AsmSyntax::Intel => "align 8",
+            AsmSyntax::Att => ".balign 8",
+        };
+        res.push_str(format!("{}\n{}:\n\t{}\n", align, global.label(), data).as_str());
+    }
+    return res;
+}
+
+/// Complete module: symbol declarations, then the `.text`, `.data`, `.rodata` and `.bss` sections.
+/// Returned along with the offsets of the lines generated for the statements.
+/// Writes the module to `out`, each function once it is generated. Returns the byte offsets of the statements of the
+/// source in the code written.
+fn visit_module(context: &CompilerContext, syntax: AsmSyntax, source: Option<&Vec<String>>, out: &mut dyn Write) -> Result<Vec<(usize, Span)>, String> {
+    let (global, external, text, data, rodata, bss) = match syntax {
+        AsmSyntax::Intel => ("global", "extern", "section .text", "section .data", "section .rodata", "section .bss"),
+        AsmSyntax::Att => (".globl", ".extern", ".text", ".data", ".section .rodata", ".bss"),
+    };
+
+    let mut res = String::new();
+    res.push_str(format!("{} main\n", global).as_str());
+    for name in &context.exported_symbols {
+        res.push_str(format!("{} {}\n", global, name).as_str());
+    }
+    for name in &context.extern_symbols {
+        let name = match syntax {
+            AsmSyntax::Intel => name.clone(),
+            AsmSyntax::Att => syntax::symbol(name),
+        };
+        res.push_str(format!("{} {}\n", external, name).as_str());
+    }
+
+    let functions = match ir::lower_module(context) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    res.push_str(format!("\n{}\n", text).as_str());
+    // bytes written before the fragment being generated.
+    let mut written = 0;
+    let mut offsets = Vec::<(usize, Span)>::new();
+    for (i, f) in functions.iter().enumerate() {
+        match visit_function(f, i, context, syntax, source) {
+            Err(e) => return Err(e),
+            Ok((code, function_offsets)) => {
+                res.push('\n');
+                offsets.extend(function_offsets.into_iter().map(|(offset, span)| (written + res.len() + offset, span)));
+                res.push_str(code.as_str());
+            },
+        };
+        if let Err(e) = write_code(out, &res) {
+            return Err(e);
+        }
+        written += res.len();
+        res.clear();
+    }
+
+    res.push_str(format!("\n{}\n", data).as_str());
+    res.push_str(visit_globals(context, syntax, true).as_str());
+    res.push_str(format!("\n{}\n", rodata).as_str());
+    res.push_str(visit_strings(&context.strings, syntax).as_str());
+    res.push_str(format!("\n{}\n", bss).as_str());
+    res.push_str(visit_globals(context, syntax, false).as_str());
+    if syntax == AsmSyntax::Att {
+        // marks the stack as non executable for the gnu linker.
+        res.push_str("\n.section .note.GNU-stack,\"\",@progbits\n");
+    }
+    return match write_code(out, &res) {
+        Err(e) => Err(e),
+        Ok(()) => Ok(offsets),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::{compile, CompilerOptions};
+
+    /// Calls of `f` given `count` arguments of `typename`, directly and while an operand is pushed.
+    fn program(count: usize, typename: &str, literal: &str) -> String {
+        let parameters: Vec<String> = (0..count).map(|i| format!("p_{}: {}", (b'a' + i as u8) as char, typename)).collect();
+        let arguments: Vec<&str> = (0..count).map(|_| literal).collect();
+        return format!(
+            "function f({}): int\n    return 1\nend\n\nfunction g(): int\n    return 1 + f({})\nend\n\nx <- f({})\nprint(x + g())\n",
+            parameters.join(", "),
+            arguments.join(", "),
+            arguments.join(", "),
+        );
+    }
+
+    /// Follows `rsp` through the nasm code of each function, failing when a `call` is made with `rsp` not 16 bytes
+    /// aligned. The caller's `call` leaves `rsp` 8 bytes past an alignment at the entry of the function.
+    fn assert_aligned_calls(code: &str) {
+        let mut depth: i64 = 8;
+        let mut frame: i64 = 8;
+        let mut calls = 0;
+        for line in code.lines().map(str::trim) {
+            let operand = |prefix: &str| line.strip_prefix(prefix).map(|value| value.parse::<i64>().unwrap());
+            if line.ends_with(':') && !line.starts_with('.') {
+                depth = 8;
+            } else if line.starts_with("push ") {
+                depth += 8;
+            } else if line.starts_with("pop ") {
+                depth -= 8;
+            } else if let Some(size) = operand("sub rsp, ") {
+                depth += size;
+            } else if let Some(size) = operand("add rsp, ") {
+                depth -= size;
+            } else if line == "mov rbp, rsp" {
+                frame = depth;
+            } else if line == "mov rsp, rbp" {
+                depth = frame;
+            } else if line.starts_with("call ") {
+                assert_eq!(depth % 16, 0, "misaligned `{}` in\n{}", line, code);
+                calls += 1;
+            }
+        }
+        assert!(calls > 0, "no call in\n{}", code);
+    }
+
+    fn compile_program(source: &str) -> String {
+        let ast = match crate::parse_str(source) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        return match compile(&ast, &CompilerOptions::default()) {
+            Err(diagnostics) => panic!("{:?}", diagnostics),
+            Ok(output) => output.code,
+        };
+    }
+
+    #[test]
+    fn aligns_int_stack_arguments() {
+        for count in [7, 8, 9] {
+            assert_aligned_calls(&compile_program(&program(count, "int", "1")));
+        }
+    }
+
+    #[test]
+    fn aligns_float_stack_arguments() {
+        for count in [9, 10, 11] {
+            assert_aligned_calls(&compile_program(&program(count, "float", "1.5")));
+        }
+    }
+}
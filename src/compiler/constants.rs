@@ -0,0 +1,58 @@
+use super::super::parser;
+use super::{Scope, insert_casts, optimize};
+
+/// Value of the constant `name` visible from `scope`, `None` when it is not a constant or a variable shadows it.
+pub(super) fn get_constant(name: &String, scope: &Scope) -> Option<parser::Ast> {
+    for (scope, visible) in scope.ancestors() {
+        if visible && scope.variables.iter().any(|v| &v.name == name) {
+            return None;
+        }
+        if let Some((_, value)) = scope.constants.iter().find(|(n, _)| n == name) {
+            return Some(value.clone());
+        }
+    }
+    return None;
+}
+
+/// Replaces the constants of `expression` with their values.
+pub(super) fn substitute_constants(expression: parser::Ast, scope: &Scope) -> Result<parser::Ast, String> {
+    return match expression {
+        parser::Ast::Variable(var) => match get_constant(&var.name, scope) {
+            Some(value) => Ok(value),
+            None => Ok(parser::Ast::Variable(var)),
+        },
+        expression => expression.map_children(&mut |child| substitute_constants(child, scope)),
+    };
+}
+
+/// Evaluates an expression made of literals and constants, `N * 2` becoming `8` when `N` is `4`.
+pub(super) fn evaluate_constant(expression: &parser::Ast, scope: &Scope) -> Result<parser::Ast, String> {
+    let expression = match substitute_constants(expression.clone(), scope) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    let variables = expression.find_nodes(|n| matches!(n, parser::Ast::Variable(..) | parser::Ast::ArrayAccess { .. } | parser::Ast::FunctionCall { .. }));
+    if let Some(node) = variables.first() {
+        return Err(format!("{:?} is not a constant expression", node.node));
+    }
+
+    let expression = match insert_casts(expression, scope) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return match optimize::fold_constants(expression) {
+        Err(e) => Err(e),
+        Ok(value @ (parser::Ast::Int(..) | parser::Ast::Float(..) | parser::Ast::Bool(..) | parser::Ast::Str(..))) => Ok(value),
+        Ok(value) => Err(format!("cannot evaluate {:?} at compile time", value)),
+    };
+}
+
+/// Number of elements of an array whose size is the constant expression `expression`.
+pub(super) fn evaluate_array_size(expression: &parser::Ast, scope: &Scope) -> Result<u64, String> {
+    return match evaluate_constant(expression, scope) {
+        Err(e) => Err(e),
+        Ok(parser::Ast::Int(val)) if val >= 0 => Ok(val as u64),
+        Ok(value) => Err(format!("invalid array size {:?}, expected a positive int", value)),
+    };
+}
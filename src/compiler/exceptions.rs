@@ -0,0 +1,317 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+
+use super::super::lexer::Span;
+use super::super::parser;
+use super::runtime;
+
+/// Global set while an exception propagates, named after a keyword not to clash with the variables of the program.
+const THROWN: &str = "throw";
+
+/// Global holding the message of the exception propagating.
+const MESSAGE: &str = "catch";
+
+/// Result of a function left by an exception, never read by its caller.
+const RESULT: &str = "return";
+
+/// Where the statements following one which threw continue.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Handler {
+    /// the rest of the `try` block is skipped, up to its handler.
+    Try,
+    /// the function returns, the caller propagating the exception.
+    Function { returns_value: bool },
+    /// the program prints the message and exits with status 1.
+    Main,
+}
+
+fn variable(name: &str) -> parser::Ast {
+    return parser::Ast::Variable(parser::Variable { name: name.to_string(), typename: None });
+}
+
+fn assign(name: &str, value: parser::Ast, span: Span) -> parser::Ast {
+    return parser::Ast::Assignement { variable: Box::new(variable(name)), expression: Box::new(value), span };
+}
+
+fn declare(name: &str, typename: &str) -> parser::Ast {
+    let typename = parser::Type { name: typename.to_string(), is_array: false, size: None, size_expression: None };
+    return parser::Ast::Variable(parser::Variable { name: name.to_string(), typename: Some(typename) });
+}
+
+fn not_thrown() -> parser::Ast {
+    return parser::Ast::EqualTo { left: Box::new(variable(THROWN)), right: Box::new(parser::Ast::Bool(false)) };
+}
+
+/// Runs `children` unless an exception propagates.
+fn unless_thrown(children: Vec<parser::Ast>, span: Span) -> parser::Ast {
+    return parser::Ast::Condition { condition: Box::new(not_thrown()), valid_branch: children, invalid_branch: Vec::new(), span };
+}
+
+/// Leaves the function when an exception propagates.
+fn propagate(handler: Handler, span: Span) -> parser::Ast {
+    let statements = match handler {
+        Handler::Try => unreachable!("the rest of a try block is skipped"),
+        Handler::Function { returns_value: false } => vec![parser::Ast::ReturnStatement { value: None, span }],
+        Handler::Function { returns_value: true } => vec![parser::Ast::ReturnStatement { value: Some(Box::new(variable(RESULT))), span }],
+        Handler::Main => vec![
+            parser::Ast::FunctionCall { name: String::from("write"), children: vec![parser::Ast::Str(String::from("uncaught exception: "))], span },
+            parser::Ast::FunctionCall { name: String::from("print"), children: vec![variable(MESSAGE)], span },
+            parser::Ast::ReturnStatement { value: Some(Box::new(parser::Ast::Int(1))), span },
+        ],
+    };
+    return parser::Ast::Condition { condition: Box::new(variable(THROWN)), valid_branch: statements, invalid_branch: Vec::new(), span };
+}
+
+/// Functions of the program which may throw, and temporaries holding the results of their calls.
+struct Throwing {
+    /// functions declared by the program, calls to other names than the builtins being calls to lambdas, which may throw.
+    declared: HashSet<String>,
+    builtins: HashSet<String>,
+    throwing: HashSet<String>,
+    /// names used by the program, which the temporaries must not clash with.
+    names: HashSet<String>,
+    temporaries: HashSet<String>,
+}
+
+impl Throwing {
+    fn new(children: &[parser::Ast]) -> Self {
+        let functions = children
+            .iter()
+            .flat_map(|child| child.find_nodes(|n| matches!(n, parser::Ast::FunctionDeclaration { .. } | parser::Ast::FunctionHeader { .. })))
+            .map(|f| f.node)
+            .collect::<Vec<&parser::Ast>>();
+        let mut res = Throwing {
+            declared: functions.iter().filter_map(|f| match f {
+                parser::Ast::FunctionDeclaration { name, .. } | parser::Ast::FunctionHeader { name, .. } => Some(name.clone()),
+                _ => None,
+            }).collect(),
+            builtins: runtime::builtin_declarations().into_iter().map(|(dec, _)| dec.name).chain(["len", "free", "contains", "remove"].map(String::from)).collect(),
+            throwing: HashSet::new(),
+            names: children.iter().flat_map(|child| child.find_nodes(|_| true)).flat_map(|n| match n.node {
+                parser::Ast::Variable(var) => vec![var.name.clone()],
+                parser::Ast::ArrayAccess { variable, .. } | parser::Ast::Try { variable, .. } => vec![variable.clone()],
+                parser::Ast::FunctionCall { name, .. } => vec![name.clone()],
+                parser::Ast::FunctionDeclaration { name, parameters, .. } | parser::Ast::FunctionHeader { name, parameters, .. }
+                    => parameters.iter().map(|p| p.name.clone()).chain(std::iter::once(name.clone())).collect(),
+                _ => Vec::new(),
+            }).collect(),
+            temporaries: HashSet::new(),
+        };
+
+        // a function throws when its body does, calls included.
+        loop {
+            let count = res.throwing.len();
+            for f in &functions {
+                if let parser::Ast::FunctionDeclaration { name, children, .. } = f {
+                    if !res.throwing.contains(name) && res.block(children) {
+                        res.throwing.insert(name.clone());
+                    }
+                }
+            }
+            if res.throwing.len() == count {
+                return res;
+            }
+        }
+    }
+
+    fn call(&self, name: &str) -> bool {
+        return self.throwing.contains(name) || !(self.declared.contains(name) || self.builtins.contains(name));
+    }
+
+    fn expression(&self, expression: &parser::Ast) -> bool {
+        return !expression.find_nodes(|n| matches!(n, parser::Ast::FunctionCall { name, .. } if self.call(name))).is_empty();
+    }
+
+    /// Whether an exception can propagate out of the statement.
+    fn statement(&self, statement: &parser::Ast) -> bool {
+        return match statement {
+            parser::Ast::Throw { .. } => true,
+            parser::Ast::Try { handler, .. } => self.block(handler),
+            parser::Ast::FunctionDeclaration { .. } | parser::Ast::FunctionHeader { .. } | parser::Ast::RecordDeclaration { .. } | parser::Ast::ConstDeclaration { .. } => false,
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => self.expression(condition) || self.block(valid_branch) || self.block(invalid_branch),
+            parser::Ast::WhileLoop { condition, children, .. } => self.expression(condition) || self.block(children),
+            parser::Ast::Match { value, cases, default, .. }
+                => self.expression(value) || cases.iter().any(|(_, children)| self.block(children)) || default.as_ref().is_some_and(|children| self.block(children)),
+            expression => self.expression(expression),
+        };
+    }
+
+    fn block(&self, children: &[parser::Ast]) -> bool {
+        return children.iter().any(|child| self.statement(child));
+    }
+
+    /// Name of a new temporary, not used by the program.
+    fn temporary(&mut self) -> String {
+        loop {
+            let name = format!("try_{}", self.temporaries.len());
+            self.temporaries.insert(name.clone());
+            if !self.names.contains(&name) {
+                return name;
+            }
+        }
+    }
+
+    /// Moves the calls of `expression` which may throw to temporaries, assigned by `hoisted` before the expression is evaluated,
+    /// so that no value is computed from the result of a call which threw.
+    /// The right operands of `and` and `or` are kept, as they are not always evaluated.
+    fn hoist(&mut self, expression: parser::Ast, hoisted: &mut Vec<parser::Ast>) -> parser::Ast {
+        return match expression {
+            parser::Ast::FunctionCall { ref name, span, .. } if self.call(name) => {
+                let temporary = self.temporary();
+                hoisted.push(assign(&temporary, expression, span));
+                variable(&temporary)
+            },
+            parser::Ast::And { left, right } => parser::Ast::And { left: Box::new(self.hoist(*left, hoisted)), right },
+            parser::Ast::Or { left, right } => parser::Ast::Or { left: Box::new(self.hoist(*left, hoisted)), right },
+            lambda @ parser::Ast::Lambda { .. } => lambda,
+            expression => expression.map_children(&mut |child| Ok::<_, Infallible>(self.hoist(child, hoisted))).unwrap(),
+        };
+    }
+
+    /// Assignments of the temporaries the statement needs, and the statement reading them.
+    fn hoist_statement(&mut self, statement: &parser::Ast) -> (Vec<parser::Ast>, parser::Ast) {
+        let mut hoisted = Vec::<parser::Ast>::new();
+        let statement = match statement {
+            parser::Ast::Assignement { variable, expression, .. }
+                if matches!(&**variable, parser::Ast::Variable(var) if self.temporaries.contains(&var.name)) && matches!(&**expression, parser::Ast::FunctionCall { .. })
+                => statement.clone(),
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, span } => parser::Ast::Condition {
+                condition: Box::new(self.hoist(*condition.clone(), &mut hoisted)),
+                valid_branch: valid_branch.clone(),
+                invalid_branch: invalid_branch.clone(),
+                span: *span,
+            },
+            parser::Ast::Match { value, cases, default, span } => parser::Ast::Match {
+                value: Box::new(self.hoist(*value.clone(), &mut hoisted)),
+                cases: cases.clone(),
+                default: default.clone(),
+                span: *span,
+            },
+            // the condition is evaluated again at the end of the body.
+            parser::Ast::WhileLoop { condition, children, span } => {
+                let condition = self.hoist(*condition.clone(), &mut hoisted);
+                let children = children.iter().chain(hoisted.iter()).cloned().collect();
+                parser::Ast::WhileLoop { condition: Box::new(condition), children, span: *span }
+            },
+            parser::Ast::Try { .. } | parser::Ast::FunctionDeclaration { .. } | parser::Ast::FunctionHeader { .. }
+            | parser::Ast::RecordDeclaration { .. } | parser::Ast::ConstDeclaration { .. } => statement.clone(),
+            statement => statement.clone().map_children(&mut |child| Ok::<_, Infallible>(self.hoist(child, &mut hoisted))).unwrap(),
+        };
+        return (hoisted, statement);
+    }
+
+    /// Lowers the statements, checking whether an exception propagates after each one which may throw.
+    fn lower_block(&mut self, children: &[parser::Ast], handler: Handler) -> Vec<parser::Ast> {
+        let mut res = Vec::<parser::Ast>::new();
+        for (i, child) in children.iter().enumerate() {
+            let span = child.span().unwrap_or_default();
+            let (mut rest, statement) = self.hoist_statement(child);
+            if !rest.is_empty() {
+                rest.push(statement);
+                rest.extend(children[i + 1..].iter().cloned());
+                res.extend(self.lower_block(&rest, handler));
+                return res;
+            }
+            res.extend(self.lower_statement(child, handler));
+            if !self.statement(child) {
+                continue;
+            }
+            match handler {
+                Handler::Try => {
+                    if i + 1 < children.len() {
+                        res.push(unless_thrown(self.lower_block(&children[i + 1..], handler), span));
+                    }
+                    return res;
+                },
+                handler => res.push(propagate(handler, span)),
+            };
+        }
+        return res;
+    }
+
+    /// Lowers a branch entered after evaluating a condition, which may have thrown when `checked`,
+    /// the calls of the right operands of `and` and `or` not being moved out of it.
+    fn lower_branch(&mut self, children: &[parser::Ast], checked: bool, handler: Handler, span: Span) -> Vec<parser::Ast> {
+        let children = self.lower_block(children, handler);
+        if !checked || children.is_empty() {
+            return children;
+        }
+        return match handler {
+            Handler::Try => vec![unless_thrown(children, span)],
+            handler => std::iter::once(propagate(handler, span)).chain(children).collect(),
+        };
+    }
+
+    fn lower_statement(&mut self, statement: &parser::Ast, handler: Handler) -> Vec<parser::Ast> {
+        return match statement {
+            parser::Ast::Throw { value, span } => vec![assign(MESSAGE, *value.clone(), *span), assign(THROWN, parser::Ast::Bool(true), *span)],
+            parser::Ast::Try { children, variable: name, handler: catch, span } => {
+                let mut caught = vec![assign(name, variable(MESSAGE), *span), assign(THROWN, parser::Ast::Bool(false), *span)];
+                caught.extend(self.lower_block(catch, handler));
+                vec![
+                    unless_thrown(self.lower_block(children, Handler::Try), *span),
+                    parser::Ast::Condition { condition: Box::new(variable(THROWN)), valid_branch: caught, invalid_branch: Vec::new(), span: *span },
+                ]
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, span } => {
+                let checked = self.expression(condition);
+                vec![parser::Ast::Condition {
+                    condition: condition.clone(),
+                    valid_branch: self.lower_branch(valid_branch, checked, handler, *span),
+                    invalid_branch: self.lower_branch(invalid_branch, checked, handler, *span),
+                    span: *span,
+                }]
+            },
+            parser::Ast::Match { value, cases, default, span } => {
+                let checked = self.expression(value);
+                let cases = cases.iter().map(|(values, children)| (values.clone(), self.lower_branch(children, checked, handler, *span))).collect();
+                let default = default.as_ref().map(|children| self.lower_branch(children, checked, handler, *span));
+                vec![parser::Ast::Match { value: value.clone(), cases, default, span: *span }]
+            },
+            parser::Ast::WhileLoop { condition, children, span } => {
+                // in a `try` block, the loop stops once an exception propagates.
+                let checked = self.expression(condition);
+                let condition = match handler {
+                    Handler::Try if self.statement(statement) => parser::Ast::And { left: Box::new(not_thrown()), right: condition.clone() },
+                    _ => *condition.clone(),
+                };
+                vec![parser::Ast::WhileLoop { condition: Box::new(condition), children: self.lower_branch(children, checked, handler, *span), span: *span }]
+            },
+            parser::Ast::FunctionDeclaration { name, children, parameters, return_type, symbol, span } => {
+                let handler = match name == "main" && parameters.is_empty() {
+                    true => Handler::Main,
+                    false => Handler::Function { returns_value: return_type.is_some() },
+                };
+                let mut body = Vec::<parser::Ast>::new();
+                if let (Handler::Function { returns_value: true }, Some(typename)) = (handler, return_type) {
+                    if self.block(children) {
+                        body.push(declare(RESULT, typename));
+                    }
+                }
+                body.extend(self.lower_block(children, handler));
+                vec![parser::Ast::FunctionDeclaration {
+                    name: name.clone(),
+                    children: body,
+                    parameters: parameters.clone(),
+                    return_type: return_type.clone(),
+                    symbol: symbol.clone(),
+                    span: *span,
+                }]
+            },
+            statement => vec![statement.clone()],
+        };
+    }
+}
+
+/// Lowers `try`, `catch` and `throw` to a flag checked after the statements which may throw, and to the message in a global.
+/// An exception skips the rest of its `try` block, returns from the functions it propagates through,
+/// and ends the program with status 1 when it is not caught. Programs without `throw` are left as is.
+pub(super) fn lower_exceptions(children: &[parser::Ast]) -> Vec<parser::Ast> {
+    if children.iter().all(|child| child.find_nodes(|n| matches!(n, parser::Ast::Throw { .. })).is_empty()) {
+        return children.to_vec();
+    }
+    let mut throwing = Throwing::new(children);
+    let mut res = vec![declare(THROWN, "bool"), declare(MESSAGE, "str")];
+    res.extend(throwing.lower_block(children, Handler::Main));
+    return res;
+}
@@ -0,0 +1,223 @@
+//! Formatted prints, `print("x = {}, ratio = {:.2}", x, ratio)`, calls to `print` with several arguments whose first
+//! one is a string literal, each `{}` of it being replaced by the next value. ints and strings are printed by the
+//! runtime, bools as `true` or `false` and floats with 6 decimals, or the precision given by the placeholder.
+//! `{{` and `}}` print a brace. The values are evaluated as they are printed, and the line ends after the format.
+//! `write a, b` prints its values as the format `{} {}` does.
+
+use super::super::lexer::Span;
+use super::super::parser::{Ast, Variable};
+use super::{Type, bool_type, float_type, int_type, string_type};
+
+/// Decimals of the floats printed by a placeholder without precision.
+const DEFAULT_PRECISION: usize = 6;
+
+/// Largest precision of a placeholder, the decimals being computed as an int.
+const MAX_PRECISION: usize = 9;
+
+/// Digits of the integer part of a float printed at once, the largest number of them fitting in an int.
+const CHUNK_DIGITS: usize = 9;
+
+/// Variables of the compiler used to print the floats of a format, declared by the caller.
+pub(super) struct FloatTemporaries {
+    /// float being printed, without its sign.
+    pub(super) value: String,
+    /// power of `10^CHUNK_DIGITS` of the digits of the integer part being printed.
+    pub(super) scale: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Piece {
+    Text(String),
+    /// placeholder of a value, with the decimals of its float.
+    Value(Option<usize>),
+}
+
+/// Text and placeholders of the format, in order.
+pub(crate) fn parse(format: &str) -> Result<Vec<Piece>, String> {
+    let mut res = Vec::new();
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            },
+            '}' => return Err(String::from("unmatched '}' in the format, '}}' printing a brace")),
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        None => return Err(String::from("unclosed placeholder in the format")),
+                        Some('}') => break,
+                        Some(c) => spec.push(c),
+                    };
+                }
+                let precision = match spec.as_str() {
+                    "" => None,
+                    spec => match spec.strip_prefix(":.").and_then(|digits| digits.parse::<usize>().ok()) {
+                        Some(precision) if precision <= MAX_PRECISION => Some(precision),
+                        _ => return Err(format!("invalid placeholder '{}' in the format, expected no specification or a precision from :.0 to :.{}", spec, MAX_PRECISION)),
+                    },
+                };
+                if !text.is_empty() {
+                    res.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                res.push(Piece::Value(precision));
+            },
+            c => text.push(c),
+        };
+    }
+    if !text.is_empty() {
+        res.push(Piece::Text(text));
+    }
+    return Ok(res);
+}
+
+/// Checks that a value of type `typeval` can be printed by a placeholder with `precision`.
+pub(super) fn check_value(typeval: &Type, precision: Option<usize>) -> Result<(), String> {
+    if typeval != &int_type() && typeval != &float_type() && typeval != &bool_type() && typeval != &string_type() {
+        return Err(format!("cannot format a value of type {}", typeval.name));
+    }
+    if precision.is_some() && typeval != &float_type() {
+        return Err(format!("precision given for a value of type {}, only floats take one", typeval.name));
+    }
+    return Ok(());
+}
+
+fn call(name: &str, children: Vec<Ast>, span: Span) -> Ast {
+    return Ast::FunctionCall { name: String::from(name), children, span };
+}
+
+fn variable(name: &str) -> Box<Ast> {
+    return Box::new(Ast::Variable(Variable { name: String::from(name), typename: None }));
+}
+
+fn truncated(value: Ast) -> Ast {
+    return Ast::Cast { child: Box::new(value), typename: String::from("int") };
+}
+
+/// Statements printing the float with `precision` decimals: its sign, its integer part and its decimals, rounded and
+/// padded with zeros by printing them after a leading 1 which is cut. The integer part is printed by chunks of
+/// `CHUNK_DIGITS` digits, from the highest power of `10^CHUNK_DIGITS` below it, so that it never overflows an int.
+fn float_statements(value: Ast, precision: usize, temporaries: &FloatTemporaries, span: Span) -> Vec<Ast> {
+    let scale = 10_i64.pow(precision as u32);
+    let chunk = 10_i64.pow(CHUNK_DIGITS as u32);
+    let temporary = || variable(&temporaries.value);
+    let chunk_scale = || variable(&temporaries.scale);
+    let assign = |name: &str, expression: Ast| Ast::Assignement { variable: variable(name), expression: Box::new(expression), span };
+    // digits of the chunk, the value being left with the digits below it, never negative once rounded.
+    let chunk_value = || truncated(Ast::Division { left: temporary(), right: chunk_scale() });
+    let remove_chunk = || vec![
+        assign(&temporaries.value, Ast::Substraction {
+            left: temporary(),
+            right: Box::new(Ast::Multiplication {
+                left: Box::new(Ast::Cast { child: Box::new(chunk_value()), typename: String::from("float") }),
+                right: chunk_scale(),
+            }),
+        }),
+        Ast::Condition {
+            condition: Box::new(Ast::LowerThan { left: temporary(), right: Box::new(Ast::Float(0.0)) }),
+            valid_branch: vec![assign(&temporaries.value, Ast::Float(0.0))],
+            invalid_branch: Vec::new(),
+            span,
+        },
+    ];
+    let padded = |digits: Ast, scale: i64, count: usize| call(
+        "substring",
+        vec![call("to_string", vec![Ast::Addition { left: Box::new(digits), right: Box::new(Ast::Int(scale)) }], span), Ast::Int(1), Ast::Int(count as i64)],
+        span,
+    );
+
+    let mut res = vec![
+        assign(&temporaries.value, value),
+        Ast::Condition {
+            condition: Box::new(Ast::LowerThan { left: temporary(), right: Box::new(Ast::Float(0.0)) }),
+            valid_branch: vec![
+                call("write", vec![Ast::Str(String::from("-"))], span),
+                assign(&temporaries.value, Ast::Substraction { left: Box::new(Ast::Float(0.0)), right: temporary() }),
+            ],
+            invalid_branch: Vec::new(),
+            span,
+        },
+        assign(&temporaries.value, Ast::Addition { left: temporary(), right: Box::new(Ast::Float(0.5 / scale as f64)) }),
+        assign(&temporaries.scale, Ast::Float(1.0)),
+        Ast::WhileLoop {
+            condition: Box::new(Ast::GreaterOrEqual {
+                left: Box::new(Ast::Division { left: temporary(), right: chunk_scale() }),
+                right: Box::new(Ast::Float(chunk as f64)),
+            }),
+            children: vec![assign(&temporaries.scale, Ast::Multiplication { left: chunk_scale(), right: Box::new(Ast::Float(chunk as f64)) })],
+            span,
+        },
+        call("write", vec![chunk_value()], span),
+    ];
+    res.extend(remove_chunk());
+    let mut chunks = vec![
+        assign(&temporaries.scale, Ast::Division { left: chunk_scale(), right: Box::new(Ast::Float(chunk as f64)) }),
+        call("write", vec![padded(chunk_value(), chunk, CHUNK_DIGITS)], span),
+    ];
+    chunks.extend(remove_chunk());
+    res.push(Ast::WhileLoop {
+        condition: Box::new(Ast::GreaterThan { left: chunk_scale(), right: Box::new(Ast::Float(1.0)) }),
+        children: chunks,
+        span,
+    });
+    if precision > 0 {
+        let decimals = truncated(Ast::Multiplication { left: temporary(), right: Box::new(Ast::Float(scale as f64)) });
+        res.push(call("write", vec![Ast::Str(String::from("."))], span));
+        res.push(call("write", vec![padded(decimals, scale, precision)], span));
+    }
+    return res;
+}
+
+/// Pieces of the format of `write` with `count` values, which are separated by a space.
+pub(super) fn write_pieces(count: usize) -> Vec<Piece> {
+    let mut res = Vec::new();
+    for i in 0..count {
+        if i > 0 {
+            res.push(Piece::Text(String::from(" ")));
+        }
+        res.push(Piece::Value(None));
+    }
+    return res;
+}
+
+/// Statements printing the `pieces` of a checked format with the `values` of its placeholders, of type `types`, and
+/// ending the line. The floats are printed through the `temporaries`, which the caller declares when there are some.
+pub(super) fn lower(pieces: Vec<Piece>, values: Vec<Ast>, types: &[Type], temporaries: &FloatTemporaries, span: Span) -> Vec<Ast> {
+    let mut res = Vec::new();
+    let mut values = values.into_iter().zip(types);
+    let mut text = String::new();
+    for piece in pieces {
+        let (value, typeval, precision) = match piece {
+            Piece::Text(val) => {
+                text = val;
+                continue;
+            },
+            Piece::Value(precision) => match values.next() {
+                None => break,
+                Some((value, typeval)) => (value, typeval, precision),
+            },
+        };
+        if !text.is_empty() {
+            res.push(call("write", vec![Ast::Str(std::mem::take(&mut text))], span));
+        }
+        match typeval {
+            typeval if typeval == &bool_type() => res.push(Ast::Condition {
+                condition: Box::new(value),
+                valid_branch: vec![call("write", vec![Ast::Str(String::from("true"))], span)],
+                invalid_branch: vec![call("write", vec![Ast::Str(String::from("false"))], span)],
+                span,
+            }),
+            typeval if typeval == &float_type() => res.extend(float_statements(value, precision.unwrap_or(DEFAULT_PRECISION), temporaries, span)),
+            _ => res.push(call("write", vec![value], span)),
+        };
+    }
+    res.push(call("print", vec![Ast::Str(text)], span));
+    return res;
+}
@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use super::super::Variable;
+use super::{Function, Instr, Operand};
+
+/// Largest number of instructions of an inlined function.
+const INLINE_LIMIT: usize = 16;
+
+/// Whether calls to `func` can be replaced by its body.
+/// Only small functions calling nothing are inlined, so inlining never recurses.
+fn is_inlinable(func: &Function) -> bool {
+    return func.instructions.iter().filter(|i| !matches!(i, Instr::Line(..))).count() <= INLINE_LIMIT
+        && !func.instructions.iter().any(|i| matches!(i, Instr::Call {..}));
+}
+
+/// Replaces the calls to small leaf functions by their body.
+/// The inlined functions are still generated, they may be referenced by address.
+pub fn inline_functions(functions: &mut [Function]) {
+    let candidates = functions
+        .iter()
+        .filter(|f| is_inlinable(f))
+        .map(|f| (f.name.clone(), f.clone()))
+        .collect::<HashMap<String, Function>>();
+
+    for caller in functions.iter_mut() {
+        let instructions = std::mem::take(&mut caller.instructions);
+        let mut count = 0;
+        for instruction in instructions {
+            if let Instr::Call { dest, function, arguments } = &instruction {
+                if let Some(callee) = candidates.get(function) {
+                    inline_call(caller, callee, *dest, arguments, count);
+                    count += 1;
+                    continue;
+                }
+            }
+            caller.instructions.push(instruction);
+        }
+    }
+}
+
+/// Appends the body of `callee` to `caller`, its variables and temporaries are renamed into the caller's.
+/// Variables of a callee are shared by all its inlined calls, as they never overlap.
+fn inline_call(caller: &mut Function, callee: &Function, dest: Option<usize>, arguments: &[Operand], id: usize) {
+    let variable = |name: &String| format!("{}.{}", callee.name, name);
+    // globals are shared with the caller.
+    let local = |name: &String| match callee.variables.iter().any(|v| &v.name == name) {
+        true => variable(name),
+        false => name.clone(),
+    };
+    // `return` is a keyword, it cannot clash with a variable of the callee.
+    let result = variable(&String::from("return"));
+    let label = |name: &String| format!("inline{}.{}", id, name);
+    let end_label = label(&String::from("end"));
+    let offset = caller.temporaries.len();
+
+    let mut variables = callee.variables.iter().map(|v| (variable(&v.name), v.typeval.clone())).collect::<Vec<_>>();
+    if let (Some(typeval), Some(..)) = (&callee.return_type, dest) {
+        variables.push((result.clone(), typeval.clone()));
+    }
+    for (name, typeval) in variables {
+        if !caller.variables.iter().any(|v| v.name == name) {
+            caller.variables.push(Variable { name, typeval });
+        }
+    }
+    caller.temporaries.extend(callee.temporaries.iter().cloned());
+
+    let operand = |operand: &Operand| match operand {
+        Operand::Temp(t) => Operand::Temp(t + offset),
+        Operand::Variable(name) => Operand::Variable(local(name)),
+        val => val.clone(),
+    };
+
+    for (param, argument) in callee.variables[..callee.parameter_count].iter().zip(arguments) {
+        caller.instructions.push(Instr::Assign { variable: variable(&param.name), value: argument.clone() });
+    }
+
+    for (i, instruction) in callee.instructions.iter().enumerate() {
+        let instruction = match instruction {
+            Instr::Assign { variable: name, value } => Instr::Assign { variable: local(name), value: operand(value) },
+            Instr::Copy { dest, value } => Instr::Copy { dest: dest + offset, value: operand(value) },
+            Instr::Binary { dest, op, left, right } => Instr::Binary { dest: dest + offset, op: *op, left: operand(left), right: operand(right) },
+            Instr::Unary { dest, op, value } => Instr::Unary { dest: dest + offset, op: *op, value: operand(value) },
+            Instr::LoadElement { dest, array, index } => Instr::LoadElement { dest: dest + offset, array: local(array), index: operand(index) },
+            Instr::StoreElement { array, index, value } => Instr::StoreElement { array: local(array), index: operand(index), value: operand(value) },
+            Instr::LoadField { dest, variable: name, offset: field } => Instr::LoadField { dest: dest + offset, variable: local(name), offset: *field },
+            Instr::StoreField { variable: name, offset: field, value } => Instr::StoreField { variable: local(name), offset: *field, value: operand(value) },
+            Instr::Call { dest, function, arguments } => Instr::Call {
+                dest: dest.map(|d| d + offset),
+                function: function.clone(),
+                arguments: arguments.iter().map(operand).collect(),
+            },
+            Instr::Line(line) => Instr::Line(*line),
+            Instr::Label(name) => Instr::Label(label(name)),
+            Instr::Jump(name) => Instr::Jump(label(name)),
+            Instr::JumpIfFalse { condition, label: name } => Instr::JumpIfFalse { condition: operand(condition), label: label(name) },
+            Instr::JumpIfNoOverflow(name) => Instr::JumpIfNoOverflow(label(name)),
+            Instr::Return(value) => {
+                if let (Some(value), Some(..)) = (value, dest) {
+                    caller.instructions.push(Instr::Assign { variable: result.clone(), value: operand(value) });
+                }
+                if i + 1 == callee.instructions.len() {
+                    continue;
+                }
+                Instr::Jump(end_label.clone())
+            },
+        };
+        caller.instructions.push(instruction);
+    }
+
+    caller.instructions.push(Instr::Label(end_label));
+    if let Some(dest) = dest {
+        caller.instructions.push(Instr::Copy { dest, value: Operand::Variable(result) });
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::super::parser;
+use super::codegen::{expression_type, is_float};
+use super::super::error::CompileError;
+use super::{codegen_error, runtime, Backend, CompilerContext, OptLevel, RuntimeChecks, Type};
+
+mod inline;
+mod propagate;
+mod reduce;
+mod types;
+
+pub use types::{BinaryOp, DecidedBranch, Function, Instr, Operand, UnaryOp};
+
+/// Prints the three-address code of the program.
+pub struct IrBackend;
+
+impl Backend for IrBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        let mut res = Vec::new();
+        if let Err(e) = self.write(context, &mut res) {
+            return Err(e.diagnostics.iter().map(|d| d.message.clone()).collect::<Vec<String>>().join("\n"));
+        }
+        return Ok(String::from_utf8_lossy(&res).into_owned());
+    }
+
+    /// Writes the functions one at a time.
+    fn write(&self, context: &CompilerContext, out: &mut dyn Write) -> Result<(), CompileError> {
+        let functions = match lower_module(context) {
+            Err(e) => return Err(codegen_error(e)),
+            Ok(val) => val,
+        };
+        let mut globals = String::new();
+        for global in &context.globals {
+            match &global.value {
+                Some(value) => globals.push_str(format!("global {}: {} = {:?}\n", global.variable.name, global.variable.typeval, value).as_str()),
+                None => globals.push_str(format!("global {}: {}\n", global.variable.name, global.variable.typeval).as_str()),
+            };
+        }
+        if !context.globals.is_empty() {
+            globals.push('\n');
+        }
+        let written = write!(out, "{}", globals).and_then(|_| functions.iter().try_for_each(|f| write!(out, "{}\n\n", f)));
+        return match written {
+            Err(e) => Err(codegen_error(format!("could not write the generated code: {}", e))),
+            Ok(()) => Ok(()),
+        };
+    }
+}
+
+/// Lowers every function of the program, `main` last, applying the IR optimizations of the context's level.
+pub(crate) fn lower_module(context: &CompilerContext) -> Result<Vec<Function>, String> {
+    let mut res = Vec::new();
+    for f in context.functions.iter().chain(std::iter::once(&context.main_function)) {
+        match lower_function(f, context) {
+            Err(e) => return Err(e),
+            Ok(val) => res.push(val),
+        };
+    }
+    if context.options.opt_level >= OptLevel::O2 {
+        inline::inline_functions(&mut res);
+    }
+    if context.options.opt_level >= OptLevel::O1 {
+        for f in res.iter_mut() {
+            propagate::propagate_constants(f, context.options.runtime_checks.overflow);
+        }
+    }
+    // the constants found by the propagation are reduced as well.
+    if context.options.opt_level >= OptLevel::O2 {
+        for f in res.iter_mut() {
+            reduce::reduce_strength(f);
+        }
+    }
+    return Ok(res);
+}
+
+struct Lowering<'a> {
+    context: &'a CompilerContext,
+    variables: HashMap<String, Type>,
+    temporaries: Vec<Type>,
+    instructions: Vec<Instr>,
+    label_count: usize,
+    /// line of the statement being lowered, reported by the runtime checks.
+    line: usize,
+}
+
+impl<'a> Lowering<'a> {
+
+    fn new_temp(&mut self, typeval: Type) -> usize {
+        self.temporaries.push(typeval);
+        return self.temporaries.len() - 1;
+    }
+
+    fn new_label_id(&mut self) -> usize {
+        self.label_count += 1;
+        return self.label_count - 1;
+    }
+
+    fn expression_type(&self, expression: &parser::Ast) -> Result<Type, String> {
+        return expression_type(expression, &self.variables, self.context);
+    }
+
+    /// Variable holding the field accessed by `expression`, along with the offset and type of the field.
+    fn member(&self, expression: &parser::Ast) -> Result<(String, u64, Type), String> {
+        return match expression {
+            parser::Ast::Variable(var) => match self.variables.get(&var.name) {
+                None => Err(format!("codegen: unknown variable '{}'", var.name)),
+                Some(typeval) => Ok((var.name.clone(), 0, typeval.clone())),
+            },
+            parser::Ast::MemberAccess { record, field } => {
+                let (variable, offset, typeval) = match self.member(record) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                match typeval.field(field) {
+                    None => Err(format!("codegen: unknown field '{}' of {}", field, typeval)),
+                    Some(val) => Ok((variable, offset + val.offset, val.typeval.clone())),
+                }
+            },
+            val => Err(format!("codegen: records can only be copied between variables and fields, got {:?}", val)),
+        };
+    }
+
+    /// Copies a record field by field, nested records and arrays being copied element by element.
+    fn copy_record(&mut self, target: &parser::Ast, source: &parser::Ast) -> Result<(), String> {
+        let (target, target_offset, typeval) = match self.member(target) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let (source, source_offset, _) = match self.member(source) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        for (offset, scalar) in scalars(&typeval, 0) {
+            let dest = self.new_temp(scalar);
+            self.instructions.push(Instr::LoadField { dest, variable: source.clone(), offset: source_offset + offset });
+            self.instructions.push(Instr::StoreField { variable: target.clone(), offset: target_offset + offset, value: Operand::Temp(dest) });
+        }
+        return Ok(());
+    }
+
+    /// Lowers the expression, returning the operand holding its value.
+    fn visit_expression(&mut self, expression: &parser::Ast) -> Result<Operand, String> {
+        let typeval = match self.expression_type(expression) {
+            Err(e) => return Err(e),
+            Ok(val) if val.is_record() => return Err(format!("codegen: record {} cannot be used as a value", val)),
+            Ok(val) => val,
+        };
+
+        return Ok(match expression {
+            parser::Ast::Int(val) => Operand::Int(*val),
+            parser::Ast::Float(val) => Operand::Float(*val),
+            parser::Ast::Bool(val) => Operand::Bool(*val),
+            parser::Ast::Str(val) => match self.context.strings.label(val) {
+                None => return Err(format!("codegen: missing label for string {:?}", val)),
+                Some(label) => Operand::Str { label, length: val.len() },
+            },
+            parser::Ast::Variable(var) => Operand::Variable(var.name.clone()),
+            parser::Ast::FunctionReference(name) => Operand::Function(name.clone()),
+            parser::Ast::ArrayAccess { variable, index } => {
+                let index = match self.visit_expression(index) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::LoadElement { dest, array: variable.clone(), index });
+                Operand::Temp(dest)
+            },
+            parser::Ast::MemberAccess { .. } => {
+                let (variable, offset, _) = match self.member(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::LoadField { dest, variable, offset });
+                Operand::Temp(dest)
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                let arguments = match self.visit_arguments(children) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::Call { dest: Some(dest), function: name.clone(), arguments });
+                Operand::Temp(dest)
+            },
+            // the runtime allocates the elements, the array being returned with its length.
+            parser::Ast::NewArray { size, .. } => {
+                let length = match self.visit_expression(size) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let element_size = typeval.element.as_ref().map_or(0, |element| element.size);
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::Call {
+                    dest: Some(dest),
+                    function: String::from(runtime::ALLOC_ROUTINE),
+                    arguments: vec![length, Operand::Int(element_size as i64)],
+                });
+                Operand::Temp(dest)
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_expression(child),
+            parser::Ast::UnaryMinus { child } => {
+                let value = match self.visit_expression(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let checked = !is_float(&typeval) && self.context.options.runtime_checks.overflow;
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::Unary { dest, op: UnaryOp::Neg, value });
+                if checked {
+                    if let Err(e) = self.check_overflow() {
+                        return Err(e);
+                    }
+                }
+                Operand::Temp(dest)
+            },
+            parser::Ast::Cast { child, .. } => {
+                let value = match self.visit_expression(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let op = match &typeval {
+                    typeval if typeval.is_slice() => UnaryOp::ToSlice,
+                    typeval if is_float(typeval) => UnaryOp::IntToFloat,
+                    _ => UnaryOp::FloatToInt,
+                };
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::Unary { dest, op, value });
+                Operand::Temp(dest)
+            },
+            // the right operand is only evaluated when the left one does not decide the result.
+            parser::Ast::And { left, right } | parser::Ast::Or { left, right } => {
+                let id = self.new_label_id();
+                let right_label = format!("right{}", id);
+                let end_label = format!("endlogic{}", id);
+                let value = match self.visit_expression(left) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::Copy { dest, value: value.clone() });
+                match expression {
+                    parser::Ast::And {..} => self.instructions.push(Instr::JumpIfFalse { condition: value, label: end_label.clone() }),
+                    _ => {
+                        self.instructions.push(Instr::JumpIfFalse { condition: value, label: right_label.clone() });
+                        self.instructions.push(Instr::Jump(end_label.clone()));
+                        self.instructions.push(Instr::Label(right_label));
+                    },
+                };
+                let value = match self.visit_expression(right) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.instructions.push(Instr::Copy { dest, value });
+                self.instructions.push(Instr::Label(end_label));
+                Operand::Temp(dest)
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right } => {
+                let op = match expression {
+                    parser::Ast::Addition {..} => BinaryOp::Add,
+                    parser::Ast::Substraction {..} => BinaryOp::Sub,
+                    parser::Ast::Multiplication {..} => BinaryOp::Mul,
+                    parser::Ast::Division {..} => BinaryOp::Div,
+                    parser::Ast::Modulo {..} => BinaryOp::Mod,
+                    parser::Ast::EqualTo {..} => BinaryOp::Eq,
+                    parser::Ast::NotEqualTo {..} => BinaryOp::Ne,
+                    parser::Ast::GreaterThan {..} => BinaryOp::Gt,
+                    parser::Ast::GreaterOrEqual {..} => BinaryOp::Ge,
+                    parser::Ast::LowerThan {..} => BinaryOp::Lt,
+                    _ => BinaryOp::Le,
+                };
+                let left_type = match self.expression_type(left) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right_type = match self.expression_type(right) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if left_type != right_type {
+                    return Err(format!("codegen: implicit conversion between {} and {} is not supported", left_type, right_type));
+                }
+                let left = match self.visit_expression(left) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right = match self.visit_expression(right) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let checks = match is_float(&left_type) {
+                    true => RuntimeChecks::default(),
+                    false => self.context.options.runtime_checks,
+                };
+                if matches!(op, BinaryOp::Div | BinaryOp::Mod) && checks.division {
+                    if let Err(e) = self.check_divisor(&right) {
+                        return Err(e);
+                    }
+                }
+                if op == BinaryOp::Div && checks.overflow {
+                    if let Err(e) = self.check_quotient(&left, &right) {
+                        return Err(e);
+                    }
+                }
+                let dest = self.new_temp(typeval);
+                self.instructions.push(Instr::Binary { dest, op, left, right });
+                if matches!(op, BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul) && checks.overflow {
+                    if let Err(e) = self.check_overflow() {
+                        return Err(e);
+                    }
+                }
+                Operand::Temp(dest)
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        });
+    }
+
+    /// Calls the runtime routine reporting `message` at the line being lowered.
+    fn report_error(&mut self, message: &str) -> Result<(), String> {
+        let message = String::from(message);
+        let label = match self.context.strings.label(&message) {
+            None => return Err(format!("codegen: missing label for string {:?}", message)),
+            Some(val) => val,
+        };
+        let arguments = vec![Operand::Str { label, length: message.len() }, Operand::Int(self.line as i64)];
+        self.instructions.push(Instr::Call { dest: None, function: String::from(runtime::ERROR_ROUTINE), arguments });
+        return Ok(());
+    }
+
+    /// Reports a division by zero through the runtime when `divisor` is zero.
+    fn check_divisor(&mut self, divisor: &Operand) -> Result<(), String> {
+        if matches!(divisor, Operand::Int(val) if *val != 0) {
+            return Ok(());
+        }
+        let valid_label = format!("divisor{}", self.new_label_id());
+        let zero = self.new_temp(super::bool_type());
+        self.instructions.push(Instr::Binary { dest: zero, op: BinaryOp::Eq, left: divisor.clone(), right: Operand::Int(0) });
+        self.instructions.push(Instr::JumpIfFalse { condition: Operand::Temp(zero), label: valid_label.clone() });
+        if let Err(e) = self.report_error(runtime::DIVISION_BY_ZERO) {
+            return Err(e);
+        }
+        self.instructions.push(Instr::Label(valid_label));
+        return Ok(());
+    }
+
+    /// Reports an overflow through the runtime when dividing the smallest int by -1.
+    fn check_quotient(&mut self, dividend: &Operand, divisor: &Operand) -> Result<(), String> {
+        let smallest = i32::MIN as i64;
+        if matches!(divisor, Operand::Int(val) if *val != -1) || matches!(dividend, Operand::Int(val) if *val != smallest) {
+            return Ok(());
+        }
+        let valid_label = format!("quotient{}", self.new_label_id());
+        for (operand, value) in [(divisor, -1), (dividend, smallest)] {
+            let equal = self.new_temp(super::bool_type());
+            self.instructions.push(Instr::Binary { dest: equal, op: BinaryOp::Eq, left: operand.clone(), right: Operand::Int(value) });
+            self.instructions.push(Instr::JumpIfFalse { condition: Operand::Temp(equal), label: valid_label.clone() });
+        }
+        if let Err(e) = self.report_error(runtime::INTEGER_OVERFLOW) {
+            return Err(e);
+        }
+        self.instructions.push(Instr::Label(valid_label));
+        return Ok(());
+    }
+
+    /// Reports an overflow through the runtime unless the int operation just lowered fitted in 32 bits.
+    fn check_overflow(&mut self) -> Result<(), String> {
+        let valid_label = format!("nooverflow{}", self.new_label_id());
+        self.instructions.push(Instr::JumpIfNoOverflow(valid_label.clone()));
+        if let Err(e) = self.report_error(runtime::INTEGER_OVERFLOW) {
+            return Err(e);
+        }
+        self.instructions.push(Instr::Label(valid_label));
+        return Ok(());
+    }
+
+    fn visit_arguments(&mut self, children: &Vec<parser::Ast>) -> Result<Vec<Operand>, String> {
+        let mut arguments = Vec::new();
+        for child in children {
+            match self.visit_expression(child) {
+                Err(e) => return Err(e),
+                Ok(val) => arguments.push(val),
+            };
+        }
+        return Ok(arguments);
+    }
+
+    fn visit_condition(&mut self, condition: &parser::Ast) -> Result<Operand, String> {
+        return match self.expression_type(condition) {
+            Err(e) => Err(e),
+            Ok(typeval) if typeval != super::bool_type() => Err(format!("codegen: condition must be a bool, got {}", typeval)),
+            Ok(_) => self.visit_expression(condition),
+        };
+    }
+
+    fn visit_block(&mut self, statements: &Vec<parser::Ast>) -> Result<(), String> {
+        for statement in statements {
+            // statements added by the compiler, as the `return 0` ending `main`, have no line.
+            if let Some(span) = statement.span().filter(|span| span.line > 0) {
+                self.instructions.push(Instr::Line(span));
+                self.line = span.line;
+            }
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                match self.expression_type(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) if val.is_record() => return self.copy_record(variable, expression),
+                    Ok(_) => (),
+                };
+                let name = match &**variable {
+                    parser::Ast::Variable(var) => &var.name,
+                    parser::Ast::ArrayAccess { variable, index } => {
+                        let value = match self.visit_expression(expression) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        let index = match self.visit_expression(index) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        self.instructions.push(Instr::StoreElement { array: variable.clone(), index, value });
+                        return Ok(());
+                    },
+                    parser::Ast::MemberAccess { .. } => {
+                        let (name, offset, typeval) = match self.member(variable) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        let values = match (&**expression, &typeval.element) {
+                            (parser::Ast::ArrayValue(children), Some(element)) => children
+                                .iter()
+                                .enumerate()
+                                .map(|(i, child)| (offset + i as u64 * element.size, child))
+                                .collect::<Vec<_>>(),
+                            (expression, _) => vec![(offset, expression)],
+                        };
+                        for (offset, child) in values {
+                            let value = match self.visit_expression(child) {
+                                Err(e) => return Err(e),
+                                Ok(val) => val,
+                            };
+                            self.instructions.push(Instr::StoreField { variable: name.clone(), offset, value });
+                        }
+                        return Ok(());
+                    },
+                    _ => return Err(String::from("codegen: can only assign value to a variable.")),
+                };
+                if let parser::Ast::ArrayValue(children) = &**expression {
+                    for (i, child) in children.iter().enumerate() {
+                        let value = match self.visit_expression(child) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        self.instructions.push(Instr::StoreElement { array: name.clone(), index: Operand::Int(i as i64), value });
+                    }
+                    return Ok(());
+                }
+                let value = match self.visit_expression(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.instructions.push(Instr::Assign { variable: name.clone(), value });
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                let arguments = match self.visit_arguments(children) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.instructions.push(Instr::Call { dest: None, function: name.clone(), arguments });
+            },
+            parser::Ast::ReturnStatement { value, .. } => {
+                let value = match value {
+                    None => None,
+                    Some(value) => match self.visit_expression(value) {
+                        Err(e) => return Err(e),
+                        Ok(val) => Some(val),
+                    },
+                };
+                self.instructions.push(Instr::Return(value));
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                let id = self.new_label_id();
+                let else_label = format!("else{}", id);
+                let end_label = format!("endif{}", id);
+                let condition = match self.visit_condition(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.instructions.push(Instr::JumpIfFalse { condition, label: else_label.clone() });
+                if let Err(e) = self.visit_block(valid_branch) {
+                    return Err(e);
+                }
+                if !invalid_branch.is_empty() {
+                    self.instructions.push(Instr::Jump(end_label.clone()));
+                }
+                self.instructions.push(Instr::Label(else_label));
+                if !invalid_branch.is_empty() {
+                    if let Err(e) = self.visit_block(invalid_branch) {
+                        return Err(e);
+                    }
+                    self.instructions.push(Instr::Label(end_label));
+                }
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                let id = self.new_label_id();
+                let loop_label = format!("while{}", id);
+                let end_label = format!("endwhile{}", id);
+                self.instructions.push(Instr::Label(loop_label.clone()));
+                let condition = match self.visit_condition(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.instructions.push(Instr::JumpIfFalse { condition, label: end_label.clone() });
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+                self.instructions.push(Instr::Jump(loop_label));
+                self.instructions.push(Instr::Label(end_label));
+            },
+            expression => {
+                if let Err(e) = self.visit_expression(expression) {
+                    return Err(e);
+                }
+            },
+        };
+        return Ok(());
+    }
+}
+
+/// Offsets and types of the scalar values making up a value of type `typeval` located at `offset`.
+fn scalars(typeval: &Type, offset: u64) -> Vec<(u64, Type)> {
+    if typeval.is_record() {
+        return typeval.fields.iter().flat_map(|f| scalars(&f.typeval, offset + f.offset)).collect();
+    }
+    if let (Some(element), Some(count)) = (&typeval.element, typeval.count) {
+        return (0..count).flat_map(|i| scalars(element, offset + i * element.size)).collect();
+    }
+    return vec![(offset, typeval.clone())];
+}
+
+/// Lowers a flattened function to three-address code.
+pub(crate) fn lower_function(func: &super::Function, context: &CompilerContext) -> Result<Function, String> {
+    let mut lowering = Lowering {
+        context,
+        // locals shadow the globals.
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        temporaries: Vec::new(),
+        instructions: Vec::new(),
+        label_count: 0,
+        line: 0,
+    };
+
+    if let Err(e) = lowering.visit_block(&func.statements) {
+        return Err(e);
+    }
+
+    return Ok(Function {
+        name: func.name.clone(),
+        variables: func.variables.clone(),
+        parameter_count: func.parameter_count,
+        return_type: match context.declarations.get(&func.name) {
+            Some(dec) => dec.return_type.clone(),
+            None => None,
+        },
+        temporaries: lowering.temporaries,
+        instructions: lowering.instructions,
+        decided_branches: Vec::new(),
+    });
+}
@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet};
+
+use super::{BinaryOp, DecidedBranch, Function, Instr, Operand, UnaryOp};
+
+/// Constant values of the temporaries and variables at the instruction being propagated.
+#[derive(Default)]
+struct Constants {
+    temporaries: HashMap<usize, Operand>,
+    variables: HashMap<String, Operand>,
+}
+
+impl Constants {
+    /// Value of `operand`, the operand itself when it is not known.
+    fn value(&self, operand: &Operand) -> Operand {
+        let value = match operand {
+            Operand::Temp(id) => self.temporaries.get(id),
+            Operand::Variable(name) => self.variables.get(name),
+            _ => None,
+        };
+        return match value {
+            Some(val) => val.clone(),
+            None => operand.clone(),
+        };
+    }
+
+    fn set_temporary(&mut self, dest: usize, value: &Operand) {
+        match is_constant(value) {
+            true => self.temporaries.insert(dest, value.clone()),
+            false => self.temporaries.remove(&dest),
+        };
+    }
+
+    fn set_variable(&mut self, name: &str, value: &Operand) {
+        match is_constant(value) {
+            true => self.variables.insert(String::from(name), value.clone()),
+            false => self.variables.remove(name),
+        };
+    }
+}
+
+fn is_constant(operand: &Operand) -> bool {
+    return matches!(operand, Operand::Int(..) | Operand::Float(..) | Operand::Bool(..));
+}
+
+/// Integers are 32 bits wide once stored, folded values wrap the same way.
+fn int(value: i64) -> Operand {
+    return Operand::Int(value as i32 as i64);
+}
+
+/// Floats are only folded when the result can be written as a literal.
+fn float(value: f64) -> Option<Operand> {
+    if value.is_finite() {
+        return Some(Operand::Float(value));
+    }
+    return None;
+}
+
+/// Value of `left op right`, `None` when it is left to the runtime, as the AST folding does.
+fn fold_binary(op: BinaryOp, left: &Operand, right: &Operand, checked: bool) -> Option<Operand> {
+    return match (op, left, right) {
+        (BinaryOp::Add, Operand::Int(a), Operand::Int(b)) if checked => (*a as i32).checked_add(*b as i32).map(|v| int(v as i64)),
+        (BinaryOp::Sub, Operand::Int(a), Operand::Int(b)) if checked => (*a as i32).checked_sub(*b as i32).map(|v| int(v as i64)),
+        (BinaryOp::Mul, Operand::Int(a), Operand::Int(b)) if checked => (*a as i32).checked_mul(*b as i32).map(|v| int(v as i64)),
+        (BinaryOp::Add, Operand::Int(a), Operand::Int(b)) => Some(int(a.wrapping_add(*b))),
+        (BinaryOp::Sub, Operand::Int(a), Operand::Int(b)) => Some(int(a.wrapping_sub(*b))),
+        (BinaryOp::Mul, Operand::Int(a), Operand::Int(b)) => Some(int(a.wrapping_mul(*b))),
+        // division by zero and overflows trap at runtime.
+        (BinaryOp::Div, Operand::Int(a), Operand::Int(b)) => (*a as i32).checked_div(*b as i32).map(|v| int(v as i64)),
+        (BinaryOp::Mod, Operand::Int(a), Operand::Int(b)) => (*a as i32).checked_rem(*b as i32).map(|v| int(v as i64)),
+        (BinaryOp::Shl, Operand::Int(a), Operand::Int(b)) => Some(int((*a as i32).wrapping_shl(*b as u32) as i64)),
+        (BinaryOp::Sar, Operand::Int(a), Operand::Int(b)) => Some(int((*a as i32).wrapping_shr(*b as u32) as i64)),
+        (BinaryOp::Shr, Operand::Int(a), Operand::Int(b)) => Some(int((*a as i32 as u32).wrapping_shr(*b as u32) as i64)),
+        (BinaryOp::Add, Operand::Float(a), Operand::Float(b)) => float(a + b),
+        (BinaryOp::Sub, Operand::Float(a), Operand::Float(b)) => float(a - b),
+        (BinaryOp::Mul, Operand::Float(a), Operand::Float(b)) => float(a * b),
+        (BinaryOp::Div, Operand::Float(a), Operand::Float(b)) => float(a / b),
+        (BinaryOp::Eq, Operand::Bool(a), Operand::Bool(b)) => Some(Operand::Bool(a == b)),
+        (BinaryOp::Ne, Operand::Bool(a), Operand::Bool(b)) => Some(Operand::Bool(a != b)),
+        (op, Operand::Int(a), Operand::Int(b)) => compare(op, (*a as i32).partial_cmp(&(*b as i32))),
+        (op, Operand::Float(a), Operand::Float(b)) => compare(op, a.partial_cmp(b)),
+        _ => None,
+    };
+}
+
+/// Value of a comparison given the ordering of its operands, unordered floats (NaN) compare false.
+fn compare(op: BinaryOp, ordering: Option<std::cmp::Ordering>) -> Option<Operand> {
+    let value = match op {
+        BinaryOp::Eq => ordering.is_some_and(|o| o.is_eq()),
+        BinaryOp::Ne => ordering.is_none_or(|o| o.is_ne()),
+        BinaryOp::Gt => ordering.is_some_and(|o| o.is_gt()),
+        BinaryOp::Ge => ordering.is_some_and(|o| o.is_ge()),
+        BinaryOp::Lt => ordering.is_some_and(|o| o.is_lt()),
+        BinaryOp::Le => ordering.is_some_and(|o| o.is_le()),
+        _ => return None,
+    };
+    return Some(Operand::Bool(value));
+}
+
+fn fold_unary(op: UnaryOp, value: &Operand, checked: bool) -> Option<Operand> {
+    return match (op, value) {
+        (UnaryOp::Neg, Operand::Int(val)) if checked => (*val as i32).checked_neg().map(|v| int(v as i64)),
+        (UnaryOp::Neg, Operand::Int(val)) => Some(int(val.wrapping_neg())),
+        (UnaryOp::Neg, Operand::Float(val)) => Some(Operand::Float(-val)),
+        (UnaryOp::IntToFloat, Operand::Int(val)) => Some(Operand::Float(*val as f64)),
+        // out of range conversions are left to the runtime.
+        (UnaryOp::FloatToInt, Operand::Float(val)) if val.trunc() >= i32::MIN as f64 && val.trunc() <= i32::MAX as f64
+            => Some(Operand::Int(*val as i64)),
+        _ => None,
+    };
+}
+
+/// Statement of the source a conditional jump to `label` was lowered from,
+/// `None` for the jumps added by the compiler and the ones of inlined functions.
+fn statement_of(label: &str) -> Option<&'static str> {
+    return match label.trim_end_matches(|c: char| c.is_ascii_digit()) {
+        "else" => Some("if"),
+        "endwhile" => Some("while"),
+        _ => None,
+    };
+}
+
+/// Replaces the temporaries and variables holding a known constant by their value, folds the operations
+/// on constants and removes the branches never taken, recording the conditions found constant.
+/// Values are only known within a basic block, and the globals are forgotten by calls, which may assign them.
+/// Int operations overflowing are left to the runtime when they are `checked`.
+pub fn propagate_constants(func: &mut Function, checked: bool) {
+    // removing a branch can merge basic blocks, and make more values known.
+    loop {
+        let instructions = func.instructions.clone();
+        propagate_once(func, checked);
+        if func.instructions == instructions {
+            return;
+        }
+    }
+}
+
+fn propagate_once(func: &mut Function, checked: bool) {
+    let locals = func.variables.iter().map(|v| v.name.clone()).collect::<HashSet<String>>();
+    let mut constants = Constants::default();
+    let mut line = 0;
+    // whether the previous instruction was an int operation folded without overflowing.
+    let mut folded = false;
+
+    for instruction in std::mem::take(&mut func.instructions) {
+        let previous_folded = std::mem::replace(&mut folded, false);
+        let instruction = match instruction {
+            Instr::Line(val) => {
+                line = val.line;
+                Instr::Line(val)
+            },
+            Instr::Label(label) => {
+                constants = Constants::default();
+                Instr::Label(label)
+            },
+            Instr::Assign { variable, value } => {
+                let value = constants.value(&value);
+                constants.set_variable(&variable, &value);
+                Instr::Assign { variable, value }
+            },
+            Instr::Copy { dest, value } => {
+                let value = constants.value(&value);
+                constants.set_temporary(dest, &value);
+                Instr::Copy { dest, value }
+            },
+            Instr::Binary { dest, op, left, right } => {
+                let (left, right) = (constants.value(&left), constants.value(&right));
+                match fold_binary(op, &left, &right, checked) {
+                    Some(value) => {
+                        folded = true;
+                        constants.set_temporary(dest, &value);
+                        Instr::Copy { dest, value }
+                    },
+                    None => Instr::Binary { dest, op, left, right },
+                }
+            },
+            Instr::Unary { dest, op, value } => {
+                let value = constants.value(&value);
+                match fold_unary(op, &value, checked) {
+                    Some(value) => {
+                        folded = true;
+                        constants.set_temporary(dest, &value);
+                        Instr::Copy { dest, value }
+                    },
+                    None => Instr::Unary { dest, op, value },
+                }
+            },
+            Instr::LoadElement { dest, array, index } => Instr::LoadElement { dest, array, index: constants.value(&index) },
+            Instr::StoreElement { array, index, value } => Instr::StoreElement { array, index: constants.value(&index), value: constants.value(&value) },
+            Instr::StoreField { variable, offset, value } => Instr::StoreField { variable, offset, value: constants.value(&value) },
+            Instr::Call { dest, function, arguments } => {
+                let arguments = arguments.iter().map(|a| constants.value(a)).collect();
+                constants.variables.retain(|name, _| locals.contains(name));
+                Instr::Call { dest, function, arguments }
+            },
+            Instr::JumpIfFalse { condition, label } => {
+                let condition = constants.value(&condition);
+                let value = match condition {
+                    Operand::Bool(val) => val,
+                    condition => {
+                        func.instructions.push(Instr::JumpIfFalse { condition, label });
+                        continue;
+                    },
+                };
+                if let Some(statement) = statement_of(&label) {
+                    func.decided_branches.push(DecidedBranch { line, statement, value });
+                }
+                match value {
+                    true => continue,
+                    false => Instr::Jump(label),
+                }
+            },
+            Instr::JumpIfNoOverflow(label) if previous_folded => Instr::Jump(label),
+            Instr::Return(value) => Instr::Return(value.map(|v| constants.value(&v))),
+            instruction => instruction,
+        };
+        func.instructions.push(instruction);
+    }
+
+    remove_unreachable(&mut func.instructions);
+    remove_unused_copies(&mut func.instructions);
+}
+
+fn jump_target(instruction: &Instr) -> Option<&String> {
+    return match instruction {
+        Instr::Jump(label) | Instr::JumpIfFalse { label, .. } | Instr::JumpIfNoOverflow(label) => Some(label),
+        _ => None,
+    };
+}
+
+/// Removes the instructions following a jump or a `return` until a label some jump targets,
+/// the labels no jump targets, and the jumps to the instruction right after them.
+fn remove_unreachable(instructions: &mut Vec<Instr>) {
+    loop {
+        let count = instructions.len();
+        let targets = instructions.iter().filter_map(jump_target).cloned().collect::<HashSet<String>>();
+        let mut reachable = true;
+        instructions.retain(|instruction| {
+            if let Instr::Label(label) = instruction {
+                if !targets.contains(label) {
+                    return false;
+                }
+                reachable = true;
+            }
+            let keep = reachable;
+            if matches!(instruction, Instr::Jump(..) | Instr::Return(..)) {
+                reachable = false;
+            }
+            return keep;
+        });
+        let mut i = 0;
+        while i + 1 < instructions.len() {
+            match (&instructions[i], &instructions[i + 1]) {
+                (Instr::Jump(target), Instr::Label(label)) if target == label => {
+                    instructions.remove(i);
+                },
+                _ => i += 1,
+            };
+        }
+        if instructions.len() == count {
+            return;
+        }
+    }
+}
+
+/// Removes the copies to temporaries whose uses were all replaced by a constant.
+fn remove_unused_copies(instructions: &mut Vec<Instr>) {
+    let mut used = HashSet::<usize>::new();
+    let mut read = |operand: &Operand| {
+        if let Operand::Temp(id) = operand {
+            used.insert(*id);
+        }
+    };
+    for instruction in instructions.iter() {
+        match instruction {
+            Instr::Assign { value, .. } | Instr::Copy { value, .. } | Instr::Unary { value, .. } | Instr::StoreField { value, .. } => read(value),
+            Instr::Binary { left, right, .. } => {
+                read(left);
+                read(right);
+            },
+            Instr::LoadElement { index, .. } => read(index),
+            Instr::StoreElement { index, value, .. } => {
+                read(index);
+                read(value);
+            },
+            Instr::Call { arguments, .. } => arguments.iter().for_each(&mut read),
+            Instr::JumpIfFalse { condition, .. } => read(condition),
+            Instr::Return(Some(value)) => read(value),
+            _ => (),
+        };
+    }
+    instructions.retain(|instruction| !matches!(instruction, Instr::Copy { dest, .. } if !used.contains(dest)));
+}
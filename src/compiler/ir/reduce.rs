@@ -0,0 +1,176 @@
+use super::super::int_type;
+use super::{BinaryOp, Function, Instr, Operand};
+
+/// Exponents of the two powers of two adding up to `value`, the first being the largest.
+/// A power of two is the sum of itself and of nothing.
+fn powers_of_two(value: i64) -> Option<(u32, Option<u32>)> {
+    if value <= 1 || value > i32::MAX as i64 {
+        return None;
+    }
+    let high = 63 - value.leading_zeros();
+    let rest = value - (1 << high);
+    return match rest {
+        0 => Some((high, None)),
+        rest if rest & (rest - 1) == 0 => Some((high, Some(rest.trailing_zeros()))),
+        _ => None,
+    };
+}
+
+struct Reduction<'a> {
+    function: &'a mut Function,
+    instructions: Vec<Instr>,
+}
+
+impl<'a> Reduction<'a> {
+    /// `value << shift`, `value` itself when it is not shifted.
+    fn shift_left(&mut self, value: &Operand, shift: u32) -> Operand {
+        if shift == 0 {
+            return value.clone();
+        }
+        return self.binary(BinaryOp::Shl, value.clone(), Operand::Int(shift as i64));
+    }
+
+    /// New temporary holding `left op right`.
+    fn binary(&mut self, op: BinaryOp, left: Operand, right: Operand) -> Operand {
+        self.function.temporaries.push(int_type());
+        let dest = self.function.temporaries.len() - 1;
+        self.instructions.push(Instr::Binary { dest, op, left, right });
+        return Operand::Temp(dest);
+    }
+
+    /// Instructions computing `dest <- value * factor`, `factor` being one or two powers of two.
+    fn multiply(&mut self, dest: usize, value: &Operand, high: u32, low: Option<u32>) {
+        let instruction = match low {
+            None => Instr::Binary { dest, op: BinaryOp::Shl, left: value.clone(), right: Operand::Int(high as i64) },
+            Some(low) => {
+                let left = self.shift_left(value, high);
+                let right = self.shift_left(value, low);
+                Instr::Binary { dest, op: BinaryOp::Add, left, right }
+            },
+        };
+        self.instructions.push(instruction);
+    }
+
+    /// Instructions computing `dest <- value / 2^shift`, rounding toward zero as `idiv` does:
+    /// negative values are biased by `2^shift - 1` before being shifted.
+    fn divide(&mut self, dest: usize, value: &Operand, shift: u32) {
+        let sign = self.binary(BinaryOp::Sar, value.clone(), Operand::Int(31));
+        let bias = self.binary(BinaryOp::Shr, sign, Operand::Int(32 - shift as i64));
+        let biased = self.binary(BinaryOp::Add, value.clone(), bias);
+        self.instructions.push(Instr::Binary { dest, op: BinaryOp::Sar, left: biased, right: Operand::Int(shift as i64) });
+    }
+}
+
+/// Replaces the int multiplications by constants made of one or two powers of two with shifts and additions,
+/// and the int divisions by powers of two with shifts.
+/// Operations checked for overflow are kept, the shifts not setting the overflow flag.
+pub fn reduce_strength(function: &mut Function) {
+    let instructions = std::mem::take(&mut function.instructions);
+    let mut reduction = Reduction { function, instructions: Vec::new() };
+    for (i, instruction) in instructions.iter().enumerate() {
+        let checked = matches!(instructions.get(i + 1), Some(Instr::JumpIfNoOverflow(..)));
+        let (dest, op, left, right) = match instruction {
+            Instr::Binary { dest, op, left, right } if !checked && reduction.function.temporaries[*dest] == int_type() => (*dest, *op, left, right),
+            _ => {
+                reduction.instructions.push(instruction.clone());
+                continue;
+            },
+        };
+        match (op, left, right) {
+            (BinaryOp::Mul, Operand::Int(..), Operand::Int(..)) => reduction.instructions.push(instruction.clone()),
+            (BinaryOp::Mul, value, Operand::Int(factor)) | (BinaryOp::Mul, Operand::Int(factor), value) => match powers_of_two(*factor) {
+                Some((high, low)) => reduction.multiply(dest, value, high, low),
+                None => reduction.instructions.push(instruction.clone()),
+            },
+            (BinaryOp::Div, value, Operand::Int(divisor)) if !matches!(value, Operand::Int(..)) => match powers_of_two(*divisor) {
+                Some((shift, None)) => reduction.divide(dest, value, shift),
+                _ => reduction.instructions.push(instruction.clone()),
+            },
+            _ => reduction.instructions.push(instruction.clone()),
+        };
+    }
+    let reduced = reduction.instructions;
+    function.instructions = reduced;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::compiler::{compile, CompilerOptions, OptLevel, Target};
+    use crate::interpreter::program::run_program;
+    use crate::interpreter::trace::NoTrace;
+
+    /// Negative values around the multiples of 4, which the biased division rounds toward zero, and the ends of the
+    /// int range.
+    const VALUES: [i32; 15] = [-9, -8, -7, -5, -4, -3, -1, 0, 1, 3, 4, 7, i32::MIN, i32::MIN + 1, i32::MAX];
+
+    /// Program printing `x / 4` and `x * 6` for each of `VALUES`, read from an array so that they are not propagated.
+    fn program() -> String {
+        let mut source = format!("values: int[{}]\n", VALUES.len());
+        for (i, value) in VALUES.iter().enumerate() {
+            let literal = match *value {
+                i32::MIN => format!("0 - {} - 1", i32::MAX),
+                value if value < 0 => format!("0 - {}", -value),
+                value => value.to_string(),
+            };
+            source.push_str(format!("values[{}] <- {}\n", i, literal).as_str());
+        }
+        source.push_str(format!("i <- 0\nwhile i < {}\n    x <- values[i]\n    print(x / 4)\n    print(x * 6)\n    i <- i + 1\nend\n", VALUES.len()).as_str());
+        return source;
+    }
+
+    fn compile_program(target: Target, opt_level: OptLevel) -> (String, CompilerOptions) {
+        let ast = match crate::parse_str(&program()) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let options = CompilerOptions { target, opt_level, ..CompilerOptions::default() };
+        return match compile(&ast, &options) {
+            Err(diagnostics) => panic!("{:?}", diagnostics),
+            Ok(output) => (output.code, options),
+        };
+    }
+
+    /// Output of the program run by the interpreter, which the compiled programs are compared to.
+    fn interpret() -> String {
+        let ast = match crate::parse_str(&program()) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let mut output = Vec::<u8>::new();
+        if let Err(e) = run_program(&ast, &CompilerOptions::default(), &mut std::io::empty(), &mut output, &mut NoTrace) {
+            panic!("{}", e.message);
+        }
+        return String::from_utf8(output).unwrap();
+    }
+
+    /// Output of the program built at `opt_level`, `None` when there is no cc to build it.
+    fn run(opt_level: OptLevel) -> Option<String> {
+        if Command::new("cc").arg("--version").output().is_err() {
+            return None;
+        }
+        let (code, options) = compile_program(Target::Gas, opt_level);
+        return match crate::run_code_captured(&code, &options, &[]) {
+            Err(e) => panic!("{}", e.message),
+            Ok(output) => Some(String::from_utf8(output.stdout).unwrap()),
+        };
+    }
+
+    #[test]
+    fn reduces_division_and_multiplication() {
+        let (code, _) = compile_program(Target::Ir, OptLevel::O2);
+        assert!(code.contains(">>> 30") && code.contains(">> 2"), "x / 4 is not reduced in\n{}", code);
+        assert!(code.contains("<< 2") && code.contains("<< 1"), "x * 6 is not reduced in\n{}", code);
+    }
+
+    #[test]
+    fn reduced_operations_match_the_interpreter() {
+        let expected: String = VALUES.iter().map(|value| format!("{}\n{}\n", value / 4, value.wrapping_mul(6))).collect();
+        assert_eq!(interpret(), expected);
+        match run(OptLevel::O2) {
+            None => eprintln!("cc is not installed, the -O2 executable is not compared to the interpreter"),
+            Some(output) => assert_eq!(output, expected),
+        };
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt::{self, Debug, Display, Formatter};
+
+use super::super::super::lexer::Span;
+use super::super::{Type, Variable};
+
+/// Value read by an instruction.
+#[derive(Clone, PartialEq)]
+pub enum Operand {
+    /// result of a previous instruction, each temporary is assigned once except the result of `and` and `or`, assigned by both branches.
+    Temp(usize),
+    Variable(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    /// label and length in bytes of a string literal.
+    Str { label: String, length: usize },
+    /// address of a function.
+    Function(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    /// shifts of ints, `Sar` keeping their sign and `Shr` filling them with zeros.
+    Shl,
+    Sar,
+    Shr,
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    IntToFloat,
+    /// truncates toward zero.
+    FloatToInt,
+    /// fixed size array to the address and length of its elements.
+    ToSlice,
+}
+
+/// Three-address code instruction.
+#[derive(Clone, PartialEq)]
+pub enum Instr {
+    /// variable <- value
+    Assign { variable: String, value: Operand },
+    /// temp <- value
+    Copy { dest: usize, value: Operand },
+    /// temp <- left op right
+    Binary { dest: usize, op: BinaryOp, left: Operand, right: Operand },
+    /// temp <- op value
+    Unary { dest: usize, op: UnaryOp, value: Operand },
+    /// temp <- array[index]
+    LoadElement { dest: usize, array: String, index: Operand },
+    /// array[index] <- value
+    StoreElement { array: String, index: Operand, value: Operand },
+    /// temp <- field of the record `variable` located `offset` bytes after its start
+    LoadField { dest: usize, variable: String, offset: u64 },
+    /// field of the record `variable` located `offset` bytes after its start <- value
+    StoreField { variable: String, offset: u64, value: Operand },
+    /// temp <- function(arguments), `dest` is `None` when the result is not used.
+    Call { dest: Option<usize>, function: String, arguments: Vec<Operand> },
+    Label(String),
+    Jump(String),
+    JumpIfFalse { condition: Operand, label: String },
+    /// jumps unless the int operation of the previous instruction overflowed.
+    JumpIfNoOverflow(String),
+    Return(Option<Operand>),
+    /// start of the statement written at this position of the source.
+    Line(Span),
+}
+
+/// Function lowered to three-address code.
+#[derive(Clone)]
+pub struct Function {
+    pub name: String,
+    /// parameters followed by the local variables, as in the flattened function.
+    pub variables: Vec<Variable>,
+    pub parameter_count: usize,
+    pub return_type: Option<Type>,
+    /// type of each temporary.
+    pub temporaries: Vec<Type>,
+    pub instructions: Vec<Instr>,
+    /// conditions of the function found constant while optimizing it.
+    pub decided_branches: Vec<DecidedBranch>,
+}
+
+/// `if` or `while` of the source whose condition always has the same value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecidedBranch {
+    pub line: usize,
+    /// `if` or `while`.
+    pub statement: &'static str,
+    pub value: bool,
+}
+
+impl Display for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::Temp(id) => write!(f, "t{}", id),
+            Self::Variable(name) => write!(f, "{}", name),
+            Self::Int(val) => write!(f, "{}", val),
+            Self::Float(val) => write!(f, "{:?}", val),
+            Self::Bool(val) => write!(f, "{}", val),
+            Self::Str { label, .. } => write!(f, "&{}", label),
+            Self::Function(name) => write!(f, "&{}", name),
+        };
+    }
+}
+
+impl Debug for Operand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self);
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+            Self::Shl => "<<",
+            Self::Sar => ">>",
+            Self::Shr => ">>>",
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+        });
+    }
+}
+
+impl BinaryOp {
+    pub fn is_comparison(&self) -> bool {
+        return !matches!(self, Self::Add | Self::Sub | Self::Mul | Self::Div | Self::Mod | Self::Shl | Self::Sar | Self::Shr);
+    }
+}
+
+impl Display for Instr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            Self::Line(span) => write!(f, "\t; line {}", span.line),
+            Self::Assign { variable, value } => write!(f, "\t{} <- {}", variable, value),
+            Self::Copy { dest, value } => write!(f, "\tt{} <- {}", dest, value),
+            Self::Binary { dest, op, left, right } => write!(f, "\tt{} <- {} {} {}", dest, left, op, right),
+            Self::Unary { dest, op: UnaryOp::Neg, value } => write!(f, "\tt{} <- -{}", dest, value),
+            Self::Unary { dest, op: UnaryOp::IntToFloat, value } => write!(f, "\tt{} <- (float){}", dest, value),
+            Self::Unary { dest, op: UnaryOp::FloatToInt, value } => write!(f, "\tt{} <- (int){}", dest, value),
+            Self::Unary { dest, op: UnaryOp::ToSlice, value } => write!(f, "\tt{} <- (slice){}", dest, value),
+            Self::LoadElement { dest, array, index } => write!(f, "\tt{} <- {}[{}]", dest, array, index),
+            Self::StoreElement { array, index, value } => write!(f, "\t{}[{}] <- {}", array, index, value),
+            Self::LoadField { dest, variable, offset } => write!(f, "\tt{} <- *(&{} + {})", dest, variable, offset),
+            Self::StoreField { variable, offset, value } => write!(f, "\t*(&{} + {}) <- {}", variable, offset, value),
+            Self::Call { dest, function, arguments } => {
+                let arguments = arguments.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+                match dest {
+                    Some(dest) => write!(f, "\tt{} <- call {}({})", dest, function, arguments.join(", ")),
+                    None => write!(f, "\tcall {}({})", function, arguments.join(", ")),
+                }
+            },
+            Self::Label(label) => write!(f, "{}:", label),
+            Self::Jump(label) => write!(f, "\tgoto {}", label),
+            Self::JumpIfFalse { condition, label } => write!(f, "\tif not {} goto {}", condition, label),
+            Self::JumpIfNoOverflow(label) => write!(f, "\tif not overflow goto {}", label),
+            Self::Return(None) => write!(f, "\treturn"),
+            Self::Return(Some(value)) => write!(f, "\treturn {}", value),
+        };
+    }
+}
+
+impl Debug for Instr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self);
+    }
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let params = self.variables[..self.parameter_count]
+            .iter()
+            .map(|v| format!("{}: {}", v.name, v.typeval))
+            .collect::<Vec<String>>();
+        let header = match &self.return_type {
+            Some(typeval) => writeln!(f, "function {}({}): {}", self.name, params.join(", "), typeval),
+            None => writeln!(f, "function {}({})", self.name, params.join(", ")),
+        };
+        if let Err(e) = header {
+            return Err(e);
+        }
+        for var in &self.variables[self.parameter_count..] {
+            if let Err(e) = writeln!(f, "\tvar {}: {}", var.name, var.typeval) {
+                return Err(e);
+            }
+        }
+        for instruction in self.instructions.iter().filter(|i| !matches!(i, Instr::Line(..))) {
+            if let Err(e) = writeln!(f, "{}", instruction) {
+                return Err(e);
+            }
+        }
+        return write!(f, "end");
+    }
+}
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+
+use super::super::parser;
+use super::c::{function_name, precedence};
+use super::codegen::{expression_type, is_float};
+use super::{runtime, Backend, CompilerContext, Function, Type};
+
+/// JavaScript source, runnable with node or in a browser.
+pub struct JsBackend;
+
+impl Backend for JsBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return visit_module(context);
+    }
+}
+
+/// JavaScript reserved words which are valid variable names in the pseudocode.
+const RESERVED: [&str; 40] = [
+    "await", "break", "case", "catch", "class", "const", "continue", "debugger",
+    "default", "delete", "do", "else", "enum", "export", "extends", "false",
+    "finally", "for", "function", "if", "implements", "import", "in", "instanceof",
+    "interface", "let", "new", "null", "package", "private", "protected", "public",
+    "return", "static", "super", "switch", "this", "throw", "try", "var",
+];
+
+fn variable_name(name: &str) -> String {
+    if RESERVED.contains(&name) || name == "main" {
+        return format!("{}_", name);
+    }
+    return name.to_string();
+}
+
+/// Initial value of a variable, fixed size arrays are filled with the initial value of their elements.
+fn default_value(typeval: &Type) -> String {
+    if let (Some(element), Some(count)) = (&typeval.element, typeval.count) {
+        return format!("new Array({}).fill({})", count, default_value(element));
+    }
+    if typeval.element.is_some() {
+        return String::from("[]");
+    }
+    return match typeval.name.as_str() {
+        "int" => String::from("0"),
+        "bool" => String::from("false"),
+        "float" => String::from("0.0"),
+        "str" => String::from("\"\""),
+        _ => String::from("null"),
+    };
+}
+
+fn string_literal(value: &str) -> String {
+    let mut res = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => res.push_str(format!("\\x{:02x}", c as u32).as_str()),
+            c => res.push(c),
+        };
+    }
+    res.push('"');
+    return res;
+}
+
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    variables: HashMap<String, Type>,
+    output: String,
+    depth: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    fn emit(&mut self, line: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str("    ");
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    fn visit_operand(&self, operand: &parser::Ast, parent: &parser::Ast, is_right: bool) -> Result<String, String> {
+        let value = match self.visit_expression(operand) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let (operand_precedence, parent_precedence) = (precedence(operand), precedence(parent));
+        if operand_precedence < parent_precedence || is_right && operand_precedence == parent_precedence {
+            return Ok(format!("({})", value));
+        }
+        return Ok(value);
+    }
+
+    fn visit_expression(&self, expression: &parser::Ast) -> Result<String, String> {
+        return Ok(match expression {
+            parser::Ast::Int(val) => val.to_string(),
+            parser::Ast::Bool(val) => val.to_string(),
+            parser::Ast::Float(val) => format!("{:?}", val),
+            parser::Ast::Str(val) => string_literal(val),
+            parser::Ast::Variable(var) => variable_name(&var.name),
+            parser::Ast::MemberAccess { record, field } => match expression_type(record, &self.variables, self.context) {
+                Err(e) => return Err(e),
+                Ok(typeval) if typeval.is_record() => return Err(String::from("codegen: records are not supported by the javascript backend")),
+                Ok(_) => match self.visit_expression(record) {
+                    Err(e) => return Err(e),
+                    Ok(val) => format!("{}.{}", val, field),
+                },
+            },
+            parser::Ast::ArrayAccess { variable, index } => match self.visit_expression(index) {
+                Err(e) => return Err(e),
+                Ok(index) => format!("{}[{}]", variable_name(variable), index),
+            },
+            parser::Ast::FunctionReference(name) => function_name(name),
+            parser::Ast::NewArray { size, .. } => match (self.visit_expression(size), expression_type(expression, &self.variables, self.context)) {
+                (Err(e), _) | (_, Err(e)) => return Err(e),
+                (Ok(size), Ok(typeval)) => format!("{}({}, {})", runtime::ALLOC_ROUTINE, size, typeval.element.map_or(0, |element| element.size)),
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                let mut arguments = Vec::<String>::new();
+                for child in children {
+                    match self.visit_expression(child) {
+                        Err(e) => return Err(e),
+                        Ok(val) => arguments.push(val),
+                    };
+                }
+                format!("{}({})", function_name(name), arguments.join(", "))
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_operand(child, expression, false),
+            parser::Ast::UnaryMinus { child } => match self.visit_operand(child, expression, false) {
+                Err(e) => return Err(e),
+                // avoids `--x`, which is a decrement in javascript.
+                Ok(val) if val.starts_with('-') => format!("-({})", val),
+                Ok(val) => format!("-{}", val),
+            },
+            // ints already are numbers, and arrays know their length.
+            parser::Ast::Cast { child, typename } if typename == "float" || typename.ends_with("[]") => return self.visit_operand(child, expression, false),
+            parser::Ast::Cast { child, .. } => match self.visit_expression(child) {
+                Err(e) => return Err(e),
+                Ok(val) => format!("Math.trunc({})", val),
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right }
+            | parser::Ast::And { left, right }
+            | parser::Ast::Or { left, right } => {
+                let left_type = match expression_type(left, &self.variables, self.context) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let operator = match (expression, is_float(&left_type)) {
+                    (parser::Ast::Modulo {..}, true) => return Err(String::from("codegen: modulo is not defined on floats")),
+                    (parser::Ast::Addition {..}, _) => "+",
+                    (parser::Ast::Substraction {..}, _) => "-",
+                    (parser::Ast::Multiplication {..}, _) => "*",
+                    (parser::Ast::Division {..}, _) => "/",
+                    (parser::Ast::Modulo {..}, _) => "%",
+                    (parser::Ast::EqualTo {..}, _) => "===",
+                    (parser::Ast::NotEqualTo {..}, _) => "!==",
+                    (parser::Ast::GreaterThan {..}, _) => ">",
+                    (parser::Ast::GreaterOrEqual {..}, _) => ">=",
+                    (parser::Ast::LowerThan {..}, _) => "<",
+                    (parser::Ast::And {..}, _) => "&&",
+                    (parser::Ast::Or {..}, _) => "||",
+                    _ => "<=",
+                };
+                let left_value = match self.visit_operand(left, expression, false) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right_value = match self.visit_operand(right, expression, true) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                // numbers are floats, integer division truncates toward zero like the compiled code.
+                match (expression, is_float(&left_type)) {
+                    (parser::Ast::Division {..}, false) => format!("Math.trunc({} / {})", left_value, right_value),
+                    _ => format!("{} {} {}", left_value, operator, right_value),
+                }
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        });
+    }
+
+    fn visit_block(&mut self, statements: &[parser::Ast]) -> Result<(), String> {
+        self.depth += 1;
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        self.depth -= 1;
+        return Ok(());
+    }
+
+    /// Emits an `if`, `else if` chains are kept flat.
+    fn visit_condition(&mut self, condition: &parser::Ast, valid_branch: &[parser::Ast], invalid_branch: &[parser::Ast], keyword: &str) -> Result<(), String> {
+        match self.visit_expression(condition) {
+            Err(e) => return Err(e),
+            Ok(val) => self.emit(format!("{} ({}) {{", keyword, val).as_str()),
+        };
+        if let Err(e) = self.visit_block(valid_branch) {
+            return Err(e);
+        }
+        match invalid_branch {
+            [] => self.emit("}"),
+            [parser::Ast::Condition { condition, valid_branch, invalid_branch, .. }] => {
+                return self.visit_condition(condition, valid_branch, invalid_branch, "} else if");
+            },
+            statements => {
+                self.emit("} else {");
+                if let Err(e) = self.visit_block(statements) {
+                    return Err(e);
+                }
+                self.emit("}");
+            },
+        };
+        return Ok(());
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                let target = match self.visit_expression(variable) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if let parser::Ast::ArrayValue(children) = &**expression {
+                    // array values are copied in the fixed size arrays, like in the compiled code.
+                    for (i, child) in children.iter().enumerate() {
+                        match self.visit_expression(child) {
+                            Err(e) => return Err(e),
+                            Ok(val) => self.emit(format!("{}[{}] = {};", target, i, val).as_str()),
+                        };
+                    }
+                    return Ok(());
+                }
+                match self.visit_expression(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) => self.emit(format!("{} = {};", target, val).as_str()),
+                };
+            },
+            parser::Ast::ReturnStatement { value: None, .. } => self.emit("return;"),
+            parser::Ast::ReturnStatement { value: Some(value), .. } => match self.visit_expression(value) {
+                Err(e) => return Err(e),
+                Ok(val) => self.emit(format!("return {};", val).as_str()),
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                return self.visit_condition(condition, valid_branch, invalid_branch, "if");
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                match self.visit_expression(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => self.emit(format!("while ({}) {{", val).as_str()),
+                };
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+                self.emit("}");
+            },
+            expression => match self.visit_expression(expression) {
+                Err(e) => return Err(e),
+                Ok(val) => self.emit(format!("{};", val).as_str()),
+            },
+        };
+        return Ok(());
+    }
+}
+
+fn visit_function(func: &Function, name: &str, context: &CompilerContext) -> Result<String, String> {
+    let mut generator = FunctionGenerator {
+        context,
+        // locals shadow the globals.
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        output: String::new(),
+        depth: 0,
+    };
+
+    let params = func.variables[..func.parameter_count].iter().map(|p| variable_name(&p.name)).collect::<Vec<String>>();
+    generator.emit(format!("function {}({}) {{", name, params.join(", ")).as_str());
+
+    generator.depth += 1;
+    for var in &func.variables[func.parameter_count..] {
+        let line = format!("let {} = {};", variable_name(&var.name), default_value(&var.typeval));
+        generator.emit(line.as_str());
+    }
+    if func.variables.len() > func.parameter_count && !func.statements.is_empty() {
+        generator.output.push('\n');
+    }
+    generator.depth -= 1;
+
+    if let Err(e) = generator.visit_block(&func.statements) {
+        return Err(e);
+    }
+    generator.emit("}");
+    return Ok(generator.output);
+}
+
+/// JavaScript implementation of a runtime library function, `None` for the other symbols.
+fn runtime_function(symbol: &str) -> Option<&'static str> {
+    return match symbol {
+        "print_int" => Some("function print_int(value) {\n    console.log(value);\n}\n"),
+        "print_str" => Some("function print_str(value) {\n    console.log(value);\n}\n"),
+        "write_int" => Some("function write_int(value) {\n    process.stdout.write(String(value));\n}\n"),
+        "write_str" => Some("function write_str(value) {\n    process.stdout.write(value);\n}\n"),
+        "str_length" => Some("function str_length(value) {\n    return value.length;\n}\n"),
+        "str_substring" => Some("function str_substring(value, start, length) {\n    start = Math.min(Math.max(start, 0), value.length);\n    return value.slice(start, start + Math.max(length, 0));\n}\n"),
+        "str_concat" => Some("function str_concat(a, b) {\n    return a + b;\n}\n"),
+        "str_compare" => Some("function str_compare(a, b) {\n    return a < b ? -1 : a > b ? 1 : 0;\n}\n"),
+        "int_to_string" => Some("function int_to_string(value) {\n    return String(value);\n}\n"),
+        "int_abs" => Some("function int_abs(value) {\n    return Math.abs(value) | 0;\n}\n"),
+        "int_min" => Some("function int_min(a, b) {\n    return Math.min(a, b);\n}\n"),
+        "int_max" => Some("function int_max(a, b) {\n    return Math.max(a, b);\n}\n"),
+        "exit_program" => Some("function exit_program(code) {\n    process.exit(code);\n}\n"),
+        "alloc" => Some("function alloc(length, size) {\n    return new Array(length).fill(0);\n}\n"),
+        "release" => Some("function release(values, size) {\n    values.length = 0;\n}\n"),
+        "sqrt" => Some("function sqrt(value) {\n    return Math.sqrt(value);\n}\n"),
+        "pow" => Some("function pow(base, exponent) {\n    return Math.pow(base, exponent);\n}\n"),
+        "floor" => Some("function floor(value) {\n    return Math.floor(value);\n}\n"),
+        "ceil" => Some("function ceil(value) {\n    return Math.ceil(value);\n}\n"),
+        "log" => Some("function log(value) {\n    return Math.log(value);\n}\n"),
+        "sin" => Some("function sin(value) {\n    return Math.sin(value);\n}\n"),
+        "cos" => Some("function cos(value) {\n    return Math.cos(value);\n}\n"),
+        _ => None,
+    };
+}
+
+/// JavaScript implementation of the runtime functions on arrays, which only differ by their element type.
+fn array_function(symbol: &str) -> Option<String> {
+    let (_, operation) = match symbol.split_once("_array_") {
+        None => return None,
+        Some(val) => val,
+    };
+    return match operation {
+        "copy" => Some(format!("function {}(values) {{\n    return values.slice();\n}}\n", symbol)),
+        "append" => Some(format!("function {}(values, value) {{\n    return values.concat([value]);\n}}\n", symbol)),
+        "fill" => Some(format!("function {}(values, value) {{\n    values.fill(value);\n}}\n", symbol)),
+        // the default order compares the elements as strings.
+        "sort" => Some(format!("function {}(values) {{\n    values.sort((a, b) => a - b);\n}}\n", symbol)),
+        _ => None,
+    };
+}
+
+/// JavaScript implementation of the runtime functions on maps, which are `Map`s, `null` standing for a map never assigned.
+fn map_function(symbol: &str, context: &CompilerContext) -> Option<String> {
+    if symbol == "map_new" {
+        return Some(String::from("function map_new() {\n    return new Map();\n}\n"));
+    }
+    let (_, operation) = match symbol.split_once("_map_") {
+        None => return None,
+        Some(val) => val,
+    };
+    // missing keys read the initial value of the values.
+    let value = context.declarations.get(symbol).and_then(|dec| dec.return_type.as_ref()).map_or(String::new(), default_value);
+    return match operation {
+        "get" => Some(format!("function {}(values, key) {{\n    return values !== null && values.has(key) ? values.get(key) : {};\n}}\n", symbol, value)),
+        "set" => Some(format!("function {}(values, key, value) {{\n    values = values === null ? new Map() : values;\n    values.set(key, value);\n    return values;\n}}\n", symbol)),
+        "contains" => Some(format!("function {}(values, key) {{\n    return values !== null && values.has(key);\n}}\n", symbol)),
+        "remove" => Some(format!("function {}(values, key) {{\n    if (values !== null) {{\n        values.delete(key);\n    }}\n}}\n", symbol)),
+        _ => None,
+    };
+}
+
+fn visit_module(context: &CompilerContext) -> Result<String, String> {
+    let mut functions = Vec::<String>::new();
+
+    for name in &context.extern_symbols {
+        if let Some(source) = runtime_function(name).map(String::from).or_else(|| array_function(name)).or_else(|| map_function(name, context)) {
+            functions.push(source);
+            continue;
+        }
+        let dec = match context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec,
+        };
+        functions.push(format!(
+            "function {}(...args) {{\n    throw new Error(\"extern function {}\");\n}}\n",
+            function_name(name),
+            dec.name,
+        ));
+    }
+
+    for f in context.functions.iter().chain(std::iter::once(&context.main_function)) {
+        let name = if std::ptr::eq(f, &context.main_function) { String::from("main") } else { function_name(&f.name) };
+        match visit_function(f, name.as_str(), context) {
+            Err(e) => return Err(e),
+            Ok(source) => functions.push(source),
+        };
+    }
+
+    let mut res = String::from("\"use strict\";\n\n");
+    let generator = FunctionGenerator { context, variables: HashMap::new(), output: String::new(), depth: 0 };
+    for global in &context.globals {
+        let value = match &global.value {
+            None => default_value(&global.variable.typeval),
+            Some(value) => match generator.visit_expression(value) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            },
+        };
+        res.push_str(format!("let {} = {};\n", variable_name(&global.variable.name), value).as_str());
+    }
+    if !context.globals.is_empty() {
+        res.push('\n');
+    }
+    res.push_str(functions.join("\n").as_str());
+    // the result of `main` is the exit status, when running under node.
+    res.push_str("\nconst status = main();\nif (typeof process !== \"undefined\") {\n    process.exitCode = status;\n}\n");
+    return Ok(res);
+}
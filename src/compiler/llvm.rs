@@ -0,0 +1,568 @@
+use std::collections::HashMap;
+
+use super::super::parser;
+use super::codegen::{cast_type, expression_type, is_float};
+use super::{Backend, CompilerContext, Function, Type, bool_type, string_type};
+
+/// Textual LLVM IR module, to be compiled with `llc`/`clang` or run with `lli`.
+/// Variables live in `alloca` slots, `opt -passes=mem2reg` turns them into registers.
+pub struct LlvmBackend;
+
+impl Backend for LlvmBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return visit_module(context);
+    }
+}
+
+/// Global name of a function, quoted since effective names contain parentheses.
+fn global_name(name: &str) -> String {
+    return format!("@\"{}\"", name);
+}
+
+/// LLVM type of a value: arrays are passed as a pointer to their first element, functions as `i8*`.
+fn value_type(typeval: &Type) -> String {
+    if let Some(element) = &typeval.element {
+        return format!("{}*", value_type(element));
+    }
+    return match typeval.name.as_str() {
+        "int" => String::from("i32"),
+        "bool" => String::from("i1"),
+        "float" => String::from("double"),
+        _ => String::from("i8*"),
+    };
+}
+
+/// LLVM type of the storage of a variable, fixed size arrays are stored inline.
+fn storage_type(typeval: &Type) -> String {
+    return match (&typeval.element, typeval.count) {
+        (Some(element), Some(count)) => format!("[{} x {}]", count, value_type(element)),
+        _ => value_type(typeval),
+    };
+}
+
+fn return_type(typeval: &Option<Type>) -> String {
+    return match typeval {
+        Some(typeval) => value_type(typeval),
+        None => String::from("void"),
+    };
+}
+
+/// Content of a `c"..."` constant, non printable characters are hex escaped.
+fn string_data(value: &str) -> String {
+    let mut res = String::new();
+    for byte in value.bytes().chain(std::iter::once(0)) {
+        if byte.is_ascii_graphic() && byte != b'"' && byte != b'\\' || byte == b' ' {
+            res.push(byte as char);
+        } else {
+            res.push_str(format!("\\{:02X}", byte).as_str());
+        }
+    }
+    return res;
+}
+
+/// Generates the body of a single function.
+/// Each expression returns the name of the SSA value (or the constant) holding its result.
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    /// locals and globals, locals shadowing the globals.
+    variables: HashMap<String, Type>,
+    /// parameters and local variables.
+    locals: Vec<String>,
+    output: String,
+    value_count: usize,
+    label_count: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    fn emit(&mut self, instruction: &str) {
+        self.output.push_str("  ");
+        self.output.push_str(instruction);
+        self.output.push('\n');
+    }
+
+    fn label(&mut self, label: &str) {
+        self.output.push_str(label);
+        self.output.push_str(":\n");
+    }
+
+    fn new_label_id(&mut self) -> usize {
+        self.label_count += 1;
+        return self.label_count - 1;
+    }
+
+    /// Emits `%tN = <instruction>`, returning `%tN`.
+    fn assign(&mut self, instruction: &str) -> String {
+        let name = format!("%t{}", self.value_count);
+        self.value_count += 1;
+        self.emit(format!("{} = {}", name, instruction).as_str());
+        return name;
+    }
+
+    fn variable(&self, name: &String) -> Result<Type, String> {
+        return match self.variables.get(name) {
+            Some(typeval) => Ok(typeval.clone()),
+            None => Err(format!("codegen: unknown variable '{}'", name)),
+        };
+    }
+
+    /// Pointer to the storage of the variable, an `alloca` slot or a global.
+    fn address(&self, name: &String) -> String {
+        return match self.locals.contains(name) {
+            true => format!("%\"{}.addr\"", name),
+            false => global_name(format!("var.{}", name).as_str()),
+        };
+    }
+
+    fn expression_type(&self, expression: &parser::Ast) -> Result<Type, String> {
+        return expression_type(expression, &self.variables, self.context);
+    }
+
+    fn visit_return(&mut self, value: Option<(String, Type)>) {
+        match value {
+            Some((value, typeval)) => self.emit(format!("ret {} {}", value_type(&typeval), value).as_str()),
+            None => self.emit("ret void"),
+        };
+    }
+
+    /// Pointer to the first element of an array variable.
+    fn visit_array_pointer(&mut self, name: &String, typeval: &Type) -> String {
+        let storage = storage_type(typeval);
+        return match typeval.count {
+            Some(..) => self.assign(format!("getelementptr inbounds {}, {}* {}, i64 0, i64 0", storage, storage, self.address(name)).as_str()),
+            None => self.assign(format!("load {}, {}* {}", storage, storage, self.address(name)).as_str()),
+        };
+    }
+
+    /// Pointer to `variable[index]` and the type of the element.
+    fn visit_element_address(&mut self, variable: &String, index: &parser::Ast) -> Result<(String, Type), String> {
+        let typeval = match self.variable(variable) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let element = match &typeval.element {
+            Some(element) => *element.clone(),
+            None => return Err(format!("codegen: cannot index variable '{}' of type {}", variable, typeval)),
+        };
+
+        let pointer = self.visit_array_pointer(variable, &typeval);
+        let index = match self.visit_expression(index) {
+            Err(e) => return Err(e),
+            Ok((val, _)) => val,
+        };
+        let index = self.assign(format!("sext i32 {} to i64", index).as_str());
+        let element_type = value_type(&element);
+        let address = self.assign(format!("getelementptr inbounds {}, {}* {}, i64 {}", element_type, element_type, pointer, index).as_str());
+        return Ok((address, element));
+    }
+
+    fn visit_expression(&mut self, expression: &parser::Ast) -> Result<(String, Type), String> {
+        let value = match expression {
+            parser::Ast::Int(val) => val.to_string(),
+            parser::Ast::Bool(val) => val.to_string(),
+            parser::Ast::Float(val) => format!("0x{:016X}", val.to_bits()),
+            parser::Ast::Variable(var) => {
+                let typeval = match self.variable(&var.name) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if typeval.count.is_some() {
+                    let pointer = self.visit_array_pointer(&var.name, &typeval);
+                    return Ok((pointer, typeval));
+                }
+                let llvm_type = value_type(&typeval);
+                let value = self.assign(format!("load {}, {}* {}", llvm_type, llvm_type, self.address(&var.name)).as_str());
+                return Ok((value, typeval));
+            },
+            parser::Ast::ArrayAccess { variable, index } => {
+                let (address, element) = match self.visit_element_address(variable, index) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let llvm_type = value_type(&element);
+                let value = self.assign(format!("load {}, {}* {}", llvm_type, llvm_type, address).as_str());
+                return Ok((value, element));
+            },
+            parser::Ast::FunctionReference(name) => match self.context.declarations.get(name) {
+                None => return Err(format!("codegen: undefined function {}", name)),
+                Some(dec) => {
+                    let params = dec.parameters.iter().map(value_type).collect::<Vec<String>>();
+                    format!("bitcast ({} ({})* {} to i8*)", return_type(&dec.return_type), params.join(", "), global_name(name))
+                },
+            },
+            parser::Ast::Str(val) => match self.context.strings.label(val) {
+                None => return Err(format!("codegen: missing label for string {:?}", val)),
+                Some(label) => {
+                    let array = format!("[{} x i8]", val.len() + 1);
+                    return Ok((format!("getelementptr inbounds ({}, {}* @{}, i64 0, i64 0)", array, array, label), string_type()));
+                },
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                return match self.visit_call(name, children) {
+                    Err(e) => Err(e),
+                    Ok(None) => Err(format!("codegen: function {} has no return value", name)),
+                    Ok(Some(val)) => Ok(val),
+                };
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_expression(child),
+            parser::Ast::UnaryMinus { child } => {
+                let (value, typeval) = match self.visit_expression(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let value = match is_float(&typeval) {
+                    true => self.assign(format!("fneg double {}", value).as_str()),
+                    false => self.assign(format!("sub i32 0, {}", value).as_str()),
+                };
+                return Ok((value, typeval));
+            },
+            parser::Ast::Cast { child, typename } => {
+                let (value, _) = match self.visit_expression(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let typeval = match cast_type(typename) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let value = match is_float(&typeval) {
+                    true => self.assign(format!("sitofp i32 {} to double", value).as_str()),
+                    false => self.assign(format!("fptosi double {} to i32", value).as_str()),
+                };
+                return Ok((value, typeval));
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right } => return self.visit_binary(expression, left, right),
+            // each operand ends in a block of its own, the predecessors of the phi.
+            parser::Ast::And { left, right } | parser::Ast::Or { left, right } => {
+                let id = self.new_label_id();
+                let (left_value, _) = match self.visit_expression(left) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.emit(format!("br label %left{}", id).as_str());
+                self.label(format!("left{}", id).as_str());
+                match expression {
+                    parser::Ast::And {..} => self.emit(format!("br i1 {}, label %right{}, label %endlogic{}", left_value, id, id).as_str()),
+                    _ => self.emit(format!("br i1 {}, label %endlogic{}, label %right{}", left_value, id, id).as_str()),
+                };
+                self.label(format!("right{}", id).as_str());
+                let (right_value, _) = match self.visit_expression(right) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.emit(format!("br label %rightend{}", id).as_str());
+                self.label(format!("rightend{}", id).as_str());
+                self.emit(format!("br label %endlogic{}", id).as_str());
+                self.label(format!("endlogic{}", id).as_str());
+                self.assign(format!("phi i1 [ {}, %left{} ], [ {}, %rightend{} ]", left_value, id, right_value, id).as_str())
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        };
+
+        return match self.expression_type(expression) {
+            Err(e) => Err(e),
+            Ok(typeval) => Ok((value, typeval)),
+        };
+    }
+
+    fn visit_binary(&mut self, expression: &parser::Ast, left: &parser::Ast, right: &parser::Ast) -> Result<(String, Type), String> {
+        let (left_value, left_type) = match self.visit_expression(left) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let (right_value, right_type) = match self.visit_expression(right) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+
+        if left_type != right_type {
+            return Err(format!("codegen: implicit conversion between {} and {} is not supported", left_type, right_type));
+        }
+
+        let instruction = match (expression, is_float(&left_type)) {
+            (parser::Ast::Addition {..}, false) => "add",
+            (parser::Ast::Substraction {..}, false) => "sub",
+            (parser::Ast::Multiplication {..}, false) => "mul",
+            (parser::Ast::Division {..}, false) => "sdiv",
+            (parser::Ast::Modulo {..}, false) => "srem",
+            (parser::Ast::EqualTo {..}, false) => "icmp eq",
+            (parser::Ast::NotEqualTo {..}, false) => "icmp ne",
+            (parser::Ast::GreaterThan {..}, false) => "icmp sgt",
+            (parser::Ast::GreaterOrEqual {..}, false) => "icmp sge",
+            (parser::Ast::LowerThan {..}, false) => "icmp slt",
+            (parser::Ast::LowerOrEqual {..}, false) => "icmp sle",
+            (parser::Ast::Addition {..}, true) => "fadd",
+            (parser::Ast::Substraction {..}, true) => "fsub",
+            (parser::Ast::Multiplication {..}, true) => "fmul",
+            (parser::Ast::Division {..}, true) => "fdiv",
+            (parser::Ast::EqualTo {..}, true) => "fcmp oeq",
+            (parser::Ast::NotEqualTo {..}, true) => "fcmp une",
+            (parser::Ast::GreaterThan {..}, true) => "fcmp ogt",
+            (parser::Ast::GreaterOrEqual {..}, true) => "fcmp oge",
+            (parser::Ast::LowerThan {..}, true) => "fcmp olt",
+            (parser::Ast::LowerOrEqual {..}, true) => "fcmp ole",
+            _ => return Err(String::from("codegen: modulo is not defined on floats")),
+        };
+        let value = self.assign(format!("{} {} {}, {}", instruction, value_type(&left_type), left_value, right_value).as_str());
+
+        return match self.expression_type(expression) {
+            Err(e) => Err(e),
+            Ok(typeval) => Ok((value, typeval)),
+        };
+    }
+
+    /// Calls `name`, returning its result if it has one.
+    fn visit_call(&mut self, name: &String, children: &Vec<parser::Ast>) -> Result<Option<(String, Type)>, String> {
+        let dec = match self.context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec.clone(),
+        };
+
+        let mut arguments = Vec::<String>::new();
+        for child in children {
+            match self.visit_expression(child) {
+                Err(e) => return Err(e),
+                Ok((value, typeval)) => arguments.push(format!("{} {}", value_type(&typeval), value)),
+            };
+        }
+
+        let call = format!("call {} {}({})", return_type(&dec.return_type), global_name(name), arguments.join(", "));
+        return Ok(match dec.return_type {
+            None => {
+                self.emit(call.as_str());
+                None
+            },
+            Some(typeval) => Some((self.assign(call.as_str()), typeval)),
+        });
+    }
+
+    fn visit_block(&mut self, statements: &Vec<parser::Ast>) -> Result<(), String> {
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+
+    fn visit_condition(&mut self, condition: &parser::Ast) -> Result<String, String> {
+        return match self.visit_expression(condition) {
+            Err(e) => Err(e),
+            Ok((_, typeval)) if typeval != bool_type() => Err(format!("codegen: condition must be a bool, got {}", typeval)),
+            Ok((value, _)) => Ok(value),
+        };
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                let name = match &**variable {
+                    parser::Ast::Variable(var) => &var.name,
+                    parser::Ast::ArrayAccess { variable, index } => {
+                        let (value, _) = match self.visit_expression(expression) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        let (address, element) = match self.visit_element_address(variable, index) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        let llvm_type = value_type(&element);
+                        self.emit(format!("store {} {}, {}* {}", llvm_type, value, llvm_type, address).as_str());
+                        return Ok(());
+                    },
+                    _ => return Err(String::from("codegen: can only assign value to a variable.")),
+                };
+                let typeval = match self.variable(name) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if let (parser::Ast::ArrayValue(children), Some(element)) = (&**expression, &typeval.element) {
+                    let llvm_type = value_type(element);
+                    for (i, child) in children.iter().enumerate() {
+                        let (value, _) = match self.visit_expression(child) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        let (address, _) = match self.visit_element_address(name, &parser::Ast::Int(i as i64)) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        self.emit(format!("store {} {}, {}* {}", llvm_type, value, llvm_type, address).as_str());
+                    }
+                    return Ok(());
+                }
+                let (value, _) = match self.visit_expression(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let llvm_type = value_type(&typeval);
+                let address = self.address(name);
+                self.emit(format!("store {} {}, {}* {}", llvm_type, value, llvm_type, address).as_str());
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                if let Err(e) = self.visit_call(name, children) {
+                    return Err(e);
+                }
+            },
+            parser::Ast::ReturnStatement { value, .. } => {
+                let value = match value {
+                    None => None,
+                    Some(value) => match self.visit_expression(value) {
+                        Err(e) => return Err(e),
+                        Ok(val) => Some(val),
+                    },
+                };
+                self.visit_return(value);
+                // instructions after a return go to a new unreachable block.
+                let id = self.new_label_id();
+                self.label(format!("dead{}", id).as_str());
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                let id = self.new_label_id();
+                let condition = match self.visit_condition(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.emit(format!("br i1 {}, label %then{}, label %else{}", condition, id, id).as_str());
+                self.label(format!("then{}", id).as_str());
+                if let Err(e) = self.visit_block(valid_branch) {
+                    return Err(e);
+                }
+                self.emit(format!("br label %endif{}", id).as_str());
+                self.label(format!("else{}", id).as_str());
+                if let Err(e) = self.visit_block(invalid_branch) {
+                    return Err(e);
+                }
+                self.emit(format!("br label %endif{}", id).as_str());
+                self.label(format!("endif{}", id).as_str());
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                let id = self.new_label_id();
+                self.emit(format!("br label %while{}", id).as_str());
+                self.label(format!("while{}", id).as_str());
+                let condition = match self.visit_condition(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.emit(format!("br i1 {}, label %body{}, label %endwhile{}", condition, id, id).as_str());
+                self.label(format!("body{}", id).as_str());
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+                self.emit(format!("br label %while{}", id).as_str());
+                self.label(format!("endwhile{}", id).as_str());
+            },
+            expression => {
+                if let Err(e) = self.visit_expression(expression) {
+                    return Err(e);
+                }
+            },
+        };
+        return Ok(());
+    }
+}
+
+fn visit_function(func: &Function, context: &CompilerContext) -> Result<String, String> {
+    let result = match context.declarations.get(&func.name) {
+        Some(dec) => dec.return_type.clone(),
+        None => None,
+    };
+
+    let mut generator = FunctionGenerator {
+        context,
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        locals: func.variables.iter().map(|v| v.name.clone()).collect(),
+        output: String::new(),
+        value_count: 0,
+        label_count: 0,
+    };
+
+    let params = func.variables[..func.parameter_count]
+        .iter()
+        .map(|p| format!("{} %\"{}.arg\"", value_type(&p.typeval), p.name))
+        .collect::<Vec<String>>();
+    generator.output.push_str(format!("define {} {}({}) {{\n", return_type(&result), global_name(&func.name), params.join(", ")).as_str());
+    generator.label("entry");
+
+    for var in &func.variables {
+        generator.emit(format!("%\"{}.addr\" = alloca {}", var.name, storage_type(&var.typeval)).as_str());
+    }
+    for param in &func.variables[..func.parameter_count] {
+        let llvm_type = value_type(&param.typeval);
+        generator.emit(format!("store {} %\"{}.arg\", {}* %\"{}.addr\"", llvm_type, param.name, llvm_type, param.name).as_str());
+    }
+
+    if let Err(e) = generator.visit_block(&func.statements) {
+        return Err(e);
+    }
+
+    match result {
+        // the end of a function returning a value is never reached.
+        Some(..) => generator.emit("unreachable"),
+        None => generator.visit_return(None),
+    };
+    generator.output.push_str("}\n");
+    return Ok(generator.output);
+}
+
+fn visit_module(context: &CompilerContext) -> Result<String, String> {
+    let mut res = String::new();
+    if let Some(platform) = context.options.platform {
+        res.push_str(format!("target triple = \"{}\"\n", platform.llvm_triple()).as_str());
+    }
+    for value in &context.strings.values {
+        if let Some(label) = context.strings.label(value) {
+            res.push_str(format!("@{} = private unnamed_addr constant [{} x i8] c\"{}\"\n", label, value.len() + 1, string_data(value)).as_str());
+        }
+    }
+
+    for global in &context.globals {
+        let value = match &global.value {
+            Some(parser::Ast::Int(val)) => val.to_string(),
+            Some(parser::Ast::Bool(val)) => val.to_string(),
+            Some(parser::Ast::Float(val)) => format!("0x{:016X}", val.to_bits()),
+            _ => String::from("zeroinitializer"),
+        };
+        let name = global_name(format!("var.{}", global.variable.name).as_str());
+        res.push_str(format!("{} = internal global {} {}\n", name, storage_type(&global.variable.typeval), value).as_str());
+    }
+
+    for name in &context.extern_symbols {
+        let dec = match context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec,
+        };
+        let params = dec.parameters.iter().map(value_type).collect::<Vec<String>>();
+        res.push_str(format!("declare {} {}({})\n", return_type(&dec.return_type), global_name(name), params.join(", ")).as_str());
+    }
+
+    for f in &context.functions {
+        match visit_function(f, context) {
+            Err(e) => return Err(e),
+            Ok(val) => {
+                res.push('\n');
+                res.push_str(val.as_str());
+            },
+        };
+    }
+
+    res.push('\n');
+    return match visit_function(&context.main_function, context) {
+        Err(e) => Err(e),
+        Ok(val) => {
+            res.push_str(val.as_str());
+            Ok(res)
+        },
+    };
+}
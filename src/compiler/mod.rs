@@ -1,11 +1,90 @@
-use std::{fmt::{Debug, Formatter, self, Display}, collections::HashMap, hash::Hash};
+use std::{fmt::{Debug, Formatter, self, Display}, collections::HashMap, hash::Hash, io::{BufWriter, Write}, sync::OnceLock};
 
+use super::diagnostic::{Code, Diagnostic};
+use super::error::CompileError;
 use super::parser;
-
-#[derive(Clone, Hash, Eq)]
+use super::lexer::Span;
+use super::timings::{self, Phase};
+
+mod asserts;
+mod c;
+mod codegen;
+mod constants;
+mod exceptions;
+pub(crate) mod formats;
+mod ir;
+#[cfg(feature = "llvm")]
+mod llvm;
+mod js;
+mod optimize;
+mod options;
+pub(crate) mod pragmas;
+mod python;
+mod riscv;
+mod runtime;
+mod semantics;
+mod source_map;
+mod stack;
+mod symbol_table;
+mod syntax;
+mod wasm;
+
+pub use asserts::ASSERT_STATUS;
+pub use c::CBackend;
+pub use codegen::X86Backend;
+pub use ir::IrBackend;
+#[cfg(feature = "llvm")]
+pub use llvm::LlvmBackend;
+pub use js::JsBackend;
+pub use optimize::OptLevel;
+pub use options::{CompilerOptions, Emit, Platform, RuntimeChecks, Target, WarningLevel, WarningLevels};
+pub use python::PythonBackend;
+pub use riscv::RiscVBackend;
+pub use runtime::runtime_library;
+pub use semantics::{check, check_with_strictness};
+pub use source_map::{Mapping, SourceMap};
+pub use symbol_table::{FunctionSymbol, SymbolTable, VariableSymbol};
+pub use wasm::WasmBackend;
+pub use syntax::AsmSyntax;
+
+#[derive(Clone, Hash, PartialEq, Eq)]
 pub struct Type {
     pub name: String,
     pub size: u64,
+    /// type of the elements for arrays.
+    pub element: Option<Box<Type>>,
+    /// number of elements of fixed size arrays, unsized arrays being (pointer, length) pairs.
+    pub count: Option<u64>,
+    /// alignment of the values in memory, the size being a multiple of it.
+    pub align: u64,
+    /// fields of records, empty for the other types.
+    pub fields: Vec<Field>,
+    /// types of the keys and of the values of maps.
+    pub map: Option<(Box<Type>, Box<Type>)>,
+}
+
+/// Member of a record, stored `offset` bytes after the start of the record.
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct Field {
+    pub name: String,
+    pub typeval: Type,
+    pub offset: u64,
+}
+
+impl Type {
+    /// Whether the type is a user defined record, strings having fields but being plain values.
+    pub fn is_record(&self) -> bool {
+        return !self.fields.is_empty() && self != &string_type() && !self.is_slice();
+    }
+
+    /// Whether the type is an array of unknown size, as `int[]`.
+    pub fn is_slice(&self) -> bool {
+        return self.element.is_some() && self.count.is_none();
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        return self.fields.iter().find(|f| f.name == name);
+    }
 }
 
 impl Debug for Type {
@@ -20,16 +99,15 @@ impl Display for Type {
     }
 }
 
-impl PartialEq<Type> for Type {
-    fn eq(&self, other: &Type) -> bool {
-        return self.name == other.name && self.size == other.size;
-    }
-}
-
 pub fn int_type() -> Type {
     return Type {
         name: String::from("int"),
         size: 4,
+        element: None,
+        count: None,
+        align: 4,
+        fields: Vec::new(),
+        map: None,
     };
 }
 
@@ -37,6 +115,11 @@ pub fn bool_type() -> Type {
     return Type {
         name: String::from("bool"),
         size: 1,
+        element: None,
+        count: None,
+        align: 1,
+        fields: Vec::new(),
+        map: None,
     };
 }
 
@@ -44,20 +127,109 @@ pub fn float_type() -> Type {
     return Type {
         name: String::from("float"),
         size: 8,
+        element: None,
+        count: None,
+        align: 8,
+        fields: Vec::new(),
+        map: None,
+    };
+}
+
+/// Arrays of unknown size are (pointer, length) pairs like strings, the length being their `length` field.
+pub fn array_type(element: Type, count: Option<u64>) -> Type {
+    return Type {
+        name: match count {
+            Some(count) => format!("{}[{}]", element.name, count),
+            None => format!("{}[]", element.name),
+        },
+        size: match count {
+            Some(count) => element.size * count,
+            None => 16,
+        },
+        align: match count {
+            Some(..) => element.align,
+            None => 8,
+        },
+        element: Some(Box::new(element)),
+        count,
+        fields: match count {
+            Some(..) => Vec::new(),
+            None => vec![Field { name: String::from("length"), typeval: int_type(), offset: 8 }],
+        },
+        map: None,
+    };
+}
+
+fn align_to(offset: u64, align: u64) -> u64 {
+    return offset.div_ceil(align) * align;
+}
+
+/// Lays out the fields in declaration order, each one aligned on its type.
+pub fn record_type(name: String, fields: Vec<(String, Type)>) -> Type {
+    let mut offset = 0;
+    let mut align = 1;
+    let mut res = Vec::<Field>::new();
+    for (name, typeval) in fields {
+        offset = align_to(offset, typeval.align);
+        align = align.max(typeval.align);
+        let size = typeval.size;
+        res.push(Field { name, typeval, offset });
+        offset += size;
+    }
+    return Type {
+        name,
+        size: align_to(offset, align),
+        element: None,
+        count: None,
+        align,
+        fields: res,
+        map: None,
     };
 }
 
-pub fn array_type() -> Type {
+/// Type of a function address, named after the signature it points to.
+fn function_type(declaration: &FunctionDeclaration) -> Type {
     return Type {
-        name: String::from("array"),
+        name: format!(
+            "fn({}): {}",
+            declaration.parameters.iter().map(|t| t.name.clone()).collect::<Vec<String>>().join(", "),
+            match &declaration.return_type {
+                None => String::from("void"),
+                Some(val) => val.name.clone(),
+            },
+        ),
         size: 8,
+        element: None,
+        count: None,
+        align: 8,
+        fields: Vec::new(),
+        map: None,
     };
 }
 
+/// Strings are (pointer, length) pairs, the length can be read as the `length` field.
 pub fn string_type() -> Type {
     return Type {
         name: String::from("str"),
+        size: 16,
+        element: None,
+        count: None,
+        align: 8,
+        fields: vec![Field { name: String::from("length"), typeval: int_type(), offset: 8 }],
+        map: None,
+    };
+}
+
+/// Maps are the address of a table of entries owned by the runtime, the map being shared by the variables holding it.
+pub fn map_type(key: Type, value: Type) -> Type {
+    return Type {
+        name: format!("map[{}, {}]", key.name, value.name),
         size: 8,
+        element: None,
+        count: None,
+        align: 8,
+        fields: Vec::new(),
+        map: Some((Box::new(key), Box::new(value))),
     };
 }
 
@@ -67,6 +239,21 @@ struct Variable {
     typeval: Type,
 }
 
+/// Variable of the top level of the program, stored outside of the stack.
+#[derive(Clone)]
+struct Global {
+    variable: Variable,
+    /// literal the global starts with, zeroed globals have none.
+    value: Option<parser::Ast>,
+}
+
+impl Global {
+    /// Label of the global in the generated assembly, prefixed not to clash with the functions.
+    fn label(&self) -> String {
+        return format!("var.{}", self.variable.name);
+    }
+}
+
 #[derive(Clone, Eq)]
 struct FunctionDeclaration {
     name: String,
@@ -77,7 +264,9 @@ struct FunctionDeclaration {
 
 struct Function {
     name: String,
+    /// local variables, starting with the `parameter_count` parameters.
     variables: Vec<Variable>,
+    parameter_count: usize,
     statements: Vec<parser::Ast>,
 }
 
@@ -86,6 +275,7 @@ impl Function {
         return Function {
             name,
             variables: Vec::new(),
+            parameter_count: 0,
             statements,
         };
     }
@@ -106,20 +296,10 @@ impl Function {
 
 }
 
-impl ToString for FunctionDeclaration {
-    fn to_string(&self) -> String {
-        let mut res = String::new();
-        res.push_str(self.name.as_str());
-        res.push('(');
-        if self.parameters.len() >= 1 {
-            let mut iter = self.parameters.iter();
-            res.push_str(iter.next().unwrap().name.as_str());
-            for val in iter {
-                res.push_str(format!(",{}", val.name).as_str());
-            }
-        }
-        res.push(')');
-        return res;
+impl Display for FunctionDeclaration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let parameters: Vec<&str> = self.parameters.iter().map(|param| param.name.as_str()).collect();
+        return write!(f, "{}({})", self.name, parameters.join(","));
     }
 }
 
@@ -136,58 +316,174 @@ impl PartialEq<FunctionDeclaration> for FunctionDeclaration {
     }
 }
 
-#[derive(Clone)]
-struct Scope {
+/// Declarations of a block, borrowing the scope of the enclosing block, which is not modified while it is alive.
+struct Scope<'a> {
     functions: Vec<FunctionDeclaration>,
     variables: Vec<Variable>,
     types: Vec<Type>,
-    parent: Option<Box<Scope>>,
+    parent: Option<&'a Scope<'a>>,
     functions_symbol_table: HashMap<FunctionDeclaration, String>,
+    /// stack slots of the variables declared in this scope, when they differ from their names.
+    storage: HashMap<String, String>,
+    /// values of the `const` declarations of this scope.
+    constants: Vec<(String, parser::Ast)>,
+    /// whether the variables of the enclosing scopes are hidden up to the global scope, as they are from the functions
+    /// and lambdas nested in another function.
+    hides_locals: bool,
 }
 
-impl Scope {
+/// Scope holding the builtin functions, built once.
+static BUILTINS: OnceLock<Scope<'static>> = OnceLock::new();
+
+impl<'a> Scope<'a> {
 
+    /// Global scope, whose parent holds the builtin functions so that declarations can shadow them.
     fn new_global_scope() -> Self {
-        return Scope {
-            functions: Vec::new(),
-            variables: Vec::new(),
-            types: vec![int_type(), float_type(), string_type(), bool_type(), array_type()],
-            functions_symbol_table: HashMap::new(),
-            parent: None,
+        let builtins = BUILTINS.get_or_init(|| {
+            let mut builtins = Scope::new(None);
+            for (dec, symbol) in runtime::builtin_declarations() {
+                builtins.functions.push(dec.clone());
+                builtins.functions_symbol_table.insert(dec, symbol);
+            }
+            builtins
+        });
+
+        let mut res = Scope::new(Some(builtins));
+        res.types = vec![int_type(), float_type(), string_type(), bool_type()];
+        return res;
+    }
+
+    /// Whether the scope is the global one, the builtins being its only parent.
+    fn is_global(&self) -> bool {
+        return match &self.parent {
+            Some(parent) => parent.parent.is_none(),
+            None => false,
         };
     }
 
-    fn new(parent: Option<Box<Scope>>) -> Self {
+    fn new(parent: Option<&'a Scope<'a>>) -> Self {
         return Scope {
             functions: Vec::new(),
             variables: Vec::new(),
             types: Vec::new(),
             parent,
             functions_symbol_table: HashMap::<FunctionDeclaration, String>::new(),
+            storage: HashMap::new(),
+            constants: Vec::new(),
+            hides_locals: false,
         };
     }
-}
 
-fn function_exists(name: &str, param_types: &Vec<Type>, scope: &Scope) -> Option<FunctionDeclaration> {
+    /// Scope of a block nested in this one.
+    fn child(&'a self) -> Scope<'a> {
+        return Scope::new(Some(self));
+    }
 
-    for dec in &scope.functions {
-        if dec.name != name {
-            continue;
-        }
-        if param_types == &dec.parameters {
-            return Some(dec.clone());
+    /// This scope and the enclosing ones, from the innermost to the builtins, along with whether their variables are visible.
+    fn ancestors(&self) -> Vec<(&Scope<'a>, bool)> {
+        let mut res = Vec::new();
+        let mut hidden = false;
+        let mut current = Some(self);
+        while let Some(scope) = current {
+            res.push((scope, !hidden || scope.is_global() || scope.parent.is_none()));
+            hidden = hidden || scope.hides_locals;
+            current = scope.parent;
         }
+        return res;
     }
 
-    if let Some(parent_scope) = &scope.parent {
-        return function_exists(name, param_types, &parent_scope);
+    /// Scopes whose variables are visible from this one, from the innermost to the builtins.
+    fn variable_scopes(&self) -> Vec<&Scope<'a>> {
+        return self.ancestors().into_iter().filter(|(_, visible)| *visible).map(|(scope, _)| scope).collect();
     }
+}
+
+/// How an argument is passed for a parameter, from the best match to the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ArgumentMatch {
+    Exact,
+    /// array passed for an array of unknown size, its elements being kept.
+    Promotion,
+    /// int passed for a float.
+    Conversion,
+}
 
+/// How an argument of type `argument` can be passed for a parameter of type `parameter`, `None` when it cannot.
+fn argument_match(parameter: &Type, argument: &Type) -> Option<ArgumentMatch> {
+    if parameter == argument {
+        return Some(ArgumentMatch::Exact);
+    }
+    if parameter.is_slice() && argument.element.is_some() && parameter.element == argument.element {
+        return Some(ArgumentMatch::Promotion);
+    }
+    if parameter == &float_type() && argument == &int_type() {
+        return Some(ArgumentMatch::Conversion);
+    }
     return None;
 }
 
+/// Overload of `name` called with `param_types`, the one whose arguments need the least conversions.
+/// A declaration is better than another when none of its arguments needs a worse conversion and one needs a better one,
+/// the candidates are returned when no declaration is better than all the others.
+fn resolve_overload(name: &str, param_types: &Vec<Type>, scope: &Scope) -> Result<Option<FunctionDeclaration>, Vec<FunctionDeclaration>> {
+    let mut candidates = Vec::<(FunctionDeclaration, Vec<ArgumentMatch>)>::new();
+    let mut current = Some(scope);
+    while let Some(scope) = current {
+        for dec in scope.functions.iter().filter(|dec| dec.name == name && dec.parameters.len() == param_types.len()) {
+            // inner declarations shadow the outer ones with the same parameters.
+            if candidates.iter().any(|(candidate, _)| candidate.parameters == dec.parameters) {
+                continue;
+            }
+            let conversions = dec.parameters.iter().zip(param_types).map(|(parameter, argument)| argument_match(parameter, argument)).collect::<Option<Vec<ArgumentMatch>>>();
+            if let Some(conversions) = conversions {
+                candidates.push((dec.clone(), conversions));
+            }
+        }
+        current = scope.parent;
+    }
+
+    let better = |a: &Vec<ArgumentMatch>, b: &Vec<ArgumentMatch>| a.iter().zip(b).all(|(a, b)| a <= b) && a != b;
+    let best = candidates.iter().find(|(_, conversions)| candidates.iter().all(|(_, other)| other == conversions || better(conversions, other)));
+    return match best {
+        Some((dec, _)) => Ok(Some(dec.clone())),
+        None if candidates.is_empty() => Ok(None),
+        None => Err(candidates.into_iter().map(|(dec, _)| dec).collect()),
+    };
+}
+
+/// Function called by `name(param_types)`, `None` when there is none or the call is ambiguous.
+fn function_exists(name: &str, param_types: &Vec<Type>, scope: &Scope) -> Option<FunctionDeclaration> {
+    return resolve_overload(name, param_types, scope).ok().flatten();
+}
+
+/// Whether `name(param_types)` is `len` called on an array, which no declaration handles.
+fn is_array_length(name: &str, param_types: &[Type]) -> bool {
+    return name == "len" && param_types.len() == 1 && param_types[0].element.is_some();
+}
+
+/// Whether `name(param_types)` is a formatted `print`, which no declaration handles.
+fn is_formatted_print(name: &str, param_types: &[Type]) -> bool {
+    return name == "print" && param_types.len() > 1;
+}
+
+/// Whether `name(param_types)` is `free` called on an array of unknown size, releasing an array created with `new`.
+fn is_array_release(name: &str, param_types: &[Type]) -> bool {
+    return name == "free" && param_types.len() == 1 && param_types[0].is_slice();
+}
+
+/// Whether `name(param_types)` is `contains` or `remove` called on a map with a key of its type, which no declaration handles.
+fn is_map_operation(name: &str, param_types: &[Type]) -> bool {
+    return (name == "contains" || name == "remove")
+        && param_types.len() == 2
+        && param_types[0].map.as_ref().is_some_and(|(key, _)| **key == param_types[1]);
+}
+
 fn get_function_return_type(name: &str, param_types: &Vec<Type>, scope: &Scope) -> Result<Option<Type>, String> {
     return match function_exists(name, param_types, scope) {
+        None if is_array_length(name, param_types) => Ok(Some(int_type())),
+        None if is_array_release(name, param_types) => Ok(None),
+        None if is_map_operation(name, param_types) && name == "contains" => Ok(Some(bool_type())),
+        None if is_map_operation(name, param_types) => Ok(None),
         None => Err(format!("no function with the following signature: {}({:?})", name, param_types)),
         Some(dec) => Ok(dec.return_type),
     };
@@ -199,7 +495,56 @@ fn calculate_expression_type(expression: &parser::Ast, scope: &Scope) -> Result<
         parser::Ast::Int(..) => Ok(int_type()),
         parser::Ast::Float(..) => Ok(float_type()),
         parser::Ast::Bool(..) => Ok(bool_type()),
-        parser::Ast::ArrayValue(..) => Ok(array_type()),
+        parser::Ast::ArrayValue(children) => {
+            let mut element: Option<Type> = None;
+            for child in children {
+                let typeval = match calculate_expression_type(child, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                match &element {
+                    Some(val) if val != &typeval
+                        => return Err(format!("mismatching types '{}' and '{}' in array value", val, typeval)),
+                    _ => element = Some(typeval),
+                };
+            }
+            match element {
+                None => Err(String::from("cannot infer the type of an empty array")),
+                Some(element) => Ok(array_type(element, Some(children.len() as u64))),
+            }
+        },
+        parser::Ast::ArrayAccess { variable, index } => {
+            let index_type = match calculate_expression_type(index, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            match get_variable_type(variable, scope) {
+                Err(e) => Err(e),
+                Ok(Type { map: Some((key, value)), .. }) if index_type == *key => Ok(*value),
+                Ok(Type { map: Some((key, _)), .. }) => Err(format!("map key must be a {}, got {}", key, index_type)),
+                _ if index_type != int_type() => Err(format!("array index must be an int, got {}", index_type)),
+                Ok(Type { element: Some(element), .. }) => Ok(*element),
+                Ok(val) => Err(format!("cannot index variable '{}' of type {}", variable, val)),
+            }
+        },
+        parser::Ast::MapValue(entries) => {
+            let mut res: Option<Type> = None;
+            for (key, value) in entries {
+                let typeval = match (calculate_expression_type(key, scope), calculate_expression_type(value, scope)) {
+                    (Err(e), _) | (_, Err(e)) => return Err(e),
+                    (Ok(key), Ok(value)) => map_type(key, value),
+                };
+                match &res {
+                    Some(val) if val != &typeval
+                        => return Err(format!("mismatching entries '{}' and '{}' in map value", val, typeval)),
+                    _ => res = Some(typeval),
+                };
+            }
+            match res {
+                None => Err(String::from("cannot infer the type of an empty map")),
+                Some(val) => Ok(val),
+            }
+        },
         parser::Ast::Str(..) => Ok(string_type()),
         parser::Ast::EqualTo {..}
         | parser::Ast::NotEqualTo {..}
@@ -207,6 +552,8 @@ fn calculate_expression_type(expression: &parser::Ast, scope: &Scope) -> Result<
         | parser::Ast::GreaterOrEqual {..}
         | parser::Ast::LowerThan {..}
         | parser::Ast::LowerOrEqual {..}
+        | parser::Ast::And {..}
+        | parser::Ast::Or {..}
             => Ok(bool_type()),
         parser::Ast::Substraction { left, right }
         | parser::Ast::Addition { left, right }
@@ -235,11 +582,18 @@ fn calculate_expression_type(expression: &parser::Ast, scope: &Scope) -> Result<
                 Ok(type1)
             }
         },
-        parser::Ast::Variable(var) => get_variable_type(&var.name, &scope),
-        parser::Ast::FunctionCall { name, children } => {
+        parser::Ast::Variable(var) => match constants::get_constant(&var.name, scope) {
+            Some(value) => calculate_expression_type(&value, scope),
+            None => get_variable_type(&var.name, scope),
+        },
+        parser::Ast::FunctionReference(name) => match get_function_by_effective_name(name, scope) {
+            None => Err(format!("undefined symbol {}", name)),
+            Some(dec) => Ok(function_type(&dec)),
+        },
+        parser::Ast::FunctionCall { name, children, .. } => {
             let mut types = Vec::<Type>::new();
             for child in children {
-                types.push(match calculate_expression_type(child, &scope) {
+                types.push(match calculate_expression_type(child, scope) {
                     Ok(val) => val,
                     Err(e) => return Err(e),
                 });
@@ -247,29 +601,65 @@ fn calculate_expression_type(expression: &parser::Ast, scope: &Scope) -> Result<
             match get_function_return_type(name, &types, scope) {
                 Err(e) => return Err(e),
                 Ok(val) => match val {
-                    None => return Err(format!("function with void return type cannot be used as an expression.")),
+                    None => return Err(String::from("function with void return type cannot be used as an expression.")),
                     Some(val) => Ok(val),
                 },
             }
         },
-        _ => todo!(),
+        parser::Ast::UnaryPlus { child } | parser::Ast::UnaryMinus { child } => calculate_expression_type(child, scope),
+        parser::Ast::Cast { child, typename } => match calculate_expression_type(child, scope) {
+            // arrays passed for an array of unknown size.
+            Ok(Type { element: Some(element), .. }) => Ok(array_type(*element, None)),
+            Ok(..) => get_type(typename.clone(), scope),
+            Err(e) => Err(e),
+        },
+        parser::Ast::NewArray { typename, .. } => match get_type(typename.clone(), scope) {
+            Err(e) => Err(e),
+            Ok(element) => Ok(array_type(element, None)),
+        },
+        parser::Ast::MemberAccess { record, field } => match calculate_expression_type(record, scope) {
+            Err(e) => Err(e),
+            Ok(val) => member_type(&val, field),
+        },
+        val => Err(format!("unsupported expression {:?}", val)),
     };
 }
 
+/// Type named `typename` in `scope`, `string` being another name of `str`.
 fn get_type(typename: String, scope: &Scope) -> Result<Type, String> {
-    if let Some(typeval) = scope.types.iter().filter(|&t| t.name == typename).next() {
+    if typename == "string" {
+        return Ok(string_type());
+    } else if let Some((key, value)) = typename.strip_prefix("map[").and_then(|t| t.strip_suffix(']')).and_then(|t| t.split_once(", ")) {
+        return get_map_type(key, value, scope);
+    } else if let Some(typeval) = scope.types.iter().find(|&t| t.name == typename) {
         return Ok(typeval.clone());
-    } else if let Some(parent_scope) = scope.parent.clone() {
-        return get_type(typename, parent_scope.as_ref());
+    } else if let Some(parent_scope) = scope.parent {
+        return get_type(typename, parent_scope);
     } else {
         return Err(format!("undefined type {:?}", typename));
     }
 }
 
+/// Type `map[key, value]`, maps being indexed by ints or strings and holding scalars or strings.
+fn get_map_type(key: &str, value: &str, scope: &Scope) -> Result<Type, String> {
+    let (key, value) = match (get_type(key.to_string(), scope), get_type(value.to_string(), scope)) {
+        (Err(e), _) | (_, Err(e)) => return Err(e),
+        (Ok(key), Ok(value)) => (key, value),
+    };
+    if key != int_type() && key != string_type() {
+        return Err(format!("map keys must be ints or strings, got {}", key));
+    }
+    if value != int_type() && value != float_type() && value != bool_type() && value != string_type() {
+        return Err(format!("map values must be ints, floats, bools or strings, got {}", value));
+    }
+    return Ok(map_type(key, value));
+}
+
 fn convert_type(old_type: &Option<String>, scope: &Scope) -> Result<Option<Type>, String> {
     if let Some(val) = old_type {
         return match get_type(val.clone(), scope) {
             Err(e) => return Err(e),
+            Ok(val) if val.is_record() => Err(format!("functions cannot return the record {}", val)),
             Ok(val) => Ok(Some(val)),
         };
     } else {
@@ -277,11 +667,30 @@ fn convert_type(old_type: &Option<String>, scope: &Scope) -> Result<Option<Type>
     }
 }
 
-fn convert_params(parser_params: &Vec<parser::Variable>, scope: &Scope) -> Result<Vec<Type>, String> {
+fn convert_parser_type(parser_type: &parser::Type, scope: &Scope) -> Result<Type, String> {
+    let typeval = match get_type(parser_type.name.clone(), scope) {
+        Ok(typeval) => typeval,
+        Err(e) => return Err(e),
+    };
+    if parser_type.is_array {
+        let size = match &parser_type.size_expression {
+            None => parser_type.size,
+            Some(expression) => match constants::evaluate_array_size(expression, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => Some(val),
+            },
+        };
+        return Ok(array_type(typeval, size));
+    }
+    return Ok(typeval);
+}
+
+fn convert_params(parser_params: &[parser::Variable], scope: &Scope) -> Result<Vec<Type>, String> {
     let mut result = Vec::<Type>::new();
     for param in parser_params {
         let parser_type = param.typename.clone().unwrap();
-        let typeval = match get_type(parser_type.name.clone(), &scope) {
+        let typeval = match convert_parser_type(&parser_type, scope) {
+            Ok(typeval) if typeval.is_record() => return Err(format!("record {} cannot be passed to a function, pass its fields", typeval)),
             Ok(typeval) => typeval,
             Err(e) => return Err(e),
         };
@@ -291,284 +700,1271 @@ fn convert_params(parser_params: &Vec<parser::Variable>, scope: &Scope) -> Resul
     return Ok(result);
 }
 
+/// Declares the record type `name` in `scope`.
+fn declare_record(name: &String, fields: &Vec<parser::Variable>, scope: &mut Scope) -> Result<Type, String> {
+    if get_type(name.clone(), scope).is_ok() {
+        return Err(format!("redeclaration of type {}", name));
+    }
+    if fields.is_empty() {
+        return Err(format!("record {} has no fields", name));
+    }
+
+    let mut types = Vec::<(String, Type)>::new();
+    for field in fields {
+        if types.iter().any(|(n, _)| n == &field.name) {
+            return Err(format!("duplicate field '{}' in record {}", field.name, name));
+        }
+        let typeval = match convert_parser_type(field.typename.as_ref().unwrap(), scope) {
+            Err(e) => return Err(e),
+            Ok(val) if val.element.is_some() && val.count.is_none()
+                => return Err(format!("field '{}' of record {} must have a fixed size", field.name, name)),
+            Ok(val) => val,
+        };
+        types.push((field.name.clone(), typeval));
+    }
+
+    let typeval = record_type(name.clone(), types);
+    scope.types.push(typeval.clone());
+    return Ok(typeval);
+}
+
+/// Type of the field accessed by `record.field`.
+fn member_type(record: &Type, field: &String) -> Result<Type, String> {
+    return match record.field(field) {
+        Some(val) => Ok(val.typeval.clone()),
+        None if record.is_record() => Err(format!("record {} has no field '{}'", record, field)),
+        None => Err(format!("cannot access field '{}' of type {}", field, record)),
+    };
+}
+
+/// Symbol fragment of a type: `int[]` becomes `int_arr`, `float[4]` becomes `float_arr4`.
+fn mangle_type(typeval: &Type) -> String {
+    if let Some(element) = &typeval.element {
+        return match typeval.count {
+            Some(count) => format!("{}_arr{}", mangle_type(element), count),
+            None => format!("{}_arr", mangle_type(element)),
+        };
+    }
+    return typeval.name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_");
+}
+
+/// Mangled symbol of a function: `{scope}_{name}__{parameters}`, the parameters being joined by `_`
+/// (`void` without parameters) and the scope being the symbol of the enclosing function, if any.
+/// `max(int, float[])` at the top level becomes `max__int_float_arr`.
 fn build_function_name(scope_name: String, declaration: &FunctionDeclaration) -> String {
-    return format!("{}_{}", scope_name, declaration.to_string());
+    // `main()` at the top level is the entry point of the program.
+    if scope_name.is_empty() && declaration.name == "main" && declaration.parameters.is_empty() {
+        return String::from("main");
+    }
+    let name = declaration.name.replace('.', "_");
+    let parameters = match declaration.parameters.len() {
+        0 => String::from("void"),
+        _ => declaration.parameters.iter().map(mangle_type).collect::<Vec<String>>().join("_"),
+    };
+    return match scope_name.is_empty() {
+        true => format!("{}__{}", name, parameters),
+        false => format!("{}_{}__{}", scope_name, name, parameters),
+    };
 }
 
 fn get_function_effective_name(declaration: &FunctionDeclaration, scope: &Scope) -> Result<String, String> {
     if let Some(val) = scope.functions_symbol_table.get(declaration) {
         return Ok(val.clone());
-    } else if let Some(parent_scope) = &scope.parent {
+    } else if let Some(parent_scope) = scope.parent {
         return get_function_effective_name(declaration, parent_scope);
     } else {
-        return Err(format!("undefined symbol {}", declaration.to_string()));
+        return Err(format!("undefined symbol {}", declaration));
     }
 }
 
-fn get_variable_type(name: &String, scope: &Scope) -> Result<Type, String> {
-    if let Some(var) = scope.variables.iter().filter(|&v| &v.name == name).next() {
-        return Ok(var.typeval.clone());
-    } else if let Some(parent_scope) = &scope.parent {
-        return get_variable_type(name, &parent_scope);
+fn get_function_by_effective_name(effective_name: &String, scope: &Scope) -> Option<FunctionDeclaration> {
+    if let Some((dec, ..)) = scope.functions_symbol_table.iter().find(|(_, name)| *name == effective_name) {
+        return Some(dec.clone());
+    } else if let Some(parent_scope) = scope.parent {
+        return get_function_by_effective_name(effective_name, parent_scope);
     } else {
-        return Err(format!("unknown variable '{}'", name));
+        return None;
     }
 }
 
-fn get_local_variable_type(name: &String, scope: &Scope) -> Option<Type> {
-    return match scope.variables.iter().filter(|v| &v.name == name).next() {
-        Some(var) => Some(var.typeval.clone()),
-        None => None,
+fn get_variable_type(name: &String, scope: &Scope) -> Result<Type, String> {
+    return match scope.variable_scopes().iter().find_map(|scope| scope.variables.iter().find(|v| &v.name == name)) {
+        Some(var) => Ok(var.typeval.clone()),
+        None => Err(format!("unknown variable '{}'", name)),
     };
 }
 
-fn flatten_tree(children: &Vec<parser::Ast>, scope: Scope, scope_name: String, func_impl: &mut Function, extern_symbols: &mut Vec<FunctionDeclaration>) -> Result<Vec<Function>, String> {
-    let mut children_functions = Vec::<Function>::new();
-    let mut scope = scope;
-    for child in children {
-        match child {
-            parser::Ast::FunctionDeclaration { name, children, parameters, return_type }
-            => {
-                let parameters = match convert_params(parameters, &scope) {
-                    Ok(val) => val,
-                    Err(e) => return Err(e),
-                };
+fn get_local_variable_type(name: &String, scope: &Scope) -> Option<Type> {
+    return scope.variables.iter().find(|v| &v.name == name).map(|var| var.typeval.clone());
+}
 
-                let return_type = match convert_type(return_type, &scope) {
-                    Err(e) => return Err(e),
-                    Ok(val) => val,
-                };
+/// A function to flatten, either declared by the program or lifted from a lambda.
+struct FunctionParts<'a> {
+    name: &'a str,
+    children: &'a [parser::Ast],
+    parameters: &'a [parser::Variable],
+    return_type: Option<Type>,
+    symbol: Option<String>,
+}
 
-                let dec = FunctionDeclaration {
-                    name: name.clone(),
-                    parameters,
-                    return_type,
-                    implemented: true,
-                };
-
-                match scope.functions_symbol_table.get_key_value(&dec) {
-                    Some((key, ..)) if key.implemented => return Err(format!("redeclaration of function {}", dec.to_string())),
-                    Some((key, ..)) if key.return_type != dec.return_type
-                        => return Err(
-                            format!(
-                                "invalid return type for function {}, expected {}, found {}", dec.to_string(),
-                                match &key.return_type {
-                                    None => String::from("void"),
-                                    Some(val) => val.name.clone(),
-                                },
-                                match &dec.return_type {
-                                    None => String::from("void"),
-                                    Some(val) => val.name.clone(),
-                                },
-                            )
-                        ),
-                    _ => (),
-                };
+/// Declares the function in `scope` and flattens its body, returning the function along with its nested functions.
+fn flatten_function(function: FunctionParts, scope: &mut Scope, scope_name: &str, symbols: &mut Symbols) -> Result<Vec<Function>, String> {
+    let FunctionParts { name, children, parameters, return_type, symbol } = function;
+    let parameter_types = match convert_params(parameters, scope) {
+        Ok(val) => val,
+        Err(e) => return Err(e),
+    };
 
-                scope.functions.push(dec.clone());
+    let dec = FunctionDeclaration {
+        name: String::from(name),
+        parameters: parameter_types.clone(),
+        return_type,
+        implemented: true,
+    };
 
-                let function_name = build_function_name(scope_name.clone(), &dec);
-                scope.functions_symbol_table.remove(&dec);
-                scope.functions_symbol_table.insert(dec.clone(), function_name.clone());
+    match scope.functions_symbol_table.get_key_value(&dec) {
+        Some((key, ..)) if key.implemented => return Err(format!("redeclaration of function {}", dec)),
+        Some((key, ..)) if key.return_type != dec.return_type
+            => return Err(
+                format!(
+                    "invalid return type for function {}, expected {}, found {}", dec,
+                    match &key.return_type {
+                        None => String::from("void"),
+                        Some(val) => val.name.clone(),
+                    },
+                    match &dec.return_type {
+                        None => String::from("void"),
+                        Some(val) => val.name.clone(),
+                    },
+                )
+            ),
+        _ => (),
+    };
 
-                let mut sub_function = Function::new_empty(function_name);
-
-                let sub_scope = Scope::new(Some(Box::new(scope.clone())));
-                let mut statements = Vec::<parser::Ast>::new();
-                let sub_functions = match flatten_tree(
-                    children,
-                    sub_scope, 
-                    format!("{}_{}", scope_name.clone(), name.clone()),
-                    &mut sub_function,
-                    extern_symbols,
-                ) {
-                    Err(e) => return Err(e),
-                    Ok(val) => val,
-                };
-                for f in sub_functions {
-                    children_functions.push(f);
-                }
-                children_functions.push(sub_function);
-            },
-            parser::Ast::FunctionHeader { name, parameters, return_type }
-            if match scope.parent {None => true, _ => false,} => {
-                let parameters = match convert_params(parameters, &scope) {
-                    Ok(val) => val,
-                    Err(e) => return Err(e),
-                };
+    scope.functions.push(dec.clone());
 
-                let return_type = match convert_type(return_type, &scope) {
-                    Err(e) => return Err(e),
-                    Ok(val) => val,
-                };
+    // the implementation keeps the symbol of its declaration.
+    let function_name = match (symbol, scope.functions_symbol_table.get(&dec)) {
+        (Some(symbol), Some(previous)) if previous != &symbol
+            => return Err(format!("function {} is declared with the symbol {}, not {}", dec, previous, symbol)),
+        (Some(symbol), _) => {
+            symbols.exported_symbols.push(symbol.clone());
+            symbol
+        },
+        (None, Some(previous)) => previous.clone(),
+        (None, None) => build_function_name(String::from(scope_name), &dec),
+    };
+    match symbols.declarations.get(&function_name) {
+        Some(previous) if previous != &dec
+            => return Err(format!("functions {} and {} have the same symbol {}", previous, dec, function_name)),
+        _ => (),
+    };
+    scope.functions_symbol_table.remove(&dec);
+    scope.functions_symbol_table.insert(dec.clone(), function_name.clone());
+    symbols.declarations.insert(function_name.clone(), dec.clone());
+
+    let mut sub_function = Function::new_empty(function_name.clone());
+    sub_function.parameter_count = parameters.len();
+
+    let mut sub_scope = scope.child();
+    for (param, typeval) in parameters.iter().zip(parameter_types) {
+        let var = Variable { name: param.name.clone(), typeval };
+        sub_scope.variables.push(var.clone());
+        sub_function.variables.push(var);
+    }
 
-                let dec = FunctionDeclaration {
-                    name: name.clone(),
-                    parameters,
-                    return_type,
-                    implemented: false,
-                };
+    let mut functions = match flatten_tree(
+        children,
+        sub_scope,
+        function_name,
+        &mut sub_function,
+        symbols,
+    ) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    functions.push(sub_function);
+    return Ok(functions);
+}
 
-                match scope.functions_symbol_table.get(&dec) {
-                    Some(..) => return Err(format!("redeclaration of function {}", dec.to_string())),
-                    None => (),
-                };
+struct LiftedLambda {
+    name: String,
+    parameters: Vec<parser::Variable>,
+    return_type: Type,
+    body: parser::Ast,
+    span: Span,
+}
 
-                scope.functions.push(dec.clone());
-                let function_name = build_function_name(scope_name.clone(), &dec);
-                scope.functions_symbol_table.insert(dec.clone(), function_name.clone());
-            },
-            parser::Ast::FunctionCall { name, children } => {
-                let mut types = Vec::<Type>::new();
-                for child in children {
-                    types.push(match calculate_expression_type(child, &scope) {
+/// Replaces the lambdas in `node` with references to the functions they are lifted to.
+/// Lambdas nested in another lambda are lifted when flattening the enclosing one.
+fn lift_lambdas(node: parser::Ast, scope: &Scope, scope_name: &String, lifted: &mut Vec<LiftedLambda>, lambda_count: &mut usize) -> Result<parser::Ast, String> {
+    return match node {
+        parser::Ast::Lambda { parameters, return_type, body, span } => {
+            let parameter_types = match convert_params(&parameters, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+
+            let return_type = match convert_type(&return_type, scope) {
+                Err(e) => return Err(e),
+                Ok(Some(val)) => val,
+                Ok(None) => {
+                    let mut lambda_scope = scope.child();
+                    for (param, typeval) in parameters.iter().zip(parameter_types.iter()) {
+                        lambda_scope.variables.push(Variable { name: param.name.clone(), typeval: typeval.clone() });
+                    }
+                    match calculate_expression_type(&body, &lambda_scope) {
                         Err(e) => return Err(e),
                         Ok(val) => val,
-                    });
-                }
+                    }
+                },
+            };
 
-                let dec = match function_exists(name.as_str(), &types, &scope) {
-                    None => return Err(format!("undefined function {}", name)),
-                    Some(val) => val,
-                };
+            let name = format!("lambda.{}", lambda_count);
+            *lambda_count += 1;
+
+            let dec = FunctionDeclaration {
+                name: name.clone(),
+                parameters: parameter_types,
+                return_type: Some(return_type.clone()),
+                implemented: true,
+            };
+            let function_name = build_function_name(scope_name.clone(), &dec);
+            lifted.push(LiftedLambda { name, parameters, return_type, body: *body, span });
+            Ok(parser::Ast::FunctionReference(function_name))
+        },
+        node @ parser::Ast::FunctionDeclaration { .. } => Ok(node),
+        node => node.map_children(&mut |child| lift_lambdas(child, scope, scope_name, lifted, lambda_count)),
+    };
+}
 
-                let effective_name = match get_function_effective_name(&dec, &scope) {
+/// Replaces the names of the functions called in `expression` with their effective names.
+/// Arrays passed for arrays of unknown size are wrapped in a `Cast`, and `len` of an array becomes its length.
+fn resolve_calls(expression: parser::Ast, scope: &Scope) -> Result<parser::Ast, String> {
+    return match expression {
+        parser::Ast::FunctionCall { name, children, span } => {
+            let mut types = Vec::<Type>::new();
+            for child in &children {
+                types.push(match calculate_expression_type(child, scope) {
                     Err(e) => return Err(e),
                     Ok(val) => val,
-                };
-
-                func_impl.statements.push(parser::Ast::FunctionCall { 
-                    name: effective_name.clone(),
-                    children: children.clone(), 
                 });
-            },
-            parser::Ast::FunctionHeader {..} => return Err(format!("cannot create nested function declarations")),
-            parser::Ast::Assignement { variable, expression } => {
-                let var = match &**variable {
-                    parser::Ast::Variable(var) => var,
-                    _ => return Err(String::from("can only assign value to a variable.")),
-                };
+            }
 
-                let expression_type = match calculate_expression_type(&expression, &scope) {
-                    Ok(t) => t,
+            let dec = match function_exists(name.as_str(), &types, scope) {
+                None if is_array_length(name.as_str(), &types) => return match types[0].count {
+                    Some(count) => Ok(parser::Ast::Int(count as i64)),
+                    None => resolve_calls(parser::Ast::MemberAccess {
+                        record: Box::new(children.into_iter().next().unwrap()),
+                        field: String::from("length"),
+                    }, scope),
+                },
+                None if is_array_release(name.as_str(), &types) => {
+                    let array = match resolve_calls(children.into_iter().next().unwrap(), scope) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    };
+                    let size = types[0].element.as_ref().map_or(0, |element| element.size);
+                    return Ok(parser::Ast::FunctionCall {
+                        name: String::from(runtime::RELEASE_ROUTINE),
+                        children: vec![array, parser::Ast::Int(size as i64)],
+                        span,
+                    });
+                },
+                None if is_map_operation(name.as_str(), &types) => {
+                    let mut resolved_children = Vec::<parser::Ast>::new();
+                    for child in children {
+                        resolved_children.push(match resolve_calls(child, scope) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        });
+                    }
+                    return Ok(parser::Ast::FunctionCall { name: runtime::map_routine(name.as_str(), &types[0]), children: resolved_children, span });
+                },
+                None => return Err(format!("undefined function {}", name)),
+                Some(val) => val,
+            };
+
+            let effective_name = match get_function_effective_name(&dec, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+
+            let mut resolved_children = Vec::<parser::Ast>::new();
+            for ((child, argument), parameter) in children.into_iter().zip(types.iter()).zip(dec.parameters.iter()) {
+                let child = match resolve_calls(child, scope) {
                     Err(e) => return Err(e),
+                    Ok(val) => val,
                 };
+                resolved_children.push(match argument == parameter {
+                    true => child,
+                    false => parser::Ast::Cast { child: Box::new(child), typename: parameter.name.clone() },
+                });
+            }
 
-                match get_variable_type(&var.name, &scope) {
-                    Ok(t) if t != expression_type
-                        => return Err(format!("mismatching type for variable '{}', expected {}, got {}", &var.name, t, expression_type)),
-                    Err(..) =>  {
-                        let new_var = Variable { name: var.name.clone(), typeval: expression_type };
-                        scope.variables.push(new_var.clone());
-                        func_impl.variables.push(new_var);
-                    },
-                    _ => (),
+            Ok(parser::Ast::FunctionCall {
+                name: effective_name,
+                children: resolved_children,
+                span,
+            })
+        },
+        // maps are read and built by the runtime.
+        parser::Ast::ArrayAccess { variable, index } if is_map(&variable, scope) => {
+            let typeval = match get_variable_type(&variable, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            let index = match resolve_calls(*index, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            let map = parser::Ast::Variable(parser::Variable { name: variable, typename: None });
+            Ok(parser::Ast::FunctionCall { name: runtime::map_routine("get", &typeval), children: vec![map, index], span: Span::default() })
+        },
+        parser::Ast::MapValue(entries) => {
+            let mut res = parser::Ast::FunctionCall { name: String::from(runtime::MAP_NEW_ROUTINE), children: Vec::new(), span: Span::default() };
+            if entries.is_empty() {
+                return Ok(res);
+            }
+            let typeval = match calculate_expression_type(&parser::Ast::MapValue(entries.clone()), scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            for (key, value) in entries {
+                let (key, value) = match (resolve_calls(key, scope), resolve_calls(value, scope)) {
+                    (Err(e), _) | (_, Err(e)) => return Err(e),
+                    (Ok(key), Ok(value)) => (key, value),
                 };
+                res = parser::Ast::FunctionCall { name: runtime::map_routine("set", &typeval), children: vec![res, key, value], span: Span::default() };
+            }
+            Ok(res)
+        },
+        expression => expression.map_children(&mut |child| resolve_calls(child, scope)),
+    };
+}
 
-
-                func_impl.statements.push(child.clone());
-            },
-            child => func_impl.statements.push(child.clone()),
+/// Whether the variable `name` holds a map.
+fn is_map(name: &String, scope: &Scope) -> bool {
+    return get_variable_type(name, scope).is_ok_and(|typeval| typeval.map.is_some());
+}
+
+/// Wraps the int operand of the operators mixing ints and floats in a `Cast` to float,
+/// so that backends never convert implicitly.
+fn insert_casts(expression: parser::Ast, scope: &Scope) -> Result<parser::Ast, String> {
+    let expression = match expression.map_children(&mut |child| insert_casts(child, scope)) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    let cast = |operand: Box<parser::Ast>, other: &parser::Ast| -> Result<Box<parser::Ast>, String> {
+        let types = (calculate_expression_type(&operand, scope), calculate_expression_type(other, scope));
+        return match types {
+            (Err(e), _) | (_, Err(e)) => Err(e),
+            (Ok(a), Ok(b)) if a == int_type() && b == float_type()
+                => Ok(Box::new(parser::Ast::Cast { child: operand, typename: String::from("float") })),
+            _ => Ok(operand),
+        };
+    };
+
+    macro_rules! binary {
+        ($variant:ident, $left:expr, $right:expr) => {{
+            let left = match cast($left, &$right) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            let right = match cast($right, &left) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            parser::Ast::$variant { left, right }
+        }};
+    }
+
+    return Ok(match expression {
+        parser::Ast::Addition { left, right } => binary!(Addition, left, right),
+        parser::Ast::Substraction { left, right } => binary!(Substraction, left, right),
+        parser::Ast::Multiplication { left, right } => binary!(Multiplication, left, right),
+        parser::Ast::Division { left, right } => binary!(Division, left, right),
+        parser::Ast::EqualTo { left, right } => binary!(EqualTo, left, right),
+        parser::Ast::NotEqualTo { left, right } => binary!(NotEqualTo, left, right),
+        parser::Ast::GreaterThan { left, right } => binary!(GreaterThan, left, right),
+        parser::Ast::GreaterOrEqual { left, right } => binary!(GreaterOrEqual, left, right),
+        parser::Ast::LowerThan { left, right } => binary!(LowerThan, left, right),
+        parser::Ast::LowerOrEqual { left, right } => binary!(LowerOrEqual, left, right),
+        expression => expression,
+    });
+}
+
+/// Prepares an expression for the backends: constants are replaced, conversions are made explicit, calls are resolved and variables renamed.
+/// Casts are inserted first, types of calls are computed from their original names.
+fn resolve_expression(expression: parser::Ast, scope: &Scope) -> Result<parser::Ast, String> {
+    let expression = match constants::substitute_constants(expression, scope) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let expression = match insert_casts(expression, scope) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return match resolve_calls(expression, scope) {
+        Err(e) => Err(e),
+        Ok(val) => rename_variables(val, scope),
+    };
+}
+
+/// Whether a value of type `value` can be stored in a variable of type `target`,
+/// fixed size arrays accepting shorter array values.
+fn is_assignable(target: &Type, value: &Type) -> bool {
+    return match (target, value) {
+        (Type { element: Some(target_element), count: Some(target_count), .. }, Type { element: Some(value_element), count: Some(value_count), .. })
+            => target_element == value_element && value_count <= target_count,
+        _ => target == value,
+    };
+}
+
+/// Declares a variable with an explicit type (`buf: int[10]`) in the current function.
+/// Declares the variable in `scope`. Its stack slot gets a fresh name when the function already has one
+/// with the same name, as blocks may reuse the names of the variables of other blocks.
+fn add_variable(name: &String, typeval: Type, scope: &mut Scope, func_impl: &mut Function) {
+    let mut storage = name.clone();
+    let mut count = 0;
+    while func_impl.variables.iter().any(|v| v.name == storage) {
+        count += 1;
+        storage = format!("{}_{}", name, count);
+    }
+    if &storage != name {
+        scope.storage.insert(name.clone(), storage.clone());
+    }
+    scope.variables.push(Variable { name: name.clone(), typeval: typeval.clone() });
+    func_impl.variables.push(Variable { name: storage, typeval });
+}
+
+/// Name of the stack slot of the variable visible as `name`.
+fn storage_name(name: &String, scope: &Scope) -> String {
+    return match scope.variable_scopes().iter().find(|scope| scope.variables.iter().any(|v| &v.name == name)) {
+        Some(scope) => scope.storage.get(name).unwrap_or(name).clone(),
+        None => name.clone(),
+    };
+}
+
+/// Declares a variable of the compiler named `__<kind>_<n>`, with `n` the first number giving a name the function
+/// does not use yet. The caller removes it from `scope` once its statements are flattened, for the variables of the
+/// program to keep their names.
+fn add_temporary(kind: &str, typeval: Type, scope: &mut Scope, func_impl: &mut Function) -> String {
+    let mut count = 0;
+    while func_impl.variables.iter().any(|v| v.name == format!("__{}_{}", kind, count)) {
+        count += 1;
+    }
+    let name = format!("__{}_{}", kind, count);
+    add_variable(&name, typeval, scope, func_impl);
+    return name;
+}
+
+/// Temporaries printing the floats of a format whose values have the `types`, declared when there are floats.
+fn float_temporaries(types: &[Type], scope: &mut Scope, func_impl: &mut Function) -> formats::FloatTemporaries {
+    if !types.contains(&float_type()) {
+        return formats::FloatTemporaries { value: String::new(), scale: String::new() };
+    }
+    return formats::FloatTemporaries {
+        value: add_temporary("float", float_type(), scope, func_impl),
+        scale: add_temporary("scale", float_type(), scope, func_impl),
+    };
+}
+
+/// Replaces the names of the variables of `expression` with the names of their stack slots.
+fn rename_variables(expression: parser::Ast, scope: &Scope) -> Result<parser::Ast, String> {
+    return match expression {
+        parser::Ast::Variable(var) => Ok(parser::Ast::Variable(parser::Variable { name: storage_name(&var.name, scope), typename: var.typename })),
+        parser::Ast::ArrayAccess { variable, index } => match rename_variables(*index, scope) {
+            Err(e) => Err(e),
+            Ok(index) => Ok(parser::Ast::ArrayAccess { variable: storage_name(&variable, scope), index: Box::new(index) }),
+        },
+        expression => expression.map_children(&mut |child| rename_variables(child, scope)),
+    };
+}
+
+fn declare_variable(var: &parser::Variable, scope: &mut Scope, func_impl: &mut Function) -> Result<(), String> {
+    let typeval = match &var.typename {
+        None => return Err(format!("missing type for variable '{}'", var.name)),
+        Some(parser_type) => match convert_parser_type(parser_type, scope) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        },
+    };
+
+    match get_local_variable_type(&var.name, scope) {
+        Some(t) if t != typeval => return Err(format!("redeclaration of variable '{}' with type {}, previously {}", var.name, typeval, t)),
+        Some(..) => return Ok(()),
+        None => (),
+    };
+
+    add_variable(&var.name, typeval, scope, func_impl);
+    return Ok(());
+}
+
+/// Flattens the statements of a block, nested functions are appended to `functions`
+/// and the statements to keep in the current function are returned.
+fn flatten_statements(children: &[parser::Ast], scope: &mut Scope, scope_name: &String, func_impl: &mut Function, symbols: &mut Symbols, lambda_count: &mut usize, functions: &mut Vec<Function>) -> Result<Vec<parser::Ast>, String> {
+    let mut statements = Vec::<parser::Ast>::new();
+    for child in children {
+        let mut lambdas = Vec::<LiftedLambda>::new();
+        let child = match lift_lambdas(child.clone(), scope, scope_name, &mut lambdas, lambda_count) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+
+        for lambda in lambdas {
+            let body = vec![parser::Ast::ReturnStatement { value: Some(Box::new(lambda.body)), span: lambda.span }];
+            let function = FunctionParts {
+                name: &lambda.name,
+                children: &body,
+                parameters: &lambda.parameters,
+                return_type: Some(lambda.return_type),
+                symbol: None,
+            };
+            match flatten_function(function, scope, scope_name, symbols) {
+                Err(e) => return Err(e),
+                Ok(lambda_functions) => functions.extend(lambda_functions),
+            };
         }
 
+        match child {
+            parser::Ast::FunctionDeclaration { name, children, parameters, return_type, symbol, .. }
+            => {
+                let return_type = match convert_type(&return_type, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+
+                let function = FunctionParts {
+                    name: &name,
+                    children: &children,
+                    parameters: &parameters,
+                    return_type,
+                    symbol,
+                };
+                match flatten_function(function, scope, scope_name, symbols) {
+                    Err(e) => return Err(e),
+                    Ok(sub_functions) => functions.extend(sub_functions),
+                };
+            },
+            parser::Ast::FunctionHeader { name, parameters, return_type, symbol, .. }
+            if scope.is_global() => {
+                let parameters = match convert_params(&parameters, scope) {
+                    Ok(val) => val,
+                    Err(e) => return Err(e),
+                };
+
+                let return_type = match convert_type(&return_type, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+
+                let dec = FunctionDeclaration {
+                    name: name.clone(),
+                    parameters,
+                    return_type,
+                    implemented: false,
+                };
+
+                // repeating a header, or declaring a function implemented earlier, declares nothing new.
+                if let Some((previous, previous_symbol)) = scope.functions_symbol_table.get_key_value(&dec) {
+                    if previous.return_type != dec.return_type {
+                        return Err(format!("invalid return type for function {}", dec));
+                    }
+                    match symbol {
+                        Some(symbol) if &symbol != previous_symbol
+                            => return Err(format!("function {} is declared with the symbol {}, not {}", dec, previous_symbol, symbol)),
+                        _ => continue,
+                    };
+                }
+
+                let function_name = match symbol {
+                    Some(symbol) => symbol,
+                    None => build_function_name(scope_name.clone(), &dec),
+                };
+                if let Some(previous) = symbols.declarations.get(&function_name) {
+                    return Err(format!("functions {} and {} have the same symbol {}", previous, dec, function_name));
+                }
+                scope.functions.push(dec.clone());
+                scope.functions_symbol_table.insert(dec.clone(), function_name.clone());
+                symbols.declarations.insert(function_name, dec);
+            },
+            parser::Ast::FunctionHeader {..} => return Err(String::from("cannot create nested function declarations")),
+            parser::Ast::RecordDeclaration { name, fields, .. } => {
+                if let Err(e) = declare_record(&name, &fields, scope) {
+                    return Err(e);
+                }
+            },
+            parser::Ast::ConstDeclaration { name, value, .. } => {
+                if scope.constants.iter().any(|(n, _)| n == &name) {
+                    return Err(format!("redeclaration of constant '{}'", name));
+                }
+                let value = match constants::evaluate_constant(&value, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                scope.constants.push((name, value));
+            },
+            parser::Ast::Variable(var) if var.typename.is_some() => {
+                if let Err(e) = declare_variable(&var, scope, func_impl) {
+                    return Err(e);
+                }
+            },
+            // `m[key] <- value` assigns the map the entry is stored in, the map being created when `m` holds none.
+            parser::Ast::Assignement { variable, expression, span } if matches!(&*variable, parser::Ast::ArrayAccess { variable, .. } if is_map(variable, scope)) => {
+                let (name, index) = match *variable {
+                    parser::Ast::ArrayAccess { variable, index } => (variable, index),
+                    _ => unreachable!(),
+                };
+                let typeval = match get_variable_type(&name, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let (index, expression) = match (resolve_expression(*index, scope), resolve_expression(*expression, scope)) {
+                    (Err(e), _) | (_, Err(e)) => return Err(e),
+                    (Ok(index), Ok(expression)) => (index, expression),
+                };
+                let map = parser::Ast::Variable(parser::Variable { name: storage_name(&name, scope), typename: None });
+                let expression = parser::Ast::FunctionCall { name: runtime::map_routine("set", &typeval), children: vec![map.clone(), index, expression], span };
+                statements.push(parser::Ast::Assignement { variable: Box::new(map), expression: Box::new(expression), span });
+            },
+            parser::Ast::Assignement { variable, expression, span } => {
+                let variable = match *variable {
+                    parser::Ast::Variable(var) => {
+                        if var.typename.is_some() {
+                            if let Err(e) = declare_variable(&var, scope, func_impl) {
+                                return Err(e);
+                            }
+                        }
+
+                        // types are checked by the semantic analysis, new variables only need to be declared.
+                        if get_variable_type(&var.name, scope).is_err() {
+                            let expression_type = match calculate_expression_type(&expression, scope) {
+                                Ok(t) => t,
+                                Err(e) => return Err(e),
+                            };
+                            add_variable(&var.name, expression_type, scope, func_impl);
+                        }
+                        parser::Ast::Variable(parser::Variable { name: storage_name(&var.name, scope), typename: var.typename })
+                    },
+                    access @ (parser::Ast::ArrayAccess { .. } | parser::Ast::MemberAccess { .. }) => {
+                        match resolve_expression(access, scope) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        }
+                    },
+                    _ => return Err(String::from("can only assign value to a variable.")),
+                };
+
+                let expression = match resolve_expression(*expression, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+
+                statements.push(parser::Ast::Assignement { variable: Box::new(variable), expression: Box::new(expression), span });
+            },
+            parser::Ast::Swap { left, right, span } => {
+                // three moves through a temporary, named after the keyword not to clash with the variables of the program.
+                let typeval = match calculate_expression_type(&left, scope) {
+                    Ok(t) => t,
+                    Err(e) => return Err(e),
+                };
+                let name = String::from("swap");
+                add_variable(&name, typeval, scope, func_impl);
+                let temporary = parser::Ast::Variable(parser::Variable { name: storage_name(&name, scope), typename: None });
+                let left = match resolve_expression(*left, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right = match resolve_expression(*right, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                for (variable, expression) in [(temporary.clone(), left.clone()), (left, right.clone()), (right, temporary)] {
+                    statements.push(parser::Ast::Assignement { variable: Box::new(variable), expression: Box::new(expression), span });
+                }
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, span } => {
+                let condition = match resolve_expression(*condition, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let valid_branch = match flatten_statements(&valid_branch, &mut scope.child(), scope_name, func_impl, symbols, lambda_count, functions) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let invalid_branch = match flatten_statements(&invalid_branch, &mut scope.child(), scope_name, func_impl, symbols, lambda_count, functions) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                statements.push(parser::Ast::Condition { condition: Box::new(condition), valid_branch, invalid_branch, span });
+            },
+            parser::Ast::WhileLoop { condition, children, span } => {
+                let condition = match resolve_expression(*condition, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let children = match flatten_statements(&children, &mut scope.child(), scope_name, func_impl, symbols, lambda_count, functions) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                statements.push(parser::Ast::WhileLoop { condition: Box::new(condition), children, span });
+            },
+            // the cases become a chain of conditions on the value, stored once in a temporary named after the keyword.
+            parser::Ast::Match { value, cases, default, span } => {
+                let typeval = match calculate_expression_type(&value, scope) {
+                    Ok(t) => t,
+                    Err(e) => return Err(e),
+                };
+                let value = match resolve_expression(*value, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let name = String::from("match");
+                // an earlier match of the block may have stored a value of another type.
+                scope.variables.retain(|v| v.name != name);
+                add_variable(&name, typeval.clone(), scope, func_impl);
+                let temporary = parser::Ast::Variable(parser::Variable { name: storage_name(&name, scope), typename: None });
+                statements.push(parser::Ast::Assignement { variable: Box::new(temporary), expression: Box::new(value), span });
+
+                let mut chain = default.unwrap_or_default();
+                for (values, children) in cases.into_iter().rev() {
+                    let conditions = values.into_iter().map(|case| {
+                        let temporary = Box::new(parser::Ast::Variable(parser::Variable { name: name.clone(), typename: None }));
+                        return match typeval == string_type() {
+                            true => parser::Ast::EqualTo {
+                                left: Box::new(parser::Ast::FunctionCall { name: String::from("compare"), children: vec![*temporary, case], span }),
+                                right: Box::new(parser::Ast::Int(0)),
+                            },
+                            false => parser::Ast::EqualTo { left: temporary, right: Box::new(case) },
+                        };
+                    });
+                    let condition = match conditions.reduce(|left, right| parser::Ast::Or { left: Box::new(left), right: Box::new(right) }) {
+                        None => return Err(String::from("missing value for case")),
+                        Some(val) => val,
+                    };
+                    chain = vec![parser::Ast::Condition { condition: Box::new(condition), valid_branch: children, invalid_branch: chain, span }];
+                }
+                match flatten_statements(&chain, scope, scope_name, func_impl, symbols, lambda_count, functions) {
+                    Err(e) => return Err(e),
+                    Ok(val) => statements.extend(val),
+                };
+            },
+            // formatted prints write the pieces of their format one after the other.
+            parser::Ast::FunctionCall { name, children, span } if name == "print" => {
+                let mut types = Vec::<Type>::new();
+                for child in &children {
+                    types.push(match calculate_expression_type(child, scope) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    });
+                }
+                if !is_formatted_print(&name, &types) || function_exists(&name, &types, scope).is_some() {
+                    statements.push(match resolve_expression(parser::Ast::FunctionCall { name, children, span }, scope) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    });
+                    continue;
+                }
+                let pieces = match &children[0] {
+                    parser::Ast::Str(format) => match formats::parse(format) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    },
+                    _ => return Err(String::from("print expects the format as a string literal")),
+                };
+                let temporaries = float_temporaries(&types[1..], scope, func_impl);
+                let lowered = formats::lower(pieces, children.into_iter().skip(1).collect(), &types[1..], &temporaries, span);
+                match flatten_statements(&lowered, scope, scope_name, func_impl, symbols, lambda_count, functions) {
+                    Err(e) => return Err(e),
+                    Ok(val) => statements.extend(val),
+                };
+                scope.variables.retain(|v| v.name != temporaries.value && v.name != temporaries.scale);
+            },
+            // `write a, b` prints its values as the format `{} {}` does.
+            parser::Ast::Write { values, span } => {
+                let mut types = Vec::<Type>::new();
+                for value in &values {
+                    types.push(match calculate_expression_type(value, scope) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    });
+                }
+                let temporaries = float_temporaries(&types, scope, func_impl);
+                let lowered = formats::lower(formats::write_pieces(values.len()), values, &types, &temporaries, span);
+                match flatten_statements(&lowered, scope, scope_name, func_impl, symbols, lambda_count, functions) {
+                    Err(e) => return Err(e),
+                    Ok(val) => statements.extend(val),
+                };
+                scope.variables.retain(|v| v.name != temporaries.value && v.name != temporaries.scale);
+            },
+            // the runtime ends the program, without returning to the caller.
+            parser::Ast::Exit { code, span } => {
+                let call = parser::Ast::FunctionCall { name: String::from("exit"), children: vec![*code], span };
+                statements.push(match resolve_expression(call, scope) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                });
+            },
+            child => statements.push(match resolve_expression(child, scope) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            }),
+        }
     }
 
-    for dec in scope.functions_symbol_table.keys().filter(|f| !f.implemented) {
-        extern_symbols.push(dec.clone());
+    return Ok(statements);
+}
+
+fn flatten_tree(children: &[parser::Ast], scope: Scope, scope_name: String, func_impl: &mut Function, symbols: &mut Symbols) -> Result<Vec<Function>, String> {
+    let mut children_functions = Vec::<Function>::new();
+    let mut scope = scope;
+    let mut lambda_count = 0;
+
+    func_impl.statements = match flatten_statements(children, &mut scope, &scope_name, func_impl, symbols, &mut lambda_count, &mut children_functions) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    // in the order of the declarations, the generated code must not depend on the order of the hash map.
+    for dec in &scope.functions {
+        if let Some((key, name)) = scope.functions_symbol_table.get_key_value(dec) {
+            if !key.implemented && !symbols.extern_symbols.contains(name) {
+                symbols.extern_symbols.push(name.clone());
+            }
+        }
     }
 
     return Ok(children_functions);
 }
 
-fn build_compiler_context(children: &Vec<parser::Ast>) -> CompilerContext {
+/// Moves the variables of `main` out of its stack. A global whose first use is a top level assignment
+/// of a constant starts with that value, the assignment being removed.
+fn extract_globals(main_function: &mut Function) -> Vec<Global> {
+    let mut globals = std::mem::take(&mut main_function.variables)
+        .into_iter()
+        .map(|variable| Global { variable, value: None })
+        .collect::<Vec<Global>>();
+
+    let mut used = Vec::<String>::new();
+    for statement in std::mem::take(&mut main_function.statements) {
+        if let parser::Ast::Assignement { variable, expression, .. } = &statement {
+            let value = match optimize::fold_constants(*expression.clone()) {
+                Ok(value @ (parser::Ast::Int(..) | parser::Ast::Float(..) | parser::Ast::Bool(..))) => Some(value),
+                _ => None,
+            };
+            let global = match &**variable {
+                parser::Ast::Variable(var) if !used.contains(&var.name) => globals.iter_mut().find(|g| g.variable.name == var.name),
+                _ => None,
+            };
+            if let (Some(value), Some(global)) = (value, global) {
+                used.push(global.variable.name.clone());
+                global.value = Some(value);
+                continue;
+            }
+        }
+
+        for node in statement.find_nodes(|n| matches!(n, parser::Ast::Variable(..) | parser::Ast::ArrayAccess { .. })) {
+            match node.node {
+                parser::Ast::Variable(var) => used.push(var.name.clone()),
+                parser::Ast::ArrayAccess { variable, .. } => used.push(variable.clone()),
+                _ => (),
+            };
+        }
+        main_function.statements.push(statement);
+    }
+    return globals;
+}
+
+/// Returning from the top level ends the program successfully.
+fn exit_status(statement: parser::Ast) -> Result<parser::Ast, String> {
+    return match statement {
+        parser::Ast::ReturnStatement { value: None, span } => Ok(parser::Ast::ReturnStatement { value: Some(Box::new(parser::Ast::Int(0))), span }),
+        statement => statement.map_children(&mut exit_status),
+    };
+}
+
+fn build_compiler_context(children: &[parser::Ast], options: &CompilerOptions) -> Result<CompilerContext, String> {
+    let children = exceptions::lower_exceptions(children);
     let mut main_function = Function::new_empty(String::from("main"));
 
-    let mut extern_symbols = Vec::<FunctionDeclaration>::new();
+    let mut symbols = Symbols::default();
 
-    let functions = match flatten_tree(&children, Scope::new_global_scope(), String::new(), &mut main_function, &mut extern_symbols) {
-        Err(e) => panic!("{}", e),
+    let mut functions = match flatten_tree(&children, Scope::new_global_scope(), String::new(), &mut main_function, &mut symbols) {
+        Err(e) => return Err(e),
         Ok(f) => f,
     };
 
-    return CompilerContext {
+    // only the builtins used are imported from the runtime.
+    for (dec, symbol) in runtime::builtin_declarations().into_iter().chain(runtime::allocation_declarations()).chain(runtime::map_declarations()) {
+        let used = functions.iter().chain(std::iter::once(&main_function)).any(|f| f.statements.iter().any(|statement| {
+            return !statement.find_nodes(|n| match n {
+                parser::Ast::FunctionCall { name, .. } | parser::Ast::FunctionReference(name) => name == &symbol,
+                parser::Ast::NewArray { .. } => symbol == runtime::ALLOC_ROUTINE,
+                _ => false,
+            }).is_empty();
+        }));
+        if used && !symbols.declarations.contains_key(&symbol) {
+            symbols.extern_symbols.push(symbol.clone());
+            symbols.declarations.insert(symbol, dec);
+        }
+    }
+
+    // freestanding programs are not linked with the C library.
+    if options.freestanding {
+        if let Some(name) = symbols.extern_symbols.iter().find(|name| runtime::is_math_function(name)) {
+            return Err(format!("{} comes from the C math library, which freestanding programs are not linked with", name));
+        }
+    }
+
+    // failed runtime checks are reported by the runtime.
+    if options.runtime_checks.any() {
+        let (dec, symbol) = runtime::error_declaration();
+        symbols.extern_symbols.push(symbol.clone());
+        symbols.declarations.insert(symbol, dec);
+    }
+
+    let globals = extract_globals(&mut main_function);
+
+    // a program defining `main(): int` starts there, its top level only declares globals.
+    // Otherwise the top level statements make up `main`, whose result is the exit status.
+    match functions.iter().position(|f| f.name == "main") {
+        Some(index) => main_function = functions.remove(index),
+        None => {
+            let mut statements = Vec::<parser::Ast>::new();
+            for statement in std::mem::take(&mut main_function.statements) {
+                match exit_status(statement) {
+                    Err(e) => return Err(e),
+                    Ok(val) => statements.push(val),
+                };
+            }
+            statements.push(parser::Ast::ReturnStatement { value: Some(Box::new(parser::Ast::Int(0))), span: Span::default() });
+            main_function.statements = statements;
+            symbols.declarations.insert(main_function.name.clone(), FunctionDeclaration {
+                name: main_function.name.clone(),
+                parameters: Vec::new(),
+                return_type: Some(int_type()),
+                implemented: true,
+            });
+        },
+    };
+
+    let mut strings = StringTable::default();
+    if options.runtime_checks.division {
+        strings.insert(&runtime::DIVISION_BY_ZERO.to_string());
+    }
+    if options.runtime_checks.overflow {
+        strings.insert(&runtime::INTEGER_OVERFLOW.to_string());
+    }
+    for f in functions.iter().chain(std::iter::once(&main_function)) {
+        for statement in &f.statements {
+            for node in statement.find_nodes(|n| matches!(n, parser::Ast::Str(..))) {
+                if let parser::Ast::Str(val) = node.node {
+                    strings.insert(val);
+                }
+            }
+        }
+    }
+
+    return Ok(CompilerContext {
         functions,
         main_function,
-        extern_symbols,
-    };
+        globals,
+        extern_symbols: symbols.extern_symbols,
+        exported_symbols: symbols.exported_symbols,
+        declarations: symbols.declarations,
+        strings,
+        options: options.clone(),
+    });
+}
+
+/// Deduplicated string literals, stored in `.rodata`.
+#[derive(Default)]
+struct StringTable {
+    values: Vec<String>,
+    indexes: HashMap<String, usize>,
+}
+
+impl StringTable {
+    fn insert(&mut self, value: &String) {
+        if !self.indexes.contains_key(value) {
+            self.indexes.insert(value.clone(), self.values.len());
+            self.values.push(value.clone());
+        }
+    }
+
+    fn label(&self, value: &String) -> Option<String> {
+        return self.indexes.get(value).map(|index| format!("string.{}", index));
+    }
+}
+
+/// Functions known by the compiler, gathered while flattening the tree.
+#[derive(Default)]
+struct Symbols {
+    /// effective names of the declared but not implemented functions.
+    extern_symbols: Vec<String>,
+    /// symbols of the `no_mangle` functions, visible outside of the module.
+    exported_symbols: Vec<String>,
+    /// declaration of each function, by effective name.
+    declarations: HashMap<String, FunctionDeclaration>,
 }
 
-struct CompilerContext {
+/// Program ready for code generation: flattened functions and the symbols they use.
+pub struct CompilerContext {
     functions: Vec<Function>,
     main_function: Function,
-    extern_symbols: Vec<FunctionDeclaration>,
+    /// variables of the top level, `main` having no local variable.
+    globals: Vec<Global>,
+    extern_symbols: Vec<String>,
+    exported_symbols: Vec<String>,
+    declarations: HashMap<String, FunctionDeclaration>,
+    strings: StringTable,
+    options: CompilerOptions,
 }
 
-fn generate_variable_addresses(variables: &Vec<Variable>, stack_size: u64) -> Result<HashMap<String, u64>, String> {
-    let mut res = HashMap::new();
-
-    let mut current_offset = 0;
+/// Target of the compilation, generating a complete module from the compiled program.
+pub trait Backend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String>;
 
-    for var in variables {
-        current_offset += var.typeval.size;
-        res.insert(var.name.clone(), current_offset);
+    /// Writes the generated module to `out`, backends able to generate it piece by piece override it.
+    fn write(&self, context: &CompilerContext, out: &mut dyn Write) -> Result<(), CompileError> {
+        let code = match self.generate(context) {
+            Err(e) => return Err(codegen_error(e)),
+            Ok(val) => val,
+        };
+        return match out.write_all(code.as_bytes()) {
+            Err(e) => Err(codegen_error(format!("could not write the generated code: {}", e))),
+            Ok(()) => Ok(()),
+        };
     }
+}
 
-    if current_offset == stack_size {
-        return Ok(res);
-    } else {
-        return Err(format!("mismatched stack size, expected {}, got {}", stack_size, current_offset));
-    }
+/// Error of a backend failing to generate or to write the module, which is not located in the source.
+pub(crate) fn codegen_error(message: String) -> CompileError {
+    return CompileError::from(vec![Diagnostic::error(Code::Codegen, message, Span::default())]);
+}
+
+/// Writes a fragment of the generated module to `out`.
+pub(crate) fn write_code(out: &mut dyn Write, code: &str) -> Result<(), String> {
+    return match out.write_all(code.as_bytes()) {
+        Err(e) => Err(format!("could not write the generated code: {}", e)),
+        Ok(()) => Ok(()),
+    };
+}
 
+/// Module written by `write` to a buffer, for the backends writing their module piece by piece to generate it whole.
+pub(crate) fn written_code(write: impl FnOnce(&mut dyn Write) -> Result<(), String>) -> Result<String, String> {
+    let mut res = Vec::<u8>::new();
+    return match write(&mut res) {
+        Err(e) => Err(e),
+        Ok(()) => Ok(String::from_utf8_lossy(&res).into_owned()),
+    };
 }
 
-fn visit_function(func: &Function) -> Result<String, String> {
-    let mut res = String::new();
-    let stack_size = func.stack_size();
+/// Generated module, along with the warnings found in the program.
+pub struct CompilationOutput {
+    pub code: String,
+    pub diagnostics: Vec<Diagnostic>,
+}
 
-    let addresses = match generate_variable_addresses(&func.variables, stack_size) {
-        Err(e) => return Err(e),
-        Ok(v) => v,
+/// Semantic diagnostics of the program at the levels of `options`, failing with all of them when one of them is an error.
+fn check_program<'a>(ast: &'a parser::Ast, options: &CompilerOptions) -> Result<(&'a Vec<parser::Ast>, Vec<Diagnostic>), Vec<Diagnostic>> {
+    let children = match ast {
+        parser::Ast::Global(children) => children,
+        _ => return Err(vec![Diagnostic::error(Code::Syntax, format!("expected a global ast, got {:?}", ast), Span::default())]),
     };
-    println!("{:?}", addresses);
 
-    res.push_str(format!("{}:\n", func.name).as_str());
-    res.push_str("\tpush rbp\n");
-    if stack_size > 0 {
-        res.push_str("\tmov rbp, rsp\n");
-        res.push_str(format!("\tsub rsp, {}\n", stack_size).as_str());
+    let diagnostics = timings::time(Phase::Semantics, || check_with_strictness(ast, options.strict))
+        .into_iter()
+        .filter_map(|d| options.warnings.apply(d))
+        .collect::<Vec<Diagnostic>>();
+    if diagnostics.iter().any(|d| d.is_error()) {
+        return Err(diagnostics);
     }
+    return Ok((children, diagnostics));
+}
 
-    res.push_str("\t; TODO\n");
-
-    if stack_size > 0 {
-        res.push_str("\tmov rsp, rbp\n");
+/// Single program made of files compiled together, the top level statements of each file following those of the previous one.
+/// The files share their globals, and a function declared by a file can be implemented by another one.
+/// Tokenizing the `i`-th file with `lexer::tokenize_file(lines, i)` locates the diagnostics in their file.
+pub fn merge_programs(programs: &[parser::Ast]) -> Result<parser::Ast, Diagnostic> {
+    let mut children = Vec::<parser::Ast>::new();
+    for program in programs {
+        match program {
+            parser::Ast::Global(statements) => children.extend(statements.iter().cloned()),
+            _ => return Err(Diagnostic::error(Code::Syntax, format!("expected a global ast, got {:?}", program), Span::default())),
+        };
     }
-    res.push_str("\tpop rbp\n");
-    res.push_str("\tret\n");
-    return Ok(res);
+    return Ok(parser::Ast::Global(children));
 }
 
-pub fn test(ast: &parser::Ast) {
-    let children = match ast {
-        parser::Ast::Global(children) => children,
-        _ => return,
+/// Checks the program as `compile` does, without generating code. Returns the warnings found in the program,
+/// failing with all the diagnostics when one of them is an error.
+pub fn analyze(ast: &parser::Ast, options: &CompilerOptions) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let (ast, options) = match pragmas::apply(ast, options) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return match check_program(&asserts::lower_asserts(&ast), &options) {
+        Err(e) => Err(e),
+        Ok((_, diagnostics)) => Ok(diagnostics),
     };
+}
 
-    let context = build_compiler_context(children);
+/// Compiles the program as `options` tell.
+pub fn compile(ast: &parser::Ast, options: &CompilerOptions) -> Result<CompilationOutput, Vec<Diagnostic>> {
+    let mut code = Vec::new();
+    return match compile_to(ast, options, &mut code) {
+        Err(e) => Err(e),
+        Ok(diagnostics) => Ok(CompilationOutput { code: String::from_utf8_lossy(&code).into_owned(), diagnostics }),
+    };
+}
 
-    for dec in context.extern_symbols {
-        println!("extern: {}", dec.to_string());
+/// Compiles the program as `options` and the pragmas of the program tell, writing the generated module to `out`
+/// through a buffer. Returns the warnings found in the program.
+pub fn compile_to(ast: &parser::Ast, options: &CompilerOptions, out: &mut dyn Write) -> Result<Vec<Diagnostic>, Vec<Diagnostic>> {
+    let (ast, options) = match pragmas::apply(ast, options) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let options = &options;
+    if !Target::all().contains(&options.target) {
+        let message = format!("the {} target is not supported by this build", options.target.name());
+        return Err(vec![Diagnostic::error(Code::Unsupported, message, Span::default()).with_note("the llvm target needs the llvm feature")]);
+    }
+    if let Some(platform) = options.platform.filter(|platform| !platform.supports(options.target)) {
+        let message = format!("the {} target does not generate code for {}", options.target.name(), platform.name());
+        let targets = Target::all().into_iter().filter(|target| platform.supports(*target)).map(|target| target.name()).collect::<Vec<&str>>();
+        let note = format!("{} is supported by the {} targets", platform.name(), targets.join(", "));
+        return Err(vec![Diagnostic::error(Code::Unsupported, message, Span::default()).with_note(&note)]);
+    }
+    if options.runtime_checks.any() && !options.target.supports_runtime_checks() {
+        let message = format!("runtime checks are not supported by the {} target", options.target.name());
+        return Err(vec![Diagnostic::error(Code::Unsupported, message, Span::default()).with_note("runtime checks are supported by the nasm, gas and ir targets")]);
     }
+    if options.emit == Emit::SourceMap && options.target.assembly_syntax().is_none() {
+        let message = format!("source maps are not supported by the {} target", options.target.name());
+        return Err(vec![Diagnostic::error(Code::Unsupported, message, Span::default()).with_note("source maps are supported by the nasm and gas targets")]);
+    }
+    if options.stack_budget.is_some() && !options.target.is_generated_from_ir() {
+        let message = format!("stack budgets are not supported by the {} target", options.target.name());
+        return Err(vec![Diagnostic::error(Code::Unsupported, message, Span::default()).with_note("stack budgets are supported by the nasm, gas and ir targets")]);
+    }
+    if options.freestanding && !options.target.supports_freestanding() {
+        let message = format!("freestanding programs are not supported by the {} target", options.target.name());
+        return Err(vec![Diagnostic::error(Code::Unsupported, message, Span::default()).with_note("freestanding programs are supported by the nasm and gas targets")]);
+    }
+    let ast = asserts::lower_asserts(&ast);
+    let (children, diagnostics) = match check_program(&ast, options) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    // the errors left to the later stages are not located in the source.
+    let fail = |message: String| {
+        let mut res = diagnostics.clone();
+        res.push(Diagnostic::error(Code::Codegen, message, Span::default()));
+        return res;
+    };
 
-    for f in context.functions {
-        match visit_function(&f) {
-            Err(e) => panic!("{}", e),
-            Ok(val) => println!("{}", val),
+    let mut context = match timings::time(Phase::Codegen, || build_compiler_context(children, options)) {
+        Err(e) => return Err(fail(e)),
+        Ok(val) => val,
+    };
+    let mut out = BufWriter::new(out);
+    if options.emit == Emit::Symbols {
+        return match write!(out, "{}", symbol_table::build_symbol_table(&context)).and_then(|_| out.flush()) {
+            Err(e) => Err(fail(format!("could not write the symbol table: {}", e))),
+            Ok(()) => Ok(diagnostics),
+        };
+    }
+    if let Err(e) = timings::time(Phase::Optimization, || optimize::optimize(&mut context)) {
+        return Err(fail(e));
+    }
+    let stack_diagnostics = match options.stack_budget {
+        None => Vec::new(),
+        Some(budget) => match stack::check_stack(&context, budget) {
+            Err(e) => return Err(fail(e)),
+            Ok(val) => val.into_iter().filter_map(|d| options.warnings.apply(d)).collect::<Vec<Diagnostic>>(),
+        },
+    };
+    if stack_diagnostics.iter().any(|d| d.is_error()) {
+        return Err(diagnostics.iter().cloned().chain(stack_diagnostics).collect());
+    }
+    // the lines of the optimized code are mapped.
+    if let (Emit::SourceMap, Some(syntax)) = (options.emit, options.target.assembly_syntax()) {
+        let map = match codegen::source_map(&context, syntax) {
+            Err(e) => return Err(fail(e)),
+            Ok(val) => val,
+        };
+        return match write!(out, "{}", map.to_json()).and_then(|_| out.flush()) {
+            Err(e) => Err(fail(format!("could not write the source map: {}", e))),
+            Ok(()) => Ok(diagnostics),
         };
     }
+    let mut infos = Vec::new();
+    if options.verbose && options.target.is_generated_from_ir() {
+        match ir::lower_module(&context) {
+            Err(e) => return Err(fail(e)),
+            Ok(functions) => infos.extend(functions.iter().flat_map(|f| f.decided_branches.iter()).map(decided_branch_info)),
+        };
+    }
+
+    if let Err(e) = timings::time(Phase::Codegen, || options.target.backend().write(&context, &mut out)) {
+        return Err(diagnostics.into_iter().chain(e.diagnostics).collect());
+    }
+    return match out.flush() {
+        Err(e) => Err(fail(format!("could not write the generated code: {}", e))),
+        Ok(()) => Ok(diagnostics.into_iter().chain(stack_diagnostics).chain(infos).collect()),
+    };
+}
+
+/// Reports the branch of `branch` never taken, the IR only knowing the line of its condition.
+fn decided_branch_info(branch: &ir::DecidedBranch) -> Diagnostic {
+    let dead = match (branch.statement, branch.value) {
+        ("while", true) => "the loop only ends by returning",
+        ("while", false) => "the body of the while is never run",
+        (_, true) => "the if always runs its body",
+        (_, false) => "the body of the if is never run",
+    };
+    let message = match branch.value {
+        true => format!("condition always true, {}", dead),
+        false => format!("condition always false, {}", dead),
+    };
+    return Diagnostic::info(Code::DecidedBranch, message, Span { line: branch.line, ..Span::default() });
+}
+
+/// Functions and top level variables of the compiled program.
+pub fn symbol_table(ast: &parser::Ast) -> Result<SymbolTable, Vec<Diagnostic>> {
+    let (ast, options) = match pragmas::apply(ast, &CompilerOptions::default()) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let ast = asserts::lower_asserts(&ast);
+    let (children, diagnostics) = match check_program(&ast, &options) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    return match build_compiler_context(children, &options) {
+        Err(e) => {
+            let mut res = diagnostics;
+            res.push(Diagnostic::error(Code::Codegen, e, Span::default()));
+            Err(res)
+        },
+        Ok(context) => Ok(symbol_table::build_symbol_table(&context)),
+    };
+}
 
-    match visit_function(&context.main_function) {
-        Err(e) => panic!("{}", e),
-        Ok(val) => println!("{}", val),
+/// Transpiles the program to an equivalent C program.
+pub fn to_c(ast: &parser::Ast) -> Result<String, Vec<Diagnostic>> {
+    let options = CompilerOptions { target: Target::C, ..CompilerOptions::default() };
+    return match compile(ast, &options) {
+        Err(e) => Err(e),
+        Ok(output) => Ok(output.code),
     };
 }
@@ -0,0 +1,184 @@
+use super::super::parser;
+use super::CompilerContext;
+
+/// Optimizations applied to the flattened program before code generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum OptLevel {
+    /// the program is generated as written.
+    #[default]
+    O0,
+    /// constant subexpressions are evaluated at compile time,
+    /// the IR also propagates the constant values of variables and removes the branches never taken.
+    O1,
+    /// small leaf functions are also inlined at their call sites.
+    O2,
+}
+
+pub(super) fn optimize(context: &mut CompilerContext) -> Result<(), String> {
+    if context.options.opt_level < OptLevel::O1 {
+        return Ok(());
+    }
+    let checked = context.options.runtime_checks.overflow;
+    for f in context.functions.iter_mut().chain(std::iter::once(&mut context.main_function)) {
+        let statements = std::mem::take(&mut f.statements);
+        for statement in statements {
+            match fold(statement, checked) {
+                Err(e) => return Err(e),
+                Ok(val) => f.statements.push(val),
+            };
+        }
+    }
+    return Ok(());
+}
+
+/// Integers are 32 bits wide once stored, folded values wrap the same way.
+fn int(value: i64) -> parser::Ast {
+    return parser::Ast::Int(value as i32 as i64);
+}
+
+/// Floats are only folded when the result can be written back as a literal.
+fn float(value: f64) -> Option<parser::Ast> {
+    if value.is_finite() {
+        return Some(parser::Ast::Float(value));
+    }
+    return None;
+}
+
+/// Value of a binary operator applied to two literals, `None` when it is left to the runtime.
+/// Int operations overflowing are left to the runtime when they are `checked`.
+fn fold_binary(node: &parser::Ast, left: &parser::Ast, right: &parser::Ast, checked: bool) -> Option<parser::Ast> {
+    return match (node, left, right) {
+        (parser::Ast::Addition {..}, parser::Ast::Int(a), parser::Ast::Int(b)) if checked => (*a as i32).checked_add(*b as i32).map(|v| int(v as i64)),
+        (parser::Ast::Substraction {..}, parser::Ast::Int(a), parser::Ast::Int(b)) if checked => (*a as i32).checked_sub(*b as i32).map(|v| int(v as i64)),
+        (parser::Ast::Multiplication {..}, parser::Ast::Int(a), parser::Ast::Int(b)) if checked => (*a as i32).checked_mul(*b as i32).map(|v| int(v as i64)),
+        (parser::Ast::Addition {..}, parser::Ast::Int(a), parser::Ast::Int(b)) => Some(int(a.wrapping_add(*b))),
+        (parser::Ast::Substraction {..}, parser::Ast::Int(a), parser::Ast::Int(b)) => Some(int(a.wrapping_sub(*b))),
+        (parser::Ast::Multiplication {..}, parser::Ast::Int(a), parser::Ast::Int(b)) => Some(int(a.wrapping_mul(*b))),
+        // division by zero and overflows trap at runtime.
+        (parser::Ast::Division {..}, parser::Ast::Int(a), parser::Ast::Int(b)) => (*a as i32).checked_div(*b as i32).map(|v| int(v as i64)),
+        (parser::Ast::Modulo {..}, parser::Ast::Int(a), parser::Ast::Int(b)) => (*a as i32).checked_rem(*b as i32).map(|v| int(v as i64)),
+        (parser::Ast::Addition {..}, parser::Ast::Float(a), parser::Ast::Float(b)) => float(a + b),
+        (parser::Ast::Substraction {..}, parser::Ast::Float(a), parser::Ast::Float(b)) => float(a - b),
+        (parser::Ast::Multiplication {..}, parser::Ast::Float(a), parser::Ast::Float(b)) => float(a * b),
+        (parser::Ast::Division {..}, parser::Ast::Float(a), parser::Ast::Float(b)) => float(a / b),
+        (parser::Ast::EqualTo {..}, parser::Ast::Bool(a), parser::Ast::Bool(b)) => Some(parser::Ast::Bool(a == b)),
+        (parser::Ast::NotEqualTo {..}, parser::Ast::Bool(a), parser::Ast::Bool(b)) => Some(parser::Ast::Bool(a != b)),
+        (_, parser::Ast::Int(a), parser::Ast::Int(b)) => compare(node, (*a as i32).partial_cmp(&(*b as i32))),
+        (_, parser::Ast::Float(a), parser::Ast::Float(b)) => compare(node, a.partial_cmp(b)),
+        _ => None,
+    };
+}
+
+/// Value of a comparison given the ordering of its operands, unordered floats (NaN) compare false.
+fn compare(node: &parser::Ast, ordering: Option<std::cmp::Ordering>) -> Option<parser::Ast> {
+    let value = match node {
+        parser::Ast::EqualTo {..} => ordering.is_some_and(|o| o.is_eq()),
+        parser::Ast::NotEqualTo {..} => ordering.is_none_or(|o| o.is_ne()),
+        parser::Ast::GreaterThan {..} => ordering.is_some_and(|o| o.is_gt()),
+        parser::Ast::GreaterOrEqual {..} => ordering.is_some_and(|o| o.is_ge()),
+        parser::Ast::LowerThan {..} => ordering.is_some_and(|o| o.is_lt()),
+        parser::Ast::LowerOrEqual {..} => ordering.is_some_and(|o| o.is_le()),
+        _ => return None,
+    };
+    return Some(parser::Ast::Bool(value));
+}
+
+/// Replaces the constant subexpressions of `node` by their value, `2 * 3 + 1` becomes `7`.
+pub(super) fn fold_constants(node: parser::Ast) -> Result<parser::Ast, String> {
+    return fold(node, false);
+}
+
+fn fold(node: parser::Ast, checked: bool) -> Result<parser::Ast, String> {
+    let node = match node.map_children(&mut |child| fold(child, checked)) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    let folded = match &node {
+        parser::Ast::UnaryPlus { child } => match **child {
+            parser::Ast::Int(..) | parser::Ast::Float(..) => Some(*child.clone()),
+            _ => None,
+        },
+        parser::Ast::UnaryMinus { child } => match **child {
+            parser::Ast::Int(val) if checked => (val as i32).checked_neg().map(|v| int(v as i64)),
+            parser::Ast::Int(val) => Some(int(val.wrapping_neg())),
+            parser::Ast::Float(val) => Some(parser::Ast::Float(-val)),
+            _ => None,
+        },
+        parser::Ast::Cast { child, typename } => match (&**child, typename.as_str()) {
+            (parser::Ast::Int(val), "float") => Some(parser::Ast::Float(*val as f64)),
+            // out of range conversions are left to the runtime.
+            (parser::Ast::Float(val), "int") if val.trunc() >= i32::MIN as f64 && val.trunc() <= i32::MAX as f64
+                => Some(parser::Ast::Int(*val as i64)),
+            _ => None,
+        },
+        parser::Ast::Addition { left, right }
+        | parser::Ast::Substraction { left, right }
+        | parser::Ast::Multiplication { left, right }
+        | parser::Ast::Division { left, right }
+        | parser::Ast::Modulo { left, right }
+        | parser::Ast::EqualTo { left, right }
+        | parser::Ast::NotEqualTo { left, right }
+        | parser::Ast::GreaterThan { left, right }
+        | parser::Ast::GreaterOrEqual { left, right }
+        | parser::Ast::LowerThan { left, right }
+        | parser::Ast::LowerOrEqual { left, right } => fold_binary(&node, left, right, checked),
+        // a constant left operand decides whether the right one is evaluated.
+        parser::Ast::And { left, right } => match **left {
+            parser::Ast::Bool(true) => Some(*right.clone()),
+            parser::Ast::Bool(false) => Some(parser::Ast::Bool(false)),
+            _ => None,
+        },
+        parser::Ast::Or { left, right } => match **left {
+            parser::Ast::Bool(true) => Some(parser::Ast::Bool(true)),
+            parser::Ast::Bool(false) => Some(*right.clone()),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    return Ok(match folded {
+        Some(val) => val,
+        None => node,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fold;
+    use crate::parser::{self, to_source};
+
+    /// Source of the statements of `source` before and after folding them at -O1.
+    fn snapshot(source: &str, checked: bool) -> (String, String) {
+        let before = match crate::parse_str(source) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let after = match before.clone() {
+            parser::Ast::Global(children) => parser::Ast::Global(children.into_iter().map(|child| fold(child, checked).unwrap()).collect()),
+            ast => panic!("expected a program, got {:?}", ast),
+        };
+        return (to_source(&before), to_source(&after));
+    }
+
+    #[test]
+    fn folds_arithmetic() {
+        assert_eq!(snapshot("x <- 2 * 3 + 1\n", false), (String::from("x <- 2 * 3 + 1\n"), String::from("x <- 7\n")));
+        assert_eq!(snapshot("x <- y + 2 * 3\n", false), (String::from("x <- y + 2 * 3\n"), String::from("x <- y + 6\n")));
+        assert_eq!(snapshot("x <- 1.5 * 2.0 > 2.5\n", false), (String::from("x <- 1.5 * 2.0 > 2.5\n"), String::from("x <- true\n")));
+    }
+
+    #[test]
+    fn folds_constant_left_operand() {
+        assert_eq!(snapshot("y <- true and x\n", false), (String::from("y <- true and x\n"), String::from("y <- x\n")));
+        assert_eq!(snapshot("y <- false and x\n", false), (String::from("y <- false and x\n"), String::from("y <- false\n")));
+        assert_eq!(snapshot("y <- x and true\n", false), (String::from("y <- x and true\n"), String::from("y <- x and true\n")));
+    }
+
+    #[test]
+    fn leaves_traps_to_runtime() {
+        assert_eq!(snapshot("x <- 1 / 0\n", false).1, "x <- 1 / 0\n");
+        assert_eq!(snapshot("x <- 2147483647 + 1\n", false).1, "x <- -2147483648\n");
+        assert_eq!(snapshot("x <- 2147483647 + 1\n", true).1, "x <- 2147483647 + 1\n");
+    }
+}
@@ -0,0 +1,317 @@
+use super::{Backend, CBackend, IrBackend, JsBackend, OptLevel, PythonBackend, RiscVBackend, WasmBackend, X86Backend};
+use super::syntax::AsmSyntax;
+use super::super::diagnostic::{Code, Diagnostic, Severity};
+
+/// Language or assembly generated by the compiler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    /// x86-64 assembly for nasm.
+    #[default]
+    Nasm,
+    /// x86-64 assembly for the GNU assembler.
+    Gas,
+    RiscV,
+    /// WebAssembly text format.
+    Wasm,
+    C,
+    Python,
+    Js,
+    /// three-address code the x86 backends are generated from.
+    Ir,
+    /// LLVM textual IR, only available with the `llvm` feature.
+    Llvm,
+}
+
+#[cfg(feature = "llvm")]
+fn llvm_backend() -> Box<dyn Backend> {
+    return Box::new(super::LlvmBackend);
+}
+
+/// `compile_to` rejects the targets missing from `Target::all`.
+#[cfg(not(feature = "llvm"))]
+fn llvm_backend() -> Box<dyn Backend> {
+    unreachable!("built without the llvm feature");
+}
+
+impl Target {
+    /// Every target this build supports.
+    pub fn all() -> Vec<Target> {
+        let mut res = vec![Target::Nasm, Target::Gas, Target::RiscV, Target::Wasm, Target::C, Target::Python, Target::Js, Target::Ir];
+        if cfg!(feature = "llvm") {
+            res.push(Target::Llvm);
+        }
+        return res;
+    }
+
+    /// Name of the target on the command line.
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Target::Nasm => "nasm",
+            Target::Gas => "gas",
+            Target::RiscV => "riscv",
+            Target::Wasm => "wasm",
+            Target::C => "c",
+            Target::Python => "python",
+            Target::Js => "js",
+            Target::Ir => "ir",
+            Target::Llvm => "llvm",
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Target> {
+        return Target::all().into_iter().find(|target| target.name() == name);
+    }
+
+    /// Whether the code generated for the target can check its operations at runtime.
+    pub fn supports_runtime_checks(&self) -> bool {
+        return self.is_generated_from_ir();
+    }
+
+    /// Syntax of the x86-64 assembly generated for the target, `None` for the other targets.
+    pub fn assembly_syntax(&self) -> Option<AsmSyntax> {
+        return match self {
+            Target::Nasm => Some(AsmSyntax::Intel),
+            Target::Gas => Some(AsmSyntax::Att),
+            _ => None,
+        };
+    }
+
+    /// Whether the target can build executables started by the runtime, without the C library.
+    pub fn supports_freestanding(&self) -> bool {
+        return matches!(self, Target::Nasm | Target::Gas);
+    }
+
+    /// Whether the code generated for the target is lowered from the IR, rather than from the flattened program.
+    pub fn is_generated_from_ir(&self) -> bool {
+        return matches!(self, Target::Nasm | Target::Gas | Target::Ir);
+    }
+
+    pub(super) fn backend(&self) -> Box<dyn Backend> {
+        return match self {
+            Target::Nasm => Box::new(X86Backend { syntax: AsmSyntax::Intel }),
+            Target::Gas => Box::new(X86Backend { syntax: AsmSyntax::Att }),
+            Target::RiscV => Box::new(RiscVBackend),
+            Target::Wasm => Box::new(WasmBackend),
+            Target::C => Box::new(CBackend),
+            Target::Python => Box::new(PythonBackend),
+            Target::Js => Box::new(JsBackend),
+            Target::Ir => Box::new(IrBackend),
+            Target::Llvm => llvm_backend(),
+        };
+    }
+
+    /// Whether the generated code runs on any machine, leaving the platform to the compiler or interpreter it is given to.
+    pub fn is_portable(&self) -> bool {
+        return matches!(self, Target::C | Target::Python | Target::Js | Target::Llvm);
+    }
+}
+
+/// Machine, operating system and ABI a program is compiled for, named as on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Platform {
+    X86_64Linux,
+    Wasm32,
+}
+
+impl Platform {
+    pub fn all() -> Vec<Platform> {
+        return vec![Platform::X86_64Linux, Platform::Wasm32];
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Platform::X86_64Linux => "x86_64-linux",
+            Platform::Wasm32 => "wasm32",
+        };
+    }
+
+    pub fn from_name(name: &str) -> Option<Platform> {
+        return Platform::all().into_iter().find(|platform| platform.name() == name);
+    }
+
+    /// Target generating native code for the platform.
+    pub fn native_target(&self) -> Target {
+        return match self {
+            Platform::X86_64Linux => Target::Nasm,
+            Platform::Wasm32 => Target::Wasm,
+        };
+    }
+
+    /// Whether `target` generates code for the platform, the x86 targets following the System V ABI and calling linux.
+    pub fn supports(&self, target: Target) -> bool {
+        return target.is_portable() || match self {
+            Platform::X86_64Linux => target.is_generated_from_ir(),
+            Platform::Wasm32 => target == Target::Wasm,
+        };
+    }
+
+    /// Format of the objects the native code of the platform is assembled to, as `nasm -f` names it.
+    pub fn object_format(&self) -> &'static str {
+        return match self {
+            Platform::X86_64Linux => "elf64",
+            Platform::Wasm32 => "wasm",
+        };
+    }
+
+    /// Triple of the platform in the LLVM modules.
+    pub fn llvm_triple(&self) -> &'static str {
+        return match self {
+            Platform::X86_64Linux => "x86_64-unknown-linux-gnu",
+            Platform::Wasm32 => "wasm32-unknown-unknown",
+        };
+    }
+}
+
+/// What the compilation produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Emit {
+    /// module generated for the target.
+    #[default]
+    Code,
+    /// symbol table of the module, one symbol per line.
+    Symbols,
+    /// JSON source map of the generated assembly, only for the nasm and gas targets.
+    SourceMap,
+}
+
+/// Operations the generated code checks at runtime, rather than running into undefined behaviour.
+/// A failed check prints the line of the operation and exits with status 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RuntimeChecks {
+    /// integer division or modulo by zero.
+    pub division: bool,
+    /// int addition, substraction, multiplication, negation or division overflowing 32 bits.
+    pub overflow: bool,
+}
+
+impl RuntimeChecks {
+    /// Names of the checks on the command line.
+    pub const NAMES: [&'static str; 2] = ["div", "overflow"];
+
+    /// Enables the check named `name`, returning whether it exists.
+    pub fn enable(&mut self, name: &str) -> bool {
+        match name {
+            "div" => self.division = true,
+            "overflow" => self.overflow = true,
+            _ => return false,
+        };
+        return true;
+    }
+
+    pub fn any(&self) -> bool {
+        return self.division || self.overflow;
+    }
+}
+
+/// What the compilation does with a warning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningLevel {
+    /// the warning is not reported.
+    Allow,
+    Warn,
+    /// the warning is reported as an error, failing the compilation.
+    Deny,
+}
+
+/// Level of each warning code, every warning being reported by default.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WarningLevels {
+    /// levels in the order they were set, `None` standing for every code.
+    levels: Vec<(Option<Code>, WarningLevel)>,
+}
+
+impl WarningLevels {
+    /// Sets the level of the warning `name`, a code as `W0001` or `warnings` for all of them, returning whether it exists.
+    pub fn set(&mut self, name: &str, level: WarningLevel) -> bool {
+        let code = match name {
+            "warnings" => None,
+            name => match Code::WARNINGS.iter().find(|code| code.as_str() == name) {
+                None => return false,
+                Some(code) => Some(*code),
+            },
+        };
+        self.levels.push((code, level));
+        return true;
+    }
+
+    /// Level of the warnings of `code`, the last level set for it applying.
+    pub fn level(&self, code: Code) -> WarningLevel {
+        return match self.levels.iter().rev().find(|(c, _)| c.is_none_or(|c| c == code)) {
+            None => WarningLevel::Warn,
+            Some((_, level)) => *level,
+        };
+    }
+
+    /// `diagnostic` as reported at its level, `None` when it is silenced.
+    pub fn apply(&self, diagnostic: Diagnostic) -> Option<Diagnostic> {
+        if diagnostic.severity != Severity::Warning {
+            return Some(diagnostic);
+        }
+        return match self.level(diagnostic.code) {
+            WarningLevel::Allow => None,
+            WarningLevel::Warn => Some(diagnostic),
+            WarningLevel::Deny => {
+                let note = format!("the warning {} is denied by the options", diagnostic.code);
+                Some(Diagnostic { severity: Severity::Error, ..diagnostic }.with_note(&note))
+            },
+        };
+    }
+}
+
+/// How to compile a program, the default options generating nasm assembly without optimizations.
+#[derive(Debug, Clone, Default)]
+pub struct CompilerOptions {
+    pub target: Target,
+    pub opt_level: OptLevel,
+    pub emit: Emit,
+    /// whether reading a variable before assigning it is an error rather than a warning.
+    pub strict: bool,
+    /// warnings silenced or turned into errors.
+    pub warnings: WarningLevels,
+    /// only supported by the targets generated from the IR.
+    pub runtime_checks: RuntimeChecks,
+    /// lines of the source, quoted next to the code generated for them when the target supports it.
+    pub debug_info: Option<Vec<String>>,
+    /// whether to report the conditions the optimizations found constant, as information diagnostics.
+    pub verbose: bool,
+    /// whether the program is started by the `_start` of the runtime rather than by the C library,
+    /// the builtins of the C math library being unavailable.
+    pub freestanding: bool,
+    /// bytes of stack the frames and the calls of the program should fit in, checked when set.
+    pub stack_budget: Option<u64>,
+    /// platform the target must generate code for when set, the target choosing it otherwise.
+    pub platform: Option<Platform>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompilerOptions, Target};
+    use crate::compiler::{compile, CompilationOutput};
+    use crate::diagnostic::Diagnostic;
+
+    fn compile_llvm(source: &str) -> Result<CompilationOutput, Vec<Diagnostic>> {
+        let ast = match crate::parse_str(source) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        return compile(&ast, &CompilerOptions { target: Target::Llvm, ..CompilerOptions::default() });
+    }
+
+    #[cfg(not(feature = "llvm"))]
+    #[test]
+    fn rejects_llvm_without_the_feature() {
+        let codes = match compile_llvm("print(1)\n") {
+            Err(diagnostics) => diagnostics.into_iter().map(|d| d.code).collect::<Vec<crate::diagnostic::Code>>(),
+            Ok(..) => panic!("compiled for the llvm target without the llvm feature"),
+        };
+        assert_eq!(codes, vec![crate::diagnostic::Code::Unsupported]);
+    }
+
+    #[cfg(feature = "llvm")]
+    #[test]
+    fn compiles_llvm_with_the_feature() {
+        if let Err(diagnostics) = compile_llvm("print(1)\n") {
+            panic!("{:?}", diagnostics);
+        }
+    }
+}
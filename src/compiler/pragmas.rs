@@ -0,0 +1,114 @@
+//! Pragmas of the program, `@pragma name value` lines of the top level setting options of the compilation from the
+//! source rather than from the command line:
+//!
+//! ```text
+//! @pragma checks div,overflow
+//! @pragma strict on
+//! @pragma deny W0001
+//! ```
+//!
+//! `checks` takes the runtime checks, or `off`, `strict` takes `on` or `off`, and `allow`, `warn` and `deny` take a
+//! warning code or `warnings` as `-A`, `-W` and `-D` do. The `dialect` of the keywords, `en` or `fr`, is read by the
+//! lexer, the lines following it being written with the keywords of the dialect.
+
+use super::super::diagnostic::{Code, Diagnostic};
+use super::super::lexer::{Span, DIALECTS};
+use super::super::parser::Ast;
+use super::{CompilerOptions, RuntimeChecks, WarningLevel};
+
+/// Names of the pragmas.
+const NAMES: [&str; 6] = ["checks", "strict", "allow", "warn", "deny", "dialect"];
+
+fn invalid(message: String, span: Span) -> Diagnostic {
+    return Diagnostic::error(Code::InvalidPragma, message, span);
+}
+
+/// Runtime checks of the value of the pragma `checks`.
+fn checks(value: &str, span: Span) -> Result<RuntimeChecks, Diagnostic> {
+    let mut checks = RuntimeChecks::default();
+    for check in value.split(',').map(str::trim).filter(|_| value != "off") {
+        if !checks.enable(check) {
+            return Err(invalid(format!("unknown check '{}', expected off or one of {}", check, RuntimeChecks::NAMES.join(", ")), span));
+        }
+    }
+    return Ok(checks);
+}
+
+/// Applies the pragma `name` to `options`.
+fn apply_pragma(options: &mut CompilerOptions, name: &str, value: &str, span: Span) -> Result<(), Diagnostic> {
+    match name {
+        "checks" => {
+            options.runtime_checks = match checks(value, span) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+        },
+        "strict" => {
+            options.strict = match value {
+                "on" => true,
+                "off" => false,
+                _ => return Err(invalid(format!("expected on or off for the pragma strict, got '{}'", value), span)),
+            };
+        },
+        "allow" | "warn" | "deny" => {
+            let level = match name {
+                "allow" => WarningLevel::Allow,
+                "warn" => WarningLevel::Warn,
+                _ => WarningLevel::Deny,
+            };
+            if !options.warnings.set(value, level) {
+                let codes = Code::WARNINGS.iter().map(|code| code.as_str()).collect::<Vec<&str>>();
+                return Err(invalid(format!("unknown warning '{}', expected warnings or one of {}", value, codes.join(", ")), span));
+            }
+        },
+        // checked by the lexer, which tokenized the file in the dialect.
+        "dialect" if DIALECTS.contains(&value) => (),
+        "dialect" => return Err(invalid(format!("unknown dialect '{}', expected {}", value, DIALECTS.join(", ")), span)),
+        name => return Err(invalid(format!("unknown pragma '{}', expected one of {}", name, NAMES.join(", ")), span)),
+    };
+    return Ok(());
+}
+
+/// The program without its pragmas, along with `options` once its pragmas are applied in source order, after the
+/// options given to the compiler. Fails with the pragmas which are unknown or whose value is invalid.
+pub fn apply(ast: &Ast, options: &CompilerOptions) -> Result<(Ast, CompilerOptions), Vec<Diagnostic>> {
+    let children = match ast {
+        Ast::Global(children) => children,
+        _ => return Ok((ast.clone(), options.clone())),
+    };
+    let mut res = options.clone();
+    let mut errors = Vec::new();
+    let mut statements = Vec::with_capacity(children.len());
+    for child in children {
+        match child {
+            Ast::Pragma { name, value, span } => {
+                if let Err(e) = apply_pragma(&mut res, name, value, *span) {
+                    errors.push(e);
+                }
+            },
+            child => statements.push(child.clone()),
+        };
+    }
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    return Ok((Ast::Global(statements), res));
+}
+
+/// Runtime checks of `options` once the `checks` pragmas of the program are applied, for the interpreter running the
+/// program without compiling it. The invalid pragmas, which the analysis reports, are left out.
+pub(crate) fn runtime_checks(ast: &Ast, options: &CompilerOptions) -> RuntimeChecks {
+    let children = match ast {
+        Ast::Global(children) => children.as_slice(),
+        _ => return options.runtime_checks,
+    };
+    let mut res = options.runtime_checks;
+    for child in children {
+        if let Ast::Pragma { name, value, span } = child {
+            if let ("checks", Ok(checks)) = (name.as_str(), checks(value, *span)) {
+                res = checks;
+            }
+        }
+    }
+    return res;
+}
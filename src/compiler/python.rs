@@ -0,0 +1,494 @@
+use std::collections::HashMap;
+
+use super::super::parser;
+use super::c::function_name;
+use super::codegen::{expression_type, is_float};
+use super::{runtime, Backend, CompilerContext, Function, Type};
+
+/// Python 3 source, runnable with `python3`.
+pub struct PythonBackend;
+
+impl Backend for PythonBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return visit_module(context);
+    }
+}
+
+/// Integer division and modulo rounding toward zero, like the compiled code.
+const DIVISION_HELPERS: &str = "def _div(a, b):
+    q = abs(a) // abs(b)
+    return -q if (a < 0) != (b < 0) else q
+
+
+def _mod(a, b):
+    return a - b * _div(a, b)
+";
+
+/// Python keywords which are valid variable names in the pseudocode.
+const RESERVED: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break",
+    "class", "continue", "def", "del", "elif", "else", "except", "finally", "for",
+    "from", "global", "if", "import", "in", "is", "lambda", "nonlocal", "not",
+    "or", "pass", "raise", "return", "try", "while", "with", "yield",
+];
+
+fn variable_name(name: &str) -> String {
+    if RESERVED.contains(&name) || name == "main" {
+        return format!("{}_", name);
+    }
+    return name.to_string();
+}
+
+/// Initial value of a variable, fixed size arrays are filled with the initial value of their elements.
+fn default_value(typeval: &Type) -> String {
+    if let (Some(element), Some(count)) = (&typeval.element, typeval.count) {
+        return format!("[{}] * {}", default_value(element), count);
+    }
+    if typeval.element.is_some() {
+        return String::from("[]");
+    }
+    return match typeval.name.as_str() {
+        "int" => String::from("0"),
+        "bool" => String::from("False"),
+        "float" => String::from("0.0"),
+        "str" => String::from("\"\""),
+        _ => String::from("None"),
+    };
+}
+
+fn string_literal(value: &str) -> String {
+    let mut res = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\t' => res.push_str("\\t"),
+            c if c.is_control() => res.push_str(format!("\\x{:02x}", c as u32).as_str()),
+            c => res.push(c),
+        };
+    }
+    res.push('"');
+    return res;
+}
+
+/// Precedence of the python operator of a node, higher binds tighter.
+fn precedence(expression: &parser::Ast) -> u8 {
+    return match expression {
+        parser::Ast::Or {..} => 1,
+        parser::Ast::And {..} => 2,
+        parser::Ast::EqualTo {..}
+        | parser::Ast::NotEqualTo {..}
+        | parser::Ast::GreaterThan {..}
+        | parser::Ast::GreaterOrEqual {..}
+        | parser::Ast::LowerThan {..}
+        | parser::Ast::LowerOrEqual {..} => 3,
+        parser::Ast::Addition {..} | parser::Ast::Substraction {..} => 4,
+        parser::Ast::Multiplication {..} | parser::Ast::Division {..} | parser::Ast::Modulo {..} => 5,
+        parser::Ast::UnaryPlus {..} | parser::Ast::UnaryMinus {..} => 6,
+        _ => 7,
+    };
+}
+
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    variables: HashMap<String, Type>,
+    output: String,
+    depth: usize,
+    uses_division: bool,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    fn emit(&mut self, line: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str("    ");
+        }
+        self.output.push_str(line);
+        self.output.push('\n');
+    }
+
+    /// Operand of a binary operator, comparisons are parenthesized since python chains them.
+    fn visit_operand(&mut self, operand: &parser::Ast, parent: &parser::Ast, is_right: bool) -> Result<String, String> {
+        let value = match self.visit_expression(operand) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let (operand_precedence, parent_precedence) = (precedence(operand), precedence(parent));
+        if operand_precedence < parent_precedence
+            || operand_precedence == parent_precedence && (is_right || operand_precedence == 3) {
+            return Ok(format!("({})", value));
+        }
+        return Ok(value);
+    }
+
+    fn visit_expression(&mut self, expression: &parser::Ast) -> Result<String, String> {
+        return Ok(match expression {
+            parser::Ast::Int(val) => val.to_string(),
+            parser::Ast::Bool(true) => String::from("True"),
+            parser::Ast::Bool(false) => String::from("False"),
+            parser::Ast::Float(val) => format!("{:?}", val),
+            parser::Ast::Str(val) => string_literal(val),
+            // strings and arrays of unknown size only have a length.
+            parser::Ast::MemberAccess { record, .. } => match expression_type(record, &self.variables, self.context) {
+                Err(e) => return Err(e),
+                Ok(typeval) if typeval.is_record() => return Err(String::from("codegen: records are not supported by the python backend")),
+                Ok(_) => match self.visit_expression(record) {
+                    Err(e) => return Err(e),
+                    Ok(val) => format!("len({})", val),
+                },
+            },
+            parser::Ast::Variable(var) => variable_name(&var.name),
+            parser::Ast::ArrayAccess { variable, index } => match self.visit_expression(index) {
+                Err(e) => return Err(e),
+                Ok(index) => format!("{}[{}]", variable_name(variable), index),
+            },
+            parser::Ast::ArrayValue(children) => {
+                let mut values = Vec::<String>::new();
+                for child in children {
+                    match self.visit_expression(child) {
+                        Err(e) => return Err(e),
+                        Ok(val) => values.push(val),
+                    };
+                }
+                format!("[{}]", values.join(", "))
+            },
+            parser::Ast::FunctionReference(name) => function_name(name),
+            parser::Ast::NewArray { size, .. } => match (self.visit_expression(size), expression_type(expression, &self.variables, self.context)) {
+                (Err(e), _) | (_, Err(e)) => return Err(e),
+                (Ok(size), Ok(typeval)) => format!("{}({}, {})", runtime::ALLOC_ROUTINE, size, typeval.element.map_or(0, |element| element.size)),
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                let mut arguments = Vec::<String>::new();
+                for child in children {
+                    match self.visit_expression(child) {
+                        Err(e) => return Err(e),
+                        Ok(val) => arguments.push(val),
+                    };
+                }
+                format!("{}({})", function_name(name), arguments.join(", "))
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_operand(child, expression, false),
+            parser::Ast::UnaryMinus { child } => match self.visit_operand(child, expression, false) {
+                Err(e) => return Err(e),
+                Ok(val) => format!("-{}", val),
+            },
+            // lists already know their length.
+            parser::Ast::Cast { child, typename } if typename.ends_with("[]") => return self.visit_expression(child),
+            // int() truncates toward zero like the compiled code.
+            parser::Ast::Cast { child, typename } => match self.visit_expression(child) {
+                Err(e) => return Err(e),
+                Ok(val) if typename == "float" => format!("float({})", val),
+                Ok(val) => format!("int({})", val),
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right }
+            | parser::Ast::And { left, right }
+            | parser::Ast::Or { left, right } => {
+                let left_type = match expression_type(left, &self.variables, self.context) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let operator = match (expression, is_float(&left_type)) {
+                    (parser::Ast::Division {..}, false) | (parser::Ast::Modulo {..}, false) => return self.visit_integer_division(expression, left, right),
+                    (parser::Ast::Modulo {..}, true) => return Err(String::from("codegen: modulo is not defined on floats")),
+                    (parser::Ast::Addition {..}, _) => "+",
+                    (parser::Ast::Substraction {..}, _) => "-",
+                    (parser::Ast::Multiplication {..}, _) => "*",
+                    (parser::Ast::Division {..}, _) => "/",
+                    (parser::Ast::EqualTo {..}, _) => "==",
+                    (parser::Ast::NotEqualTo {..}, _) => "!=",
+                    (parser::Ast::GreaterThan {..}, _) => ">",
+                    (parser::Ast::GreaterOrEqual {..}, _) => ">=",
+                    (parser::Ast::LowerThan {..}, _) => "<",
+                    (parser::Ast::And {..}, _) => "and",
+                    (parser::Ast::Or {..}, _) => "or",
+                    _ => "<=",
+                };
+                let left = match self.visit_operand(left, expression, false) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right = match self.visit_operand(right, expression, true) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                format!("{} {} {}", left, operator, right)
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        });
+    }
+
+    /// Integer division and modulo go through the helpers, python's `//` rounds toward negative infinity.
+    fn visit_integer_division(&mut self, expression: &parser::Ast, left: &parser::Ast, right: &parser::Ast) -> Result<String, String> {
+        self.uses_division = true;
+        let helper = if matches!(expression, parser::Ast::Division {..}) { "_div" } else { "_mod" };
+        let left = match self.visit_expression(left) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let right = match self.visit_expression(right) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        return Ok(format!("{}({}, {})", helper, left, right));
+    }
+
+    fn visit_block(&mut self, statements: &[parser::Ast]) -> Result<(), String> {
+        self.depth += 1;
+        if statements.is_empty() {
+            self.emit("pass");
+        }
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        self.depth -= 1;
+        return Ok(());
+    }
+
+    /// Emits an `if`, `else if` chains become `elif`.
+    fn visit_condition(&mut self, condition: &parser::Ast, valid_branch: &[parser::Ast], invalid_branch: &[parser::Ast], keyword: &str) -> Result<(), String> {
+        match self.visit_expression(condition) {
+            Err(e) => return Err(e),
+            Ok(val) => self.emit(format!("{} {}:", keyword, val).as_str()),
+        };
+        if let Err(e) = self.visit_block(valid_branch) {
+            return Err(e);
+        }
+        match invalid_branch {
+            [] => (),
+            [parser::Ast::Condition { condition, valid_branch, invalid_branch, .. }] => {
+                return self.visit_condition(condition, valid_branch, invalid_branch, "elif");
+            },
+            statements => {
+                self.emit("else:");
+                if let Err(e) = self.visit_block(statements) {
+                    return Err(e);
+                }
+            },
+        };
+        return Ok(());
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                let target = match self.visit_expression(variable) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                // array values are copied in the fixed size arrays, like in the compiled code.
+                let target = match (&**variable, &**expression) {
+                    (parser::Ast::Variable(..), parser::Ast::ArrayValue(children)) => format!("{}[:{}]", target, children.len()),
+                    _ => target,
+                };
+                match self.visit_expression(expression) {
+                    Err(e) => return Err(e),
+                    Ok(val) => self.emit(format!("{} = {}", target, val).as_str()),
+                };
+            },
+            parser::Ast::ReturnStatement { value: None, .. } => self.emit("return"),
+            parser::Ast::ReturnStatement { value: Some(value), .. } => match self.visit_expression(value) {
+                Err(e) => return Err(e),
+                Ok(val) => self.emit(format!("return {}", val).as_str()),
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                return self.visit_condition(condition, valid_branch, invalid_branch, "if");
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                match self.visit_expression(condition) {
+                    Err(e) => return Err(e),
+                    Ok(val) => self.emit(format!("while {}:", val).as_str()),
+                };
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+            },
+            expression => match self.visit_expression(expression) {
+                Err(e) => return Err(e),
+                Ok(val) => self.emit(val.as_str()),
+            },
+        };
+        return Ok(());
+    }
+}
+
+/// Generates a function, returning its source and whether it uses the division helpers.
+fn visit_function(func: &Function, name: &str, context: &CompilerContext) -> Result<(String, bool), String> {
+    let mut generator = FunctionGenerator {
+        context,
+        // locals shadow the globals.
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        output: String::new(),
+        depth: 0,
+        uses_division: false,
+    };
+
+    let params = func.variables[..func.parameter_count].iter().map(|p| variable_name(&p.name)).collect::<Vec<String>>();
+    generator.emit(format!("def {}({}):", name, params.join(", ")).as_str());
+
+    generator.depth += 1;
+    // globals assigned by the function must be declared, other uses only read them or mutate them in place.
+    let mut assigned = Vec::<String>::new();
+    for statement in &func.statements {
+        for node in statement.find_nodes(|n| matches!(n, parser::Ast::Assignement { .. })) {
+            if let parser::Ast::Assignement { variable, .. } = node.node {
+                if let parser::Ast::Variable(var) = &**variable {
+                    let is_global = context.globals.iter().any(|g| g.variable.name == var.name);
+                    if is_global && !func.variables.iter().any(|v| v.name == var.name) && !assigned.contains(&var.name) {
+                        assigned.push(var.name.clone());
+                    }
+                }
+            }
+        }
+    }
+    if !assigned.is_empty() {
+        let names = assigned.iter().map(|name| variable_name(name)).collect::<Vec<String>>();
+        generator.emit(format!("global {}", names.join(", ")).as_str());
+    }
+    for var in &func.variables[func.parameter_count..] {
+        let line = format!("{} = {}", variable_name(&var.name), default_value(&var.typeval));
+        generator.emit(line.as_str());
+    }
+    generator.depth -= 1;
+
+    // the variables are enough for a valid body.
+    if func.statements.is_empty() && func.variables.len() > func.parameter_count {
+        return Ok((generator.output, false));
+    }
+    if let Err(e) = generator.visit_block(&func.statements) {
+        return Err(e);
+    }
+    return Ok((generator.output, generator.uses_division));
+}
+
+/// Python implementation of a runtime library function, `None` for the other symbols.
+fn runtime_function(symbol: &str) -> Option<&'static str> {
+    return match symbol {
+        "print_int" => Some("def print_int(value):\n    print(value)\n"),
+        "print_str" => Some("def print_str(value):\n    print(value)\n"),
+        "write_int" => Some("def write_int(value):\n    print(value, end=\"\")\n"),
+        "write_str" => Some("def write_str(value):\n    print(value, end=\"\")\n"),
+        "read_int" => Some("def read_int():\n    return int(input())\n"),
+        "str_length" => Some("def str_length(value):\n    return len(value)\n"),
+        "str_substring" => Some("def str_substring(value, start, length):\n    start = min(max(start, 0), len(value))\n    return value[start:start + max(length, 0)]\n"),
+        "str_concat" => Some("def str_concat(a, b):\n    return a + b\n"),
+        "str_compare" => Some("def str_compare(a, b):\n    return (a > b) - (a < b)\n"),
+        "int_to_string" => Some("def int_to_string(value):\n    return str(value)\n"),
+        "int_abs" => Some("def int_abs(value):\n    return abs(value)\n"),
+        "int_min" => Some("def int_min(a, b):\n    return min(a, b)\n"),
+        "int_max" => Some("def int_max(a, b):\n    return max(a, b)\n"),
+        "exit_program" => Some("def exit_program(code):\n    raise SystemExit(code)\n"),
+        "alloc" => Some("def alloc(length, size):\n    return [0] * length\n"),
+        "release" => Some("def release(values, size):\n    values.clear()\n"),
+        "sqrt" => Some("from math import sqrt\n"),
+        "pow" => Some("from math import pow\n"),
+        // the functions of the math module round to an int.
+        "floor" => Some("def floor(value):\n    import math\n    return float(math.floor(value))\n"),
+        "ceil" => Some("def ceil(value):\n    import math\n    return float(math.ceil(value))\n"),
+        "log" => Some("from math import log\n"),
+        "sin" => Some("from math import sin\n"),
+        "cos" => Some("from math import cos\n"),
+        _ => None,
+    };
+}
+
+/// Python implementation of the runtime functions on arrays, which only differ by their element type.
+fn array_function(symbol: &str) -> Option<String> {
+    let (_, operation) = match symbol.split_once("_array_") {
+        None => return None,
+        Some(val) => val,
+    };
+    return match operation {
+        "copy" => Some(format!("def {}(values):\n    return list(values)\n", symbol)),
+        "append" => Some(format!("def {}(values, value):\n    return values + [value]\n", symbol)),
+        "fill" => Some(format!("def {}(values, value):\n    values[:] = [value] * len(values)\n", symbol)),
+        "sort" => Some(format!("def {}(values):\n    values.sort()\n", symbol)),
+        _ => None,
+    };
+}
+
+/// Python implementation of the runtime functions on maps, which are dicts, `None` standing for a map never assigned.
+fn map_function(symbol: &str, context: &CompilerContext) -> Option<String> {
+    if symbol == "map_new" {
+        return Some(String::from("def map_new():\n    return {}\n"));
+    }
+    let (_, operation) = match symbol.split_once("_map_") {
+        None => return None,
+        Some(val) => val,
+    };
+    // missing keys read the initial value of the values.
+    let value = context.declarations.get(symbol).and_then(|dec| dec.return_type.as_ref()).map_or(String::new(), default_value);
+    return match operation {
+        "get" => Some(format!("def {}(values, key):\n    return {} if values is None else values.get(key, {})\n", symbol, value, value)),
+        "set" => Some(format!("def {}(values, key, value):\n    values = {{}} if values is None else values\n    values[key] = value\n    return values\n", symbol)),
+        "contains" => Some(format!("def {}(values, key):\n    return values is not None and key in values\n", symbol)),
+        "remove" => Some(format!("def {}(values, key):\n    if values is not None:\n        values.pop(key, None)\n", symbol)),
+        _ => None,
+    };
+}
+
+fn visit_module(context: &CompilerContext) -> Result<String, String> {
+    let mut functions = Vec::<String>::new();
+    let mut uses_division = false;
+
+    for name in &context.extern_symbols {
+        if let Some(source) = runtime_function(name).map(String::from).or_else(|| array_function(name)).or_else(|| map_function(name, context)) {
+            functions.push(source);
+            continue;
+        }
+        let dec = match context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec,
+        };
+        functions.push(format!(
+            "def {}(*args):\n    raise NotImplementedError(\"extern function {}\")\n",
+            function_name(name),
+            dec.name,
+        ));
+    }
+
+    for f in context.functions.iter().chain(std::iter::once(&context.main_function)) {
+        let name = if std::ptr::eq(f, &context.main_function) { String::from("main") } else { function_name(&f.name) };
+        match visit_function(f, name.as_str(), context) {
+            Err(e) => return Err(e),
+            Ok((source, division)) => {
+                functions.push(source);
+                uses_division |= division;
+            },
+        };
+    }
+
+    let mut res = String::new();
+    let mut generator = FunctionGenerator { context, variables: HashMap::new(), output: String::new(), depth: 0, uses_division: false };
+    for global in &context.globals {
+        let value = match &global.value {
+            None => default_value(&global.variable.typeval),
+            Some(value) => match generator.visit_expression(value) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            },
+        };
+        res.push_str(format!("{} = {}\n", variable_name(&global.variable.name), value).as_str());
+    }
+    if !context.globals.is_empty() {
+        res.push_str("\n\n");
+    }
+    if uses_division {
+        functions.insert(0, String::from(DIVISION_HELPERS));
+    }
+    res.push_str(functions.join("\n\n").as_str());
+    res.push_str("\n\nif __name__ == \"__main__\":\n    raise SystemExit(main())\n");
+    return Ok(res);
+}
@@ -0,0 +1,662 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::super::parser;
+use super::codegen::{cast_type, expression_type, generate_variable_addresses, is_float};
+use super::syntax;
+use super::super::error::CompileError;
+use super::{codegen_error, write_code, written_code, Backend, CompilerContext, Function, Type, bool_type};
+
+/// Number of argument registers of each kind (`a0`-`a7`, `fa0`-`fa7`) in the LP64D ABI.
+const ARGUMENT_REGISTERS: usize = 8;
+
+/// Largest frame addressable with the 12 bits immediates of `addi`/`ld`/`sd`.
+const MAX_FRAME_SIZE: u64 = 2032;
+
+/// RV64 assembly, following the LP64D calling convention.
+pub struct RiscVBackend;
+
+impl Backend for RiscVBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return written_code(|out| visit_module(context, out));
+    }
+
+    /// Writes the functions one at a time.
+    fn write(&self, context: &CompilerContext, out: &mut dyn Write) -> Result<(), CompileError> {
+        return match visit_module(context, out) {
+            Err(e) => Err(codegen_error(e)),
+            Ok(()) => Ok(()),
+        };
+    }
+}
+
+fn align_16(size: u64) -> u64 {
+    return size.div_ceil(16) * 16;
+}
+
+/// Where an argument is passed: register index, or position in the caller's stack arguments.
+/// Floats go to integer registers once the float ones are used.
+#[derive(Clone, Copy)]
+enum ArgumentLocation {
+    Integer(usize),
+    Float(usize),
+    Stack(usize),
+}
+
+fn argument_locations(types: &Vec<Type>) -> Vec<ArgumentLocation> {
+    let mut integer_count = 0;
+    let mut float_count = 0;
+    let mut stack_count = 0;
+    let mut res = Vec::with_capacity(types.len());
+    for typeval in types {
+        if is_float(typeval) && float_count < ARGUMENT_REGISTERS {
+            res.push(ArgumentLocation::Float(float_count));
+            float_count += 1;
+        } else if integer_count < ARGUMENT_REGISTERS {
+            res.push(ArgumentLocation::Integer(integer_count));
+            integer_count += 1;
+        } else {
+            res.push(ArgumentLocation::Stack(stack_count));
+            stack_count += 1;
+        }
+    }
+    return res;
+}
+
+/// Generates the code of a single function.
+/// Like the x86 generator, the result of an expression is left in `a0` (`fa0` for floats)
+/// and intermediate values are pushed on the stack, in 16 bytes slots to keep `sp` aligned.
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    addresses: HashMap<String, u64>,
+    variables: HashMap<String, Type>,
+    output: String,
+    label_count: usize,
+    function_id: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    fn emit(&mut self, instruction: &str) {
+        self.output.push('\t');
+        self.output.push_str(instruction);
+        self.output.push('\n');
+    }
+
+    fn label(&mut self, label: &str) {
+        self.output.push_str(syntax::symbol(label).as_str());
+        self.output.push_str(":\n");
+    }
+
+    fn local_label(&self, name: &str) -> String {
+        return format!(".L{}.{}", self.function_id, name);
+    }
+
+    fn new_label_id(&mut self) -> usize {
+        self.label_count += 1;
+        return self.label_count - 1;
+    }
+
+    /// Address of the variable, `offset` bytes after its start, and the type of the whole variable.
+    /// Locals are below the saved `ra` and `s0`, the address of a global is loaded in `t3`.
+    fn variable(&mut self, name: &String, offset: u64) -> Result<(String, Type), String> {
+        if let (Some(address), Some(typeval)) = (self.addresses.get(name), self.variables.get(name)) {
+            return Ok((format!("-{}(s0)", address + 16 - offset), typeval.clone()));
+        }
+        let context = self.context;
+        return match context.globals.iter().find(|g| &g.variable.name == name) {
+            None => Err(format!("codegen: unknown variable '{}'", name)),
+            Some(global) => {
+                self.emit(format!("la t3, {}", global.label()).as_str());
+                Ok((format!("{}(t3)", offset), global.variable.typeval.clone()))
+            },
+        };
+    }
+
+    /// Computes the address `base(register)` in `dest`.
+    fn load_address(&mut self, dest: &str, address: &str) {
+        if let Some((base, register)) = address.trim_end_matches(')').split_once('(') {
+            self.emit(format!("addi {}, {}, {}", dest, register, base).as_str());
+        }
+    }
+
+    fn expression_type(&self, expression: &parser::Ast) -> Result<Type, String> {
+        return expression_type(expression, &self.variables, self.context);
+    }
+
+    /// Loads the value at `address` in `a0`, or `fa0` for floats.
+    fn load(&mut self, address: &str, typeval: &Type) {
+        if is_float(typeval) {
+            self.emit(format!("fld fa0, {}", address).as_str());
+            return;
+        }
+        match typeval.size {
+            1 => self.emit(format!("lbu a0, {}", address).as_str()),
+            4 => self.emit(format!("lw a0, {}", address).as_str()),
+            _ => self.emit(format!("ld a0, {}", address).as_str()),
+        };
+    }
+
+    /// Stores `a0` (or `fa0` for floats) at `address`.
+    fn store(&mut self, address: &str, typeval: &Type) {
+        if is_float(typeval) {
+            self.emit(format!("fsd fa0, {}", address).as_str());
+            return;
+        }
+        match typeval.size {
+            1 => self.emit(format!("sb a0, {}", address).as_str()),
+            4 => self.emit(format!("sw a0, {}", address).as_str()),
+            _ => self.emit(format!("sd a0, {}", address).as_str()),
+        };
+    }
+
+    fn push_value(&mut self, typeval: &Type) {
+        self.emit("addi sp, sp, -16");
+        if is_float(typeval) {
+            self.emit("fsd fa0, 0(sp)");
+        } else {
+            self.emit("sd a0, 0(sp)");
+        }
+    }
+
+    /// Pops the value pushed by `push_value` in `a0`/`fa0`.
+    fn pop_value(&mut self, typeval: &Type) {
+        if is_float(typeval) {
+            self.emit("fld fa0, 0(sp)");
+        } else {
+            self.emit("ld a0, 0(sp)");
+        }
+        self.emit("addi sp, sp, 16");
+    }
+
+    /// Evaluates the expression in `a0`, or `fa0` for floats.
+    fn visit_expression(&mut self, expression: &parser::Ast) -> Result<Type, String> {
+        match expression {
+            parser::Ast::Int(val) => self.emit(format!("li a0, {}", val).as_str()),
+            parser::Ast::Bool(val) => self.emit(format!("li a0, {}", *val as u8).as_str()),
+            parser::Ast::Float(val) => {
+                self.emit(format!("li a0, 0x{:x}", val.to_bits()).as_str());
+                self.emit("fmv.d.x fa0, a0");
+            },
+            parser::Ast::Variable(var) => {
+                let (address, typeval) = match self.variable(&var.name, 0) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                match typeval.count {
+                    // fixed size arrays are evaluated to their address.
+                    Some(..) => self.load_address("a0", address.as_str()),
+                    None => self.load(address.as_str(), &typeval),
+                };
+            },
+            parser::Ast::ArrayAccess { variable, index } => {
+                let element = match self.visit_element_address(variable, index) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.load("0(t1)", &element);
+                return Ok(element);
+            },
+            parser::Ast::FunctionReference(name) => self.emit(format!("la a0, {}", syntax::symbol(name)).as_str()),
+            parser::Ast::Str(val) => match self.context.strings.label(val) {
+                None => return Err(format!("codegen: missing label for string {:?}", val)),
+                Some(label) => self.emit(format!("la a0, {}", label).as_str()),
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                if let Err(e) = self.visit_call(name, children) {
+                    return Err(e);
+                }
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_expression(child),
+            parser::Ast::UnaryMinus { child } => {
+                let typeval = match self.visit_expression(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if is_float(&typeval) {
+                    self.emit("fneg.d fa0, fa0");
+                } else {
+                    self.emit("neg a0, a0");
+                }
+                return Ok(typeval);
+            },
+            parser::Ast::Cast { child, typename } => {
+                if let Err(e) = self.visit_expression(child) {
+                    return Err(e);
+                }
+                let typeval = match cast_type(typename) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.emit(if is_float(&typeval) { "fcvt.d.w fa0, a0" } else { "fcvt.w.d a0, fa0, rtz" });
+                return Ok(typeval);
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right } => return self.visit_binary(expression, left, right),
+            // the left operand stays in `a0` when it decides the result.
+            parser::Ast::And { left, right } | parser::Ast::Or { left, right } => {
+                let id = self.new_label_id();
+                let end_label = self.local_label(format!("endlogic{}", id).as_str());
+                if let Err(e) = self.visit_expression(left) {
+                    return Err(e);
+                }
+                let branch = if matches!(expression, parser::Ast::And {..}) { "beqz" } else { "bnez" };
+                self.emit(format!("{} a0, {}", branch, end_label).as_str());
+                if let Err(e) = self.visit_expression(right) {
+                    return Err(e);
+                }
+                self.label(end_label.as_str());
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        };
+
+        return self.expression_type(expression);
+    }
+
+    /// Evaluates an arithmetic operation or a comparison, the left operand in `a0`/`fa0` and the right one in `t1`/`ft1`.
+    fn visit_binary(&mut self, expression: &parser::Ast, left: &parser::Ast, right: &parser::Ast) -> Result<Type, String> {
+        let left_type = match self.visit_expression(left) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        self.push_value(&left_type);
+        let right_type = match self.visit_expression(right) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+
+        if left_type != right_type {
+            return Err(format!("codegen: implicit conversion between {} and {} is not supported", left_type, right_type));
+        }
+
+        if is_float(&left_type) {
+            self.emit("fmv.d ft1, fa0");
+            self.pop_value(&left_type);
+            match expression {
+                parser::Ast::Addition {..} => self.emit("fadd.d fa0, fa0, ft1"),
+                parser::Ast::Substraction {..} => self.emit("fsub.d fa0, fa0, ft1"),
+                parser::Ast::Multiplication {..} => self.emit("fmul.d fa0, fa0, ft1"),
+                parser::Ast::Division {..} => self.emit("fdiv.d fa0, fa0, ft1"),
+                parser::Ast::EqualTo {..} => self.emit("feq.d a0, fa0, ft1"),
+                parser::Ast::NotEqualTo {..} => {
+                    self.emit("feq.d a0, fa0, ft1");
+                    self.emit("xori a0, a0, 1");
+                },
+                parser::Ast::LowerThan {..} => self.emit("flt.d a0, fa0, ft1"),
+                parser::Ast::LowerOrEqual {..} => self.emit("fle.d a0, fa0, ft1"),
+                parser::Ast::GreaterThan {..} => self.emit("flt.d a0, ft1, fa0"),
+                parser::Ast::GreaterOrEqual {..} => self.emit("fle.d a0, ft1, fa0"),
+                _ => return Err(String::from("codegen: modulo is not defined on floats")),
+            };
+        } else {
+            self.emit("mv t1, a0");
+            self.pop_value(&left_type);
+            match expression {
+                parser::Ast::Addition {..} => self.emit("add a0, a0, t1"),
+                parser::Ast::Substraction {..} => self.emit("sub a0, a0, t1"),
+                parser::Ast::Multiplication {..} => self.emit("mul a0, a0, t1"),
+                parser::Ast::Division {..} => self.emit("div a0, a0, t1"),
+                parser::Ast::Modulo {..} => self.emit("rem a0, a0, t1"),
+                parser::Ast::EqualTo {..} => {
+                    self.emit("sub a0, a0, t1");
+                    self.emit("seqz a0, a0");
+                },
+                parser::Ast::NotEqualTo {..} => {
+                    self.emit("sub a0, a0, t1");
+                    self.emit("snez a0, a0");
+                },
+                parser::Ast::LowerThan {..} => self.emit("slt a0, a0, t1"),
+                parser::Ast::GreaterThan {..} => self.emit("slt a0, t1, a0"),
+                parser::Ast::LowerOrEqual {..} => {
+                    self.emit("slt a0, t1, a0");
+                    self.emit("xori a0, a0, 1");
+                },
+                _ => {
+                    self.emit("slt a0, a0, t1");
+                    self.emit("xori a0, a0, 1");
+                },
+            };
+        }
+
+        return self.expression_type(expression);
+    }
+
+    fn visit_block(&mut self, statements: &Vec<parser::Ast>) -> Result<(), String> {
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Computes the address of `variable[index]` in `t1`, returning the type of the element.
+    fn visit_element_address(&mut self, variable: &String, index: &parser::Ast) -> Result<Type, String> {
+        // the index is computed first, calls would not preserve the address of a global.
+        if let Err(e) = self.visit_expression(index) {
+            return Err(e);
+        }
+        let (address, typeval) = match self.variable(variable, 0) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let element = match &typeval.element {
+            Some(element) => *element.clone(),
+            None => return Err(format!("codegen: cannot index variable '{}' of type {}", variable, typeval)),
+        };
+
+        if element.size.is_power_of_two() {
+            self.emit(format!("slli t1, a0, {}", element.size.trailing_zeros()).as_str());
+        } else {
+            self.emit(format!("li t1, {}", element.size).as_str());
+            self.emit("mul t1, a0, t1");
+        }
+
+        match typeval.count {
+            Some(..) => self.load_address("t2", address.as_str()),
+            None => self.emit(format!("ld t2, {}", address).as_str()),
+        };
+        self.emit("add t1, t2, t1");
+        return Ok(element);
+    }
+
+    /// Calls `name` following the LP64D calling convention, the result is left in `a0`/`fa0`.
+    fn visit_call(&mut self, name: &String, children: &Vec<parser::Ast>) -> Result<(), String> {
+        let dec = match self.context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec.clone(),
+        };
+
+        let mut types = Vec::<Type>::new();
+        for child in children {
+            let typeval = match self.visit_expression(child) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            self.push_value(&typeval);
+            types.push(typeval);
+        }
+
+        // arguments are on the stack, the last one on top, stack arguments are copied below them.
+        let count = types.len() as u64;
+        let locations = argument_locations(&types);
+        let stack_count = locations.iter().filter(|l| matches!(l, ArgumentLocation::Stack(..))).count() as u64;
+        let extra = align_16(8 * stack_count);
+        if extra > 0 {
+            self.emit(format!("addi sp, sp, -{}", extra).as_str());
+        }
+
+        for (i, location) in locations.iter().enumerate() {
+            let slot = format!("{}(sp)", extra + 16 * (count - 1 - i as u64));
+            match location {
+                ArgumentLocation::Float(register) => self.emit(format!("fld fa{}, {}", register, slot).as_str()),
+                ArgumentLocation::Integer(register) => self.emit(format!("ld a{}, {}", register, slot).as_str()),
+                ArgumentLocation::Stack(position) => {
+                    self.emit(format!("ld t0, {}", slot).as_str());
+                    self.emit(format!("sd t0, {}(sp)", 8 * position).as_str());
+                },
+            };
+        }
+
+        self.emit(format!("call {}", syntax::symbol(name)).as_str());
+        if count > 0 || extra > 0 {
+            self.emit(format!("addi sp, sp, {}", extra + 16 * count).as_str());
+        }
+
+        match &dec.return_type {
+            Some(typeval) if !is_float(typeval) && typeval.size == 4 => self.emit("sext.w a0, a0"),
+            Some(typeval) if typeval.size == 1 => self.emit("andi a0, a0, 0xff"),
+            _ => (),
+        };
+
+        return Ok(());
+    }
+
+    /// Copies the parameters from their registers (or the caller's frame) to their local slots.
+    fn store_parameters(&mut self, func: &Function) -> Result<(), String> {
+        let types = func.variables[..func.parameter_count].iter().map(|p| p.typeval.clone()).collect::<Vec<Type>>();
+        let locations = argument_locations(&types);
+        for (param, location) in func.variables[..func.parameter_count].iter().zip(locations) {
+            let address = match self.variable(&param.name, 0) {
+                Err(e) => return Err(e),
+                Ok((address, _)) => address,
+            };
+            match location {
+                ArgumentLocation::Float(register) => self.emit(format!("fmv.d fa0, fa{}", register).as_str()),
+                ArgumentLocation::Integer(register) if is_float(&param.typeval) => self.emit(format!("fmv.d.x fa0, a{}", register).as_str()),
+                ArgumentLocation::Integer(register) => self.emit(format!("mv a0, a{}", register).as_str()),
+                // the caller's stack arguments start at its stack pointer, which is our frame pointer.
+                ArgumentLocation::Stack(position) => self.load(format!("{}(s0)", 8 * position).as_str(), &param.typeval),
+            };
+            self.store(address.as_str(), &param.typeval);
+        }
+        return Ok(());
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                let name = match &**variable {
+                    parser::Ast::Variable(var) => &var.name,
+                    parser::Ast::ArrayAccess { variable, index } => {
+                        let typeval = match self.visit_expression(expression) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        self.push_value(&typeval);
+                        let element = match self.visit_element_address(variable, index) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        self.pop_value(&element);
+                        self.store("0(t1)", &element);
+                        return Ok(());
+                    },
+                    _ => return Err(String::from("codegen: can only assign value to a variable.")),
+                };
+                let typeval = match self.variables.get(name) {
+                    None => return Err(format!("codegen: unknown variable '{}'", name)),
+                    Some(val) => val.clone(),
+                };
+                // addresses are computed once the value is, calls would not preserve the address of a global.
+                if let (parser::Ast::ArrayValue(children), Some(element)) = (&**expression, &typeval.element) {
+                    for (i, child) in children.iter().enumerate() {
+                        if let Err(e) = self.visit_expression(child) {
+                            return Err(e);
+                        }
+                        let address = match self.variable(name, i as u64 * element.size) {
+                            Err(e) => return Err(e),
+                            Ok((address, _)) => address,
+                        };
+                        self.store(address.as_str(), element);
+                    }
+                    return Ok(());
+                }
+                if let Err(e) = self.visit_expression(expression) {
+                    return Err(e);
+                }
+                let address = match self.variable(name, 0) {
+                    Err(e) => return Err(e),
+                    Ok((address, _)) => address,
+                };
+                self.store(address.as_str(), &typeval);
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                if let Err(e) = self.visit_call(name, children) {
+                    return Err(e);
+                }
+            },
+            parser::Ast::ReturnStatement { value, .. } => {
+                if let Some(value) = value {
+                    if let Err(e) = self.visit_expression(value) {
+                        return Err(e);
+                    }
+                }
+                let label = self.local_label("return");
+                self.emit(format!("j {}", label).as_str());
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                let id = self.new_label_id();
+                let else_label = self.local_label(format!("else{}", id).as_str());
+                let end_label = self.local_label(format!("endif{}", id).as_str());
+                if let Err(e) = self.visit_condition(condition) {
+                    return Err(e);
+                }
+                self.emit(format!("beqz a0, {}", else_label).as_str());
+                if let Err(e) = self.visit_block(valid_branch) {
+                    return Err(e);
+                }
+                if !invalid_branch.is_empty() {
+                    self.emit(format!("j {}", end_label).as_str());
+                }
+                self.label(else_label.as_str());
+                if !invalid_branch.is_empty() {
+                    if let Err(e) = self.visit_block(invalid_branch) {
+                        return Err(e);
+                    }
+                    self.label(end_label.as_str());
+                }
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                let id = self.new_label_id();
+                let loop_label = self.local_label(format!("while{}", id).as_str());
+                let end_label = self.local_label(format!("endwhile{}", id).as_str());
+                self.label(loop_label.as_str());
+                if let Err(e) = self.visit_condition(condition) {
+                    return Err(e);
+                }
+                self.emit(format!("beqz a0, {}", end_label).as_str());
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+                self.emit(format!("j {}", loop_label).as_str());
+                self.label(end_label.as_str());
+            },
+            expression => {
+                if let Err(e) = self.visit_expression(expression) {
+                    return Err(e);
+                }
+            },
+        };
+        return Ok(());
+    }
+
+    /// Evaluates a condition in `a0`, checking it is a bool.
+    fn visit_condition(&mut self, condition: &parser::Ast) -> Result<(), String> {
+        return match self.visit_expression(condition) {
+            Err(e) => Err(e),
+            Ok(val) if val != bool_type() => Err(format!("codegen: condition must be a bool, got {}", val)),
+            _ => Ok(()),
+        };
+    }
+}
+
+fn visit_function(func: &Function, function_id: usize, context: &CompilerContext) -> Result<String, String> {
+    let stack_size = func.stack_size();
+
+    let addresses = match generate_variable_addresses(&func.variables, stack_size) {
+        Err(e) => return Err(e),
+        Ok(v) => v,
+    };
+
+    // saved ra and s0, then the variables.
+    let frame_size = align_16(stack_size + 16);
+    if frame_size > MAX_FRAME_SIZE {
+        return Err(format!("codegen: frame of {} is too large ({} bytes, at most {})", func.name, frame_size, MAX_FRAME_SIZE));
+    }
+
+    let mut generator = FunctionGenerator {
+        context,
+        addresses,
+        // locals shadow the globals.
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        output: String::new(),
+        label_count: 0,
+        function_id,
+    };
+
+    generator.label(func.name.as_str());
+    generator.emit(format!("addi sp, sp, -{}", frame_size).as_str());
+    generator.emit(format!("sd ra, {}(sp)", frame_size - 8).as_str());
+    generator.emit(format!("sd s0, {}(sp)", frame_size - 16).as_str());
+    generator.emit(format!("addi s0, sp, {}", frame_size).as_str());
+
+    if let Err(e) = generator.store_parameters(func) {
+        return Err(e);
+    }
+
+    if let Err(e) = generator.visit_block(&func.statements) {
+        return Err(e);
+    }
+
+    let return_label = generator.local_label("return");
+    generator.label(return_label.as_str());
+    generator.emit(format!("addi sp, s0, -{}", frame_size).as_str());
+    generator.emit(format!("ld ra, {}(sp)", frame_size - 8).as_str());
+    generator.emit(format!("ld s0, {}(sp)", frame_size - 16).as_str());
+    generator.emit(format!("addi sp, sp, {}", frame_size).as_str());
+    generator.emit("ret");
+    return Ok(generator.output);
+}
+
+/// Globals of the `.data` section (those with an initial value) or of the `.bss` section.
+fn visit_globals(context: &CompilerContext, initialized: bool) -> String {
+    let mut res = String::new();
+    for global in context.globals.iter().filter(|g| g.value.is_some() == initialized) {
+        let data = match &global.value {
+            Some(parser::Ast::Int(val)) => format!(".word {}", val),
+            Some(parser::Ast::Float(val)) => format!(".dword 0x{:x}", val.to_bits()),
+            Some(parser::Ast::Bool(val)) => format!(".byte {}", *val as u8),
+            _ => format!(".zero {}", global.variable.typeval.size),
+        };
+        res.push_str(format!(".balign 8\n{}:\n\t{}\n", global.label(), data).as_str());
+    }
+    return res;
+}
+
+/// Writes the module to `out`, each function once it is generated.
+fn visit_module(context: &CompilerContext, out: &mut dyn Write) -> Result<(), String> {
+    let mut res = String::from(".globl main\n");
+    for name in &context.exported_symbols {
+        res.push_str(format!(".globl {}\n", name).as_str());
+    }
+    for name in &context.extern_symbols {
+        res.push_str(format!(".extern {}\n", syntax::symbol(name)).as_str());
+    }
+
+    res.push_str("\n.text\n");
+    for (i, f) in context.functions.iter().chain(std::iter::once(&context.main_function)).enumerate() {
+        match visit_function(f, i, context) {
+            Err(e) => return Err(e),
+            Ok(val) => {
+                res.push('\n');
+                res.push_str(val.as_str());
+            },
+        };
+        if let Err(e) = write_code(out, &res) {
+            return Err(e);
+        }
+        res.clear();
+    }
+
+    res.push_str("\n.data\n");
+    res.push_str(visit_globals(context, true).as_str());
+    res.push_str("\n.section .rodata\n");
+    for value in &context.strings.values {
+        if let Some(label) = context.strings.label(value) {
+            res.push_str(format!("{}:\n\t{}\n", label, syntax::asciz(value)).as_str());
+        }
+    }
+    res.push_str("\n.bss\n");
+    res.push_str(visit_globals(context, false).as_str());
+    return write_code(out, &res);
+}
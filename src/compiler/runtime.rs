@@ -0,0 +1,1147 @@
+use super::syntax::{self, AsmSyntax};
+use super::{FunctionDeclaration, Type, array_type, bool_type, float_type, int_type, map_type, string_type};
+
+/// Function of the runtime library, written in intel syntax with `.name` local labels.
+struct RuntimeFunction {
+    symbol: &'static str,
+    parameters: Vec<Type>,
+    return_type: Option<Type>,
+    body: &'static [&'static str],
+}
+
+/// Prints the integer followed by a new line.
+const PRINT_INT: &[&str] = &[
+    "movsxd rax, edi",
+    "sub rsp, 32",
+    "lea rsi, [rsp + 32]",
+    "dec rsi",
+    "mov byte [rsi], 10",
+    "mov r8, rax",
+    "test rax, rax",
+    "jns .digits",
+    "neg rax",
+    ".digits:",
+    "mov ecx, 10",
+    "xor edx, edx",
+    "div rcx",
+    "add dl, 48",
+    "dec rsi",
+    "mov byte [rsi], dl",
+    "test rax, rax",
+    "jnz .digits",
+    "test r8, r8",
+    "jns .write",
+    "dec rsi",
+    "mov byte [rsi], 45",
+    ".write:",
+    "lea rdx, [rsp + 32]",
+    "sub rdx, rsi",
+    "mov eax, 1",
+    "mov edi, 1",
+    "syscall",
+    "add rsp, 32",
+    "ret",
+];
+
+/// Prints the string followed by a new line.
+const PRINT_STR: &[&str] = &[
+    "movsxd rdx, esi",
+    "mov rsi, rdi",
+    "mov edi, 1",
+    "mov eax, 1",
+    "syscall",
+    "push 10",
+    "mov rsi, rsp",
+    "mov edx, 1",
+    "mov edi, 1",
+    "mov eax, 1",
+    "syscall",
+    "add rsp, 8",
+    "ret",
+];
+
+/// Prints the integer, without a new line.
+const WRITE_INT: &[&str] = &[
+    "movsxd rax, edi",
+    "sub rsp, 32",
+    "lea rsi, [rsp + 32]",
+    "mov r8, rax",
+    "test rax, rax",
+    "jns .digits",
+    "neg rax",
+    ".digits:",
+    "mov ecx, 10",
+    "xor edx, edx",
+    "div rcx",
+    "add dl, 48",
+    "dec rsi",
+    "mov byte [rsi], dl",
+    "test rax, rax",
+    "jnz .digits",
+    "test r8, r8",
+    "jns .write",
+    "dec rsi",
+    "mov byte [rsi], 45",
+    ".write:",
+    "lea rdx, [rsp + 32]",
+    "sub rdx, rsi",
+    "mov eax, 1",
+    "mov edi, 1",
+    "syscall",
+    "add rsp, 32",
+    "ret",
+];
+
+/// Prints the string, without a new line.
+const WRITE_STR: &[&str] = &[
+    "movsxd rdx, esi",
+    "mov rsi, rdi",
+    "mov edi, 1",
+    "mov eax, 1",
+    "syscall",
+    "ret",
+];
+
+/// Reads a line of stdin as an integer, other characters than digits and `-` are ignored.
+const READ_INT: &[&str] = &[
+    "push rbx",
+    "push r12",
+    "sub rsp, 8",
+    "xor ebx, ebx",
+    "xor r12d, r12d",
+    ".read:",
+    "xor eax, eax",
+    "xor edi, edi",
+    "mov rsi, rsp",
+    "mov edx, 1",
+    "syscall",
+    "cmp rax, 1",
+    "jne .done",
+    "movzx eax, byte [rsp]",
+    "cmp eax, 10",
+    "je .done",
+    "cmp eax, 45",
+    "jne .digit",
+    "mov r12d, 1",
+    "jmp .read",
+    ".digit:",
+    "sub eax, 48",
+    "cmp eax, 9",
+    "ja .read",
+    "imul ebx, ebx, 10",
+    "add ebx, eax",
+    "jmp .read",
+    ".done:",
+    "mov eax, ebx",
+    "test r12d, r12d",
+    "jz .end",
+    "neg eax",
+    ".end:",
+    "add rsp, 8",
+    "pop r12",
+    "pop rbx",
+    "ret",
+];
+
+/// Length of the string.
+const STR_LENGTH: &[&str] = &[
+    "mov eax, esi",
+    "ret",
+];
+
+/// Part of the string starting at a byte index, with a number of bytes, both clamped to the string.
+/// The part shares the bytes of the string.
+const STR_SUBSTRING: &[&str] = &[
+    "movsxd rsi, esi",
+    "movsxd rdx, edx",
+    "movsxd rcx, ecx",
+    "xor eax, eax",
+    "test rdx, rdx",
+    "cmovl rdx, rax",
+    "cmp rdx, rsi",
+    "cmovg rdx, rsi",
+    "sub rsi, rdx",
+    "test rcx, rcx",
+    "cmovl rcx, rax",
+    "cmp rcx, rsi",
+    "cmovg rcx, rsi",
+    "mov rax, rdi",
+    "add rax, rdx",
+    "mov edx, ecx",
+    "ret",
+];
+
+/// Maps memory holding the bytes of both strings, one after the other.
+const STR_CONCAT: &[&str] = &[
+    "movsxd rsi, esi",
+    "movsxd rcx, ecx",
+    "push rdi",
+    "push rsi",
+    "push rdx",
+    "push rcx",
+    // one more byte, as nothing can be mapped for two empty strings.
+    "add rsi, rcx",
+    "inc rsi",
+    "xor edi, edi",
+    "mov edx, 3",
+    "mov r10d, 34",
+    "mov r8, -1",
+    "xor r9d, r9d",
+    "mov eax, 9",
+    "syscall",
+    "pop rcx",
+    "pop rdx",
+    "pop rsi",
+    "pop rdi",
+    "xor r8d, r8d",
+    ".first:",
+    "cmp r8, rsi",
+    "je .second",
+    "movzx r9d, byte [rdi + r8*1]",
+    "mov byte [rax + r8*1], r9b",
+    "inc r8",
+    "jmp .first",
+    ".second:",
+    "add rax, rsi",
+    "xor r8d, r8d",
+    ".copy:",
+    "cmp r8, rcx",
+    "je .done",
+    "movzx r9d, byte [rdx + r8*1]",
+    "mov byte [rax + r8*1], r9b",
+    "inc r8",
+    "jmp .copy",
+    ".done:",
+    "sub rax, rsi",
+    "lea rdx, [rsi + rcx*1]",
+    "ret",
+];
+
+/// Compares the bytes of two strings, returning -1, 0 or 1 as the first one sorts before, as or after the second one.
+const STR_COMPARE: &[&str] = &[
+    "movsxd rsi, esi",
+    "movsxd rcx, ecx",
+    "xor r8d, r8d",
+    ".compare:",
+    "cmp r8, rsi",
+    "je .first_end",
+    "cmp r8, rcx",
+    "je .greater",
+    "movzx eax, byte [rdi + r8*1]",
+    "movzx r9d, byte [rdx + r8*1]",
+    "cmp eax, r9d",
+    "jb .lower",
+    "ja .greater",
+    "inc r8",
+    "jmp .compare",
+    ".first_end:",
+    "cmp r8, rcx",
+    "je .equal",
+    ".lower:",
+    "mov eax, -1",
+    "ret",
+    ".greater:",
+    "mov eax, 1",
+    "ret",
+    ".equal:",
+    "xor eax, eax",
+    "ret",
+];
+
+/// Decimal representation of the integer, written at the end of a mapped page.
+const INT_TO_STRING: &[&str] = &[
+    "push rdi",
+    "mov esi, 16",
+    "xor edi, edi",
+    "mov edx, 3",
+    "mov r10d, 34",
+    "mov r8, -1",
+    "xor r9d, r9d",
+    "mov eax, 9",
+    "syscall",
+    "pop rdi",
+    "lea rsi, [rax + 16]",
+    "mov r9, rsi",
+    "movsxd rax, edi",
+    "mov r8, rax",
+    "test rax, rax",
+    "jns .digits",
+    "neg rax",
+    ".digits:",
+    "mov ecx, 10",
+    "xor edx, edx",
+    "div rcx",
+    "add dl, 48",
+    "dec rsi",
+    "mov byte [rsi], dl",
+    "test rax, rax",
+    "jnz .digits",
+    "test r8, r8",
+    "jns .done",
+    "dec rsi",
+    "mov byte [rsi], 45",
+    ".done:",
+    "mov rax, rsi",
+    "mov rdx, r9",
+    "sub rdx, rsi",
+    "ret",
+];
+
+const INT_ABS: &[&str] = &[
+    "mov eax, edi",
+    "neg eax",
+    "cmovl eax, edi",
+    "ret",
+];
+
+const INT_MIN: &[&str] = &[
+    "mov eax, edi",
+    "cmp esi, edi",
+    "cmovl eax, esi",
+    "ret",
+];
+
+const INT_MAX: &[&str] = &[
+    "mov eax, edi",
+    "cmp esi, edi",
+    "cmovg eax, esi",
+    "ret",
+];
+
+/// Maps zeroed memory for `length` elements of `size` bytes, returning the array and its length.
+const ALLOC: &[&str] = &[
+    "push rdi",
+    "movsxd rax, edi",
+    "movsxd rsi, esi",
+    "imul rsi, rax",
+    "xor edi, edi",
+    "mov edx, 3",
+    "mov r10d, 34",
+    "mov r8, -1",
+    "xor r9d, r9d",
+    "mov eax, 9",
+    "syscall",
+    "pop rdx",
+    "ret",
+];
+
+/// Unmaps an array of `length` elements of `size` bytes, given as the array and the element size.
+const RELEASE: &[&str] = &[
+    "movsxd rsi, esi",
+    "movsxd rdx, edx",
+    "imul rsi, rdx",
+    "mov eax, 11",
+    "syscall",
+    "ret",
+];
+
+/// Maps memory for a new length of elements of `size` bytes, given as the array, the element size and the length.
+/// The elements fitting in the new length are copied, the other ones are zeroed.
+const ARRAY_RESIZE: &[&str] = &[
+    "movsxd rsi, esi",
+    "movsxd rdx, edx",
+    "movsxd rcx, ecx",
+    "cmp rsi, rcx",
+    "cmovg rsi, rcx",
+    "imul rsi, rdx",
+    "push rdi",
+    "push rsi",
+    "push rcx",
+    "imul rcx, rdx",
+    // one more byte, as nothing can be mapped for an empty array.
+    "lea rsi, [rcx + 1]",
+    "xor edi, edi",
+    "mov edx, 3",
+    "mov r10d, 34",
+    "mov r8, -1",
+    "xor r9d, r9d",
+    "mov eax, 9",
+    "syscall",
+    "pop rdx",
+    "pop rsi",
+    "pop rdi",
+    "xor r8d, r8d",
+    ".copy:",
+    "cmp r8, rsi",
+    "je .done",
+    "movzx r9d, byte [rdi + r8*1]",
+    "mov byte [rax + r8*1], r9b",
+    "inc r8",
+    "jmp .copy",
+    ".done:",
+    "ret",
+];
+
+const INT_ARRAY_COPY: &[&str] = &["mov edx, 4", "mov ecx, esi", "jmp array_resize"];
+const FLOAT_ARRAY_COPY: &[&str] = &["mov edx, 8", "mov ecx, esi", "jmp array_resize"];
+const BOOL_ARRAY_COPY: &[&str] = &["mov edx, 1", "mov ecx, esi", "jmp array_resize"];
+const STR_ARRAY_COPY: &[&str] = &["mov edx, 16", "mov ecx, esi", "jmp array_resize"];
+
+const INT_ARRAY_APPEND: &[&str] = &[
+    "push rdx",
+    "mov edx, 4",
+    "lea ecx, [rsi + 1]",
+    "call array_resize",
+    "pop rcx",
+    "mov dword [rax + rdx*4 - 4], ecx",
+    "ret",
+];
+
+const FLOAT_ARRAY_APPEND: &[&str] = &[
+    "mov edx, 8",
+    "lea ecx, [rsi + 1]",
+    "call array_resize",
+    "movsd qword [rax + rdx*8 - 8], xmm0",
+    "ret",
+];
+
+const BOOL_ARRAY_APPEND: &[&str] = &[
+    "push rdx",
+    "mov edx, 1",
+    "lea ecx, [rsi + 1]",
+    "call array_resize",
+    "pop rcx",
+    "mov byte [rax + rdx*1 - 1], cl",
+    "ret",
+];
+
+const STR_ARRAY_APPEND: &[&str] = &[
+    "push rdx",
+    "push rcx",
+    "mov edx, 16",
+    "lea ecx, [rsi + 1]",
+    "call array_resize",
+    "pop rcx",
+    "pop r8",
+    "mov r9, rdx",
+    "shl r9, 4",
+    "add r9, rax",
+    "mov qword [r9 - 16], r8",
+    "mov dword [r9 - 8], ecx",
+    "ret",
+];
+
+const INT_ARRAY_FILL: &[&str] = &[
+    "movsxd rsi, esi",
+    "xor eax, eax",
+    ".fill:",
+    "cmp rax, rsi",
+    "je .done",
+    "mov dword [rdi + rax*4], edx",
+    "inc rax",
+    "jmp .fill",
+    ".done:",
+    "ret",
+];
+
+const FLOAT_ARRAY_FILL: &[&str] = &[
+    "movsxd rsi, esi",
+    "xor eax, eax",
+    ".fill:",
+    "cmp rax, rsi",
+    "je .done",
+    "movsd qword [rdi + rax*8], xmm0",
+    "inc rax",
+    "jmp .fill",
+    ".done:",
+    "ret",
+];
+
+const BOOL_ARRAY_FILL: &[&str] = &[
+    "movsxd rsi, esi",
+    "xor eax, eax",
+    ".fill:",
+    "cmp rax, rsi",
+    "je .done",
+    "mov byte [rdi + rax*1], dl",
+    "inc rax",
+    "jmp .fill",
+    ".done:",
+    "ret",
+];
+
+const STR_ARRAY_FILL: &[&str] = &[
+    "movsxd rsi, esi",
+    "shl rsi, 4",
+    "add rsi, rdi",
+    ".fill:",
+    "cmp rdi, rsi",
+    "je .done",
+    "mov qword [rdi], rdx",
+    "mov dword [rdi + 8], ecx",
+    "add rdi, 16",
+    "jmp .fill",
+    ".done:",
+    "ret",
+];
+
+/// Sorts the array in place in ascending order, by insertion.
+const INT_ARRAY_SORT: &[&str] = &[
+    "movsxd rsi, esi",
+    "mov ecx, 1",
+    ".outer:",
+    "cmp rcx, rsi",
+    "jge .done",
+    "mov eax, dword [rdi + rcx*4]",
+    "mov rdx, rcx",
+    ".inner:",
+    "test rdx, rdx",
+    "jz .insert",
+    "mov r8d, dword [rdi + rdx*4 - 4]",
+    "cmp r8d, eax",
+    "jle .insert",
+    "mov dword [rdi + rdx*4], r8d",
+    "dec rdx",
+    "jmp .inner",
+    ".insert:",
+    "mov dword [rdi + rdx*4], eax",
+    "inc rcx",
+    "jmp .outer",
+    ".done:",
+    "ret",
+];
+
+/// Sorts the array in place in ascending order, by insertion.
+const FLOAT_ARRAY_SORT: &[&str] = &[
+    "movsxd rsi, esi",
+    "mov ecx, 1",
+    ".outer:",
+    "cmp rcx, rsi",
+    "jge .done",
+    "movsd xmm0, qword [rdi + rcx*8]",
+    "mov rdx, rcx",
+    ".inner:",
+    "test rdx, rdx",
+    "jz .insert",
+    "movsd xmm1, qword [rdi + rdx*8 - 8]",
+    "ucomisd xmm1, xmm0",
+    "jbe .insert",
+    "movsd qword [rdi + rdx*8], xmm1",
+    "dec rdx",
+    "jmp .inner",
+    ".insert:",
+    "movsd qword [rdi + rdx*8], xmm0",
+    "inc rcx",
+    "jmp .outer",
+    ".done:",
+    "ret",
+];
+
+/// Maps the header of an empty map: its number of entries, its capacity and its entries.
+/// Entries are 32 bytes: the key, the length of a string key, the value and the length of a string value.
+const MAP_NEW: &[&str] = &[
+    "xor edi, edi",
+    "mov esi, 24",
+    "mov edx, 3",
+    "mov r10d, 34",
+    "mov r8, -1",
+    "xor r9d, r9d",
+    "mov eax, 9",
+    "syscall",
+    "ret",
+];
+
+/// Entry of an int key in the map, 0 when it has none.
+const INT_MAP_FIND: &[&str] = &[
+    "xor eax, eax",
+    "test rdi, rdi",
+    "je .done",
+    "movsxd r9, esi",
+    "mov r10, qword [rdi + 16]",
+    "mov r11, qword [rdi]",
+    "shl r11, 5",
+    "add r11, r10",
+    ".find:",
+    "cmp r10, r11",
+    "je .done",
+    "cmp qword [r10], r9",
+    "je .found",
+    "add r10, 32",
+    "jmp .find",
+    ".found:",
+    "mov rax, r10",
+    ".done:",
+    "ret",
+];
+
+/// Entry of a string key in the map, 0 when it has none.
+const STR_MAP_FIND: &[&str] = &[
+    "xor eax, eax",
+    "test rdi, rdi",
+    "je .done",
+    "push rbx",
+    "movsxd rdx, edx",
+    "mov r9, qword [rdi + 16]",
+    "mov r10, qword [rdi]",
+    "shl r10, 5",
+    "add r10, r9",
+    ".find:",
+    "cmp r9, r10",
+    "je .missing",
+    "cmp qword [r9 + 8], rdx",
+    "jne .next",
+    "mov rbx, qword [r9]",
+    "xor r11d, r11d",
+    ".compare:",
+    "cmp r11, rdx",
+    "je .found",
+    "movzx eax, byte [rsi + r11*1]",
+    "cmp al, byte [rbx + r11*1]",
+    "jne .next",
+    "inc r11",
+    "jmp .compare",
+    ".next:",
+    "add r9, 32",
+    "jmp .find",
+    ".missing:",
+    "xor r9d, r9d",
+    ".found:",
+    "mov rax, r9",
+    "pop rbx",
+    ".done:",
+    "ret",
+];
+
+/// Adds an entry at the end of the map, mapping twice its capacity when it is full.
+const MAP_APPEND: &[&str] = &[
+    "mov rax, qword [rdi]",
+    "cmp rax, qword [rdi + 8]",
+    "jne .append",
+    "push rdi",
+    "push rsi",
+    "push rdx",
+    "push rcx",
+    "push r8",
+    "mov rsi, qword [rdi + 8]",
+    "add rsi, rsi",
+    "mov eax, 8",
+    "cmp rsi, rax",
+    "cmovl rsi, rax",
+    "push rsi",
+    "shl rsi, 5",
+    "xor edi, edi",
+    "mov edx, 3",
+    "mov r10d, 34",
+    "mov r8, -1",
+    "xor r9d, r9d",
+    "mov eax, 9",
+    "syscall",
+    "pop r9",
+    "mov rdi, qword [rsp + 32]",
+    "mov rsi, qword [rdi + 16]",
+    "mov rcx, qword [rdi]",
+    "shl rcx, 2",
+    "xor r8d, r8d",
+    ".copy:",
+    "cmp r8, rcx",
+    "je .copied",
+    "mov r10, qword [rsi + r8*8]",
+    "mov qword [rax + r8*8], r10",
+    "inc r8",
+    "jmp .copy",
+    ".copied:",
+    "mov qword [rdi + 16], rax",
+    "mov qword [rdi + 8], r9",
+    "pop r8",
+    "pop rcx",
+    "pop rdx",
+    "pop rsi",
+    "pop rdi",
+    ".append:",
+    "mov rax, qword [rdi]",
+    "inc qword [rdi]",
+    "shl rax, 5",
+    "add rax, qword [rdi + 16]",
+    "ret",
+];
+
+/// Entry of an int key, added when the map has none, returning the entry and the map, created when null.
+const INT_MAP_ENTRY: &[&str] = &[
+    "test rdi, rdi",
+    "jne .find",
+    "push rsi",
+    "call map_new",
+    "mov rdi, rax",
+    "pop rsi",
+    ".find:",
+    "call int_map_find",
+    "test rax, rax",
+    "jne .done",
+    "call map_append",
+    "movsxd rsi, esi",
+    "mov qword [rax], rsi",
+    ".done:",
+    "mov rdx, rdi",
+    "ret",
+];
+
+/// Entry of a string key, added when the map has none, returning the entry and the map, created when null.
+const STR_MAP_ENTRY: &[&str] = &[
+    "test rdi, rdi",
+    "jne .find",
+    "push rsi",
+    "push rdx",
+    "call map_new",
+    "mov rdi, rax",
+    "pop rdx",
+    "pop rsi",
+    ".find:",
+    "call str_map_find",
+    "test rax, rax",
+    "jne .done",
+    "call map_append",
+    "movsxd rdx, edx",
+    "mov qword [rax], rsi",
+    "mov qword [rax + 8], rdx",
+    ".done:",
+    "mov rdx, rdi",
+    "ret",
+];
+
+/// Stores the 8 bytes value of an int key, returning the map.
+const INT_MAP_STORE: &[&str] = &[
+    "push rdx",
+    "call int_map_entry",
+    "pop rcx",
+    "mov qword [rax + 16], rcx",
+    "mov rax, rdx",
+    "ret",
+];
+
+/// Stores the 8 bytes value of a string key, returning the map.
+const STR_MAP_STORE: &[&str] = &[
+    "push rcx",
+    "call str_map_entry",
+    "pop rcx",
+    "mov qword [rax + 16], rcx",
+    "mov rax, rdx",
+    "ret",
+];
+
+const INT_INT_MAP_SET: &[&str] = &["movsxd rdx, edx", "jmp int_map_store"];
+const INT_FLOAT_MAP_SET: &[&str] = &["movq rdx, xmm0", "jmp int_map_store"];
+const INT_BOOL_MAP_SET: &[&str] = &["movzx edx, dl", "jmp int_map_store"];
+const STR_INT_MAP_SET: &[&str] = &["movsxd rcx, ecx", "jmp str_map_store"];
+const STR_FLOAT_MAP_SET: &[&str] = &["movq rcx, xmm0", "jmp str_map_store"];
+const STR_BOOL_MAP_SET: &[&str] = &["movzx ecx, cl", "jmp str_map_store"];
+
+const INT_STR_MAP_SET: &[&str] = &[
+    "push rdx",
+    "push rcx",
+    "call int_map_entry",
+    "pop rcx",
+    "pop r8",
+    "movsxd rcx, ecx",
+    "mov qword [rax + 16], r8",
+    "mov qword [rax + 24], rcx",
+    "mov rax, rdx",
+    "ret",
+];
+
+const STR_STR_MAP_SET: &[&str] = &[
+    "push rcx",
+    "push r8",
+    "call str_map_entry",
+    "pop r8",
+    "pop rcx",
+    "movsxd r8, r8d",
+    "mov qword [rax + 16], rcx",
+    "mov qword [rax + 24], r8",
+    "mov rax, rdx",
+    "ret",
+];
+
+/// Value of the entry, or the zero value of its type when it is 0.
+const MAP_INT_VALUE: &[&str] = &[
+    "test rax, rax",
+    "je .done",
+    "mov eax, dword [rax + 16]",
+    ".done:",
+    "ret",
+];
+
+const MAP_FLOAT_VALUE: &[&str] = &[
+    "test rax, rax",
+    "je .done",
+    "mov rax, qword [rax + 16]",
+    ".done:",
+    "movq xmm0, rax",
+    "ret",
+];
+
+const MAP_BOOL_VALUE: &[&str] = &[
+    "test rax, rax",
+    "je .done",
+    "movzx eax, byte [rax + 16]",
+    ".done:",
+    "ret",
+];
+
+const MAP_STR_VALUE: &[&str] = &[
+    "xor edx, edx",
+    "test rax, rax",
+    "je .done",
+    "mov rdx, qword [rax + 24]",
+    "mov rax, qword [rax + 16]",
+    ".done:",
+    "ret",
+];
+
+const INT_INT_MAP_GET: &[&str] = &["call int_map_find", "jmp map_int_value"];
+const INT_FLOAT_MAP_GET: &[&str] = &["call int_map_find", "jmp map_float_value"];
+const INT_BOOL_MAP_GET: &[&str] = &["call int_map_find", "jmp map_bool_value"];
+const INT_STR_MAP_GET: &[&str] = &["call int_map_find", "jmp map_str_value"];
+const STR_INT_MAP_GET: &[&str] = &["call str_map_find", "jmp map_int_value"];
+const STR_FLOAT_MAP_GET: &[&str] = &["call str_map_find", "jmp map_float_value"];
+const STR_BOOL_MAP_GET: &[&str] = &["call str_map_find", "jmp map_bool_value"];
+const STR_STR_MAP_GET: &[&str] = &["call str_map_find", "jmp map_str_value"];
+
+const INT_MAP_CONTAINS: &[&str] = &["call int_map_find", "test rax, rax", "setne al", "movzx eax, al", "ret"];
+const STR_MAP_CONTAINS: &[&str] = &["call str_map_find", "test rax, rax", "setne al", "movzx eax, al", "ret"];
+
+/// Removes the entry, 0 when the key was missing, by moving the last entry of the map in its place.
+const MAP_REMOVE_ENTRY: &[&str] = &[
+    "test rax, rax",
+    "je .done",
+    "dec qword [rdi]",
+    "mov rcx, qword [rdi]",
+    "shl rcx, 5",
+    "add rcx, qword [rdi + 16]",
+    "mov rdx, qword [rcx]",
+    "mov qword [rax], rdx",
+    "mov rdx, qword [rcx + 8]",
+    "mov qword [rax + 8], rdx",
+    "mov rdx, qword [rcx + 16]",
+    "mov qword [rax + 16], rdx",
+    "mov rdx, qword [rcx + 24]",
+    "mov qword [rax + 24], rdx",
+    ".done:",
+    "ret",
+];
+
+const INT_MAP_REMOVE: &[&str] = &["call int_map_find", "jmp map_remove_entry"];
+const STR_MAP_REMOVE: &[&str] = &["call str_map_find", "jmp map_remove_entry"];
+
+/// Prints `line: message` to stderr and exits with status 1, the line being positive.
+const RUNTIME_ERROR: &[&str] = &[
+    "mov r8, rdi",
+    "mov r9d, esi",
+    "mov eax, edx",
+    "sub rsp, 32",
+    "lea rsi, [rsp + 32]",
+    "dec rsi",
+    "mov byte [rsi], 32",
+    "dec rsi",
+    "mov byte [rsi], 58",
+    ".digits:",
+    "mov ecx, 10",
+    "xor edx, edx",
+    "div rcx",
+    "add dl, 48",
+    "dec rsi",
+    "mov byte [rsi], dl",
+    "test rax, rax",
+    "jnz .digits",
+    "lea rdx, [rsp + 32]",
+    "sub rdx, rsi",
+    "mov eax, 1",
+    "mov edi, 2",
+    "syscall",
+    "mov rsi, r8",
+    "movsxd rdx, r9d",
+    "mov eax, 1",
+    "mov edi, 2",
+    "syscall",
+    "push 10",
+    "mov rsi, rsp",
+    "mov edx, 1",
+    "mov edi, 2",
+    "mov eax, 1",
+    "syscall",
+    "mov edi, 1",
+    "mov eax, 60",
+    "syscall",
+];
+
+/// Ends the program with the status given.
+const EXIT_PROGRAM: &[&str] = &[
+    "mov eax, 60",
+    "syscall",
+];
+
+/// Entry point of the freestanding programs, exiting with the status returned by `main`.
+const START: &[&str] = &[
+    "xor ebp, ebp",
+    "call main",
+    "mov edi, eax",
+    "mov eax, 60",
+    "syscall",
+];
+
+/// Symbol of the routine reporting a failed runtime check.
+pub(super) const ERROR_ROUTINE: &str = "runtime_error";
+
+/// Message of a failed division check.
+pub(super) const DIVISION_BY_ZERO: &str = "runtime error: division by zero";
+
+/// Message of a failed overflow check.
+pub(super) const INTEGER_OVERFLOW: &str = "runtime error: integer overflow";
+
+/// Symbol of the routine allocating the arrays created with `new`, called with their length and element size.
+pub(super) const ALLOC_ROUTINE: &str = "alloc";
+
+/// Symbol of the routine freeing an array created with `new`, called with the array and its element size.
+pub(super) const RELEASE_ROUTINE: &str = "release";
+
+/// Symbol of the routine creating an empty map, the maps written `{}`.
+pub(super) const MAP_NEW_ROUTINE: &str = "map_new";
+
+/// Symbol of the routine running `operation` on maps of type `typeval`: `get` and `set` are per key and value types,
+/// `contains` and `remove` per key type, as `str_int_map_get` or `int_map_remove`.
+pub(super) fn map_routine(operation: &str, typeval: &Type) -> String {
+    let (key, value) = typeval.map.as_ref().unwrap();
+    return match operation {
+        "get" | "set" => format!("{}_{}_map_{}", key.name, value.name, operation),
+        operation => format!("{}_map_{}", key.name, operation),
+    };
+}
+
+fn int_array() -> Type {
+    return array_type(int_type(), None);
+}
+
+fn float_array() -> Type {
+    return array_type(float_type(), None);
+}
+
+fn bool_array() -> Type {
+    return array_type(bool_type(), None);
+}
+
+fn str_array() -> Type {
+    return array_type(string_type(), None);
+}
+
+fn int_map(value: Type) -> Type {
+    return map_type(int_type(), value);
+}
+
+fn str_map(value: Type) -> Type {
+    return map_type(string_type(), value);
+}
+
+fn runtime_functions() -> Vec<RuntimeFunction> {
+    return vec![
+        RuntimeFunction { symbol: "print_int", parameters: vec![int_type()], return_type: None, body: PRINT_INT },
+        RuntimeFunction { symbol: "print_str", parameters: vec![string_type()], return_type: None, body: PRINT_STR },
+        RuntimeFunction { symbol: "write_int", parameters: vec![int_type()], return_type: None, body: WRITE_INT },
+        RuntimeFunction { symbol: "write_str", parameters: vec![string_type()], return_type: None, body: WRITE_STR },
+        RuntimeFunction { symbol: "read_int", parameters: Vec::new(), return_type: Some(int_type()), body: READ_INT },
+        RuntimeFunction { symbol: "str_length", parameters: vec![string_type()], return_type: Some(int_type()), body: STR_LENGTH },
+        RuntimeFunction { symbol: "str_substring", parameters: vec![string_type(), int_type(), int_type()], return_type: Some(string_type()), body: STR_SUBSTRING },
+        RuntimeFunction { symbol: "str_concat", parameters: vec![string_type(), string_type()], return_type: Some(string_type()), body: STR_CONCAT },
+        RuntimeFunction { symbol: "str_compare", parameters: vec![string_type(), string_type()], return_type: Some(int_type()), body: STR_COMPARE },
+        RuntimeFunction { symbol: "int_to_string", parameters: vec![int_type()], return_type: Some(string_type()), body: INT_TO_STRING },
+        RuntimeFunction { symbol: "int_abs", parameters: vec![int_type()], return_type: Some(int_type()), body: INT_ABS },
+        RuntimeFunction { symbol: "int_min", parameters: vec![int_type(), int_type()], return_type: Some(int_type()), body: INT_MIN },
+        RuntimeFunction { symbol: "int_max", parameters: vec![int_type(), int_type()], return_type: Some(int_type()), body: INT_MAX },
+        RuntimeFunction { symbol: "exit_program", parameters: vec![int_type()], return_type: None, body: EXIT_PROGRAM },
+        RuntimeFunction { symbol: ALLOC_ROUTINE, parameters: vec![int_type(), int_type()], return_type: Some(array_type(int_type(), None)), body: ALLOC },
+        RuntimeFunction { symbol: RELEASE_ROUTINE, parameters: vec![array_type(int_type(), None), int_type()], return_type: None, body: RELEASE },
+        RuntimeFunction { symbol: "array_resize", parameters: vec![array_type(int_type(), None), int_type(), int_type()], return_type: Some(array_type(int_type(), None)), body: ARRAY_RESIZE },
+        RuntimeFunction { symbol: "int_array_copy", parameters: vec![int_array()], return_type: Some(int_array()), body: INT_ARRAY_COPY },
+        RuntimeFunction { symbol: "float_array_copy", parameters: vec![float_array()], return_type: Some(float_array()), body: FLOAT_ARRAY_COPY },
+        RuntimeFunction { symbol: "bool_array_copy", parameters: vec![bool_array()], return_type: Some(bool_array()), body: BOOL_ARRAY_COPY },
+        RuntimeFunction { symbol: "str_array_copy", parameters: vec![str_array()], return_type: Some(str_array()), body: STR_ARRAY_COPY },
+        RuntimeFunction { symbol: "int_array_append", parameters: vec![int_array(), int_type()], return_type: Some(int_array()), body: INT_ARRAY_APPEND },
+        RuntimeFunction { symbol: "float_array_append", parameters: vec![float_array(), float_type()], return_type: Some(float_array()), body: FLOAT_ARRAY_APPEND },
+        RuntimeFunction { symbol: "bool_array_append", parameters: vec![bool_array(), bool_type()], return_type: Some(bool_array()), body: BOOL_ARRAY_APPEND },
+        RuntimeFunction { symbol: "str_array_append", parameters: vec![str_array(), string_type()], return_type: Some(str_array()), body: STR_ARRAY_APPEND },
+        RuntimeFunction { symbol: "int_array_fill", parameters: vec![int_array(), int_type()], return_type: None, body: INT_ARRAY_FILL },
+        RuntimeFunction { symbol: "float_array_fill", parameters: vec![float_array(), float_type()], return_type: None, body: FLOAT_ARRAY_FILL },
+        RuntimeFunction { symbol: "bool_array_fill", parameters: vec![bool_array(), bool_type()], return_type: None, body: BOOL_ARRAY_FILL },
+        RuntimeFunction { symbol: "str_array_fill", parameters: vec![str_array(), string_type()], return_type: None, body: STR_ARRAY_FILL },
+        RuntimeFunction { symbol: "int_array_sort", parameters: vec![int_array()], return_type: None, body: INT_ARRAY_SORT },
+        RuntimeFunction { symbol: "float_array_sort", parameters: vec![float_array()], return_type: None, body: FLOAT_ARRAY_SORT },
+        RuntimeFunction { symbol: MAP_NEW_ROUTINE, parameters: Vec::new(), return_type: Some(int_map(int_type())), body: MAP_NEW },
+        RuntimeFunction { symbol: "int_map_find", parameters: vec![int_map(int_type()), int_type()], return_type: Some(int_map(int_type())), body: INT_MAP_FIND },
+        RuntimeFunction { symbol: "str_map_find", parameters: vec![str_map(int_type()), string_type()], return_type: Some(int_map(int_type())), body: STR_MAP_FIND },
+        RuntimeFunction { symbol: "map_append", parameters: vec![int_map(int_type())], return_type: Some(int_map(int_type())), body: MAP_APPEND },
+        RuntimeFunction { symbol: "int_map_entry", parameters: vec![int_map(int_type()), int_type()], return_type: Some(int_map(int_type())), body: INT_MAP_ENTRY },
+        RuntimeFunction { symbol: "str_map_entry", parameters: vec![str_map(int_type()), string_type()], return_type: Some(int_map(int_type())), body: STR_MAP_ENTRY },
+        RuntimeFunction { symbol: "int_map_store", parameters: vec![int_map(int_type()), int_type(), int_type()], return_type: Some(int_map(int_type())), body: INT_MAP_STORE },
+        RuntimeFunction { symbol: "str_map_store", parameters: vec![str_map(int_type()), string_type(), int_type()], return_type: Some(str_map(int_type())), body: STR_MAP_STORE },
+        RuntimeFunction { symbol: "int_int_map_set", parameters: vec![int_map(int_type()), int_type(), int_type()], return_type: Some(int_map(int_type())), body: INT_INT_MAP_SET },
+        RuntimeFunction { symbol: "int_float_map_set", parameters: vec![int_map(float_type()), int_type(), float_type()], return_type: Some(int_map(float_type())), body: INT_FLOAT_MAP_SET },
+        RuntimeFunction { symbol: "int_bool_map_set", parameters: vec![int_map(bool_type()), int_type(), bool_type()], return_type: Some(int_map(bool_type())), body: INT_BOOL_MAP_SET },
+        RuntimeFunction { symbol: "int_str_map_set", parameters: vec![int_map(string_type()), int_type(), string_type()], return_type: Some(int_map(string_type())), body: INT_STR_MAP_SET },
+        RuntimeFunction { symbol: "str_int_map_set", parameters: vec![str_map(int_type()), string_type(), int_type()], return_type: Some(str_map(int_type())), body: STR_INT_MAP_SET },
+        RuntimeFunction { symbol: "str_float_map_set", parameters: vec![str_map(float_type()), string_type(), float_type()], return_type: Some(str_map(float_type())), body: STR_FLOAT_MAP_SET },
+        RuntimeFunction { symbol: "str_bool_map_set", parameters: vec![str_map(bool_type()), string_type(), bool_type()], return_type: Some(str_map(bool_type())), body: STR_BOOL_MAP_SET },
+        RuntimeFunction { symbol: "str_str_map_set", parameters: vec![str_map(string_type()), string_type(), string_type()], return_type: Some(str_map(string_type())), body: STR_STR_MAP_SET },
+        RuntimeFunction { symbol: "map_int_value", parameters: vec![int_map(int_type())], return_type: Some(int_type()), body: MAP_INT_VALUE },
+        RuntimeFunction { symbol: "map_float_value", parameters: vec![int_map(int_type())], return_type: Some(float_type()), body: MAP_FLOAT_VALUE },
+        RuntimeFunction { symbol: "map_bool_value", parameters: vec![int_map(int_type())], return_type: Some(bool_type()), body: MAP_BOOL_VALUE },
+        RuntimeFunction { symbol: "map_str_value", parameters: vec![int_map(int_type())], return_type: Some(string_type()), body: MAP_STR_VALUE },
+        RuntimeFunction { symbol: "int_int_map_get", parameters: vec![int_map(int_type()), int_type()], return_type: Some(int_type()), body: INT_INT_MAP_GET },
+        RuntimeFunction { symbol: "int_float_map_get", parameters: vec![int_map(float_type()), int_type()], return_type: Some(float_type()), body: INT_FLOAT_MAP_GET },
+        RuntimeFunction { symbol: "int_bool_map_get", parameters: vec![int_map(bool_type()), int_type()], return_type: Some(bool_type()), body: INT_BOOL_MAP_GET },
+        RuntimeFunction { symbol: "int_str_map_get", parameters: vec![int_map(string_type()), int_type()], return_type: Some(string_type()), body: INT_STR_MAP_GET },
+        RuntimeFunction { symbol: "str_int_map_get", parameters: vec![str_map(int_type()), string_type()], return_type: Some(int_type()), body: STR_INT_MAP_GET },
+        RuntimeFunction { symbol: "str_float_map_get", parameters: vec![str_map(float_type()), string_type()], return_type: Some(float_type()), body: STR_FLOAT_MAP_GET },
+        RuntimeFunction { symbol: "str_bool_map_get", parameters: vec![str_map(bool_type()), string_type()], return_type: Some(bool_type()), body: STR_BOOL_MAP_GET },
+        RuntimeFunction { symbol: "str_str_map_get", parameters: vec![str_map(string_type()), string_type()], return_type: Some(string_type()), body: STR_STR_MAP_GET },
+        RuntimeFunction { symbol: "int_map_contains", parameters: vec![int_map(int_type()), int_type()], return_type: Some(bool_type()), body: INT_MAP_CONTAINS },
+        RuntimeFunction { symbol: "str_map_contains", parameters: vec![str_map(int_type()), string_type()], return_type: Some(bool_type()), body: STR_MAP_CONTAINS },
+        RuntimeFunction { symbol: "map_remove_entry", parameters: vec![int_map(int_type())], return_type: None, body: MAP_REMOVE_ENTRY },
+        RuntimeFunction { symbol: "int_map_remove", parameters: vec![int_map(int_type()), int_type()], return_type: None, body: INT_MAP_REMOVE },
+        RuntimeFunction { symbol: "str_map_remove", parameters: vec![str_map(int_type()), string_type()], return_type: None, body: STR_MAP_REMOVE },
+        RuntimeFunction { symbol: ERROR_ROUTINE, parameters: vec![string_type(), int_type()], return_type: None, body: RUNTIME_ERROR },
+    ];
+}
+
+/// Builtin functions callable without declaration, with the runtime symbol implementing them.
+const BUILTINS: [(&str, &str); 29] = [
+    ("print", "print_int"),
+    ("print", "print_str"),
+    // only called by the `write` statement, `write` being a keyword.
+    ("write", "write_int"),
+    ("write", "write_str"),
+    ("len", "str_length"),
+    ("length", "str_length"),
+    ("substring", "str_substring"),
+    ("concat", "str_concat"),
+    ("compare", "str_compare"),
+    ("to_string", "int_to_string"),
+    ("copy", "int_array_copy"),
+    ("copy", "float_array_copy"),
+    ("copy", "bool_array_copy"),
+    ("copy", "str_array_copy"),
+    ("append", "int_array_append"),
+    ("append", "float_array_append"),
+    ("append", "bool_array_append"),
+    ("append", "str_array_append"),
+    ("fill", "int_array_fill"),
+    ("fill", "float_array_fill"),
+    ("fill", "bool_array_fill"),
+    ("fill", "str_array_fill"),
+    ("sort", "int_array_sort"),
+    ("sort", "float_array_sort"),
+    ("read_int", "read_int"),
+    ("abs", "int_abs"),
+    ("min", "int_min"),
+    ("max", "int_max"),
+    // only called by the `exit` and `halt` statements, `exit` being a keyword.
+    ("exit", "exit_program"),
+];
+
+/// Builtin float functions of the C math library, called by their own symbol, with their number of parameters.
+/// Programs using them are linked with `-lm`.
+pub(super) const MATH_BUILTINS: [(&str, usize); 7] = [
+    ("sqrt", 1),
+    ("pow", 2),
+    ("floor", 1),
+    ("ceil", 1),
+    ("log", 1),
+    ("sin", 1),
+    ("cos", 1),
+];
+
+/// Declarations of the builtin functions, along with their runtime symbols.
+pub(super) fn builtin_declarations() -> Vec<(FunctionDeclaration, String)> {
+    let functions = runtime_functions();
+    let runtime = BUILTINS
+        .iter()
+        .filter_map(|(name, symbol)| functions.iter().find(|f| f.symbol == *symbol).map(|f| (name, f)))
+        .map(|(name, f)| {
+            let dec = FunctionDeclaration { name: name.to_string(), parameters: f.parameters.clone(), return_type: f.return_type.clone(), implemented: false };
+            (dec, f.symbol.to_string())
+        });
+    let math = MATH_BUILTINS.iter().map(|(name, count)| {
+        let dec = FunctionDeclaration { name: name.to_string(), parameters: vec![float_type(); *count], return_type: Some(float_type()), implemented: false };
+        (dec, name.to_string())
+    });
+    return runtime.chain(math).collect();
+}
+
+/// Whether `symbol` is a function of the C math library.
+pub(super) fn is_math_function(symbol: &str) -> bool {
+    return MATH_BUILTINS.iter().any(|(name, _)| *name == symbol);
+}
+
+/// Declaration of the routine reporting a failed runtime check, called with the message and the line of the check.
+pub(super) fn error_declaration() -> (FunctionDeclaration, String) {
+    let symbol = String::from(ERROR_ROUTINE);
+    let dec = FunctionDeclaration { name: symbol.clone(), parameters: vec![string_type(), int_type()], return_type: None, implemented: false };
+    return (dec, symbol);
+}
+
+/// Declarations of the routines managing the arrays created with `new`, along with their symbols.
+/// The element type of the arrays does not matter to them, they are declared for `int[]`.
+pub(super) fn allocation_declarations() -> Vec<(FunctionDeclaration, String)> {
+    return runtime_functions()
+        .into_iter()
+        .filter(|f| f.symbol == ALLOC_ROUTINE || f.symbol == RELEASE_ROUTINE)
+        .map(|f| {
+            let dec = FunctionDeclaration { name: f.symbol.to_string(), parameters: f.parameters, return_type: f.return_type, implemented: false };
+            (dec, f.symbol.to_string())
+        })
+        .collect();
+}
+
+/// Declarations of the routines reading and writing maps, along with their symbols.
+/// `contains` and `remove` do not depend on the value type, they are declared for int values.
+pub(super) fn map_declarations() -> Vec<(FunctionDeclaration, String)> {
+    return runtime_functions()
+        .into_iter()
+        .filter(|f| f.symbol == MAP_NEW_ROUTINE || f.symbol.ends_with("_map_get") || f.symbol.ends_with("_map_set")
+            || f.symbol.ends_with("_map_contains") || f.symbol.ends_with("_map_remove"))
+        .map(|f| {
+            let dec = FunctionDeclaration { name: f.symbol.to_string(), parameters: f.parameters, return_type: f.return_type, implemented: false };
+            (dec, f.symbol.to_string())
+        })
+        .collect();
+}
+
+/// Assembly of the runtime library, to link with the generated programs on x86-64 linux.
+/// Only system calls are used, so programs do not depend on the C library.
+/// A `freestanding` runtime also defines `_start`, for programs linked without the C library.
+pub fn runtime_library(syntax: AsmSyntax, freestanding: bool) -> String {
+    let (global, text) = match syntax {
+        AsmSyntax::Intel => ("global", "section .text"),
+        AsmSyntax::Att => (".globl", ".text"),
+    };
+
+    let mut functions = runtime_functions();
+    if freestanding {
+        functions.push(RuntimeFunction { symbol: "_start", parameters: Vec::new(), return_type: None, body: START });
+    }
+
+    let mut res = String::new();
+    for function in &functions {
+        res.push_str(format!("{} {}\n", global, function.symbol).as_str());
+    }
+    res.push_str(format!("\n{}\n", text).as_str());
+
+    for (i, function) in functions.iter().enumerate() {
+        res.push_str(format!("\n{}:\n", function.symbol).as_str());
+        for line in function.body.iter() {
+            // gas needs labels unique to the file, nasm scopes `.name` to the function.
+            let line = match syntax {
+                AsmSyntax::Intel => line.to_string(),
+                AsmSyntax::Att => line.replace(" .", format!(" .Lruntime{}.", i).as_str()),
+            };
+            match line.strip_suffix(':') {
+                Some(label) if syntax == AsmSyntax::Att => res.push_str(format!(".Lruntime{}{}:\n", i, label).as_str()),
+                Some(..) => res.push_str(format!("{}\n", line).as_str()),
+                None if syntax == AsmSyntax::Att => res.push_str(format!("\t{}\n", syntax::to_att(line.as_str())).as_str()),
+                None => res.push_str(format!("\t{}\n", line).as_str()),
+            };
+        }
+    }
+
+    if syntax == AsmSyntax::Att {
+        res.push_str("\n.section .note.GNU-stack,\"\",@progbits\n");
+    }
+    return res;
+}
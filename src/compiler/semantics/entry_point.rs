@@ -0,0 +1,45 @@
+use super::super::super::diagnostic::{Code, Diagnostic};
+use super::super::super::lexer::Span;
+use super::super::super::parser;
+use super::super::Scope;
+use super::super::constants::evaluate_constant;
+
+fn error(message: &str, span: Span) -> Diagnostic {
+    return Diagnostic::error(Code::InvalidEntryPoint, message.to_string(), span);
+}
+
+/// Checks the `main()` function of the program, whose result is the exit status.
+/// The top level of such a program only declares functions, records, constants and globals,
+/// a global being initialized once, with a constant.
+pub(super) fn check_entry_point(children: &Vec<parser::Ast>, scope: &Scope, errors: &mut Vec<Diagnostic>) {
+    let entry_point = children.iter().find_map(|child| match child {
+        parser::Ast::FunctionDeclaration { name, parameters, return_type, span, .. } if name == "main" && parameters.is_empty() => Some((return_type, *span)),
+        _ => None,
+    });
+    let (return_type, span) = match entry_point {
+        None => return,
+        Some(val) => val,
+    };
+    if return_type.as_deref() != Some("int") {
+        errors.push(error("the main function must return an int", span).with_note("the result of main is the exit status of the program"));
+    }
+
+    let mut initialized = Vec::<&String>::new();
+    for child in children {
+        let span = child.span().unwrap_or(span);
+        match child {
+            parser::Ast::FunctionDeclaration { .. } | parser::Ast::FunctionHeader { .. } | parser::Ast::RecordDeclaration { .. } | parser::Ast::ConstDeclaration { .. } => (),
+            parser::Ast::Variable(var) if var.typename.is_some() => (),
+            parser::Ast::Assignement { variable, expression, .. } => match &**variable {
+                parser::Ast::Variable(var) if !initialized.contains(&&var.name) => {
+                    initialized.push(&var.name);
+                    if !matches!(evaluate_constant(expression, scope), Ok(parser::Ast::Int(..) | parser::Ast::Float(..) | parser::Ast::Bool(..))) {
+                        errors.push(error("a global of a program defining main can only be initialized with a constant", span));
+                    }
+                },
+                _ => errors.push(error("a global of a program defining main can only be initialized once", span)),
+            },
+            _ => errors.push(error("a program defining main cannot have statements outside of functions", span).with_note("move the statement into main")),
+        };
+    }
+}
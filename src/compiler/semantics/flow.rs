@@ -0,0 +1,74 @@
+use super::super::super::diagnostic::{Code, Diagnostic};
+use super::super::super::lexer::Span;
+use super::super::super::parser;
+
+/// Whether every path through the statement ends with a `return`, a `throw` or an `exit`.
+fn returns(statement: &parser::Ast) -> bool {
+    return match statement {
+        parser::Ast::ReturnStatement { .. } | parser::Ast::Throw { .. } | parser::Ast::Exit { .. } => true,
+        parser::Ast::Condition { valid_branch, invalid_branch, .. } => always_returns(valid_branch) && always_returns(invalid_branch),
+        parser::Ast::Try { children, handler, .. } => always_returns(children) && always_returns(handler),
+        // a `match` without `else` may handle no value.
+        parser::Ast::Match { cases, default: Some(default), .. } => cases.iter().all(|(_, children)| always_returns(children)) && always_returns(default),
+        _ => false,
+    };
+}
+
+/// Whether every path through the statements ends with a `return`.
+pub(super) fn always_returns(children: &[parser::Ast]) -> bool {
+    return children.iter().any(returns);
+}
+
+/// Where a path through the statements ends without a `return`, `None` when every path returns.
+/// The branch lacking the return is described along with the span of its condition.
+pub(super) fn missing_return(children: &[parser::Ast], span: Span, place: &str) -> Option<(Span, String)> {
+    if always_returns(children) {
+        return None;
+    }
+    for child in children.iter().rev() {
+        if let parser::Ast::Condition { valid_branch, invalid_branch, span, .. } = child {
+            if always_returns(valid_branch) {
+                return missing_return(invalid_branch, *span, "the else branch");
+            } else if always_returns(invalid_branch) {
+                return missing_return(valid_branch, *span, "the if branch");
+            }
+        }
+    }
+    return Some((span, place.to_string()));
+}
+
+/// Warns about the statements which can never run: those following a `return` or a `throw` and the bodies of `while false`.
+pub(super) fn check_reachability(children: &Vec<parser::Ast>, parent_span: Span, errors: &mut Vec<Diagnostic>) {
+    let mut returned = false;
+    for child in children {
+        let span = child.span().unwrap_or(parent_span);
+        if returned {
+            // the rest of the block is reported once.
+            errors.push(Diagnostic::warning(Code::UnreachableCode, String::from("unreachable statement"), span));
+            return;
+        }
+
+        match child {
+            parser::Ast::FunctionDeclaration { children, span, .. } => check_reachability(children, *span, errors),
+            parser::Ast::Condition { valid_branch, invalid_branch, span, .. } => {
+                check_reachability(valid_branch, *span, errors);
+                check_reachability(invalid_branch, *span, errors);
+            },
+            parser::Ast::Try { children, handler, span, .. } => {
+                check_reachability(children, *span, errors);
+                check_reachability(handler, *span, errors);
+            },
+            parser::Ast::Match { cases, default, span, .. } => {
+                for children in cases.iter().map(|(_, children)| children).chain(default.iter()) {
+                    check_reachability(children, *span, errors);
+                }
+            },
+            parser::Ast::WhileLoop { condition, children, span } => match &**condition {
+                parser::Ast::Bool(false) => errors.push(Diagnostic::warning(Code::UnreachableCode, String::from("the body of a 'while false' loop is never executed"), *span)),
+                _ => check_reachability(children, *span, errors),
+            },
+            _ => (),
+        };
+        returned = returns(child);
+    }
+}
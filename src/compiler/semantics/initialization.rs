@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+
+use super::super::super::diagnostic::{Code, Diagnostic, Severity};
+use super::super::super::lexer::Span;
+use super::super::super::parser;
+
+/// Reports the variables read on a path where they were never assigned.
+/// Only variables declared without a value (`x: int`) can be read before being assigned, the arrays of a fixed size
+/// (`t: int[10]`) being filled with zeros when they are declared.
+pub(super) struct InitializationChecker {
+    pub severity: Severity,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl InitializationChecker {
+
+    pub fn check_function(&mut self, children: &Vec<parser::Ast>, span: Span) {
+        self.check_block(children, &mut HashSet::new(), Some(HashSet::new()), span);
+    }
+
+    /// Returns the variables assigned at the end of the block, `None` when it cannot be reached.
+    fn check_block(&mut self, children: &Vec<parser::Ast>, declared: &mut HashSet<String>, assigned: Option<HashSet<String>>, parent_span: Span) -> Option<HashSet<String>> {
+        let mut assigned = assigned;
+        for child in children {
+            let span = child.span().unwrap_or(parent_span);
+            let mut current = match assigned {
+                None => return None,
+                Some(val) => val,
+            };
+
+            assigned = match child {
+                parser::Ast::FunctionDeclaration { children, span, .. } => {
+                    self.check_function(children, *span);
+                    Some(current)
+                },
+                parser::Ast::Variable(var) if var.typename.as_ref().is_some_and(|typename| typename.is_array && typename.size.is_some()) => {
+                    current.insert(var.name.clone());
+                    Some(current)
+                },
+                parser::Ast::Variable(var) if var.typename.is_some() => {
+                    declared.insert(var.name.clone());
+                    current.remove(&var.name);
+                    Some(current)
+                },
+                parser::Ast::Assignement { variable, expression, .. } => {
+                    self.check_reads(expression, declared, &mut current, span);
+                    match &**variable {
+                        parser::Ast::Variable(var) => {
+                            current.insert(var.name.clone());
+                        },
+                        parser::Ast::ArrayAccess { variable, index } => {
+                            self.check_reads(index, declared, &mut current, span);
+                            current.insert(variable.clone());
+                        },
+                        access => {
+                            // assigning a field initializes the record.
+                            if let Some(root) = access.find_nodes(|n| matches!(n, parser::Ast::Variable(..))).first() {
+                                if let parser::Ast::Variable(var) = &root.node {
+                                    current.insert(var.name.clone());
+                                }
+                            }
+                        },
+                    };
+                    Some(current)
+                },
+                parser::Ast::Condition { condition, valid_branch, invalid_branch, span } => {
+                    self.check_reads(condition, declared, &mut current, *span);
+                    let valid = self.check_block(valid_branch, &mut declared.clone(), Some(current.clone()), *span);
+                    let invalid = self.check_block(invalid_branch, &mut declared.clone(), Some(current), *span);
+                    match (valid, invalid) {
+                        (Some(valid), Some(invalid)) => Some(valid.intersection(&invalid).cloned().collect()),
+                        (Some(val), None) | (None, Some(val)) => Some(val),
+                        (None, None) => None,
+                    }
+                },
+                parser::Ast::WhileLoop { condition, children, span } => {
+                    // the body may not run, what it assigns is not kept.
+                    self.check_reads(condition, declared, &mut current, *span);
+                    self.check_block(children, &mut declared.clone(), Some(current.clone()), *span);
+                    Some(current)
+                },
+                parser::Ast::Try { children, handler, span, .. } => {
+                    // the handler may run after any statement of the block.
+                    let block = self.check_block(children, &mut declared.clone(), Some(current.clone()), *span);
+                    let handler = self.check_block(handler, &mut declared.clone(), Some(current), *span);
+                    match (block, handler) {
+                        (Some(block), Some(handler)) => Some(block.intersection(&handler).cloned().collect()),
+                        (Some(val), None) | (None, Some(val)) => Some(val),
+                        (None, None) => None,
+                    }
+                },
+                parser::Ast::Match { value, cases, default, span } => {
+                    // without `else`, no case may run.
+                    self.check_reads(value, declared, &mut current, *span);
+                    let mut assigned = match default {
+                        None => Some(current.clone()),
+                        Some(children) => self.check_block(children, &mut declared.clone(), Some(current.clone()), *span),
+                    };
+                    for (_, children) in cases {
+                        let branch = self.check_block(children, &mut declared.clone(), Some(current.clone()), *span);
+                        assigned = match (assigned, branch) {
+                            (Some(assigned), Some(branch)) => Some(assigned.intersection(&branch).cloned().collect()),
+                            (Some(val), None) | (None, Some(val)) => Some(val),
+                            (None, None) => None,
+                        };
+                    }
+                    assigned
+                },
+                parser::Ast::Throw { value, span } | parser::Ast::Exit { code: value, span } => {
+                    self.check_reads(value, declared, &mut current, *span);
+                    None
+                },
+                parser::Ast::ReturnStatement { value, span } => {
+                    if let Some(value) = value {
+                        self.check_reads(value, declared, &mut current, *span);
+                    }
+                    None
+                },
+                parser::Ast::FunctionHeader { .. } | parser::Ast::RecordDeclaration { .. } | parser::Ast::Variable(..) => Some(current),
+                expression => {
+                    self.check_reads(expression, declared, &mut current, span);
+                    Some(current)
+                },
+            };
+        }
+        return assigned;
+    }
+
+    /// Reports the unassigned variables read by `expression`, each one being reported once.
+    fn check_reads(&mut self, expression: &parser::Ast, declared: &HashSet<String>, assigned: &mut HashSet<String>, span: Span) {
+        let name = match expression {
+            // lambda parameters may shadow the variables.
+            parser::Ast::Lambda { .. } => return,
+            parser::Ast::Variable(var) => Some(&var.name),
+            parser::Ast::ArrayAccess { variable, .. } => Some(variable),
+            _ => None,
+        };
+
+        if let Some(name) = name {
+            if declared.contains(name) && !assigned.contains(name) {
+                self.errors.push(Diagnostic {
+                    severity: self.severity,
+                    ..Diagnostic::warning(Code::UninitializedVariable, format!("variable '{}' is used before being assigned", name), span)
+                });
+                assigned.insert(name.clone());
+            }
+        }
+
+        for child in expression.children() {
+            self.check_reads(child, declared, assigned, span);
+        }
+    }
+}
@@ -0,0 +1,847 @@
+use super::super::diagnostic::{Code, Diagnostic, Severity};
+use super::super::lexer::Span;
+use super::super::parser;
+use super::constants::{evaluate_constant, get_constant};
+use super::formats::{self, Piece};
+use super::{
+    FunctionDeclaration, Scope, Type, Variable,
+    array_type, bool_type, convert_params, convert_parser_type, convert_type, declare_record, float_type,
+    function_type, get_function_by_effective_name, is_array_length, is_array_release, is_map_operation, get_type, get_local_variable_type, get_variable_type, int_type,
+    is_assignable, is_formatted_print, map_type, member_type, resolve_overload, string_type,
+};
+
+mod entry_point;
+mod flow;
+mod initialization;
+
+use initialization::InitializationChecker;
+
+/// Type checks the whole program, returning every error found instead of stopping at the first one.
+pub fn check(ast: &parser::Ast) -> Vec<Diagnostic> {
+    return check_with_strictness(ast, false);
+}
+
+/// Same as `check`, reading a variable before assigning it is a warning, or an error when `strict`.
+pub fn check_with_strictness(ast: &parser::Ast, strict: bool) -> Vec<Diagnostic> {
+    let mut checker = Checker { errors: Vec::new(), untyped: Vec::new(), symbols: Vec::new(), enclosing: Vec::new(), captures: Vec::new() };
+    let mut initialization = InitializationChecker {
+        severity: if strict { Severity::Error } else { Severity::Warning },
+        errors: Vec::new(),
+    };
+    match ast {
+        parser::Ast::Global(children) => {
+            let mut scope = Scope::new_global_scope();
+            checker.check_statements(children, &mut scope, &None, Span::default());
+            initialization.check_function(children, Span::default());
+            flow::check_reachability(children, Span::default(), &mut checker.errors);
+            entry_point::check_entry_point(children, &scope, &mut checker.errors);
+        },
+        val => checker.error(Code::Syntax, format!("expected a global ast, got {:?}", val), Span::default()),
+    };
+
+    let mut errors = checker.errors;
+    errors.extend(initialization.errors);
+    errors.sort_by_key(|e| (e.span.line, e.span.column));
+    return errors;
+}
+
+fn is_numeric(typeval: &Type) -> bool {
+    return typeval == &int_type() || typeval == &float_type();
+}
+
+fn operator(expression: &parser::Ast) -> &'static str {
+    return match expression {
+        parser::Ast::Addition {..} | parser::Ast::UnaryPlus {..} => "+",
+        parser::Ast::Substraction {..} | parser::Ast::UnaryMinus {..} => "-",
+        parser::Ast::Multiplication {..} => "*",
+        parser::Ast::Division {..} => "/",
+        parser::Ast::Modulo {..} => "%",
+        parser::Ast::EqualTo {..} => "==",
+        parser::Ast::NotEqualTo {..} => "!=",
+        parser::Ast::GreaterThan {..} => ">",
+        parser::Ast::GreaterOrEqual {..} => ">=",
+        parser::Ast::LowerThan {..} => "<",
+        parser::Ast::And {..} => "and",
+        parser::Ast::Or {..} => "or",
+        _ => "<=",
+    };
+}
+
+fn type_name(typeval: &Option<Type>) -> String {
+    return match typeval {
+        None => String::from("void"),
+        Some(val) => val.name.clone(),
+    };
+}
+
+struct Checker {
+    errors: Vec<Diagnostic>,
+    /// variables whose type could not be inferred, their uses are not reported again.
+    untyped: Vec<String>,
+    /// symbols given to the functions which are not mangled.
+    symbols: Vec<(String, FunctionDeclaration)>,
+    /// functions and lambdas being checked, the innermost last.
+    enclosing: Vec<String>,
+    /// variables of the enclosing functions along with the function declaring them,
+    /// nested functions and lambdas are lifted to the top level and cannot access them.
+    captures: Vec<(String, String)>,
+}
+
+impl Checker {
+
+    fn error(&mut self, code: Code, message: String, span: Span) {
+        self.errors.push(Diagnostic::error(code, message, span));
+    }
+
+    /// Scope of a function or lambda nested in `scope`, which only sees the globals of the enclosing functions.
+    /// The variables left out are recorded as captures, to report their uses.
+    fn nested_scope<'a>(&mut self, scope: &'a Scope<'a>) -> Scope<'a> {
+        let mut res = scope.child();
+        let owner = match self.enclosing.last() {
+            None => return res,
+            Some(val) => val.clone(),
+        };
+        for scope in scope.variable_scopes() {
+            if scope.is_global() || scope.parent.is_none() {
+                break;
+            }
+            self.captures.extend(scope.variables.iter().map(|var| (var.name.clone(), owner.clone())));
+        }
+        res.hides_locals = true;
+        return res;
+    }
+
+    /// Reports the use of `name` when it is a variable of an enclosing function, returning whether it is.
+    fn check_capture(&mut self, name: &String, span: Span) -> bool {
+        let owner = match self.captures.iter().rev().find(|(var, _)| var == name) {
+            None => return false,
+            Some((_, owner)) => owner.clone(),
+        };
+        let error = Diagnostic::error(Code::Unsupported, format!("cannot capture '{}' from the enclosing {}", name, owner), span)
+            .with_note("nested functions and lambdas only access their parameters, their variables and the globals");
+        self.errors.push(error);
+        return true;
+    }
+
+    /// Type of a variable, `None` when it is unknown.
+    fn variable_type(&mut self, name: &String, scope: &Scope, span: Span) -> Option<Type> {
+        if let Some(value) = get_constant(name, scope) {
+            return self.expression_type(&value, scope, span);
+        }
+        return match get_variable_type(name, scope) {
+            Ok(val) => Some(val),
+            Err(..) if self.untyped.contains(name) => None,
+            Err(..) if self.check_capture(name, span) => None,
+            Err(e) => {
+                self.error(Code::UnknownVariable, e, span);
+                None
+            },
+        };
+    }
+
+    /// Return type of the function matching the call, `Some(None)` for functions without return type.
+    fn call_type(&mut self, name: &String, children: &Vec<parser::Ast>, scope: &Scope, span: Span) -> Option<Option<Type>> {
+        let mut types = Vec::<Type>::new();
+        let mut typed = true;
+        for child in children {
+            match self.expression_type(child, scope, span) {
+                None => typed = false,
+                Some(val) => types.push(val),
+            };
+        }
+        if !typed {
+            return None;
+        }
+
+        let arguments = types.iter().map(|t| t.name.clone()).collect::<Vec<String>>().join(", ");
+        return match resolve_overload(name, &types, scope) {
+            Ok(Some(dec)) => Some(dec.return_type),
+            Ok(None) if is_array_length(name, &types) => Some(Some(int_type())),
+            Ok(None) if is_array_release(name, &types) => Some(None),
+            Ok(None) if is_formatted_print(name, &types) => {
+                self.check_format(children, &types, span);
+                Some(None)
+            },
+            Ok(None) if is_map_operation(name, &types) && name == "contains" => Some(Some(bool_type())),
+            Ok(None) if is_map_operation(name, &types) => Some(None),
+            Ok(None) => {
+                self.error(Code::UnknownFunction, format!("no function matching {}({})", name, arguments), span);
+                None
+            },
+            Err(candidates) => {
+                let mut error = Diagnostic::error(Code::AmbiguousCall, format!("ambiguous call to {}({})", name, arguments), span);
+                for dec in candidates {
+                    let parameters = dec.parameters.iter().map(|t| t.name.clone()).collect::<Vec<String>>().join(", ");
+                    error = error.with_note(format!("candidate: {}({})", dec.name, parameters).as_str());
+                }
+                self.errors.push(error);
+                None
+            },
+        };
+    }
+
+    /// Type of the expression, errors are reported once where they occur and `None` is returned.
+    fn expression_type(&mut self, expression: &parser::Ast, scope: &Scope, span: Span) -> Option<Type> {
+        return match expression {
+            parser::Ast::Int(..) => Some(int_type()),
+            parser::Ast::Float(..) => Some(float_type()),
+            parser::Ast::Bool(..) => Some(bool_type()),
+            parser::Ast::Str(..) => Some(string_type()),
+            parser::Ast::ArrayValue(children) => {
+                let mut element: Option<Type> = None;
+                let mut typed = true;
+                for child in children {
+                    match (self.expression_type(child, scope, span), &element) {
+                        (None, _) => typed = false,
+                        (Some(val), Some(previous)) if &val != previous => {
+                            self.error(Code::MismatchedTypes, format!("mismatching types '{}' and '{}' in array value", previous, val), span);
+                            typed = false;
+                        },
+                        (Some(val), _) => element = Some(val),
+                    };
+                }
+                match element {
+                    None if children.is_empty() => {
+                        self.error(Code::MismatchedTypes, String::from("cannot infer the type of an empty array"), span);
+                        None
+                    },
+                    Some(element) if typed => Some(array_type(element, Some(children.len() as u64))),
+                    _ => None,
+                }
+            },
+            parser::Ast::ArrayAccess { variable, index } => {
+                let index = self.expression_type(index, scope, span);
+                match (self.variable_type(variable, scope, span), index) {
+                    (Some(Type { map: Some((key, value)), .. }), index) => {
+                        match index {
+                            Some(val) if val != *key => self.error(Code::MismatchedTypes, format!("map key must be a {}, got {}", key, val), span),
+                            _ => (),
+                        };
+                        Some(*value)
+                    },
+                    (_, Some(val)) if val != int_type() => {
+                        self.error(Code::MismatchedTypes, format!("array index must be an int, got {}", val), span);
+                        None
+                    },
+                    (None, _) => None,
+                    (Some(Type { element: Some(element), .. }), _) => Some(*element),
+                    (Some(val), _) => {
+                        self.error(Code::MismatchedTypes, format!("cannot index variable '{}' of type {}", variable, val), span);
+                        None
+                    },
+                }
+            },
+            parser::Ast::MapValue(entries) => {
+                let mut res: Option<Type> = None;
+                let mut typed = true;
+                for (key, value) in entries {
+                    let typeval = match (self.expression_type(key, scope, span), self.expression_type(value, scope, span)) {
+                        (Some(key), Some(value)) => map_type(key, value),
+                        _ => {
+                            typed = false;
+                            continue;
+                        },
+                    };
+                    match &res {
+                        Some(previous) if &typeval != previous => {
+                            self.error(Code::MismatchedTypes, format!("mismatching entries '{}' and '{}' in map value", previous, typeval), span);
+                            typed = false;
+                        },
+                        _ => res = Some(typeval),
+                    };
+                }
+                match res {
+                    None if entries.is_empty() => {
+                        self.error(Code::MismatchedTypes, String::from("cannot infer the type of an empty map"), span);
+                        None
+                    },
+                    Some(typeval) if typed => match get_type(typeval.name.clone(), scope) {
+                        Err(e) => {
+                            self.error(Code::UnknownType, e, span);
+                            None
+                        },
+                        Ok(val) => Some(val),
+                    },
+                    _ => None,
+                }
+            },
+            parser::Ast::Variable(var) => self.variable_type(&var.name, scope, span),
+            parser::Ast::FunctionCall { name, children, span } => match self.call_type(name, children, scope, *span) {
+                None => None,
+                Some(None) => {
+                    self.error(Code::MismatchedTypes, format!("function {} has no return value and cannot be used as an expression", name), *span);
+                    None
+                },
+                Some(val) => val,
+            },
+            parser::Ast::FunctionReference(name) => match get_function_by_effective_name(name, scope) {
+                Some(dec) => Some(function_type(&dec)),
+                None => {
+                    self.error(Code::UnknownFunction, format!("undefined symbol {}", name), span);
+                    None
+                },
+            },
+            parser::Ast::Lambda { parameters, return_type, body, span } => {
+                let parameter_types = match convert_params(parameters, scope) {
+                    Err(e) => {
+                        self.error(Code::UnknownType, e, *span);
+                        return None;
+                    },
+                    Ok(val) => val,
+                };
+                let return_type = match convert_type(return_type, scope) {
+                    Err(e) => {
+                        self.error(Code::UnknownType, e, *span);
+                        return None;
+                    },
+                    Ok(val) => val,
+                };
+                let captures = self.captures.len();
+                let mut lambda_scope = self.nested_scope(scope);
+                for (param, typeval) in parameters.iter().zip(parameter_types.iter()) {
+                    lambda_scope.variables.push(Variable { name: param.name.clone(), typeval: typeval.clone() });
+                }
+                self.enclosing.push(String::from("lambda"));
+                let body_type = self.expression_type(body, &lambda_scope, *span);
+                self.enclosing.pop();
+                self.captures.truncate(captures);
+                let body_type = match body_type {
+                    None => return None,
+                    Some(val) => val,
+                };
+                if let Some(expected) = &return_type {
+                    if expected != &body_type {
+                        self.error(Code::MismatchedTypes, format!("mismatching return type for lambda, expected {}, got {}", expected, body_type), *span);
+                    }
+                }
+                Some(function_type(&FunctionDeclaration {
+                    name: String::from("lambda"),
+                    parameters: parameter_types,
+                    return_type: Some(return_type.unwrap_or(body_type)),
+                    implemented: true,
+                }))
+            },
+            parser::Ast::Cast { child, typename } => match (self.expression_type(child, scope, span), get_type(typename.clone(), scope)) {
+                (Some(..), Ok(val)) => Some(val),
+                (_, Err(e)) => {
+                    self.error(Code::UnknownType, e, span);
+                    None
+                },
+                _ => None,
+            },
+            parser::Ast::NewArray { typename, size } => {
+                match self.expression_type(size, scope, span) {
+                    Some(val) if val != int_type() => self.error(Code::MismatchedTypes, format!("array size must be an int, got {}", val), span),
+                    _ => (),
+                };
+                match get_type(typename.clone(), scope) {
+                    Err(e) => {
+                        self.error(Code::UnknownType, e, span);
+                        None
+                    },
+                    Ok(element) if element.is_record() => {
+                        self.error(Code::Unsupported, format!("arrays of records cannot be created with new, got {}", element), span);
+                        None
+                    },
+                    Ok(element) => Some(array_type(element, None)),
+                }
+            },
+            parser::Ast::MemberAccess { record, field } => match self.expression_type(record, scope, span) {
+                None => None,
+                Some(val) => match member_type(&val, field) {
+                    Err(e) => {
+                        self.error(Code::UnknownField, e, span);
+                        None
+                    },
+                    Ok(val) => Some(val),
+                },
+            },
+            parser::Ast::UnaryPlus { child } | parser::Ast::UnaryMinus { child } => match self.expression_type(child, scope, span) {
+                Some(val) if !is_numeric(&val) => {
+                    self.error(Code::MismatchedTypes, format!("operator {} is not defined on {}", operator(expression), val), span);
+                    None
+                },
+                val => val,
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right }
+            | parser::Ast::And { left, right }
+            | parser::Ast::Or { left, right } => {
+                let left = self.expression_type(left, scope, span);
+                let right = self.expression_type(right, scope, span);
+                let (left, right) = match (left, right) {
+                    (Some(left), Some(right)) => (left, right),
+                    _ => return None,
+                };
+                let numeric = is_numeric(&left) && is_numeric(&right);
+                let valid = match expression {
+                    parser::Ast::EqualTo {..} | parser::Ast::NotEqualTo {..} => numeric || (left == right && !left.is_record()),
+                    parser::Ast::Modulo {..} => left == int_type() && right == int_type(),
+                    parser::Ast::And {..} | parser::Ast::Or {..} => left == bool_type() && right == bool_type(),
+                    _ => numeric,
+                };
+                if !valid {
+                    self.error(Code::MismatchedTypes, format!("operator {} is not defined on {} and {}", operator(expression), left, right), span);
+                    return None;
+                }
+                match expression {
+                    parser::Ast::Addition {..}
+                    | parser::Ast::Substraction {..}
+                    | parser::Ast::Multiplication {..}
+                    | parser::Ast::Division {..}
+                    | parser::Ast::Modulo {..}
+                        => Some(if right == float_type() { right } else { left }),
+                    _ => Some(bool_type()),
+                }
+            },
+            val => {
+                self.error(Code::Unsupported, format!("unsupported expression {:?}", val), span);
+                None
+            },
+        };
+    }
+
+    /// Checks the condition of an `if` or a `while`.
+    fn check_condition(&mut self, condition: &parser::Ast, scope: &Scope, span: Span) {
+        match self.expression_type(condition, scope, span) {
+            Some(val) if val != bool_type() => self.error(Code::MismatchedTypes, format!("condition must be a bool, got {}", val), span),
+            _ => (),
+        };
+    }
+
+    /// Checks the format of a formatted `print` against the values of `children`, of type `types`.
+    fn check_format(&mut self, children: &[parser::Ast], types: &[Type], span: Span) {
+        let pieces = match &children[0] {
+            parser::Ast::Str(format) => formats::parse(format),
+            _ => Err(String::from("print expects the format as a string literal")),
+        };
+        let pieces = match pieces {
+            Err(e) => return self.error(Code::InvalidFormat, e, span),
+            Ok(val) => val,
+        };
+        let placeholders = pieces
+            .into_iter()
+            .filter_map(|piece| match piece {
+                Piece::Value(precision) => Some(precision),
+                Piece::Text(..) => None,
+            })
+            .collect::<Vec<Option<usize>>>();
+        if placeholders.len() != types.len() - 1 {
+            let message = format!("expected {} values for the placeholders of the format, got {}", placeholders.len(), types.len() - 1);
+            return self.error(Code::InvalidFormat, message, span);
+        }
+        for (precision, typeval) in placeholders.into_iter().zip(&types[1..]) {
+            if let Err(e) = formats::check_value(typeval, precision) {
+                self.error(Code::InvalidFormat, e, span);
+            }
+        }
+    }
+
+    /// Checks that the cases of a `match` list constants of the type of `value`, each of them once,
+    /// and that every value is handled, by the cases or by an `else` branch.
+    fn check_match(&mut self, value: &parser::Ast, cases: &Vec<(Vec<parser::Ast>, Vec<parser::Ast>)>, default: &Option<Vec<parser::Ast>>, scope: &Scope, return_type: &Option<Type>, span: Span) {
+        let typeval = match self.expression_type(value, scope, span) {
+            Some(val) if ![int_type(), string_type(), bool_type()].contains(&val) => {
+                self.error(Code::MismatchedTypes, format!("can only match ints, strings and bools, got {}", val), span);
+                None
+            },
+            val => val,
+        };
+
+        let mut matched = Vec::<String>::new();
+        for (values, children) in cases {
+            for case in values {
+                let case = match evaluate_constant(case, scope) {
+                    Err(e) => {
+                        self.error(Code::NotConstant, e, span);
+                        continue;
+                    },
+                    Ok(val) => val,
+                };
+                match (&typeval, self.expression_type(&case, scope, span)) {
+                    (Some(expected), Some(val)) if expected != &val
+                        => self.error(Code::MismatchedTypes, format!("mismatching type for case, expected {}, got {}", expected, val), span),
+                    _ => (),
+                };
+                let case = format!("{:?}", case);
+                if matched.contains(&case) {
+                    self.error(Code::Redeclaration, format!("{} is matched by several cases", case), span);
+                }
+                matched.push(case);
+            }
+            self.check_statements(children, &mut scope.child(), return_type, span);
+        }
+        if let Some(children) = default {
+            self.check_statements(children, &mut scope.child(), return_type, span);
+        }
+
+        let exhaustive = default.is_some() || (typeval == Some(bool_type()) && [true, false].iter().all(|val| matched.contains(&format!("{:?}", parser::Ast::Bool(*val)))));
+        if !exhaustive && typeval.is_some() {
+            self.error(Code::NonExhaustiveMatch, String::from("match does not handle every value, missing else"), span);
+        }
+    }
+
+    /// Type of `{}` assigned to `target`, the map type of the target.
+    fn empty_map_type(&mut self, target: &parser::Ast, scope: &Scope, span: Span) -> Option<Type> {
+        let typeval = match target {
+            // unknown types are reported by the declaration.
+            parser::Ast::Variable(parser::Variable { typename: Some(parser_type), .. }) => match convert_parser_type(parser_type, scope) {
+                Err(..) => return None,
+                Ok(val) => Some(val),
+            },
+            parser::Ast::Variable(var) => get_variable_type(&var.name, scope).ok(),
+            parser::Ast::ArrayAccess { .. } | parser::Ast::MemberAccess { .. } => self.expression_type(target, scope, span),
+            _ => None,
+        };
+        return match typeval {
+            Some(val) if val.map.is_some() => Some(val),
+            _ => {
+                self.error(Code::MismatchedTypes, String::from("cannot infer the type of an empty map"), span);
+                None
+            },
+        };
+    }
+
+    /// Declares a variable with an explicit type (`buf: int[10]`), returning its type.
+    fn declare_variable(&mut self, var: &parser::Variable, scope: &mut Scope, span: Span) -> Option<Type> {
+        let typeval = match &var.typename {
+            None => return None,
+            Some(parser_type) => match convert_parser_type(parser_type, scope) {
+                Err(e) => {
+                    self.error(Code::UnknownType, e, span);
+                    self.untyped.push(var.name.clone());
+                    return None;
+                },
+                Ok(val) => val,
+            },
+        };
+
+        match get_local_variable_type(&var.name, scope) {
+            Some(t) if t != typeval => {
+                self.error(Code::Redeclaration, format!("redeclaration of variable '{}' with type {}, previously {}", var.name, typeval, t), span);
+                return Some(t);
+            },
+            Some(..) => (),
+            None => scope.variables.push(Variable { name: var.name.clone(), typeval: typeval.clone() }),
+        };
+        return Some(typeval);
+    }
+
+    /// Declares the function in `scope`, checking it against a previous declaration with the same signature.
+    /// Binds `symbol` to the function, a symbol can only be shared by the declaration and the implementation of a function.
+    fn declare_symbol(&mut self, symbol: &String, dec: &FunctionDeclaration, span: Span) {
+        match self.symbols.iter().find(|(s, _)| s == symbol) {
+            Some((_, previous)) if previous != dec => self.error(Code::Redeclaration, format!("redeclaration of symbol {}", symbol), span),
+            Some(..) => (),
+            None => self.symbols.push((symbol.clone(), dec.clone())),
+        };
+    }
+
+    /// Declares the function in `scope`, a function is implemented once but its header can be repeated,
+    /// before or after the implementation, as when several files declare it.
+    fn declare_function(&mut self, dec: FunctionDeclaration, scope: &mut Scope, span: Span) {
+        let previous = scope.functions.iter().filter(|f| **f == dec).collect::<Vec<&FunctionDeclaration>>();
+        if dec.implemented && previous.iter().any(|f| f.implemented) {
+            self.error(Code::Redeclaration, format!("redeclaration of function {}", dec), span);
+        } else if let Some(previous) = previous.iter().find(|f| f.return_type != dec.return_type) {
+            let message = format!(
+                "invalid return type for function {}, expected {}, found {}",
+                dec,
+                type_name(&previous.return_type),
+                type_name(&dec.return_type),
+            );
+            self.error(Code::Redeclaration, message, span);
+        }
+        scope.functions.push(dec);
+    }
+
+    fn check_function(&mut self, function: &parser::Ast, scope: &mut Scope) {
+        let (name, children, parameters, return_type, symbol, span) = match function {
+            parser::Ast::FunctionDeclaration { name, children, parameters, return_type, symbol, span } => (name, children, parameters, return_type, symbol, *span),
+            _ => return,
+        };
+        let return_type = match convert_type(return_type, scope) {
+            Err(e) => return self.error(Code::UnknownType, e, span),
+            Ok(val) => val,
+        };
+        let parameter_types = match convert_params(parameters, scope) {
+            Err(e) => return self.error(Code::UnknownType, e, span),
+            Ok(val) => val,
+        };
+
+        let dec = FunctionDeclaration {
+            name: name.clone(),
+            parameters: parameter_types.clone(),
+            return_type: return_type.clone(),
+            implemented: true,
+        };
+        if let Some(symbol) = symbol {
+            self.declare_symbol(symbol, &dec, span);
+        }
+        self.declare_function(dec, scope, span);
+
+        let captures = self.captures.len();
+        let mut sub_scope = self.nested_scope(scope);
+        for (param, typeval) in parameters.iter().zip(parameter_types) {
+            sub_scope.variables.push(Variable { name: param.name.clone(), typeval });
+        }
+
+        let untyped = self.untyped.clone();
+        self.enclosing.push(format!("function '{}'", name));
+        self.check_statements(children, &mut sub_scope, &return_type, span);
+        self.enclosing.pop();
+        self.captures.truncate(captures);
+        self.untyped = untyped;
+
+        if return_type.is_some() {
+            if let Some((span, place)) = flow::missing_return(children, span, "the function") {
+                self.error(Code::InvalidReturn, format!("function {} can reach the end of {} without returning a value", name, place), span);
+            }
+        }
+    }
+
+    fn check_statements(&mut self, children: &Vec<parser::Ast>, scope: &mut Scope, return_type: &Option<Type>, parent_span: Span) {
+        for child in children {
+            let span = child.span().unwrap_or(parent_span);
+            match child {
+                parser::Ast::FunctionDeclaration { .. } => {
+                    self.check_function(child, scope);
+                },
+                parser::Ast::FunctionHeader { span, .. } if !scope.is_global() => {
+                    self.error(Code::Unsupported, String::from("cannot create nested function declarations"), *span);
+                },
+                parser::Ast::FunctionHeader { name, parameters, return_type, symbol, span } => {
+                    let parameters = match convert_params(parameters, scope) {
+                        Err(e) => {
+                            self.error(Code::UnknownType, e, *span);
+                            continue;
+                        },
+                        Ok(val) => val,
+                    };
+                    let return_type = match convert_type(return_type, scope) {
+                        Err(e) => {
+                            self.error(Code::UnknownType, e, *span);
+                            continue;
+                        },
+                        Ok(val) => val,
+                    };
+                    let dec = FunctionDeclaration { name: name.clone(), parameters, return_type, implemented: false };
+                    if let Some(symbol) = symbol {
+                        self.declare_symbol(symbol, &dec, *span);
+                    }
+                    self.declare_function(dec, scope, *span);
+                },
+                parser::Ast::Variable(var) if var.typename.is_some() => {
+                    self.declare_variable(var, scope, span);
+                },
+                parser::Ast::ConstDeclaration { name, value, span } => {
+                    if scope.constants.iter().any(|(n, _)| n == name) {
+                        self.error(Code::Redeclaration, format!("redeclaration of constant '{}'", name), *span);
+                        continue;
+                    }
+                    match evaluate_constant(value, scope) {
+                        Err(e) => self.error(Code::NotConstant, e, *span),
+                        Ok(value) => scope.constants.push((name.clone(), value)),
+                    };
+                },
+                parser::Ast::RecordDeclaration { name, fields, span } => {
+                    if let Err(e) = declare_record(name, fields, scope) {
+                        self.error(Code::InvalidRecord, e, *span);
+                    }
+                },
+                parser::Ast::Assignement { variable, expression, span } => {
+                    let span = *span;
+                    let value = match &**expression {
+                        parser::Ast::MapValue(entries) if entries.is_empty() => self.empty_map_type(variable, scope, span),
+                        expression => self.expression_type(expression, scope, span),
+                    };
+                    match &**variable {
+                        parser::Ast::Variable(var) if var.typename.is_none() && get_constant(&var.name, scope).is_some() => {
+                            self.error(Code::InvalidAssignment, format!("cannot assign a value to the constant '{}'", var.name), span);
+                        },
+                        parser::Ast::Variable(var) => {
+                            let target = match self.declare_variable(var, scope, span) {
+                                Some(val) => Ok(val),
+                                None => get_variable_type(&var.name, scope),
+                            };
+                            match (target, value) {
+                                (Ok(target), Some(value)) if !is_assignable(&target, &value)
+                                    => self.error(Code::MismatchedTypes, format!("mismatching type for variable '{}', expected {}, got {}", var.name, target, value), span),
+                                (Ok(..), _) => (),
+                                (Err(..), _) if self.check_capture(&var.name, span) => (),
+                                (Err(..), Some(value)) => scope.variables.push(Variable { name: var.name.clone(), typeval: value }),
+                                (Err(..), None) => self.untyped.push(var.name.clone()),
+                            };
+                        },
+                        access @ parser::Ast::ArrayAccess { variable, .. } => {
+                            match (self.expression_type(access, scope, span), value) {
+                                (Some(target), Some(value)) if target != value
+                                    => self.error(Code::MismatchedTypes, format!("mismatching type for {}[], expected {}, got {}", variable, target, value), span),
+                                _ => (),
+                            };
+                        },
+                        access @ parser::Ast::MemberAccess { field, .. } => {
+                            match (self.expression_type(access, scope, span), value) {
+                                (Some(target), Some(value)) if !is_assignable(&target, &value)
+                                    => self.error(Code::MismatchedTypes, format!("mismatching type for field '{}', expected {}, got {}", field, target, value), span),
+                                _ => (),
+                            };
+                        },
+                        _ => self.error(Code::InvalidAssignment, String::from("can only assign value to a variable."), span),
+                    };
+                },
+                parser::Ast::Swap { left, right, span } => {
+                    let mut types = Vec::<Type>::new();
+                    for operand in [left, right] {
+                        match &**operand {
+                            parser::Ast::Variable(var) if var.typename.is_none() && get_constant(&var.name, scope).is_some() => {
+                                self.error(Code::InvalidAssignment, format!("cannot swap the constant '{}'", var.name), *span);
+                            },
+                            parser::Ast::ArrayAccess { variable, .. } if get_variable_type(variable, scope).is_ok_and(|t| t.map.is_some()) => {
+                                self.error(Code::InvalidAssignment, String::from("cannot swap map entries"), *span);
+                            },
+                            parser::Ast::Variable(parser::Variable { typename: None, .. }) | parser::Ast::ArrayAccess { .. } | parser::Ast::MemberAccess { .. } => {
+                                if let Some(typeval) = self.expression_type(operand, scope, *span) {
+                                    types.push(typeval);
+                                }
+                            },
+                            _ => self.error(Code::InvalidAssignment, String::from("can only swap variables, array elements and fields."), *span),
+                        };
+                    }
+                    if let [left, right] = types.as_slice() {
+                        if left != right {
+                            self.error(Code::MismatchedTypes, format!("cannot swap values of types {} and {}", left, right), *span);
+                        }
+                    }
+                },
+                parser::Ast::Condition { condition, valid_branch, invalid_branch, span } => {
+                    self.check_condition(condition, scope, *span);
+                    self.check_statements(valid_branch, &mut scope.child(), return_type, *span);
+                    self.check_statements(invalid_branch, &mut scope.child(), return_type, *span);
+                },
+                parser::Ast::WhileLoop { condition, children, span } => {
+                    self.check_condition(condition, scope, *span);
+                    self.check_statements(children, &mut scope.child(), return_type, *span);
+                },
+                parser::Ast::Try { children, variable, handler, span } => {
+                    self.check_statements(children, &mut scope.child(), return_type, *span);
+                    let mut handler_scope = scope.child();
+                    handler_scope.variables.push(Variable { name: variable.clone(), typeval: string_type() });
+                    self.check_statements(handler, &mut handler_scope, return_type, *span);
+                },
+                parser::Ast::Throw { value, span } => match self.expression_type(value, scope, *span) {
+                    Some(val) if val != string_type() => self.error(Code::MismatchedTypes, format!("can only throw strings, got {}", val), *span),
+                    _ => (),
+                },
+                parser::Ast::Match { value, cases, default, span } => {
+                    self.check_match(value, cases, default, scope, return_type, *span);
+                },
+                parser::Ast::Exit { code, span } => match self.expression_type(code, scope, *span) {
+                    Some(val) if val != int_type() => self.error(Code::MismatchedTypes, format!("exit status must be an int, got {}", val), *span),
+                    _ => (),
+                },
+                parser::Ast::ReturnStatement { value, span } => {
+                    let value_type = match value {
+                        None => None,
+                        Some(value) => match self.expression_type(value, scope, *span) {
+                            None => continue,
+                            val => val,
+                        },
+                    };
+                    match (return_type, value_type) {
+                        (Some(expected), Some(val)) if expected != &val
+                            => self.error(Code::InvalidReturn, format!("mismatching return type, expected {}, got {}", expected, val), *span),
+                        (None, Some(..)) => self.error(Code::InvalidReturn, String::from("cannot return a value from a function without return type"), *span),
+                        (Some(expected), None) => self.error(Code::InvalidReturn, format!("missing return value, expected {}", expected), *span),
+                        _ => (),
+                    };
+                },
+                parser::Ast::FunctionCall { name, children, span } => {
+                    self.call_type(name, children, scope, *span);
+                },
+                parser::Ast::Write { values, span } => {
+                    for value in values {
+                        if let Some(typeval) = self.expression_type(value, scope, *span) {
+                            if formats::check_value(&typeval, None).is_err() {
+                                self.error(Code::MismatchedTypes, format!("cannot write a value of type {}", typeval), *span);
+                            }
+                        }
+                    }
+                },
+                expression => {
+                    self.expression_type(expression, scope, span);
+                },
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::{compile, CompilerOptions};
+    use crate::diagnostic::{Code, Diagnostic};
+
+    /// Errors of the semantic analysis of `source`.
+    fn errors(source: &str) -> Vec<Diagnostic> {
+        let ast = match crate::parse_str(source) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        return match compile(&ast, &CompilerOptions::default()) {
+            Err(diagnostics) => diagnostics,
+            Ok(..) => Vec::new(),
+        };
+    }
+
+    #[test]
+    fn rejects_captured_variables() {
+        let source = "function compose(a: int): int\n    inc <- fn(x: int): int => x + a\n    return a\nend\n";
+        let errors = errors(source);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code, Code::Unsupported);
+        assert_eq!(errors[0].message, "cannot capture 'a' from the enclosing function 'compose'");
+        assert_eq!(errors[0].span.line, 2);
+    }
+
+    #[test]
+    fn writes_formatted_values() {
+        assert!(errors("x <- 2.5\nwrite 2.5, true, 3, \"a\", x\n").is_empty());
+        let errors = errors("a <- [1, 2]\nwrite 1, a\n");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "cannot write a value of type int[2]");
+    }
+
+    #[test]
+    fn string_names_str() {
+        let source = "declare function puts(s: string): int as \"puts\"\nm: map[string, int]\nm[\"a\"] <- 1\nx: string <- \"a\"\nr <- puts(x)\n";
+        assert!(errors(source).is_empty());
+    }
+
+    #[test]
+    fn fixed_size_arrays_are_initialized() {
+        let strict = |source: &str| match compile(&crate::parse_str(source).unwrap(), &CompilerOptions { strict: true, ..CompilerOptions::default() }) {
+            Err(diagnostics) => diagnostics.into_iter().map(|d| d.code).collect::<Vec<Code>>(),
+            Ok(..) => Vec::new(),
+        };
+        let source = "function f(): int\n    t: int[3]\n    i <- 0\n    while i < 3\n        t[i] <- i\n        i <- i + 1\n    end\n    return t[2]\nend\n";
+        assert_eq!(strict(source), Vec::new());
+        // the other variables assigned in a loop may still be read unassigned.
+        let source = "function f(n: int): int\n    x: int\n    while n > 0\n        x <- n\n        n <- n - 1\n    end\n    return x\nend\n";
+        assert_eq!(strict(source), vec![Code::UninitializedVariable]);
+    }
+
+    #[test]
+    fn lambdas_access_globals() {
+        assert!(errors("step <- 2\nfunction f(a: int): int\n    inc <- fn(x: int): int => x + step\n    return a\nend\n").is_empty());
+        assert!(errors(include_str!("../../../examples/test_lambdas.algo")).is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+use super::super::lexer::Span;
+
+/// Line of the generated assembly, along with the statement of the source it was generated for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mapping {
+    /// line of the assembly, starting at 1.
+    pub line: usize,
+    pub span: Span,
+}
+
+/// Lines of the generated assembly generated for a statement, in the order of the assembly.
+/// Instructions and labels of the prologues and epilogues of the functions are not mapped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    pub mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Statement the line of the assembly was generated for.
+    pub fn span(&self, line: usize) -> Option<Span> {
+        return self.mappings.iter().find(|m| m.line == line).map(|m| m.span);
+    }
+
+    /// JSON object of the mappings, `{"mappings": [{"line": 12, "source_line": 3, "source_column": 1, "file": 0}]}`.
+    pub fn to_json(&self) -> String {
+        let mappings = self.mappings
+            .iter()
+            .map(|m| format!(
+                "{{\"line\": {}, \"source_line\": {}, \"source_column\": {}, \"file\": {}}}",
+                m.line, m.span.line, m.span.column, m.span.file,
+            ))
+            .collect::<Vec<String>>();
+        return format!("{{\"mappings\": [{}]}}\n", mappings.join(", "));
+    }
+}
+
+/// Source map of `code`, from the offsets of the lines generated for each statement.
+pub(super) fn build_source_map(code: &str, offsets: &Vec<(usize, Span)>) -> SourceMap {
+    let mut mappings = Vec::<Mapping>::new();
+    let mut line = 1;
+    let mut position = 0;
+    for (offset, span) in offsets {
+        line += code[position..*offset].matches('\n').count();
+        position = *offset;
+        mappings.push(Mapping { line, span: *span });
+    }
+    return SourceMap { mappings };
+}
@@ -0,0 +1,152 @@
+use std::collections::{HashMap, HashSet};
+
+use super::super::diagnostic::{Code, Diagnostic};
+use super::super::lexer::Span;
+use super::{codegen, ir, CompilerContext};
+
+/// Bytes pushed by a call besides the frame of the callee, its return address and the saved `rbp`.
+const CALL_SIZE: u64 = 16;
+
+struct Frames<'a> {
+    context: &'a CompilerContext,
+    functions: HashMap<String, &'a ir::Function>,
+    /// functions of the module called by each function, in the order of their first call.
+    callees: HashMap<String, Vec<String>>,
+    /// worst case stack use of the functions already visited, along with the calls reaching it.
+    usages: HashMap<String, (u64, Vec<String>)>,
+}
+
+impl<'a> Frames<'a> {
+    /// `f(int)` rather than the symbol of the function.
+    fn display_name(&self, name: &String) -> String {
+        return match self.context.declarations.get(name) {
+            Some(dec) => dec.to_string(),
+            None => name.clone(),
+        };
+    }
+
+    /// First statement of the function, the IR not locating the functions themselves.
+    fn span(&self, name: &String) -> Span {
+        return self.functions[name]
+            .instructions
+            .iter()
+            .find_map(|instr| match instr {
+                ir::Instr::Line(span) => Some(*span),
+                _ => None,
+            })
+            .unwrap_or_default();
+    }
+
+    fn frame(&self, name: &String) -> u64 {
+        return codegen::frame_size(self.functions[name]) + CALL_SIZE;
+    }
+
+    /// Whether `to` is called by `from`, directly or not.
+    fn reaches(&self, from: &String, to: &String) -> bool {
+        let mut visited = HashSet::<&String>::new();
+        let mut pending = vec![from];
+        while let Some(name) = pending.pop() {
+            for callee in &self.callees[name] {
+                if callee == to {
+                    return true;
+                }
+                if visited.insert(callee) {
+                    pending.push(callee);
+                }
+            }
+        }
+        return false;
+    }
+
+    /// Worst case stack use of a call to `name`, the recursive calls counting once.
+    fn usage(&mut self, name: &String) -> (u64, Vec<String>) {
+        if let Some(usage) = self.usages.get(name) {
+            return usage.clone();
+        }
+        let mut deepest = (0, Vec::new());
+        for callee in self.callees[name].clone() {
+            if self.reaches(&callee, name) {
+                continue;
+            }
+            let usage = self.usage(&callee);
+            if usage.0 > deepest.0 {
+                deepest = usage;
+            }
+        }
+        let mut calls = vec![name.clone()];
+        calls.extend(deepest.1);
+        let usage = (self.frame(name) + deepest.0, calls);
+        self.usages.insert(name.clone(), usage.clone());
+        return usage;
+    }
+}
+
+/// Warnings for the frames exceeding `budget` bytes, for the program whose calls may exceed it,
+/// and for the recursive functions, whose stack use cannot be bounded.
+/// The frames are those laid out by the x86-64 backends.
+pub(super) fn check_stack(context: &CompilerContext, budget: u64) -> Result<Vec<Diagnostic>, String> {
+    let functions = match ir::lower_module(context) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let mut frames = Frames {
+        context,
+        functions: functions.iter().map(|f| (f.name.clone(), f)).collect(),
+        callees: HashMap::new(),
+        usages: HashMap::new(),
+    };
+    for f in &functions {
+        let mut callees = Vec::<String>::new();
+        for instr in &f.instructions {
+            if let ir::Instr::Call { function, .. } = instr {
+                if frames.functions.contains_key(function) && !callees.contains(function) {
+                    callees.push(function.clone());
+                }
+            }
+        }
+        frames.callees.insert(f.name.clone(), callees);
+    }
+
+    let mut diagnostics = Vec::<Diagnostic>::new();
+    for f in &functions {
+        let frame = frames.frame(&f.name);
+        if frame > budget {
+            let message = format!("the frame of {} takes {} bytes, over the stack budget of {} bytes", frames.display_name(&f.name), frame, budget);
+            diagnostics.push(Diagnostic::warning(Code::StackBudget, message, frames.span(&f.name)));
+        }
+    }
+
+    // functions calling each other are reported together, by the first of them.
+    let mut reported = HashSet::<String>::new();
+    for f in &functions {
+        if reported.contains(&f.name) || !frames.reaches(&f.name, &f.name) {
+            continue;
+        }
+        let cycle = functions
+            .iter()
+            .filter(|g| frames.reaches(&f.name, &g.name) && frames.reaches(&g.name, &f.name))
+            .map(|g| g.name.clone())
+            .collect::<Vec<String>>();
+        let names = cycle.iter().map(|name| frames.display_name(name)).collect::<Vec<String>>();
+        let message = match names.len() {
+            1 => format!("{} calls itself, its stack use has no bound", names[0]),
+            _ => format!("{} call each other, their stack use has no bound", names.join(", ")),
+        };
+        diagnostics.push(
+            Diagnostic::warning(Code::UnboundedRecursion, message, frames.span(&f.name))
+                .with_note("each recursive call pushes a new frame, deep recursions overflow the stack")
+        );
+        reported.extend(cycle);
+    }
+
+    // the calls are only reported when none of their frames is.
+    let main = context.main_function.name.clone();
+    let (usage, calls) = frames.usage(&main);
+    if usage > budget && calls.iter().all(|name| frames.frame(name) <= budget) {
+        let message = format!("the program may use {} bytes of stack, over the stack budget of {} bytes", usage, budget);
+        let names = calls.iter().map(|name| frames.display_name(name)).collect::<Vec<String>>();
+        let note = format!("the deepest calls are {}", names.join(" -> "));
+        diagnostics.push(Diagnostic::warning(Code::StackBudget, message, Span::default()).with_note(&note));
+    }
+    return Ok(diagnostics);
+}
@@ -0,0 +1,82 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::CompilerContext;
+
+/// Function of a compiled module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionSymbol {
+    /// mangled name, as found in the generated code.
+    pub symbol: String,
+    /// source signature, `max(int, float[]): float`.
+    pub signature: String,
+    /// whether the module implements the function, or expects it from another module.
+    pub defined: bool,
+}
+
+/// Variable declared at the top level of a module.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariableSymbol {
+    pub name: String,
+    pub typename: String,
+    /// size in bytes.
+    pub size: u64,
+}
+
+/// What a compiled module defines and expects, sorted by symbol name.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SymbolTable {
+    pub functions: Vec<FunctionSymbol>,
+    pub variables: Vec<VariableSymbol>,
+}
+
+impl SymbolTable {
+    pub fn function(&self, symbol: &str) -> Option<&FunctionSymbol> {
+        return self.functions.iter().find(|f| f.symbol == symbol);
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&VariableSymbol> {
+        return self.variables.iter().find(|v| v.name == name);
+    }
+}
+
+/// One line per symbol, in the manner of `nm`: `T` for defined functions, `U` for extern ones, `D` for variables.
+impl Display for SymbolTable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for function in &self.functions {
+            let kind = if function.defined { "T" } else { "U" };
+            if let Err(e) = writeln!(f, "{} {} {}", kind, function.symbol, function.signature) {
+                return Err(e);
+            }
+        }
+        for variable in &self.variables {
+            if let Err(e) = writeln!(f, "D {} {} {}", variable.name, variable.typename, variable.size) {
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+}
+
+pub(super) fn build_symbol_table(context: &CompilerContext) -> SymbolTable {
+    let mut functions = Vec::<FunctionSymbol>::new();
+    for (symbol, dec) in &context.declarations {
+        let parameters = dec.parameters.iter().map(|t| t.name.clone()).collect::<Vec<String>>().join(", ");
+        functions.push(FunctionSymbol {
+            symbol: symbol.clone(),
+            signature: match &dec.return_type {
+                None => format!("{}({})", dec.name, parameters),
+                Some(val) => format!("{}({}): {}", dec.name, parameters, val.name),
+            },
+            defined: context.functions.iter().chain(std::iter::once(&context.main_function)).any(|f| &f.name == symbol),
+        });
+    }
+    functions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+
+    let mut variables = context.globals
+        .iter()
+        .map(|g| VariableSymbol { name: g.variable.name.clone(), typename: g.variable.typeval.name.clone(), size: g.variable.typeval.size })
+        .collect::<Vec<VariableSymbol>>();
+    variables.sort_by(|a, b| a.name.cmp(&b.name));
+
+    return SymbolTable { functions, variables };
+}
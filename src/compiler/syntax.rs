@@ -0,0 +1,169 @@
+/// Assembly dialect of the generated code.
+/// The code generator works in intel syntax, instructions are rewritten when emitting AT&T.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AsmSyntax {
+    /// nasm flavored intel syntax.
+    #[default]
+    Intel,
+    /// GNU `as` flavored AT&T syntax.
+    Att,
+}
+
+const REGISTERS_64: [&str; 16] = ["rax", "rbx", "rcx", "rdx", "rsi", "rdi", "rbp", "rsp", "r8", "r9", "r10", "r11", "r12", "r13", "r14", "r15"];
+const REGISTERS_32: [&str; 16] = ["eax", "ebx", "ecx", "edx", "esi", "edi", "ebp", "esp", "r8d", "r9d", "r10d", "r11d", "r12d", "r13d", "r14d", "r15d"];
+const REGISTERS_8: [&str; 16] = ["al", "bl", "cl", "dl", "sil", "dil", "bpl", "spl", "r8b", "r9b", "r10b", "r11b", "r12b", "r13b", "r14b", "r15b"];
+
+/// Size in bytes of a general purpose register, `None` if `name` is not one.
+fn register_size(name: &str) -> Option<u64> {
+    if REGISTERS_64.contains(&name) {
+        return Some(8);
+    } else if REGISTERS_32.contains(&name) {
+        return Some(4);
+    } else if REGISTERS_8.contains(&name) {
+        return Some(1);
+    }
+    return None;
+}
+
+fn is_register(name: &str) -> bool {
+    return register_size(name).is_some()
+        || name.strip_prefix("xmm").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+}
+
+fn is_number(operand: &str) -> bool {
+    let digits = operand.strip_prefix('-').unwrap_or(operand);
+    return digits.starts_with(|c: char| c.is_ascii_digit());
+}
+
+fn size_suffix(size: u64) -> &'static str {
+    return match size {
+        1 => "b",
+        2 => "w",
+        4 => "l",
+        _ => "q",
+    };
+}
+
+/// Symbol name, quoted when it is not a plain identifier.
+pub fn symbol(name: &str) -> String {
+    if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+        return name.to_string();
+    }
+    return format!("\"{}\"", name);
+}
+
+/// Converts an intel memory operand (`[rbp - 8 + rcx*4]`, `[rel label + 8]`) to `-8(%rbp,%rcx,4)`.
+fn memory_operand(address: &str) -> String {
+    let inner = address.trim_start_matches('[').trim_end_matches(']');
+    if let Some(label) = inner.strip_prefix("rel ") {
+        return match label.split_once(" + ") {
+            Some((label, offset)) => format!("{}+{}(%rip)", symbol(label.trim()), offset.trim()),
+            None => format!("{}(%rip)", symbol(label.trim())),
+        };
+    }
+
+    let mut base: Option<&str> = None;
+    let mut index: Option<(&str, &str)> = None;
+    let mut displacement: i64 = 0;
+    let mut negative = false;
+    for term in inner.split_whitespace() {
+        match term {
+            "+" => negative = false,
+            "-" => negative = true,
+            term => {
+                if let Some((register, scale)) = term.split_once('*') {
+                    index = Some((register, scale));
+                } else if is_register(term) {
+                    base = Some(term);
+                } else if let Ok(value) = term.parse::<i64>() {
+                    displacement += if negative { -value } else { value };
+                }
+            },
+        };
+    }
+
+    let mut res = if displacement != 0 { displacement.to_string() } else { String::new() };
+    res.push('(');
+    if let Some(base) = base {
+        res.push_str(format!("%{}", base).as_str());
+    }
+    if let Some((register, scale)) = index {
+        res.push_str(format!(",%{},{}", register, scale).as_str());
+    }
+    res.push(')');
+    return res;
+}
+
+/// Rewrites a single intel instruction in AT&T syntax.
+pub fn to_att(instruction: &str) -> String {
+    let (mnemonic, operands) = match instruction.split_once(' ') {
+        None => (instruction, ""),
+        Some(val) => val,
+    };
+
+    if mnemonic == "cqo" {
+        return String::from("cqto");
+    }
+
+    let is_branch = mnemonic == "call" || mnemonic.starts_with('j');
+    let mut converted = Vec::<String>::new();
+    let mut memory_size: Option<u64> = None;
+    let mut register_sizes = Vec::<Option<u64>>::new();
+    let mut has_register = false;
+    for operand in operands.split(", ").filter(|o| !o.is_empty()) {
+        let (size, operand) = match operand.split_once(' ') {
+            Some(("byte", rest)) => (Some(1), rest),
+            Some(("word", rest)) => (Some(2), rest),
+            Some(("dword", rest)) => (Some(4), rest),
+            Some(("qword", rest)) => (Some(8), rest),
+            _ => (None, operand),
+        };
+        if operand.starts_with('[') {
+            memory_size = size;
+            register_sizes.push(size);
+            converted.push(memory_operand(operand));
+        } else if is_register(operand) {
+            has_register = true;
+            register_sizes.push(register_size(operand));
+            converted.push(format!("%{}", operand));
+        } else if is_number(operand) {
+            register_sizes.push(None);
+            converted.push(format!("${}", operand));
+        } else {
+            register_sizes.push(None);
+            converted.push(if is_branch { symbol(operand) } else { format!("${}", symbol(operand)) });
+        }
+    }
+
+    // the size of extensions is part of the mnemonic: movzx eax, al => movzbl %al, %eax
+    let mnemonic = match (mnemonic, register_sizes.as_slice()) {
+        ("movsxd", _) => String::from("movslq"),
+        ("movzx", [Some(dest), Some(source)]) | ("movsx", [Some(dest), Some(source)]) => {
+            let kind = if mnemonic == "movzx" { "movz" } else { "movs" };
+            format!("{}{}{}", kind, size_suffix(*source), size_suffix(*dest))
+        },
+        (mnemonic, _) => match memory_size {
+            Some(size) if !has_register => format!("{}{}", mnemonic, size_suffix(size)),
+            _ => mnemonic.to_string(),
+        },
+    };
+
+    converted.reverse();
+    if converted.is_empty() {
+        return mnemonic;
+    }
+    return format!("{} {}", mnemonic, converted.join(", "));
+}
+
+/// gas `.asciz` directive, other characters than printable ones are octal escaped.
+pub fn asciz(value: &str) -> String {
+    let mut res = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_graphic() && byte != b'"' && byte != b'\\' || byte == b' ' {
+            res.push(byte as char);
+        } else {
+            res.push_str(format!("\\{:03o}", byte).as_str());
+        }
+    }
+    return format!(".asciz \"{}\"", res);
+}
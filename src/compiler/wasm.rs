@@ -0,0 +1,643 @@
+use std::collections::HashMap;
+use std::io::Write;
+
+use super::super::parser;
+use super::codegen::{cast_type, expression_type, is_float};
+use super::super::error::CompileError;
+use super::{codegen_error, write_code, written_code, Backend, CompilerContext, Function, Type, bool_type};
+
+/// Size of the linear memory, in 64KiB pages. Fixed size arrays live on a stack growing down from its end.
+const MEMORY_PAGES: u64 = 1;
+
+/// Address of the first string literal, keeps `0` as an invalid address.
+const DATA_START: u64 = 16;
+
+/// WebAssembly text module, exporting `main` and its memory.
+/// Extern functions are imported from the `env` module.
+pub struct WasmBackend;
+
+impl Backend for WasmBackend {
+    fn generate(&self, context: &CompilerContext) -> Result<String, String> {
+        return written_code(|out| visit_module(context, out));
+    }
+
+    /// Writes the functions one at a time.
+    fn write(&self, context: &CompilerContext, out: &mut dyn Write) -> Result<(), CompileError> {
+        return match visit_module(context, out) {
+            Err(e) => Err(codegen_error(e)),
+            Ok(()) => Ok(()),
+        };
+    }
+}
+
+/// Identifier of a function or local, characters not allowed in wat identifiers are replaced.
+fn identifier(name: &str) -> String {
+    let mut res = String::from("$");
+    for c in name.chars() {
+        res.push(match c {
+            '(' => '<',
+            ')' => '>',
+            c if c.is_ascii_alphanumeric() || "!#$%&'*+-./:<=>?@\\^_`|~".contains(c) => c,
+            _ => '_',
+        });
+    }
+    return res;
+}
+
+/// Identifier of a global, prefixed not to clash with the functions.
+fn global_identifier(name: &str) -> String {
+    return identifier(format!("var.{}", name).as_str());
+}
+
+/// Value type holding a variable: `f64` for floats, `i32` for everything else (addresses included).
+fn value_type(typeval: &Type) -> &'static str {
+    return if is_float(typeval) { "f64" } else { "i32" };
+}
+
+fn signature(parameters: &Vec<Type>, return_type: &Option<Type>) -> String {
+    let mut res = String::new();
+    for param in parameters {
+        res.push_str(format!(" (param {})", value_type(param)).as_str());
+    }
+    if let Some(return_type) = return_type {
+        res.push_str(format!(" (result {})", value_type(return_type)).as_str());
+    }
+    return res;
+}
+
+/// Content of a wat string literal, non printable characters are hex escaped.
+fn string_data(value: &str) -> String {
+    let mut res = String::new();
+    for byte in value.bytes().chain(std::iter::once(0)) {
+        if byte.is_ascii_graphic() && byte != b'"' && byte != b'\\' || byte == b' ' {
+            res.push(byte as char);
+        } else {
+            res.push_str(format!("\\{:02x}", byte).as_str());
+        }
+    }
+    return res;
+}
+
+/// Addresses of the module level data: string literals, global arrays and the function table.
+/// Other globals are wasm globals.
+struct ModuleLayout {
+    strings: HashMap<String, u64>,
+    arrays: HashMap<String, u64>,
+    table: HashMap<String, usize>,
+}
+
+/// Generates the code of a single function.
+/// Scalar variables are wasm locals, fixed size arrays are stored in a frame of the linear memory,
+/// addressed from `$fp`.
+struct FunctionGenerator<'a> {
+    context: &'a CompilerContext,
+    layout: &'a ModuleLayout,
+    /// locals and globals, locals shadowing the globals.
+    variables: HashMap<String, Type>,
+    /// parameters and local variables.
+    locals: Vec<String>,
+    /// offset of the fixed size arrays in the frame.
+    frame_offsets: HashMap<String, u64>,
+    frame_size: u64,
+    output: String,
+    depth: usize,
+    label_count: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+
+    fn emit(&mut self, instruction: &str) {
+        for _ in 0..self.depth {
+            self.output.push_str("  ");
+        }
+        self.output.push_str(instruction);
+        self.output.push('\n');
+    }
+
+    fn new_label_id(&mut self) -> usize {
+        self.label_count += 1;
+        return self.label_count - 1;
+    }
+
+    fn variable(&self, name: &String) -> Result<Type, String> {
+        return match self.variables.get(name) {
+            Some(typeval) => Ok(typeval.clone()),
+            None => Err(format!("codegen: unknown variable '{}'", name)),
+        };
+    }
+
+    fn expression_type(&self, expression: &parser::Ast) -> Result<Type, String> {
+        return expression_type(expression, &self.variables, self.context);
+    }
+
+    /// Loads a value of type `typeval` from the address on top of the stack.
+    fn load(&mut self, typeval: &Type) {
+        if is_float(typeval) {
+            self.emit("f64.load");
+        } else if typeval.size == 1 {
+            self.emit("i32.load8_u");
+        } else {
+            self.emit("i32.load");
+        }
+    }
+
+    /// Stores the value on top of the stack at the address below it.
+    fn store(&mut self, typeval: &Type) {
+        if is_float(typeval) {
+            self.emit("f64.store");
+        } else if typeval.size == 1 {
+            self.emit("i32.store8");
+        } else {
+            self.emit("i32.store");
+        }
+    }
+
+    /// Releases the frame of the fixed size arrays.
+    fn restore_stack(&mut self) {
+        if self.frame_size > 0 {
+            self.emit("local.get $fp");
+            self.emit(format!("i32.const {}", self.frame_size).as_str());
+            self.emit("i32.add");
+            self.emit("global.set $sp");
+        }
+    }
+
+    /// Pushes the value of the expression on the wasm stack.
+    fn visit_expression(&mut self, expression: &parser::Ast) -> Result<Type, String> {
+        match expression {
+            parser::Ast::Int(val) => self.emit(format!("i32.const {}", val).as_str()),
+            parser::Ast::Bool(val) => self.emit(format!("i32.const {}", *val as u8).as_str()),
+            parser::Ast::Float(val) => self.emit(format!("f64.const {:?}", val).as_str()),
+            parser::Ast::Variable(var) => {
+                let typeval = match self.variable(&var.name) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.visit_variable(&var.name);
+                return Ok(typeval);
+            },
+            parser::Ast::ArrayAccess { variable, index } => {
+                let element = match self.visit_element_address(variable, index) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.load(&element);
+                return Ok(element);
+            },
+            parser::Ast::FunctionReference(name) => match self.layout.table.get(name) {
+                None => return Err(format!("codegen: undefined function {}", name)),
+                Some(index) => self.emit(format!("i32.const {}", index).as_str()),
+            },
+            parser::Ast::Str(val) => match self.layout.strings.get(val) {
+                None => return Err(format!("codegen: missing address for string {:?}", val)),
+                Some(address) => self.emit(format!("i32.const {}", address).as_str()),
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                if let Err(e) = self.visit_call(name, children) {
+                    return Err(e);
+                }
+            },
+            parser::Ast::UnaryPlus { child } => return self.visit_expression(child),
+            parser::Ast::UnaryMinus { child } => {
+                let typeval = match self.expression_type(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if !is_float(&typeval) {
+                    self.emit("i32.const 0");
+                }
+                if let Err(e) = self.visit_expression(child) {
+                    return Err(e);
+                }
+                if is_float(&typeval) {
+                    self.emit("f64.neg");
+                } else {
+                    self.emit("i32.sub");
+                }
+                return Ok(typeval);
+            },
+            parser::Ast::Cast { child, typename } => {
+                if let Err(e) = self.visit_expression(child) {
+                    return Err(e);
+                }
+                let typeval = match cast_type(typename) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                self.emit(if is_float(&typeval) { "f64.convert_i32_s" } else { "i32.trunc_f64_s" });
+                return Ok(typeval);
+            },
+            parser::Ast::Addition { left, right }
+            | parser::Ast::Substraction { left, right }
+            | parser::Ast::Multiplication { left, right }
+            | parser::Ast::Division { left, right }
+            | parser::Ast::Modulo { left, right }
+            | parser::Ast::EqualTo { left, right }
+            | parser::Ast::NotEqualTo { left, right }
+            | parser::Ast::GreaterThan { left, right }
+            | parser::Ast::GreaterOrEqual { left, right }
+            | parser::Ast::LowerThan { left, right }
+            | parser::Ast::LowerOrEqual { left, right } => return self.visit_binary(expression, left, right),
+            // the right operand is only evaluated in the branch where the left one does not decide the result.
+            parser::Ast::And { left, right } | parser::Ast::Or { left, right } => {
+                if let Err(e) = self.visit_expression(left) {
+                    return Err(e);
+                }
+                self.emit("if (result i32)");
+                self.depth += 1;
+                if matches!(expression, parser::Ast::Or {..}) {
+                    self.emit("i32.const 1");
+                    self.depth -= 1;
+                    self.emit("else");
+                    self.depth += 1;
+                }
+                if let Err(e) = self.visit_expression(right) {
+                    return Err(e);
+                }
+                if matches!(expression, parser::Ast::And {..}) {
+                    self.depth -= 1;
+                    self.emit("else");
+                    self.depth += 1;
+                    self.emit("i32.const 0");
+                }
+                self.depth -= 1;
+                self.emit("end");
+            },
+            val => return Err(format!("codegen: unsupported expression {:?}", val)),
+        };
+
+        return self.expression_type(expression);
+    }
+
+    fn visit_binary(&mut self, expression: &parser::Ast, left: &parser::Ast, right: &parser::Ast) -> Result<Type, String> {
+        let left_type = match self.visit_expression(left) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let right_type = match self.visit_expression(right) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+
+        if left_type != right_type {
+            return Err(format!("codegen: implicit conversion between {} and {} is not supported", left_type, right_type));
+        }
+
+        let instruction = match (expression, is_float(&left_type)) {
+            (parser::Ast::Addition {..}, false) => "i32.add",
+            (parser::Ast::Substraction {..}, false) => "i32.sub",
+            (parser::Ast::Multiplication {..}, false) => "i32.mul",
+            (parser::Ast::Division {..}, false) => "i32.div_s",
+            (parser::Ast::Modulo {..}, false) => "i32.rem_s",
+            (parser::Ast::EqualTo {..}, false) => "i32.eq",
+            (parser::Ast::NotEqualTo {..}, false) => "i32.ne",
+            (parser::Ast::GreaterThan {..}, false) => "i32.gt_s",
+            (parser::Ast::GreaterOrEqual {..}, false) => "i32.ge_s",
+            (parser::Ast::LowerThan {..}, false) => "i32.lt_s",
+            (parser::Ast::LowerOrEqual {..}, false) => "i32.le_s",
+            (parser::Ast::Addition {..}, true) => "f64.add",
+            (parser::Ast::Substraction {..}, true) => "f64.sub",
+            (parser::Ast::Multiplication {..}, true) => "f64.mul",
+            (parser::Ast::Division {..}, true) => "f64.div",
+            (parser::Ast::EqualTo {..}, true) => "f64.eq",
+            (parser::Ast::NotEqualTo {..}, true) => "f64.ne",
+            (parser::Ast::GreaterThan {..}, true) => "f64.gt",
+            (parser::Ast::GreaterOrEqual {..}, true) => "f64.ge",
+            (parser::Ast::LowerThan {..}, true) => "f64.lt",
+            (parser::Ast::LowerOrEqual {..}, true) => "f64.le",
+            _ => return Err(String::from("codegen: modulo is not defined on floats")),
+        };
+        self.emit(instruction);
+
+        return self.expression_type(expression);
+    }
+
+    /// Pushes the value of a variable, fixed size arrays are evaluated to their address.
+    fn visit_variable(&mut self, name: &String) {
+        match self.frame_offsets.get(name) {
+            Some(offset) => {
+                let offset = *offset;
+                self.emit("local.get $fp");
+                self.emit(format!("i32.const {}", offset).as_str());
+                self.emit("i32.add");
+            },
+            None if self.locals.contains(name) => self.emit(format!("local.get {}", identifier(name)).as_str()),
+            None => match self.layout.arrays.get(name) {
+                Some(address) => self.emit(format!("i32.const {}", address).as_str()),
+                None => self.emit(format!("global.get {}", global_identifier(name)).as_str()),
+            },
+        };
+    }
+
+    /// Pushes the address of `variable[index]`, returning the type of the element.
+    fn visit_element_address(&mut self, variable: &String, index: &parser::Ast) -> Result<Type, String> {
+        let typeval = match self.variable(variable) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let element = match &typeval.element {
+            Some(element) => *element.clone(),
+            None => return Err(format!("codegen: cannot index variable '{}' of type {}", variable, typeval)),
+        };
+
+        self.visit_variable(variable);
+        if let Err(e) = self.visit_expression(index) {
+            return Err(e);
+        }
+        self.emit(format!("i32.const {}", element.size).as_str());
+        self.emit("i32.mul");
+        self.emit("i32.add");
+        return Ok(element);
+    }
+
+    fn visit_call(&mut self, name: &String, children: &Vec<parser::Ast>) -> Result<(), String> {
+        if !self.context.declarations.contains_key(name) {
+            return Err(format!("codegen: undefined function {}", name));
+        }
+        for child in children {
+            if let Err(e) = self.visit_expression(child) {
+                return Err(e);
+            }
+        }
+        self.emit(format!("call {}", identifier(name)).as_str());
+        return Ok(());
+    }
+
+    fn visit_block(&mut self, statements: &Vec<parser::Ast>) -> Result<(), String> {
+        for statement in statements {
+            if let Err(e) = self.visit_statement(statement) {
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+
+    /// Pushes the condition, checking it is a bool.
+    fn visit_condition(&mut self, condition: &parser::Ast) -> Result<(), String> {
+        return match self.visit_expression(condition) {
+            Err(e) => Err(e),
+            Ok(val) if val != bool_type() => Err(format!("codegen: condition must be a bool, got {}", val)),
+            _ => Ok(()),
+        };
+    }
+
+    fn visit_statement(&mut self, statement: &parser::Ast) -> Result<(), String> {
+        match statement {
+            parser::Ast::Assignement { variable, expression, .. } => {
+                let name = match &**variable {
+                    parser::Ast::Variable(var) => &var.name,
+                    parser::Ast::ArrayAccess { variable, index } => {
+                        let element = match self.visit_element_address(variable, index) {
+                            Err(e) => return Err(e),
+                            Ok(val) => val,
+                        };
+                        if let Err(e) = self.visit_expression(expression) {
+                            return Err(e);
+                        }
+                        self.store(&element);
+                        return Ok(());
+                    },
+                    _ => return Err(String::from("codegen: can only assign value to a variable.")),
+                };
+                let typeval = match self.variable(name) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if let (parser::Ast::ArrayValue(children), Some(element)) = (&**expression, &typeval.element) {
+                    for (i, child) in children.iter().enumerate() {
+                        let index = parser::Ast::Int(i as i64);
+                        if let Err(e) = self.visit_element_address(name, &index) {
+                            return Err(e);
+                        }
+                        if let Err(e) = self.visit_expression(child) {
+                            return Err(e);
+                        }
+                        self.store(element);
+                    }
+                    return Ok(());
+                }
+                if let Err(e) = self.visit_expression(expression) {
+                    return Err(e);
+                }
+                match self.locals.contains(name) {
+                    true => self.emit(format!("local.set {}", identifier(name)).as_str()),
+                    false => self.emit(format!("global.set {}", global_identifier(name)).as_str()),
+                };
+            },
+            parser::Ast::FunctionCall { name, children, .. } => {
+                if let Err(e) = self.visit_call(name, children) {
+                    return Err(e);
+                }
+                if let Some(dec) = self.context.declarations.get(name) {
+                    if dec.return_type.is_some() {
+                        self.emit("drop");
+                    }
+                }
+            },
+            parser::Ast::ReturnStatement { value, .. } => {
+                if let Some(value) = value {
+                    if let Err(e) = self.visit_expression(value) {
+                        return Err(e);
+                    }
+                }
+                self.restore_stack();
+                self.emit("return");
+            },
+            parser::Ast::Condition { condition, valid_branch, invalid_branch, .. } => {
+                if let Err(e) = self.visit_condition(condition) {
+                    return Err(e);
+                }
+                self.emit("if");
+                self.depth += 1;
+                if let Err(e) = self.visit_block(valid_branch) {
+                    return Err(e);
+                }
+                self.depth -= 1;
+                if !invalid_branch.is_empty() {
+                    self.emit("else");
+                    self.depth += 1;
+                    if let Err(e) = self.visit_block(invalid_branch) {
+                        return Err(e);
+                    }
+                    self.depth -= 1;
+                }
+                self.emit("end");
+            },
+            parser::Ast::WhileLoop { condition, children, .. } => {
+                let id = self.new_label_id();
+                self.emit(format!("block $endwhile{}", id).as_str());
+                self.depth += 1;
+                self.emit(format!("loop $while{}", id).as_str());
+                self.depth += 1;
+                if let Err(e) = self.visit_condition(condition) {
+                    return Err(e);
+                }
+                self.emit("i32.eqz");
+                self.emit(format!("br_if $endwhile{}", id).as_str());
+                if let Err(e) = self.visit_block(children) {
+                    return Err(e);
+                }
+                self.emit(format!("br $while{}", id).as_str());
+                self.depth -= 1;
+                self.emit("end");
+                self.depth -= 1;
+                self.emit("end");
+            },
+            expression => {
+                if let Err(e) = self.visit_expression(expression) {
+                    return Err(e);
+                }
+                self.emit("drop");
+            },
+        };
+        return Ok(());
+    }
+}
+
+fn visit_function(func: &Function, context: &CompilerContext, layout: &ModuleLayout) -> Result<String, String> {
+    let mut frame_offsets = HashMap::new();
+    let mut frame_size = 0;
+    for var in &func.variables[func.parameter_count..] {
+        if var.typeval.count.is_some() {
+            frame_offsets.insert(var.name.clone(), frame_size);
+            frame_size += var.typeval.size;
+        }
+    }
+    // keeps the stack 8 bytes aligned for the f64 accesses.
+    frame_size = frame_size.div_ceil(8) * 8;
+
+    let mut generator = FunctionGenerator {
+        context,
+        layout,
+        variables: context.globals.iter().map(|g| &g.variable).chain(func.variables.iter()).map(|v| (v.name.clone(), v.typeval.clone())).collect(),
+        locals: func.variables.iter().map(|v| v.name.clone()).collect(),
+        frame_offsets,
+        frame_size,
+        output: String::new(),
+        depth: 2,
+        label_count: 0,
+    };
+
+    let return_type = match context.declarations.get(&func.name) {
+        Some(dec) => dec.return_type.clone(),
+        None => None,
+    };
+
+    let mut header = format!("  (func {}", identifier(&func.name));
+    for param in &func.variables[..func.parameter_count] {
+        header.push_str(format!(" (param {} {})", identifier(&param.name), value_type(&param.typeval)).as_str());
+    }
+    if let Some(return_type) = &return_type {
+        header.push_str(format!(" (result {})", value_type(return_type)).as_str());
+    }
+    generator.output.push_str(header.as_str());
+    generator.output.push('\n');
+
+    for var in &func.variables[func.parameter_count..] {
+        if var.typeval.count.is_none() {
+            generator.emit(format!("(local {} {})", identifier(&var.name), value_type(&var.typeval)).as_str());
+        }
+    }
+
+    if frame_size > 0 {
+        generator.emit("(local $fp i32)");
+        generator.emit("global.get $sp");
+        generator.emit(format!("i32.const {}", frame_size).as_str());
+        generator.emit("i32.sub");
+        generator.emit("local.tee $fp");
+        generator.emit("global.set $sp");
+    }
+
+    if let Err(e) = generator.visit_block(&func.statements) {
+        return Err(e);
+    }
+
+    if return_type.is_some() {
+        // the end of a function returning a value is never reached.
+        generator.emit("unreachable");
+    } else {
+        generator.restore_stack();
+    }
+    generator.output.push_str("  )\n");
+    return Ok(generator.output);
+}
+
+/// Writes the module to `out`, each function once it is generated.
+fn visit_module(context: &CompilerContext, out: &mut dyn Write) -> Result<(), String> {
+    let mut layout = ModuleLayout {
+        strings: HashMap::new(),
+        arrays: HashMap::new(),
+        table: HashMap::new(),
+    };
+
+    let mut data = String::new();
+    let mut address = DATA_START;
+    for value in &context.strings.values {
+        layout.strings.insert(value.clone(), address);
+        data.push_str(format!("  (data (i32.const {}) \"{}\")\n", address, string_data(value)).as_str());
+        address += value.len() as u64 + 1;
+    }
+
+    // global arrays follow the strings, other globals only hold a value.
+    let mut globals = String::new();
+    for global in &context.globals {
+        let typeval = &global.variable.typeval;
+        if typeval.count.is_some() {
+            address = address.div_ceil(8) * 8;
+            layout.arrays.insert(global.variable.name.clone(), address);
+            address += typeval.size;
+            continue;
+        }
+        let value = match &global.value {
+            Some(parser::Ast::Float(val)) => format!("f64.const {:?}", val),
+            Some(parser::Ast::Int(val)) => format!("i32.const {}", val),
+            Some(parser::Ast::Bool(val)) => format!("i32.const {}", *val as u8),
+            _ if is_float(typeval) => String::from("f64.const 0"),
+            _ => String::from("i32.const 0"),
+        };
+        let identifier = global_identifier(&global.variable.name);
+        globals.push_str(format!("  (global {} (mut {}) ({}))\n", identifier, value_type(typeval), value).as_str());
+    }
+    // the stack gets the pages following the data.
+    let pages = MEMORY_PAGES + address / 65536;
+
+    // every function can be referenced, through its index in the table.
+    let names = context.extern_symbols.iter().chain(context.functions.iter().map(|f| &f.name)).collect::<Vec<&String>>();
+    for (i, name) in names.iter().enumerate() {
+        layout.table.insert((*name).clone(), i);
+    }
+
+    let mut res = String::from("(module\n");
+    for name in &context.extern_symbols {
+        let dec = match context.declarations.get(name) {
+            None => return Err(format!("codegen: undefined function {}", name)),
+            Some(dec) => dec,
+        };
+        res.push_str(format!("  (import \"env\" \"{}\" (func {}{}))\n", name, identifier(name), signature(&dec.parameters, &dec.return_type)).as_str());
+    }
+
+    res.push_str(format!("  (memory (export \"memory\") {})\n", pages).as_str());
+    res.push_str(format!("  (global $sp (mut i32) (i32.const {}))\n", pages * 65536).as_str());
+    res.push_str(globals.as_str());
+    res.push_str(data.as_str());
+    if !names.is_empty() {
+        res.push_str(format!("  (table {} funcref)\n", names.len()).as_str());
+        let elements = names.iter().map(|n| identifier(n)).collect::<Vec<String>>();
+        res.push_str(format!("  (elem (i32.const 0) {})\n", elements.join(" ")).as_str());
+    }
+
+    for f in context.functions.iter().chain(std::iter::once(&context.main_function)) {
+        match visit_function(f, context, &layout) {
+            Err(e) => return Err(e),
+            Ok(val) => res.push_str(val.as_str()),
+        };
+        if let Err(e) = write_code(out, &res) {
+            return Err(e);
+        }
+        res.clear();
+    }
+
+    res.push_str(format!("  (export \"main\" (func {}))\n", identifier(&context.main_function.name)).as_str());
+    res.push_str(")\n");
+    return write_code(out, &res);
+}
@@ -0,0 +1,157 @@
+//! `algo_parser debug`, running the program with the interpreter and pausing it before its first statement, the
+//! commands read from stdin setting breakpoints, stepping through the program and printing its calls and variables.
+//! The debugger writes to stderr, the program writing stdout and reading the file given by `--input`, or nothing.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::exit;
+
+use algo_parser::interpreter::debug::{Breakpoint, Command, Debugger, Session};
+use algo_parser::interpreter::program::Frame;
+use algo_parser::interpreter::trace::binding;
+use algo_parser::lexer::Span;
+
+use super::trace::{checked_program, interpreter_options, run_program};
+use super::{input_paths, message_format, message_language, read_sources, usage_error, Reporter, EXIT_FAILURE};
+
+/// Options of `debug`, all of them followed by a value.
+const DEBUG_OPTIONS: [&str; 4] = ["--input", "--checks", "--message-format", "--lang"];
+
+const HELP: &str = "commands:
+    break [<file>:]<line>    pause before the statements of the line, the file being the first one when not given
+    delete [<file>:]<line>   remove the breakpoint of the line
+    step, s                  run the statement, pausing in the calls it makes
+    next, n                  run the statement and the calls it makes
+    continue, c              run to the next breakpoint
+    backtrace, bt            print the calls being run, the innermost first
+    print [<name>], p        print the variable, or every variable of the function
+    help                     print this message
+    quit, q                  stop the program
+";
+
+/// Session reading the commands from stdin.
+struct Console<'a> {
+    paths: &'a [String],
+    sources: &'a [String],
+}
+
+impl<'a> Console<'a> {
+    fn location(&self, span: Span) -> String {
+        let path = self.paths.get(span.file).map(|path| path.as_str()).unwrap_or_default();
+        return format!("{}:{}", path, span.line);
+    }
+
+    /// Breakpoint of `[<file>:]<line>`, the file being given by its path or its name.
+    fn breakpoint(&self, text: &str) -> Result<Breakpoint, String> {
+        let (file, line) = match text.rsplit_once(':') {
+            None => (0, text),
+            Some((name, line)) => {
+                let named = |path: &String| path == name || Path::new(path).file_name().is_some_and(|file| file == name);
+                match self.paths.iter().position(named) {
+                    None => return Err(format!("no input file '{}'", name)),
+                    Some(file) => (file, line),
+                }
+            },
+        };
+        return match line.parse::<usize>() {
+            Ok(line) if line > 0 => Ok(Breakpoint { file, line }),
+            _ => Err(format!("invalid line '{}'", line)),
+        };
+    }
+}
+
+impl<'a> Session for Console<'a> {
+    fn paused(&mut self, span: Span, frames: &[Frame], breakpoints: &mut Vec<Breakpoint>) -> Command {
+        let source = self.sources.get(span.file).and_then(|source| source.lines().nth(span.line.saturating_sub(1))).unwrap_or_default();
+        eprintln!("{}: {}", self.location(span), source.trim());
+        let stdin = std::io::stdin();
+        loop {
+            eprint!("(algo) ");
+            let _ = std::io::stderr().flush();
+            let mut line = String::new();
+            match stdin.lock().read_line(&mut line) {
+                Err(e) => {
+                    eprintln!("could not read the commands: {}", e);
+                    exit(EXIT_FAILURE);
+                },
+                // the program runs to its end once the commands are over.
+                Ok(0) => {
+                    eprintln!();
+                    breakpoints.clear();
+                    return Command::Continue;
+                },
+                Ok(_) => (),
+            };
+            let words = line.split_whitespace().collect::<Vec<&str>>();
+            match words.as_slice() {
+                [] => (),
+                ["step" | "s"] => return Command::Step,
+                ["next" | "n"] => return Command::Next,
+                ["continue" | "c"] => return Command::Continue,
+                ["break" | "b", text] => match self.breakpoint(text) {
+                    Err(e) => eprintln!("{}", e),
+                    Ok(breakpoint) => {
+                        if !breakpoints.contains(&breakpoint) {
+                            breakpoints.push(breakpoint);
+                        }
+                        eprintln!("breakpoint at {}", self.location(Span { file: breakpoint.file, line: breakpoint.line, ..Span::default() }));
+                    },
+                },
+                ["delete", text] => match self.breakpoint(text) {
+                    Err(e) => eprintln!("{}", e),
+                    Ok(breakpoint) if !breakpoints.contains(&breakpoint) => eprintln!("no breakpoint at {}", text),
+                    Ok(breakpoint) => breakpoints.retain(|b| *b != breakpoint),
+                },
+                ["backtrace" | "bt"] => {
+                    for (i, frame) in frames.iter().rev().enumerate() {
+                        eprintln!("#{} {} at {}", i, frame.function, self.location(frame.span));
+                    }
+                },
+                ["print" | "p"] => {
+                    for (name, value) in frames.last().iter().flat_map(|frame| frame.environment.iter()) {
+                        eprintln!("{} = {}", name, binding(value));
+                    }
+                },
+                ["print" | "p", name] => {
+                    // the variables of the function hide the globals.
+                    let value = [frames.last(), frames.first()].into_iter().flatten().find_map(|frame| frame.environment.get(name));
+                    match value {
+                        None => eprintln!("no variable '{}'", name),
+                        Some(value) => eprintln!("{} = {}", name, binding(value)),
+                    };
+                },
+                ["help"] => eprint!("{}", HELP),
+                ["quit" | "q"] => exit(EXIT_FAILURE),
+                _ => eprintln!("unknown command '{}', help lists the commands", line.trim()),
+            };
+        }
+    }
+}
+
+pub fn run(args: &[String]) {
+    let paths = match input_paths(args, &DEBUG_OPTIONS, &[]) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let mut input: Box<dyn BufRead> = match args.iter().position(|arg| arg == "--input").map(|i| args.get(i + 1)) {
+        None => Box::new(std::io::empty()),
+        Some(None) => usage_error("missing value for --input"),
+        Some(Some(path)) => match std::fs::File::open(path) {
+            Err(e) => {
+                eprintln!("could not read {}: {}", path, e);
+                exit(EXIT_FAILURE);
+            },
+            Ok(file) => Box::new(BufReader::new(file)),
+        },
+    };
+    let format = message_format(args);
+    let language = message_language(args);
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+    let options = interpreter_options(args);
+    let ast = checked_program(&reporter, &options);
+
+    let mut console = Console { paths: &paths, sources: &sources };
+    let mut debugger = Debugger::new(&mut console, Vec::new(), Command::Step);
+    run_program(&ast, &options, &reporter, &mut input, &mut debugger);
+}
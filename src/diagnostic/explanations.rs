@@ -0,0 +1,466 @@
+use super::Code;
+
+/// Longer description of the diagnostics of `code`, with an example and how to fix it, as printed by `--explain`.
+pub fn explain(code: Code) -> &'static str {
+    return match code {
+        Code::InvalidToken => r#"The lexer found a character that starts no token of the language.
+
+Statements end with the line, there is no separator between them:
+
+    x <- 3;
+
+Remove the character, or quote it when it belongs to a string:
+
+    x <- 3
+"#,
+        Code::Syntax => r#"The tokens of the line do not form a statement, usually because an
+expression is left incomplete or a block is not closed by `end`.
+
+    total <- (price +
+
+Complete the expression, and close each `if`, `while`, `function` or
+`record` with its `end`:
+
+    total <- (price + tax)
+"#,
+        Code::UnknownVariable => r#"A variable is read while no assignment or declaration visible from the
+statement gives it a name.
+
+    function double(): int
+        return count * 2
+    end
+
+Assign the variable before reading it, or pass it as a parameter:
+
+    function double(count: int): int
+        return count * 2
+    end
+"#,
+        Code::UnknownFunction => r#"No declared function has the name of the call and parameters matching
+the types of its arguments.
+
+    done <- true
+    print(done)
+
+Declare the function with `function` or `declare function`, or call it
+with arguments of the types it expects:
+
+    declare function print_bool(value: bool)
+
+    print_bool(done)
+"#,
+        Code::UnknownType => r#"A type name is neither a builtin type (`int`, `float`, `bool`, `str`, also
+named `string`) nor a declared record.
+
+    count: integer <- 3
+
+Use one of the builtin types, or declare the record before using it:
+
+    count: int <- 3
+"#,
+        Code::UnknownField => r#"A field is accessed on a record which does not declare it.
+
+    record Point
+        x: int
+        y: int
+    end
+
+    p: Point
+    p.z <- 3
+
+Access one of the fields of the record, or add the field to it:
+
+    p.y <- 3
+"#,
+        Code::InvalidRecord => r#"A record declares no field, declares a field twice, or has a field whose
+size is unknown, as a field of its own record type.
+
+    record Point
+        x: int
+        x: int
+    end
+
+Give each field its own name:
+
+    record Point
+        x: int
+        y: int
+    end
+"#,
+        Code::MismatchedTypes => r#"An expression has a type other than the one its place expects, as a value
+assigned to a variable of another type or a condition which is not a bool.
+ints are only converted to floats implicitly when passed to a function.
+
+    ratio: int <- 1.5
+
+Give the variable the type of its value, or compute a value of its type:
+
+    ratio: float <- 1.5
+"#,
+        Code::Redeclaration => r#"A name is declared twice in the same scope: a variable with another type,
+a function with the same parameters, a constant, or a value listed by
+several cases of a `match`.
+
+    function area(side: int): int
+        return side * side
+    end
+
+    function area(side: int): int
+        return 4 * side
+    end
+
+Rename one of the declarations, or remove the duplicate:
+
+    function perimeter(side: int): int
+        return 4 * side
+    end
+"#,
+        Code::InvalidReturn => r#"A function returning a value can reach its end without `return`, or a
+`return` does not match the return type of its function.
+
+    function sign(x: int): int
+        if x < 0
+            return 0 - 1
+        end
+    end
+
+Return a value on every path of the function:
+
+    function sign(x: int): int
+        if x < 0
+            return 0 - 1
+        end
+        return 1
+    end
+"#,
+        Code::InvalidAssignment => r#"A value is assigned to something that cannot hold it: a constant, or an
+expression which is not a variable, an array element or a field.
+
+    const LIMIT <- 10
+    LIMIT <- 20
+
+Assign a variable instead, or change the value of the constant where it
+is declared:
+
+    limit <- LIMIT
+    limit <- 20
+"#,
+        Code::NotConstant => r#"A constant, or a case of a `match`, is given a value which can only be
+computed at runtime, as a call or a variable.
+
+    const LIMIT <- read_limit()
+
+Give it a literal, or an expression of literals and other constants:
+
+    const LIMIT <- 10 * 2
+"#,
+        Code::InvalidEntryPoint => r#"A program defining its own `main` does not follow its rules: `main` takes
+no parameter and returns the exit status as an int, and the top level
+only declares functions, records, constants and globals initialized once
+with a constant.
+
+    count <- 3
+    print(count)
+
+    function main(): int
+        return 0
+    end
+
+Move the statements of the top level into `main`:
+
+    function main(): int
+        count <- 3
+        print(count)
+        return 0
+    end
+"#,
+        Code::Unsupported => r#"The program, or the options it is compiled with, use a construct the
+compiler or the selected target does not support, as runtime checks
+with a target other than nasm, gas and ir:
+
+    algo_parser --emit c --checks div
+
+Drop the option, or choose a target supporting it:
+
+    algo_parser --emit gas --checks div
+"#,
+        Code::Codegen => r#"The program passed the semantic analysis but was rejected while
+generating code for the target, as a freestanding program calling a
+builtin of the C math library:
+
+    algo_parser --freestanding
+
+    root <- sqrt(2.0)
+
+Link the program with the C library, or compute the value without the
+builtin. Errors of this kind are not located in the source.
+"#,
+        Code::AmbiguousCall => r#"Several overloads match a call equally well, each one converting a
+different argument from int to float.
+
+    function mix(a: int, b: float): float
+        return b
+    end
+
+    function mix(a: float, b: int): float
+        return a
+    end
+
+    value <- mix(1, 2)
+
+Pass arguments matching one of the overloads exactly:
+
+    value <- mix(1, 2.0)
+"#,
+        Code::Evaluation => r#"A program run by the interpreter, with `algo_parser run --interp`, `test`,
+`bench`, `trace`, `debug` or `repl`, failed while computing a value: an
+int divided by zero, an array indexed out of its bounds, or an int
+overflowing its 32 bits when the overflow is checked with
+`--checks overflow` or `@pragma checks overflow`.
+
+    total / count
+
+Guard the operation:
+
+    count > 0 and total / count > 10
+"#,
+        Code::NonExhaustiveMatch => r#"A `match` without `else` does not list every value of its subject, the
+values of bools being the only ones which can all be listed.
+
+    match count
+    case 0
+        print("none")
+    case 1
+        print("one")
+    end
+
+Add an `else` branch handling the other values:
+
+    match count
+    case 0
+        print("none")
+    case 1
+        print("one")
+    else
+        print("many")
+    end
+"#,
+        Code::Io => r#"A file given to the compiler cannot be read, or a file it generates
+cannot be written, as a source file which does not exist or an output
+in a directory without write permission.
+
+    algo_parser --emit gas -o /missing/directory/program
+
+Check the path and the permissions of the file, or write the output to
+another directory.
+"#,
+        Code::Build => r#"The assembler or the linker rejected the generated code, or could not be
+run, when building or running an executable. The message quotes the
+output of the failing tool.
+
+    algo_parser --emit nasm -o program
+
+Install the tool the build needs, nasm for the nasm target and cc for
+both x86 targets, or build with the gas target, which only needs cc:
+
+    algo_parser --emit gas -o program
+"#,
+        Code::InvalidPragma => r#"A `@pragma` line names no pragma the compiler knows, or gives it an
+invalid value.
+
+    @pragma checks bounds
+
+Pragmas are written at the top level of a file, one per line: `checks`
+takes the runtime checks separated by commas or `off`, `strict` takes
+`on` or `off`, `allow`, `warn` and `deny` take a warning code or
+`warnings`, and `dialect` takes the dialect of the keywords, `en` or `fr`.
+
+    @pragma checks div,overflow
+"#,
+        Code::InvalidFormat => r#"A `print` given several arguments formats its first one, a string
+literal, each `{}` of it printing the next value. The number of
+placeholders differs from the number of values, a placeholder is not
+closed, or a value is neither an int, a float, a bool nor a str.
+
+    print("{} + {} = {}", a, b)
+
+Give one value per placeholder, `{:.2}` printing a float with 2 decimals
+and `{{` and `}}` printing a brace:
+
+    print("{} + {} = {:.2}", a, b, a + b)
+"#,
+        Code::InvalidConfig => r#"A line of the lint configuration given by `--config` does not set a rule
+on or off, or names a rule the linter does not have.
+
+    magic-numbers = no
+
+Write one rule per line, by its name or its code, followed by `= on` or
+`= off`, `all` setting every rule:
+
+    magic-number = off
+"#,
+        Code::UnreachableCode => r#"A statement follows a `return`, `throw` or `exit` of its block, and is
+never run.
+
+    function half(x: int): int
+        return x / 2
+        print("halved")
+    end
+
+Remove the statement, or move it before the statement ending the block:
+
+    function half(x: int): int
+        print("halved")
+        return x / 2
+    end
+"#,
+        Code::UninitializedVariable => r#"A variable is read on a path where it has not been assigned yet, as a
+variable declared without value and only assigned by one branch of an
+`if`. It is an error with `--strict`.
+
+    sign: int
+    if x > 0
+        sign <- 1
+    end
+    print(sign)
+
+Assign the variable on every path reaching the read:
+
+    sign: int <- 0
+    if x > 0
+        sign <- 1
+    end
+    print(sign)
+"#,
+        Code::StackBudget => r#"The frame of a function, or the deepest calls of the program, take more
+bytes of stack than the budget given by `--stack-budget`. Local arrays
+of fixed size are stored in the frame of their function.
+
+    function fill(): int
+        values: int[100000]
+        values[0] <- 1
+        return values[0]
+    end
+
+Allocate the large arrays with `new`, which does not use the stack, or
+raise the budget:
+
+    function fill(): int
+        values <- new int[100000]
+        values[0] <- 1
+        return values[0]
+    end
+"#,
+        Code::UnboundedRecursion => r#"A function calls itself, directly or through other functions, so the
+stack it uses depends on the depth of the recursion, which the compiler
+cannot bound. Deep recursions overflow the stack.
+
+    function sum(n: int): int
+        if n == 0
+            return 0
+        end
+        return n + sum(n - 1)
+    end
+
+Compute the value with a loop, or silence the warning with `-A W0004`
+when the depth is known to be small:
+
+    function sum(n: int): int
+        total <- 0
+        while n > 0
+            total <- total + n
+            n <- n - 1
+        end
+        return total
+    end
+"#,
+        Code::DecidedBranch => r#"The optimizations found the condition of an `if` or a `while` to always
+have the same value, one of its branches never running. It is only
+reported with `--verbose`.
+
+    debug <- false
+    if debug
+        print("debugging")
+    end
+
+Remove the dead branch, or make the condition depend on the input of the
+program.
+"#,
+        Code::UnusedFunction => r#"The linter found a function which is never called nor referenced, other
+than by itself. `main` and the functions given their own symbol are
+used from outside of the program, and never reported.
+
+    function square(x: int): int
+        return x * x
+    end
+
+    print(3)
+
+Call the function, or remove it:
+
+    print(square(3))
+"#,
+        Code::AssignmentInCondition => r#"The linter found an assignment used as the condition of an `if` or a
+`while`, which is usually a comparison written with the wrong operator.
+
+    if count <- 0
+        print("empty")
+    end
+
+Compare the values with `==`, or assign the variable before the `if`:
+
+    if count == 0
+        print("empty")
+    end
+"#,
+        Code::MagicNumber => r#"The linter found a number other than 0, 1 and 2 written in an
+expression, whose meaning is left to the reader.
+
+    if speed > 130
+        print("too fast")
+    end
+
+Give the number a name with a constant:
+
+    const SPEED_LIMIT <- 130
+
+    if speed > SPEED_LIMIT
+        print("too fast")
+    end
+"#,
+        Code::NonSnakeCase => r#"The linter found a function, a parameter or a variable whose name is not
+in snake_case: lowercase words separated by underscores.
+
+    maxValue <- 10
+
+Rename it in snake_case, constants being the ones written in uppercase:
+
+    max_value <- 10
+"#,
+        Code::DeadFunction => r#"The analysis of the whole program found a function which no run of it
+reaches: it is neither used by the top level statements, by `main` or by
+a function given its own symbol, nor by a function they reach. Functions
+only calling one another are dead together.
+
+    function is_even(n: int): int
+        if n == 0
+            return 1
+        end
+        return is_odd(n - 1)
+    end
+
+    function is_odd(n: int): int
+        if n == 0
+            return 0
+        end
+        return is_even(n - 1)
+    end
+
+    print(3)
+
+Call one of the functions from the program, or remove them:
+
+    print(is_even(3))
+"#,
+    };
+}
@@ -0,0 +1,559 @@
+//! Catalog of the messages of the diagnostics in the languages the compiler speaks. The compiler writes its messages in
+//! English, and `translate` finds the template of the catalog a message was formatted from to format it again in
+//! another language, the values it quotes being kept. Messages missing from the catalog are left in English.
+
+/// Language of the messages of the diagnostics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    French,
+}
+
+impl Language {
+    /// Language named by its code or by its English name, as `fr` or `french`.
+    pub fn from_name(name: &str) -> Option<Language> {
+        return match name.to_ascii_lowercase().as_str() {
+            "en" | "english" => Some(Language::English),
+            "fr" | "french" => Some(Language::French),
+            _ => None,
+        };
+    }
+
+    pub fn severity(&self, severity: super::Severity) -> &'static str {
+        return match (self, severity) {
+            (Language::French, super::Severity::Error) => "erreur",
+            (Language::French, super::Severity::Warning) => "avertissement",
+            (_, severity) => severity.as_str(),
+        };
+    }
+
+    pub fn help(&self) -> &'static str {
+        return match self {
+            Language::English => "help",
+            Language::French => "aide",
+        };
+    }
+
+    /// Help pointing to the explanation of `code`.
+    pub fn explain_help(&self, code: super::Code) -> String {
+        return match self {
+            Language::English => format!("for more information, run `algo_parser --explain {}`", code),
+            Language::French => format!("pour plus d'informations, lancez `algo_parser --explain {}`", code),
+        };
+    }
+}
+
+/// English templates, with `{}` where the compiler writes a value, and their French translations, with `{0}`, `{1}`, ...
+/// for the values in the order of the English template.
+const CATALOG: &[(&str, &str)] = &[
+    // lexer
+    ("integer '{}' is too large", "l'entier '{0}' est trop grand"),
+    ("invalid character '{}'", "caractère '{0}' invalide"),
+    ("invalid member access '{}'", "accès au membre '{0}' invalide"),
+    ("invalid number '{}'", "nombre '{0}' invalide"),
+    ("invalid operator '{}'", "opérateur '{0}' invalide"),
+    ("invalid separator '{}'", "séparateur '{0}' invalide"),
+    ("invalid token '{}' in context None", "jeton '{0}' invalide hors de tout contexte"),
+    ("invalid UTF-8 sequence", "séquence UTF-8 invalide"),
+    ("missing name for pragma", "nom de pragma manquant"),
+    ("unknown dialect '{}', expected {}", "dialecte '{0}' inconnu, {1} attendu"),
+    // parser
+    ("function '{}' cannot be both no_mangle and bound to a symbol", "la fonction '{0}' ne peut pas être à la fois no_mangle et liée à un symbole"),
+    (
+        "invalid expression in create_binary_operator_ast, missing value for operator {}",
+        "expression invalide dans create_binary_operator_ast, valeur manquante pour l'opérateur {0}",
+    ),
+    ("invalid expression in create_unary_operator_ast", "expression invalide dans create_unary_operator_ast"),
+    ("invalid expression parsing ')' in build_expression_ast", "expression invalide en lisant ')' dans build_expression_ast"),
+    (
+        "invalid expression, parsing items in build_expression_ast, expected length of 1, got {}",
+        "expression invalide en lisant les éléments dans build_expression_ast, longueur 1 attendue, {0} obtenue",
+    ),
+    ("invalid function declaration for '{}'", "déclaration de fonction invalide pour '{0}'"),
+    ("invalid token {}", "jeton {0} invalide"),
+    ("invalid token {} for function name", "jeton {0} invalide pour un nom de fonction"),
+    ("invalid token {} in build_expression_ast", "jeton {0} invalide dans build_expression_ast"),
+    ("missing function call.", "appel de fonction manquant."),
+    ("missing left parenthesis", "parenthèse ouvrante manquante"),
+    ("missing name for function", "nom de fonction manquant"),
+    ("missing token", "jeton manquant"),
+    ("missing token for variable", "jeton manquant pour la variable"),
+    ("missing type declaration for variable {}", "déclaration de type manquante pour la variable {0}"),
+    ("missing typedef for variable '{}'", "type manquant pour la variable '{0}'"),
+    ("parser: can only assign value to variable", "parser: seule une variable peut recevoir une valeur"),
+    ("parser: error in while loop, unexpected end of document", "parser: erreur dans la boucle while, fin de document inattendue"),
+    ("parser: expected '(', got {} after fn keyword", "parser: '(' attendu, {0} obtenu après le mot-clé fn"),
+    ("parser: expected '(', got {} for function '{}'", "parser: '(' attendu, {0} obtenu pour la fonction '{1}'"),
+    ("parser: expected '=>', got {} in lambda", "parser: '=>' attendu, {0} obtenu dans la lambda"),
+    ("parser: expected '[', got {} after new {}", "parser: '[' attendu, {0} obtenu après new {1}"),
+    ("parser: expected 'const NAME <- value', got {}", "parser: 'const NOM <- valeur' attendu, {0} obtenu"),
+    ("parser: expected '{}', got {} in map type", "parser: '{0}' attendu, {1} obtenu dans le type de dictionnaire"),
+    (
+        "parser: expected a symbol name after 'as', got {} in function declaration '{}'",
+        "parser: nom de symbole attendu après 'as', {0} obtenu dans la déclaration de la fonction '{1}'",
+    ),
+    ("parser: expected a type, got {} after new keyword", "parser: type attendu, {0} obtenu après le mot-clé new"),
+    ("parser: expected a type, got {} in map type", "parser: type attendu, {0} obtenu dans le type de dictionnaire"),
+    ("parser: expected a variable after catch, got {}", "parser: variable attendue après catch, {0} obtenu"),
+    ("parser: expected a variable after read, got {}", "parser: variable attendue après read, {0} obtenu"),
+    ("parser: expected array value, got {}", "parser: tableau attendu, {0} obtenu"),
+    ("parser: expected case after match, got {}", "parser: case attendu après match, {0} obtenu"),
+    (
+        "parser: expected end of line, got {} in function declaration '{}'",
+        "parser: fin de ligne attendue, {0} obtenu dans la déclaration de la fonction '{1}'",
+    ),
+    ("parser: expected end of line, got {} in record '{}'", "parser: fin de ligne attendue, {0} obtenu dans l'enregistrement '{1}'"),
+    ("parser: invalid array size {} for variable '{}'", "parser: taille de tableau {0} invalide pour la variable '{1}'"),
+    ("parser: invalid constant name {}", "parser: nom de constante {0} invalide"),
+    ("parser: invalid token {} for record name", "parser: jeton {0} invalide pour un nom d'enregistrement"),
+    ("parser: invalid token {} for variable declaration.", "parser: jeton {0} invalide pour une déclaration de variable."),
+    ("parser: invalid type token {} for variable '{}'", "parser: jeton de type {0} invalide pour la variable '{1}'"),
+    ("parser: missing '(' after function declaration ('{}').", "parser: '(' manquant après la déclaration de fonction ('{0}')."),
+    ("parser: missing ']' after new {}", "parser: ']' manquant après new {0}"),
+    ("parser: missing ']' for variable '{}'", "parser: ']' manquant pour la variable '{0}'"),
+    ("parser: missing '}' at the end of the map value", "parser: '}' manquant à la fin du dictionnaire"),
+    ("parser: missing array size for variable '{}'", "parser: taille de tableau manquante pour la variable '{0}'"),
+    ("parser: missing implementation for operator '{}'", "parser: implémentation manquante pour l'opérateur '{0}'"),
+    ("parser: missing lambda body", "parser: corps de la lambda manquant"),
+    ("parser: missing lambda return type", "parser: type de retour de la lambda manquant"),
+    ("parser: missing name for record", "parser: nom d'enregistrement manquant"),
+    ("parser: missing operand for {}", "parser: opérande manquant pour {0}"),
+    ("parser: missing size for new {}[]", "parser: taille manquante pour new {0}[]"),
+    ("parser: missing variable after catch", "parser: variable manquante après catch"),
+    ("parser: more than {} blocks and expressions nested in one another", "parser: plus de {0} blocs et expressions imbriqués les uns dans les autres"),
+    ("parser: more than {} operators in an expression", "parser: plus de {0} opérateurs dans une expression"),
+    ("parser: swap expects 2 operands, got {}", "parser: swap attend 2 opérandes, {0} obtenus"),
+    ("parser: unexpected end of document after fn keyword", "parser: fin de document inattendue après le mot-clé fn"),
+    ("parser: unexpected end of document after new keyword", "parser: fin de document inattendue après le mot-clé new"),
+    ("parser: unexpected end of document after new {}", "parser: fin de document inattendue après new {0}"),
+    ("parser: unexpected end of document in build_array_value_ast", "parser: fin de document inattendue dans build_array_value_ast"),
+    ("parser: unexpected end of document in function declaration '{}'", "parser: fin de document inattendue dans la déclaration de la fonction '{0}'"),
+    ("parser: unexpected end of document in lambda", "parser: fin de document inattendue dans la lambda"),
+    ("parser: unexpected end of document in map type", "parser: fin de document inattendue dans le type de dictionnaire"),
+    ("parser: unexpected end of document parsing function '{}'", "parser: fin de document inattendue en lisant la fonction '{0}'"),
+    ("parser: unexpected end of document parsing record '{}'", "parser: fin de document inattendue en lisant l'enregistrement '{0}'"),
+    ("parser: unexpected end of document, expected case", "parser: fin de document inattendue, case attendu"),
+    ("parser: unexpected end of document, expected {}", "parser: fin de document inattendue, {0} attendu"),
+    ("parser: unexpected member access '.{}'", "parser: accès au membre '.{0}' inattendu"),
+    (
+        "parser: unexpected token {} for lambda return type, expected TypeDef",
+        "parser: jeton {0} inattendu pour le type de retour de la lambda, TypeDef attendu",
+    ),
+    ("parser: unexpected token {} in function '{}' declaration", "parser: jeton {0} inattendu dans la déclaration de la fonction '{1}'"),
+    ("parser: unexpected token {} in map value", "parser: jeton {0} inattendu dans le dictionnaire"),
+    ("parser: unexpected token {} in map value, expected 'key: value'", "parser: jeton {0} inattendu dans le dictionnaire, 'clé: valeur' attendu"),
+    ("parser: unexpected token {} while parsing array value.", "parser: jeton {0} inattendu en lisant le tableau."),
+    ("parser: pragma '{}' outside of the top level", "parser: pragma '{0}' hors du niveau principal"),
+    ("parser: unfinished if statement", "parser: instruction if inachevée"),
+    ("parser: unfinished if-else statement", "parser: instruction if-else inachevée"),
+    ("unexpected end of document after declare keyword", "fin de document inattendue après le mot-clé declare"),
+    ("unexpected end of document after no_mangle keyword", "fin de document inattendue après le mot-clé no_mangle"),
+    (
+        "unexpected token {} in function declaration '{}', expected TypeDef",
+        "jeton {0} inattendu dans la déclaration de la fonction '{1}', TypeDef attendu",
+    ),
+    ("unexpected token {}, after declare keyword", "jeton {0} inattendu après le mot-clé declare"),
+    ("unexpected token {}, after no_mangle keyword", "jeton {0} inattendu après le mot-clé no_mangle"),
+    // semantic analysis
+    ("variable '{}' is used before being assigned", "la variable '{0}' est utilisée avant d'être affectée"),
+    ("the body of a 'while false' loop is never executed", "le corps d'une boucle 'while false' n'est jamais exécuté"),
+    ("unreachable statement", "instruction inaccessible"),
+    ("a global of a program defining main can only be initialized once", "une globale d'un programme définissant main ne peut être initialisée qu'une fois"),
+    (
+        "a global of a program defining main can only be initialized with a constant",
+        "une globale d'un programme définissant main ne peut être initialisée qu'avec une constante",
+    ),
+    ("a program defining main cannot have statements outside of functions", "un programme définissant main ne peut pas avoir d'instructions hors des fonctions"),
+    ("move the statement into main", "déplacez l'instruction dans main"),
+    ("the main function must return an int", "la fonction main doit renvoyer un int"),
+    ("the result of main is the exit status of the program", "le résultat de main est le statut de sortie du programme"),
+    ("ambiguous call to {}({})", "appel ambigu à {0}({1})"),
+    ("array index must be an int, got {}", "l'indice d'un tableau doit être un int, {0} obtenu"),
+    ("array size must be an int, got {}", "la taille d'un tableau doit être un int, {0} obtenu"),
+    ("arrays of records cannot be created with new, got {}", "les tableaux d'enregistrements ne peuvent pas être créés avec new, {0} obtenu"),
+    ("can only assign value to a variable.", "seule une variable peut recevoir une valeur."),
+    ("can only match ints, strings and bools, got {}", "match n'accepte que des int, des string et des bool, {0} obtenu"),
+    ("can only swap variables, array elements and fields.", "seuls des variables, des éléments de tableaux et des champs peuvent être échangés."),
+    ("can only throw strings, got {}", "seules des string peuvent être levées, {0} obtenu"),
+    ("candidate: {}({})", "candidat : {0}({1})"),
+    ("cannot assign a value to the constant '{}'", "impossible d'affecter une valeur à la constante '{0}'"),
+    ("cannot capture '{}' from the enclosing {}", "impossible de capturer '{0}' depuis la {1} englobante"),
+    ("function '{}'", "fonction '{0}'"),
+    (
+        "nested functions and lambdas only access their parameters, their variables and the globals",
+        "les fonctions imbriquées et les lambdas n'accèdent qu'à leurs paramètres, à leurs variables et aux globales",
+    ),
+    ("cannot create nested function declarations", "impossible de déclarer des fonctions imbriquées"),
+    ("cannot index variable '{}' of type {}", "impossible d'indexer la variable '{0}' de type {1}"),
+    ("cannot infer the type of an empty array", "impossible de déduire le type d'un tableau vide"),
+    ("cannot infer the type of an empty map", "impossible de déduire le type d'un dictionnaire vide"),
+    ("cannot return a value from a function without return type", "impossible de renvoyer une valeur depuis une fonction sans type de retour"),
+    ("cannot swap map entries", "impossible d'échanger des entrées de dictionnaire"),
+    ("cannot swap the constant '{}'", "impossible d'échanger la constante '{0}'"),
+    ("cannot swap values of types {} and {}", "impossible d'échanger des valeurs de types {0} et {1}"),
+    ("condition must be a bool, got {}", "la condition doit être un bool, {0} obtenu"),
+    ("exit status must be an int, got {}", "le statut de sortie doit être un int, {0} obtenu"),
+    ("expected a global ast, got {}", "arbre global attendu, {0} obtenu"),
+    ("function {} can reach the end of {} without returning a value", "la fonction {0} peut atteindre la fin de {1} sans renvoyer de valeur"),
+    ("the function", "la fonction"),
+    ("the else branch", "la branche else"),
+    ("the if branch", "la branche if"),
+    (
+        "function {} has no return value and cannot be used as an expression",
+        "la fonction {0} ne renvoie pas de valeur et ne peut pas être utilisée comme expression",
+    ),
+    ("map key must be a {}, got {}", "la clé du dictionnaire doit être un {0}, {1} obtenu"),
+    ("match does not handle every value, missing else", "match ne traite pas toutes les valeurs, else manquant"),
+    ("mismatching entries '{}' and '{}' in map value", "entrées '{0}' et '{1}' incompatibles dans le dictionnaire"),
+    ("mismatching return type for lambda, expected {}, got {}", "type de retour incompatible pour la lambda, {0} attendu, {1} obtenu"),
+    ("mismatching return type, expected {}, got {}", "type de retour incompatible, {0} attendu, {1} obtenu"),
+    ("mismatching type for case, expected {}, got {}", "type incompatible pour case, {0} attendu, {1} obtenu"),
+    ("mismatching type for field '{}', expected {}, got {}", "type incompatible pour le champ '{0}', {1} attendu, {2} obtenu"),
+    ("mismatching type for variable '{}', expected {}, got {}", "type incompatible pour la variable '{0}', {1} attendu, {2} obtenu"),
+    ("mismatching type for {}[], expected {}, got {}", "type incompatible pour {0}[], {1} attendu, {2} obtenu"),
+    ("mismatching types '{}' and '{}'", "types '{0}' et '{1}' incompatibles"),
+    ("mismatching types '{}' and '{}' in array value", "types '{0}' et '{1}' incompatibles dans le tableau"),
+    ("missing return value, expected {}", "valeur de retour manquante, {0} attendu"),
+    ("no function matching {}({})", "aucune fonction ne correspond à {0}({1})"),
+    ("operator {} is not defined on {}", "l'opérateur {0} n'est pas défini sur {1}"),
+    ("operator {} is not defined on {} and {}", "l'opérateur {0} n'est pas défini sur {1} et {2}"),
+    ("redeclaration of constant '{}'", "redéclaration de la constante '{0}'"),
+    ("redeclaration of function {}", "redéclaration de la fonction {0}"),
+    ("redeclaration of symbol {}", "redéclaration du symbole {0}"),
+    ("redeclaration of type {}", "redéclaration du type {0}"),
+    ("redeclaration of variable '{}' with type {}, previously {}", "redéclaration de la variable '{0}' avec le type {1}, auparavant {2}"),
+    ("undefined symbol {}", "symbole {0} non défini"),
+    ("unsupported expression {}", "expression {0} non prise en charge"),
+    ("{} is matched by several cases", "{0} correspond à plusieurs case"),
+    // compilation
+    ("cannot access field '{}' of type {}", "impossible d'accéder au champ '{0}' du type {1}"),
+    ("condition always true, {}", "condition toujours vraie, {0}"),
+    ("condition always false, {}", "condition toujours fausse, {0}"),
+    ("the loop only ends by returning", "la boucle ne se termine qu'en revenant de la fonction"),
+    ("the body of the while is never run", "le corps du while n'est jamais exécuté"),
+    ("the if always runs its body", "le if exécute toujours son corps"),
+    ("the body of the if is never run", "le corps du if n'est jamais exécuté"),
+    ("could not write the generated code: {}", "impossible d'écrire le code généré : {0}"),
+    ("could not write the source map: {}", "impossible d'écrire la source map : {0}"),
+    ("could not write the symbol table: {}", "impossible d'écrire la table des symboles : {0}"),
+    ("duplicate field '{}' in record {}", "champ '{0}' en double dans l'enregistrement {1}"),
+    ("field '{}' of record {} must have a fixed size", "le champ '{0}' de l'enregistrement {1} doit avoir une taille fixe"),
+    ("freestanding programs are not supported by the {} target", "les programmes autonomes ne sont pas pris en charge par la cible {0}"),
+    ("freestanding programs are supported by the nasm and gas targets", "les programmes autonomes sont pris en charge par les cibles nasm et gas"),
+    ("function with void return type cannot be used as an expression.", "une fonction sans type de retour ne peut pas être utilisée comme expression."),
+    ("function {} is declared with the symbol {}, not {}", "la fonction {0} est déclarée avec le symbole {1}, pas {2}"),
+    ("functions cannot return the record {}", "les fonctions ne peuvent pas renvoyer l'enregistrement {0}"),
+    ("functions {} and {} have the same symbol {}", "les fonctions {0} et {1} ont le même symbole {2}"),
+    ("invalid return type for function {}", "type de retour invalide pour la fonction {0}"),
+    ("map keys must be ints or strings, got {}", "les clés d'un dictionnaire doivent être des int ou des string, {0} obtenu"),
+    (
+        "map values must be ints, floats, bools or strings, got {}",
+        "les valeurs d'un dictionnaire doivent être des int, des float, des bool ou des string, {0} obtenu",
+    ),
+    ("missing type for variable '{}'", "type manquant pour la variable '{0}'"),
+    ("missing value for case", "valeur manquante pour case"),
+    ("no function with the following signature: {}({})", "aucune fonction avec la signature suivante : {0}({1})"),
+    ("record {} cannot be passed to a function, pass its fields", "l'enregistrement {0} ne peut pas être passé à une fonction, passez ses champs"),
+    ("record {} has no field '{}'", "l'enregistrement {0} n'a pas de champ '{1}'"),
+    ("record {} has no fields", "l'enregistrement {0} n'a aucun champ"),
+    ("runtime checks are not supported by the {} target", "les vérifications à l'exécution ne sont pas prises en charge par la cible {0}"),
+    ("runtime checks are supported by the nasm, gas and ir targets", "les vérifications à l'exécution sont prises en charge par les cibles nasm, gas et ir"),
+    ("source maps are not supported by the {} target", "les source maps ne sont pas prises en charge par la cible {0}"),
+    ("source maps are supported by the nasm and gas targets", "les source maps sont prises en charge par les cibles nasm et gas"),
+    ("stack budgets are not supported by the {} target", "les budgets de pile ne sont pas pris en charge par la cible {0}"),
+    ("stack budgets are supported by the nasm, gas and ir targets", "les budgets de pile sont pris en charge par les cibles nasm, gas et ir"),
+    ("the {} target is not supported by this build", "la cible {0} n'est pas prise en charge par cette version"),
+    ("the llvm target needs the llvm feature", "la cible llvm nécessite la feature llvm"),
+    ("the {} target does not generate code for {}", "la cible {0} ne génère pas de code pour {1}"),
+    ("{} is supported by the {} targets", "{0} est pris en charge par les cibles {1}"),
+    ("undefined function {}", "fonction {0} non définie"),
+    ("undefined type {}", "type {0} non défini"),
+    ("unknown variable '{}'", "variable '{0}' inconnue"),
+    (
+        "{} comes from the C math library, which freestanding programs are not linked with",
+        "{0} vient de la bibliothèque mathématique du C, avec laquelle les programmes autonomes ne sont pas liés",
+    ),
+    ("unknown check '{}', expected off or one of {}", "vérification '{0}' inconnue, off ou l'une de {1} attendue"),
+    ("expected on or off for the pragma strict, got '{}'", "on ou off attendu pour le pragma strict, '{0}' obtenu"),
+    ("unknown warning '{}', expected warnings or one of {}", "avertissement '{0}' inconnu, warnings ou l'un de {1} attendu"),
+    ("unknown pragma '{}', expected one of {}", "pragma '{0}' inconnu, l'un de {1} attendu"),
+    ("print expects the format as a string literal", "print attend le format comme une chaîne littérale"),
+    ("unclosed placeholder in the format", "emplacement non fermé dans le format"),
+    ("unmatched '}' in the format, '}}' printing a brace", "'}' sans correspondance dans le format, '}}' affichant une accolade"),
+    (
+        "invalid placeholder '{}' in the format, expected no specification or a precision from :.0 to :.{}",
+        "emplacement '{0}' invalide dans le format, aucune spécification ou une précision de :.0 à :.{1} attendue",
+    ),
+    ("expected {} values for the placeholders of the format, got {}", "{0} valeurs attendues pour les emplacements du format, {1} obtenues"),
+    ("cannot format a value of type {}", "impossible de formater une valeur de type {0}"),
+    ("cannot write a value of type {}", "impossible d'écrire une valeur de type {0}"),
+    ("precision given for a value of type {}, only floats take one", "précision donnée pour une valeur de type {0}, seuls les float en prennent une"),
+    ("expected a single expression", "une seule expression attendue"),
+    ("division by zero", "division par zéro"),
+    ("integer overflow", "dépassement d'entier"),
+    ("index {} out of bounds for an array of {} elements", "indice {0} hors des bornes d'un tableau de {1} éléments"),
+    ("cannot allocate an array of {} elements", "impossible d'allouer un tableau de {0} éléments"),
+    ("unknown type '{}'", "type '{0}' inconnu"),
+    ("cannot index a value of type {}", "impossible d'indexer une valeur de type {0}"),
+    ("missing value for a placeholder of the format", "valeur manquante pour un emplacement du format"),
+    ("could not write the output: {}", "impossible d'écrire la sortie : {0}"),
+    ("could not read the input: {}", "impossible de lire l'entrée : {0}"),
+    ("expected an int on the input, got '{}'", "int attendu sur l'entrée, '{0}' obtenu"),
+    ("more than {} calls nested in one another", "plus de {0} appels imbriqués les uns dans les autres"),
+    ("function {} returns no value", "la fonction {0} ne renvoie pas de valeur"),
+    ("the call to {} did not return", "l'appel à {0} n'a pas retourné"),
+    ("the warning {} is denied by the options","l'avertissement {0} est interdit par les options"),
+    ("cannot evaluate {} at compile time", "impossible d'évaluer {0} à la compilation"),
+    ("invalid array size {}, expected a positive int", "taille de tableau {0} invalide, int positif attendu"),
+    ("{} is not a constant expression", "{0} n'est pas une expression constante"),
+    ("each recursive call pushes a new frame, deep recursions overflow the stack", "chaque appel récursif empile une nouvelle frame, les récursions profondes débordent de la pile"),
+    ("the deepest calls are {}", "les appels les plus profonds sont {0}"),
+    ("the frame of {} takes {} bytes, over the stack budget of {} bytes", "la frame de {0} occupe {1} octets, au-delà du budget de pile de {2} octets"),
+    ("the program may use {} bytes of stack, over the stack budget of {} bytes", "le programme peut utiliser {0} octets de pile, au-delà du budget de pile de {1} octets"),
+    ("{} call each other, their stack use has no bound", "{0} s'appellent les unes les autres, leur utilisation de la pile n'a pas de borne"),
+    ("{} calls itself, its stack use has no bound", "{0} s'appelle elle-même, son utilisation de la pile n'a pas de borne"),
+    // code generation, whose errors the semantic analysis reports first
+    ("codegen: cannot allocate {}", "codegen: impossible d'allouer {0}"),
+    ("codegen: missing address for string {}", "codegen: adresse manquante pour la chaîne {0}"),
+    ("codegen: missing array to release", "codegen: tableau à libérer manquant"),
+    ("codegen: missing label for string {}", "codegen: étiquette manquante pour la chaîne {0}"),
+    ("codegen: records are not supported by the C backend", "codegen: les enregistrements ne sont pas pris en charge par le backend C"),
+    ("codegen: records are not supported by the javascript backend", "codegen: les enregistrements ne sont pas pris en charge par le backend javascript"),
+    ("codegen: unknown temporary t{}", "codegen: temporaire t{0} inconnu"),
+    ("mismatched stack size, expected {}, got {}", "taille de pile incohérente, {0} attendu, {1} obtenu"),
+    ("codegen: can only assign value to a variable.", "codegen: seule une variable peut recevoir une valeur."),
+    ("codegen: cannot index variable '{}' of type {}", "codegen: impossible d'indexer la variable '{0}' de type {1}"),
+    ("codegen: condition must be a bool, got {}", "codegen: la condition doit être un bool, {0} obtenu"),
+    ("codegen: empty array value", "codegen: tableau vide"),
+    ("codegen: frame of {} is too large ({} bytes, at most {})", "codegen: la frame de {0} est trop grande ({1} octets, au plus {2})"),
+    ("codegen: function {} has no return value", "codegen: la fonction {0} ne renvoie pas de valeur"),
+    ("codegen: implicit conversion between {} and {} is not supported", "codegen: la conversion implicite entre {0} et {1} n'est pas prise en charge"),
+    ("codegen: modulo is not defined on floats", "codegen: le modulo n'est pas défini sur les float"),
+    ("codegen: record {} cannot be used as a value", "codegen: l'enregistrement {0} ne peut pas être utilisé comme valeur"),
+    ("codegen: records are not supported by the python backend", "codegen: les enregistrements ne sont pas pris en charge par le backend python"),
+    (
+        "codegen: records can only be copied between variables and fields, got {}",
+        "codegen: les enregistrements ne peuvent être copiés qu'entre variables et champs, {0} obtenu",
+    ),
+    ("codegen: shifts are not defined on floats", "codegen: les décalages ne sont pas définis sur les float"),
+    ("codegen: undefined function {}", "codegen: fonction {0} non définie"),
+    ("codegen: unknown field '{}' of {}", "codegen: champ '{0}' de {1} inconnu"),
+    ("codegen: unknown variable '{}'", "codegen: variable '{0}' inconnue"),
+    ("codegen: unsupported array of {}", "codegen: tableau de {0} non pris en charge"),
+    ("codegen: unsupported conversion to {}", "codegen: conversion en {0} non prise en charge"),
+    ("codegen: unsupported expression {}", "codegen: expression {0} non prise en charge"),
+    // analyses
+    ("assignment in a condition, compare the values with `==`", "affectation dans une condition, comparez les valeurs avec `==`"),
+    ("function '{}' is never used", "la fonction '{0}' n'est jamais utilisée"),
+    ("magic number {}, give it a name with `const`", "nombre magique {0}, nommez-le avec `const`"),
+    ("function '{}' should have a snake_case name, as '{}'", "la fonction '{0}' devrait avoir un nom en snake_case, comme '{1}'"),
+    ("parameter '{}' should have a snake_case name, as '{}'", "le paramètre '{0}' devrait avoir un nom en snake_case, comme '{1}'"),
+    ("variable '{}' should have a snake_case name, as '{}'", "la variable '{0}' devrait avoir un nom en snake_case, comme '{1}'"),
+    ("function '{}' is never reached from the program", "la fonction '{0}' n'est jamais atteinte depuis le programme"),
+    ("it is only used by '{}', which is never reached either", "elle n'est utilisée que par '{0}', qui n'est jamais atteinte non plus"),
+    ("it is only used by {}, which are never reached either", "elle n'est utilisée que par {0}, qui ne sont jamais atteintes non plus"),
+    ("expected `rule = on` or `rule = off`, got '{}'", "`règle = on` ou `règle = off` attendu, '{0}' obtenu"),
+    ("expected on or off for {}, got '{}'", "on ou off attendu pour {0}, '{1}' obtenu"),
+    ("unknown rule '{}'", "règle '{0}' inconnue"),
+    // files, builds and runs
+    ("cannot run programs compiled for the {} target", "impossible d'exécuter les programmes compilés pour la cible {0}"),
+    ("programs compiled for the nasm and gas targets can be run", "les programmes compilés pour les cibles nasm et gas peuvent être exécutés"),
+    ("could not read {}: {}", "impossible de lire {0} : {1}"),
+    ("could not run {}: {}", "impossible d'exécuter {0} : {1}"),
+    ("could not write {}: {}", "impossible d'écrire {0} : {1}"),
+    ("{} failed:\n{}", "{0} a échoué :\n{1}"),
+    ("the program failed with {}", "le programme a échoué avec {0}"),
+    ("in --call '{}': {}", "dans --call '{0}' : {1}"),
+    ("--call '{}' is not a function call", "--call '{0}' n'est pas un appel de fonction"),
+    ("{} is not followed by a function", "{0} n'est pas suivi d'une fonction"),
+    ("test function '{}' takes parameters", "la fonction de test '{0}' prend des paramètres"),
+    ("the {} is a null pointer", "{0} est un pointeur nul"),
+    ("the {} is not valid UTF-8", "{0} n'est pas de l'UTF-8 valide"),
+    ("unknown target '{}'", "cible '{0}' inconnue"),
+];
+
+/// Values `text` gives to the `{}` of `template`, when it was formatted from it.
+fn values<'a>(template: &str, text: &'a str) -> Option<Vec<&'a str>> {
+    let literals = template.split("{}").collect::<Vec<&str>>();
+    let (first, last) = (literals[0], literals[literals.len() - 1]);
+    if literals.len() == 1 {
+        return if template == text { Some(Vec::new()) } else { None };
+    }
+    if !text.starts_with(first) || !text.ends_with(last) || text.len() < first.len() + last.len() {
+        return None;
+    }
+    let end = text.len() - last.len();
+    let mut start = first.len();
+    let mut res = Vec::new();
+    for literal in &literals[1..literals.len() - 1] {
+        let i = match text[start..end].find(literal) {
+            None => return None,
+            Some(i) => i,
+        };
+        res.push(&text[start..start + i]);
+        start += i + literal.len();
+    }
+    res.push(&text[start..end]);
+    return Some(res);
+}
+
+/// `translation` whose `{0}`, `{1}`, ... are replaced by `values`.
+fn fill(translation: &str, values: &[String]) -> String {
+    let mut res = String::new();
+    let mut rest = translation;
+    while let Some(i) = rest.find('{') {
+        res.push_str(&rest[..i]);
+        let index = rest[i + 1..].find('}').and_then(|end| rest[i + 1..i + 1 + end].parse::<usize>().ok().map(|index| (index, end)));
+        match index.and_then(|(index, end)| values.get(index).map(|value| (value, end))) {
+            Some((value, end)) => {
+                res.push_str(value);
+                rest = &rest[i + end + 2..];
+            },
+            None => {
+                res.push('{');
+                rest = &rest[i + 1..];
+            },
+        };
+    }
+    res.push_str(rest);
+    return res;
+}
+
+/// `text` in `language`, formatted from the template of the catalog it matches, the one quoting the most text when several
+/// do. The values written in words, as the branch never taken of a `condition always` message, are translated as well.
+pub fn translate(text: &str, language: Language) -> String {
+    if language == Language::English {
+        return String::from(text);
+    }
+    let matched = CATALOG
+        .iter()
+        .filter_map(|(template, french)| values(template, text).map(|values| (template.len() - 2 * values.len(), french, values)))
+        .max_by_key(|(quoted, ..)| *quoted);
+    return match matched {
+        None => String::from(text),
+        Some((_, french, values)) => {
+            let values = values
+                .into_iter()
+                .map(|value| match value.contains(' ') {
+                    true => translate(value, language),
+                    false => String::from(value),
+                })
+                .collect::<Vec<String>>();
+            fill(french, &values)
+        },
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{translate, Language, CATALOG};
+
+    /// Files whose texts are not diagnostics: the usage errors of the command line, the replies of the debugger to its
+    /// commands, the messages printed by the compiled programs and the failures of the tests.
+    const UNTRANSLATED: [&str; 5] = ["main.rs", "debug.rs", "compiler/asserts.rs", "parser/testing.rs", "diagnostic/messages.rs"];
+
+    /// Texts of the lines building the message or a note of a diagnostic.
+    const MARKERS: [&str; 12] = [
+        "Err(", "Diagnostic::error(", "Diagnostic::warning(", "Diagnostic::info(", "self.error(", "self.warning(",
+        "error(Code::", "warning(Code::", ".with_note(", "let message =", "let note =", "let error =",
+    ];
+
+    /// Template of the literal starting `text`, its placeholders being written `{}` when it is formatted, with the rest
+    /// of the text.
+    fn literal(text: &str, formatted: bool) -> (String, &str) {
+        let mut res = String::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return (res, &text[i + 1..]),
+                '\\' => res.push(match chars.next().map(|(_, c)| c) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some(c) => c,
+                    None => break,
+                }),
+                '{' | '}' if formatted && chars.peek().is_some_and(|(_, next)| *next == c) => {
+                    chars.next();
+                    res.push(c);
+                },
+                '{' if formatted => {
+                    for (_, c) in chars.by_ref() {
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                    res.push_str("{}");
+                },
+                c => res.push(c),
+            };
+        }
+        return (res, "");
+    }
+
+    /// Templates of the messages formatted on `line`.
+    fn templates(line: &str) -> Vec<String> {
+        let mut res = Vec::<String>::new();
+        if !MARKERS.iter().any(|marker| line.contains(marker)) {
+            return res;
+        }
+        let mut rest = line;
+        while let Some((i, formatted)) = [("format!(\"", true), ("String::from(\"", false)]
+            .iter()
+            .filter_map(|(start, formatted)| rest.find(start).map(|i| (i + start.len(), *formatted)))
+            .min()
+        {
+            let (template, after) = literal(&rest[i..], formatted);
+            res.push(template);
+            rest = after;
+        }
+        return res;
+    }
+
+    /// Paths and texts of the Rust files of `dir` and of its subdirectories.
+    fn sources(dir: &Path, res: &mut Vec<(String, String)>) {
+        let mut entries = std::fs::read_dir(dir).unwrap().map(|entry| entry.unwrap().path()).collect::<Vec<_>>();
+        entries.sort();
+        for path in entries {
+            if path.is_dir() {
+                sources(&path, res);
+            } else if path.extension().is_some_and(|extension| extension == "rs") {
+                res.push((path.to_string_lossy().replace('\\', "/"), std::fs::read_to_string(&path).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn catalog_has_the_messages_of_the_diagnostics() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = Vec::<(String, String)>::new();
+        sources(&root, &mut files);
+        let mut missing = Vec::<String>::new();
+        for (path, text) in &files {
+            if UNTRANSLATED.iter().any(|file| path.ends_with(&format!("src/{}", file))) {
+                continue;
+            }
+            for (i, line) in text.lines().enumerate() {
+                for template in templates(line) {
+                    if !CATALOG.iter().any(|(english, _)| *english == template) {
+                        missing.push(format!("{}:{}: {:?}", path, i + 1, template));
+                    }
+                }
+            }
+        }
+        assert!(missing.is_empty(), "messages missing from the catalog:\n{}", missing.join("\n"));
+    }
+
+    #[test]
+    fn translations_use_every_value() {
+        for (english, french) in CATALOG {
+            let count = english.matches("{}").count();
+            for i in 0..count {
+                assert!(french.contains(&format!("{{{}}}", i)), "{:?} does not use the value {} of {:?}", french, i, english);
+            }
+        }
+        assert_eq!(translate("unknown variable 'x'", Language::French), "variable 'x' inconnue");
+    }
+}
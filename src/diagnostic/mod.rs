@@ -0,0 +1,247 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::json;
+use super::lexer::Span;
+
+mod explanations;
+mod messages;
+mod render;
+
+pub use explanations::explain;
+pub use messages::Language;
+pub use render::render;
+
+/// Whether a diagnostic stops the compilation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    /// what the compiler found about the program, only reported when asked for.
+    Info,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Info => "info",
+        };
+    }
+}
+
+/// Kind of a diagnostic, identified by a stable code: `E` codes for errors, `W` codes for warnings, `I` codes for information
+/// and `L` codes for the rules of the linter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// character or token the lexer does not know.
+    InvalidToken,
+    /// tokens not forming a valid statement.
+    Syntax,
+    UnknownVariable,
+    /// call or reference matching no declared function.
+    UnknownFunction,
+    UnknownType,
+    UnknownField,
+    /// record declaration without fields, with duplicate fields or fields of unknown size.
+    InvalidRecord,
+    MismatchedTypes,
+    Redeclaration,
+    /// function returning a value which can end without `return`, or `return` not matching its function.
+    InvalidReturn,
+    /// assignment to a constant or to an expression.
+    InvalidAssignment,
+    NotConstant,
+    /// user defined `main` or top level statements not fitting it.
+    InvalidEntryPoint,
+    /// construct the compiler does not support.
+    Unsupported,
+    /// program rejected while generating code for the target.
+    Codegen,
+    /// call matched as well by several overloads.
+    AmbiguousCall,
+    /// runtime failure of a program run by the interpreter, as an int divided by zero.
+    Evaluation,
+    /// `match` without `else` whose cases do not list every value.
+    NonExhaustiveMatch,
+    /// file that cannot be read or written.
+    Io,
+    /// assembler or linker failing on the generated code, or built program failing to start.
+    Build,
+    /// `@pragma` unknown, or whose value is invalid.
+    InvalidPragma,
+    /// formatted `print` whose format is invalid or does not match its values.
+    InvalidFormat,
+    /// line of a lint configuration file which sets no known rule.
+    InvalidConfig,
+    UnreachableCode,
+    UninitializedVariable,
+    /// function whose frame, or the stack used by its calls, exceeds the stack budget of the options.
+    StackBudget,
+    /// functions calling themselves, whose stack use has no bound.
+    UnboundedRecursion,
+    /// condition of an `if` or a `while` always having the same value, one of its branches being dead.
+    DecidedBranch,
+    /// function never called nor referenced.
+    UnusedFunction,
+    /// assignment used as a condition.
+    AssignmentInCondition,
+    /// number written outside of a constant.
+    MagicNumber,
+    /// name of a function, parameter or variable which is not in snake_case.
+    NonSnakeCase,
+    /// function never reached from the top level statements, `main` nor the functions with a symbol.
+    DeadFunction,
+}
+
+impl Code {
+    /// Codes of the warnings, which the options can silence or turn into errors.
+    pub const WARNINGS: [Code; 4] = [Code::UnreachableCode, Code::UninitializedVariable, Code::StackBudget, Code::UnboundedRecursion];
+
+    pub const ALL: [Code; 33] = [
+        Code::InvalidToken, Code::Syntax, Code::UnknownVariable, Code::UnknownFunction, Code::UnknownType, Code::UnknownField,
+        Code::InvalidRecord, Code::MismatchedTypes, Code::Redeclaration, Code::InvalidReturn, Code::InvalidAssignment,
+        Code::NotConstant, Code::InvalidEntryPoint, Code::Unsupported, Code::Codegen, Code::AmbiguousCall, Code::Evaluation,
+        Code::NonExhaustiveMatch, Code::Io, Code::Build, Code::InvalidPragma, Code::InvalidFormat, Code::InvalidConfig,
+        Code::UnreachableCode, Code::UninitializedVariable, Code::StackBudget, Code::UnboundedRecursion, Code::DecidedBranch,
+        Code::UnusedFunction, Code::AssignmentInCondition, Code::MagicNumber, Code::NonSnakeCase, Code::DeadFunction,
+    ];
+
+    /// Code written as `E0003`, `None` when no diagnostic has it.
+    pub fn from_name(name: &str) -> Option<Code> {
+        return Code::ALL.iter().find(|code| code.as_str() == name).copied();
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        return match self {
+            Code::InvalidToken => "E0001",
+            Code::Syntax => "E0002",
+            Code::UnknownVariable => "E0003",
+            Code::UnknownFunction => "E0004",
+            Code::UnknownType => "E0005",
+            Code::UnknownField => "E0006",
+            Code::InvalidRecord => "E0007",
+            Code::MismatchedTypes => "E0008",
+            Code::Redeclaration => "E0009",
+            Code::InvalidReturn => "E0010",
+            Code::InvalidAssignment => "E0011",
+            Code::NotConstant => "E0012",
+            Code::InvalidEntryPoint => "E0013",
+            Code::Unsupported => "E0014",
+            Code::Codegen => "E0015",
+            Code::AmbiguousCall => "E0016",
+            Code::Evaluation => "E0017",
+            Code::NonExhaustiveMatch => "E0018",
+            Code::Io => "E0019",
+            Code::Build => "E0020",
+            Code::InvalidPragma => "E0021",
+            Code::InvalidFormat => "E0022",
+            Code::InvalidConfig => "E0023",
+            Code::UnreachableCode => "W0001",
+            Code::UninitializedVariable => "W0002",
+            Code::StackBudget => "W0003",
+            Code::UnboundedRecursion => "W0004",
+            Code::DecidedBranch => "I0001",
+            Code::UnusedFunction => "L0001",
+            Code::AssignmentInCondition => "L0002",
+            Code::MagicNumber => "L0003",
+            Code::NonSnakeCase => "L0004",
+            Code::DeadFunction => "L0005",
+        };
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.as_str());
+    }
+}
+
+/// Error or warning found by the lexer, the parser or the compiler, located where it occurs.
+/// Diagnostics found after the semantic analysis have no location, their span being `0:0`, or only their line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub code: Code,
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    /// explanations printed after the message.
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(code: Code, message: String, span: Span) -> Self {
+        return Diagnostic { code, severity: Severity::Error, span, message, notes: Vec::new() };
+    }
+
+    pub fn warning(code: Code, message: String, span: Span) -> Self {
+        return Diagnostic { code, severity: Severity::Warning, span, message, notes: Vec::new() };
+    }
+
+    pub fn info(code: Code, message: String, span: Span) -> Self {
+        return Diagnostic { code, severity: Severity::Info, span, message, notes: Vec::new() };
+    }
+
+    pub fn with_note(mut self, note: &str) -> Self {
+        self.notes.push(note.to_string());
+        return self;
+    }
+
+    pub fn is_error(&self) -> bool {
+        return self.severity == Severity::Error;
+    }
+
+    /// The diagnostic with its message and its notes in `language`, those missing from the catalog staying in English.
+    pub fn translate(&self, language: Language) -> Diagnostic {
+        return Diagnostic {
+            message: messages::translate(&self.message, language),
+            notes: self.notes.iter().map(|note| messages::translate(note, language)).collect(),
+            ..self.clone()
+        };
+    }
+
+    /// JSON object of the diagnostic, on a single line:
+    /// `{"code": "E0003", "severity": "error", "file": "main.algo", "span": {"line": 3, "column": 5}, "message": ..., "notes": [...]}`.
+    /// The file and the span are `null` when the diagnostic is not located, `file` being the path of the file of its span.
+    /// The severity is written in `language`.
+    pub fn to_json(&self, file: Option<&str>, language: Language) -> String {
+        let (file, span) = match (file, self.span.line) {
+            (_, 0) => (String::from("null"), String::from("null")),
+            (file, line) => (
+                file.map(json::string).unwrap_or(String::from("null")),
+                format!("{{\"line\": {}, \"column\": {}}}", line, self.span.column),
+            ),
+        };
+        let notes = self.notes.iter().map(|note| json::string(note)).collect::<Vec<String>>();
+        return format!(
+            "{{\"code\": {}, \"severity\": {}, \"file\": {}, \"span\": {}, \"message\": {}, \"notes\": [{}]}}",
+            json::string(self.code.as_str()), json::string(language.severity(self.severity)), file, span, json::string(&self.message), notes.join(", "),
+        );
+    }
+}
+
+/// JSON array of the diagnostics, none of them being located in a file.
+pub fn json_array(diagnostics: &[Diagnostic]) -> String {
+    return format!("[{}]", diagnostics.iter().map(|d| d.to_json(None, Language::English)).collect::<Vec<String>>().join(", "));
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl Diagnostic {
+    /// `3:5: error[E0003]: unknown variable 'x'`, each note on its own line, the severity being written in `language`.
+    pub fn to_short(&self, language: Language) -> String {
+        let mut res = format!("{}: {}[{}]: {}", self.span, language.severity(self.severity), self.code, self.message);
+        for note in &self.notes {
+            res.push_str(format!("\n    note: {}", note).as_str());
+        }
+        return res;
+    }
+}
+
+/// The short form of the diagnostic, in English.
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.to_short(Language::English));
+    }
+}
@@ -0,0 +1,88 @@
+use super::{Diagnostic, Language, Severity};
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+/// gutter, arrow and `=` of the notes.
+const BLUE: &str = "\x1b[1;34m";
+
+fn severity_style(severity: Severity) -> &'static str {
+    return match severity {
+        Severity::Error => "\x1b[1;31m",
+        Severity::Warning => "\x1b[1;33m",
+        Severity::Info => "\x1b[1;36m",
+    };
+}
+
+/// First character and length of the text underlined in `line`. Most spans locate the statement rather than the
+/// faulty expression, so the name the message quotes is underlined when it follows the 1-based `column`, then the word
+/// starting at the column, or its character when it starts no word. The whole line is underlined when only the line is known.
+fn underline(line: &str, column: usize, message: &str) -> (usize, usize) {
+    let chars = line.chars().collect::<Vec<char>>();
+    if column == 0 {
+        let start = chars.iter().position(|c| !c.is_whitespace()).unwrap_or(0);
+        let end = chars.iter().rposition(|c| !c.is_whitespace()).map_or(start, |end| end + 1);
+        return (start, (end - start).max(1));
+    }
+    let start = (column - 1).min(chars.len());
+    let quoted = message.split('\'').nth(1).filter(|quoted| !quoted.is_empty()).map(|quoted| quoted.chars().collect::<Vec<char>>());
+    if let Some(quoted) = quoted {
+        if let Some(i) = chars[start..].windows(quoted.len()).position(|window| window == quoted.as_slice()) {
+            return (start + i, quoted.len());
+        }
+    }
+    let word = chars[start..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+    return (start, word.max(1));
+}
+
+/// The diagnostic as rustc prints its own, followed by the line of `source` it is located at with its position underlined:
+///
+/// ```text
+/// error[E0003]: unknown variable 'y'
+///  --> bad.algo:2:5
+///   |
+/// 2 |     return y
+///   |            ^
+///   = help: for more information, run `algo_parser --explain E0003`
+/// ```
+///
+/// `path` names the file of the diagnostic, and `source` is its text. `color` highlights the severity and the gutter
+/// with ANSI escape codes. The labels are written in `language`, the message being translated beforehand.
+pub fn render(d: &Diagnostic, path: Option<&str>, source: Option<&str>, color: bool, language: Language) -> String {
+    let paint = |style: &str, text: &str| match color {
+        true => format!("{}{}{}", style, text, RESET),
+        false => String::from(text),
+    };
+    let style = severity_style(d.severity);
+    let mut res = format!("{}{}", paint(style, &format!("{}[{}]", language.severity(d.severity), d.code)), paint(BOLD, &format!(": {}", d.message)));
+
+    let mut pad = String::new();
+    if d.span.line > 0 {
+        pad = " ".repeat(d.span.line.to_string().len());
+        let position = match d.span.column {
+            0 => format!("{}", d.span.line),
+            column => format!("{}:{}", d.span.line, column),
+        };
+        let location = match path {
+            Some(path) => format!("{}:{}", path, position),
+            None => position,
+        };
+        res.push_str(format!("\n{}{} {}", pad, paint(BLUE, "-->"), location).as_str());
+
+        if let Some(line) = source.and_then(|source| source.lines().nth(d.span.line - 1)) {
+            let (start, length) = underline(line, d.span.column, &d.message);
+            // tabs are kept before the carets for them to line up with the quoted line.
+            let indent = line.chars().take(start).map(|c| if c == '\t' { '\t' } else { ' ' }).collect::<String>();
+            let gutter = paint(BLUE, &format!("{} |", pad));
+            res.push_str(format!("\n{}", gutter).as_str());
+            res.push_str(format!("\n{} {}", paint(BLUE, &format!("{} |", d.span.line)), line).as_str());
+            res.push_str(format!("\n{} {}{}", gutter, indent, paint(style, &"^".repeat(length))).as_str());
+        }
+    }
+
+    for note in &d.notes {
+        res.push_str(format!("\n{} {} {}: {}", pad, paint(BLUE, "="), paint(BOLD, "note"), note).as_str());
+    }
+    let help = language.explain_help(d.code);
+    res.push_str(format!("\n{} {} {}: {}", pad, paint(BLUE, "="), paint(BOLD, language.help()), help).as_str());
+    return res;
+}
@@ -0,0 +1,242 @@
+//! Line editing of `algo_parser repl`, written without a library: while a line is read, `stty` takes the terminal out
+//! of its line mode for the keys to reach the editor, which moves the cursor, erases and recalls the previous lines.
+//! When stdin or stdout is not a terminal, or `stty` cannot be run, the lines are read as they come.
+
+use std::io::{BufRead, ErrorKind, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+/// Spaces inserted by the tab key, the indentation of the blocks.
+const TAB: &str = "    ";
+
+/// Reads the lines, keeping those entered for the arrows to recall them.
+pub struct Editor {
+    history: Vec<String>,
+    terminal: bool,
+}
+
+/// Runs `stty` on the terminal of stdin, returning what it prints.
+fn stty(args: &[&str]) -> std::io::Result<String> {
+    let output = match Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output() {
+        Err(e) => return Err(e),
+        Ok(output) => output,
+    };
+    if !output.status.success() {
+        return Err(std::io::Error::other(output.status.to_string()));
+    }
+    return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+}
+
+fn read_byte(input: &mut dyn Read) -> std::io::Result<Option<u8>> {
+    let mut byte = [0u8];
+    return match input.read(&mut byte) {
+        Err(e) => Err(e),
+        Ok(0) => Ok(None),
+        Ok(_) => Ok(Some(byte[0])),
+    };
+}
+
+/// Character starting with the byte `first`, the bytes following it in a UTF-8 sequence being read from `input`.
+fn read_char(first: u8, input: &mut dyn Read) -> std::io::Result<Option<char>> {
+    let mut bytes = vec![first];
+    for _ in 1..first.leading_ones() {
+        match read_byte(input) {
+            Err(e) => return Err(e),
+            Ok(None) => break,
+            Ok(Some(byte)) => bytes.push(byte),
+        };
+    }
+    return Ok(String::from_utf8(bytes).ok().and_then(|text| text.chars().next()));
+}
+
+/// Line being edited, with the position of the cursor in its characters.
+struct Line {
+    chars: Vec<char>,
+    cursor: usize,
+}
+
+impl Line {
+    fn set(&mut self, text: &str) {
+        self.chars = text.chars().collect();
+        self.cursor = self.chars.len();
+    }
+
+    fn text(&self) -> String {
+        return self.chars.iter().collect();
+    }
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        let terminal = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+        return Editor { history: Vec::new(), terminal };
+    }
+
+    /// Next line, without its end, read after printing `prompt`. `None` at the end of the input, and an error of kind
+    /// `Interrupted` when ctrl-c drops the line.
+    pub fn read_line(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        let saved = match self.terminal {
+            true => stty(&["-g"]).ok(),
+            false => None,
+        };
+        // ctrl-c and ctrl-d reach the editor as keys, the terminal not turning them into a signal or an end of file.
+        let saved = saved.filter(|_| stty(&["-icanon", "-echo", "-isig", "min", "1", "time", "0"]).is_ok());
+        let res = match &saved {
+            None => self.read_plain(prompt),
+            Some(_) => self.edit(prompt),
+        };
+        if let Some(saved) = saved {
+            let _ = stty(&[&saved]);
+        }
+        if let Ok(Some(line)) = &res {
+            if !line.trim().is_empty() && self.history.last() != Some(line) {
+                self.history.push(line.clone());
+            }
+        }
+        return res;
+    }
+
+    fn read_plain(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        print!("{}", prompt);
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        return match std::io::stdin().lock().read_line(&mut line) {
+            Err(e) => Err(e),
+            Ok(0) => {
+                println!();
+                Ok(None)
+            },
+            Ok(_) => Ok(Some(line.trim_end_matches(['\n', '\r']).to_string())),
+        };
+    }
+
+    fn edit(&mut self, prompt: &str) -> std::io::Result<Option<String>> {
+        let stdin = std::io::stdin();
+        let mut input = stdin.lock();
+        let mut out = std::io::stdout();
+        let mut line = Line { chars: Vec::new(), cursor: 0 };
+        // line of the history shown, the one being entered when past the last, which is kept while the others are.
+        let mut recalled = self.history.len();
+        let mut entered = String::new();
+        loop {
+            // the line is written again after each key, the cursor being moved back to its position.
+            let back = match line.chars.len() - line.cursor {
+                0 => String::new(),
+                count => format!("\x1b[{}D", count),
+            };
+            let _ = write!(out, "\r{}{}\x1b[K{}", prompt, line.text(), back);
+            let _ = out.flush();
+
+            let byte = match read_byte(&mut input) {
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+                Ok(None) if line.chars.is_empty() => {
+                    let _ = write!(out, "\r\n");
+                    return Ok(None);
+                },
+                Ok(None) => b'\n',
+                Ok(Some(byte)) => byte,
+            };
+            match byte {
+                b'\r' | b'\n' => {
+                    let _ = write!(out, "\r\n");
+                    return Ok(Some(line.text()));
+                },
+                // ctrl-c
+                0x03 => {
+                    let _ = write!(out, "^C\r\n");
+                    return Err(std::io::Error::from(ErrorKind::Interrupted));
+                },
+                // ctrl-d
+                0x04 if line.chars.is_empty() => {
+                    let _ = write!(out, "\r\n");
+                    return Ok(None);
+                },
+                0x04 if line.cursor < line.chars.len() => {
+                    line.chars.remove(line.cursor);
+                },
+                // backspace
+                0x7f | 0x08 if line.cursor > 0 => {
+                    line.cursor -= 1;
+                    line.chars.remove(line.cursor);
+                },
+                0x7f => (),
+                // ctrl-a and ctrl-e
+                0x01 => line.cursor = 0,
+                0x05 => line.cursor = line.chars.len(),
+                // ctrl-b and ctrl-f
+                0x02 => line.cursor = line.cursor.saturating_sub(1),
+                0x06 => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                // ctrl-u and ctrl-k
+                0x15 => {
+                    line.chars.drain(..line.cursor);
+                    line.cursor = 0;
+                },
+                0x0b => line.chars.truncate(line.cursor),
+                b'\t' => {
+                    for c in TAB.chars() {
+                        line.chars.insert(line.cursor, c);
+                        line.cursor += 1;
+                    }
+                },
+                0x1b => {
+                    let key = match read_byte(&mut input) {
+                        Ok(Some(b'[' | b'O')) => read_byte(&mut input).ok().flatten(),
+                        _ => None,
+                    };
+                    // the keys sent as `ESC [ <number> ~` are read up to the `~`, the delete key being 3.
+                    let key = match key {
+                        Some(digit @ b'0'..=b'9') => {
+                            let mut number = vec![digit];
+                            while let Ok(Some(byte)) = read_byte(&mut input) {
+                                if byte == b'~' {
+                                    break;
+                                }
+                                number.push(byte);
+                            }
+                            match number.as_slice() {
+                                b"3" => Some(b'~'),
+                                b"1" | b"7" => Some(b'H'),
+                                b"4" | b"8" => Some(b'F'),
+                                _ => None,
+                            }
+                        },
+                        key => key,
+                    };
+                    match key {
+                        Some(b'A') if recalled > 0 => {
+                            if recalled == self.history.len() {
+                                entered = line.text();
+                            }
+                            recalled -= 1;
+                            line.set(&self.history[recalled]);
+                        },
+                        Some(b'B') if recalled < self.history.len() => {
+                            recalled += 1;
+                            match self.history.get(recalled) {
+                                Some(text) => line.set(text),
+                                None => line.set(&entered),
+                            };
+                        },
+                        Some(b'C') => line.cursor = (line.cursor + 1).min(line.chars.len()),
+                        Some(b'D') => line.cursor = line.cursor.saturating_sub(1),
+                        Some(b'H') => line.cursor = 0,
+                        Some(b'F') => line.cursor = line.chars.len(),
+                        Some(b'~') if line.cursor < line.chars.len() => {
+                            line.chars.remove(line.cursor);
+                        },
+                        _ => (),
+                    };
+                },
+                byte if byte < 0x20 => (),
+                byte => match read_char(byte, &mut input) {
+                    Err(e) => return Err(e),
+                    Ok(None) => (),
+                    Ok(Some(c)) => {
+                        line.chars.insert(line.cursor, c);
+                        line.cursor += 1;
+                    },
+                },
+            };
+        }
+    }
+}
@@ -0,0 +1,150 @@
+//! Errors of the functions chaining the stages of the compiler, implementing `std::error::Error` for callers to
+//! propagate them with `?`. The stages themselves fail with diagnostics, which implement it as well.
+
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+use super::diagnostic::{Code, Diagnostic};
+use super::lexer::Span;
+
+/// Source of a program which could not be split into tokens, at the character the diagnostic locates.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LexError {
+    pub diagnostic: Diagnostic,
+}
+
+impl From<Diagnostic> for LexError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        return LexError { diagnostic };
+    }
+}
+
+impl Display for LexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.diagnostic);
+    }
+}
+
+/// The diagnostic being written by the error, it has no source.
+impl Error for LexError {}
+
+/// Tokens which do not form a program, the diagnostic locating the first statement which could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub diagnostic: Diagnostic,
+}
+
+impl From<Diagnostic> for ParseError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        return ParseError { diagnostic };
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}", self.diagnostic);
+    }
+}
+
+/// The diagnostic being written by the error, it has no source.
+impl Error for ParseError {}
+
+/// Diagnostics of a program which failed to compile, its errors along with the warnings found before them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl From<Vec<Diagnostic>> for CompileError {
+    fn from(diagnostics: Vec<Diagnostic>) -> Self {
+        return CompileError { diagnostics };
+    }
+}
+
+/// Each diagnostic on its own line.
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, d) in self.diagnostics.iter().enumerate() {
+            let separator = if i > 0 { "\n" } else { "" };
+            if let Err(e) = write!(f, "{}{}", separator, d) {
+                return Err(e);
+            }
+        }
+        return Ok(());
+    }
+}
+
+/// The diagnostics being written by the error, it has no source.
+impl Error for CompileError {}
+
+/// Stage of `compile_file` or `run` which failed.
+#[derive(Debug)]
+pub enum AlgoError {
+    /// the file of the program could not be read.
+    Io { path: String, error: io::Error },
+    /// the source could not be split into tokens.
+    Lex(LexError),
+    /// the tokens do not form a program.
+    Parse(ParseError),
+    /// the program is incorrect, or its code could not be generated.
+    Compile(CompileError),
+    /// the executable could not be built or run.
+    Build(Diagnostic),
+}
+
+impl AlgoError {
+    /// Diagnostics of the error, an I/O error being reported with the `Io` code.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        return match self {
+            AlgoError::Io { path, error } => vec![Diagnostic::error(Code::Io, format!("could not read {}: {}", path, error), Span::default())],
+            AlgoError::Lex(e) => vec![e.diagnostic.clone()],
+            AlgoError::Parse(e) => vec![e.diagnostic.clone()],
+            AlgoError::Build(d) => vec![d.clone()],
+            AlgoError::Compile(e) => e.diagnostics.clone(),
+        };
+    }
+}
+
+impl From<LexError> for AlgoError {
+    fn from(e: LexError) -> Self {
+        return AlgoError::Lex(e);
+    }
+}
+
+impl From<ParseError> for AlgoError {
+    fn from(e: ParseError) -> Self {
+        return AlgoError::Parse(e);
+    }
+}
+
+impl From<CompileError> for AlgoError {
+    fn from(e: CompileError) -> Self {
+        return AlgoError::Compile(e);
+    }
+}
+
+/// What failed, the cause being the source of the error.
+impl Display for AlgoError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        return match self {
+            AlgoError::Io { path, .. } => write!(f, "could not read {}", path),
+            AlgoError::Lex(..) => write!(f, "could not split the program into tokens"),
+            AlgoError::Parse(..) => write!(f, "could not parse the program"),
+            AlgoError::Compile(..) => write!(f, "could not compile the program"),
+            AlgoError::Build(..) => write!(f, "could not build or run the program"),
+        };
+    }
+}
+
+impl Error for AlgoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        return match self {
+            AlgoError::Io { error, .. } => Some(error),
+            AlgoError::Lex(e) => Some(e),
+            AlgoError::Parse(e) => Some(e),
+            AlgoError::Build(d) => Some(d),
+            AlgoError::Compile(e) => Some(e),
+        };
+    }
+}
@@ -0,0 +1,103 @@
+//! C bindings, built with the `ffi` feature into the dynamic library of the crate, declared by
+//! `include/algo_parser.h`. The functions take the source of a program as a C string and return a result holding
+//! a JSON text, which the caller frees with `algo_free_result`.
+
+use std::ffi::{c_char, CStr, CString};
+
+use super::compiler::{self, CompilerOptions, Target};
+use super::diagnostic::{self, Code, Diagnostic};
+use super::json;
+use super::lexer::Span;
+use super::parser;
+
+/// Result of a call, allocated by the library.
+#[repr(C)]
+pub struct AlgoResult {
+    /// whether the call succeeded, `json` holding the JSON array of the diagnostics when it did not.
+    pub ok: bool,
+    pub json: *mut c_char,
+}
+
+fn result(ok: bool, json: String) -> *mut AlgoResult {
+    // the JSON texts escape their control characters, they hold no NUL byte.
+    let json = CString::new(json).unwrap_or_default().into_raw();
+    return Box::into_raw(Box::new(AlgoResult { ok, json }));
+}
+
+fn failure(diagnostics: &[Diagnostic]) -> *mut AlgoResult {
+    return result(false, diagnostic::json_array(diagnostics));
+}
+
+/// Text of the C string `text`, named `name` in the errors.
+unsafe fn read_str<'a>(text: *const c_char, name: &str) -> Result<&'a str, Diagnostic> {
+    if text.is_null() {
+        return Err(Diagnostic::error(Code::Unsupported, format!("the {} is a null pointer", name), Span::default()));
+    }
+    return match CStr::from_ptr(text).to_str() {
+        Err(_) => Err(Diagnostic::error(Code::Unsupported, format!("the {} is not valid UTF-8", name), Span::default())),
+        Ok(text) => Ok(text),
+    };
+}
+
+/// Parses `source`, the JSON of the result being the syntax tree as `parser::to_json` writes it.
+///
+/// # Safety
+///
+/// `source` must be null or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn algo_parse(source: *const c_char) -> *mut AlgoResult {
+    let source = match read_str(source, "source") {
+        Err(e) => return failure(&[e]),
+        Ok(source) => source,
+    };
+    return match super::parse_str(source) {
+        Err(e) => failure(&[e]),
+        Ok(ast) => result(true, parser::to_json(&ast)),
+    };
+}
+
+/// Compiles `source` for `target`, named as on the command line, the JSON of the result being
+/// `{"code": "...", "diagnostics": [...]}`, the diagnostics being the warnings of the program.
+///
+/// # Safety
+///
+/// `source` and `target` must be null or point to NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn algo_compile(source: *const c_char, target: *const c_char) -> *mut AlgoResult {
+    let (source, name) = match (read_str(source, "source"), read_str(target, "target")) {
+        (Err(e), _) | (_, Err(e)) => return failure(&[e]),
+        (Ok(source), Ok(name)) => (source, name),
+    };
+    let target = match Target::from_name(name) {
+        None => return failure(&[Diagnostic::error(Code::Unsupported, format!("unknown target '{}'", name), Span::default())]),
+        Some(target) => target,
+    };
+    let ast = match super::parse_str(source) {
+        Err(e) => return failure(&[e]),
+        Ok(ast) => ast,
+    };
+    let options = CompilerOptions { target, ..CompilerOptions::default() };
+    return match compiler::compile(&ast, &options) {
+        Err(diagnostics) => failure(&diagnostics),
+        Ok(compiled) => {
+            let json = format!("{{\"code\": {}, \"diagnostics\": {}}}", json::string(&compiled.code), diagnostic::json_array(&compiled.diagnostics));
+            result(true, json)
+        },
+    };
+}
+
+/// Frees a result returned by the library, doing nothing when it is null.
+///
+/// # Safety
+///
+/// `result` must be null or a result returned by the library, which has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn algo_free_result(result: *mut AlgoResult) {
+    if result.is_null() {
+        return;
+    }
+    let result = Box::from_raw(result);
+    if !result.json.is_null() {
+        drop(CString::from_raw(result.json));
+    }
+}
@@ -0,0 +1,132 @@
+//! Debugger of the programs run by `program::run_program`, pausing them before the statements of the lines with a
+//! breakpoint or after a step, for a `Session` to inspect the call stack and the variables and to tell how the program
+//! goes on.
+
+use super::super::lexer::Span;
+use super::program::Frame;
+use super::trace::Tracer;
+
+/// How the program goes on after a pause.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// pause before the next statement, entering the calls.
+    Step,
+    /// pause before the next statement of the function, or of its callers once it returns, running the calls without
+    /// pausing in them.
+    Next,
+    /// pause at the next breakpoint.
+    Continue,
+}
+
+/// Line of a file before whose statements the program pauses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breakpoint {
+    /// index of the file, as in the spans.
+    pub file: usize,
+    pub line: usize,
+}
+
+/// Told of the pauses of the program.
+pub trait Session {
+    /// Called when the program pauses before the statement at `span`, `frames` being the top level followed by the
+    /// calls being run. The breakpoints can be changed before the program goes on as the command tells.
+    fn paused(&mut self, span: Span, frames: &[Frame], breakpoints: &mut Vec<Breakpoint>) -> Command;
+}
+
+/// Tracer pausing the program for the session.
+pub struct Debugger<'a> {
+    pub breakpoints: Vec<Breakpoint>,
+    session: &'a mut dyn Session,
+    /// deepest call whose statements pause the program, none pausing only at the breakpoints.
+    depth: Option<usize>,
+}
+
+impl<'a> Debugger<'a> {
+    /// Debugger of a program starting as `start` tells, `Command::Step` pausing before its first statement and
+    /// `Command::Continue` running it to the first breakpoint.
+    pub fn new(session: &'a mut dyn Session, breakpoints: Vec<Breakpoint>, start: Command) -> Self {
+        let mut res = Debugger { breakpoints, session, depth: None };
+        res.go_on(start, 1);
+        return res;
+    }
+
+    fn go_on(&mut self, command: Command, depth: usize) {
+        self.depth = match command {
+            Command::Step => Some(usize::MAX),
+            Command::Next => Some(depth),
+            Command::Continue => None,
+        };
+    }
+}
+
+impl<'a> Tracer for Debugger<'a> {
+    fn statement(&mut self, span: Span, frames: &[Frame]) {
+        let breakpoint = self.breakpoints.iter().any(|breakpoint| breakpoint.file == span.file && breakpoint.line == span.line);
+        if !breakpoint && self.depth.is_none_or(|depth| frames.len() > depth) {
+            return;
+        }
+        let command = self.session.paused(span, frames, &mut self.breakpoints);
+        self.go_on(command, frames.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Breakpoint, Command, Debugger, Session};
+    use crate::compiler::CompilerOptions;
+    use crate::interpreter::program::{run_program, Frame};
+    use crate::lexer::Span;
+
+    /// Session giving its commands in turn, continuing once they are all given, and keeping the pauses as the line
+    /// and the functions of the call stack.
+    struct Script {
+        commands: Vec<Command>,
+        pauses: Vec<(usize, Vec<String>)>,
+    }
+
+    impl Session for Script {
+        fn paused(&mut self, span: Span, frames: &[Frame], _breakpoints: &mut Vec<Breakpoint>) -> Command {
+            self.pauses.push((span.line, frames.iter().map(|frame| frame.function.clone()).collect()));
+            return match self.commands.is_empty() {
+                true => Command::Continue,
+                false => self.commands.remove(0),
+            };
+        }
+    }
+
+    const PROGRAM: &str = "function double(x: int): int\n    y <- x * 2\n    return y\nend\n\na <- double(1)\nb <- double(a)\nprint(b)\n";
+
+    /// Lines and call stacks of the pauses of `PROGRAM`.
+    fn pauses(breakpoints: Vec<Breakpoint>, start: Command, commands: Vec<Command>) -> Vec<(usize, Vec<String>)> {
+        let ast = match crate::parse_str(PROGRAM) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let mut script = Script { commands, pauses: Vec::new() };
+        let mut debugger = Debugger::new(&mut script, breakpoints, start);
+        let mut output = Vec::<u8>::new();
+        if let Err(e) = run_program(&ast, &CompilerOptions::default(), &mut "".as_bytes(), &mut output, &mut debugger) {
+            panic!("{}", e.message);
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), "4\n");
+        return script.pauses;
+    }
+
+    fn stack(functions: &[&str]) -> Vec<String> {
+        return functions.iter().map(|function| function.to_string()).collect();
+    }
+
+    #[test]
+    fn pauses_at_breakpoints() {
+        let res = pauses(vec![Breakpoint { file: 0, line: 3 }], Command::Continue, vec![]);
+        let inner = stack(&["<top level>", "double"]);
+        assert_eq!(res, vec![(3, inner.clone()), (3, inner)]);
+    }
+
+    #[test]
+    fn steps_into_and_over_calls() {
+        let res = pauses(vec![], Command::Step, vec![Command::Step, Command::Next, Command::Next, Command::Next]);
+        let (top, inner) = (stack(&["<top level>"]), stack(&["<top level>", "double"]));
+        assert_eq!(res, vec![(6, top.clone()), (2, inner.clone()), (3, inner), (7, top.clone()), (8, top)]);
+    }
+}
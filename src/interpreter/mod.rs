@@ -0,0 +1,586 @@
+//! Interpreter running the programs without compiling them, for students to watch their algorithm execute line by
+//! line. `program::run_program` runs whole programs statement by statement, reporting each of them to a
+//! `trace::Tracer`, which `debug::Debugger` is to pause them.
+//!
+//! `eval_expression` evaluates a single expression for the applications embedding the language, to compute
+//! `len(name) > 3` or `total <- total + price * 2` against the values of an `Environment` they prepared, as a
+//! grader checking the variables of a student's program. `price * 2 + 1` is `Value::Int(9)` once the environment
+//! sets `price` to `Value::Int(4)`.
+//!
+//! Expressions follow the rules of the compiled programs: operators are checked on the types of their values, ints
+//! are 32 bits and wrap around when they overflow, unless the overflow check is on, and the builtins computing a value
+//! can be called. An assignment gives its
+//! variable the value, which it returns.
+//!
+//! Maps are values of their own, indexed by ints or strings, a missing key being read as the zero value of the values
+//! of the map as in the compiled programs.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::compiler::RuntimeChecks;
+use super::diagnostic::{Code, Diagnostic};
+use super::json;
+use super::lexer::Span;
+use super::parser::Ast;
+
+pub mod debug;
+pub mod program;
+pub mod trace;
+
+/// Value of an expression or of a variable.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Array(Vec<Value>),
+    /// value of the record `name`, with its fields in order.
+    Record { name: String, fields: Vec<(String, Value)> },
+    /// function of the program, or lambda, called through a variable.
+    Function(String),
+    /// map with the names of the types of its keys and of its values, empty when the map is an empty literal whose
+    /// types are given by the variable it is assigned to.
+    Map { key: String, value: String, entries: HashMap<Key, Value> },
+}
+
+/// Key of a map.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Key {
+    Int(i64),
+    Str(String),
+}
+
+impl Key {
+    /// The key of the value, only ints and strings being keys.
+    pub fn from_value(value: &Value) -> Option<Key> {
+        return match value {
+            Value::Int(val) => Some(Key::Int(*val)),
+            Value::Str(val) => Some(Key::Str(val.clone())),
+            _ => None,
+        };
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Key::Int(val) => write!(f, "{}", val),
+            Key::Str(val) => write!(f, "{}", val),
+        };
+    }
+}
+
+/// Entries of the map sorted by key, for the map to be printed the same way on each run.
+fn sorted(entries: &HashMap<Key, Value>) -> Vec<(&Key, &Value)> {
+    let mut res = entries.iter().collect::<Vec<(&Key, &Value)>>();
+    res.sort_by_key(|(key, _)| *key);
+    return res;
+}
+
+/// Names of the types of the keys and of the values of the map type `typename`, as `map[string, int]`.
+fn map_types(typename: &str) -> Option<(String, String)> {
+    let normalized = |name: &str| if name == "string" { String::from("str") } else { name.to_string() };
+    return typename
+        .strip_prefix("map[")
+        .and_then(|types| types.strip_suffix(']'))
+        .and_then(|types| types.split_once(", "))
+        .map(|(key, value)| (normalized(key), normalized(value)));
+}
+
+impl Value {
+    /// Name of the type of the value, as written in programs.
+    pub fn type_name(&self) -> String {
+        return match self {
+            Value::Int(..) => String::from("int"),
+            Value::Float(..) => String::from("float"),
+            Value::Bool(..) => String::from("bool"),
+            Value::Str(..) => String::from("str"),
+            Value::Array(values) => match values.first() {
+                Some(value) => format!("{}[{}]", value.type_name(), values.len()),
+                None => String::from("[]"),
+            },
+            Value::Record { name, .. } => name.clone(),
+            Value::Function(..) => String::from("function"),
+            Value::Map { key, value, .. } => format!("map[{}, {}]", key, value),
+        };
+    }
+
+    /// The value as a JSON document, arrays as lists, records as objects of their fields and maps as objects of their
+    /// entries. Floats which are not finite are `null`.
+    pub fn to_json(&self) -> String {
+        return match self {
+            Value::Int(val) => val.to_string(),
+            Value::Float(val) if val.is_finite() => format!("{:?}", val),
+            Value::Float(..) => String::from("null"),
+            Value::Bool(val) => val.to_string(),
+            Value::Str(val) => json::string(val),
+            Value::Array(values) => format!("[{}]", values.iter().map(|value| value.to_json()).collect::<Vec<String>>().join(", ")),
+            Value::Record { fields, .. } => {
+                let fields = fields.iter().map(|(field, value)| format!("{}: {}", json::string(field), value.to_json())).collect::<Vec<String>>();
+                format!("{{{}}}", fields.join(", "))
+            },
+            Value::Function(name) => json::string(name),
+            Value::Map { entries, .. } => {
+                let entries = sorted(entries).into_iter().map(|(key, value)| format!("{}: {}", json::string(&key.to_string()), value.to_json())).collect::<Vec<String>>();
+                format!("{{{}}}", entries.join(", "))
+            },
+        };
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        return match self {
+            Value::Int(val) => Some(*val as f64),
+            Value::Float(val) => Some(*val),
+            _ => None,
+        };
+    }
+}
+
+/// Values as `print` formats them, floats with 6 decimals.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Value::Int(val) => write!(f, "{}", val),
+            Value::Float(val) => write!(f, "{:.6}", val),
+            Value::Bool(val) => write!(f, "{}", val),
+            Value::Str(val) => write!(f, "{}", val),
+            Value::Array(values) => write!(f, "[{}]", values.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(", ")),
+            Value::Record { name, fields } => {
+                let fields = fields.iter().map(|(field, value)| format!("{}: {}", field, value)).collect::<Vec<String>>();
+                write!(f, "{} {{{}}}", name, fields.join(", "))
+            },
+            Value::Function(name) => write!(f, "<function {}>", name),
+            Value::Map { entries, .. } => {
+                let entries = sorted(entries).into_iter().map(|(key, value)| format!("{}: {}", key, value)).collect::<Vec<String>>();
+                write!(f, "{{{}}}", entries.join(", "))
+            },
+        };
+    }
+}
+
+/// Variables of a function being run, or those the expressions given to `eval_expression` read and assign.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Environment {
+    variables: Vec<(String, Value)>,
+}
+
+impl Environment {
+    pub fn new() -> Environment {
+        return Environment::default();
+    }
+
+    /// Gives the variable `name` the value, whatever the type of its previous one.
+    pub fn set(&mut self, name: &str, value: Value) {
+        match self.variables.iter_mut().find(|(n, _)| n == name) {
+            Some((_, previous)) => *previous = value,
+            None => self.variables.push((name.to_string(), value)),
+        };
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        return self.variables.iter().find(|(n, _)| n == name).map(|(_, value)| value);
+    }
+
+    /// Variables in the order they were first given a value.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Value)> {
+        return self.variables.iter().map(|(name, value)| (name, value));
+    }
+
+    fn get_mut(&mut self, name: &str) -> Option<&mut Value> {
+        return self.variables.iter_mut().find(|(n, _)| n == name).map(|(_, value)| value);
+    }
+}
+
+/// Variables and functions of the place an expression is evaluated in.
+trait Context {
+    fn get(&self, name: &str) -> Option<&Value>;
+
+    fn set(&mut self, name: &str, value: Value);
+
+    /// Result of the call to a function other than the builtins computing a value, its arguments being evaluated by
+    /// the context. `None` when the context has no such function.
+    fn call(&mut self, name: &str, children: &[Ast], span: Span) -> Option<Result<Value, Diagnostic>>;
+
+    /// Value of the elements of a new array of `typename`.
+    fn initial(&self, typename: &str) -> Option<Value>;
+
+    /// Function value of the lambda, `None` when the context cannot call it.
+    fn lambda(&mut self, lambda: &Ast) -> Option<Value>;
+
+    /// Operations checked as the compiled program checks them at runtime.
+    fn checks(&self) -> RuntimeChecks;
+}
+
+/// The variables of the environment, which has no function.
+impl Context for Environment {
+    fn get(&self, name: &str) -> Option<&Value> {
+        return Environment::get(self, name);
+    }
+
+    fn set(&mut self, name: &str, value: Value) {
+        Environment::set(self, name, value);
+    }
+
+    fn call(&mut self, _name: &str, _children: &[Ast], _span: Span) -> Option<Result<Value, Diagnostic>> {
+        return None;
+    }
+
+    fn initial(&self, typename: &str) -> Option<Value> {
+        return initial(typename);
+    }
+
+    fn lambda(&mut self, _lambda: &Ast) -> Option<Value> {
+        return None;
+    }
+
+    fn checks(&self) -> RuntimeChecks {
+        return RuntimeChecks::default();
+    }
+}
+
+/// Value of a variable of the builtin type `typename` which is not assigned yet, maps being empty.
+fn initial(typename: &str) -> Option<Value> {
+    if let Some((key, value)) = map_types(typename) {
+        return Some(Value::Map { key, value, entries: HashMap::new() });
+    }
+    return match typename {
+        "int" => Some(Value::Int(0)),
+        "float" => Some(Value::Float(0.0)),
+        "bool" => Some(Value::Bool(false)),
+        "str" | "string" => Some(Value::Str(String::new())),
+        _ => None,
+    };
+}
+
+/// The value given the types of the map type `typename` when it is an empty map literal.
+fn typed(value: Value, typename: &str) -> Value {
+    return match (value, map_types(typename)) {
+        (Value::Map { entries, .. }, Some((key, value))) if entries.is_empty() => Value::Map { key, value, entries },
+        (value, _) => value,
+    };
+}
+
+/// Position of the expressions, which the statement running them replaces.
+const SPAN: Span = Span { line: 1, column: 1, file: 0 };
+
+fn error(code: Code, message: String) -> Diagnostic {
+    return Diagnostic::error(code, message, SPAN);
+}
+
+/// The int wrapped around to the 32 bits of the ints of the programs, or an overflow when it does not fit in them and
+/// `checked`.
+fn int(value: i128, checked: bool) -> Result<Value, Diagnostic> {
+    return match i32::try_from(value) {
+        Ok(val) => Ok(Value::Int(val as i64)),
+        Err(_) if checked => Err(error(Code::Evaluation, String::from("integer overflow"))),
+        Err(_) => Ok(Value::Int(value as i32 as i64)),
+    };
+}
+
+fn float(value: &Value) -> f64 {
+    return value.as_float().unwrap_or(0.0);
+}
+
+/// Value of the call to the builtin `name`, the builtins printing or reading being left out.
+fn call(name: &str, arguments: Vec<Value>) -> Result<Value, Diagnostic> {
+    let res = match (name, arguments.as_slice()) {
+        ("len" | "length", [Value::Str(val)]) => Value::Int(val.chars().count() as i64),
+        ("len" | "length", [Value::Array(values)]) => Value::Int(values.len() as i64),
+        ("substring", [Value::Str(val), Value::Int(start), Value::Int(length)]) => {
+            let start = (*start).clamp(0, val.chars().count() as i64) as usize;
+            Value::Str(val.chars().skip(start).take((*length).max(0) as usize).collect())
+        },
+        ("concat", [Value::Str(a), Value::Str(b)]) => Value::Str(format!("{}{}", a, b)),
+        ("compare", [Value::Str(a), Value::Str(b)]) => Value::Int(a.cmp(b) as i64),
+        ("to_string", [Value::Int(val)]) => Value::Str(val.to_string()),
+        ("contains", [Value::Map { key, entries, .. }, index]) if index.type_name() == *key => {
+            Value::Bool(Key::from_value(index).is_some_and(|index| entries.contains_key(&index)))
+        },
+        ("copy", [values @ Value::Array(..)]) => values.clone(),
+        ("append", [Value::Array(values), value]) => Value::Array(values.iter().chain([value]).cloned().collect()),
+        // abs is not checked by the compiled programs, abs(-2147483648) staying negative.
+        ("abs", [Value::Int(val)]) => return int((*val as i128).abs(), false),
+        ("min", [Value::Int(a), Value::Int(b)]) => Value::Int(*a.min(b)),
+        ("max", [Value::Int(a), Value::Int(b)]) => Value::Int(*a.max(b)),
+        ("sqrt", [value]) if value.as_float().is_some() => Value::Float(float(value).sqrt()),
+        ("floor", [value]) if value.as_float().is_some() => Value::Float(float(value).floor()),
+        ("ceil", [value]) if value.as_float().is_some() => Value::Float(float(value).ceil()),
+        ("log", [value]) if value.as_float().is_some() => Value::Float(float(value).ln()),
+        ("sin", [value]) if value.as_float().is_some() => Value::Float(float(value).sin()),
+        ("cos", [value]) if value.as_float().is_some() => Value::Float(float(value).cos()),
+        ("pow", [a, b]) if a.as_float().is_some() && b.as_float().is_some() => Value::Float(float(a).powf(float(b))),
+        _ => {
+            let types = arguments.iter().map(|value| value.type_name()).collect::<Vec<String>>();
+            return Err(error(Code::UnknownFunction, format!("no function matching {}({})", name, types.join(", "))));
+        },
+    };
+    return Ok(res);
+}
+
+/// Value of the binary operator `operator` applied to the values of its operands.
+fn operation(operator: &str, left: Value, right: Value, checks: RuntimeChecks) -> Result<Value, Diagnostic> {
+    let undefined = |left: &Value, right: &Value| {
+        let message = format!("operator {} is not defined on {} and {}", operator, left.type_name(), right.type_name());
+        return Err(error(Code::MismatchedTypes, message));
+    };
+    if let ("==" | "!=", false) = (operator, left.as_float().is_some() && right.as_float().is_some()) {
+        if left.type_name() != right.type_name() || matches!(left, Value::Record { .. }) {
+            return undefined(&left, &right);
+        }
+        return Ok(Value::Bool((left == right) == (operator == "==")));
+    }
+    let res = match (&left, &right) {
+        (Value::Int(a), Value::Int(b)) => match operator {
+            "+" => return int(*a as i128 + *b as i128, checks.overflow),
+            "-" => return int(*a as i128 - *b as i128, checks.overflow),
+            "*" => return int(*a as i128 * *b as i128, checks.overflow),
+            // the division by zero has no value, the compiled program being killed by it without the check.
+            "/" | "%" if *b == 0 => return Err(error(Code::Evaluation, String::from("division by zero"))),
+            "/" => return int(*a as i128 / *b as i128, checks.overflow),
+            "%" => return int(*a as i128 % *b as i128, checks.overflow),
+            "==" => Value::Bool(a == b),
+            "!=" => Value::Bool(a != b),
+            ">" => Value::Bool(a > b),
+            ">=" => Value::Bool(a >= b),
+            "<" => Value::Bool(a < b),
+            _ => Value::Bool(a <= b),
+        },
+        // ints are converted to floats when mixed with them.
+        (a, b) if a.as_float().is_some() && b.as_float().is_some() && operator != "%" => {
+            let (a, b) = (float(a), float(b));
+            match operator {
+                "+" => Value::Float(a + b),
+                "-" => Value::Float(a - b),
+                "*" => Value::Float(a * b),
+                "/" => Value::Float(a / b),
+                "==" => Value::Bool(a == b),
+                "!=" => Value::Bool(a != b),
+                ">" => Value::Bool(a > b),
+                ">=" => Value::Bool(a >= b),
+                "<" => Value::Bool(a < b),
+                _ => Value::Bool(a <= b),
+            }
+        },
+        _ => return undefined(&left, &right),
+    };
+    return Ok(res);
+}
+
+/// Element `index` of the array `variable`, or the value of the key `index` of the map.
+fn element(variable: &str, array: &Value, index: Value) -> Result<Value, Diagnostic> {
+    let values = match array {
+        Value::Array(values) => values,
+        Value::Map { key, value, entries } => {
+            return match Key::from_value(&index).filter(|_| index.type_name() == *key) {
+                None => Err(error(Code::MismatchedTypes, format!("map key must be a {}, got {}", key, index.type_name()))),
+                Some(index) => match entries.get(&index) {
+                    Some(value) => Ok(value.clone()),
+                    None => initial(value).ok_or_else(|| error(Code::UnknownType, format!("unknown type '{}'", value))),
+                },
+            };
+        },
+        array => return Err(error(Code::MismatchedTypes, format!("cannot index variable '{}' of type {}", variable, array.type_name()))),
+    };
+    let index = match index {
+        Value::Int(val) => val,
+        index => return Err(error(Code::MismatchedTypes, format!("array index must be an int, got {}", index.type_name()))),
+    };
+    return match usize::try_from(index).ok().and_then(|i| values.get(i)) {
+        Some(value) => Ok(value.clone()),
+        None => Err(error(Code::Evaluation, format!("index {} out of bounds for an array of {} elements", index, values.len()))),
+    };
+}
+
+fn evaluate(expression: &Ast, environment: &mut dyn Context) -> Result<Value, Diagnostic> {
+    let (left, right, operator) = match expression {
+        Ast::Int(val) => return int(*val as i128, environment.checks().overflow),
+        Ast::Float(val) => return Ok(Value::Float(*val)),
+        Ast::Bool(val) => return Ok(Value::Bool(*val)),
+        Ast::Str(val) => return Ok(Value::Str(val.clone())),
+        Ast::ArrayValue(children) => {
+            let mut values = Vec::<Value>::new();
+            for child in children {
+                let value = match evaluate(child, environment) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                if let Some(first) = values.first().filter(|first| first.type_name() != value.type_name()) {
+                    let message = format!("mismatching types '{}' and '{}' in array value", first.type_name(), value.type_name());
+                    return Err(error(Code::MismatchedTypes, message));
+                }
+                values.push(value);
+            }
+            if values.is_empty() {
+                return Err(error(Code::MismatchedTypes, String::from("cannot infer the type of an empty array")));
+            }
+            return Ok(Value::Array(values));
+        },
+        Ast::MapValue(entries) => {
+            let mut res = HashMap::<Key, Value>::new();
+            let mut types: Option<(String, String)> = None;
+            for (key, value) in entries {
+                let (key, value) = match (evaluate(key, environment), evaluate(value, environment)) {
+                    (Err(e), _) | (_, Err(e)) => return Err(e),
+                    (Ok(key), Ok(value)) => (key, value),
+                };
+                let entry = (key.type_name(), value.type_name());
+                if let Some(first) = types.as_ref().filter(|first| **first != entry) {
+                    let names = [first, &entry].map(|(key, value)| format!("map[{}, {}]", key, value));
+                    let message = format!("mismatching entries '{}' and '{}' in map value", names[0], names[1]);
+                    return Err(error(Code::MismatchedTypes, message));
+                }
+                match Key::from_value(&key) {
+                    None => return Err(error(Code::MismatchedTypes, format!("map keys must be ints or strings, got {}", key.type_name()))),
+                    Some(key) => res.insert(key, value),
+                };
+                types = Some(entry);
+            }
+            // the types of an empty map are given by the variable it is assigned to.
+            let (key, value) = types.unwrap_or_default();
+            return Ok(Value::Map { key, value, entries: res });
+        },
+        Ast::Variable(var) => {
+            return match environment.get(&var.name) {
+                Some(value) => Ok(value.clone()),
+                None => Err(error(Code::UnknownVariable, format!("unknown variable '{}'", var.name))),
+            };
+        },
+        Ast::ArrayAccess { variable, index } => {
+            let index = match evaluate(index, environment) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            return match environment.get(variable) {
+                Some(array) => element(variable, array, index),
+                None => Err(error(Code::UnknownVariable, format!("unknown variable '{}'", variable))),
+            };
+        },
+        Ast::MemberAccess { record, field } => {
+            return match evaluate(record, environment) {
+                Err(e) => Err(e),
+                Ok(Value::Record { name, fields }) => match fields.into_iter().find(|(n, _)| n == field) {
+                    Some((_, value)) => Ok(value),
+                    None => Err(error(Code::UnknownField, format!("record {} has no field '{}'", name, field))),
+                },
+                Ok(Value::Str(val)) if field == "length" => Ok(Value::Int(val.chars().count() as i64)),
+                Ok(Value::Array(values)) if field == "length" => Ok(Value::Int(values.len() as i64)),
+                Ok(value) => Err(error(Code::UnknownField, format!("cannot access field '{}' of type {}", field, value.type_name()))),
+            };
+        },
+        Ast::FunctionCall { name, children, span } => {
+            if let Some(res) = environment.call(name, children, *span) {
+                return res;
+            }
+            let mut arguments = Vec::<Value>::new();
+            for child in children {
+                arguments.push(match evaluate(child, environment) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                });
+            }
+            return call(name, arguments);
+        },
+        Ast::Lambda { .. } | Ast::FunctionReference(..) => {
+            let value = match expression {
+                Ast::FunctionReference(name) => Some(Value::Function(name.clone())),
+                lambda => environment.lambda(lambda),
+            };
+            return match value {
+                Some(value) => Ok(value),
+                None => Err(error(Code::Unsupported, format!("unsupported expression {:?}", expression))),
+            };
+        },
+        Ast::NewArray { typename, size } => {
+            let size = match evaluate(size, environment) {
+                Err(e) => return Err(e),
+                Ok(Value::Int(val)) if val >= 0 => val as usize,
+                Ok(Value::Int(val)) => return Err(error(Code::Evaluation, format!("cannot allocate an array of {} elements", val))),
+                Ok(size) => return Err(error(Code::MismatchedTypes, format!("array size must be an int, got {}", size.type_name()))),
+            };
+            return match environment.initial(typename) {
+                Some(value) => Ok(Value::Array(vec![value; size])),
+                None => Err(error(Code::UnknownType, format!("unknown type '{}'", typename))),
+            };
+        },
+        Ast::UnaryPlus { child } | Ast::UnaryMinus { child } => {
+            let operator = if matches!(expression, Ast::UnaryPlus { .. }) { "+" } else { "-" };
+            return match (evaluate(child, environment), operator) {
+                (Err(e), _) => Err(e),
+                (Ok(Value::Int(val)), "-") => int(-(val as i128), environment.checks().overflow),
+                (Ok(Value::Float(val)), "-") => Ok(Value::Float(-val)),
+                (Ok(value @ (Value::Int(..) | Value::Float(..))), _) => Ok(value),
+                (Ok(value), _) => Err(error(Code::MismatchedTypes, format!("operator {} is not defined on {}", operator, value.type_name()))),
+            };
+        },
+        // the right operand is only evaluated when the left one does not decide the result.
+        Ast::And { left, right } | Ast::Or { left, right } => {
+            let (operator, decisive) = if matches!(expression, Ast::And { .. }) { ("and", false) } else { ("or", true) };
+            let left = match evaluate(left, environment) {
+                Err(e) => return Err(e),
+                Ok(Value::Bool(val)) if val == decisive => return Ok(Value::Bool(val)),
+                Ok(val) => val,
+            };
+            return match (left, evaluate(right, environment)) {
+                (_, Err(e)) => Err(e),
+                (Value::Bool(..), Ok(Value::Bool(val))) => Ok(Value::Bool(val)),
+                (left, Ok(right)) => {
+                    let message = format!("operator {} is not defined on {} and {}", operator, left.type_name(), right.type_name());
+                    Err(error(Code::MismatchedTypes, message))
+                },
+            };
+        },
+        Ast::Assignement { variable, expression, .. } => {
+            let value = match evaluate(expression, environment) {
+                Err(e) => return Err(e),
+                Ok(val) => val,
+            };
+            let name = match &**variable {
+                Ast::Variable(var) => &var.name,
+                _ => return Err(error(Code::InvalidAssignment, String::from("can only assign value to a variable."))),
+            };
+            let value = match environment.get(name) {
+                Some(previous) => typed(value, &previous.type_name()),
+                None => value,
+            };
+            if let Some(previous) = environment.get(name).filter(|previous| previous.type_name() != value.type_name()) {
+                let message = format!("mismatching type for variable '{}', expected {}, got {}", name, previous.type_name(), value.type_name());
+                return Err(error(Code::MismatchedTypes, message));
+            }
+            environment.set(name, value.clone());
+            return Ok(value);
+        },
+        Ast::Addition { left, right } => (left, right, "+"),
+        Ast::Substraction { left, right } => (left, right, "-"),
+        Ast::Multiplication { left, right } => (left, right, "*"),
+        Ast::Division { left, right } => (left, right, "/"),
+        Ast::Modulo { left, right } => (left, right, "%"),
+        Ast::EqualTo { left, right } => (left, right, "=="),
+        Ast::NotEqualTo { left, right } => (left, right, "!="),
+        Ast::GreaterThan { left, right } => (left, right, ">"),
+        Ast::GreaterOrEqual { left, right } => (left, right, ">="),
+        Ast::LowerThan { left, right } => (left, right, "<"),
+        Ast::LowerOrEqual { left, right } => (left, right, "<="),
+        expression => return Err(error(Code::Unsupported, format!("unsupported expression {:?}", expression))),
+    };
+    let left = match evaluate(left, environment) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return match evaluate(right, environment) {
+        Err(e) => Err(e),
+        Ok(right) => operation(operator, left, right, environment.checks()),
+    };
+}
+
+/// Value of the expression written in `source`, or of the assignment, which gives its variable the value in
+/// `environment`. Fails when `source` is not a single expression, or when its evaluation does.
+pub fn eval_expression(source: &str, environment: &mut Environment) -> Result<Value, Diagnostic> {
+    let expression = match super::parse_str(source) {
+        Err(e) => return Err(e),
+        Ok(Ast::Global(mut children)) if children.len() == 1 => children.remove(0),
+        Ok(_) => return Err(error(Code::Syntax, String::from("expected a single expression"))),
+    };
+    return evaluate(&expression, environment);
+}
@@ -0,0 +1,1045 @@
+//! Runs of whole programs without compiling them, statement by statement, the tracer being told of each statement
+//! before it runs. Programs are expected to have passed the semantic analysis, the types being checked again only
+//! where a value is used.
+//!
+//! The top level statements run first, followed by `main` when the program defines it. Arrays, records and maps are
+//! passed to functions by reference as in the compiled programs, the changes the function made to its parameter being
+//! copied back to the argument when it returns. A map assigned to another variable is copied, where the compiled
+//! programs share it.
+
+use std::cmp::Ordering;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use super::super::compiler::formats::{self, Piece};
+use super::super::compiler::{pragmas, CompilerOptions, RuntimeChecks, ASSERT_STATUS};
+use super::super::diagnostic::{Code, Diagnostic};
+use super::super::lexer::Span;
+use super::super::parser::{Ast, Type, Variable};
+use super::trace::{NoTrace, Tracer};
+use super::{error, evaluate, initial, map_types, typed, Context, Environment, Key, Value};
+
+/// Name of the frame of the top level statements.
+pub const TOP_LEVEL: &str = "<top level>";
+
+/// Calls nested in one another past which the program is stopped, before the interpreter runs out of stack.
+const MAX_CALLS: usize = 512;
+
+/// Function being run, with its variables and the statement it is running.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    pub environment: Environment,
+    pub span: Span,
+}
+
+/// What leaves the statements of a block before their end.
+enum Stop {
+    Return(Option<Value>),
+    /// exception thrown with its message.
+    Throw(String),
+    Exit(i64),
+    Error(Diagnostic),
+}
+
+/// Element, entry or field of a variable being assigned.
+enum Step {
+    Index(Value),
+    Field(String),
+}
+
+/// Variables and lambdas left by the top level statements run so far, for a program to be run piece by piece by
+/// `run_statements`.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub globals: Environment,
+    /// functions of the lambdas evaluated so far, which the function values of the globals name.
+    lambdas: Vec<Rc<Ast>>,
+}
+
+struct Machine<'a> {
+    functions: Vec<&'a Ast>,
+    /// functions of the lambdas evaluated so far.
+    lambdas: Vec<Rc<Ast>>,
+    records: Vec<(&'a String, &'a Vec<Variable>)>,
+    /// the top level, whose variables are the globals, followed by the calls being run.
+    frames: Vec<Frame>,
+    input: &'a mut dyn BufRead,
+    output: &'a mut dyn Write,
+    tracer: &'a mut dyn Tracer,
+    /// what ended a call made by an expression, which the statement of the expression is left with.
+    pending: Option<Stop>,
+    checks: RuntimeChecks,
+}
+
+/// Whether the value can be given to a variable of type `typename`, ints being converted to floats.
+fn fits(value: &Value, typename: &Type) -> bool {
+    if typename.is_array {
+        let element = Type { name: typename.name.clone(), is_array: false, size: None, size_expression: None };
+        return match value {
+            Value::Array(values) => values.iter().all(|value| fits(value, &element)),
+            _ => false,
+        };
+    }
+    return match (typename.name.as_str(), value) {
+        ("int", Value::Int(..)) | ("float", Value::Int(..) | Value::Float(..)) | ("bool", Value::Bool(..)) => true,
+        ("str" | "string", Value::Str(..)) => true,
+        (name, Value::Record { name: record, .. }) => name == record,
+        (name, Value::Map { key, value, entries }) => map_types(name).is_some_and(|types| entries.is_empty() || types == (key.clone(), value.clone())),
+        _ => false,
+    };
+}
+
+/// The value converted to a float when `typename` is one, empty maps being given the types of `typename`.
+fn converted(value: Value, typename: Option<&Type>) -> Value {
+    return match (value, typename) {
+        (Value::Int(val), Some(Type { name, is_array: false, .. })) if name == "float" => Value::Float(val as f64),
+        (value, Some(Type { name, is_array: false, .. })) => typed(value, name),
+        (value, _) => value,
+    };
+}
+
+/// Order of the elements sorted by `sort`.
+fn order(a: &Value, b: &Value) -> Ordering {
+    return match (a, b) {
+        (Value::Int(a), Value::Int(b)) => a.cmp(b),
+        (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Str(a), Value::Str(b)) => a.cmp(b),
+        _ => Ordering::Equal,
+    };
+}
+
+/// Text of a value printed by a placeholder with `precision`.
+fn formatted(value: &Value, precision: Option<usize>) -> String {
+    return match (value, precision) {
+        (Value::Float(val), Some(precision)) => format!("{:.*}", precision, val),
+        (value, _) => value.to_string(),
+    };
+}
+
+fn is_place(expression: &Ast) -> bool {
+    return matches!(expression, Ast::Variable(..) | Ast::ArrayAccess { .. } | Ast::MemberAccess { .. });
+}
+
+impl<'a> Machine<'a> {
+    /// The error located at the statement being run.
+    fn locate(&self, e: Diagnostic) -> Stop {
+        let span = self.frames.last().map(|frame| frame.span).unwrap_or_default();
+        return Stop::Error(Diagnostic { span, ..e });
+    }
+
+    fn value(&mut self, expression: &Ast) -> Result<Value, Stop> {
+        return match evaluate(expression, self) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(match self.pending.take() {
+                Some(stop) => stop,
+                None => self.locate(e),
+            }),
+        };
+    }
+
+    fn condition(&mut self, expression: &Ast) -> Result<bool, Stop> {
+        return match self.value(expression) {
+            Err(stop) => Err(stop),
+            Ok(Value::Bool(val)) => Ok(val),
+            Ok(value) => Err(self.locate(error(Code::MismatchedTypes, format!("condition must be a bool, got {}", value.type_name())))),
+        };
+    }
+
+    fn write(&mut self, text: &str) -> Result<(), Stop> {
+        return match self.output.write_all(text.as_bytes()) {
+            Err(e) => Err(self.locate(error(Code::Io, format!("could not write the output: {}", e)))),
+            Ok(()) => Ok(()),
+        };
+    }
+
+    /// Value of a variable of type `typename` declared without value.
+    fn default(&mut self, typename: &Type) -> Result<Value, Stop> {
+        let element = match Context::initial(self, &typename.name) {
+            None => return Err(self.locate(error(Code::UnknownType, format!("unknown type '{}'", typename.name)))),
+            Some(val) => val,
+        };
+        if !typename.is_array {
+            return Ok(element);
+        }
+        let size = match (&typename.size_expression, typename.size) {
+            (Some(size), _) => match self.value(size) {
+                Err(stop) => return Err(stop),
+                Ok(Value::Int(val)) if val >= 0 => val as usize,
+                Ok(size) => return Err(self.locate(error(Code::Evaluation, format!("cannot allocate an array of {} elements", size)))),
+            },
+            (None, size) => size.unwrap_or(0) as usize,
+        };
+        return Ok(Value::Array(vec![element; size]));
+    }
+
+    /// Variable `name` of the function being run, or the global.
+    fn variable_mut(&mut self, name: &str) -> Option<&mut Value> {
+        let local = self.frames.last().is_some_and(|frame| frame.environment.get(name).is_some());
+        let frame = if local { self.frames.last_mut() } else { self.frames.first_mut() };
+        return frame.and_then(|frame| frame.environment.get_mut(name));
+    }
+
+    /// Gives the variable, the array element or the field the value.
+    fn assign(&mut self, target: &Ast, value: Value) -> Result<(), Stop> {
+        let mut steps = Vec::<Step>::new();
+        let mut target = target;
+        let name = loop {
+            match target {
+                Ast::Variable(var) => break &var.name,
+                Ast::ArrayAccess { variable, index } => {
+                    match self.value(index) {
+                        Err(stop) => return Err(stop),
+                        Ok(val) => steps.push(Step::Index(val)),
+                    };
+                    break variable;
+                },
+                Ast::MemberAccess { record, field } => {
+                    steps.push(Step::Field(field.clone()));
+                    target = record;
+                },
+                _ => return Err(self.locate(error(Code::InvalidAssignment, String::from("can only assign value to a variable.")))),
+            };
+        };
+        if steps.is_empty() {
+            Context::set(self, name, value);
+            return Ok(());
+        }
+
+        let span = self.frames.last().map(|frame| frame.span).unwrap_or_default();
+        let located = |e: Diagnostic| Stop::Error(Diagnostic { span, ..e });
+        let mut place = match self.variable_mut(name) {
+            None => return Err(located(error(Code::UnknownVariable, format!("unknown variable '{}'", name)))),
+            Some(val) => val,
+        };
+        for step in steps.iter().rev() {
+            place = match (step, place) {
+                (Step::Index(Value::Int(index)), Value::Array(values)) => {
+                    let count = values.len();
+                    match usize::try_from(*index).ok().and_then(|i| values.get_mut(i)) {
+                        None => return Err(located(error(Code::Evaluation, format!("index {} out of bounds for an array of {} elements", index, count)))),
+                        Some(val) => val,
+                    }
+                },
+                (Step::Index(index), Value::Array(..)) => return Err(located(error(Code::MismatchedTypes, format!("array index must be an int, got {}", index.type_name())))),
+                // the entry is created by the assignment, its value being replaced right after.
+                (Step::Index(index), Value::Map { key, entries, .. }) => match Key::from_value(index).filter(|_| index.type_name() == *key) {
+                    None => return Err(located(error(Code::MismatchedTypes, format!("map key must be a {}, got {}", key, index.type_name())))),
+                    Some(index) => entries.entry(index).or_insert(Value::Bool(false)),
+                },
+                (Step::Field(field), Value::Record { name, fields }) => match fields.iter_mut().find(|(n, _)| n == field) {
+                    None => return Err(located(error(Code::UnknownField, format!("record {} has no field '{}'", name, field)))),
+                    Some((_, val)) => val,
+                },
+                (Step::Index(..), value) => return Err(located(error(Code::MismatchedTypes, format!("cannot index a value of type {}", value.type_name())))),
+                (Step::Field(field), value) => return Err(located(error(Code::UnknownField, format!("cannot access field '{}' of type {}", field, value.type_name())))),
+            };
+        }
+        *place = value;
+        self.assigned(name);
+        return Ok(());
+    }
+
+    /// Tells the tracer of the value the variable was given.
+    fn assigned(&mut self, name: &str) {
+        let span = self.frames.last().map(|frame| frame.span).unwrap_or_default();
+        let local = self.frames.last().and_then(|frame| frame.environment.get(name));
+        if let Some(value) = local.or_else(|| self.frames.first().and_then(|frame| frame.environment.get(name))) {
+            self.tracer.assign(span, &self.frames, name, value);
+        }
+    }
+
+    /// Declares the variable in the function being run, hiding the global of the same name.
+    fn declare(&mut self, name: &str, value: Value) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.environment.set(name, value);
+        }
+        self.assigned(name);
+    }
+
+    fn print(&mut self, children: &[Ast], arguments: &[Value]) -> Result<Option<Value>, Stop> {
+        let text = match (children.first(), arguments) {
+            (Some(Ast::Str(format)), [_, values @ ..]) if !values.is_empty() => {
+                let pieces = match formats::parse(format) {
+                    Err(e) => return Err(self.locate(error(Code::InvalidFormat, e))),
+                    Ok(val) => val,
+                };
+                let mut values = values.iter();
+                let mut text = String::new();
+                for piece in pieces {
+                    match piece {
+                        Piece::Text(val) => text.push_str(&val),
+                        Piece::Value(precision) => match values.next() {
+                            Some(value) => text.push_str(&formatted(value, precision)),
+                            None => return Err(self.locate(error(Code::InvalidFormat, String::from("missing value for a placeholder of the format")))),
+                        },
+                    };
+                }
+                text
+            },
+            _ => arguments.iter().map(|value| value.to_string()).collect::<Vec<String>>().join(" "),
+        };
+        return match self.write(&format!("{}\n", text)) {
+            Err(stop) => Err(stop),
+            Ok(()) => Ok(None),
+        };
+    }
+
+    fn read_int(&mut self) -> Result<Option<Value>, Stop> {
+        if let Err(e) = self.output.flush() {
+            return Err(self.locate(error(Code::Io, format!("could not write the output: {}", e))));
+        }
+        let mut line = String::new();
+        if let Err(e) = self.input.read_line(&mut line) {
+            return Err(self.locate(error(Code::Io, format!("could not read the input: {}", e))));
+        }
+        return match line.trim().parse::<i32>() {
+            Err(..) => Err(self.locate(error(Code::Evaluation, format!("expected an int on the input, got '{}'", line.trim())))),
+            Ok(val) => Ok(Some(Value::Int(val as i64))),
+        };
+    }
+
+    /// Runs the function with the arguments, `children` being the expressions they were computed from and `call` the
+    /// position of the call.
+    fn run_function(&mut self, function: &Ast, arguments: Vec<Value>, children: &[Ast], call: Span) -> Result<Option<Value>, Stop> {
+        let (name, parameters, return_type, body, span) = match function {
+            Ast::FunctionDeclaration { name, parameters, return_type, children, span, .. } => (name, parameters, return_type, children, *span),
+            _ => return Ok(None),
+        };
+        if self.frames.len() > MAX_CALLS {
+            return Err(self.locate(error(Code::Evaluation, format!("more than {} calls nested in one another", MAX_CALLS))));
+        }
+        let mut environment = Environment::new();
+        for (parameter, argument) in parameters.iter().zip(arguments) {
+            environment.set(&parameter.name, converted(argument, parameter.typename.as_ref()));
+        }
+        self.frames.push(Frame { function: name.clone(), environment, span });
+        self.tracer.call(call, &self.frames);
+        let return_type = return_type.as_ref().map(|name| Type { name: name.clone(), is_array: false, size: None, size_expression: None });
+        let res = match self.run_block(body) {
+            Ok(()) => Ok(None),
+            Err(Stop::Return(value)) => Ok(value.map(|value| converted(value, return_type.as_ref()))),
+            Err(stop) => Err(stop),
+        };
+        if let Ok(value) = &res {
+            let span = self.frames.last().map(|frame| frame.span).unwrap_or_default();
+            self.tracer.returned(span, &self.frames, value.as_ref());
+        }
+        let frame = self.frames.pop().unwrap();
+        let value = match res {
+            Err(stop) => return Err(stop),
+            Ok(value) => value,
+        };
+
+        for (parameter, child) in parameters.iter().zip(children) {
+            let value = match frame.environment.get(&parameter.name) {
+                Some(value @ (Value::Array(..) | Value::Record { .. } | Value::Map { .. })) if is_place(child) => value.clone(),
+                _ => continue,
+            };
+            if let Err(stop) = self.assign(child, value) {
+                return Err(stop);
+            }
+        }
+        return Ok(value);
+    }
+
+    /// Result of a call to a function of the program, or to a builtin printing, reading or changing its arguments.
+    /// `None` for the other builtins, which `evaluate` computes.
+    fn invoke(&mut self, name: &str, children: &[Ast], span: Span) -> Option<Result<Option<Value>, Stop>> {
+        let named = |f: &Ast| matches!(f, Ast::FunctionDeclaration { name: n, .. } if n == name);
+        let declared = self.functions.iter().any(|f| named(f));
+        // a variable holding a function calls it.
+        let name = match Context::get(self, name) {
+            Some(Value::Function(function)) if !declared => function.clone(),
+            _ => name.to_string(),
+        };
+        let name = name.as_str();
+        let named = |f: &Ast| matches!(f, Ast::FunctionDeclaration { name: n, .. } if n == name);
+        let declared = self.functions.iter().any(|f| named(f)) || self.lambdas.iter().any(|f| named(f));
+        if !declared && !["print", "read_int", "assert", "free", "fill", "sort", "remove"].contains(&name) {
+            return None;
+        }
+        let mut arguments = Vec::<Value>::new();
+        for child in children {
+            match self.value(child) {
+                Err(stop) => return Some(Err(stop)),
+                Ok(val) => arguments.push(val),
+            };
+        }
+        let matching = |f: &Ast| match f {
+            Ast::FunctionDeclaration { name: n, parameters, .. } => {
+                n == name
+                    && parameters.len() == arguments.len()
+                    && parameters.iter().zip(&arguments).all(|(parameter, value)| parameter.typename.as_ref().is_none_or(|typename| fits(value, typename)))
+            },
+            _ => false,
+        };
+        if let Some(function) = self.functions.iter().copied().find(|f| matching(f)) {
+            return Some(self.run_function(function, arguments, children, span));
+        }
+        if let Some(function) = self.lambdas.iter().find(|f| matching(f)).cloned() {
+            return Some(self.run_function(&function, arguments, children, span));
+        }
+
+        // the arrays changed by `fill` and `sort` and the maps changed by `remove` are given back to their variable.
+        let changed = match (name, arguments.as_mut_slice()) {
+            ("print", _) => return Some(self.print(children, &arguments)),
+            ("read_int", []) => return Some(self.read_int()),
+            ("assert", [Value::Bool(true)]) => return Some(Ok(None)),
+            // the arrays of `new` are dropped with their last variable, there is nothing to release.
+            ("free", [Value::Array(..)]) => return Some(Ok(None)),
+            ("assert", [Value::Bool(false)]) => {
+                // printed by the program as the compiled one does, this is not a diagnostic.
+                let text = format!("assertion failed at line {}\n", span.line);
+                return Some(match self.write(&text) {
+                    Err(stop) => Err(stop),
+                    Ok(()) => Err(Stop::Exit(ASSERT_STATUS)),
+                });
+            },
+            ("fill", [Value::Array(values), value]) => {
+                values.iter_mut().for_each(|element| *element = value.clone());
+                arguments.remove(0)
+            },
+            ("sort", [Value::Array(values)]) => {
+                values.sort_by(order);
+                arguments.remove(0)
+            },
+            ("remove", [Value::Map { key, entries, .. }, index]) if index.type_name() == *key => {
+                if let Some(index) = Key::from_value(index) {
+                    entries.remove(&index);
+                }
+                arguments.remove(0)
+            },
+            _ => {
+                let types = arguments.iter().map(|value| value.type_name()).collect::<Vec<String>>();
+                return Some(Err(self.locate(error(Code::UnknownFunction, format!("no function matching {}({})", name, types.join(", "))))));
+            },
+        };
+        return Some(match children.first().filter(|child| is_place(child)) {
+            None => Ok(None),
+            Some(child) => match self.assign(child, changed) {
+                Err(stop) => Err(stop),
+                Ok(()) => Ok(None),
+            },
+        });
+    }
+
+    fn run_block(&mut self, statements: &[Ast]) -> Result<(), Stop> {
+        for statement in statements {
+            if let Err(stop) = self.run_statement(statement) {
+                return Err(stop);
+            }
+        }
+        return Ok(());
+    }
+
+    fn run_statement(&mut self, statement: &Ast) -> Result<(), Stop> {
+        let declaration = matches!(statement, Ast::FunctionDeclaration { .. } | Ast::FunctionHeader { .. } | Ast::RecordDeclaration { .. } | Ast::Pragma { .. });
+        // the declarations of variables are not located, their position is left unknown.
+        if let (Some(frame), false) = (self.frames.last_mut(), declaration) {
+            frame.span = statement.span().unwrap_or_default();
+        }
+        if let (Some(span), false) = (statement.span(), declaration) {
+            self.tracer.statement(span, &self.frames);
+        }
+
+        match statement {
+            _ if declaration => (),
+            Ast::Statement { children } => return self.run_block(children),
+            Ast::Assignement { variable, expression, .. } => {
+                let value = match self.value(expression) {
+                    Err(stop) => return Err(stop),
+                    Ok(val) => val,
+                };
+                return match &**variable {
+                    Ast::Variable(Variable { name, typename: Some(typename) }) => {
+                        let value = match (value, typename.size) {
+                            // the elements past those of the value keep their initial value.
+                            (Value::Array(mut values), Some(size)) if (values.len() as u64) < size => match Context::initial(self, &typename.name) {
+                                Some(element) => {
+                                    values.resize(size as usize, element);
+                                    Value::Array(values)
+                                },
+                                None => Value::Array(values),
+                            },
+                            (value, _) => value,
+                        };
+                        self.declare(name, converted(value, Some(typename)));
+                        Ok(())
+                    },
+                    target => self.assign(target, value),
+                };
+            },
+            Ast::Variable(Variable { name, typename: Some(typename) }) => {
+                let value = match self.default(typename) {
+                    Err(stop) => return Err(stop),
+                    Ok(val) => val,
+                };
+                self.declare(name, value);
+            },
+            Ast::Condition { condition, valid_branch, invalid_branch, span } => {
+                let taken = match self.condition(condition) {
+                    Err(stop) => return Err(stop),
+                    Ok(val) => val,
+                };
+                self.tracer.branch(*span, &self.frames, taken);
+                return self.run_block(if taken { valid_branch } else { invalid_branch });
+            },
+            Ast::WhileLoop { condition, children, span } => {
+                for iteration in 1.. {
+                    match self.condition(condition) {
+                        Err(stop) => return Err(stop),
+                        Ok(false) => break,
+                        Ok(true) => (),
+                    };
+                    self.tracer.iteration(*span, &self.frames, iteration);
+                    if let Err(stop) = self.run_block(children) {
+                        return Err(stop);
+                    }
+                }
+            },
+            Ast::ReturnStatement { value, .. } => {
+                let value = match value.as_ref().map(|value| self.value(value)) {
+                    Some(Err(stop)) => return Err(stop),
+                    Some(Ok(val)) => Some(val),
+                    None => None,
+                };
+                return Err(Stop::Return(value));
+            },
+            Ast::FunctionCall { name, children, span } => {
+                return match self.invoke(name, children, *span) {
+                    Some(Err(stop)) => Err(stop),
+                    Some(Ok(_)) => Ok(()),
+                    None => self.value(statement).map(|_| ()),
+                };
+            },
+            Ast::Write { values, .. } => {
+                let mut texts = Vec::<String>::new();
+                for value in values {
+                    match self.value(value) {
+                        Err(stop) => return Err(stop),
+                        Ok(val) => texts.push(val.to_string()),
+                    };
+                }
+                return self.write(&format!("{}\n", texts.join(" ")));
+            },
+            Ast::Swap { left, right, .. } => {
+                let (a, b) = match (self.value(left), self.value(right)) {
+                    (Err(stop), _) | (_, Err(stop)) => return Err(stop),
+                    (Ok(a), Ok(b)) => (a, b),
+                };
+                return match self.assign(left, b) {
+                    Err(stop) => Err(stop),
+                    Ok(()) => self.assign(right, a),
+                };
+            },
+            Ast::Try { children, variable, handler, .. } => {
+                return match self.run_block(children) {
+                    Err(Stop::Throw(message)) => {
+                        self.declare(variable, Value::Str(message));
+                        self.run_block(handler)
+                    },
+                    res => res,
+                };
+            },
+            Ast::Throw { value, .. } => {
+                return match self.value(value) {
+                    Err(stop) => Err(stop),
+                    Ok(val) => Err(Stop::Throw(val.to_string())),
+                };
+            },
+            Ast::Match { value, cases, default, .. } => {
+                let value = match self.value(value) {
+                    Err(stop) => return Err(stop),
+                    Ok(val) => val,
+                };
+                for (values, children) in cases {
+                    for case in values {
+                        match self.value(case) {
+                            Err(stop) => return Err(stop),
+                            Ok(case) if case == value => return self.run_block(children),
+                            Ok(..) => (),
+                        };
+                    }
+                }
+                if let Some(children) = default {
+                    return self.run_block(children);
+                }
+            },
+            Ast::Exit { code, .. } => {
+                return match self.value(code) {
+                    Err(stop) => Err(stop),
+                    Ok(Value::Int(val)) => Err(Stop::Exit(val)),
+                    Ok(code) => Err(self.locate(error(Code::MismatchedTypes, format!("exit status must be an int, got {}", code.type_name())))),
+                };
+            },
+            Ast::ConstDeclaration { name, value, .. } => {
+                return match self.value(value) {
+                    Err(stop) => Err(stop),
+                    Ok(val) => {
+                        Context::set(self, name, val);
+                        Ok(())
+                    },
+                };
+            },
+            expression => {
+                if let Err(stop) = self.value(expression) {
+                    return Err(stop);
+                }
+            },
+        };
+        return Ok(());
+    }
+}
+
+/// The variables of the function being run, the globals being visible from every function.
+impl<'a> Context for Machine<'a> {
+    fn get(&self, name: &str) -> Option<&Value> {
+        return match self.frames.last().and_then(|frame| frame.environment.get(name)) {
+            Some(value) => Some(value),
+            None => self.frames.first().and_then(|frame| frame.environment.get(name)),
+        };
+    }
+
+    /// Assigns the variable of the function, or the global of the name when the function has none.
+    fn set(&mut self, name: &str, value: Value) {
+        match self.variable_mut(name) {
+            Some(previous) => {
+                *previous = typed(value, &previous.type_name());
+                self.assigned(name);
+            },
+            None => self.declare(name, value),
+        };
+    }
+
+    fn call(&mut self, name: &str, children: &[Ast], span: Span) -> Option<Result<Value, Diagnostic>> {
+        return match self.invoke(name, children, span) {
+            None => None,
+            Some(Ok(Some(value))) => Some(Ok(value)),
+            Some(Ok(None)) => Some(Err(error(Code::MismatchedTypes, format!("function {} returns no value", name)))),
+            Some(Err(stop)) => {
+                self.pending = Some(stop);
+                Some(Err(error(Code::Evaluation, format!("the call to {} did not return", name))))
+            },
+        };
+    }
+
+    fn initial(&self, typename: &str) -> Option<Value> {
+        if let Some(value) = initial(typename) {
+            return Some(value);
+        }
+        let (name, fields) = match self.records.iter().find(|(name, _)| *name == typename) {
+            None => return None,
+            Some(val) => val,
+        };
+        let mut values = Vec::<(String, Value)>::new();
+        for field in fields.iter() {
+            let value = match field.typename.as_ref().and_then(|typename| self.initial(&typename.name).map(|value| (typename, value))) {
+                None => return None,
+                Some((typename, value)) if typename.is_array => Value::Array(vec![value; typename.size.unwrap_or(0) as usize]),
+                Some((_, value)) => value,
+            };
+            values.push((field.name.clone(), value));
+        }
+        return Some(Value::Record { name: name.to_string(), fields: values });
+    }
+
+    /// The lambda as a function named after its position, returning the value of its body.
+    fn lambda(&mut self, lambda: &Ast) -> Option<Value> {
+        let (parameters, return_type, body, span) = match lambda {
+            Ast::Lambda { parameters, return_type, body, span } => (parameters, return_type, body, *span),
+            _ => return None,
+        };
+        let name = format!("<lambda {}:{}>", span.line, span.column);
+        if !self.lambdas.iter().any(|f| matches!(&**f, Ast::FunctionDeclaration { name: n, .. } if *n == name)) {
+            let children = vec![Ast::ReturnStatement { value: Some(body.clone()), span }];
+            let function = Ast::FunctionDeclaration { name: name.clone(), children, parameters: parameters.clone(), return_type: return_type.clone(), symbol: None, span };
+            self.lambdas.push(Rc::new(function));
+        }
+        return Some(Value::Function(name));
+    }
+
+    fn checks(&self) -> RuntimeChecks {
+        return self.checks;
+    }
+}
+
+/// Runs the program, its top level statements followed by its `main` when it defines one, reading `input` and writing
+/// to `output`. Returns the exit status of the program: the value returned by `main` or given to `exit`, 1 when an
+/// exception is not caught, 0 otherwise. Fails when a statement does, as an int divided by zero, or an int overflowing
+/// when the runtime checks of `options` or of the pragmas of the program have the overflow check.
+pub fn run_program(program: &Ast, options: &CompilerOptions, input: &mut dyn BufRead, output: &mut dyn Write, tracer: &mut dyn Tracer) -> Result<i64, Diagnostic> {
+    return match run(program, options, &mut State::default(), true, None, input, output, tracer) {
+        Err(e) => Err(e),
+        Ok((status, _)) => Ok(status),
+    };
+}
+
+/// Runs the top level statements of the program as `run_program` does, without its `main`, the globals and the
+/// lambdas of `state` being those of the statements run before, and `state` being left with those of the program.
+pub fn run_statements(program: &Ast, options: &CompilerOptions, state: &mut State, input: &mut dyn BufRead, output: &mut dyn Write, tracer: &mut dyn Tracer) -> Result<i64, Diagnostic> {
+    return match run(program, options, state, false, None, input, output, tracer) {
+        Err(e) => Err(e),
+        Ok((status, _)) => Ok(status),
+    };
+}
+
+/// Runs the top level statements of the program as `run_statements` does, followed by `expression`, which calls the
+/// functions of the program. Returns the exit status with the value of the expression, `None` when it is a call
+/// returning no value or when the program stops before it is evaluated.
+pub fn run_expression(
+    program: &Ast,
+    expression: &Ast,
+    options: &CompilerOptions,
+    state: &mut State,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> Result<(i64, Option<Value>), Diagnostic> {
+    return run(program, options, state, false, Some(expression), input, output, &mut NoTrace);
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run(
+    program: &Ast,
+    options: &CompilerOptions,
+    state: &mut State,
+    with_main: bool,
+    expression: Option<&Ast>,
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+    tracer: &mut dyn Tracer,
+) -> Result<(i64, Option<Value>), Diagnostic> {
+    let children = match program {
+        Ast::Global(children) => children.as_slice(),
+        statement => std::slice::from_ref(statement),
+    };
+    let functions = children.iter().filter(|child| matches!(child, Ast::FunctionDeclaration { .. })).collect::<Vec<&Ast>>();
+    let records = children
+        .iter()
+        .filter_map(|child| match child {
+            Ast::RecordDeclaration { name, fields, .. } => Some((name, fields)),
+            _ => None,
+        })
+        .collect();
+    let main = functions.iter().filter(|_| with_main).find_map(|f| match f {
+        Ast::FunctionDeclaration { name, parameters, span, .. } if name == "main" && parameters.is_empty() => Some(*span),
+        _ => None,
+    });
+    let top_level = Frame { function: String::from(TOP_LEVEL), environment: std::mem::take(&mut state.globals), span: Span::default() };
+    let lambdas = std::mem::take(&mut state.lambdas);
+    let checks = pragmas::runtime_checks(program, options);
+    let mut machine = Machine { functions, lambdas, records, frames: vec![top_level], input, output, tracer, pending: None, checks };
+
+    let mut value = None;
+    let res = match (machine.run_block(children), main, expression) {
+        (Ok(()), Some(span), _) => match machine.invoke("main", &[], span) {
+            Some(Err(stop)) => Err(stop),
+            Some(Ok(Some(Value::Int(status)))) => Err(Stop::Exit(status)),
+            _ => Ok(()),
+        },
+        (Ok(()), None, Some(expression)) => {
+            // a call is run as a statement would run it, the functions returning no value being called too.
+            let res = match expression {
+                Ast::FunctionCall { name, children, span } => machine.invoke(name, children, *span),
+                _ => None,
+            };
+            match res.unwrap_or_else(|| machine.value(expression).map(Some)) {
+                Err(stop) => Err(stop),
+                Ok(val) => {
+                    value = val;
+                    Ok(())
+                },
+            }
+        },
+        (res, _, _) => res,
+    };
+    let status = match res {
+        Ok(()) => Ok(0),
+        Err(Stop::Return(Some(Value::Int(status)))) | Err(Stop::Exit(status)) => Ok(status),
+        Err(Stop::Return(..)) => Ok(0),
+        Err(Stop::Throw(message)) => {
+            let text = format!("uncaught exception: {}\n", message);
+            match machine.write(&text) {
+                Err(Stop::Error(e)) => Err(e),
+                _ => Ok(1),
+            }
+        },
+        Err(Stop::Error(e)) => Err(e),
+    };
+    let status = match (status, machine.output.flush()) {
+        (Ok(_), Err(e)) => Err(error(Code::Io, format!("could not write the output: {}", e))),
+        (status, _) => status,
+    };
+    // the calls left by a failed statement are dropped, the top level being the first frame.
+    state.globals = std::mem::take(&mut machine.frames[0].environment);
+    state.lambdas = machine.lambdas;
+    return match status {
+        Err(e) => Err(e),
+        Ok(status) => Ok((status, value)),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{run_expression, run_program, run_statements, Frame, State};
+    use crate::compiler::{CompilerOptions, RuntimeChecks};
+    use crate::interpreter::trace::{NoTrace, Tracer};
+    use crate::interpreter::Value;
+    use crate::lexer::Span;
+
+    /// Lines of the statements traced, with the variables of their function.
+    struct Steps(Vec<(usize, Vec<(String, Value)>)>);
+
+    impl Tracer for Steps {
+        fn statement(&mut self, span: Span, frames: &[Frame]) {
+            let variables = frames.last().unwrap().environment.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+            self.0.push((span.line, variables));
+        }
+    }
+
+    /// Events other than the statements, each one with its line.
+    struct Events(Vec<String>);
+
+    impl Tracer for Events {
+        fn statement(&mut self, _span: Span, _frames: &[Frame]) {}
+
+        fn call(&mut self, span: Span, frames: &[Frame]) {
+            self.0.push(format!("{}: call {}", span.line, frames.last().unwrap().function));
+        }
+
+        fn returned(&mut self, span: Span, _frames: &[Frame], value: Option<&Value>) {
+            self.0.push(format!("{}: return {:?}", span.line, value));
+        }
+
+        fn assign(&mut self, span: Span, _frames: &[Frame], name: &str, value: &Value) {
+            self.0.push(format!("{}: {} = {}", span.line, name, value));
+        }
+
+        fn branch(&mut self, span: Span, _frames: &[Frame], taken: bool) {
+            self.0.push(format!("{}: branch {}", span.line, taken));
+        }
+
+        fn iteration(&mut self, span: Span, _frames: &[Frame], iteration: usize) {
+            self.0.push(format!("{}: iteration {}", span.line, iteration));
+        }
+    }
+
+    /// Output and exit status of the program, with its trace.
+    fn run(source: &str) -> (String, i64, Steps) {
+        let ast = match crate::parse_str(source) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let mut output = Vec::<u8>::new();
+        let mut steps = Steps(Vec::new());
+        let status = match run_program(&ast, &CompilerOptions::default(), &mut "".as_bytes(), &mut output, &mut steps) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        return (String::from_utf8(output).unwrap(), status, steps);
+    }
+
+    #[test]
+    fn traces_each_statement_with_its_variables() {
+        let (output, status, steps) = run("i <- 0\nwhile i < 2\n    i <- i + 1\nend\nprint(i)\n");
+        assert_eq!((output.as_str(), status), ("2\n", 0));
+        let lines = steps.0.iter().map(|(line, _)| *line).collect::<Vec<usize>>();
+        assert_eq!(lines, vec![1, 2, 3, 3, 5]);
+        assert_eq!(steps.0[3].1, vec![(String::from("i"), Value::Int(1))]);
+    }
+
+    #[test]
+    fn runs_main_with_arrays_passed_by_reference() {
+        let source = "function set(a: int[3])\n    a[1] <- 5\nend\n\nfunction main(): int\n    a: int[3]\n    set(a)\n    return a[1]\nend\n";
+        let (_, status, steps) = run(source);
+        assert_eq!(status, 5);
+        assert_eq!(steps.0.last().unwrap(), &(8, vec![(String::from("a"), Value::Array(vec![Value::Int(0), Value::Int(5), Value::Int(0)]))]));
+    }
+
+    #[test]
+    fn traces_calls_assignments_branches_and_iterations() {
+        let source = "function bump(a: int[2], i: int): int\n    a[i] <- a[i] + 1\n    return i\nend\n\na: int[2]\ni <- 0\nwhile i < 2\n    if bump(a, i) == 1\n        i <- 2\n    else\n        i <- 1\n    end\nend\n";
+        let ast = match crate::parse_str(source) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let mut events = Events(Vec::new());
+        if let Err(e) = run_program(&ast, &CompilerOptions::default(), &mut "".as_bytes(), &mut Vec::<u8>::new(), &mut events) {
+            panic!("{}", e.message);
+        }
+        let expected = [
+            "0: a = [0, 0]", "7: i = 0", "8: iteration 1", "9: call bump", "2: a = [1, 0]", "3: return Some(Int(0))", "9: a = [1, 0]",
+            "9: branch false", "12: i = 1", "8: iteration 2", "9: call bump", "2: a = [1, 1]", "3: return Some(Int(1))", "9: a = [1, 1]",
+            "9: branch true", "10: i = 2",
+        ];
+        assert_eq!(events.0, expected);
+    }
+
+    #[test]
+    fn wraps_ints_around_unless_the_overflow_is_checked() {
+        let source = "x <- 2147483647\nprint(x + 1, -(x + 1) - 1, x * 2)\n";
+        let pragma = format!("@pragma checks overflow\n{}", source);
+        let checked = CompilerOptions { runtime_checks: RuntimeChecks { overflow: true, ..RuntimeChecks::default() }, ..CompilerOptions::default() };
+        let overflow = Err(String::from("integer overflow"));
+        for (source, options, expected) in [
+            (source, &CompilerOptions::default(), Ok(String::from("-2147483648 2147483647 -2\n"))),
+            (pragma.as_str(), &CompilerOptions::default(), overflow.clone()),
+            (source, &checked, overflow),
+        ] {
+            let ast = match crate::parse_str(source) {
+                Err(e) => panic!("{}", e.message),
+                Ok(val) => val,
+            };
+            let mut output = Vec::<u8>::new();
+            let res = run_program(&ast, options, &mut "".as_bytes(), &mut output, &mut NoTrace);
+            assert_eq!(res.map(|_| String::from_utf8(output).unwrap()).map_err(|e| e.message), expected);
+        }
+    }
+
+    #[test]
+    fn runs_maps() {
+        let source = "function f(m: map[int, int])\n    m[1] <- 5\n    remove(m, 0)\nend\n\nscores: map[str, int]\nscores[\"ann\"] <- 3\n\
+            scores[\"bob\"] <- scores[\"ann\"] + 1\nwrite scores[\"bob\"], scores[\"zoe\"]\nm <- {0: 1, 2: 4}\nf(m)\nx <- contains(m, 0)\n\
+            y <- contains(m, 1)\nwrite x, y, m[1], m[2]\ne: map[int, float] <- {}\nwrite e[3]\n";
+        let (output, status, _) = run(source);
+        assert_eq!((output.as_str(), status), ("4 0\nfalse true 5 4\n0.000000\n", 0));
+    }
+
+    #[test]
+    fn runs_statements_with_the_state_of_the_previous_ones() {
+        let mut state = State::default();
+        let mut output = Vec::<u8>::new();
+        for source in ["x <- 2\ndouble <- fn(v: int): int => v * 2", "y <- double(x)\nx <- y + 1", "y <- double(x)\nprint(y)"] {
+            let ast = match crate::parse_str(source) {
+                Err(e) => panic!("{}", e.message),
+                Ok(val) => val,
+            };
+            if let Err(e) = run_statements(&ast, &CompilerOptions::default(), &mut state, &mut "".as_bytes(), &mut output, &mut NoTrace) {
+                panic!("{}", e.message);
+            }
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), "10\n");
+        assert_eq!(state.globals.get("x"), Some(&Value::Int(5)));
+    }
+
+    #[test]
+    fn runs_expressions_calling_the_functions_of_the_program() {
+        let mut state = State::default();
+        state.globals.set("x", Value::Int(3));
+        let mut output = Vec::<u8>::new();
+        for (source, expected) in [("f(x) + 1", Some(Value::Int(7))), ("f(2)", Some(Value::Int(4))), ("show(x)", None)] {
+            let (expression, program) = match crate::parse_str(&format!("function f(v: int): int\n    return v * 2\nend\n\nfunction show(v: int)\n    print(v)\nend\n\n{}\n", source)) {
+                Err(e) => panic!("{}", e.message),
+                Ok(crate::parser::Ast::Global(mut children)) => (children.pop().unwrap(), crate::parser::Ast::Global(children)),
+                Ok(_) => unreachable!(),
+            };
+            match run_expression(&program, &expression, &CompilerOptions::default(), &mut state, &mut "".as_bytes(), &mut output) {
+                Err(e) => panic!("{}", e.message),
+                Ok(res) => assert_eq!(res, (0, expected)),
+            };
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), "3\n");
+    }
+
+    /// Program using every statement, expression and builtin the analysis accepts.
+    const EVERY_CONSTRUCT: &str = r#"@pragma checks div,overflow
+const N <- 2 * 2
+
+record Point
+    x: int
+    y: float
+end
+
+function area(x: int, y: float): float
+    return x * y
+end
+
+function grow(a: int[], n: int)
+    i <- 0
+    while i < n
+        a[i] <- a[i] + i
+        i <- i + 1
+    end
+end
+
+function check(x: int): int
+    if x < 0
+        throw "negative"
+    end
+    return x
+end
+
+p: Point
+p.x <- 3
+p.y <- 1.5
+surface <- area(p.x, p.y)
+write surface, p.x
+a <- new int[N]
+fill(a, 1)
+grow(a, N)
+sort(a)
+write a[0], a[3], len(a)
+free(a)
+b: int[3]
+b[0] <- 4
+b[2] <- 7
+swap b[0], b[2]
+x <- 1
+y <- 2
+swap x, y
+write b[0], b[2], x, y
+twice <- fn(v: int): int => v * 2
+try
+    v <- check(0 - 1)
+    print("unreachable")
+catch e
+    print(e)
+end
+match N
+    case 1, 2
+        print("small")
+    case 4
+        print("four")
+    else
+        print("other")
+end
+s <- "ab"
+c <- compare(s, "ab")
+sub <- substring("hello", 1, 3)
+write len(s), c, to_string(12), sub
+f <- sqrt(16.0)
+low <- min(1, 2)
+high <- max(1, 2)
+write f, abs(0 - 3), low, high, 7 / 2, 7 % 2, 7.0 / 2.0
+flag <- N > 3 and x == 1 or false
+write flag, N != 4
+m <- {"one": 1, "two": 2}
+m["three"] <- 3
+remove(m, "one")
+found <- contains(m, "one")
+write m["two"] + m["three"], found, m["zero"]
+read k
+write k + read_int()
+assert(k == 5)
+exit 3
+"#;
+
+    #[test]
+    fn runs_every_construct_the_analysis_accepts() {
+        let ast = match crate::parse_str(EVERY_CONSTRUCT) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        if let Err(diagnostics) = crate::compiler::analyze(&ast, &crate::compiler::CompilerOptions::default()) {
+            panic!("{}", diagnostics[0].message);
+        }
+        let mut output = Vec::<u8>::new();
+        let status = match run_program(&ast, &CompilerOptions::default(), &mut "5\n6\n".as_bytes(), &mut output, &mut Steps(Vec::new())) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        let expected = "4.500000 3\n1 4 4\n7 4 2 1\nnegative\nfour\n2 0 12 ell\n4.000000 3 1 2 3 1 3.500000\nfalse false\n5 false 0\n11\n";
+        assert_eq!((String::from_utf8(output).unwrap().as_str(), status), (expected, 3));
+    }
+}
@@ -0,0 +1,150 @@
+//! Tracers of the programs run by `program::run_program`, told of each statement before it runs along with the
+//! variables of the functions being run, for students to watch their algorithm execute line by line. They are also
+//! told of the calls, the returns, the assignments, the branches and the loop iterations, which `JsonTrace` writes
+//! for the visualizers animating the variables over time.
+
+use std::io::Write;
+
+use super::super::json;
+use super::super::lexer::Span;
+use super::program::Frame;
+use super::Value;
+
+/// Receives the steps of a program being run.
+pub trait Tracer {
+    /// Called before the statement at `span` runs, `frames` being the top level followed by the calls being run,
+    /// the last one running the statement.
+    fn statement(&mut self, span: Span, frames: &[Frame]);
+
+    /// Called when the call at `span` starts, the last frame being the function called with its parameters.
+    fn call(&mut self, _span: Span, _frames: &[Frame]) {}
+
+    /// Called when the function of the last frame returns, at `span`, with its value when it has one.
+    fn returned(&mut self, _span: Span, _frames: &[Frame], _value: Option<&Value>) {}
+
+    /// Called once the variable `name`, or one of its elements or fields, is given a value by the statement at
+    /// `span`, `value` being the whole variable.
+    fn assign(&mut self, _span: Span, _frames: &[Frame], _name: &str, _value: &Value) {}
+
+    /// Called when the condition of the `if` at `span` is evaluated, `taken` telling whether it holds.
+    fn branch(&mut self, _span: Span, _frames: &[Frame], _taken: bool) {}
+
+    /// Called before each run of the body of the loop at `span`, the first one being `iteration` 1.
+    fn iteration(&mut self, _span: Span, _frames: &[Frame], _iteration: usize) {}
+}
+
+/// Tracer of the runs which are not traced.
+pub struct NoTrace;
+
+impl Tracer for NoTrace {
+    fn statement(&mut self, _span: Span, _frames: &[Frame]) {}
+}
+
+/// Value of a variable as the text trace prints it, strings being quoted.
+pub fn binding(value: &Value) -> String {
+    return match value {
+        Value::Str(val) => format!("{:?}", val),
+        value => value.to_string(),
+    };
+}
+
+/// Writes each statement as `path:line: source`, followed by the variables of its function on the next line,
+/// indented by the depth of its call.
+pub struct TextTrace<'a> {
+    out: &'a mut dyn Write,
+    paths: &'a [String],
+    sources: &'a [String],
+}
+
+impl<'a> TextTrace<'a> {
+    /// Tracer writing to `out` the statements of the files at `paths`, whose texts are `sources`.
+    pub fn new(out: &'a mut dyn Write, paths: &'a [String], sources: &'a [String]) -> Self {
+        return TextTrace { out, paths, sources };
+    }
+}
+
+impl<'a> Tracer for TextTrace<'a> {
+    fn statement(&mut self, span: Span, frames: &[Frame]) {
+        let indent = "  ".repeat(frames.len().saturating_sub(1));
+        let path = self.paths.get(span.file).map(|path| path.as_str()).unwrap_or_default();
+        let source = self.sources.get(span.file).and_then(|source| source.lines().nth(span.line.saturating_sub(1))).unwrap_or_default();
+        let variables = match frames.last() {
+            None => Vec::new(),
+            Some(frame) => frame.environment.iter().map(|(name, value)| format!("{} = {}", name, binding(value))).collect::<Vec<String>>(),
+        };
+        // the trace goes on when it cannot be written, the run of the program being what matters.
+        let _ = writeln!(self.out, "{}{}:{}: {}", indent, path, span.line, source.trim());
+        if !variables.is_empty() {
+            let _ = writeln!(self.out, "{}    {}", indent, variables.join(", "));
+        }
+    }
+}
+
+/// Writes each event as a JSON object on its own line, with its position, the function running it and the depth of the
+/// call, the number of calls being run:
+/// `{"event": "statement", "file": "sum.algo", "line": 4, "column": 5, "function": "sum", "depth": 1, "variables": {"i": 2}}`.
+/// The statements have the variables of their function, the calls the parameters of the function called, the returns
+/// their value, the assignments the variable with its value, the branches whether they are taken and the loop
+/// iterations their number.
+pub struct JsonTrace<'a> {
+    out: &'a mut dyn Write,
+    paths: &'a [String],
+}
+
+impl<'a> JsonTrace<'a> {
+    /// Tracer writing to `out` the events of the files at `paths`.
+    pub fn new(out: &'a mut dyn Write, paths: &'a [String]) -> Self {
+        return JsonTrace { out, paths };
+    }
+
+    /// Writes the event at `span`, followed by its own fields. The position is `null` when it is not known, as for the
+    /// declarations of variables.
+    fn event(&mut self, event: &str, span: Span, frames: &[Frame], fields: &str) {
+        let (file, line, column) = match (self.paths.get(span.file), span.line) {
+            (Some(path), 1..) => (json::string(path), span.line.to_string(), span.column.to_string()),
+            _ => (String::from("null"), String::from("null"), String::from("null")),
+        };
+        let function = frames.last().map(|frame| json::string(&frame.function)).unwrap_or(String::from("null"));
+        let _ = writeln!(
+            self.out,
+            "{{\"event\": {}, \"file\": {}, \"line\": {}, \"column\": {}, \"function\": {}, \"depth\": {}, {}}}",
+            json::string(event), file, line, column, function, frames.len().saturating_sub(1), fields,
+        );
+    }
+}
+
+/// Variables of the frame as a JSON object.
+fn variables_json(frame: Option<&Frame>) -> String {
+    let variables = match frame {
+        None => Vec::new(),
+        Some(frame) => frame.environment.iter().map(|(name, value)| format!("{}: {}", json::string(name), value.to_json())).collect::<Vec<String>>(),
+    };
+    return format!("{{{}}}", variables.join(", "));
+}
+
+impl<'a> Tracer for JsonTrace<'a> {
+    fn statement(&mut self, span: Span, frames: &[Frame]) {
+        self.event("statement", span, frames, &format!("\"variables\": {}", variables_json(frames.last())));
+    }
+
+    fn call(&mut self, span: Span, frames: &[Frame]) {
+        self.event("call", span, frames, &format!("\"arguments\": {}", variables_json(frames.last())));
+    }
+
+    fn returned(&mut self, span: Span, frames: &[Frame], value: Option<&Value>) {
+        let value = value.map(|value| value.to_json()).unwrap_or(String::from("null"));
+        self.event("return", span, frames, &format!("\"value\": {}", value));
+    }
+
+    fn assign(&mut self, span: Span, frames: &[Frame], name: &str, value: &Value) {
+        self.event("assign", span, frames, &format!("\"variable\": {}, \"value\": {}", json::string(name), value.to_json()));
+    }
+
+    fn branch(&mut self, span: Span, frames: &[Frame], taken: bool) {
+        self.event("branch", span, frames, &format!("\"taken\": {}", taken));
+    }
+
+    fn iteration(&mut self, span: Span, frames: &[Frame], iteration: usize) {
+        self.event("iteration", span, frames, &format!("\"iteration\": {}", iteration));
+    }
+}
@@ -0,0 +1,36 @@
+//! Writing of the JSON documents printed by the compiler and its bindings.
+
+/// JSON string literal of `value`: quotes, backslashes and control characters are escaped, control characters
+/// without a short escape as `\u001b`, the other characters being kept as UTF-8.
+pub(crate) fn string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    res.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            '\u{8}' => res.push_str("\\b"),
+            '\u{c}' => res.push_str("\\f"),
+            c if (c as u32) < 0x20 => res.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => res.push(c),
+        };
+    }
+    res.push('"');
+    return res;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::string;
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!(string("a \"b\" \\ c"), r#""a \"b\" \\ c""#);
+        assert_eq!(string("line\nnext\ttab"), r#""line\nnext\ttab""#);
+        assert_eq!(string("\u{1b}[0m\u{0}"), r#""\u001b[0m\u0000""#);
+        assert_eq!(string("é ✓ \u{7f}"), "\"é ✓ \u{7f}\"");
+    }
+}
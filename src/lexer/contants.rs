@@ -1,11 +1,27 @@
 
 pub static OPERATOR_STRING: &str = "+-%/-*<>=!";
-pub static SEPARATORS: &str = "()[]:,";
+pub static SEPARATORS: &str = "()[]{}:,";
 pub static START_NAME_CHARACTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_";
 pub static NUMERIC_CHARACTERS: &str = ".0123456789";
 
 pub static TYPES: [&str; 4] = ["int", "float", "string", "char"];
-pub static BINARY_OPERATORS: [&str; 13] = [">", "<", ">=", "<=", "+", "-", "<-", "/", "%", "*", "==", "!=", "!"];
+pub static BINARY_OPERATORS: [&str; 14] = [">", "<", ">=", "<=", "+", "-", "<-", "/", "%", "*", "==", "!=", "!", "=>"];
+/// binary operators spelled as words, `a and b`.
+pub static WORD_OPERATORS: [&str; 2] = ["and", "or"];
+/// length of the longest operator.
+pub static OPERATOR_MAX_LENGTH: usize = 2;
 pub static UNARY_OPERATORS: [&str; 2] = ["-", "+"];
-pub static KEYWORDS: [&str; 8] = ["end", "return", "function", "while", "for", "if", "else", "declare"];
-
+pub static KEYWORDS: [&str; 24] = ["end", "return", "function", "while", "for", "if", "else", "declare", "fn", "record", "as", "no_mangle", "const", "new", "read", "write", "swap", "try", "catch", "throw", "exit", "halt", "match", "case"];
+/// start of the lines holding a pragma, `@pragma name value`.
+pub static PRAGMA: &str = "@pragma";
+/// dialects of the keywords a file can be written in, with `@pragma dialect`.
+pub static DIALECTS: [&str; 2] = ["en", "fr"];
+/// keywords, word operators and booleans of the `fr` dialect, with the English words they stand for.
+/// `fn` and `no_mangle` are the same in both dialects.
+pub static FRENCH_KEYWORDS: [(&str, &str); 26] = [
+    ("fin", "end"), ("retourner", "return"), ("fonction", "function"), ("tantque", "while"), ("pour", "for"),
+    ("si", "if"), ("sinon", "else"), ("declarer", "declare"), ("enregistrement", "record"), ("comme", "as"),
+    ("constante", "const"), ("nouveau", "new"), ("lire", "read"), ("ecrire", "write"), ("echanger", "swap"),
+    ("essayer", "try"), ("attraper", "catch"), ("lancer", "throw"), ("quitter", "exit"), ("arreter", "halt"),
+    ("selon", "match"), ("cas", "case"), ("et", "and"), ("ou", "or"), ("vrai", "true"), ("faux", "false"),
+];
@@ -1,7 +1,10 @@
 
 
+use super::diagnostic::{Code, Diagnostic};
+use super::timings::{self, Phase};
+
 mod types;
-pub use types::TokenType;
+pub use types::{TokenType, Span};
 use types::TokenizerContext;
 
 mod utils;
@@ -9,49 +12,33 @@ use utils::*;
 
 mod contants;
 use contants::*;
+pub use contants::DIALECTS;
 
+/// Splits `token_value` into operators, each one being the longest operator starting where the previous one ends.
 fn lex_operators(token_value: String, last_token: Option<&TokenType>) -> Result<Vec<TokenType>, String> {
-    let mut op_string = token_value.clone();
     let mut token_index = 0;
-    let mut op_string_index = op_string.len();
+    let mut op_string_index = token_value.len().min(OPERATOR_MAX_LENGTH);
     let mut result = match last_token {
         Some(token) => vec![token.clone()],
         None => Vec::<TokenType>::new(),
     };
 
     while op_string_index > 0 {
-        op_string = op_string[..op_string_index].to_string();
-        if let Some(last_token) = result.last() {
-            match last_token {
-                TokenType::BinaryOperator(_)
-                    | TokenType::UnaryOperator(_)
-                    | TokenType::Keyword(_)
-                    | TokenType::Comma
-                    if UNARY_OPERATORS.iter().any(|&s| s == op_string) => {
-                        result.push(TokenType::UnaryOperator(op_string));
-                        token_index += op_string_index;
-                        op_string = token_value[token_index..].to_string();
-                        op_string_index = op_string.len();
-                    },
-                    _ if BINARY_OPERATORS.iter().any(|&s| s == op_string) => {
-                        result.push(TokenType::BinaryOperator(op_string));
-                        token_index += op_string_index;
-                        op_string = token_value[token_index..].to_string();
-                        op_string_index = op_string.len();
-                    },
-                    _ if op_string_index > 0 => {
-                        op_string_index -= 1;
-                    },
-                    _ => return Err(format!("invalid operator '{}'", token_value)),
-            };
-        } else if UNARY_OPERATORS.iter().any(|&s| s == op_string) {
-            result.push(TokenType::UnaryOperator(op_string[..op_string_index].to_string()));
-            token_index += op_string_index;
-            op_string = token_value[token_index..].to_string();
-            op_string_index = op_string.len();
-        } else {
-            op_string_index -= 1;
-        }
+        let op_string = &token_value[token_index..token_index + op_string_index];
+        let is_unary = UNARY_OPERATORS.contains(&op_string);
+        let operator = match result.last() {
+            Some(TokenType::BinaryOperator(_) | TokenType::UnaryOperator(_) | TokenType::Keyword(_) | TokenType::Comma) | None if is_unary => {
+                TokenType::UnaryOperator(op_string.to_string())
+            },
+            Some(_) if BINARY_OPERATORS.contains(&op_string) => TokenType::BinaryOperator(op_string.to_string()),
+            _ => {
+                op_string_index -= 1;
+                continue;
+            },
+        };
+        result.push(operator);
+        token_index += op_string_index;
+        op_string_index = (token_value.len() - token_index).min(OPERATOR_MAX_LENGTH);
     }
 
     if token_value.len() == token_index {
@@ -67,18 +54,48 @@ fn lex_operators(token_value: String, last_token: Option<&TokenType>) -> Result<
     }
 }
 
+/// Lexes `p.x.y` as the name `p` followed by the members `x` and `y`.
+fn lex_member_token(token_value: String, result: &mut Vec<TokenType>) -> Result<(), String> {
+    let mut parts = token_value.split('.');
+    if let Some(name) = parts.next() {
+        lex_name_token(name.to_string(), result);
+    }
+    for field in parts {
+        if field.is_empty() || !START_NAME_CHARACTERS.contains(field.chars().next().unwrap()) {
+            return Err(format!("invalid member access '{}'", token_value));
+        }
+        result.push(TokenType::Member(field.to_string()));
+    }
+    return Ok(());
+}
+
+/// Whether the tokens end inside the braces of a map value, where a colon is followed by a value rather than a type.
+fn in_map_value(tokens: &[TokenType]) -> bool {
+    for token in tokens.iter().rev() {
+        match token {
+            TokenType::OpeningBrace => return true,
+            TokenType::ClosingBrace | TokenType::EndLine => return false,
+            _ => (),
+        };
+    }
+    return false;
+}
+
 fn lex_name_token(token_value: String, result: &mut Vec<TokenType>) {
     if TYPES.iter().any(|&s| s == token_value) {
         result.push(TokenType::TypeDef(token_value));
     } else if KEYWORDS.iter().any(|&s| s == token_value) {
         result.push(TokenType::Keyword(token_value));
+    } else if WORD_OPERATORS.iter().any(|&s| s == token_value) {
+        result.push(TokenType::BinaryOperator(token_value));
     } else if token_value == "true" {
         result.push(TokenType::Bool(true));
     } else if token_value == "false" {
         result.push(TokenType::Bool(false));
     } else if let Some(last_token) = result.last() {
         result.push(match last_token {
-            TokenType::Colon => TokenType::TypeDef(token_value),
+            TokenType::Colon if !in_map_value(result) => TokenType::TypeDef(token_value),
+            TokenType::Keyword(val) if val == "new" => TokenType::TypeDef(token_value),
             _ => TokenType::Variable(token_value)
         });
     } else {
@@ -87,9 +104,11 @@ fn lex_name_token(token_value: String, result: &mut Vec<TokenType>) {
 }
 
 fn lex_value_token(token_value: &String, result: &mut Vec<TokenType>) -> Result<(), String> {
-    if let Some(val) = to_int(&token_value) {
+    if let Some(val) = to_int(token_value) {
         result.push(TokenType::Int(val));
-    } else if let Some(val) = to_float(&token_value) {
+    } else if token_value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("integer '{}' is too large", token_value));
+    } else if let Some(val) = to_float(token_value) {
         result.push(TokenType::Float(val));
     } else {
         // TODO: implement proper errors
@@ -99,57 +118,42 @@ fn lex_value_token(token_value: &String, result: &mut Vec<TokenType>) -> Result<
     return Ok(());
 }
 
-fn lex_closing_brackets(old_tokens: &Vec<TokenType>, result: &mut Vec<TokenType>) {
-
+fn lex_closing_brackets(result: &mut Vec<TokenType>) {
     let tokens_len = result.len();
     if tokens_len >= 2 {
-        match old_tokens.get(tokens_len - 1).unwrap() {
-            TokenType::OpeningBracket => {
-                match old_tokens.get(tokens_len - 2).unwrap() {
-                    TokenType::TypeDef(val) => {
-                        result.pop();
-                        result.pop();
-                        result.push(TokenType::ArrayTypeDef(val.clone()));
-                    },
-                    _ => {
-                        result.push(TokenType::ClosingBracket);
-                    }
-                }
-            },
-            _ => {
-                result.push(TokenType::ClosingBracket);
-            }
+        if let (TokenType::TypeDef(val), TokenType::OpeningBracket) = (&result[tokens_len - 2], &result[tokens_len - 1]) {
+            let array_type = TokenType::ArrayTypeDef(val.clone());
+            result.truncate(tokens_len - 2);
+            result.push(array_type);
+            return;
         }
     }
+    result.push(TokenType::ClosingBracket);
 }
 
-fn lex_opening_parenthesis(old_tokens: &Vec<TokenType>, result: &mut Vec<TokenType>) {
-
-    if let Some(last_token) = old_tokens.last() {
-        if let TokenType::Variable(val) = last_token {
-            if let Some(before_last_token) = old_tokens.get(old_tokens.len() - 2) {
-                match before_last_token {
-                    TokenType::Keyword(val) if val == "function" => (),
-                    _ => {
-                        result.pop();
-                        result.push(TokenType::FunctionCall(val.clone()));
-                    }
-                }
-            } else {
+fn lex_opening_parenthesis(result: &mut Vec<TokenType>) {
+    let tokens_len = result.len();
+    if let Some(TokenType::Variable(val)) = result.last() {
+        match tokens_len.checked_sub(2).and_then(|i| result.get(i)) {
+            Some(TokenType::Keyword(keyword)) if keyword == "function" => (),
+            _ => {
+                let call = TokenType::FunctionCall(val.clone());
                 result.pop();
-                result.push(TokenType::FunctionCall(val.clone()));
+                result.push(call);
             }
         }
     }
     result.push(TokenType::OpeningParenthesis);
 }
 
-fn lex_separator(token_value: &String, old_tokens: &Vec<TokenType>, result: &mut Vec<TokenType>) -> Result<(), String> {
+fn lex_separator(token_value: &String, result: &mut Vec<TokenType>) -> Result<(), String> {
     match token_value.to_string().as_str() {
-        "(" => lex_opening_parenthesis(&old_tokens, result),
+        "(" => lex_opening_parenthesis(result),
         ")" => result.push(TokenType::ClosingParenthesis),
         "[" => result.push(TokenType::OpeningBracket),
-        "]" => lex_closing_brackets(&old_tokens, result),
+        "]" => lex_closing_brackets(result),
+        "{" => result.push(TokenType::OpeningBrace),
+        "}" => result.push(TokenType::ClosingBrace),
         ":" => result.push(TokenType::Colon),
         "," => result.push(TokenType::Comma),
         _   => return Err(format!("invalid separator '{}'", token_value))
@@ -158,24 +162,37 @@ fn lex_separator(token_value: &String, old_tokens: &Vec<TokenType>, result: &mut
     return Ok(());
 }
 
-fn create_token(token_value: String, context: TokenizerContext, old_tokens: Vec<TokenType>) -> Result<Vec<TokenType>, String> {
-
-    let mut tokens: Vec<TokenType> = Vec::with_capacity(old_tokens.len());
-    for element in old_tokens.iter() {
-        tokens.push(element.clone());
+/// English word of the keyword, word operator or boolean `token_value` of `dialect`, other names being kept.
+/// The English words stay keywords in every dialect.
+fn english_name(token_value: String, dialect: &str) -> String {
+    if dialect != "fr" {
+        return token_value;
     }
+    return match FRENCH_KEYWORDS.iter().find(|(french, _)| *french == token_value) {
+        Some((_, english)) => String::from(*english),
+        None => token_value,
+    };
+}
+
+/// Lexes `token_value`, written in `dialect`, into the tokens following `tokens`, some of which are changed by it as
+/// `f` becoming a call before `(`.
+fn create_token(token_value: String, context: TokenizerContext, dialect: &str, tokens: &mut Vec<TokenType>) -> Result<(), String> {
 
     match context {
-        TokenizerContext::Name => lex_name_token(token_value, &mut tokens),
+        TokenizerContext::Name if token_value.contains('.') => {
+            if let Err(e) = lex_member_token(token_value, tokens) {
+                return Err(e);
+            }
+        },
+        TokenizerContext::Name => lex_name_token(english_name(token_value, dialect), tokens),
         TokenizerContext::Operator => {
-            match lex_operators(token_value.clone(), old_tokens.last()) {
-                Ok(operators) =>
-                    operators.iter().for_each(|token| tokens.push(token.clone())),
+            match lex_operators(token_value.clone(), tokens.last()) {
+                Ok(operators) => tokens.extend(operators),
                 Err(e) => return Err(e),
             };
         },
         TokenizerContext::Value => {
-            if let Err(e) = lex_value_token(&token_value, &mut tokens) {
+            if let Err(e) = lex_value_token(&token_value, tokens) {
                 return Err(e);
             }
         },
@@ -184,7 +201,7 @@ fn create_token(token_value: String, context: TokenizerContext, old_tokens: Vec<
         },
         TokenizerContext::Separator => {
 
-            if let Err(e) = lex_separator(&token_value, &old_tokens, &mut tokens) {
+            if let Err(e) = lex_separator(&token_value, tokens) {
                 return Err(e);
             }
         }
@@ -193,16 +210,92 @@ fn create_token(token_value: String, context: TokenizerContext, old_tokens: Vec<
         },
     };
 
-    return Ok(tokens);
+    return Ok(());
+}
+
+/// Pragma written on `line`, `@pragma name value` with the value running until the end of the line, `None` when the
+/// line holds none. A dialect must be one the lexer knows.
+fn lex_pragma(line: &str) -> Option<Result<TokenType, String>> {
+    let rest = match line.trim_start().strip_prefix(PRAGMA) {
+        Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => rest,
+        _ => return None,
+    };
+    let mut words = rest.split_whitespace();
+    let name = match words.next() {
+        Some(name) => name,
+        None => return Some(Err(String::from("missing name for pragma"))),
+    };
+    let value = words.collect::<Vec<&str>>().join(" ");
+    if name == "dialect" && !DIALECTS.contains(&value.as_str()) {
+        return Some(Err(format!("unknown dialect '{}', expected {}", value, DIALECTS.join(", "))));
+    }
+    return Some(Ok(TokenType::Pragma(String::from(name), value)));
+}
+
+fn push_spans(spans: &mut Vec<Span>, token_count: usize, span: Span) {
+    spans.truncate(token_count);
+    while spans.len() < token_count {
+        spans.push(span);
+    }
+}
+
+pub fn tokenize(lines: &[String]) -> Result<Vec<TokenType>, Diagnostic> {
+    return match tokenize_with_spans(lines) {
+        Err(e) => Err(e),
+        Ok((tokens, _)) => Ok(tokens),
+    };
+}
+
+/// Tokenizes the lines, also returning the position of each token.
+/// Both vectors have the same length, `spans[i]` being the 1-based position of `tokens[i]`.
+pub fn tokenize_with_spans(lines: &[String]) -> Result<(Vec<TokenType>, Vec<Span>), Diagnostic> {
+    return tokenize_file(lines, 0);
+}
+
+/// One token per line, after the position where it starts: `3:5 <Variable (x)>`.
+pub fn format_tokens(tokens: &[TokenType], spans: &[Span]) -> String {
+    let mut res = String::new();
+    for (token, span) in tokens.iter().zip(spans) {
+        res.push_str(format!("{} {}\n", span, token).as_str());
+    }
+    return res;
+}
+
+/// Same as `tokenize_with_spans`, the spans referring to the `file`-th file of the compilation.
+pub fn tokenize_file(lines: &[String], file: usize) -> Result<(Vec<TokenType>, Vec<Span>), Diagnostic> {
+    return timings::time(Phase::Lexing, || lex_file(lines, file));
 }
 
-pub fn tokenize(lines: &Vec<String>) -> Result<Vec<TokenType>, String> {
+fn lex_file(lines: &[String], file: usize) -> Result<(Vec<TokenType>, Vec<Span>), Diagnostic> {
 
     let mut context = TokenizerContext::None;
     let mut current_token = Vec::<char>::new();
     let mut result = Vec::<TokenType>::new();
+    let mut spans = Vec::<Span>::new();
+    let mut token_start = Span { file, ..Span::default() };
+    // the keywords of the lines following a `dialect` pragma are those of its dialect.
+    let mut dialect = String::from(DIALECTS[0]);
 
     for (line_index, l) in lines.iter().enumerate() {
+        // a pragma takes its whole line.
+        match lex_pragma(l) {
+            None => (),
+            Some(pragma) => {
+                let span = Span { line: line_index + 1, column: l.chars().take_while(|c| c.is_whitespace()).count() + 1, file };
+                match pragma {
+                    Err(e) => return Err(Diagnostic::error(Code::InvalidPragma, e, span)),
+                    Ok(TokenType::Pragma(name, value)) if name == "dialect" => {
+                        dialect = value.clone();
+                        result.push(TokenType::Pragma(name, value));
+                    },
+                    Ok(token) => result.push(token),
+                };
+                push_spans(&mut spans, result.len(), span);
+                result.push(TokenType::EndLine);
+                push_spans(&mut spans, result.len(), Span { line: line_index + 1, column: l.chars().count() + 1, file });
+                continue;
+            },
+        };
         let mut chars = l.chars().enumerate();
         if let Some((mut char_index, mut c)) = chars.next() {
             loop {
@@ -220,6 +313,7 @@ pub fn tokenize(lines: &Vec<String>) -> Result<Vec<TokenType>, String> {
                 } else {
                     match context {
                         TokenizerContext::None => {
+                            token_start = Span { line: line_index + 1, column: char_index + 1, file };
                             if OPERATOR_STRING.contains(c) {
                                 context = TokenizerContext::Operator;
                             } else if SEPARATORS.contains(c) {
@@ -232,7 +326,7 @@ pub fn tokenize(lines: &Vec<String>) -> Result<Vec<TokenType>, String> {
                                 context = TokenizerContext::QuotedValue;
                                 should_push = false;
                             } else {
-                                return Err(format!("invalid character '{}' at {}:{}", c, line_index, char_index));
+                                return Err(Diagnostic::error(Code::InvalidToken, format!("invalid character '{}'", c), token_start));
                             }
                         },
                         TokenizerContext::Name if !START_NAME_CHARACTERS.contains(c) && !NUMERIC_CHARACTERS.contains(c) => {
@@ -260,17 +354,14 @@ pub fn tokenize(lines: &Vec<String>) -> Result<Vec<TokenType>, String> {
                     }
                 }
 
-                match push_context {
-                    Some(_) => {
-                        let token_value = current_token.iter().collect::<String>();
-                        match create_token(token_value, context, result) {
-                            Ok(val) => result = val,
-                            Err(e) => return Err(e),
-                        };
-                        context = TokenizerContext::None;
-                        current_token.clear();
-                    },
-                    None => (),
+                if push_context.is_some() {
+                    let token_value = current_token.iter().collect::<String>();
+                    if let Err(e) = create_token(token_value, context, &dialect, &mut result) {
+                        return Err(Diagnostic::error(Code::InvalidToken, e, token_start));
+                    }
+                    push_spans(&mut spans, result.len(), token_start);
+                    context = TokenizerContext::None;
+                    current_token.clear();
                 };
 
                 if next_char && should_push {
@@ -292,15 +383,40 @@ pub fn tokenize(lines: &Vec<String>) -> Result<Vec<TokenType>, String> {
             TokenizerContext::None => (),
             _ => {
                 let token_value = current_token.iter().collect::<String>();
-                match create_token(token_value, context, result) {
-                    Ok(val) => result = val,
-                    Err(e) => return Err(e),
-                };
+                if let Err(e) = create_token(token_value, context, &dialect, &mut result) {
+                    return Err(Diagnostic::error(Code::InvalidToken, e, token_start));
+                }
+                push_spans(&mut spans, result.len(), token_start);
                 current_token.clear();
                 context = TokenizerContext::None;
             },
         };
         result.push(TokenType::EndLine);
+        push_spans(&mut spans, result.len(), Span { line: line_index + 1, column: l.chars().count() + 1, file });
+    }
+    return Ok((result, spans));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, TokenType};
+
+    /// Tokens of `source` as they are printed, without its pragmas but with the end of their lines.
+    fn tokens(source: &str) -> Vec<String> {
+        let lines = source.lines().map(String::from).collect::<Vec<String>>();
+        let tokens = match tokenize(&lines) {
+            Err(e) => panic!("{}", e.message),
+            Ok(val) => val,
+        };
+        return tokens.iter().filter(|token| !matches!(token, TokenType::Pragma(..))).map(|token| token.to_string()).collect();
+    }
+
+    #[test]
+    fn lexes_french_keywords() {
+        let french = "@pragma dialect fr\nfonction f(x: int): int\n    si x > 0 et vrai\n        retourner x\n    sinon\n        ecrire faux\n    fin\n    retourner 0\nfin\n";
+        let english = "\nfunction f(x: int): int\n    if x > 0 and true\n        return x\n    else\n        write false\n    end\n    return 0\nend\n";
+        assert_eq!(tokens(french), tokens(english));
+        // the French words are names in the English dialect.
+        assert_eq!(tokens("si <- fin\n"), tokens("x <- y\n").into_iter().map(|token| token.replace("(x)", "(si)").replace("(y)", "(fin)")).collect::<Vec<String>>());
     }
-    return Ok(result);
 }
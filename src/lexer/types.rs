@@ -6,6 +6,9 @@ pub enum TokenType {
     ClosingParenthesis,
     OpeningBracket,
     ClosingBracket,
+    /// `{` opening a map value.
+    OpeningBrace,
+    ClosingBrace,
     Comma,
     Colon,
     EndLine,
@@ -17,9 +20,33 @@ pub enum TokenType {
     BinaryOperator(String),
     UnaryOperator(String),
     Variable(String),
+    /// `.field` following a value.
+    Member(String),
     FunctionCall(String),
     Keyword(String),
     TypeDef(String),
+    /// `@pragma name value`, the whole line, with the name and the value of the pragma.
+    Pragma(String, String),
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    /// index of the source file among the files compiled together.
+    pub file: usize,
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}:{}", self.line, self.column);
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{}:{}", self.line, self.column);
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -46,15 +73,19 @@ impl fmt::Display for TokenType {
             Self::ClosingParenthesis => write!(f, "<ClosingParenthesis ')'>"),
             Self::OpeningBracket => write!(f, "<OpeningBracket '['>"),
             Self::ClosingBracket => write!(f, "<ClosingBracket ']'>"),
+            Self::OpeningBrace => write!(f, "<OpeningBrace '{{'>"),
+            Self::ClosingBrace => write!(f, "<ClosingBrace '}}'>"),
             Self::EndLine => write!(f, "<EndLine>"),
             Self::Comma => write!(f, "<Comma ','>"),
             Self::Colon => write!(f, "<Colon ':'>"),
             Self::BinaryOperator(val) => write!(f, "<BinaryOperator ({})>", val),
             Self::UnaryOperator(val) => write!(f, "<UnaryOperator ({})>", val),
             Self::Variable(val) => write!(f, "<Variable ({})>", val),
+            Self::Member(val) => write!(f, "<Member (.{})>", val),
             Self::FunctionCall(val) => write!(f, "<FunctionCall ({})>", val),
             Self::Keyword(val) => write!(f, "<Keyword ({})>", val),
             Self::TypeDef(val) => write!(f, "<TypeDef ({})>", val),
+            Self::Pragma(name, value) => write!(f, "<Pragma ({} {})>", name, value),
             Self::Int(val) => write!(f, "<Int ({})>", val),
             Self::Float(val) => write!(f, "<Float ({})>", val),
             Self::String(val) => write!(f, "<String ({})>", val),
@@ -1,11 +1,11 @@
 
-pub fn to_float(token_value: &String) -> Option<f64> {
+pub fn to_float(token_value: &str) -> Option<f64> {
 
     let mut upper_part: f64 = 0.0;
     let mut lower_part: f64 = 0.0;
     let mut chars = token_value.chars();
 
-    while let Some(c) = chars.next() {
+    for c in chars.by_ref() {
         if let Some(val) = c.to_digit(10) {
             upper_part = upper_part * 10.0 + val as f64;
         } else if c == '.' {
@@ -28,14 +28,13 @@ pub fn to_float(token_value: &String) -> Option<f64> {
     return Some(upper_part + lower_part);
 }
 
-pub fn to_int(token_value: &String) -> Option<i64> {
+pub fn to_int(token_value: &str) -> Option<i64> {
     let mut result: i64 = 0;
     for c in token_value.chars() {
-        if let Some(val) = c.to_digit(10) {
-            result = result * 10 + val as i64;
-        } else {
-            return None;
-        }
+        match c.to_digit(10).and_then(|val| result.checked_mul(10).and_then(|res| res.checked_add(val as i64))) {
+            Some(val) => result = val,
+            None => return None,
+        };
     }
 
     return Some(result);
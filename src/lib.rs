@@ -0,0 +1,188 @@
+//! Compiler of the algo language, from the source of a program to assembly, C, python, js or WebAssembly.
+//!
+//! The stages are exposed on their own: `lexer`, `parser`, then `compiler`, which checks the program before
+//! generating code. `tokenize_str`, `parse_str`, `parse_sources`, `compile_file`, `run`, `run_code` and
+//! `run_code_captured` chain them for the common cases, `compile_file` and `run` failing with an `error::AlgoError`.
+//! `parse_untrusted` parses bytes of any origin, as those of a fuzzer.
+//! `interpreter::eval_expression` evaluates a single expression against the variables of an environment.
+//! `interpreter::program::run_program` runs a whole program without compiling it, telling a tracer of its statements,
+//! calls and assignments, and `interpreter::debug::Debugger` pauses it at breakpoints.
+//! `parser::testing` generates random programs for property based tests of the parser.
+//! `timings` measures the time spent in each phase, which also runs in a `tracing` span.
+//! The `wasm` feature adds the JavaScript bindings of `web`, and the `ffi` feature the C bindings of `ffi`.
+
+// The code base favours explicit `return` statements and `match` based error
+// propagation over `?`.
+#![allow(clippy::needless_return, clippy::question_mark)]
+
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub mod analysis;
+pub mod build;
+pub mod cache;
+pub mod compiler;
+pub mod diagnostic;
+pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod interpreter;
+mod json;
+pub mod lexer;
+pub mod parser;
+pub mod timings;
+#[cfg(feature = "wasm")]
+pub mod web;
+
+use compiler::{CompilationOutput, CompilerOptions, Platform};
+use diagnostic::{Code, Diagnostic};
+use error::{AlgoError, CompileError, LexError, ParseError};
+use lexer::Span;
+
+/// Tokens of `source`, the text of a whole program, along with the position of each of them.
+pub fn tokenize_str(source: &str) -> Result<(Vec<lexer::TokenType>, Vec<Span>), Diagnostic> {
+    let lines = source.lines().map(String::from).collect::<Vec<String>>();
+    return lexer::tokenize_with_spans(&lines);
+}
+
+/// Tokenizes and parses `source`, the text of a whole program.
+pub fn parse_str(source: &str) -> Result<parser::Ast, Diagnostic> {
+    let (tokens, spans) = match tokenize_str(source) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return parser::load_ast_with_spans(&tokens, &spans);
+}
+
+/// Parses the texts of files compiled together into a single program, as `compiler::merge_programs` does.
+/// The spans of the `i`-th source refer to the `i`-th file.
+pub fn parse_sources(sources: &[String]) -> Result<parser::Ast, Diagnostic> {
+    let mut programs = Vec::<parser::Ast>::new();
+    for (i, source) in sources.iter().enumerate() {
+        let lines = source.lines().map(String::from).collect::<Vec<String>>();
+        let (tokens, spans) = match lexer::tokenize_file(&lines, i) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        match parser::load_ast_with_spans(&tokens, &spans) {
+            Err(e) => return Err(e),
+            Ok(program) => programs.push(program),
+        };
+    }
+    return compiler::merge_programs(&programs);
+}
+
+/// Tokenizes and parses `bytes` as `parse_str` does, without panicking whatever they are: text that is not UTF-8,
+/// invalid characters and programs nesting too many blocks, expressions or operators for the stack of the parser
+/// all fail with a diagnostic. The time taken grows linearly with the number of lines.
+pub fn parse_untrusted(bytes: &[u8]) -> Result<parser::Ast, Diagnostic> {
+    let source = match std::str::from_utf8(bytes) {
+        Err(e) => {
+            let valid = &bytes[..e.valid_up_to()];
+            let line = valid.iter().filter(|b| **b == b'\n').count() + 1;
+            let line_start = valid.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+            let column = String::from_utf8_lossy(&valid[line_start..]).chars().count() + 1;
+            return Err(Diagnostic::error(Code::InvalidToken, String::from("invalid UTF-8 sequence"), Span { line, column, file: 0 }));
+        },
+        Ok(source) => source,
+    };
+    return parse_str(source);
+}
+
+/// Tokenizes and parses `source` as `parse_str` does, the error telling which of the stages failed.
+fn parse_stages(source: &str) -> Result<parser::Ast, AlgoError> {
+    let (tokens, spans) = match tokenize_str(source) {
+        Err(e) => return Err(AlgoError::Lex(LexError::from(e))),
+        Ok(val) => val,
+    };
+    return match parser::load_ast_with_spans(&tokens, &spans) {
+        Err(e) => Err(AlgoError::Parse(ParseError::from(e))),
+        Ok(ast) => Ok(ast),
+    };
+}
+
+/// Reads, parses and compiles the program written at `path`, as `options` tell.
+pub fn compile_file(path: &str, options: &CompilerOptions) -> Result<CompilationOutput, AlgoError> {
+    let source = match std::fs::read_to_string(path) {
+        Err(error) => return Err(AlgoError::Io { path: String::from(path), error }),
+        Ok(val) => val,
+    };
+    let ast = match parse_stages(&source) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return match compiler::compile(&ast, options) {
+        Err(diagnostics) => Err(AlgoError::Compile(CompileError::from(diagnostics))),
+        Ok(compiled) => Ok(compiled),
+    };
+}
+
+/// Exit status of a program run by `run`, along with the warnings found while compiling it.
+pub struct RunOutput {
+    pub status: ExitStatus,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Executables built by `run` in this process, each one getting its own path.
+static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+/// Compiles `source` as `options` tell, builds it in the temporary directory and runs it with `args`,
+/// the program sharing the standard streams of the caller. Only the nasm and gas targets build executables.
+pub fn run(source: &str, options: &CompilerOptions, args: &[&str]) -> Result<RunOutput, AlgoError> {
+    let ast = match parse_stages(source) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let compiled = match compiler::compile(&ast, options) {
+        Err(diagnostics) => return Err(AlgoError::Compile(CompileError::from(diagnostics))),
+        Ok(val) => val,
+    };
+    return match run_code(&compiled.code, options, args) {
+        Err(e) => Err(AlgoError::Build(e)),
+        Ok(status) => Ok(RunOutput { status, diagnostics: compiled.diagnostics }),
+    };
+}
+
+/// Builds `code`, generated by the compiler with `options`, in the temporary directory and runs it with `args`,
+/// as `run` does.
+pub fn run_code(code: &str, options: &CompilerOptions, args: &[&str]) -> Result<ExitStatus, Diagnostic> {
+    return with_executable(code, options, |path| match Command::new(path).args(args).status() {
+        Err(e) => Err(Diagnostic::error(Code::Build, format!("could not run {}: {}", path, e), Span::default())),
+        Ok(status) => Ok(status),
+    });
+}
+
+/// Same as `run_code`, the output of the program being returned instead of written to the standard streams,
+/// which the program does not read from.
+pub fn run_code_captured(code: &str, options: &CompilerOptions, args: &[&str]) -> Result<Output, Diagnostic> {
+    return with_executable(code, options, |path| match Command::new(path).args(args).stdin(Stdio::null()).output() {
+        Err(e) => Err(Diagnostic::error(Code::Build, format!("could not run {}: {}", path, e), Span::default())),
+        Ok(output) => Ok(output),
+    });
+}
+
+/// Builds `code`, generated by the compiler with `options`, in the temporary directory under a path of its own,
+/// and returns what `f` does with the executable, which is removed afterwards.
+pub fn with_executable<T>(code: &str, options: &CompilerOptions, f: impl FnOnce(&str) -> Result<T, Diagnostic>) -> Result<T, Diagnostic> {
+    let syntax = match options.target.assembly_syntax() {
+        None => {
+            let message = format!("cannot run programs compiled for the {} target", options.target.name());
+            return Err(Diagnostic::error(Code::Unsupported, message, Span::default()).with_note("programs compiled for the nasm and gas targets can be run"));
+        },
+        Some(val) => val,
+    };
+
+    let id = RUNS.fetch_add(1, Ordering::Relaxed);
+    let output = std::env::temp_dir().join(format!("algo-{}-{}", std::process::id(), id)).to_string_lossy().into_owned();
+    let program = build::program_path(&output, syntax);
+    if let Err(e) = std::fs::write(&program, code) {
+        return Err(Diagnostic::error(Code::Io, format!("could not write {}: {}", program, e), Span::default()));
+    }
+    let built = build::build_executable(syntax, &output, options.freestanding, options.platform.unwrap_or(Platform::X86_64Linux));
+    let res = match built {
+        Err(e) => Err(e),
+        Ok(()) => f(&output),
+    };
+    build::remove_executable(syntax, &output);
+    return res;
+}
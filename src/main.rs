@@ -1,50 +1,736 @@
+// The code base favours explicit `return` statements and `match` based error
+// propagation over `?`.
+#![allow(clippy::needless_return, clippy::question_mark)]
+
 use std::fs::File;
+use std::io::IsTerminal;
+use std::path::Path;
 use std::process::exit;
-use std::io::{BufRead, BufReader};
 
-pub mod lexer;
-pub mod parser;
-pub mod compiler;
+use algo_parser::interpreter::trace::NoTrace;
+use algo_parser::{analysis, build, cache, compiler, diagnostic, lexer, parser, timings};
+
+mod bench;
+mod debug;
+mod editor;
+mod repl;
+mod testing;
+mod trace;
+
+const USAGE: &str = "usage: algo_parser [options] <file.algo>...
+       algo_parser lint [lint options] <file.algo>...
+       algo_parser dead-functions [--format warnings|json] [--message-format <fmt>] <file.algo>...
+       algo_parser run [--interp] [options] <file.algo>... [-- <program arguments>]
+       algo_parser test [--filter <text>] [--coverage lcov|json|annotate] [--checks <checks>] <file.algo>...
+       algo_parser bench [--native [-O0|-O1|-O2]] [--runs <count>] [--call <call>]... [--checks <checks>] <file.algo>...
+       algo_parser trace [--format text|json] [--checks <checks>] <file.algo>...
+       algo_parser debug [--input <file>] [--checks <checks>] <file.algo>...
+       algo_parser repl
+
+options:
+    --emit <target>          generate nasm (default), gas, riscv, wasm, c, python, js or ir code
+    --emit-tokens            print the tokens of the program with their position
+    --semantic-tokens <fmt>  print the keywords, names and literals of the program with their kind, for editors
+                             to highlight it, as a json list or as lsp semantic tokens
+    --emit-ast <format>      print the syntax tree as debug, json, dot or source
+    --check                  only check the program, without generating code
+    --target <platform>      compile for x86_64-linux or wasm32
+    -O0, -O1, -O2            optimization level
+    -o <output>              build the executable <output>, for the nasm and gas targets
+    --checks <checks>        check div and overflow at runtime, separated by commas
+    -A, -W, -D <warning>     allow, warn or deny a warning code, or all of them with `warnings`
+    --strict                 reject variables read before being assigned
+    --freestanding           start the program without the C library
+    --stack-budget <bytes>   warn about frames and calls over the budget
+    --symbols                print the symbol table
+    --source-map             print the source map, or write it next to the executable
+    --source-comments        quote the source in the generated assembly
+    --verbose                report the conditions found constant
+    --timings                print the time spent lexing, parsing, checking, optimizing and generating code
+    --metrics <format>       print the metrics of the functions as a table or json
+    --explain <code>         describe a diagnostic code
+    --cache <dir>            keep the syntax trees of the files and the generated code in <dir>, for the next builds
+                             to reuse them while the files are unchanged
+    --message-format <fmt>   print the diagnostics quoting the source (text, the default), on one line (short)
+                             or as one json object per line (json)
+    --lang <en|fr>           write the messages of the diagnostics in English (the default) or in French, the
+                             ALGO_LANG environment variable giving the language when the option is not given
+    -h, --help               print this message
+
+lint options, the rules being unused-function, assignment-in-condition, magic-number and snake-case:
+    --enable <rule>          run a rule, or all of them with `all`
+    --disable <rule>         do not run a rule, or any of them with `all`
+    --config <file>          enable or disable the rules listed in the file, one `rule = on|off` per line
+    --message-format <fmt>   print the diagnostics quoting the source (text, the default), on one line (short)
+                             or as one json object per line (json)
+    --lang <en|fr>           write the messages of the diagnostics in English or in French
+
+run builds the program in the temporary directory, gas being the default target, and exits with its status. With
+--interp, the interpreter runs the program instead of building it, without arguments. As in the compiled programs,
+the ints of the interpreted ones wrap around when they overflow, unless the overflow is checked with --checks.
+test runs with the interpreter the functions without parameters named test_* or following a `@test` line, each one
+after the top level statements, a failed `assert(condition)` failing the test. --filter only runs the tests whose
+name contains the text. --coverage counts the runs of each line by the tests, printed after their results as an lcov
+tracefile, as json or as the source annotated with them, `#####` marking the lines never run.
+dead-functions lists the functions of the files, compiled together, that the program never reaches from its top level
+statements, main or the functions with their own symbol, as warnings or as a json report, failing when there are some.
+bench times the calls given with --call, as `fibo(25)`, or the functions without parameters named bench_*, run by the
+interpreter, or compiled at -O2 unless told otherwise with --native, each call being compared to the program without it.
+trace runs the program with the interpreter instead of compiling it, each statement being written to stderr before it
+runs along with the variables of its function, as text or as one json object per line, the json trace also having
+the calls, returns, assignments, branches and loop iterations, for visualizers.
+debug runs the program with the interpreter, pausing before its first statement for the commands read from stdin
+to set breakpoints, step through the program and print its calls and variables, `help` listing them. The program
+reads the file given by --input, or nothing, and runs to its end once the commands are over, `quit` stopping it.
+repl runs the statements entered one by one with the interpreter, keeping their variables, and prints the value of
+the expressions. In a terminal, the lines are edited and the arrows recall the previous ones, `:history` printing the
+entries of the session.
+
+pragmas: `@pragma <name> <value>` lines at the top level of a file set the runtime checks (checks div,overflow or off),
+the strictness (strict on|off), the warning levels (allow, warn or deny <warning>) and the dialect of the keywords
+(dialect en|fr, the French keywords being si, sinon, tantque, fin, ...), after the options of the command line.
+
+exit status: 0 on success, 1 when errors were reported or tests failed, 2 for invalid arguments and 101 for internal
+errors of the compiler. run exits with the status of the program instead, or 1 when it was terminated by a signal.
+";
+
+/// Exit status of the commands which reported errors: diagnostics of the programs, failed tests or lints, files
+/// which could not be read or written and executables which could not be built.
+const EXIT_FAILURE: i32 = 1;
+/// Exit status of the commands given invalid arguments.
+const EXIT_USAGE: i32 = 2;
+/// Exit status of the compiler when it panics, which is a bug of its own.
+const EXIT_INTERNAL: i32 = 101;
+
+/// Options followed by a value.
+const VALUE_OPTIONS: [&str; 15] = [
+    "--emit", "--emit-ast", "--target", "-o", "--checks", "-A", "-W", "-D", "--stack-budget", "--metrics", "--explain", "--message-format",
+    "--cache", "--semantic-tokens", "--lang",
+];
+
+/// Options standing alone.
+const OPTIONS: [&str; 15] = [
+    "-O0", "-O1", "-O2", "--emit-tokens", "--check", "--strict", "--freestanding", "--symbols", "--source-map", "--source-comments", "--verbose",
+    "--timings", "--interp", "-h", "--help",
+];
+
+/// Options of `lint`, all of them followed by a value.
+const LINT_OPTIONS: [&str; 5] = ["--enable", "--disable", "--config", "--message-format", "--lang"];
+
+/// Options of `dead-functions`, all of them followed by a value.
+const DEAD_FUNCTIONS_OPTIONS: [&str; 3] = ["--format", "--message-format", "--lang"];
+
+/// Prints the time spent in each phase to the standard error, when `--timings` is given.
+fn print_timings(args: &[String]) {
+    if args.iter().any(|arg| arg == "--timings") {
+        eprint!("{}", timings::to_table(&timings::take()));
+    }
+}
+
+/// Prints `message` followed by the usage to the standard error, and exits.
+fn usage_error(message: &str) -> ! {
+    eprint!("{}\n\n{}", message, USAGE);
+    exit(EXIT_USAGE);
+}
+
+/// Paths of the files compiled together, the arguments which are neither an option nor the value of one.
+fn input_paths(args: &[String], value_options: &[&str], options: &[&str]) -> Result<Vec<String>, String> {
+    let mut inputs = Vec::<&String>::new();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if value_options.contains(&arg.as_str()) {
+            i += 1;
+        } else if arg.starts_with('-') && !options.contains(&arg.as_str()) && !arg.starts_with("-O") {
+            return Err(format!("unknown option '{}'", arg));
+        } else if !arg.starts_with('-') {
+            inputs.push(arg);
+        }
+        i += 1;
+    }
+
+    if inputs.is_empty() {
+        return Err(String::from("missing input file"));
+    }
+    for (i, path) in inputs.iter().enumerate() {
+        if Path::new(path).extension().is_none_or(|extension| extension != "algo") {
+            return Err(format!("input file '{}' does not have the .algo extension", path));
+        }
+        if !Path::new(path).is_file() {
+            return Err(format!("input file '{}' does not exist", path));
+        }
+        if inputs[..i].contains(path) {
+            return Err(format!("input file '{}' is given twice", path));
+        }
+    }
+    return Ok(inputs.into_iter().cloned().collect());
+}
+
+/// Texts of the files at `paths`, exiting when one of them cannot be read.
+fn read_sources(paths: &[String]) -> Vec<String> {
+    let mut sources = Vec::<String>::new();
+    for path in paths {
+        match std::fs::read_to_string(path) {
+            Err(e) => {
+                eprintln!("could not read {}: {}", path, e);
+                exit(EXIT_FAILURE);
+            },
+            Ok(source) => sources.push(source),
+        };
+    }
+    return sources;
+}
+
+/// `algo_parser lint`, reporting the warnings of the enabled rules and failing when there are some.
+fn lint(args: &[String]) {
+    let paths = match input_paths(args, &LINT_OPTIONS, &[]) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let format = message_format(args);
+    let language = message_language(args);
+    let mut config = analysis::lint::LintConfig::default();
+    // the configuration file applies first, the rules enabled or disabled on the command line overriding it.
+    if let Some(i) = args.iter().position(|arg| arg == "--config") {
+        let path = match args.get(i + 1) {
+            Some(path) => path,
+            None => usage_error("missing value for --config"),
+        };
+        let text = match std::fs::read_to_string(path) {
+            Err(e) => {
+                eprintln!("could not read {}: {}", path, e);
+                exit(EXIT_USAGE);
+            },
+            Ok(text) => text,
+        };
+        if let Err(e) = config.read(&text) {
+            let reporter = Reporter { paths: std::slice::from_ref(path), sources: &[text], format, language };
+            reporter.report(&vec![e]);
+            exit(EXIT_USAGE);
+        }
+    }
+    for (i, arg) in args.iter().enumerate() {
+        let enabled = match arg.as_str() {
+            "--enable" => true,
+            "--disable" => false,
+            _ => continue,
+        };
+        let name = match args.get(i + 1) {
+            Some(name) => name,
+            None => usage_error(&format!("missing value for {}", arg)),
+        };
+        if !config.set(name, enabled) {
+            let rules = analysis::lint::Rule::ALL.iter().map(|rule| rule.name()).collect::<Vec<&str>>();
+            usage_error(&format!("unknown rule '{}', expected all or one of {}", name, rules.join(", ")));
+        }
+    }
 
-fn read_lines(filename: String) -> Vec<String> {
-    let file = File::open(filename);
-    if let Ok(buffer) = file {
-        let lines = BufReader::new(buffer).lines();
-        return lines.map(|l| l.unwrap()).collect::<Vec<String>>();
-    } else if let Err(e) = file {
-        println!("{}", e);
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+    let ast = match algo_parser::parse_sources(&sources) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(ast) => ast,
+    };
+    let diagnostics = analysis::lint::lint(&ast, &config);
+    reporter.report(&diagnostics);
+    if !diagnostics.is_empty() {
+        exit(EXIT_FAILURE);
     }
+}
 
-    return Vec::new();
+/// `algo_parser dead-functions`, reporting the functions the program never reaches and failing when there are some.
+fn dead_functions(args: &[String]) {
+    let paths = match input_paths(args, &DEAD_FUNCTIONS_OPTIONS, &[]) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let json = match args.iter().position(|arg| arg == "--format").map(|i| args.get(i + 1).map(|format| format.as_str())) {
+        None | Some(Some("warnings")) => false,
+        Some(Some("json")) => true,
+        Some(Some(format)) => usage_error(&format!("unknown report format '{}', expected warnings or json", format)),
+        Some(None) => usage_error("missing value for --format"),
+    };
+    let format = message_format(args);
+    let language = message_language(args);
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+    let ast = match algo_parser::parse_sources(&sources) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(ast) => ast,
+    };
+    let dead = analysis::dead_functions::dead_functions(&ast);
+    match json {
+        true => print!("{}", analysis::dead_functions::to_json(&dead, &paths)),
+        false => reporter.report(&analysis::dead_functions::to_diagnostics(&dead)),
+    };
+    if !dead.is_empty() {
+        exit(EXIT_FAILURE);
+    }
+}
 
+/// How the diagnostics are printed.
+#[derive(Clone, Copy, PartialEq)]
+enum MessageFormat {
+    /// the message followed by the source line it is located at, as rustc does.
+    Text,
+    /// `3:5: error[E0003]: unknown variable 'x'`, each note on its own line.
+    Short,
+    /// one JSON object per diagnostic and per line, for the tools reading them.
+    Json,
 }
 
+/// Format given by `--message-format`, text by default.
+fn message_format(args: &[String]) -> MessageFormat {
+    return match args.iter().position(|arg| arg == "--message-format") {
+        None => MessageFormat::Text,
+        Some(i) => match args.get(i + 1).map(|format| format.as_str()) {
+            Some("text") => MessageFormat::Text,
+            Some("short") => MessageFormat::Short,
+            Some("json") => MessageFormat::Json,
+            Some(format) => usage_error(&format!("unknown message format '{}', expected text, short or json", format)),
+            None => usage_error("missing value for --message-format"),
+        },
+    };
+}
 
-fn lex(filename: String) -> Result<Vec<lexer::TokenType>, String> {
-    let lines = read_lines(filename);
-    let tokens = match lexer::tokenize(&lines) {
-        Err(e) => return Err(e),
-        Ok(tokens) => tokens,
+/// Language of the diagnostics given by `--lang`, or else by the `ALGO_LANG` environment variable, English by default
+/// or when the variable names no language the compiler speaks.
+fn message_language(args: &[String]) -> diagnostic::Language {
+    return match args.iter().position(|arg| arg == "--lang") {
+        None => std::env::var("ALGO_LANG").ok().and_then(|name| diagnostic::Language::from_name(&name)).unwrap_or_default(),
+        Some(i) => match args.get(i + 1).map(|name| (name, diagnostic::Language::from_name(name))) {
+            Some((_, Some(language))) => language,
+            Some((name, None)) => usage_error(&format!("unknown language '{}', expected en or fr", name)),
+            None => usage_error("missing value for --lang"),
+        },
     };
+}
 
-    return Ok(tokens);
+/// Operations checked at runtime given by `--checks`, none by default.
+fn runtime_checks(args: &[String]) -> compiler::RuntimeChecks {
+    let mut runtime_checks = compiler::RuntimeChecks::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--checks") {
+        let names = match args.get(i + 1) {
+            Some(names) => names,
+            None => usage_error("missing value for --checks"),
+        };
+        for name in names.split(',') {
+            if !runtime_checks.enable(name) {
+                usage_error(&format!("unknown check '{}', expected one of {}", name, compiler::RuntimeChecks::NAMES.join(", ")));
+            }
+        }
+    }
+    return runtime_checks;
+}
 
+/// Prints the diagnostics of the files at `paths`, whose texts are `sources`, in the format and the language of the
+/// options.
+struct Reporter<'a> {
+    paths: &'a [String],
+    sources: &'a [String],
+    format: MessageFormat,
+    language: diagnostic::Language,
+}
+
+impl<'a> Reporter<'a> {
+    /// The diagnostic as printed, in color when `terminal` and `NO_COLOR` is not set.
+    /// The short text is prefixed with the path of its file when several files are compiled together,
+    /// diagnostics which are not located in the source not being prefixed.
+    fn format(&self, d: &diagnostic::Diagnostic, terminal: bool) -> String {
+        let d = &d.translate(self.language);
+        let path = self.paths.get(d.span.file).map(|path| path.as_str());
+        return match (self.format, path) {
+            (MessageFormat::Text, path) => {
+                let color = terminal && std::env::var_os("NO_COLOR").is_none_or(|value| value.is_empty());
+                let source = self.sources.get(d.span.file).map(|source| source.as_str());
+                diagnostic::render(d, path, source, color, self.language)
+            },
+            (MessageFormat::Json, path) => d.to_json(path, self.language),
+            (MessageFormat::Short, Some(path)) if self.paths.len() > 1 && d.span.line > 0 => format!("{}:{}", path, d.to_short(self.language)),
+            (MessageFormat::Short, _) => d.to_short(self.language),
+        };
+    }
+
+    /// Prints the diagnostics to stderr, not to mix them with the generated code.
+    fn report(&self, diagnostics: &Vec<diagnostic::Diagnostic>) {
+        for d in diagnostics {
+            eprintln!("{}", self.format(d, std::io::stderr().is_terminal()));
+        }
+    }
 }
 
 fn main() {
-    let filename = "./examples/test_functions.algo".to_string();
-    let tokens = match lex(filename) {
+    // panics exit with their own status, whatever the panic strategy of the build.
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        eprintln!("internal compiler error, please report it along with the program being compiled");
+        exit(EXIT_INTERNAL);
+    }));
+
+    let mut args = std::env::args().collect::<Vec<String>>();
+    // `run` is the compilation followed by the execution of the program, which gets the arguments following `--`.
+    let running = args.get(1).is_some_and(|arg| arg == "run");
+    let mut program_args = Vec::<String>::new();
+    if running {
+        args.remove(1);
+        if let Some(i) = args.iter().position(|arg| arg == "--") {
+            program_args = args.split_off(i + 1);
+            args.pop();
+        }
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--explain") {
+        match args.get(i + 1).map(|name| (name, diagnostic::Code::from_name(name))) {
+            Some((_, Some(code))) => print!("{}: {}", code, diagnostic::explain(code)),
+            Some((name, None)) => usage_error(&format!("unknown diagnostic code '{}'", name)),
+            None => usage_error("missing value for --explain"),
+        };
+        return;
+    }
+
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        print!("{}", USAGE);
+        return;
+    }
+    if args.get(1).is_some_and(|arg| arg == "repl") {
+        repl::run();
+        return;
+    }
+    if args.get(1).is_some_and(|arg| arg == "bench") {
+        bench::run(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|arg| arg == "test") {
+        testing::run(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|arg| arg == "lint") {
+        lint(&args[2..]);
+        return;
+    }
+    if args.get(1).is_some_and(|arg| arg == "trace") {
+        trace::run(&args[2..]);
+    }
+    if args.get(1).is_some_and(|arg| arg == "debug") {
+        debug::run(&args[2..]);
+    }
+
+    if args.get(1).is_some_and(|arg| arg == "dead-functions") {
+        dead_functions(&args[2..]);
+        return;
+    }
+    let paths = match input_paths(&args[1..], &VALUE_OPTIONS, &OPTIONS) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let interpreted = args.iter().any(|arg| arg == "--interp");
+    if interpreted && !running {
+        usage_error("--interp is only taken by run");
+    }
+    let format = message_format(&args);
+    let language = message_language(&args);
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+    if args.iter().any(|arg| arg == "--emit-tokens") {
+        for (i, source) in sources.iter().enumerate() {
+            let lines = source.lines().map(String::from).collect::<Vec<String>>();
+            let (tokens, spans) = match lexer::tokenize_file(&lines, i) {
+                Err(e) => {
+                    reporter.report(&vec![e]);
+                    exit(EXIT_FAILURE);
+                },
+                Ok(val) => val,
+            };
+            // the tokens of each file follow its path when several files are compiled together.
+            if paths.len() > 1 {
+                println!("{}:", paths[i]);
+            }
+            print!("{}", lexer::format_tokens(&tokens, &spans));
+        }
+        return;
+    }
+    if let Some(i) = args.iter().position(|arg| arg == "--semantic-tokens") {
+        let format = match args.get(i + 1).map(|format| format.as_str()) {
+            Some(format @ ("json" | "lsp")) => format,
+            Some(format) => usage_error(&format!("unknown semantic tokens format '{}', expected json or lsp", format)),
+            None => usage_error("missing value for --semantic-tokens"),
+        };
+        for (i, source) in sources.iter().enumerate() {
+            let tokens = match analysis::semantic_tokens::semantic_tokens(source) {
+                Err(e) => {
+                    reporter.report(&vec![diagnostic::Diagnostic { span: lexer::Span { file: i, ..e.span }, ..e }]);
+                    exit(EXIT_FAILURE);
+                },
+                Ok(tokens) => tokens,
+            };
+            if paths.len() > 1 {
+                println!("{}:", paths[i]);
+            }
+            match format {
+                "json" => print!("{}", analysis::semantic_tokens::to_json(&tokens)),
+                _ => print!("{}", analysis::semantic_tokens::to_lsp(&tokens, source)),
+            };
+        }
+        return;
+    }
+
+    let cache = match args.iter().position(|arg| arg == "--cache") {
+        Some(i) => match args.get(i + 1) {
+            Some(dir) => Some(cache::Cache::new(dir)),
+            None => usage_error("missing value for --cache"),
+        },
+        None => None,
+    };
+    if args.iter().any(|arg| arg == "--timings") {
+        timings::enable();
+    }
+    let parsed = match &cache {
+        Some(cache) => cache.parse_sources(&sources),
+        None => algo_parser::parse_sources(&sources),
+    };
+    let ast = match parsed {
         Err(e) => {
-            println!("{}", e);
-            exit(-1);
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
         },
-        Ok(tokens) => tokens,
+        Ok(ast) => ast,
     };
 
-    let ast = match parser::load_ast(&tokens) {
-        Err(e) => panic!("{}", e),
-        Ok(ast) => ast,
+    if let Some(i) = args.iter().position(|arg| arg == "--emit-ast") {
+        match args.get(i + 1).map(|format| format.as_str()) {
+            Some("debug") => print!("{:?}", ast),
+            Some("json") => println!("{}", parser::to_json(&ast)),
+            Some("dot") => print!("{}", parser::to_dot(&ast)),
+            Some("source") => print!("{}", parser::to_source(&ast)),
+            Some(format) => usage_error(&format!("unknown ast format '{}', expected debug, json, dot or source", format)),
+            None => usage_error("missing value for --emit-ast"),
+        };
+        print_timings(&args);
+        return;
+    }
+
+    if let Some(i) = args.iter().position(|arg| arg == "--metrics") {
+        let metrics = analysis::metrics::function_metrics(&ast);
+        match args.get(i + 1).map(|format| format.as_str()) {
+            Some("table") => print!("{}", analysis::metrics::to_table(&metrics)),
+            Some("json") => print!("{}", analysis::metrics::to_json(&metrics)),
+            Some(format) => usage_error(&format!("unknown metrics format '{}', expected table or json", format)),
+            None => usage_error("missing value for --metrics"),
+        };
+        return;
+    }
+
+    let platform = match args.iter().position(|arg| arg == "--target") {
+        Some(i) => match args.get(i + 1) {
+            Some(name) => match compiler::Platform::from_name(name) {
+                Some(platform) => Some(platform),
+                None => {
+                    let names = compiler::Platform::all().iter().map(|platform| platform.name()).collect::<Vec<&str>>();
+                    usage_error(&format!("unknown target '{}', expected one of {}", name, names.join(", ")));
+                },
+            },
+            None => usage_error("missing value for --target"),
+        },
+        None => None,
+    };
+    let target = match args.iter().position(|arg| arg == "--emit") {
+        Some(i) => match args.get(i + 1) {
+            Some(name) => match compiler::Target::from_name(name) {
+                Some(target) => target,
+                None => {
+                    let names = compiler::Target::all().iter().map(|target| target.name()).collect::<Vec<&str>>();
+                    usage_error(&format!("unknown target '{}', expected one of {}", name, names.join(", ")));
+                },
+            },
+            None => usage_error("missing value for --emit"),
+        },
+        // programs are run from gas assembly, which only needs cc to be built.
+        None if running => compiler::Target::Gas,
+        // the platform picks its native code, without platform the nasm assembly is generated.
+        None => match platform {
+            None => compiler::Target::Nasm,
+            Some(platform) => platform.native_target(),
+        },
+    };
+    let opt_level = match args.iter().rev().find(|arg| arg.starts_with("-O")) {
+        None => compiler::OptLevel::O0,
+        Some(arg) => match arg.as_str() {
+            "-O0" => compiler::OptLevel::O0,
+            "-O1" => compiler::OptLevel::O1,
+            "-O2" => compiler::OptLevel::O2,
+            level => usage_error(&format!("unknown optimization level '{}', expected -O0, -O1 or -O2", level)),
+        },
+    };
+    let runtime_checks = runtime_checks(&args);
+    let mut warnings = compiler::WarningLevels::default();
+    for (i, arg) in args.iter().enumerate() {
+        let level = match arg.as_str() {
+            "-A" => compiler::WarningLevel::Allow,
+            "-W" => compiler::WarningLevel::Warn,
+            "-D" => compiler::WarningLevel::Deny,
+            _ => continue,
+        };
+        let name = match args.get(i + 1) {
+            Some(name) => name,
+            None => usage_error(&format!("missing value for {}", arg)),
+        };
+        if !warnings.set(name, level) {
+            let codes = diagnostic::Code::WARNINGS.iter().map(|code| code.as_str()).collect::<Vec<&str>>();
+            usage_error(&format!("unknown warning '{}', expected warnings or one of {}", name, codes.join(", ")));
+        }
+    }
+    let output = match args.iter().position(|arg| arg == "-o") {
+        Some(i) => match args.get(i + 1) {
+            Some(output) => Some(output.as_str()),
+            None => usage_error("missing value for -o"),
+        },
+        None => None,
     };
 
-    compiler::test(&ast);
+    let stack_budget = match args.iter().position(|arg| arg == "--stack-budget") {
+        Some(i) => match args.get(i + 1).map(|bytes| bytes.parse::<u64>()) {
+            Some(Ok(bytes)) => Some(bytes),
+            Some(Err(..)) => usage_error("invalid value for --stack-budget, expected a number of bytes"),
+            None => usage_error("missing value for --stack-budget"),
+        },
+        None => None,
+    };
+
+    // built executables get the source map next to their assembly.
+    let source_map = args.iter().any(|arg| arg == "--source-map");
+    let options = compiler::CompilerOptions {
+        target,
+        opt_level,
+        emit: match (args.iter().any(|arg| arg == "--symbols"), source_map && output.is_none()) {
+            (true, _) => compiler::Emit::Symbols,
+            (false, true) => compiler::Emit::SourceMap,
+            (false, false) => compiler::Emit::Code,
+        },
+        strict: args.iter().any(|arg| arg == "--strict"),
+        warnings,
+        runtime_checks,
+        debug_info: match (args.iter().any(|arg| arg == "--source-comments"), sources.as_slice()) {
+            (false, _) => None,
+            (true, [source]) => Some(source.lines().map(String::from).collect()),
+            (true, _) => usage_error("--source-comments only supports a single input file"),
+        },
+        verbose: args.iter().any(|arg| arg == "--verbose"),
+        freestanding: args.iter().any(|arg| arg == "--freestanding"),
+        stack_budget,
+        platform,
+    };
+    if args.iter().any(|arg| arg == "--check") {
+        match compiler::analyze(&ast, &options) {
+            Err(diagnostics) => {
+                reporter.report(&diagnostics);
+                exit(EXIT_FAILURE);
+            },
+            Ok(diagnostics) => reporter.report(&diagnostics),
+        };
+        print_timings(&args);
+        return;
+    }
+    if interpreted {
+        if !program_args.is_empty() {
+            usage_error("run --interp does not pass arguments to the program");
+        }
+        match compiler::analyze(&ast, &options) {
+            Err(diagnostics) => {
+                reporter.report(&diagnostics);
+                exit(EXIT_FAILURE);
+            },
+            Ok(diagnostics) => reporter.report(&diagnostics),
+        };
+        print_timings(&args);
+        let stdin = std::io::stdin();
+        trace::run_program(&ast, &options, &reporter, &mut stdin.lock(), &mut NoTrace);
+    }
+    if running {
+        if output.is_some() {
+            usage_error("run does not take -o, the program being built in the temporary directory");
+        }
+        let compiled = match compiler::compile(&ast, &options) {
+            Err(diagnostics) => {
+                reporter.report(&diagnostics);
+                exit(EXIT_FAILURE);
+            },
+            Ok(compiled) => compiled,
+        };
+        reporter.report(&compiled.diagnostics);
+        print_timings(&args);
+        let args = program_args.iter().map(|arg| arg.as_str()).collect::<Vec<&str>>();
+        match algo_parser::run_code(&compiled.code, &options, &args) {
+            Err(e) => {
+                reporter.report(&vec![e]);
+                exit(EXIT_FAILURE);
+            },
+            Ok(status) => match status.code() {
+                Some(code) => exit(code),
+                None => {
+                    eprintln!("the program was terminated by {}", status);
+                    exit(EXIT_FAILURE);
+                },
+            },
+        };
+    }
+    let syntax = match (output, options.emit) {
+        (None, _) | (_, compiler::Emit::Symbols | compiler::Emit::SourceMap) => None,
+        (Some(..), compiler::Emit::Code) => match target {
+            compiler::Target::Nasm => Some(compiler::AsmSyntax::Intel),
+            compiler::Target::Gas => Some(compiler::AsmSyntax::Att),
+            target => usage_error(&format!("cannot build an executable for target '{}', expected nasm or gas", target.name())),
+        },
+    };
+    let compile_to = |out: &mut dyn std::io::Write| match &cache {
+        Some(cache) => cache.compile_to(&ast, &sources, &options, out),
+        None => compiler::compile_to(&ast, &options, out),
+    };
+    let compiled = match (output, syntax) {
+        (Some(output), Some(syntax)) => {
+            let program = build::program_path(output, syntax);
+            match File::create(&program) {
+                Err(e) => {
+                    eprintln!("could not write {}: {}", program, e);
+                    exit(EXIT_FAILURE);
+                },
+                Ok(mut file) => compile_to(&mut file),
+            }
+        },
+        _ => compile_to(&mut std::io::stdout().lock()),
+    };
+    match compiled {
+        Err(diagnostics) => {
+            reporter.report(&diagnostics);
+            exit(EXIT_FAILURE);
+        },
+        Ok(diagnostics) => reporter.report(&diagnostics),
+    };
+    print_timings(&args);
+    if let (Some(output), Some(syntax)) = (output, syntax) {
+        if let Err(e) = build::build_executable(syntax, output, options.freestanding, options.platform.unwrap_or(compiler::Platform::X86_64Linux)) {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        }
+        if source_map {
+            let path = format!("{}.map.json", build::program_path(output, syntax));
+            let options = compiler::CompilerOptions { emit: compiler::Emit::SourceMap, ..options };
+            let written = match File::create(&path) {
+                Err(e) => {
+                    eprintln!("could not write {}: {}", path, e);
+                    exit(EXIT_FAILURE);
+                },
+                Ok(mut file) => compiler::compile_to(&ast, &options, &mut file),
+            };
+            if let Err(diagnostics) = written {
+                reporter.report(&diagnostics);
+                exit(EXIT_FAILURE);
+            }
+        }
+    }
 }
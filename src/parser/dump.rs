@@ -0,0 +1,239 @@
+use super::super::json;
+use super::{Ast, Variable};
+
+/// Field of a node, as written in the dumps of the tree.
+enum Field<'a> {
+    Text(String),
+    /// number or bool, written as is.
+    Literal(String),
+    Node(&'a Ast),
+    Nodes(&'a Vec<Ast>),
+    /// `name: type` of each variable.
+    Variables(&'a Vec<Variable>),
+    /// cases of a `match`, the values listed by each case along with its statements.
+    Cases(&'a Vec<(Vec<Ast>, Vec<Ast>)>),
+    /// keys and values of a map.
+    Entries(&'a Vec<(Ast, Ast)>),
+    Missing,
+}
+
+fn text(value: &str) -> Field<'_> {
+    return Field::Text(String::from(value));
+}
+
+fn optional_text(value: &Option<String>) -> Field<'_> {
+    return match value {
+        Some(value) => text(value),
+        None => Field::Missing,
+    };
+}
+
+fn variable(var: &Variable) -> String {
+    return format!("{:?}", var);
+}
+
+/// Name of the kind of the node, along with its fields in source order.
+fn describe(ast: &Ast) -> (&'static str, Vec<(&'static str, Field<'_>)>) {
+    let binary = |kind, left, right| (kind, vec![("left", Field::Node(left)), ("right", Field::Node(right))]);
+    return match ast {
+        Ast::Global(children) => ("Global", vec![("children", Field::Nodes(children))]),
+        Ast::FunctionHeader { name, parameters, return_type, symbol, .. } => ("FunctionHeader", vec![
+            ("name", text(name)),
+            ("parameters", Field::Variables(parameters)),
+            ("return_type", optional_text(return_type)),
+            ("symbol", optional_text(symbol)),
+        ]),
+        Ast::FunctionDeclaration { name, children, parameters, return_type, symbol, .. } => ("FunctionDeclaration", vec![
+            ("name", text(name)),
+            ("parameters", Field::Variables(parameters)),
+            ("return_type", optional_text(return_type)),
+            ("symbol", optional_text(symbol)),
+            ("children", Field::Nodes(children)),
+        ]),
+        Ast::FunctionCall { name, children, .. } => ("FunctionCall", vec![("name", text(name)), ("arguments", Field::Nodes(children))]),
+        Ast::Int(value) => ("Int", vec![("value", Field::Literal(value.to_string()))]),
+        Ast::Float(value) if value.is_finite() => ("Float", vec![("value", Field::Literal(format!("{:?}", value)))]),
+        Ast::Float(value) => ("Float", vec![("value", Field::Text(value.to_string()))]),
+        Ast::Str(value) => ("Str", vec![("value", text(value))]),
+        Ast::Bool(value) => ("Bool", vec![("value", Field::Literal(value.to_string()))]),
+        Ast::ArrayValue(children) => ("ArrayValue", vec![("elements", Field::Nodes(children))]),
+        Ast::Assignement { variable, expression, .. } => ("Assignement", vec![("variable", Field::Node(variable)), ("expression", Field::Node(expression))]),
+        Ast::Condition { condition, valid_branch, invalid_branch, .. } => ("Condition", vec![
+            ("condition", Field::Node(condition)),
+            ("then", Field::Nodes(valid_branch)),
+            ("else", Field::Nodes(invalid_branch)),
+        ]),
+        Ast::WhileLoop { condition, children, .. } => ("WhileLoop", vec![("condition", Field::Node(condition)), ("children", Field::Nodes(children))]),
+        Ast::Variable(var) => ("Variable", vec![
+            ("name", text(&var.name)),
+            ("type", match &var.typename {
+                Some(typename) => Field::Text(format!("{:?}", typename)),
+                None => Field::Missing,
+            }),
+        ]),
+        Ast::Statement { children } => ("Statement", vec![("children", Field::Nodes(children))]),
+        Ast::Addition { left, right } => binary("Addition", left, right),
+        Ast::Substraction { left, right } => binary("Substraction", left, right),
+        Ast::Multiplication { left, right } => binary("Multiplication", left, right),
+        Ast::Division { left, right } => binary("Division", left, right),
+        Ast::Modulo { left, right } => binary("Modulo", left, right),
+        Ast::GreaterThan { left, right } => binary("GreaterThan", left, right),
+        Ast::LowerThan { left, right } => binary("LowerThan", left, right),
+        Ast::GreaterOrEqual { left, right } => binary("GreaterOrEqual", left, right),
+        Ast::LowerOrEqual { left, right } => binary("LowerOrEqual", left, right),
+        Ast::And { left, right } => binary("And", left, right),
+        Ast::Or { left, right } => binary("Or", left, right),
+        Ast::EqualTo { left, right } => binary("EqualTo", left, right),
+        Ast::NotEqualTo { left, right } => binary("NotEqualTo", left, right),
+        Ast::UnaryPlus { child } => ("UnaryPlus", vec![("child", Field::Node(child))]),
+        Ast::UnaryMinus { child } => ("UnaryMinus", vec![("child", Field::Node(child))]),
+        Ast::ReturnStatement { value, .. } => ("ReturnStatement", vec![("value", match value {
+            Some(value) => Field::Node(value),
+            None => Field::Missing,
+        })]),
+        Ast::ArrayAccess { variable, index } => ("ArrayAccess", vec![("variable", text(variable)), ("index", Field::Node(index))]),
+        Ast::Lambda { parameters, return_type, body, .. } => ("Lambda", vec![
+            ("parameters", Field::Variables(parameters)),
+            ("return_type", optional_text(return_type)),
+            ("body", Field::Node(body)),
+        ]),
+        Ast::FunctionReference(name) => ("FunctionReference", vec![("name", text(name))]),
+        Ast::ConstDeclaration { name, value, .. } => ("ConstDeclaration", vec![("name", text(name)), ("value", Field::Node(value))]),
+        Ast::RecordDeclaration { name, fields, .. } => ("RecordDeclaration", vec![("name", text(name)), ("fields", Field::Variables(fields))]),
+        Ast::MemberAccess { record, field } => ("MemberAccess", vec![("record", Field::Node(record)), ("field", text(field))]),
+        Ast::Cast { child, typename } => ("Cast", vec![("type", text(typename)), ("child", Field::Node(child))]),
+        Ast::NewArray { typename, size } => ("NewArray", vec![("type", text(typename)), ("size", Field::Node(size))]),
+        Ast::MapValue(entries) => ("MapValue", vec![("entries", Field::Entries(entries))]),
+        Ast::Swap { left, right, .. } => ("Swap", vec![("left", Field::Node(left)), ("right", Field::Node(right))]),
+        Ast::Write { values, .. } => ("Write", vec![("values", Field::Nodes(values))]),
+        Ast::Try { children, variable, handler, .. } => ("Try", vec![
+            ("children", Field::Nodes(children)),
+            ("variable", text(variable)),
+            ("handler", Field::Nodes(handler)),
+        ]),
+        Ast::Throw { value, .. } => ("Throw", vec![("value", Field::Node(value))]),
+        Ast::Match { value, cases, default, .. } => ("Match", vec![
+            ("value", Field::Node(value)),
+            ("cases", Field::Cases(cases)),
+            ("default", match default {
+                Some(default) => Field::Nodes(default),
+                None => Field::Missing,
+            }),
+        ]),
+        Ast::Exit { code, .. } => ("Exit", vec![("code", Field::Node(code))]),
+        Ast::Pragma { name, value, .. } => ("Pragma", vec![("name", text(name)), ("value", text(value))]),
+    };
+}
+
+fn json_nodes(nodes: &[Ast]) -> String {
+    return format!("[{}]", nodes.iter().map(to_json).collect::<Vec<String>>().join(", "));
+}
+
+/// JSON object of the node, `{"kind": "Assignement", "line": 1, "column": 1, ...}` along with its fields,
+/// only the statements being located.
+pub fn to_json(ast: &Ast) -> String {
+    let (kind, fields) = describe(ast);
+    let mut members = vec![format!("\"kind\": {}", json::string(kind))];
+    if let Some(span) = ast.span() {
+        members.push(format!("\"line\": {}, \"column\": {}", span.line, span.column));
+    }
+    for (name, field) in fields {
+        let value = match field {
+            Field::Text(value) => json::string(&value),
+            Field::Literal(value) => value,
+            Field::Node(node) => to_json(node),
+            Field::Nodes(nodes) => json_nodes(nodes),
+            Field::Variables(variables) => format!("[{}]", variables.iter().map(|var| json::string(&variable(var))).collect::<Vec<String>>().join(", ")),
+            Field::Cases(cases) => {
+                let cases = cases
+                    .iter()
+                    .map(|(values, children)| format!("{{\"values\": {}, \"children\": {}}}", json_nodes(values), json_nodes(children)))
+                    .collect::<Vec<String>>();
+                format!("[{}]", cases.join(", "))
+            },
+            Field::Entries(entries) => {
+                let entries = entries
+                    .iter()
+                    .map(|(key, value)| format!("{{\"key\": {}, \"value\": {}}}", to_json(key), to_json(value)))
+                    .collect::<Vec<String>>();
+                format!("[{}]", entries.join(", "))
+            },
+            Field::Missing => String::from("null"),
+        };
+        members.push(format!("{}: {}", json::string(name), value));
+    }
+    return format!("{{{}}}", members.join(", "));
+}
+
+/// `value` escaped to be written between the quotes of a DOT string.
+fn quote(value: &str) -> String {
+    return value.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+/// Nodes and edges of the graph, each node being numbered in the order it is written.
+struct Graph {
+    lines: Vec<String>,
+    count: usize,
+}
+
+impl Graph {
+    /// Writes the node and its descendants, returning its number.
+    fn node(&mut self, ast: &Ast) -> usize {
+        let id = self.count;
+        self.count += 1;
+        let (kind, fields) = describe(ast);
+        let mut label = vec![String::from(kind)];
+        if let Some(span) = ast.span() {
+            label[0].push_str(format!(" ({})", span).as_str());
+        }
+        let index = self.lines.len();
+        self.lines.push(String::new());
+        for (name, field) in fields {
+            match field {
+                Field::Text(value) | Field::Literal(value) => label.push(format!("{}: {}", name, value)),
+                Field::Variables(variables) => {
+                    let variables = variables.iter().map(variable).collect::<Vec<String>>();
+                    label.push(format!("{}: {}", name, variables.join(", ")));
+                },
+                Field::Node(node) => self.edge(id, node, name),
+                Field::Nodes(nodes) => {
+                    for (i, node) in nodes.iter().enumerate() {
+                        self.edge(id, node, format!("{} {}", name, i).as_str());
+                    }
+                },
+                Field::Cases(cases) => {
+                    for (i, (values, children)) in cases.iter().enumerate() {
+                        for value in values {
+                            self.edge(id, value, format!("case {}", i).as_str());
+                        }
+                        for (j, child) in children.iter().enumerate() {
+                            self.edge(id, child, format!("case {} {}", i, j).as_str());
+                        }
+                    }
+                },
+                Field::Entries(entries) => {
+                    for (i, (key, value)) in entries.iter().enumerate() {
+                        self.edge(id, key, format!("key {}", i).as_str());
+                        self.edge(id, value, format!("value {}", i).as_str());
+                    }
+                },
+                Field::Missing => (),
+            };
+        }
+        let label = label.iter().map(|line| quote(line)).collect::<Vec<String>>().join("\\n");
+        self.lines[index] = format!("    n{} [label=\"{}\"];", id, label);
+        return id;
+    }
+
+    fn edge(&mut self, parent: usize, child: &Ast, label: &str) {
+        let child = self.node(child);
+        self.lines.push(format!("    n{} -> n{} [label=\"{}\"];", parent, child, quote(label)));
+    }
+}
+
+/// Graphviz graph of the tree, each node labelled with its kind and the fields which are not nodes.
+pub fn to_dot(ast: &Ast) -> String {
+    let mut graph = Graph { lines: Vec::new(), count: 0 };
+    graph.node(ast);
+    return format!("digraph ast {{\n    node [shape=box];\n{}\n}}\n", graph.lines.join("\n"));
+}
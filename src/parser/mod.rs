@@ -1,21 +1,54 @@
-use std::{slice::Iter, iter::Peekable };
-
-use super::lexer::TokenType;
+use super::diagnostic::{Code, Diagnostic};
+use super::error::ParseError;
+use super::lexer::{TokenType, Span};
+use super::timings::{self, Phase};
 mod types;
 pub use types::{Ast, Variable, Type};
+use types::TokenStream;
 
 mod utils;
 use utils::get_operator_precedency;
 
-pub fn load_ast(tokens: &Vec<TokenType>) -> Result<Ast, String> {
+mod query;
+pub use query::NodeRef;
+
+mod dump;
+pub use dump::{to_dot, to_json};
+
+mod print;
+pub use print::to_source;
+
+pub mod testing;
+
+mod serialize;
+pub use serialize::{from_bytes, to_bytes, to_canonical_bytes};
+
+pub fn load_ast(tokens: &[TokenType]) -> Result<Ast, Diagnostic> {
+    return load_ast_with_spans(tokens, &[]);
+}
+
+/// Builds the ast, locating statements with the `spans` returned by `lexer::tokenize_with_spans`.
+/// A syntax error is located at the start of the top level statement containing it.
+pub fn load_ast_with_spans(tokens: &[TokenType], spans: &[Span]) -> Result<Ast, Diagnostic> {
+    return timings::time(Phase::Parsing, || parse_tokens(tokens, spans));
+}
+
+fn parse_tokens(tokens: &[TokenType], spans: &[Span]) -> Result<Ast, Diagnostic> {
 
-    let mut token_iter = tokens.iter().peekable();
+    let mut token_iter = TokenStream::new(tokens, spans);
     let mut children = Vec::<Ast>::new();
-    while let Some(_) = token_iter.peek() {
+    while let Some(&token) = token_iter.peek() {
+        let span = token_iter.span();
+        // pragmas are only read at the top level.
+        if let TokenType::Pragma(name, value) = token {
+            children.push(Ast::Pragma { name: name.clone(), value: value.clone(), span });
+            token_iter.next();
+            continue;
+        }
         if let Some(child) = build_ast(&mut token_iter) {
             match child {
-                Err(e) => return Err(e),
-                Ok(child) => children.push(child),
+                Err(e) => return Err(Diagnostic::error(Code::Syntax, e, span)),
+                Ok(child) => push_statement(&mut children, child),
             };
         }
     }
@@ -23,7 +56,9 @@ pub fn load_ast(tokens: &Vec<TokenType>) -> Result<Ast, String> {
     return Ok(Ast::Global(children));
 }
 
-fn build_conditional_ast(tokens: &mut Peekable<Iter<TokenType>>, nested_if: bool) -> Result<Ast, String> {
+// the else branch is read until its `end`, which breaks out of the loop.
+#[allow(clippy::while_immutable_condition)]
+fn build_conditional_ast(tokens: &mut TokenStream, nested_if: bool, span: Span) -> Result<Ast, String> {
 
     let condition = Box::new(match build_expression_ast(tokens) {
         Err(e) => return Err(e),
@@ -54,11 +89,9 @@ fn build_conditional_ast(tokens: &mut Peekable<Iter<TokenType>>, nested_if: bool
             _ => {
                 match build_ast(tokens) {
                     None => (),
-                    Some(result) => {
-                        valid_branch_children.push(match result {
-                            Ok(child) => child,
-                            Err(e) => return Err(e),
-                        });
+                    Some(result) => match result {
+                        Ok(child) => push_statement(&mut valid_branch_children, child),
+                        Err(e) => return Err(e),
                     },
                 };
             }
@@ -83,20 +116,20 @@ fn build_conditional_ast(tokens: &mut Peekable<Iter<TokenType>>, nested_if: bool
                 tokens.next();
             },
             TokenType::Keyword(val) if val == "if" => {
+                let span = tokens.span();
                 tokens.next();
-                invalid_branch_children.push(match build_conditional_ast(tokens, true) {
+                invalid_branch_children.push(match build_conditional_ast(tokens, true, span) {
                     Ok(child) => child,
                     Err(e) => return Err(e),
                 });
             }
             _ => {
-                match build_ast(tokens) {
-                    Some(result) => invalid_branch_children.push(match result {
-                        Ok(child) => child,
+                if let Some(result) = build_ast(tokens) {
+                    match result {
+                        Ok(child) => push_statement(&mut invalid_branch_children, child),
                         Err(e) => return Err(e),
-                    }),
-                    None => (),
-                };
+                    };
+                }
             }
         }
     }
@@ -105,10 +138,11 @@ fn build_conditional_ast(tokens: &mut Peekable<Iter<TokenType>>, nested_if: bool
         condition,
         valid_branch: valid_branch_children,
         invalid_branch: invalid_branch_children,
+        span,
     });
 }
 
-fn create_binary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>) -> Result<(), String> {
+fn create_binary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>, span: Span) -> Result<(), String> {
     if output_stack.len() < 2 {
         return Err(format!("invalid expression in create_binary_operator_ast, missing value for operator {}", operator_str));
     }
@@ -122,8 +156,8 @@ fn create_binary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>) -
         "*" => Ast::Multiplication { left, right },
         "/" => Ast::Division { left, right },
         "<-" => match *left {
-                Ast::Variable(..) | Ast::ArrayAccess { .. } => Ast::Assignement { variable: left, expression: right },
-                _ => return Err(format!("parser: can only assign value to variable")),
+                Ast::Variable(..) | Ast::ArrayAccess { .. } | Ast::MemberAccess { .. } => Ast::Assignement { variable: left, expression: right, span },
+                _ => return Err(String::from("parser: can only assign value to variable")),
         },
         "%" => Ast::Modulo { left, right },
         "==" => Ast::EqualTo { left, right },
@@ -132,6 +166,8 @@ fn create_binary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>) -
         "<" => Ast::LowerThan { left, right },
         "<=" => Ast::LowerOrEqual { left, right },
         ">=" => Ast::GreaterOrEqual { left, right },
+        "and" => Ast::And { left, right },
+        "or" => Ast::Or { left, right },
         op => return Err(format!("parser: missing implementation for operator '{}'", op)),
     });
 
@@ -140,25 +176,19 @@ fn create_binary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>) -
 
 fn create_function_ast(function_name: &str, output_stack: &mut Vec<Ast>) -> Result<(), String> {
     let mut children = Vec::<Ast>::new();
-    loop {
-        let child = match output_stack.pop() {
-            Some(c) => c,
-            None => {
-                break;
-            },
-        };
-
+    while let Some(child) = output_stack.pop() {
         match child {
-            Ast::FunctionCall { name: _name, children: _children } => {
+            Ast::FunctionCall { span, .. } => {
                 children.reverse();
                 output_stack.push(Ast::FunctionCall {
                     name: function_name.to_string(),
-                    children: children.clone(),
+                    children,
+                    span,
                 });
                 return Ok(());
             },
             val => {
-                children.push(val.clone());
+                children.push(val);
             },
         };
     }
@@ -174,7 +204,7 @@ fn create_unary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>) ->
         "+" => Ast::UnaryPlus {
             child: el1,
         },
-        "-" | _ => Ast::UnaryMinus {
+        _ => Ast::UnaryMinus {
             child: el1,
         },
     });
@@ -182,18 +212,18 @@ fn create_unary_operator_ast(operator_str: &str, output_stack: &mut Vec<Ast>) ->
     return Ok(());
 }
 
-fn parse_function_header(tokens: &mut Peekable<Iter<TokenType>>) -> Result<(String, Vec<Variable>, Option<String>), String> {
-    let name: String;
+fn parse_function_header(tokens: &mut TokenStream) -> Result<(String, Vec<Variable>, Option<String>), String> {
+    
     let mut params = Vec::<Variable>::new();
-    let return_type: Option<String>;
+    
 
     let token = match tokens.next() {
         Some(token) => token,
         None => return Err(String::from("missing name for function")),
     };
 
-    match token {
-        TokenType::Variable(func_name) => name = func_name.clone(),
+    let name: String = match token {
+        TokenType::Variable(func_name) => func_name.clone(),
         _ => return Err(format!("invalid token {} for function name", token)),
     };
 
@@ -232,6 +262,7 @@ fn parse_function_header(tokens: &mut Peekable<Iter<TokenType>>) -> Result<(Stri
 
     match token {
         TokenType::EndLine => return Ok((name, params, None)),
+        TokenType::Keyword(val) if val == "as" => return Ok((name, params, None)),
         TokenType::Colon => {
             tokens.next();
         },
@@ -243,55 +274,106 @@ fn parse_function_header(tokens: &mut Peekable<Iter<TokenType>>) -> Result<(Stri
         Some(token) => token,
     };
 
-    return_type = Some(match token {
+    let return_type: Option<String> = Some(match token {
+        TokenType::TypeDef(return_type) if return_type == "map" && matches!(tokens.peek(), Some(TokenType::OpeningBracket)) => match parse_map_type(tokens) {
+            Ok(val) => val,
+            Err(e) => return Err(e),
+        },
         TokenType::TypeDef(return_type) => return_type.clone(),
         _ => return Err(format!("unexpected token {} in function declaration '{}', expected TypeDef", token, name)),
     });
 
-    let token = match tokens.next() {
+    let token = match tokens.peek() {
         None => return Err(format!("parser: unexpected end of document in function declaration '{}'", name)),
         Some(token) => token,
     };
 
     return match token {
-        TokenType::EndLine => Ok((name, params, return_type)),
+        TokenType::EndLine => {
+            tokens.next();
+            Ok((name, params, return_type))
+        },
+        TokenType::Keyword(val) if val == "as" => Ok((name, params, return_type)),
         _ => Err(format!("parser: expected end of line, got {} in function declaration '{}'", token, name)),
     };
 }
 
-fn build_return_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
-    match tokens.peek() {
-        None => return Ok(Ast::ReturnStatement(None)),
-        Some(_) => (),
+fn build_return_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    if tokens.peek().is_none() { return Ok(Ast::ReturnStatement { value: None, span }) };
+
+    return match build_expression_ast(tokens) {
+        Err(e) => Err(e),
+        Ok(ast) => return Ok(Ast::ReturnStatement { value: Some(Box::new(ast)), span }),
     };
+}
 
+/// Parses `const N <- 4 * 2`, the `const` keyword being already consumed.
+fn build_const_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
     return match build_expression_ast(tokens) {
         Err(e) => Err(e),
-        Ok(ast) => return Ok(Ast::ReturnStatement(Some(Box::new(ast)))),
+        Ok(Ast::Assignement { variable, expression, .. }) => match *variable {
+            Ast::Variable(Variable { name, typename: None }) => Ok(Ast::ConstDeclaration { name, value: expression, span }),
+            variable => Err(format!("parser: invalid constant name {:?}", variable)),
+        },
+        Ok(ast) => Err(format!("parser: expected 'const NAME <- value', got {:?}", ast)),
     };
 }
 
-fn build_declaration_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
+fn build_declaration_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
     let token = match tokens.next() {
-        None => return Err(format!("unexpected end of document after declare keyword")),
+        None => return Err(String::from("unexpected end of document after declare keyword")),
         Some(val) => val,
     };
 
     return match token {
-        TokenType::Keyword(val) if val == "function" => build_function_declaration_ast(tokens),
+        TokenType::Keyword(val) if val == "function" => build_function_declaration_ast(tokens, span),
         val => Err(format!("unexpected token {}, after declare keyword", val)),
     };
 }
 
-fn build_function_declaration_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
+fn build_function_declaration_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
     let (name, parameters, return_type) = match parse_function_header(tokens) {
         Ok(v) => v,
         Err(e) => return Err(e),
     };
-    return Ok(Ast::FunctionHeader { name, parameters, return_type });
+
+    // `as "symbol"` links the declaration to an external symbol, without name mangling.
+    let symbol = match tokens.peek() {
+        Some(TokenType::Keyword(val)) if val == "as" => {
+            tokens.next();
+            match tokens.next() {
+                Some(TokenType::String(symbol)) if !symbol.is_empty() => Some(symbol.clone()),
+                Some(token) => return Err(format!("parser: expected a symbol name after 'as', got {} in function declaration '{}'", token, name)),
+                None => return Err(format!("parser: unexpected end of document in function declaration '{}'", name)),
+            }
+        },
+        _ => None,
+    };
+
+    return Ok(Ast::FunctionHeader { name, parameters, return_type, symbol, span });
+}
+
+/// `no_mangle` before a function or a declaration keeps the function name as its symbol.
+fn build_no_mangle_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let ast = match tokens.next() {
+        Some(TokenType::Keyword(val)) if val == "function" => build_function_ast(tokens, span),
+        Some(TokenType::Keyword(val)) if val == "declare" => build_declaration_ast(tokens, span),
+        Some(token) => return Err(format!("unexpected token {}, after no_mangle keyword", token)),
+        None => return Err(String::from("unexpected end of document after no_mangle keyword")),
+    };
+
+    return match ast {
+        Ok(Ast::FunctionDeclaration { name, children, parameters, return_type, span, .. })
+            => Ok(Ast::FunctionDeclaration { symbol: Some(name.clone()), name, children, parameters, return_type, span }),
+        Ok(Ast::FunctionHeader { symbol: Some(..), name, .. })
+            => Err(format!("function '{}' cannot be both no_mangle and bound to a symbol", name)),
+        Ok(Ast::FunctionHeader { name, parameters, return_type, span, .. })
+            => Ok(Ast::FunctionHeader { symbol: Some(name.clone()), name, parameters, return_type, span }),
+        val => val,
+    };
 }
 
-fn build_function_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
+fn build_function_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
 
 
     let (name, parameters, return_type) = match parse_function_header(tokens) {
@@ -312,15 +394,10 @@ fn build_function_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, Str
                 break;
             },
             _ => {
-                match build_ast(tokens) {
-                    Some(ast) => {
-                        children.push(match ast {
-                            Ok(ast) => ast,
-                            Err(e) => return Err(e),
-                        });
-                    },
-                    None => (),
-                };
+                if let Some(ast) = build_ast(tokens) { match ast {
+                    Ok(ast) => push_statement(&mut children, ast),
+                    Err(e) => return Err(e),
+                } };
             },
         };
     };
@@ -330,19 +407,41 @@ fn build_function_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, Str
         children ,
         parameters,
         return_type,
+        symbol: None,
+        span,
     });
 }
 
-fn parse_variable(tokens: &mut Peekable<Iter<TokenType>>, require_type: bool) -> Result<Variable, String> {
+/// Parses the `[key, value]` following `map`, returning the name of the type, `map[key, value]`.
+fn parse_map_type(tokens: &mut TokenStream) -> Result<String, String> {
+    let mut names = Vec::<String>::new();
+    for expected in ["[", ",", "]"] {
+        match tokens.next() {
+            Some(TokenType::OpeningBracket) if expected == "[" => (),
+            Some(TokenType::Comma) if expected == "," => (),
+            Some(TokenType::ClosingBracket) if expected == "]" => break,
+            Some(token) => return Err(format!("parser: expected '{}', got {} in map type", expected, token)),
+            None => return Err(String::from("parser: unexpected end of document in map type")),
+        };
+        names.push(match tokens.next() {
+            Some(TokenType::TypeDef(name) | TokenType::Variable(name)) => name.clone(),
+            Some(token) => return Err(format!("parser: expected a type, got {} in map type", token)),
+            None => return Err(String::from("parser: unexpected end of document in map type")),
+        });
+    }
+    return Ok(format!("map[{}]", names.join(", ")));
+}
+
+fn parse_variable(tokens: &mut TokenStream, require_type: bool) -> Result<Variable, String> {
     let mut token = match tokens.next() {
         None => return Err(String::from("missing token for variable")),
         Some(val) => val,
     };
 
-    let var_name: String;
+    
 
-    match token {
-        TokenType::Variable(name) => var_name = name.to_string(),
+    let var_name: String = match token {
+        TokenType::Variable(name) => name.to_string(),
         _ => return Err(format!("parser: invalid token {} for variable declaration.", token)),
     };
 
@@ -362,25 +461,73 @@ fn parse_variable(tokens: &mut Peekable<Iter<TokenType>>, require_type: bool) ->
         Some(token) => token,
     };
 
-    let var_type: Type;
-    match token {
-        TokenType::TypeDef(name) => var_type = Type {
+    let var_type = match token {
+        TokenType::TypeDef(name) if name == "map" && matches!(tokens.peek(), Some(TokenType::OpeningBracket)) => Type {
+            name: match parse_map_type(tokens) {
+                Ok(val) => val,
+                Err(e) => return Err(e),
+            },
+            is_array: false,
+            size: None,
+            size_expression: None,
+        },
+        TokenType::TypeDef(name) => Type {
             name: name.clone(),
             is_array: false,
+            size: None,
+            size_expression: None,
         },
-        TokenType::ArrayTypeDef(name) => var_type = Type{
+        TokenType::ArrayTypeDef(name) => Type{
             name: name.clone(),
             is_array: true,
+            size: None,
+            size_expression: None,
         },
         _ => return Err(format!("parser: invalid type token {} for variable '{}'", token, var_name)),
     };
 
+    if let (false, Some(TokenType::OpeningBracket)) = (var_type.is_array, tokens.peek()) {
+        let span = tokens.span();
+        tokens.next();
+        // the size spans until the matching bracket, `int[N * 2]` being evaluated by the compiler.
+        let mut buffer = Vec::<&TokenType>::new();
+        let mut depth = 0;
+        loop {
+            match tokens.next() {
+                Some(TokenType::ClosingBracket) if depth == 0 => break,
+                Some(TokenType::EndLine) | None => return Err(format!("parser: missing ']' for variable '{}'", var_name)),
+                Some(token) => {
+                    match token {
+                        TokenType::OpeningBracket => depth += 1,
+                        TokenType::ClosingBracket => depth -= 1,
+                        _ => (),
+                    };
+                    buffer.push(token);
+                },
+            };
+        }
+        if buffer.is_empty() {
+            return Err(format!("parser: missing array size for variable '{}'", var_name));
+        }
+        let (size, size_expression) = match build_expression_ast(&mut tokens.nested(&buffer, span)) {
+            Err(e) => return Err(e),
+            Ok(Ast::Int(val)) if val >= 0 => (Some(val as u64), None),
+            Ok(Ast::Int(val)) => return Err(format!("parser: invalid array size {} for variable '{}'", val, var_name)),
+            Ok(expression) => (None, Some(Box::new(expression))),
+        };
+        return Ok(Variable {
+            name: var_name,
+            typename: Some(Type { name: var_type.name, is_array: true, size, size_expression }),
+        });
+    }
+
     return Ok(Variable { name: var_name, typename: Some(var_type) });
 }
 
-fn build_array_value_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
+fn build_array_value_ast(tokens: &mut TokenStream) -> Result<Ast, String> {
 
-    let mut buffer = Vec::<TokenType>::new();
+    let span = tokens.span();
+    let mut buffer = Vec::<&TokenType>::new();
     let mut result = Vec::<Ast>::new();
 
     loop {
@@ -392,8 +539,7 @@ fn build_array_value_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast,
         match *token {
             TokenType::Comma => {
                 tokens.next();
-                buffer.push(TokenType::EndLine);
-                match build_expression_ast(&mut buffer.iter().peekable()) {
+                match build_expression_ast(&mut tokens.nested(&buffer, span)) {
                     Ok(child) => result.push(child),
                     Err(e) => return Err(e),
                 };
@@ -401,8 +547,7 @@ fn build_array_value_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast,
             },
             TokenType::ClosingBracket => {
                 tokens.next();
-                buffer.push(TokenType::EndLine);
-                match build_expression_ast(&mut buffer.iter().peekable()) {
+                match build_expression_ast(&mut tokens.nested(&buffer, span)) {
                     Ok(child) => result.push(child),
                     Err(e) => return Err(e),
                 };
@@ -411,7 +556,7 @@ fn build_array_value_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast,
             TokenType::EndLine => return Err(format!("parser: unexpected token {} while parsing array value.", TokenType::EndLine)),
             val => {
                 tokens.next();
-                buffer.push(val.clone());
+                buffer.push(val);
             },
         };
     };
@@ -419,37 +564,222 @@ fn build_array_value_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast,
     return Ok(Ast::ArrayValue(result));
 }
 
-fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
+/// Parses the entries of `{"a": 1, "b": 2}` until the closing brace, the opening one being already consumed.
+fn build_map_value_ast(tokens: &mut TokenStream) -> Result<Ast, String> {
+    let span = tokens.span();
+    let mut entries = Vec::<(Ast, Ast)>::new();
+    let mut key: Option<Ast> = None;
+    let mut buffer = Vec::<&TokenType>::new();
+    let mut depth = 0;
+    loop {
+        let token = match tokens.next() {
+            Some(TokenType::EndLine) | None => return Err(String::from("parser: missing '}' at the end of the map value")),
+            Some(token) => token,
+        };
+        match token {
+            TokenType::Colon | TokenType::Comma | TokenType::ClosingBrace if depth == 0 => (),
+            token => {
+                match token {
+                    TokenType::OpeningParenthesis | TokenType::OpeningBracket | TokenType::OpeningBrace => depth += 1,
+                    TokenType::ClosingParenthesis | TokenType::ClosingBracket | TokenType::ClosingBrace => depth -= 1,
+                    _ => (),
+                };
+                buffer.push(token);
+                continue;
+            },
+        };
+        let closing = matches!(token, TokenType::ClosingBrace);
+        if closing && buffer.is_empty() && key.is_none() && entries.is_empty() {
+            break;
+        }
+        if buffer.is_empty() {
+            return Err(format!("parser: unexpected token {} in map value", token));
+        }
+        let expression = match build_expression_ast(&mut tokens.nested(&buffer, span)) {
+            Ok(val) => val,
+            Err(e) => return Err(e),
+        };
+        buffer.clear();
+        match (token, key.take()) {
+            (TokenType::Colon, None) => key = Some(expression),
+            (TokenType::Comma | TokenType::ClosingBrace, Some(key)) => entries.push((key, expression)),
+            (token, _) => return Err(format!("parser: unexpected token {} in map value, expected 'key: value'", token)),
+        };
+        if closing {
+            break;
+        }
+    }
+    return Ok(Ast::MapValue(entries));
+}
+
+/// Parses `new int[n]`, the `new` keyword being already consumed.
+fn build_new_array_ast(tokens: &mut TokenStream) -> Result<Ast, String> {
+    let typename = match tokens.next() {
+        Some(TokenType::TypeDef(name)) => name.clone(),
+        Some(TokenType::ArrayTypeDef(name)) => return Err(format!("parser: missing size for new {}[]", name)),
+        Some(token) => return Err(format!("parser: expected a type, got {} after new keyword", token)),
+        None => return Err(String::from("parser: unexpected end of document after new keyword")),
+    };
+    let span = tokens.span();
+    match tokens.next() {
+        Some(TokenType::OpeningBracket) => (),
+        Some(token) => return Err(format!("parser: expected '[', got {} after new {}", token, typename)),
+        None => return Err(format!("parser: unexpected end of document after new {}", typename)),
+    };
+
+    let mut buffer = Vec::<&TokenType>::new();
+    let mut depth = 0;
+    loop {
+        match tokens.next() {
+            Some(TokenType::ClosingBracket) if depth == 0 => break,
+            Some(TokenType::EndLine) | None => return Err(format!("parser: missing ']' after new {}", typename)),
+            Some(token) => {
+                match token {
+                    TokenType::OpeningBracket => depth += 1,
+                    TokenType::ClosingBracket => depth -= 1,
+                    _ => (),
+                };
+                buffer.push(token);
+            },
+        };
+    }
+    return match build_expression_ast(&mut tokens.nested(&buffer, span)) {
+        Err(e) => Err(e),
+        Ok(size) => Ok(Ast::NewArray { typename, size: Box::new(size) }),
+    };
+}
+
+/// Parses `fn(x: int): int => x * 2`, the `fn` keyword being already consumed.
+/// The body spans until the end of the enclosing expression (end of line, or `,`/`)` closing the call it is an argument of).
+fn build_lambda_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    match tokens.next() {
+        Some(TokenType::OpeningParenthesis) => (),
+        Some(token) => return Err(format!("parser: expected '(', got {} after fn keyword", token)),
+        None => return Err(String::from("parser: unexpected end of document after fn keyword")),
+    };
 
+    let mut parameters = Vec::<Variable>::new();
+    while let Some(token) = tokens.peek() {
+        match token {
+            TokenType::ClosingParenthesis => {
+                tokens.next();
+                break;
+            },
+            TokenType::Comma => {
+                tokens.next();
+            },
+            _ => {
+                parameters.push(match parse_variable(tokens, true) {
+                    Ok(val) => val,
+                    Err(e) => return Err(e),
+                });
+            },
+        }
+    }
+
+    let mut return_type: Option<String> = None;
+    if let Some(TokenType::Colon) = tokens.peek() {
+        tokens.next();
+        return_type = match tokens.next() {
+            Some(TokenType::TypeDef(val)) => Some(val.clone()),
+            Some(token) => return Err(format!("parser: unexpected token {} for lambda return type, expected TypeDef", token)),
+            None => return Err(String::from("parser: missing lambda return type")),
+        };
+    }
+
+    match tokens.next() {
+        Some(TokenType::BinaryOperator(val)) if val == "=>" => (),
+        Some(token) => return Err(format!("parser: expected '=>', got {} in lambda", token)),
+        None => return Err(String::from("parser: unexpected end of document in lambda")),
+    };
+
+    let body_span = tokens.span();
+    let mut buffer = Vec::<&TokenType>::new();
+    let mut depth = 0;
+    while let Some(token) = tokens.peek() {
+        match token {
+            TokenType::EndLine => break,
+            TokenType::Comma | TokenType::ClosingParenthesis | TokenType::ClosingBracket if depth == 0 => break,
+            TokenType::OpeningParenthesis | TokenType::OpeningBracket => depth += 1,
+            TokenType::ClosingParenthesis | TokenType::ClosingBracket => depth -= 1,
+            _ => (),
+        };
+        buffer.push(*token);
+        tokens.next();
+    }
+
+    if buffer.is_empty() {
+        return Err(String::from("parser: missing lambda body"));
+    }
+
+    let body = match build_expression_ast(&mut tokens.nested(&buffer, body_span)) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    return Ok(Ast::Lambda { parameters, return_type, body: Box::new(body), span });
+}
+
+/// Operators and members of an expression past which the parser gives up, each of them nesting the nodes built before it.
+const MAX_OPERATORS: usize = 256;
+
+/// Builds the expression ending at the next end of line, nested in the expression or the block being parsed.
+fn build_expression_ast(tokens: &mut TokenStream) -> Result<Ast, String> {
+    if let Err(e) = tokens.enter() {
+        return Err(e);
+    }
+    let res = build_operations_ast(tokens);
+    tokens.leave();
+    return res;
+}
+
+/// Builds the expression ending at the next end of line with the shunting yard algorithm, the operator stack holding
+/// the tokens of the stream rather than copies of them.
+fn build_operations_ast<'a>(tokens: &mut TokenStream<'a>) -> Result<Ast, String> {
+
+    let span = tokens.span();
     let mut output_stack = Vec::<Ast>::new();
-    let mut operator_stack = Vec::<TokenType>::new();
+    let mut operator_stack = Vec::<&'a TokenType>::new();
+    let mut last_is_variable = false;
+    let mut operators = 0;
 
     loop {
-        let token = match tokens.peek_mut() {
+        let token: &'a TokenType = match tokens.peek() {
             Some(token) => token,
             None => {
-                return Err(format!("missing token"));
+                return Err(String::from("missing token"));
             },
         };
 
+        if let TokenType::UnaryOperator(_) | TokenType::BinaryOperator(_) | TokenType::Member(_) | TokenType::FunctionCall(_) = token {
+            operators += 1;
+            if operators > MAX_OPERATORS {
+                return Err(format!("parser: more than {} operators in an expression", MAX_OPERATORS));
+            }
+        }
+
+        // `a[i]` is an array access only when the bracket directly follows the variable.
+        let previous_is_variable = last_is_variable;
+        last_is_variable = matches!(token, TokenType::Variable(_));
+
         match token {
             TokenType::Bool(val) => {
-                output_stack.push(Ast::Bool(val.clone()));
+                output_stack.push(Ast::Bool(*val));
                 tokens.next();
             },
             TokenType::Int(val) => {
-                output_stack.push(Ast::Int(val.clone()));
+                output_stack.push(Ast::Int(*val));
                 tokens.next();
             },
             TokenType::Float(val) => {
-                output_stack.push(Ast::Float(val.clone()));
+                output_stack.push(Ast::Float(*val));
                 tokens.next();
             },
             TokenType::String(val) => {
                 output_stack.push(Ast::Str(val.clone()));
                 tokens.next();
             },
-            TokenType::Variable(_) if operator_stack.len() == 0 => {
+            TokenType::Variable(_) if operator_stack.is_empty() => {
                 output_stack.push(match parse_variable(tokens, false) {
                     Ok(var) => Ast::Variable(var),
                     Err(e) => return Err(e),
@@ -459,16 +789,25 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                 output_stack.push(Ast::Variable(Variable { name: name.clone(), typename: None }));
                 tokens.next();
             },
+            TokenType::Member(field) => {
+                let record = match output_stack.pop() {
+                    Some(record @ (Ast::Variable(Variable { typename: None, .. }) | Ast::MemberAccess { .. })) => record,
+                    _ => return Err(format!("parser: unexpected member access '.{}'", field)),
+                };
+                output_stack.push(Ast::MemberAccess { record: Box::new(record), field: field.clone() });
+                tokens.next();
+            },
             TokenType::FunctionCall(val) => {
-                operator_stack.push(token.clone());
+                operator_stack.push(token);
                 output_stack.push(Ast::FunctionCall {
                     name: val.clone(),
                     children: Vec::new(),
+                    span: tokens.span(),
                 });
                 tokens.next();
             },
             TokenType::UnaryOperator(_) | TokenType::BinaryOperator(_) => {
-                let precedency = get_operator_precedency(&token.clone());
+                let precedency = get_operator_precedency(token);
                 loop {
                     let operator = match operator_stack.last() {
                         None => {
@@ -477,14 +816,14 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                         Some(operator) => operator,
                     };
 
-                    match operator.clone() {
-                        TokenType::BinaryOperator(val) if get_operator_precedency(&operator) >= precedency => {
+                    match operator {
+                        TokenType::BinaryOperator(val) if get_operator_precedency(operator) >= precedency => {
                             operator_stack.pop();
-                            if let Err(e) = create_binary_operator_ast(val.as_str(), &mut output_stack) {
+                            if let Err(e) = create_binary_operator_ast(val.as_str(), &mut output_stack, span) {
                                 return Err(e);
                             }
                         },
-                        TokenType::UnaryOperator(val) if get_operator_precedency(&operator) > precedency => {
+                        TokenType::UnaryOperator(val) if get_operator_precedency(operator) > precedency => {
                             operator_stack.pop();
                             if let Err(e) = create_unary_operator_ast(val.as_str(), &mut output_stack) {
                                 return Err(e);
@@ -495,7 +834,7 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                         },
                     };
                 }
-                operator_stack.push(token.clone());
+                operator_stack.push(token);
                 tokens.next();
             },
             TokenType::Comma => {
@@ -506,7 +845,7 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                     };
                     match operator {
                         TokenType::BinaryOperator(val) => {
-                            if let Err(e) = create_binary_operator_ast(val.as_str(), &mut output_stack) {
+                            if let Err(e) = create_binary_operator_ast(val.as_str(), &mut output_stack, span) {
                                 return Err(e);
                             }
                             operator_stack.pop();
@@ -517,7 +856,7 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                             }
                             operator_stack.pop();
                         },
-                        TokenType::OpeningParenthesis | _ => {
+                        _ => {
                             break;
                         }
                     }
@@ -525,7 +864,7 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                 tokens.next();
             },
             TokenType::OpeningParenthesis => {
-                operator_stack.push(token.clone());
+                operator_stack.push(token);
                 tokens.next();
             },
             TokenType::ClosingParenthesis => {
@@ -542,23 +881,21 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                             }
                         },
                         TokenType::BinaryOperator(val) => {
-                            if let Err(e) = create_binary_operator_ast(val.as_str(), &mut output_stack) {
+                            if let Err(e) = create_binary_operator_ast(val.as_str(), &mut output_stack, span) {
                                 return Err(e);
                             }
                         },
-                        TokenType::OpeningParenthesis | _ => {
+                        _ => {
                             break;
                         },
                     };
                 };
 
-                if let Some(last_token) = operator_stack.last_mut() {
-                    if let TokenType::FunctionCall(func_call) = last_token {
-                        if let Err(e) = create_function_ast(func_call.as_str(), &mut output_stack) {
-                            return Err(e);
-                        }
-                        operator_stack.pop();
+                if let Some(TokenType::FunctionCall(func_call)) = operator_stack.last() {
+                    if let Err(e) = create_function_ast(func_call.as_str(), &mut output_stack) {
+                        return Err(e);
                     }
+                    operator_stack.pop();
                 }
                 tokens.next();
             },
@@ -566,43 +903,50 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                 tokens.next();
                 break;
             },
-            TokenType::OpeningBracket => {
+            TokenType::Keyword(val) if val == "new" => {
                 tokens.next();
-                let array_token = match build_array_value_ast(tokens) {
+                output_stack.push(match build_new_array_ast(tokens) {
                     Ok(val) => val,
                     Err(e) => return Err(e),
+                });
+            },
+            TokenType::Keyword(val) if val == "fn" => {
+                let lambda_span = tokens.span();
+                tokens.next();
+                output_stack.push(match build_lambda_ast(tokens, lambda_span) {
+                    Ok(val) => val,
+                    Err(e) => return Err(e),
+                });
+            },
+            TokenType::OpeningBrace => {
+                tokens.next();
+                output_stack.push(match build_map_value_ast(tokens) {
+                    Ok(val) => val,
+                    Err(e) => return Err(e),
+                });
+            },
+            TokenType::OpeningBracket => {
+                tokens.next();
+                let mut children = match build_array_value_ast(tokens) {
+                    Ok(Ast::ArrayValue(val)) => val,
+                    Ok(val) => return Err(format!("parser: expected array value, got {:?}", val)),
+                    Err(e) => return Err(e),
                 };
-                let children = match &array_token {
-                    Ast::ArrayValue(val) => val,
-                    _ => return Err(String::new()),
-                };
-                if children.len() != 1 {
-                    output_stack.push(array_token);
+                if !previous_is_variable || children.len() != 1 {
+                    output_stack.push(Ast::ArrayValue(children));
                     continue;
                 }
-                let offset = match children.get(0).unwrap() {
-                    Ast::Int(val) => *val as u64,
-                    _ => {
-                        output_stack.push(array_token);
-                        continue;
-                    },
-                };
-                let last_token = match output_stack.pop() {
-                    Some(val) => val,
-                    None => {
-                        output_stack.push(array_token);
-                        continue;
+                match output_stack.pop() {
+                    Some(Ast::Variable(var)) if var.typename.is_none() => {
+                        output_stack.push(Ast::ArrayAccess { variable: var.name, index: Box::new(children.remove(0)) });
                     },
-                };
-                let last_token_name = match last_token {
-                    Ast::Variable(var) if var.typename == None => var.name.clone(),
-                    val => {
-                        output_stack.push(val.clone());
-                        output_stack.push(array_token);
-                        continue;
+                    last_token => {
+                        if let Some(val) = last_token {
+                            output_stack.push(val);
+                        }
+                        output_stack.push(Ast::ArrayValue(children));
                     },
                 };
-                output_stack.push(Ast::ArrayAccess { variable: last_token_name, offset });
             },
             _ => return Err(format!("invalid token {}", token)),
         }
@@ -611,17 +955,17 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
     while let Some(operator) = operator_stack.pop() {
         match operator {
             TokenType::UnaryOperator(operator_str) => {
-                if let Err(e) = create_unary_operator_ast(&operator_str, &mut output_stack) {
+                if let Err(e) = create_unary_operator_ast(operator_str, &mut output_stack) {
                     return Err(e);
                 }
             },
             TokenType::BinaryOperator(operator_str) => {
-                if let Err(e) = create_binary_operator_ast(&operator_str, &mut output_stack) {
+                if let Err(e) = create_binary_operator_ast(operator_str, &mut output_stack, span) {
                     return Err(e);
                 }
             },
             TokenType::FunctionCall(func_name) => {
-                if let Err(e) = create_function_ast(&func_name, &mut output_stack) {
+                if let Err(e) = create_function_ast(func_name, &mut output_stack) {
                     return Err(e);
                 }
             },
@@ -631,14 +975,24 @@ fn build_expression_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
 
 
     if output_stack.len() != 1 {
-        println!("{:?}", output_stack);
         return Err(format!("invalid expression, parsing items in build_expression_ast, expected length of 1, got {}", output_stack.len()));
     }
 
     return Ok(output_stack.pop().unwrap());
 }
 
-fn build_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Option<Result<Ast, String>> {
+/// Builds the statement starting at the next token, nested in the block being parsed, or nothing for an empty line.
+fn build_ast(tokens: &mut TokenStream) -> Option<Result<Ast, String>> {
+    if let Err(e) = tokens.enter() {
+        return Some(Err(e));
+    }
+    let res = build_statement_ast(tokens);
+    tokens.leave();
+    return res;
+}
+
+fn build_statement_ast(tokens: &mut TokenStream) -> Option<Result<Ast, String>> {
+    let span = tokens.span();
     let next_token = match tokens.peek() {
         Some(token) => token,
         None => return Some(Err(String::from("missing token"))),
@@ -650,29 +1004,283 @@ fn build_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Option<Result<Ast, Strin
         },
         TokenType::Keyword(val) if val == "if" => {
             tokens.next();
-            return Some(build_conditional_ast(tokens, false));
+            return Some(build_conditional_ast(tokens, false, span));
         },
+        TokenType::Pragma(name, _) => return Some(Err(format!("parser: pragma '{}' outside of the top level", name))),
         TokenType::Keyword(val) if val == "function" => {
             tokens.next();
-            return Some(build_function_ast(tokens));
+            return Some(build_function_ast(tokens, span));
         },
         TokenType::Keyword(val) if val == "declare" => {
             tokens.next();
-            return Some(build_declaration_ast(tokens));
+            return Some(build_declaration_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "no_mangle" => {
+            tokens.next();
+            return Some(build_no_mangle_ast(tokens, span));
         },
         TokenType::Keyword(val) if val == "while" => {
             tokens.next();
-            return Some(build_while_loop_ast(tokens));
+            return Some(build_while_loop_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "record" => {
+            tokens.next();
+            return Some(build_record_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "const" => {
+            tokens.next();
+            return Some(build_const_ast(tokens, span));
         },
         TokenType::Keyword(val) if val == "return" => {
             tokens.next();
-            return Some(build_return_ast(tokens));
+            return Some(build_return_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "read" => {
+            tokens.next();
+            return Some(build_read_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "write" => {
+            tokens.next();
+            return Some(build_write_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "swap" => {
+            tokens.next();
+            return Some(build_swap_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "try" => {
+            tokens.next();
+            return Some(build_try_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "throw" => {
+            tokens.next();
+            return Some(match build_expression_ast(tokens) {
+                Err(e) => Err(e),
+                Ok(value) => Ok(Ast::Throw { value: Box::new(value), span }),
+            });
+        },
+        TokenType::Keyword(val) if val == "match" => {
+            tokens.next();
+            return Some(build_match_ast(tokens, span));
+        },
+        TokenType::Keyword(val) if val == "exit" => {
+            tokens.next();
+            return Some(match build_expression_ast(tokens) {
+                Err(e) => Err(e),
+                Ok(code) => Ok(Ast::Exit { code: Box::new(code), span }),
+            });
+        },
+        TokenType::Keyword(val) if val == "halt" => {
+            tokens.next();
+            return Some(Ok(Ast::Exit { code: Box::new(Ast::Int(0)), span }));
         },
         _ => return Some(build_expression_ast(tokens)),
     };
 }
 
-fn build_while_loop_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, String> {
+/// Parses `read x`, the `read` keyword being already consumed, as the assignment of an int read from stdin.
+fn build_read_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let variable = match build_expression_ast(tokens) {
+        Err(e) => return Err(e),
+        Ok(variable @ (Ast::Variable(..) | Ast::ArrayAccess { .. } | Ast::MemberAccess { .. })) => variable,
+        Ok(ast) => return Err(format!("parser: expected a variable after read, got {:?}", ast)),
+    };
+    let expression = Ast::FunctionCall { name: String::from("read_int"), children: Vec::new(), span };
+    return Ok(Ast::Assignement { variable: Box::new(variable), expression: Box::new(expression), span });
+}
+
+/// Parses the comma separated expressions until the end of the line, as the operands of `write` and `swap`.
+fn build_operands_ast(tokens: &mut TokenStream, keyword: &str, span: Span) -> Result<Vec<Ast>, String> {
+    let mut operands = Vec::<Ast>::new();
+    let mut buffer = Vec::<&TokenType>::new();
+    let mut depth = 0;
+    loop {
+        let token = tokens.next();
+        match token {
+            Some(TokenType::Comma) if depth == 0 => (),
+            Some(TokenType::EndLine) | None => (),
+            Some(token) => {
+                match token {
+                    TokenType::OpeningParenthesis | TokenType::OpeningBracket => depth += 1,
+                    TokenType::ClosingParenthesis | TokenType::ClosingBracket => depth -= 1,
+                    _ => (),
+                };
+                buffer.push(token);
+                continue;
+            },
+        };
+        if buffer.is_empty() {
+            return Err(format!("parser: missing operand for {}", keyword));
+        }
+        operands.push(match build_expression_ast(&mut tokens.nested(&buffer, span)) {
+            Ok(operand) => operand,
+            Err(e) => return Err(e),
+        });
+        buffer.clear();
+        if !matches!(token, Some(TokenType::Comma)) {
+            break;
+        }
+    }
+    return Ok(operands);
+}
+
+/// Parses `write a, b`, the `write` keyword being already consumed.
+fn build_write_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let values = match build_operands_ast(tokens, "write", span) {
+        Ok(values) => values,
+        Err(e) => return Err(e),
+    };
+
+    return Ok(Ast::Write { values, span });
+}
+
+/// Parses `swap a, b`, the `swap` keyword being already consumed.
+fn build_swap_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let mut operands = match build_operands_ast(tokens, "swap", span) {
+        Ok(operands) => operands,
+        Err(e) => return Err(e),
+    };
+    if operands.len() != 2 {
+        return Err(format!("parser: swap expects 2 operands, got {}", operands.len()));
+    }
+    let right = operands.pop().unwrap();
+    let left = operands.pop().unwrap();
+    return Ok(Ast::Swap { left: Box::new(left), right: Box::new(right), span });
+}
+
+/// Parses the statements of a block until the `keyword` closing it, which is consumed.
+fn build_block_ast(tokens: &mut TokenStream, keyword: &str) -> Result<Vec<Ast>, String> {
+    return match build_branch_ast(tokens, &[keyword]) {
+        Err(e) => Err(e),
+        Ok((children, _)) => Ok(children),
+    };
+}
+
+/// Parses the statements of a block until one of the `keywords` ending it, which is consumed and returned.
+fn build_branch_ast(tokens: &mut TokenStream, keywords: &[&str]) -> Result<(Vec<Ast>, String), String> {
+    let mut children = Vec::<Ast>::new();
+    loop {
+        match tokens.peek() {
+            None => return Err(format!("parser: unexpected end of document, expected {}", keywords.join(" or "))),
+            Some(TokenType::Keyword(val)) if keywords.contains(&val.as_str()) => {
+                let keyword = val.clone();
+                tokens.next();
+                return Ok((children, keyword));
+            },
+            Some(..) => match build_ast(tokens) {
+                None => (),
+                Some(Ok(ast)) => push_statement(&mut children, ast),
+                Some(Err(e)) => return Err(e),
+            },
+        };
+    }
+}
+
+/// Parses `try ... catch e ... end`, the `try` keyword being already consumed.
+fn build_try_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let children = match build_block_ast(tokens, "catch") {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    let variable = match tokens.next() {
+        Some(TokenType::Variable(val)) => val.clone(),
+        Some(token) => return Err(format!("parser: expected a variable after catch, got {}", token)),
+        None => return Err(String::from("parser: missing variable after catch")),
+    };
+    let handler = match build_block_ast(tokens, "end") {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+    return Ok(Ast::Try { children, variable, handler, span });
+}
+
+/// Parses `match value case a, b ... else ... end`, the `match` keyword being already consumed.
+fn build_match_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let value = match build_expression_ast(tokens) {
+        Err(e) => return Err(e),
+        Ok(val) => val,
+    };
+
+    // only blank lines come before the first case.
+    let mut keyword = loop {
+        match tokens.next() {
+            Some(TokenType::EndLine) => (),
+            Some(TokenType::Keyword(val)) if val == "case" || val == "else" || val == "end" => break val.clone(),
+            Some(token) => return Err(format!("parser: expected case after match, got {}", token)),
+            None => return Err(String::from("parser: unexpected end of document, expected case")),
+        };
+    };
+    let mut cases = Vec::<(Vec<Ast>, Vec<Ast>)>::new();
+    while keyword == "case" {
+        let values = match build_operands_ast(tokens, "case", span) {
+            Err(e) => return Err(e),
+            Ok(val) => val,
+        };
+        let children = match build_branch_ast(tokens, &["case", "else", "end"]) {
+            Err(e) => return Err(e),
+            Ok((children, val)) => {
+                keyword = val;
+                children
+            },
+        };
+        cases.push((values, children));
+    }
+    let default = match keyword.as_str() {
+        "else" => match build_block_ast(tokens, "end") {
+            Err(e) => return Err(e),
+            Ok(val) => Some(val),
+        },
+        _ => None,
+    };
+    return Ok(Ast::Match { value: Box::new(value), cases, default, span });
+}
+
+/// Adds the statement to the block, the statements of an `Ast::Statement` being added one by one.
+fn push_statement(block: &mut Vec<Ast>, statement: Ast) {
+    match statement {
+        Ast::Statement { children } => block.extend(children),
+        statement => block.push(statement),
+    };
+}
+
+/// Parses a record declaration, one `name: type` field per line until `end`.
+fn build_record_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
+    let name = match tokens.next() {
+        Some(TokenType::Variable(val)) => val.clone(),
+        Some(token) => return Err(format!("parser: invalid token {} for record name", token)),
+        None => return Err(String::from("parser: missing name for record")),
+    };
+
+    let mut fields = Vec::<Variable>::new();
+    loop {
+        let token = match tokens.peek() {
+            Some(token) => token,
+            None => return Err(format!("parser: unexpected end of document parsing record '{}'", name)),
+        };
+        match token {
+            TokenType::EndLine => {
+                tokens.next();
+            },
+            TokenType::Keyword(val) if val == "end" => {
+                tokens.next();
+                break;
+            },
+            _ => {
+                fields.push(match parse_variable(tokens, true) {
+                    Ok(val) => val,
+                    Err(e) => return Err(e),
+                });
+                match tokens.next() {
+                    Some(TokenType::EndLine) | None => (),
+                    Some(token) => return Err(format!("parser: expected end of line, got {} in record '{}'", token, name)),
+                };
+            },
+        };
+    }
+
+    return Ok(Ast::RecordDeclaration { name, fields, span });
+}
+
+fn build_while_loop_ast(tokens: &mut TokenStream, span: Span) -> Result<Ast, String> {
     let condition = match build_expression_ast(tokens) {
         Ok(ast) => Box::new(ast),
         Err(e) => return Err(e),
@@ -683,7 +1291,7 @@ fn build_while_loop_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
     loop {
         let token = match tokens.peek() {
             Some(token) => token,
-            None => return Err(format!("parser: error in while loop, unexpected end of document")),
+            None => return Err(String::from("parser: error in while loop, unexpected end of document")),
         };
         match token {
             TokenType::Keyword(val) if val == "end" => {
@@ -691,28 +1299,23 @@ fn build_while_loop_ast(tokens: &mut Peekable<Iter<TokenType>>) -> Result<Ast, S
                 break;
             },
             _ => {
-                match build_ast(tokens) {
-                    Some(ast) => {
-                        children.push(match ast {
-                            Ok(ast) => ast,
-                            Err(e) => return Err(e),
-                        });
-                    },
-                    None => (),
-                };
+                if let Some(ast) = build_ast(tokens) { match ast {
+                    Ok(ast) => push_statement(&mut children, ast),
+                    Err(e) => return Err(e),
+                } };
             },
         };
     };
 
-    return Ok(Ast::WhileLoop { condition, children });
+    return Ok(Ast::WhileLoop { condition, children, span });
 }
 
 pub trait Visitor<T> {
-    fn visit(&self, current: T, element: &Ast) -> Result<T, String>;
-    fn visit_global(&self, current: T, children: &Vec<Ast>) -> Result<T, String>;
-    fn visit_function(&self, current: T, name: &String, children: &Vec<Ast>, parameters: &Vec<Variable>, return_type: &Option<Type>) -> Result<T, String>;
-    fn visit_value(&self, current: T, value: &Ast) -> Result<T, String>;
-    fn visit_binary_operator(&self, current: T, value: &Ast) -> Result<T, String>;
-    fn visit_unary_operator(&self, current: T, value: &Ast) -> Result<T, String>;
+    fn visit(&self, current: T, element: &Ast) -> Result<T, ParseError>;
+    fn visit_global(&self, current: T, children: &[Ast]) -> Result<T, ParseError>;
+    fn visit_function(&self, current: T, name: &str, children: &[Ast], parameters: &[Variable], return_type: &Option<Type>) -> Result<T, ParseError>;
+    fn visit_value(&self, current: T, value: &Ast) -> Result<T, ParseError>;
+    fn visit_binary_operator(&self, current: T, value: &Ast) -> Result<T, ParseError>;
+    fn visit_unary_operator(&self, current: T, value: &Ast) -> Result<T, ParseError>;
 }
 
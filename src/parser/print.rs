@@ -0,0 +1,219 @@
+use super::super::lexer::TokenType;
+use super::utils::get_operator_precedency;
+use super::{Ast, Type, Variable};
+
+const INDENT: &str = "    ";
+
+/// Precedence of the unary operators, binding tighter than the binary ones.
+const UNARY_PRECEDENCE: i64 = 7;
+
+/// Token of the binary operator of `ast` along with its operands, `None` for the other nodes.
+fn binary_operator(ast: &Ast) -> Option<(&'static str, &Ast, &Ast)> {
+    return match ast {
+        Ast::Addition { left, right } => Some(("+", left, right)),
+        Ast::Substraction { left, right } => Some(("-", left, right)),
+        Ast::Multiplication { left, right } => Some(("*", left, right)),
+        Ast::Division { left, right } => Some(("/", left, right)),
+        Ast::Modulo { left, right } => Some(("%", left, right)),
+        Ast::GreaterThan { left, right } => Some((">", left, right)),
+        Ast::LowerThan { left, right } => Some(("<", left, right)),
+        Ast::GreaterOrEqual { left, right } => Some((">=", left, right)),
+        Ast::LowerOrEqual { left, right } => Some(("<=", left, right)),
+        Ast::EqualTo { left, right } => Some(("==", left, right)),
+        Ast::NotEqualTo { left, right } => Some(("!=", left, right)),
+        Ast::And { left, right } => Some(("and", left, right)),
+        Ast::Or { left, right } => Some(("or", left, right)),
+        Ast::Assignement { variable, expression, .. } => Some(("<-", variable, expression)),
+        _ => None,
+    };
+}
+
+/// Precedence the parser gives to the operator of `ast`, the lambdas, whose body spans until the end of the
+/// expression, having the lowest one and the operands the highest.
+fn precedence(ast: &Ast) -> i64 {
+    return match (ast, binary_operator(ast)) {
+        (_, Some((operator, ..))) => get_operator_precedency(&TokenType::BinaryOperator(String::from(operator))),
+        (Ast::UnaryPlus { .. } | Ast::UnaryMinus { .. }, None) => UNARY_PRECEDENCE,
+        (Ast::Lambda { .. }, None) => -1,
+        _ => i64::MAX,
+    };
+}
+
+/// `ast` as the operand of an operator of `precedence`, in parentheses when it binds less tightly than the operator.
+fn operand(ast: &Ast, precedence_of_operator: i64) -> String {
+    return match precedence(ast) < precedence_of_operator {
+        true => format!("({})", expression(ast)),
+        false => expression(ast),
+    };
+}
+
+fn type_name(typename: &Type) -> String {
+    return match (typename.is_array, typename.size, &typename.size_expression) {
+        (true, _, Some(size)) => format!("{}[{}]", typename.name, expression(size)),
+        (true, Some(size), None) => format!("{}[{}]", typename.name, size),
+        (true, None, None) => format!("{}[]", typename.name),
+        (false, ..) => typename.name.clone(),
+    };
+}
+
+fn variable(var: &Variable) -> String {
+    return match &var.typename {
+        Some(typename) => format!("{}: {}", var.name, type_name(typename)),
+        None => var.name.clone(),
+    };
+}
+
+fn list(nodes: &[Ast]) -> String {
+    return nodes.iter().map(expression).collect::<Vec<String>>().join(", ");
+}
+
+/// `(a: int, b: float[])` followed by the return type of a function or a lambda.
+fn signature(parameters: &[Variable], return_type: &Option<String>) -> String {
+    let parameters = parameters.iter().map(variable).collect::<Vec<String>>().join(", ");
+    return match return_type {
+        Some(return_type) => format!("({}): {}", parameters, return_type),
+        None => format!("({})", parameters),
+    };
+}
+
+fn expression(ast: &Ast) -> String {
+    if let Some((operator, left, right)) = binary_operator(ast) {
+        // the operators group from the left, the right operand of the same precedence being in parentheses.
+        let precedence = precedence(ast);
+        return format!("{} {} {}", operand(left, precedence), operator, operand(right, precedence + 1));
+    }
+    return match ast {
+        Ast::Int(value) => value.to_string(),
+        Ast::Float(value) => format!("{:?}", value),
+        Ast::Str(value) => format!("\"{}\"", value),
+        Ast::Bool(value) => value.to_string(),
+        Ast::Variable(var) => variable(var),
+        Ast::FunctionReference(name) => name.clone(),
+        Ast::FunctionCall { name, children, .. } => format!("{}({})", name, list(children)),
+        Ast::ArrayValue(children) => format!("[{}]", list(children)),
+        Ast::ArrayAccess { variable, index } => format!("{}[{}]", variable, expression(index)),
+        Ast::MemberAccess { record, field } => format!("{}.{}", expression(record), field),
+        Ast::MapValue(entries) => format!(
+            "{{{}}}",
+            entries.iter().map(|(key, value)| format!("{}: {}", expression(key), expression(value))).collect::<Vec<String>>().join(", ")
+        ),
+        Ast::NewArray { typename, size } => format!("new {}[{}]", typename, expression(size)),
+        Ast::Lambda { parameters, return_type, body, .. } => format!("fn{} => {}", signature(parameters, return_type), expression(body)),
+        Ast::UnaryPlus { child } => format!("+{}", operand(child, UNARY_PRECEDENCE)),
+        Ast::UnaryMinus { child } => format!("-{}", operand(child, UNARY_PRECEDENCE)),
+        Ast::Cast { child, .. } => expression(child),
+        // statements have no place in expressions, the parser never nesting them there.
+        ast => format!("{:?}", ast),
+    };
+}
+
+fn line(res: &mut String, depth: usize, text: &str) {
+    res.push_str(&INDENT.repeat(depth));
+    res.push_str(text);
+    res.push('\n');
+}
+
+fn block(res: &mut String, depth: usize, statements: &[Ast]) {
+    for ast in statements {
+        statement(res, depth, ast);
+    }
+}
+
+/// `keyword condition` followed by the branches of the condition, without the `end` closing it.
+/// An `if` ending an `else` block continues it, sharing its `end`, as `else if` does.
+fn conditional(res: &mut String, depth: usize, keyword: &str, ast: &Ast) {
+    let (condition, valid_branch, invalid_branch) = match ast {
+        Ast::Condition { condition, valid_branch, invalid_branch, .. } => (condition, valid_branch, invalid_branch),
+        _ => return,
+    };
+    line(res, depth, &format!("{} {}", keyword, expression(condition)));
+    block(res, depth + 1, valid_branch);
+    match invalid_branch.as_slice() {
+        [] => (),
+        [nested @ Ast::Condition { .. }] => conditional(res, depth, "else if", nested),
+        [statements @ .., nested @ Ast::Condition { .. }] => {
+            line(res, depth, "else");
+            block(res, depth + 1, statements);
+            conditional(res, depth + 1, "if", nested);
+        },
+        statements => {
+            line(res, depth, "else");
+            block(res, depth + 1, statements);
+        },
+    };
+}
+
+fn statement(res: &mut String, depth: usize, ast: &Ast) {
+    match ast {
+        Ast::Global(children) | Ast::Statement { children } => block(res, depth, children),
+        Ast::FunctionDeclaration { name, children, parameters, return_type, symbol, .. } => {
+            let no_mangle = if symbol.as_ref() == Some(name) { "no_mangle " } else { "" };
+            line(res, depth, &format!("{}function {}{}", no_mangle, name, signature(parameters, return_type)));
+            block(res, depth + 1, children);
+            line(res, depth, "end");
+        },
+        Ast::FunctionHeader { name, parameters, return_type, symbol, .. } => {
+            let header = format!("declare function {}{}", name, signature(parameters, return_type));
+            line(res, depth, &match symbol {
+                Some(symbol) if symbol == name => format!("no_mangle {}", header),
+                Some(symbol) => format!("{} as \"{}\"", header, symbol),
+                None => header,
+            });
+        },
+        Ast::Condition { .. } => {
+            conditional(res, depth, "if", ast);
+            line(res, depth, "end");
+        },
+        Ast::WhileLoop { condition, children, .. } => {
+            line(res, depth, &format!("while {}", expression(condition)));
+            block(res, depth + 1, children);
+            line(res, depth, "end");
+        },
+        Ast::ReturnStatement { value: Some(value), .. } => line(res, depth, &format!("return {}", expression(value))),
+        Ast::ReturnStatement { value: None, .. } => line(res, depth, "return"),
+        Ast::ConstDeclaration { name, value, .. } => line(res, depth, &format!("const {} <- {}", name, expression(value))),
+        Ast::RecordDeclaration { name, fields, .. } => {
+            line(res, depth, &format!("record {}", name));
+            for field in fields {
+                line(res, depth + 1, &variable(field));
+            }
+            line(res, depth, "end");
+        },
+        Ast::Swap { left, right, .. } => line(res, depth, &format!("swap {}, {}", expression(left), expression(right))),
+        Ast::Write { values, .. } => line(res, depth, &format!("write {}", list(values))),
+        // `read x` is parsed as the assignment of the int read.
+        Ast::Assignement { variable, expression: value, .. } if matches!(&**value, Ast::FunctionCall { name, children, .. } if name == "read_int" && children.is_empty())
+            => line(res, depth, &format!("read {}", expression(variable))),
+        Ast::Try { children, variable, handler, .. } => {
+            line(res, depth, "try");
+            block(res, depth + 1, children);
+            line(res, depth, &format!("catch {}", variable));
+            block(res, depth + 1, handler);
+            line(res, depth, "end");
+        },
+        Ast::Throw { value, .. } => line(res, depth, &format!("throw {}", expression(value))),
+        Ast::Match { value, cases, default, .. } => {
+            line(res, depth, &format!("match {}", expression(value)));
+            for (values, children) in cases {
+                line(res, depth, &format!("case {}", list(values)));
+                block(res, depth + 1, children);
+            }
+            if let Some(default) = default {
+                line(res, depth, "else");
+                block(res, depth + 1, default);
+            }
+            line(res, depth, "end");
+        },
+        Ast::Exit { code, .. } => line(res, depth, &format!("exit {}", expression(code))),
+        Ast::Pragma { name, value, .. } => line(res, depth, format!("@pragma {} {}", name, value).trim_end()),
+        ast => line(res, depth, &expression(ast)),
+    };
+}
+
+/// Source of the program, one statement per line and blocks indented by four spaces, which the parser reads back as
+/// the same tree when the tree comes from the parser. Operators are put in parentheses where their precedence needs it.
+pub fn to_source(ast: &Ast) -> String {
+    let mut res = String::new();
+    statement(&mut res, 0, ast);
+    return res;
+}
@@ -0,0 +1,371 @@
+use std::fmt::Debug;
+
+use super::super::lexer::Span;
+use super::Ast;
+
+/// Reference to a node of the tree, along with the position of the statement containing it.
+#[derive(Clone, Copy)]
+pub struct NodeRef<'a> {
+    pub node: &'a Ast,
+    pub span: Span,
+}
+
+impl<'a> Debug for NodeRef<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}: {:?}", self.span, self.node);
+    }
+}
+
+impl Ast {
+
+    /// Position of the node, only statements (and function calls) are located.
+    pub fn span(&self) -> Option<Span> {
+        return match self {
+            Self::FunctionHeader { span, .. }
+            | Self::FunctionDeclaration { span, .. }
+            | Self::FunctionCall { span, .. }
+            | Self::Assignement { span, .. }
+            | Self::Condition { span, .. }
+            | Self::WhileLoop { span, .. }
+            | Self::ReturnStatement { span, .. }
+            | Self::Lambda { span, .. }
+            | Self::RecordDeclaration { span, .. }
+            | Self::ConstDeclaration { span, .. }
+            | Self::Swap { span, .. }
+            | Self::Write { span, .. }
+            | Self::Try { span, .. }
+            | Self::Throw { span, .. }
+            | Self::Match { span, .. }
+            | Self::Exit { span, .. }
+            | Self::Pragma { span, .. }
+                => Some(*span),
+            _ => None,
+        };
+    }
+
+    /// Direct children of the node, in source order.
+    pub fn children(&self) -> Vec<&Ast> {
+        return match self {
+            Self::Global(children)
+            | Self::ArrayValue(children)
+            | Self::FunctionDeclaration { children, .. }
+            | Self::FunctionCall { children, .. }
+            | Self::Statement { children }
+            | Self::Write { values: children, .. }
+                => children.iter().collect(),
+            Self::Assignement { variable, expression, .. } => vec![variable, expression],
+            Self::Swap { left, right, .. } => vec![left, right],
+            Self::MapValue(entries) => entries.iter().flat_map(|(key, value)| [key, value]).collect(),
+            Self::Condition { condition, valid_branch, invalid_branch, .. } => {
+                let mut res: Vec<&Ast> = vec![condition];
+                res.extend(valid_branch.iter());
+                res.extend(invalid_branch.iter());
+                res
+            },
+            Self::WhileLoop { condition, children, .. } => {
+                let mut res: Vec<&Ast> = vec![condition];
+                res.extend(children.iter());
+                res
+            },
+            Self::Try { children, handler, .. } => children.iter().chain(handler.iter()).collect(),
+            Self::Match { value, cases, default, .. } => {
+                let mut res: Vec<&Ast> = vec![value];
+                for (values, children) in cases {
+                    res.extend(values.iter().chain(children.iter()));
+                }
+                res.extend(default.iter().flatten());
+                res
+            },
+            Self::UnaryPlus { child } | Self::UnaryMinus { child } | Self::Cast { child, .. } => vec![child],
+            Self::MemberAccess { record, .. } => vec![record],
+            Self::ArrayAccess { index, .. } | Self::NewArray { size: index, .. } => vec![index],
+            Self::Addition { left, right }
+            | Self::Substraction { left, right }
+            | Self::Multiplication { left, right }
+            | Self::Division { left, right }
+            | Self::Modulo { left, right }
+            | Self::GreaterThan { left, right }
+            | Self::LowerThan { left, right }
+            | Self::GreaterOrEqual { left, right }
+            | Self::LowerOrEqual { left, right }
+            | Self::And { left, right }
+            | Self::Or { left, right }
+            | Self::EqualTo { left, right }
+            | Self::NotEqualTo { left, right }
+                => vec![left, right],
+            Self::ReturnStatement { value: Some(value), .. } | Self::Throw { value, .. } | Self::Exit { code: value, .. } => vec![value],
+            Self::Lambda { body, .. } | Self::ConstDeclaration { value: body, .. } => vec![body],
+            Self::ReturnStatement { value: None, .. }
+            | Self::FunctionHeader { .. }
+            | Self::RecordDeclaration { .. }
+            | Self::Int(..)
+            | Self::Float(..)
+            | Self::Str(..)
+            | Self::Bool(..)
+            | Self::Variable(..)
+            | Self::FunctionReference(..)
+            | Self::Pragma { .. }
+                => Vec::new(),
+        };
+    }
+
+    /// Rebuilds the node, replacing each of its direct children by the result of `f`, failing with its first error.
+    pub fn map_children<E>(self, f: &mut dyn FnMut(Ast) -> Result<Ast, E>) -> Result<Ast, E> {
+        fn map_vec<E>(children: Vec<Ast>, f: &mut dyn FnMut(Ast) -> Result<Ast, E>) -> Result<Vec<Ast>, E> {
+            let mut res = Vec::with_capacity(children.len());
+            for child in children {
+                res.push(match f(child) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                });
+            }
+            return Ok(res);
+        }
+
+        fn map_box<E>(child: Ast, f: &mut dyn FnMut(Ast) -> Result<Ast, E>) -> Result<Box<Ast>, E> {
+            return match f(child) {
+                Err(e) => Err(e),
+                Ok(val) => Ok(Box::new(val)),
+            };
+        }
+
+        macro_rules! binary {
+            ($variant:ident, $left:expr, $right:expr) => {{
+                let left = match map_box(*$left, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right = match map_box(*$right, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                Self::$variant { left, right }
+            }};
+        }
+
+        return Ok(match self {
+            Self::Global(children) => match map_vec(children, f) {
+                Err(e) => return Err(e),
+                Ok(children) => Self::Global(children),
+            },
+            Self::ArrayValue(children) => match map_vec(children, f) {
+                Err(e) => return Err(e),
+                Ok(children) => Self::ArrayValue(children),
+            },
+            Self::Statement { children } => match map_vec(children, f) {
+                Err(e) => return Err(e),
+                Ok(children) => Self::Statement { children },
+            },
+            Self::FunctionDeclaration { name, children, parameters, return_type, symbol, span } => match map_vec(children, f) {
+                Err(e) => return Err(e),
+                Ok(children) => Self::FunctionDeclaration { name, children, parameters, return_type, symbol, span },
+            },
+            Self::FunctionCall { name, children, span } => match map_vec(children, f) {
+                Err(e) => return Err(e),
+                Ok(children) => Self::FunctionCall { name, children, span },
+            },
+            Self::Assignement { variable, expression, span } => {
+                let variable = match map_box(*variable, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let expression = match map_box(*expression, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                Self::Assignement { variable, expression, span }
+            },
+            Self::MapValue(entries) => {
+                let mut res = Vec::with_capacity(entries.len());
+                for (key, value) in entries {
+                    let key = match f(key) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    };
+                    let value = match f(value) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    };
+                    res.push((key, value));
+                }
+                Self::MapValue(res)
+            },
+            Self::Write { values, span } => match map_vec(values, f) {
+                Err(e) => return Err(e),
+                Ok(values) => Self::Write { values, span },
+            },
+            Self::Swap { left, right, span } => {
+                let left = match map_box(*left, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let right = match map_box(*right, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                Self::Swap { left, right, span }
+            },
+            Self::Condition { condition, valid_branch, invalid_branch, span } => {
+                let condition = match map_box(*condition, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let valid_branch = match map_vec(valid_branch, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let invalid_branch = match map_vec(invalid_branch, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                Self::Condition { condition, valid_branch, invalid_branch, span }
+            },
+            Self::WhileLoop { condition, children, span } => {
+                let condition = match map_box(*condition, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let children = match map_vec(children, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                Self::WhileLoop { condition, children, span }
+            },
+            Self::Try { children, variable, handler, span } => {
+                let children = match map_vec(children, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let handler = match map_vec(handler, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                Self::Try { children, variable, handler, span }
+            },
+            Self::Throw { value, span } => match map_box(*value, f) {
+                Err(e) => return Err(e),
+                Ok(value) => Self::Throw { value, span },
+            },
+            Self::Match { value, cases, default, span } => {
+                let value = match map_box(*value, f) {
+                    Err(e) => return Err(e),
+                    Ok(val) => val,
+                };
+                let mut mapped = Vec::with_capacity(cases.len());
+                for (values, children) in cases {
+                    let values = match map_vec(values, f) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    };
+                    let children = match map_vec(children, f) {
+                        Err(e) => return Err(e),
+                        Ok(val) => val,
+                    };
+                    mapped.push((values, children));
+                }
+                let default = match default {
+                    None => None,
+                    Some(children) => match map_vec(children, f) {
+                        Err(e) => return Err(e),
+                        Ok(val) => Some(val),
+                    },
+                };
+                Self::Match { value, cases: mapped, default, span }
+            },
+            Self::Exit { code, span } => match map_box(*code, f) {
+                Err(e) => return Err(e),
+                Ok(code) => Self::Exit { code, span },
+            },
+            Self::Pragma { .. } => self,
+            Self::UnaryPlus { child } => match map_box(*child, f) {
+                Err(e) => return Err(e),
+                Ok(child) => Self::UnaryPlus { child },
+            },
+            Self::UnaryMinus { child } => match map_box(*child, f) {
+                Err(e) => return Err(e),
+                Ok(child) => Self::UnaryMinus { child },
+            },
+            Self::Cast { child, typename } => match map_box(*child, f) {
+                Err(e) => return Err(e),
+                Ok(child) => Self::Cast { child, typename },
+            },
+            Self::MemberAccess { record, field } => match map_box(*record, f) {
+                Err(e) => return Err(e),
+                Ok(record) => Self::MemberAccess { record, field },
+            },
+            Self::ArrayAccess { variable, index } => match map_box(*index, f) {
+                Err(e) => return Err(e),
+                Ok(index) => Self::ArrayAccess { variable, index },
+            },
+            Self::NewArray { typename, size } => match map_box(*size, f) {
+                Err(e) => return Err(e),
+                Ok(size) => Self::NewArray { typename, size },
+            },
+            Self::Addition { left, right } => binary!(Addition, left, right),
+            Self::Substraction { left, right } => binary!(Substraction, left, right),
+            Self::Multiplication { left, right } => binary!(Multiplication, left, right),
+            Self::Division { left, right } => binary!(Division, left, right),
+            Self::Modulo { left, right } => binary!(Modulo, left, right),
+            Self::GreaterThan { left, right } => binary!(GreaterThan, left, right),
+            Self::LowerThan { left, right } => binary!(LowerThan, left, right),
+            Self::GreaterOrEqual { left, right } => binary!(GreaterOrEqual, left, right),
+            Self::LowerOrEqual { left, right } => binary!(LowerOrEqual, left, right),
+            Self::And { left, right } => binary!(And, left, right),
+            Self::Or { left, right } => binary!(Or, left, right),
+            Self::EqualTo { left, right } => binary!(EqualTo, left, right),
+            Self::NotEqualTo { left, right } => binary!(NotEqualTo, left, right),
+            Self::ReturnStatement { value: Some(value), span } => match map_box(*value, f) {
+                Err(e) => return Err(e),
+                Ok(value) => Self::ReturnStatement { value: Some(value), span },
+            },
+            Self::Lambda { parameters, return_type, body, span } => match map_box(*body, f) {
+                Err(e) => return Err(e),
+                Ok(body) => Self::Lambda { parameters, return_type, body, span },
+            },
+            Self::ConstDeclaration { name, value, span } => match map_box(*value, f) {
+                Err(e) => return Err(e),
+                Ok(value) => Self::ConstDeclaration { name, value, span },
+            },
+            node @ (Self::ReturnStatement { value: None, .. }
+            | Self::FunctionHeader { .. }
+            | Self::RecordDeclaration { .. }
+            | Self::Int(..)
+            | Self::Float(..)
+            | Self::Str(..)
+            | Self::Bool(..)
+            | Self::Variable(..)
+            | Self::FunctionReference(..)) => node,
+        });
+    }
+
+    /// Every node of the tree (including `self`) matching the predicate, in pre-order.
+    /// Expressions get the span of the closest located ancestor.
+    pub fn find_nodes<F: Fn(&Ast) -> bool>(&self, predicate: F) -> Vec<NodeRef<'_>> {
+        let mut res = Vec::new();
+        collect_nodes(self, Span::default(), &predicate, &mut res);
+        return res;
+    }
+
+    /// Every function implementation, including nested ones.
+    pub fn find_functions(&self) -> Vec<NodeRef<'_>> {
+        return self.find_nodes(|n| matches!(n, Ast::FunctionDeclaration { .. }));
+    }
+
+    /// Every call to the function `name`, both as statements and in expressions.
+    pub fn find_calls(&self, name: &str) -> Vec<NodeRef<'_>> {
+        return self.find_nodes(|n| match n {
+            Ast::FunctionCall { name: call_name, .. } => call_name == name,
+            _ => false,
+        });
+    }
+}
+
+fn collect_nodes<'a, F: Fn(&Ast) -> bool>(node: &'a Ast, parent_span: Span, predicate: &F, result: &mut Vec<NodeRef<'a>>) {
+    let span = node.span().unwrap_or(parent_span);
+    if predicate(node) {
+        result.push(NodeRef { node, span });
+    }
+    for child in node.children() {
+        collect_nodes(child, span, predicate, result);
+    }
+}
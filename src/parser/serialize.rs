@@ -0,0 +1,524 @@
+//! Binary form of the syntax tree, written by `to_bytes` and read back by `from_bytes`, for the cache to store the trees
+//! of the files. Each node is its tag followed by its fields, numbers as 8 bytes little endian and texts prefixed by their length.
+//! Without the spans, the same form is the canonical one of the tree, on which its equality and its hash are defined.
+
+use std::hash::{Hash, Hasher};
+
+use super::super::lexer::Span;
+use super::types::{Ast, Type, Variable};
+
+struct Writer {
+    bytes: Vec<u8>,
+    /// whether the positions of the nodes are written, the canonical form leaving them out.
+    spans: bool,
+}
+
+impl Writer {
+    fn tag(&mut self, tag: u8) {
+        self.bytes.push(tag);
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.bytes.push(value as u8);
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u64(value.len() as u64);
+        self.bytes.extend_from_slice(value.as_bytes());
+    }
+
+    fn opt_str(&mut self, value: &Option<String>) {
+        self.bool(value.is_some());
+        if let Some(value) = value {
+            self.str(value);
+        }
+    }
+
+    fn span(&mut self, span: &Span) {
+        if !self.spans {
+            return;
+        }
+        self.u64(span.line as u64);
+        self.u64(span.column as u64);
+        self.u64(span.file as u64);
+    }
+
+    fn nodes(&mut self, nodes: &[Ast]) {
+        self.u64(nodes.len() as u64);
+        for node in nodes {
+            self.node(node);
+        }
+    }
+
+    fn binary(&mut self, tag: u8, left: &Ast, right: &Ast) {
+        self.tag(tag);
+        self.node(left);
+        self.node(right);
+    }
+
+    fn typename(&mut self, typename: &Type) {
+        self.str(&typename.name);
+        self.bool(typename.is_array);
+        self.bool(typename.size.is_some());
+        self.u64(typename.size.unwrap_or_default());
+        self.bool(typename.size_expression.is_some());
+        if let Some(size) = &typename.size_expression {
+            self.node(size);
+        }
+    }
+
+    fn variable(&mut self, variable: &Variable) {
+        self.str(&variable.name);
+        self.bool(variable.typename.is_some());
+        if let Some(typename) = &variable.typename {
+            self.typename(typename);
+        }
+    }
+
+    fn variables(&mut self, variables: &[Variable]) {
+        self.u64(variables.len() as u64);
+        for variable in variables {
+            self.variable(variable);
+        }
+    }
+
+    fn node(&mut self, node: &Ast) {
+        match node {
+            Ast::Global(children) => {
+                self.tag(0);
+                self.nodes(children);
+            },
+            Ast::FunctionHeader { name, parameters, return_type, symbol, span } => {
+                self.tag(1);
+                self.str(name);
+                self.variables(parameters);
+                self.opt_str(return_type);
+                self.opt_str(symbol);
+                self.span(span);
+            },
+            Ast::FunctionDeclaration { name, children, parameters, return_type, symbol, span } => {
+                self.tag(2);
+                self.str(name);
+                self.nodes(children);
+                self.variables(parameters);
+                self.opt_str(return_type);
+                self.opt_str(symbol);
+                self.span(span);
+            },
+            Ast::FunctionCall { name, children, span } => {
+                self.tag(3);
+                self.str(name);
+                self.nodes(children);
+                self.span(span);
+            },
+            Ast::Int(value) => {
+                self.tag(4);
+                self.u64(*value as u64);
+            },
+            Ast::Float(value) => {
+                self.tag(5);
+                self.u64(value.to_bits());
+            },
+            Ast::Str(value) => {
+                self.tag(6);
+                self.str(value);
+            },
+            Ast::Bool(value) => {
+                self.tag(7);
+                self.bool(*value);
+            },
+            Ast::ArrayValue(children) => {
+                self.tag(8);
+                self.nodes(children);
+            },
+            Ast::Assignement { variable, expression, span } => {
+                self.binary(9, variable, expression);
+                self.span(span);
+            },
+            Ast::Condition { condition, valid_branch, invalid_branch, span } => {
+                self.tag(10);
+                self.node(condition);
+                self.nodes(valid_branch);
+                self.nodes(invalid_branch);
+                self.span(span);
+            },
+            Ast::WhileLoop { condition, children, span } => {
+                self.tag(11);
+                self.node(condition);
+                self.nodes(children);
+                self.span(span);
+            },
+            Ast::Variable(variable) => {
+                self.tag(12);
+                self.variable(variable);
+            },
+            Ast::Statement { children } => {
+                self.tag(13);
+                self.nodes(children);
+            },
+            Ast::Addition { left, right } => self.binary(14, left, right),
+            Ast::UnaryPlus { child } => {
+                self.tag(15);
+                self.node(child);
+            },
+            Ast::UnaryMinus { child } => {
+                self.tag(16);
+                self.node(child);
+            },
+            Ast::Substraction { left, right } => self.binary(17, left, right),
+            Ast::Multiplication { left, right } => self.binary(18, left, right),
+            Ast::Division { left, right } => self.binary(19, left, right),
+            Ast::Modulo { left, right } => self.binary(20, left, right),
+            Ast::GreaterThan { left, right } => self.binary(21, left, right),
+            Ast::LowerThan { left, right } => self.binary(22, left, right),
+            Ast::GreaterOrEqual { left, right } => self.binary(23, left, right),
+            Ast::LowerOrEqual { left, right } => self.binary(24, left, right),
+            Ast::And { left, right } => self.binary(25, left, right),
+            Ast::Or { left, right } => self.binary(26, left, right),
+            Ast::EqualTo { left, right } => self.binary(27, left, right),
+            Ast::NotEqualTo { left, right } => self.binary(28, left, right),
+            Ast::ReturnStatement { value, span } => {
+                self.tag(29);
+                self.bool(value.is_some());
+                if let Some(value) = value {
+                    self.node(value);
+                }
+                self.span(span);
+            },
+            Ast::ArrayAccess { variable, index } => {
+                self.tag(30);
+                self.str(variable);
+                self.node(index);
+            },
+            Ast::Lambda { parameters, return_type, body, span } => {
+                self.tag(31);
+                self.variables(parameters);
+                self.opt_str(return_type);
+                self.node(body);
+                self.span(span);
+            },
+            Ast::FunctionReference(name) => {
+                self.tag(32);
+                self.str(name);
+            },
+            Ast::ConstDeclaration { name, value, span } => {
+                self.tag(33);
+                self.str(name);
+                self.node(value);
+                self.span(span);
+            },
+            Ast::RecordDeclaration { name, fields, span } => {
+                self.tag(34);
+                self.str(name);
+                self.variables(fields);
+                self.span(span);
+            },
+            Ast::MemberAccess { record, field } => {
+                self.tag(35);
+                self.node(record);
+                self.str(field);
+            },
+            Ast::Cast { child, typename } => {
+                self.tag(36);
+                self.node(child);
+                self.str(typename);
+            },
+            Ast::NewArray { typename, size } => {
+                self.tag(37);
+                self.str(typename);
+                self.node(size);
+            },
+            Ast::MapValue(entries) => {
+                self.tag(38);
+                self.u64(entries.len() as u64);
+                for (key, value) in entries {
+                    self.node(key);
+                    self.node(value);
+                }
+            },
+            Ast::Swap { left, right, span } => {
+                self.binary(39, left, right);
+                self.span(span);
+            },
+            Ast::Try { children, variable, handler, span } => {
+                self.tag(40);
+                self.nodes(children);
+                self.str(variable);
+                self.nodes(handler);
+                self.span(span);
+            },
+            Ast::Throw { value, span } => {
+                self.tag(41);
+                self.node(value);
+                self.span(span);
+            },
+            Ast::Match { value, cases, default, span } => {
+                self.tag(42);
+                self.node(value);
+                self.u64(cases.len() as u64);
+                for (values, children) in cases {
+                    self.nodes(values);
+                    self.nodes(children);
+                }
+                self.bool(default.is_some());
+                if let Some(default) = default {
+                    self.nodes(default);
+                }
+                self.span(span);
+            },
+            Ast::Exit { code, span } => {
+                self.tag(43);
+                self.node(code);
+                self.span(span);
+            },
+            Ast::Pragma { name, value, span } => {
+                self.tag(44);
+                self.str(name);
+                self.str(value);
+                self.span(span);
+            },
+            Ast::Write { values, span } => {
+                self.tag(45);
+                self.nodes(values);
+                self.span(span);
+            },
+        };
+    }
+}
+
+/// Binary form of the tree.
+pub fn to_bytes(ast: &Ast) -> Vec<u8> {
+    let mut writer = Writer { bytes: Vec::new(), spans: true };
+    writer.node(ast);
+    return writer.bytes;
+}
+
+/// Canonical form of the tree, its binary form without the spans: two trees have the same canonical form when they
+/// only differ by the positions of their nodes. Floats are compared by their bits, `NaN` being equal to itself.
+pub fn to_canonical_bytes(ast: &Ast) -> Vec<u8> {
+    let mut writer = Writer { bytes: Vec::new(), spans: false };
+    writer.node(ast);
+    return writer.bytes;
+}
+
+/// Trees are equal when their canonical forms are, whatever the positions of their nodes.
+impl PartialEq for Ast {
+    fn eq(&self, other: &Ast) -> bool {
+        return to_canonical_bytes(self) == to_canonical_bytes(other);
+    }
+}
+
+impl Eq for Ast {}
+
+/// Hash of the canonical form, the same for the trees parsed from sources differing only by their layout.
+impl Hash for Ast {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write(&to_canonical_bytes(self));
+    }
+}
+
+/// Reads the fields in the order they were written. Reading past the end or an unknown tag marks the reader as failed,
+/// the values read afterwards being placeholders.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    failed: bool,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> &'a [u8] {
+        match self.position.checked_add(count).filter(|end| *end <= self.bytes.len() && !self.failed) {
+            Some(end) => {
+                let res = &self.bytes[self.position..end];
+                self.position = end;
+                return res;
+            },
+            None => {
+                self.failed = true;
+                return &[];
+            },
+        };
+    }
+
+    fn tag(&mut self) -> u8 {
+        return self.take(1).first().copied().unwrap_or_default();
+    }
+
+    fn u64(&mut self) -> u64 {
+        return match <[u8; 8]>::try_from(self.take(8)) {
+            Ok(bytes) => u64::from_le_bytes(bytes),
+            Err(_) => 0,
+        };
+    }
+
+    fn usize(&mut self) -> usize {
+        return self.u64() as usize;
+    }
+
+    fn bool(&mut self) -> bool {
+        return self.tag() != 0;
+    }
+
+    fn str(&mut self) -> String {
+        let length = self.usize();
+        return match std::str::from_utf8(self.take(length)) {
+            Ok(value) => String::from(value),
+            Err(_) => {
+                self.failed = true;
+                String::new()
+            },
+        };
+    }
+
+    fn opt_str(&mut self) -> Option<String> {
+        return match self.bool() {
+            true => Some(self.str()),
+            false => None,
+        };
+    }
+
+    fn span(&mut self) -> Span {
+        return Span { line: self.usize(), column: self.usize(), file: self.usize() };
+    }
+
+    /// Reads `count` items with `f`, stopping at the first failure for a corrupted count not to be looped over.
+    fn items<T>(&mut self, f: fn(&mut Reader<'a>) -> T) -> Vec<T> {
+        let count = self.u64();
+        let mut res = Vec::new();
+        for _ in 0..count {
+            if self.failed {
+                break;
+            }
+            res.push(f(self));
+        }
+        return res;
+    }
+
+    fn nodes(&mut self) -> Vec<Ast> {
+        return self.items(Reader::node);
+    }
+
+    fn boxed(&mut self) -> Box<Ast> {
+        return Box::new(self.node());
+    }
+
+    fn typename(&mut self) -> Type {
+        let name = self.str();
+        let is_array = self.bool();
+        let size = match (self.bool(), self.u64()) {
+            (true, size) => Some(size),
+            (false, _) => None,
+        };
+        let size_expression = match self.bool() {
+            true => Some(self.boxed()),
+            false => None,
+        };
+        return Type { name, is_array, size, size_expression };
+    }
+
+    fn variable(&mut self) -> Variable {
+        let name = self.str();
+        let typename = match self.bool() {
+            true => Some(self.typename()),
+            false => None,
+        };
+        return Variable { name, typename };
+    }
+
+    fn variables(&mut self) -> Vec<Variable> {
+        return self.items(Reader::variable);
+    }
+
+    fn node(&mut self) -> Ast {
+        if self.failed {
+            return Ast::Int(0);
+        }
+        return match self.tag() {
+            0 => Ast::Global(self.nodes()),
+            1 => Ast::FunctionHeader { name: self.str(), parameters: self.variables(), return_type: self.opt_str(), symbol: self.opt_str(), span: self.span() },
+            2 => Ast::FunctionDeclaration {
+                name: self.str(),
+                children: self.nodes(),
+                parameters: self.variables(),
+                return_type: self.opt_str(),
+                symbol: self.opt_str(),
+                span: self.span(),
+            },
+            3 => Ast::FunctionCall { name: self.str(), children: self.nodes(), span: self.span() },
+            4 => Ast::Int(self.u64() as i64),
+            5 => Ast::Float(f64::from_bits(self.u64())),
+            6 => Ast::Str(self.str()),
+            7 => Ast::Bool(self.bool()),
+            8 => Ast::ArrayValue(self.nodes()),
+            9 => Ast::Assignement { variable: self.boxed(), expression: self.boxed(), span: self.span() },
+            10 => Ast::Condition { condition: self.boxed(), valid_branch: self.nodes(), invalid_branch: self.nodes(), span: self.span() },
+            11 => Ast::WhileLoop { condition: self.boxed(), children: self.nodes(), span: self.span() },
+            12 => Ast::Variable(self.variable()),
+            13 => Ast::Statement { children: self.nodes() },
+            14 => Ast::Addition { left: self.boxed(), right: self.boxed() },
+            15 => Ast::UnaryPlus { child: self.boxed() },
+            16 => Ast::UnaryMinus { child: self.boxed() },
+            17 => Ast::Substraction { left: self.boxed(), right: self.boxed() },
+            18 => Ast::Multiplication { left: self.boxed(), right: self.boxed() },
+            19 => Ast::Division { left: self.boxed(), right: self.boxed() },
+            20 => Ast::Modulo { left: self.boxed(), right: self.boxed() },
+            21 => Ast::GreaterThan { left: self.boxed(), right: self.boxed() },
+            22 => Ast::LowerThan { left: self.boxed(), right: self.boxed() },
+            23 => Ast::GreaterOrEqual { left: self.boxed(), right: self.boxed() },
+            24 => Ast::LowerOrEqual { left: self.boxed(), right: self.boxed() },
+            25 => Ast::And { left: self.boxed(), right: self.boxed() },
+            26 => Ast::Or { left: self.boxed(), right: self.boxed() },
+            27 => Ast::EqualTo { left: self.boxed(), right: self.boxed() },
+            28 => Ast::NotEqualTo { left: self.boxed(), right: self.boxed() },
+            29 => {
+                let value = match self.bool() {
+                    true => Some(self.boxed()),
+                    false => None,
+                };
+                Ast::ReturnStatement { value, span: self.span() }
+            },
+            30 => Ast::ArrayAccess { variable: self.str(), index: self.boxed() },
+            31 => Ast::Lambda { parameters: self.variables(), return_type: self.opt_str(), body: self.boxed(), span: self.span() },
+            32 => Ast::FunctionReference(self.str()),
+            33 => Ast::ConstDeclaration { name: self.str(), value: self.boxed(), span: self.span() },
+            34 => Ast::RecordDeclaration { name: self.str(), fields: self.variables(), span: self.span() },
+            35 => Ast::MemberAccess { record: self.boxed(), field: self.str() },
+            36 => Ast::Cast { child: self.boxed(), typename: self.str() },
+            37 => Ast::NewArray { typename: self.str(), size: self.boxed() },
+            38 => Ast::MapValue(self.items(|reader| (reader.node(), reader.node()))),
+            39 => Ast::Swap { left: self.boxed(), right: self.boxed(), span: self.span() },
+            40 => Ast::Try { children: self.nodes(), variable: self.str(), handler: self.nodes(), span: self.span() },
+            41 => Ast::Throw { value: self.boxed(), span: self.span() },
+            42 => {
+                let value = self.boxed();
+                let cases = self.items(|reader| (reader.nodes(), reader.nodes()));
+                let default = match self.bool() {
+                    true => Some(self.nodes()),
+                    false => None,
+                };
+                Ast::Match { value, cases, default, span: self.span() }
+            },
+            43 => Ast::Exit { code: self.boxed(), span: self.span() },
+            44 => Ast::Pragma { name: self.str(), value: self.str(), span: self.span() },
+            45 => Ast::Write { values: self.nodes(), span: self.span() },
+            _ => {
+                self.failed = true;
+                Ast::Int(0)
+            },
+        };
+    }
+}
+
+/// Tree written by `to_bytes`, `None` when the bytes are not one.
+pub fn from_bytes(bytes: &[u8]) -> Option<Ast> {
+    let mut reader = Reader { bytes, position: 0, failed: false };
+    let ast = reader.node();
+    if reader.failed || reader.position != bytes.len() {
+        return None;
+    }
+    return Some(ast);
+}
@@ -0,0 +1,351 @@
+//! Random programs for property based tests of the parser and of `to_source`.
+//!
+//! `Generator` builds syntax trees from a seed, within the grammar the parser reads back: a call is never the direct
+//! argument of another one, unary operators only follow binary ones, and the elements of arrays hold no comma nor
+//! bracket. `check_round_trip` is the property the trees are expected to satisfy. With the `proptest` feature,
+//! `programs` is the strategy of `proptest` drawing them.
+
+use super::{to_source, Ast, Type, Variable};
+use super::super::diagnostic::{Code, Diagnostic};
+use super::super::error::ParseError;
+use super::super::lexer::Span;
+
+const VARIABLES: [&str; 6] = ["a", "b", "count", "total", "item", "x2"];
+const FUNCTIONS: [&str; 4] = ["f", "compute", "print", "len"];
+const RECORDS: [&str; 2] = ["Point", "Pair"];
+const FIELDS: [&str; 3] = ["x", "y", "first"];
+const CONSTANTS: [&str; 2] = ["N", "LIMIT"];
+const TYPES: [&str; 4] = ["int", "float", "string", "char"];
+const WORDS: [&str; 4] = ["", "hello", "a b", "42"];
+
+/// Expressions nested in one another, and blocks, past which the generator only builds leaves.
+const MAX_DEPTH: usize = 4;
+
+/// Where an expression is written, some places of the grammar ending it at their separators.
+#[derive(Clone, Copy, PartialEq)]
+enum Place {
+    Value,
+    /// element or index of an array, ending at the first comma or bracket.
+    Element,
+    /// body of a lambda, ending at a comma closing no parenthesis.
+    LambdaBody,
+    /// key or value of a map, holding no lambda, whose colons would separate the entry, nor map, after which the lexer
+    /// reads the names following colons as types.
+    Entry,
+}
+
+/// Generator of random syntax trees, the same seed giving the same trees.
+pub struct Generator {
+    state: u64,
+    depth: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Generator {
+        // xorshift never leaves the state 0.
+        return Generator { state: seed ^ 0x9e37_79b9_7f4a_7c15, depth: 0 };
+    }
+
+    fn step(&mut self) -> u64 {
+        if self.state == 0 {
+            self.state = 1;
+        }
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        return self.state;
+    }
+
+    /// Number in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        return (self.step() % n as u64) as usize;
+    }
+
+    fn pick(&mut self, items: &[&str]) -> String {
+        return String::from(items[self.below(items.len())]);
+    }
+
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Generator) -> T) -> T {
+        self.depth += 1;
+        let res = f(self);
+        self.depth -= 1;
+        return res;
+    }
+
+    fn typename(&mut self) -> Type {
+        let name = match self.below(4) {
+            0 => self.pick(&RECORDS),
+            _ => self.pick(&TYPES),
+        };
+        let (is_array, size) = match self.below(4) {
+            0 => (true, None),
+            1 => (true, Some(self.below(8) as u64)),
+            _ => (false, None),
+        };
+        return Type { name, is_array, size, size_expression: None };
+    }
+
+    fn typed_variable(&mut self, names: &[&str]) -> Variable {
+        return Variable { name: self.pick(names), typename: Some(self.typename()) };
+    }
+
+    fn variable(&mut self) -> Ast {
+        return Ast::Variable(Variable { name: self.pick(&VARIABLES), typename: None });
+    }
+
+    fn member_access(&mut self) -> Ast {
+        let mut res = self.variable();
+        for _ in 0..1 + self.below(2) {
+            res = Ast::MemberAccess { record: Box::new(res), field: self.pick(&FIELDS) };
+        }
+        return res;
+    }
+
+    fn leaf(&mut self) -> Ast {
+        return match self.below(7) {
+            0 => Ast::Int(self.below(1000) as i64),
+            // quarters are written exactly in decimal.
+            1 => Ast::Float(self.below(400) as f64 / 4.0),
+            2 => Ast::Str(self.pick(&WORDS)),
+            3 => Ast::Bool(self.below(2) == 0),
+            4 => self.member_access(),
+            _ => self.variable(),
+        };
+    }
+
+    /// Expression never starting with a unary operator, which the lexer would read as a binary one.
+    pub fn expression(&mut self) -> Ast {
+        return self.expression_at(Place::Value);
+    }
+
+    fn expression_at(&mut self, place: Place) -> Ast {
+        if self.depth >= MAX_DEPTH || self.below(3) == 0 {
+            return self.leaf();
+        }
+        let brackets = place != Place::Element;
+        return self.nested(|g| match g.below(10) {
+            0..=3 => g.binary_operation(place),
+            4 => g.call(place),
+            5 if brackets => Ast::ArrayValue((0..1 + g.below(3)).map(|_| g.expression_at(Place::Element)).collect()),
+            6 if brackets => Ast::ArrayAccess { variable: g.pick(&VARIABLES), index: Box::new(g.expression_at(Place::Element)) },
+            7 if brackets => Ast::NewArray { typename: g.pick(&["int", "float"]), size: Box::new(g.expression_at(Place::Element)) },
+            8 if place == Place::Value => {
+                Ast::MapValue((0..g.below(3)).map(|_| (g.expression_at(Place::Entry), g.expression_at(Place::Entry))).collect())
+            },
+            9 if place == Place::Value => g.lambda(),
+            _ => g.leaf(),
+        });
+    }
+
+    /// Expression following a binary operator or a keyword, where a unary operator is read as such.
+    fn signed_expression(&mut self, place: Place) -> Ast {
+        return match self.below(6) {
+            0 => Ast::UnaryMinus { child: Box::new(self.nested(|g| g.expression_at(place))) },
+            1 => Ast::UnaryPlus { child: Box::new(self.leaf()) },
+            _ => self.expression_at(place),
+        };
+    }
+
+    fn binary_operation(&mut self, place: Place) -> Ast {
+        let left = Box::new(self.expression_at(place));
+        let right = Box::new(self.signed_expression(place));
+        return match self.below(13) {
+            0 => Ast::Addition { left, right },
+            1 => Ast::Substraction { left, right },
+            2 => Ast::Multiplication { left, right },
+            3 => Ast::Division { left, right },
+            4 => Ast::Modulo { left, right },
+            5 => Ast::GreaterThan { left, right },
+            6 => Ast::LowerThan { left, right },
+            7 => Ast::GreaterOrEqual { left, right },
+            8 => Ast::LowerOrEqual { left, right },
+            9 => Ast::EqualTo { left, right },
+            10 => Ast::NotEqualTo { left, right },
+            11 => Ast::And { left, right },
+            _ => Ast::Or { left, right },
+        };
+    }
+
+    /// Call of a function, the arguments of the calls written in array elements being single, without commas.
+    fn call(&mut self, place: Place) -> Ast {
+        let count = match place {
+            Place::Element => self.below(2),
+            _ => self.below(4),
+        };
+        let mut children = Vec::new();
+        for i in 0..count {
+            let argument = match i {
+                0 => self.expression_at(place),
+                _ => self.signed_expression(place),
+            };
+            children.push(match argument {
+                // the parser takes a call following the parenthesis of another one as the end of its arguments.
+                call @ Ast::FunctionCall { .. } => Ast::Addition { left: Box::new(call), right: Box::new(Ast::Int(1)) },
+                argument => argument,
+            });
+        }
+        return Ast::FunctionCall { name: self.pick(&FUNCTIONS), children, span: Span::default() };
+    }
+
+    fn lambda(&mut self) -> Ast {
+        let parameters = (0..self.below(3))
+            .map(|_| Variable { name: self.pick(&VARIABLES), typename: Some(Type { name: self.pick(&TYPES), is_array: false, size: None, size_expression: None }) })
+            .collect();
+        let return_type = match self.below(2) {
+            0 => Some(self.pick(&TYPES)),
+            _ => None,
+        };
+        let body = Box::new(self.signed_expression(Place::LambdaBody));
+        return Ast::Lambda { parameters, return_type, body, span: Span::default() };
+    }
+
+    fn assignable(&mut self) -> Ast {
+        return match self.below(5) {
+            0 => Ast::ArrayAccess { variable: self.pick(&VARIABLES), index: Box::new(self.expression_at(Place::Element)) },
+            1 => self.member_access(),
+            _ => self.variable(),
+        };
+    }
+
+    fn block(&mut self) -> Vec<Ast> {
+        return self.nested(|g| (0..g.below(4)).map(|_| g.statement()).collect());
+    }
+
+    /// Statements of an `else`, an `if` only ending them: the parser takes an `if` there as the continuation of the
+    /// condition, whose `end` closes both.
+    fn else_block(&mut self) -> Vec<Ast> {
+        let mut res = self.block().into_iter().filter(|statement| !matches!(statement, Ast::Condition { .. })).collect::<Vec<Ast>>();
+        if self.below(2) == 0 {
+            res.push(self.nested(|g| g.condition()));
+        }
+        return res;
+    }
+
+    fn condition(&mut self) -> Ast {
+        let condition = Box::new(self.expression());
+        let valid_branch = self.block();
+        let invalid_branch = match self.below(3) {
+            0 => self.else_block(),
+            _ => Vec::new(),
+        };
+        return Ast::Condition { condition, valid_branch, invalid_branch, span: Span::default() };
+    }
+
+    pub fn statement(&mut self) -> Ast {
+        let span = Span::default();
+        if self.depth >= MAX_DEPTH {
+            return Ast::Assignement { variable: Box::new(self.assignable()), expression: Box::new(self.signed_expression(Place::Value)), span };
+        }
+        return match self.below(14) {
+            0 => Ast::Assignement {
+                variable: Box::new(Ast::Variable(self.typed_variable(&VARIABLES))),
+                expression: Box::new(self.signed_expression(Place::Value)),
+                span,
+            },
+            1 => self.call(Place::Value),
+            2 => self.condition(),
+            3 => Ast::WhileLoop { condition: Box::new(self.expression()), children: self.block(), span },
+            4 => Ast::ReturnStatement { value: Some(Box::new(self.signed_expression(Place::Value))), span },
+            5 => Ast::Swap { left: Box::new(self.assignable()), right: Box::new(self.assignable()), span },
+            6 => Ast::Try { children: self.block(), variable: self.pick(&VARIABLES), handler: self.block(), span },
+            7 => Ast::Throw { value: Box::new(self.expression()), span },
+            8 => Ast::Exit { code: Box::new(self.signed_expression(Place::Value)), span },
+            9 => {
+                let value = Box::new(self.expression());
+                let cases = (0..self.below(3))
+                    .map(|_| ((0..1 + self.below(2)).map(|_| self.expression_at(Place::Element)).collect(), self.block()))
+                    .collect();
+                let default = match self.below(2) {
+                    0 => Some(self.block()),
+                    _ => None,
+                };
+                Ast::Match { value, cases, default, span }
+            },
+            10 => Ast::Write { values: (0..1 + self.below(3)).map(|_| self.signed_expression(Place::Entry)).collect(), span },
+            11 => {
+                let read = Ast::FunctionCall { name: String::from("read_int"), children: Vec::new(), span };
+                Ast::Assignement { variable: Box::new(self.assignable()), expression: Box::new(read), span }
+            },
+            _ => Ast::Assignement { variable: Box::new(self.assignable()), expression: Box::new(self.signed_expression(Place::Value)), span },
+        };
+    }
+
+    /// Declaration of the top level: a record, a constant, a function or the header of an external one.
+    fn declaration(&mut self) -> Ast {
+        let span = Span::default();
+        let parameters = (0..self.below(3)).map(|_| self.typed_variable(&VARIABLES)).collect::<Vec<Variable>>();
+        let return_type = match self.below(2) {
+            0 => Some(self.pick(&TYPES)),
+            _ => None,
+        };
+        let name = self.pick(&FUNCTIONS);
+        return match self.below(5) {
+            0 => Ast::RecordDeclaration { name: self.pick(&RECORDS), fields: (0..self.below(4)).map(|_| self.typed_variable(&FIELDS)).collect(), span },
+            1 => Ast::ConstDeclaration { name: self.pick(&CONSTANTS), value: Box::new(self.signed_expression(Place::Value)), span },
+            2 => {
+                let symbol = match self.below(3) {
+                    0 => Some(name.clone()),
+                    1 => Some(format!("ext_{}", name)),
+                    _ => None,
+                };
+                Ast::FunctionHeader { name, parameters, return_type, symbol, span }
+            },
+            _ => {
+                let symbol = match self.below(4) {
+                    0 => Some(name.clone()),
+                    _ => None,
+                };
+                Ast::FunctionDeclaration { name, children: self.block(), parameters, return_type, symbol, span }
+            },
+        };
+    }
+
+    /// Program made of declarations and statements, syntactically valid but seldom well typed.
+    pub fn program(&mut self) -> Ast {
+        let mut children = Vec::new();
+        for _ in 0..self.below(8) {
+            children.push(match self.below(2) {
+                0 => self.declaration(),
+                _ => self.statement(),
+            });
+        }
+        return Ast::Global(children);
+    }
+}
+
+/// Round trip of `ast` through its source: the tree parsed from `to_source(ast)` must equal `ast`, spans aside.
+/// The error is the diagnostic of the parser, or one quoting the sources of the trees that differ, the source
+/// being noted.
+pub fn check_round_trip(ast: &Ast) -> Result<(), ParseError> {
+    let source = to_source(ast);
+    let parsed = match super::super::parse_str(&source) {
+        Err(e) => return Err(ParseError::from(e.with_note(&source))),
+        Ok(parsed) => parsed,
+    };
+    if parsed != *ast {
+        let message = format!("the parsed program differs\n{}\n---\n{}", source, to_source(&parsed));
+        return Err(ParseError::from(Diagnostic::error(Code::Syntax, message, Span::default())));
+    }
+    return Ok(());
+}
+
+/// Strategy of `proptest` drawing the programs of `Generator`, shrunk by their seed.
+#[cfg(feature = "proptest")]
+pub fn programs() -> impl proptest::strategy::Strategy<Value = Ast> {
+    use proptest::strategy::Strategy;
+    return proptest::num::u64::ANY.prop_map(|seed| Generator::new(seed).program());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_round_trip, Generator};
+
+    #[test]
+    fn programs_round_trip() {
+        for seed in 0..2000 {
+            if let Err(e) = check_round_trip(&Generator::new(seed).program()) {
+                panic!("seed {}: {}", seed, e);
+            }
+        }
+    }
+}
@@ -1,22 +1,140 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, iter::{Chain, Copied, Once, Peekable}, slice::Iter};
+
+use super::super::lexer::{TokenType, Span};
+
+/// Token closing the expressions of the nested streams.
+static END_LINE: TokenType = TokenType::EndLine;
+
+/// Blocks and expressions nested in one another past which the parser gives up, before it runs out of stack.
+const MAX_DEPTH: usize = 64;
+
+/// Tokens of a stream, borrowed from the output of the lexer.
+enum Tokens<'a> {
+    Source(Iter<'a, TokenType>),
+    /// tokens extracted from another stream, followed by the end of line closing their expression.
+    Nested(Chain<Copied<Iter<'a, &'a TokenType>>, Once<&'a TokenType>>),
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = &'a TokenType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        return match self {
+            Tokens::Source(tokens) => tokens.next(),
+            Tokens::Nested(tokens) => tokens.next(),
+        };
+    }
+}
+
+/// Token iterator used by the parser, keeping track of the position of the next token.
+pub struct TokenStream<'a> {
+    tokens: Peekable<Tokens<'a>>,
+    spans: &'a [Span],
+    index: usize,
+    line: usize,
+    base: Option<Span>,
+    /// blocks and expressions containing the tokens being parsed.
+    depth: usize,
+}
+
+impl<'a> TokenStream<'a> {
+    pub fn new(tokens: &'a [TokenType], spans: &'a [Span]) -> Self {
+        return TokenStream {
+            tokens: Tokens::Source(tokens.iter()).peekable(),
+            spans,
+            index: 0,
+            line: 1,
+            base: None,
+            depth: 0,
+        };
+    }
+
+    /// Stream over tokens that were extracted from this stream, followed by the end of line closing their expression.
+    /// Every node built from it is located at `span`.
+    pub fn nested<'b>(&self, tokens: &'b [&'b TokenType], span: Span) -> TokenStream<'b> {
+        return TokenStream {
+            tokens: Tokens::Nested(tokens.iter().copied().chain(std::iter::once(&END_LINE))).peekable(),
+            spans: &[],
+            index: 0,
+            line: span.line,
+            base: Some(span),
+            depth: self.depth,
+        };
+    }
+
+    /// Enters a block or an expression, failing when too many of them are nested.
+    pub(crate) fn enter(&mut self) -> Result<(), String> {
+        if self.depth >= MAX_DEPTH {
+            return Err(format!("parser: more than {} blocks and expressions nested in one another", MAX_DEPTH));
+        }
+        self.depth += 1;
+        return Ok(());
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    pub fn peek(&mut self) -> Option<&&'a TokenType> {
+        return self.tokens.peek();
+    }
+
+    /// Position of the next token in the source.
+    pub fn span(&self) -> Span {
+        if let Some(span) = self.base {
+            return span;
+        }
+        return match self.spans.get(self.index) {
+            Some(span) => *span,
+            None => Span {
+                line: self.line,
+                column: 1,
+                file: self.spans.last().map(|s| s.file).unwrap_or_default(),
+            },
+        };
+    }
+}
+
+impl<'a> Iterator for TokenStream<'a> {
+    type Item = &'a TokenType;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.next();
+        if let Some(TokenType::EndLine) = token {
+            self.line += 1;
+        }
+        if token.is_some() {
+            self.index += 1;
+        }
+        return token;
+    }
+}
 
 #[derive(Clone)]
 pub struct Type {
     pub name: String,
     pub is_array: bool,
+    /// number of elements of the array, `int[]` having none.
+    pub size: Option<u64>,
+    /// size of the array when it is not a literal, evaluated by the compiler.
+    pub size_expression: Option<Box<Ast>>,
 }
 
 impl PartialEq<Type> for Type {
     fn eq(&self, other: &Type) -> bool {
-        return self.name == other.name && self.is_array == other.is_array;
+        return self.name == other.name && self.is_array == other.is_array && self.size == other.size;
     }
 }
 
 impl Debug for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.is_array {
-            true => write!(f, "{}[]", self.name),
-            false => write!(f, "{}", self.name),
+        if let (true, Some(size)) = (self.is_array, &self.size_expression) {
+            return write!(f, "{}[{:?}]", self.name, size);
+        }
+        match (self.is_array, self.size) {
+            (true, Some(size)) => write!(f, "{}[{}]", self.name, size),
+            (true, None) => write!(f, "{}[]", self.name),
+            (false, _) => write!(f, "{}", self.name),
         }
     }
 }
@@ -49,16 +167,23 @@ pub enum Ast {
         name: String,
         parameters: Vec<Variable>,
         return_type: Option<String>,
+        /// external symbol of the function, used as is instead of the mangled name.
+        symbol: Option<String>,
+        span: Span,
     },
     FunctionDeclaration{
         name: String,
         children: Vec<Ast>,
         parameters: Vec<Variable>,
         return_type: Option<String>,
+        /// symbol of the function when it is not mangled.
+        symbol: Option<String>,
+        span: Span,
     },
     FunctionCall{
         name: String,
-        children: Vec<Ast>
+        children: Vec<Ast>,
+        span: Span,
     },
     Int(i64),
     Float(f64),
@@ -68,17 +193,21 @@ pub enum Ast {
     Assignement{
         variable: Box<Ast>,
         expression: Box<Ast>,
+        span: Span,
     },
     Condition {
         condition: Box<Ast>,
         valid_branch: Vec<Ast>,
         invalid_branch: Vec<Ast>,
+        span: Span,
     },
     WhileLoop {
         condition: Box<Ast>,
         children: Vec<Ast>,
+        span: Span,
     },
     Variable(Variable),
+    /// statements written on a single line, as the calls of `write a, b`, added one by one to the enclosing block.
     Statement {
         children: Vec<Ast>
     },
@@ -124,6 +253,16 @@ pub enum Ast {
         left: Box<Ast>,
         right: Box<Ast>,
     },
+    /// `left and right`, `right` is only evaluated when `left` is true.
+    And {
+        left: Box<Ast>,
+        right: Box<Ast>,
+    },
+    /// `left or right`, `right` is only evaluated when `left` is false.
+    Or {
+        left: Box<Ast>,
+        right: Box<Ast>,
+    },
     EqualTo {
         left: Box<Ast>,
         right: Box<Ast>,
@@ -132,10 +271,92 @@ pub enum Ast {
         left: Box<Ast>,
         right: Box<Ast>,
     },
-    ReturnStatement(Option<Box<Ast>>),
+    ReturnStatement {
+        value: Option<Box<Ast>>,
+        span: Span,
+    },
     ArrayAccess {
         variable: String,
-        offset: u64,
+        index: Box<Ast>,
+    },
+    Lambda {
+        parameters: Vec<Variable>,
+        return_type: Option<String>,
+        body: Box<Ast>,
+        span: Span,
+    },
+    /// Address of a compiled function, lambdas are replaced by it once lifted.
+    FunctionReference(String),
+    /// `const N <- 4 * 2`, a name for the value of a constant expression.
+    ConstDeclaration {
+        name: String,
+        value: Box<Ast>,
+        span: Span,
+    },
+    /// `record Point ... end`, declaring a type made of the given fields.
+    RecordDeclaration {
+        name: String,
+        fields: Vec<Variable>,
+        span: Span,
+    },
+    /// `record.field`, `record` being a variable or another member access.
+    MemberAccess {
+        record: Box<Ast>,
+        field: String,
+    },
+    /// Numeric conversion of `child` to `typename`, inserted by the compiler for implicit conversions.
+    Cast {
+        child: Box<Ast>,
+        typename: String,
+    },
+    /// `new int[n]`, an array of `size` elements allocated at runtime.
+    NewArray {
+        typename: String,
+        size: Box<Ast>,
+    },
+    /// `{"a": 1, "b": 2}`, the entries of a map.
+    MapValue(Vec<(Ast, Ast)>),
+    /// `swap a, b`, exchanging the values of two variables, array elements or fields of the same type.
+    Swap {
+        left: Box<Ast>,
+        right: Box<Ast>,
+        span: Span,
+    },
+    /// `write a, b`, printing the values separated by spaces and ending the line.
+    Write {
+        values: Vec<Ast>,
+        span: Span,
+    },
+    /// `try ... catch e ... end`, running `handler` when `children` throw, with the message thrown bound to `variable`.
+    Try {
+        children: Vec<Ast>,
+        variable: String,
+        handler: Vec<Ast>,
+        span: Span,
+    },
+    /// `throw message`, leaving the statements up to the closest enclosing `try`.
+    Throw {
+        value: Box<Ast>,
+        span: Span,
+    },
+    /// `match value case a, b ... case c ... else ... end`, running the statements of the first case listing `value`,
+    /// or those of `default` when no case does.
+    Match {
+        value: Box<Ast>,
+        cases: Vec<(Vec<Ast>, Vec<Ast>)>,
+        default: Option<Vec<Ast>>,
+        span: Span,
+    },
+    /// `exit code`, ending the program with the status `code`, `halt` being `exit 0`.
+    Exit {
+        code: Box<Ast>,
+        span: Span,
+    },
+    /// `@pragma name value`, setting an option of the compilation from the source. Only found at the top level.
+    Pragma {
+        name: String,
+        value: String,
+        span: Span,
     },
 }
 
@@ -156,32 +377,52 @@ impl Debug for Ast {
             Self::Str(val) => write!(f, "{}", val),
             Self::Bool(val) => write!(f, "{}", val),
             Self::ArrayValue(children) => write!(f, "{:?}", children),
-            Self::ArrayAccess { variable, offset } => write!(f, "{}[{}]", variable, offset),
+            Self::ArrayAccess { variable, index } => write!(f, "{}[{:?}]", variable, index),
             Self::Addition { left, right } => write!(f, "({:?} + {:?})", left, right),
             Self::Substraction { left, right } => write!(f, "({:?} - {:?})", left, right),
             Self::Multiplication { left, right } => write!(f, "({:?} * {:?})", left, right),
             Self::Division { left, right } => write!(f, "({:?} / {:?})", left, right),
+            Self::Modulo { left, right } => write!(f, "({:?} % {:?})", left, right),
             Self::UnaryPlus { child } => write!(f, "(+{:?})", child),
             Self::UnaryMinus { child } => write!(f, "(-{:?})", child),
             Self::Variable(var)  => write!(f, "{:?}", var),
-            Self::FunctionCall { name, children } => write!(f, "<FunctionCall name={:?}, params={:?} />", name, children),
-            Self::Assignement { variable, expression } => write!(f, "<Assignement variable={:?}, expression={:?} />", variable, expression),
+            Self::FunctionCall { name, children, .. } => write!(f, "<FunctionCall name={:?}, params={:?} />", name, children),
+            Self::Assignement { variable, expression, .. } => write!(f, "<Assignement variable={:?}, expression={:?} />", variable, expression),
             Self::EqualTo { left, right } => write!(f, "({:?} == {:?})", left, right),
             Self::NotEqualTo { left, right } => write!(f, "({:?} != {:?})", left, right),
             Self::GreaterThan { left, right } => write!(f, "({:?} > {:?})", left, right),
             Self::LowerThan { left, right } => write!(f, "({:?} < {:?})", left, right),
             Self::GreaterOrEqual { left, right } => write!(f, "({:?} >= {:?})", left, right),
             Self::LowerOrEqual { left, right } => write!(f, "({:?} <= {:?})", left, right),
-            Self::Condition { condition, valid_branch, invalid_branch } =>
+            Self::And { left, right } => write!(f, "({:?} and {:?})", left, right),
+            Self::Or { left, right } => write!(f, "({:?} or {:?})", left, right),
+            Self::Condition { condition, valid_branch, invalid_branch, .. } =>
                 write!(f, "<Condition condition={:?} then={:?} else={:?} />", condition, valid_branch, invalid_branch),
-            Self::WhileLoop { condition, children } =>
+            Self::WhileLoop { condition, children, .. } =>
                 write!(f, "<While condition={:?} children={:?} />", condition, children),
-            Self::ReturnStatement(ast) => write!(f, "<Return {:?} />", ast),
-            Self::FunctionDeclaration { name, children, parameters, return_type } =>
-                write!(f, "<Function name={:?} parameters={:?} return_type={:?} children={:?} />", name, parameters, return_type, children),
-            Self::FunctionHeader { name, parameters, return_type } =>
-                write!(f, "<FunctionHeader name={:?} parameters={:?} return_type={:?} />", name, parameters, return_type),
-            _ => todo!("ast fmt::Debug not implemented"),
+            Self::ReturnStatement { value, .. } => write!(f, "<Return {:?} />", value),
+            Self::FunctionDeclaration { name, children, parameters, return_type, symbol, .. } =>
+                write!(f, "<Function name={:?} parameters={:?} return_type={:?} symbol={:?} children={:?} />", name, parameters, return_type, symbol, children),
+            Self::Lambda { parameters, return_type, body, .. } =>
+                write!(f, "<Lambda parameters={:?} return_type={:?} body={:?} />", parameters, return_type, body),
+            Self::FunctionReference(name) => write!(f, "&{}", name),
+            Self::Cast { child, typename } => write!(f, "({}){:?}", typename, child),
+            Self::NewArray { typename, size } => write!(f, "new {}[{:?}]", typename, size),
+            Self::MapValue(entries) => write!(f, "{{{}}}", entries.iter().map(|(key, value)| format!("{:?}: {:?}", key, value)).collect::<Vec<String>>().join(", ")),
+            Self::Swap { left, right, .. } => write!(f, "<Swap left={:?} right={:?} />", left, right),
+            Self::Write { values, .. } => write!(f, "<Write values={:?} />", values),
+            Self::Try { children, variable, handler, .. } =>
+                write!(f, "<Try children={:?} catch={:?} handler={:?} />", children, variable, handler),
+            Self::Throw { value, .. } => write!(f, "<Throw {:?} />", value),
+            Self::Match { value, cases, default, .. } => write!(f, "<Match value={:?} cases={:?} default={:?} />", value, cases, default),
+            Self::Exit { code, .. } => write!(f, "<Exit {:?} />", code),
+            Self::Pragma { name, value, .. } => write!(f, "<Pragma name={:?} value={:?} />", name, value),
+            Self::ConstDeclaration { name, value, .. } => write!(f, "<Const name={:?} value={:?} />", name, value),
+            Self::RecordDeclaration { name, fields, .. } => write!(f, "<Record name={:?} fields={:?} />", name, fields),
+            Self::MemberAccess { record, field } => write!(f, "{:?}.{}", record, field),
+            Self::FunctionHeader { name, parameters, return_type, symbol, .. } =>
+                write!(f, "<FunctionHeader name={:?} parameters={:?} return_type={:?} symbol={:?} />", name, parameters, return_type, symbol),
+            Self::Statement { children } => write!(f, "<Statement children={:?} />", children),
         };
     }
 }
@@ -3,12 +3,15 @@ use super::super::lexer::TokenType;
 pub fn get_operator_precedency(operator: &TokenType) -> i64 {
 
     return match operator {
-        TokenType::UnaryOperator(_) => 4,
+        TokenType::UnaryOperator(_) => 7,
         TokenType::BinaryOperator(val) => {
             match val.as_str() {
-                "+" | "-"   => 1,
-                "*" | "/"   => 3,
-                "%"         => 2,
+                "+" | "-"   => 4,
+                "*" | "/"   => 6,
+                "%"         => 5,
+                "==" | "!=" | "<" | ">" | "<=" | ">=" => 3,
+                "and"       => 2,
+                "or"        => 1,
                 "<-"        => 0,
                 _ => -1,
             }
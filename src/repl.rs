@@ -0,0 +1,239 @@
+//! Interactive session of `algo_parser repl`, whose entries are run by the interpreter. The entries kept so far are
+//! checked again with each new one, which only runs its own statements, the variables and the lambdas being kept from
+//! one entry to the next along with the functions and the records declared. An entry made of a single expression is
+//! evaluated with `program::run_expression`, calling the functions of the session, and its value printed. The program
+//! reads the lines entered after the entry reading them.
+//!
+//! In a terminal, the lines are edited with the keys of `editor::Editor`, the arrows recalling the previous ones.
+
+use std::io::ErrorKind;
+
+use algo_parser::interpreter::program::{run_expression, run_statements, State};
+use algo_parser::interpreter::trace::{binding, NoTrace};
+use algo_parser::{compiler, diagnostic, lexer, parser};
+
+use super::editor::Editor;
+
+const HELP: &str = "enter statements, functions or records, blocks being run once their `end` is read, and expressions
+to print their value. the arrows move in the line and recall the previous ones, ctrl-c dropping the line.
+commands:
+    :history    print the entries of the session
+    :reset      forget the entries of the session and their variables
+    :help       print this message
+    :quit       leave the session, as end of file does
+";
+
+/// Blocks the tokens of a line open, minus those they close.
+/// An `else if` continues the block of its `if`, and a `declare function` opens none.
+fn depth_change(tokens: &[lexer::TokenType]) -> i64 {
+    let mut res = 0;
+    let mut previous: Option<&str> = None;
+    for token in tokens {
+        let keyword = match token {
+            lexer::TokenType::Keyword(keyword) => keyword.as_str(),
+            _ => {
+                previous = None;
+                continue;
+            },
+        };
+        res += match (previous, keyword) {
+            (Some("else"), "if") | (Some("declare"), "function") => 0,
+            (_, "if" | "while" | "function" | "record" | "match" | "try") => 1,
+            (_, "end") => -1,
+            _ => 0,
+        };
+        previous = Some(keyword);
+    }
+    return res;
+}
+
+/// Whether the statement is an expression whose value the session prints, rather than a statement or a declaration.
+fn is_expression(node: &parser::Ast) -> bool {
+    return match node {
+        parser::Ast::Variable(variable) => variable.typename.is_none(),
+        node => matches!(
+            node,
+            parser::Ast::Int(..)
+                | parser::Ast::Float(..)
+                | parser::Ast::Str(..)
+                | parser::Ast::Bool(..)
+                | parser::Ast::ArrayValue(..)
+                | parser::Ast::MapValue(..)
+                | parser::Ast::Addition { .. }
+                | parser::Ast::UnaryPlus { .. }
+                | parser::Ast::UnaryMinus { .. }
+                | parser::Ast::Substraction { .. }
+                | parser::Ast::Multiplication { .. }
+                | parser::Ast::Division { .. }
+                | parser::Ast::Modulo { .. }
+                | parser::Ast::GreaterThan { .. }
+                | parser::Ast::LowerThan { .. }
+                | parser::Ast::GreaterOrEqual { .. }
+                | parser::Ast::LowerOrEqual { .. }
+                | parser::Ast::And { .. }
+                | parser::Ast::Or { .. }
+                | parser::Ast::EqualTo { .. }
+                | parser::Ast::NotEqualTo { .. }
+                | parser::Ast::ArrayAccess { .. }
+                | parser::Ast::MemberAccess { .. }
+                | parser::Ast::FunctionCall { .. }
+        ),
+    };
+}
+
+struct Session {
+    /// entries kept in the program, each one made of the lines of a statement or of a block.
+    entries: Vec<String>,
+    /// statements of the top level of the kept entries.
+    statements: usize,
+    /// variables and lambdas left by the kept entries.
+    state: State,
+    /// language of the diagnostics, given by `ALGO_LANG`.
+    language: diagnostic::Language,
+}
+
+impl Session {
+    fn new() -> Session {
+        return Session { entries: Vec::new(), statements: 0, state: State::default(), language: super::message_language(&[]) };
+    }
+
+    /// Checks the program followed by `entry` and runs the statements of the entry, printing the warnings it adds and
+    /// the value of an expression. Returns whether the entry is kept, the entries failing to compile or to run being
+    /// dropped along with the variables they changed.
+    fn evaluate(&mut self, entry: &str) -> bool {
+        let offset = self.entries.iter().map(|entry| entry.lines().count()).sum::<usize>();
+        let mut source = self.entries.join("\n");
+        source.push('\n');
+        source.push_str(entry);
+
+        // the diagnostics are located in the entry, those of the previous entries having been reported already.
+        let report = |diagnostics: Vec<diagnostic::Diagnostic>| {
+            for mut d in diagnostics {
+                if d.span.line > 0 && d.span.line <= offset {
+                    continue;
+                }
+                d.span.line = d.span.line.saturating_sub(offset);
+                eprintln!("{}", d.translate(self.language));
+            }
+        };
+        let children = match algo_parser::parse_str(&source) {
+            Err(e) => {
+                report(vec![e]);
+                return false;
+            },
+            Ok(parser::Ast::Global(children)) => children,
+            Ok(ast) => vec![ast],
+        };
+        let ast = parser::Ast::Global(children);
+        let options = compiler::CompilerOptions::default();
+        match compiler::analyze(&ast, &options) {
+            Err(diagnostics) => {
+                report(diagnostics);
+                return false;
+            },
+            Ok(diagnostics) => report(diagnostics),
+        };
+        let children = match ast {
+            parser::Ast::Global(children) => children,
+            _ => Vec::new(),
+        };
+
+        // the statements of the previous entries have run, only their declarations are kept.
+        let statements = children.len();
+        let mut program = Vec::<parser::Ast>::new();
+        for (i, child) in children.into_iter().enumerate() {
+            let declaration = matches!(child, parser::Ast::FunctionDeclaration { .. } | parser::Ast::FunctionHeader { .. } | parser::Ast::RecordDeclaration { .. });
+            if declaration || i >= self.statements {
+                program.push(child);
+            }
+        }
+        let expression = match program.last() {
+            Some(last) if statements == self.statements + 1 && is_expression(last) => program.pop(),
+            _ => None,
+        };
+        let program = parser::Ast::Global(program);
+        let previous = self.state.clone();
+        let stdin = std::io::stdin();
+        let res = match &expression {
+            Some(expression) => run_expression(&program, expression, &options, &mut self.state, &mut stdin.lock(), &mut std::io::stdout()),
+            None => run_statements(&program, &options, &mut self.state, &mut stdin.lock(), &mut std::io::stdout(), &mut NoTrace).map(|status| (status, None)),
+        };
+        match res {
+            Ok((0, value)) => {
+                if let Some(value) = value {
+                    println!("{}", binding(&value));
+                }
+                self.statements = statements;
+                return true;
+            },
+            Ok((status, _)) => eprintln!("the entry exited with status {}, it is dropped", status),
+            Err(e) => report(vec![e]),
+        };
+        self.state = previous;
+        return false;
+    }
+}
+
+/// Reads entries from the standard input until its end or `:quit`, the lines of a block being read until its `end`.
+pub fn run() {
+    let mut session = Session::new();
+    let mut editor = Editor::new();
+    let mut pending = Vec::<String>::new();
+    let mut depth = 0;
+    loop {
+        let line = match editor.read_line(if pending.is_empty() { "algo> " } else { "....> " }) {
+            // ctrl-c drops the block being entered.
+            Err(e) if e.kind() == ErrorKind::Interrupted => {
+                pending.clear();
+                depth = 0;
+                continue;
+            },
+            Err(e) => {
+                eprintln!("could not read the input: {}", e);
+                return;
+            },
+            Ok(None) => return,
+            Ok(Some(line)) => line,
+        };
+        let line = line.trim_end();
+
+        if pending.is_empty() && line.trim_start().starts_with(':') {
+            match line.trim() {
+                ":quit" | ":q" => return,
+                ":help" => print!("{}", HELP),
+                ":history" => {
+                    for entry in &session.entries {
+                        println!("{}", entry);
+                    }
+                },
+                ":reset" => session = Session::new(),
+                command => eprintln!("unknown command '{}', :help lists them", command),
+            };
+            continue;
+        }
+
+        let tokens = match algo_parser::tokenize_str(line) {
+            Err(e) => {
+                eprintln!("{}", e.translate(session.language));
+                pending.clear();
+                depth = 0;
+                continue;
+            },
+            Ok((tokens, _)) => tokens,
+        };
+        depth += depth_change(&tokens);
+        pending.push(String::from(line));
+        if depth > 0 {
+            continue;
+        }
+        let entry = pending.join("\n");
+        pending.clear();
+        depth = 0;
+        if entry.trim().is_empty() {
+            continue;
+        }
+        if session.evaluate(&entry) {
+            session.entries.push(entry);
+        }
+    }
+}
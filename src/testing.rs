@@ -0,0 +1,188 @@
+//! `algo_parser test`, running each test function of the programs with the interpreter, after the top level
+//! statements. A test passes when its program exits with status 0, a failed `assert` stopping it with another status
+//! and a failed statement, as an int divided by zero, failing it as well. With `--coverage`, the statements the tests
+//! ran are counted and reported after their results.
+
+use std::process::exit;
+
+use algo_parser::analysis::coverage;
+use algo_parser::diagnostic::{Code, Diagnostic};
+use algo_parser::interpreter::program::run_program;
+use algo_parser::interpreter::trace::NoTrace;
+use algo_parser::lexer::Span;
+use algo_parser::{compiler, parser};
+
+use super::trace::interpreter_options;
+use super::{input_paths, message_format, message_language, read_sources, usage_error, Reporter, EXIT_FAILURE};
+
+/// Line marking the function declared after it as a test, whatever its name.
+const ANNOTATION: &str = "@test";
+
+/// Options of `test`, all of them followed by a value.
+const TEST_OPTIONS: [&str; 5] = ["--filter", "--checks", "--message-format", "--lang", "--coverage"];
+
+/// Report of the coverage given to `--coverage`.
+#[derive(Clone, Copy, PartialEq)]
+enum CoverageFormat {
+    Lcov,
+    Json,
+    Annotate,
+}
+
+/// Sources without their annotations, replaced by empty lines for the others to keep their numbers,
+/// along with the position of each annotation.
+fn strip_annotations(sources: &[String]) -> (Vec<String>, Vec<Span>) {
+    let mut stripped = Vec::new();
+    let mut annotations = Vec::new();
+    for (file, source) in sources.iter().enumerate() {
+        let mut lines = Vec::new();
+        for (i, line) in source.lines().enumerate() {
+            match line.trim() == ANNOTATION {
+                true => {
+                    annotations.push(Span { line: i + 1, column: line.find('@').unwrap_or(0) + 1, file });
+                    lines.push("");
+                },
+                false => lines.push(line),
+            };
+        }
+        stripped.push(lines.join("\n"));
+    }
+    return (stripped, annotations);
+}
+
+/// Names of the test functions of the top level, in source order: those following an annotation, which must not take
+/// parameters, and those without parameters whose name starts with `test_`.
+fn find_tests(ast: &parser::Ast, annotations: &[Span]) -> Result<Vec<String>, Diagnostic> {
+    let functions = match ast {
+        parser::Ast::Global(children) => children
+            .iter()
+            .filter_map(|child| match child {
+                parser::Ast::FunctionDeclaration { name, parameters, span, .. } => Some((name, parameters.len(), *span)),
+                _ => None,
+            })
+            .collect::<Vec<(&String, usize, Span)>>(),
+        _ => Vec::new(),
+    };
+
+    let mut annotated = Vec::<&String>::new();
+    for annotation in annotations {
+        let function = functions
+            .iter()
+            .filter(|(_, _, span)| span.file == annotation.file && span.line > annotation.line)
+            .min_by_key(|(_, _, span)| span.line);
+        match function {
+            None => return Err(Diagnostic::error(Code::Syntax, format!("{} is not followed by a function", ANNOTATION), *annotation)),
+            Some((name, parameters, span)) if *parameters > 0 => {
+                return Err(Diagnostic::error(Code::Unsupported, format!("test function '{}' takes parameters", name), *span));
+            },
+            Some((name, _, _)) => annotated.push(name),
+        };
+    }
+    return Ok(functions
+        .iter()
+        .filter(|(name, parameters, _)| annotated.contains(name) || (name.starts_with("test_") && *parameters == 0))
+        .map(|(name, _, _)| name.to_string())
+        .collect());
+}
+
+/// Runs the tests of the files given in `args`, exiting with a failure status when one of them fails.
+pub fn run(args: &[String]) {
+    let paths = match input_paths(args, &TEST_OPTIONS, &[]) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let format = message_format(args);
+    let language = message_language(args);
+    let filter = match args.iter().position(|arg| arg == "--filter") {
+        Some(i) => match args.get(i + 1) {
+            Some(filter) => Some(filter.as_str()),
+            None => usage_error("missing value for --filter"),
+        },
+        None => None,
+    };
+    let coverage_format = match args.iter().position(|arg| arg == "--coverage").map(|i| args.get(i + 1).map(|format| format.as_str())) {
+        None => None,
+        Some(Some("lcov")) => Some(CoverageFormat::Lcov),
+        Some(Some("json")) => Some(CoverageFormat::Json),
+        Some(Some("annotate")) => Some(CoverageFormat::Annotate),
+        Some(Some(format)) => usage_error(&format!("unknown coverage format '{}', expected lcov, json or annotate", format)),
+        Some(None) => usage_error("missing value for --coverage"),
+    };
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+
+    let (stripped, annotations) = strip_annotations(&sources);
+    let ast = match algo_parser::parse_sources(&stripped) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(ast) => ast,
+    };
+    let tests = match find_tests(&ast, &annotations) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(tests) => tests.into_iter().filter(|test| filter.is_none_or(|filter| test.contains(filter))).collect::<Vec<String>>(),
+    };
+    let options = interpreter_options(args);
+    match compiler::analyze(&ast, &options) {
+        Err(diagnostics) => {
+            reporter.report(&diagnostics);
+            exit(EXIT_FAILURE);
+        },
+        Ok(diagnostics) => reporter.report(&diagnostics),
+    };
+    let mut coverage = coverage::Coverage::new(&ast);
+    let children = match ast {
+        parser::Ast::Global(children) => children,
+        _ => Vec::new(),
+    };
+
+    println!("running {} test{}", tests.len(), if tests.len() == 1 { "" } else { "s" });
+    let mut failures = Vec::<(&String, String)>::new();
+    for test in &tests {
+        let mut program = children.clone();
+        program.push(parser::Ast::FunctionCall { name: test.clone(), children: Vec::new(), span: Span::default() });
+        let program = parser::Ast::Global(program);
+        let mut output = Vec::<u8>::new();
+        let res = match coverage_format {
+            Some(_) => run_program(&program, &options, &mut std::io::empty(), &mut output, &mut coverage),
+            None => run_program(&program, &options, &mut std::io::empty(), &mut output, &mut NoTrace),
+        };
+        let mut details = String::from_utf8_lossy(&output).to_string();
+        // the assertions print their line before stopping the program with their status.
+        match res {
+            Ok(0) => println!("test {} ... ok", test),
+            Ok(status) => {
+                println!("test {} ... FAILED", test);
+                details.push_str(&format!("exit status: {}\n", status));
+                failures.push((test, details));
+            },
+            Err(e) => {
+                println!("test {} ... FAILED", test);
+                details.push_str(&format!("{} at line {}\n", e.message, e.span.line));
+                failures.push((test, details));
+            },
+        };
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (test, details) in &failures {
+            print!("\n---- {} ----\n{}", test, details);
+        }
+    }
+    let result = if failures.is_empty() { "ok" } else { "FAILED" };
+    println!("\ntest result: {}. {} passed; {} failed", result, tests.len() - failures.len(), failures.len());
+    match coverage_format {
+        None => (),
+        Some(CoverageFormat::Lcov) => print!("\n{}", coverage::to_lcov(&coverage, &paths)),
+        Some(CoverageFormat::Json) => print!("\n{}", coverage::to_json(&coverage, &paths)),
+        Some(CoverageFormat::Annotate) => print!("\n{}", coverage::annotate(&coverage, &paths, &sources)),
+    };
+    if !failures.is_empty() {
+        exit(EXIT_FAILURE);
+    }
+}
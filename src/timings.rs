@@ -0,0 +1,87 @@
+//! Time spent in each phase of the compilation: lexing, parsing, semantic analysis, optimization and code generation.
+//! Each phase runs in a `tracing` span named after it, for the subscriber of the program using the crate, and its
+//! duration is recorded on the thread once `enable` was called, until `take` reads the durations back.
+
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Lexing,
+    Parsing,
+    Semantics,
+    Optimization,
+    Codegen,
+}
+
+impl Phase {
+    /// Phases in the order the compilation runs them.
+    pub const ALL: [Phase; 5] = [Phase::Lexing, Phase::Parsing, Phase::Semantics, Phase::Optimization, Phase::Codegen];
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Phase::Lexing => "lexing",
+            Phase::Parsing => "parsing",
+            Phase::Semantics => "semantic analysis",
+            Phase::Optimization => "optimization",
+            Phase::Codegen => "codegen",
+        };
+    }
+
+    fn span(&self) -> tracing::Span {
+        return match self {
+            Phase::Lexing => tracing::info_span!("lexing"),
+            Phase::Parsing => tracing::info_span!("parsing"),
+            Phase::Semantics => tracing::info_span!("semantics"),
+            Phase::Optimization => tracing::info_span!("optimization"),
+            Phase::Codegen => tracing::info_span!("codegen"),
+        };
+    }
+}
+
+thread_local! {
+    /// durations of the phases run since `enable`, `None` while they are not recorded.
+    static TIMINGS: RefCell<Option<Vec<(Phase, Duration)>>> = const { RefCell::new(None) };
+}
+
+/// Starts recording the durations of the phases run by the thread, forgetting those recorded before.
+pub fn enable() {
+    TIMINGS.with(|timings| *timings.borrow_mut() = Some(Vec::new()));
+}
+
+/// Time spent in each phase since `enable`, the phases run several times, as the lexing of each file, being summed.
+/// The phases that did not run are left out. Stops the recording.
+pub fn take() -> Vec<(Phase, Duration)> {
+    let recorded = TIMINGS.with(|timings| timings.borrow_mut().take()).unwrap_or_default();
+    return Phase::ALL
+        .into_iter()
+        .filter(|phase| recorded.iter().any(|(p, _)| p == phase))
+        .map(|phase| (phase, recorded.iter().filter(|(p, _)| *p == phase).map(|(_, duration)| *duration).sum()))
+        .collect();
+}
+
+/// Runs `f` as `phase`, in its span, recording its duration when the phases are timed.
+pub(crate) fn time<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let span = phase.span();
+    let _entered = span.enter();
+    let start = Instant::now();
+    let res = f();
+    let elapsed = start.elapsed();
+    TIMINGS.with(|timings| {
+        if let Some(timings) = timings.borrow_mut().as_mut() {
+            timings.push((phase, elapsed));
+        }
+    });
+    return res;
+}
+
+/// One phase per line with its duration in milliseconds, followed by the total.
+pub fn to_table(timings: &[(Phase, Duration)]) -> String {
+    let mut res = String::new();
+    for (phase, duration) in timings {
+        res.push_str(&format!("{:<20}{:>10.3} ms\n", phase.name(), duration.as_secs_f64() * 1000.0));
+    }
+    let total = timings.iter().map(|(_, duration)| *duration).sum::<Duration>();
+    res.push_str(&format!("{:<20}{:>10.3} ms\n", "total", total.as_secs_f64() * 1000.0));
+    return res;
+}
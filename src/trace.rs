@@ -0,0 +1,79 @@
+//! `algo_parser trace`, running the program with the interpreter instead of compiling it, each statement being written
+//! to stderr before it runs along with the variables of its function, as text or as one JSON object per line, the JSON
+//! trace also having the calls, the returns, the assignments, the branches and the loop iterations.
+//! The program reads stdin and writes stdout as the compiled one does, and exits with its status.
+
+use std::io::{BufRead, Write};
+use std::process::exit;
+
+use algo_parser::interpreter::program;
+use algo_parser::interpreter::trace::{JsonTrace, TextTrace, Tracer};
+use algo_parser::{compiler, parser};
+
+use super::{input_paths, message_format, message_language, read_sources, runtime_checks, usage_error, Reporter, EXIT_FAILURE};
+
+/// Options of `trace`, all of them followed by a value.
+const TRACE_OPTIONS: [&str; 4] = ["--format", "--checks", "--message-format", "--lang"];
+
+/// Options of the programs run by the interpreter, the runtime checks being given by `--checks`.
+pub fn interpreter_options(args: &[String]) -> compiler::CompilerOptions {
+    return compiler::CompilerOptions { runtime_checks: runtime_checks(args), ..compiler::CompilerOptions::default() };
+}
+
+/// Checks the program of the files as `options` tell, reporting its diagnostics, and returns it once it has no errors.
+pub fn checked_program(reporter: &Reporter, options: &compiler::CompilerOptions) -> parser::Ast {
+    let ast = match algo_parser::parse_sources(reporter.sources) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(ast) => ast,
+    };
+    match compiler::analyze(&ast, options) {
+        Err(diagnostics) => {
+            reporter.report(&diagnostics);
+            exit(EXIT_FAILURE);
+        },
+        Ok(diagnostics) => reporter.report(&diagnostics),
+    };
+    return ast;
+}
+
+/// Runs the program as `options` tell with the tracer, reading `input`, and exits with its status.
+pub fn run_program(ast: &parser::Ast, options: &compiler::CompilerOptions, reporter: &Reporter, input: &mut dyn BufRead, tracer: &mut dyn Tracer) -> ! {
+    let mut output = std::io::stdout();
+    match program::run_program(ast, options, input, &mut output as &mut dyn Write, tracer) {
+        Err(e) => {
+            reporter.report(&vec![e]);
+            exit(EXIT_FAILURE);
+        },
+        Ok(status) => exit(status as i32),
+    };
+}
+
+pub fn run(args: &[String]) {
+    let paths = match input_paths(args, &TRACE_OPTIONS, &[]) {
+        Err(e) => usage_error(&e),
+        Ok(val) => val,
+    };
+    let json = match args.iter().position(|arg| arg == "--format").map(|i| args.get(i + 1).map(|format| format.as_str())) {
+        None | Some(Some("text")) => false,
+        Some(Some("json")) => true,
+        Some(Some(format)) => usage_error(&format!("unknown trace format '{}', expected text or json", format)),
+        Some(None) => usage_error("missing value for --format"),
+    };
+    let format = message_format(args);
+    let language = message_language(args);
+    let sources = read_sources(&paths);
+    let reporter = Reporter { paths: &paths, sources: &sources, format, language };
+    let options = interpreter_options(args);
+    let ast = checked_program(&reporter, &options);
+
+    let stdin = std::io::stdin();
+    let mut input = stdin.lock();
+    let mut stderr = std::io::stderr();
+    match json {
+        true => run_program(&ast, &options, &reporter, &mut input, &mut JsonTrace::new(&mut stderr, &paths)),
+        false => run_program(&ast, &options, &reporter, &mut input, &mut TextTrace::new(&mut stderr, &paths, &sources)),
+    };
+}
@@ -0,0 +1,125 @@
+//! JavaScript bindings of the front end, built with the `wasm` feature for the `wasm32-unknown-unknown` target,
+//! for a playground to check and run programs in the browser. The functions exchange JSON texts, the errors being
+//! thrown as the JSON array of their diagnostics, as `check` returns them.
+
+use wasm_bindgen::prelude::*;
+
+use super::analysis::semantic_tokens;
+use super::compiler::{self, CompilerOptions, Target};
+use super::diagnostic::{self, Diagnostic};
+use super::interpreter::program::run_program;
+use super::interpreter::trace::NoTrace;
+use super::json;
+use super::lexer::{Span, TokenType};
+use super::parser;
+
+fn diagnostic_error(d: Diagnostic) -> JsValue {
+    return JsValue::from_str(&diagnostic::json_array(&[d]));
+}
+
+/// `{"kind": "Keyword", "value": "if", "line": 1, "column": 1}`, the punctuation having no value.
+fn token_json(token: &TokenType, span: &Span) -> String {
+    let (kind, value) = match token {
+        TokenType::OpeningParenthesis => ("OpeningParenthesis", None),
+        TokenType::ClosingParenthesis => ("ClosingParenthesis", None),
+        TokenType::OpeningBracket => ("OpeningBracket", None),
+        TokenType::ClosingBracket => ("ClosingBracket", None),
+        TokenType::OpeningBrace => ("OpeningBrace", None),
+        TokenType::ClosingBrace => ("ClosingBrace", None),
+        TokenType::Comma => ("Comma", None),
+        TokenType::Colon => ("Colon", None),
+        TokenType::EndLine => ("EndLine", None),
+        TokenType::Int(value) => ("Int", Some(value.to_string())),
+        TokenType::Bool(value) => ("Bool", Some(value.to_string())),
+        TokenType::Float(value) => ("Float", Some(format!("{:?}", value))),
+        TokenType::String(value) => ("String", Some(json::string(value))),
+        TokenType::ArrayTypeDef(value) => ("ArrayTypeDef", Some(json::string(value))),
+        TokenType::BinaryOperator(value) => ("BinaryOperator", Some(json::string(value))),
+        TokenType::UnaryOperator(value) => ("UnaryOperator", Some(json::string(value))),
+        TokenType::Variable(value) => ("Variable", Some(json::string(value))),
+        TokenType::Member(value) => ("Member", Some(json::string(value))),
+        TokenType::FunctionCall(value) => ("FunctionCall", Some(json::string(value))),
+        TokenType::Keyword(value) => ("Keyword", Some(json::string(value))),
+        TokenType::TypeDef(value) => ("TypeDef", Some(json::string(value))),
+        TokenType::Pragma(name, value) => ("Pragma", Some(json::string(format!("{} {}", name, value).trim_end()))),
+    };
+    return format!(
+        "{{\"kind\": {}, \"value\": {}, \"line\": {}, \"column\": {}}}",
+        json::string(kind), value.unwrap_or(String::from("null")), span.line, span.column,
+    );
+}
+
+/// JSON array of the tokens of `source`, as `token_json` writes them.
+#[wasm_bindgen]
+pub fn tokenize(source: &str) -> Result<String, JsValue> {
+    let (tokens, spans) = match super::tokenize_str(source) {
+        Err(e) => return Err(diagnostic_error(e)),
+        Ok(val) => val,
+    };
+    let tokens = tokens.iter().zip(spans.iter()).map(|(token, span)| token_json(token, span)).collect::<Vec<String>>();
+    return Ok(format!("[{}]", tokens.join(", ")));
+}
+
+/// JSON array of the semantic tokens of `source`, as `analysis::semantic_tokens::to_json` writes them, for the editor
+/// of the page to highlight the program.
+#[wasm_bindgen]
+pub fn semantic_tokens(source: &str) -> Result<String, JsValue> {
+    return match semantic_tokens::semantic_tokens(source) {
+        Err(e) => Err(diagnostic_error(e)),
+        Ok(tokens) => Ok(semantic_tokens::to_json(&tokens)),
+    };
+}
+
+/// JSON object of the syntax tree of `source`, as `parser::to_json` writes it.
+#[wasm_bindgen]
+pub fn parse_to_json(source: &str) -> Result<String, JsValue> {
+    return match super::parse_str(source) {
+        Err(e) => Err(diagnostic_error(e)),
+        Ok(ast) => Ok(parser::to_json(&ast)),
+    };
+}
+
+/// JSON array of the diagnostics of `source`, errors and warnings alike, empty when the program is correct.
+#[wasm_bindgen]
+pub fn check(source: &str) -> String {
+    let ast = match super::parse_str(source) {
+        Err(e) => return diagnostic::json_array(&[e]),
+        Ok(ast) => ast,
+    };
+    return match compiler::analyze(&ast, &CompilerOptions::default()) {
+        Err(diagnostics) | Ok(diagnostics) => diagnostic::json_array(&diagnostics),
+    };
+}
+
+/// JavaScript program of `source`, printing with `console.log`. The warnings are not returned, `check` reports them.
+#[wasm_bindgen]
+pub fn compile_js(source: &str) -> Result<String, JsValue> {
+    let ast = match super::parse_str(source) {
+        Err(e) => return Err(diagnostic_error(e)),
+        Ok(ast) => ast,
+    };
+    let options = CompilerOptions { target: Target::Js, ..CompilerOptions::default() };
+    return match compiler::compile(&ast, &options) {
+        Err(diagnostics) => Err(JsValue::from_str(&diagnostic::json_array(&diagnostics))),
+        Ok(compiled) => Ok(compiled.code),
+    };
+}
+
+/// Output of the program of `source` run by the interpreter, reading `input`, a failed statement being thrown as its
+/// diagnostic. The warnings are not returned, `check` reports them.
+#[wasm_bindgen]
+pub fn interpret(source: &str, input: &str) -> Result<String, JsValue> {
+    let ast = match super::parse_str(source) {
+        Err(e) => return Err(diagnostic_error(e)),
+        Ok(ast) => ast,
+    };
+    let options = CompilerOptions::default();
+    if let Err(diagnostics) = compiler::analyze(&ast, &options) {
+        return Err(JsValue::from_str(&diagnostic::json_array(&diagnostics)));
+    }
+    let mut output = Vec::<u8>::new();
+    return match run_program(&ast, &options, &mut input.as_bytes(), &mut output, &mut NoTrace) {
+        Err(e) => Err(diagnostic_error(e)),
+        Ok(_) => Ok(String::from_utf8_lossy(&output).to_string()),
+    };
+}